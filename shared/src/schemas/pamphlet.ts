@@ -12,6 +12,27 @@ export const tileMetadataSchema = z.object({
   hash: z.string().regex(/^[a-f0-9]{64}$/, 'Invalid SHA256 hash format'),
 });
 
+/**
+ * テキストレイヤーの単語スキーマ（座標はページの原寸ピクセル）
+ */
+export const wordBoxSchema = z.object({
+  text: z.string(),
+  x: z.number(),
+  y: z.number(),
+  width: z.number().nonnegative(),
+  height: z.number().nonnegative(),
+});
+
+/**
+ * 自動切り抜きの範囲スキーマ（切り抜く前のスキャンのピクセル座標）
+ */
+export const cropRectSchema = z.object({
+  x: z.number().int().nonnegative(),
+  y: z.number().int().nonnegative(),
+  width: z.number().int().positive(),
+  height: z.number().int().positive(),
+});
+
 /**
  * ページ情報スキーマ
  */
@@ -20,8 +41,29 @@ export const pageInfoSchema = z.object({
   width: z.number().int().positive(),
   height: z.number().int().positive(),
   tiles: z.array(tileMetadataSchema).min(1),
+  words: z.array(wordBoxSchema).optional(),
+  dpi: z.number().positive().optional(),
+  crop: cropRectSchema.optional(),
+  scale: z.number().positive().optional(),
 });
 
+/**
+ * 目次の項目スキーマ
+ */
+export type TocEntryInput = {
+  title: string;
+  page: number;
+  children?: TocEntryInput[];
+};
+
+export const tocEntrySchema: z.ZodType<TocEntryInput> = z.lazy(() =>
+  z.object({
+    title: z.string(),
+    page: z.number().int().nonnegative(),
+    children: z.array(tocEntrySchema).optional(),
+  })
+);
+
 /**
  * メタデータスキーマ（サーバー保存用）
  */
@@ -29,6 +71,7 @@ export const metadataSchema = z.object({
   version: z.number().int().positive(),
   tile_size: z.number().int().positive(),
   pages: z.array(pageInfoSchema).min(1),
+  toc: z.array(tocEntrySchema).optional(),
 });
 
 /**
@@ -38,6 +81,7 @@ export const metadataSchema = z.object({
 export const uploadMetadataSchema = z.object({
   tile_size: z.number().int().positive(),
   pages: z.array(pageInfoSchema).min(1),
+  toc: z.array(tocEntrySchema).optional(),
 });
 
 /**
@@ -30,10 +30,270 @@ export interface JsTileResult {
   tile_size: number;
   /** タイル情報の配列 */
   tiles: JsTileInfo[];
+  /** 処理時間の内訳（`set_timing_enabled(true)` の後にタイル化した場合のみ） */
+  timings?: TileTimings;
+  /** タイル化は続けたが知らせる問題（ない場合は空） */
+  warnings: TileWarning[];
   /** タイル数を取得 */
   tile_count(): number;
+  /** タイルのデータをすべて解放する（タイル情報と大きさは残る） */
+  dispose(): void;
+  /** `dispose()` を呼んだか */
+  is_disposed(): boolean;
+  /** WASM側で保持しているタイルのデータのバイト数 */
+  retained_bytes(): number;
+  /** 結果そのものを解放する（wasm-bindgenが生成、以降のアクセスはエラー） */
+  free(): void;
   /** 指定インデックスのタイルデータ（WebP）を取得 */
   get_tile_data(index: number): Uint8Array;
+  /** 指定インデックスのタイルデータのバイト数 */
+  tile_data_length(index: number): number;
+  /** 指定インデックスのタイルデータを指すWASMメモリのビュー（WASMの呼び出しで無効になるため、すぐに使い切る） */
+  tile_data_view(index: number): Uint8Array;
+  /** 指定インデックスのタイルデータを `target` の `offset` 以降に書き込み、バイト数を返す */
+  read_tile_data_into(index: number, target: Uint8Array, offset?: number): number;
+  /** 指定インデックスのタイルをデータURLに変換し、区切りごとの文字列を `chunk_callback` に渡す（URLの長さを返す） */
+  encode_data_url(index: number, chunk_callback: (chunk: string) => void, chunk_size?: number): number;
+  /** 指定インデックスのタイルデータを取り出し、WASM側のバッファを解放する（以降は取得できない） */
+  take_tile_data(index: number): Uint8Array;
+  /** タイル情報とデータを取り出して結果を解放する（呼び出した後は結果を使えない） */
+  into_tiles(): Array<JsTileInfo & { data: Uint8Array }>;
+  /** まだエンコードしていないタイルをすべてエンコードする（`tile_image_lazy` の結果） */
+  encode_all(): void;
+  /** metadataのページ情報（`generate_metadata` や `PipelineCoordinator` に渡す、`tile_image_lazy` の結果は `encode_all()` の後） */
+  page_info(page: number): PageInfo;
+  /** 指定インデックスのタイルのハッシュ（`tile_image_lazy` の結果ではエンコードするとWebPのハッシュに置き換わる） */
+  tile_hash(index: number): string | undefined;
+  /** 読み出したタイルのデータをその場でWASM側から解放するかを設定する（既定では解放しない） */
+  set_release_after_read(enabled: boolean): void;
+  /** 指定インデックスのタイルデータを読み出したか */
+  is_retrieved(index: number): boolean;
+  /** データを読み出したタイルの数 */
+  readonly retrieved_count: number;
+}
+
+/**
+ * WASMのAPIが投げるエラーのコード
+ */
+export type PamphletErrorCode =
+  | 'decode_failed'
+  | 'unsupported_format'
+  | 'image_too_large'
+  | 'encode_failed'
+  | 'invalid_tile_size'
+  | 'out_of_memory'
+  | 'invalid_argument'
+  | 'not_found'
+  | 'internal';
+
+/**
+ * WASMのAPIが投げるエラー
+ */
+export interface PamphletError extends Error {
+  /** 機械で判別するためのコード */
+  code: PamphletErrorCode;
+  /** コードごとの詳細（`invalid_tile_size` は `{ tile_size, min, max }`、`image_too_large` は `{ max_dimension, max_pixels }`、`out_of_memory` は `{ bytes }`） */
+  details: Record<string, number> | null;
+  /** 翻訳のキー（`errors.<code>`、`error_messages` のキー） */
+  key: `errors.${PamphletErrorCode}`;
+  /** 翻訳したメッセージに埋め込む値（`details` の値と、`width`・`height`・元のメッセージの `detail`） */
+  params: Record<string, number | string>;
+}
+
+/**
+ * タイル化の設定（`tile_image_with_options`・`tile_from_buffer`、省略した項目は既定値）
+ */
+export interface TileOptions {
+  /** タイルサイズ（ピクセル、16-4096、デフォルト512） */
+  tile_size?: number;
+  /** WebP品質（1-100、デフォルト80） */
+  quality?: number;
+  /** タイルの画像形式 */
+  format?: 'webp';
+  /** 端のタイルをタイルサイズまで透明で埋める（`transparent`、デフォルト）か、埋めない（`none`） */
+  padding?: 'transparent' | 'none';
+}
+
+/**
+ * `Tiler` のインスタンスの設定（省略した項目はモジュール全体の設定に従う）
+ */
+export interface TilerSettings {
+  /** デコードする画像の大きさの上限 */
+  decode_limits?: { max_dimension?: number; max_pixels?: number } | null;
+  /** デコードを許可する入力の形式 */
+  allowed_formats?: InputFormatName[] | null;
+  /** 処理時間を記録するか */
+  timing?: boolean | null;
+  /** metadataのバージョン */
+  metadata_version?: number | null;
+  /** ページ情報・目次を厳密に読み込むか */
+  strict_metadata?: boolean | null;
+  /** ログのレベル */
+  log_level?: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace' | null;
+}
+
+/**
+ * 設定と作業用バッファを持つタイル化のインスタンス
+ */
+export interface Tiler {
+  readonly settings: TilerSettings;
+  set_settings(settings?: TilerSettings): void;
+  tile_image(imageData: Uint8Array, tileSize: number, quality?: number): JsTileResult;
+  tile_image_with_options(imageData: Uint8Array, options?: TileOptions): JsTileResult;
+  tile_image_async(imageData: Uint8Array, tileSize: number, quality?: number): Promise<JsTileResult>;
+  tile_image_with_options_async(imageData: Uint8Array, options?: TileOptions): Promise<JsTileResult>;
+  tile_image_preprocessed(imageData: Uint8Array, tileSize: number, quality?: number, options?: object): JsTileResult;
+  tile_image_split(imageData: Uint8Array, tileSize: number, quality?: number, options?: object): JsTileResult[];
+  tile_image_level(imageData: Uint8Array, level: number, tileSize: number, quality?: number): JsTileResult;
+  tile_image_pages(imageData: Uint8Array, tileSize: number, quality?: number, animation?: 'first_frame' | 'pages'): JsTileResult[];
+  tile_image_banded(imageData: Uint8Array, tileSize: number, quality?: number): JsTileResult;
+  tile_image_lazy(imageData: Uint8Array, tileSize: number, quality?: number): JsTileResult;
+  tile_rgba(pixels: Uint8Array | Uint8ClampedArray, width: number, height: number, tileSize: number, quality?: number): JsTileResult;
+  /** `svg` フィーチャーが必要 */
+  tile_svg?(svgData: Uint8Array, tileSize: number, quality?: number, dpi?: number): JsTileResult;
+  /** `pdf` フィーチャーが必要（`document` は `PdfDocument`） */
+  tile_pdf_page?(document: object, index: number, dpi: number | undefined, tileSize: number, quality?: number): JsTileResult;
+  /** `zip` フィーチャーが必要 */
+  tile_archive?(zipData: Uint8Array, options?: { tile_size?: number; quality?: number }): { pages: JsTileResult[]; names: string[]; metadata: string };
+  tile_image_with_hook(
+    imageData: Uint8Array,
+    tileSize: number,
+    quality: number | undefined,
+    hook: (input: { data: Uint8ClampedArray; width: number; height: number; x?: number; y?: number }) => unknown,
+    mode?: 'page' | 'tile'
+  ): Promise<JsTileResult>;
+  generate_metadata(pages: PageInfo[] | string, tileSize: number, toc?: TocEntry[] | string): string;
+  generate_metadata_value(pages: PageInfo[] | string, tileSize: number, toc?: TocEntry[] | string): Metadata;
+  retained_bytes(): number;
+  dispose(): void;
+  free(): void;
+}
+
+/**
+ * 入力の形式の名前（`set_allowed_formats`）
+ */
+export type InputFormatName =
+  | 'jpeg'
+  | 'png'
+  | 'webp'
+  | 'gif'
+  | 'heic'
+  | 'avif'
+  | 'jxl'
+  | 'jpeg2000'
+  | 'psd'
+  | 'raw';
+
+/**
+ * メインスレッドからワーカーへのメッセージ（`handle_worker_message`）
+ */
+export interface WorkerCommand {
+  type: 'tile_request';
+  /** 応答と対応付けるための番号 */
+  id: number;
+  /** 元画像のバイトデータ */
+  data: Uint8Array | ArrayBuffer;
+  options?: TileOptions;
+  /** ページ情報に入れるページ番号（デフォルト0） */
+  page?: number;
+}
+
+/**
+ * ワーカーからメインスレッドへのメッセージ
+ */
+export type WorkerEvent =
+  | { type: 'progress'; id: number; done: number; total: number }
+  | {
+      type: 'tile_ready';
+      id: number;
+      tile_size: number;
+      page: PageInfo;
+      tiles: Array<TileMetadata & { data: Uint8Array }>;
+      warnings: TileWarning[];
+    }
+  | {
+      type: 'error';
+      id: number | null;
+      error: {
+        code: string;
+        message: string;
+        details: Record<string, unknown> | null;
+        key: string;
+        params: Record<string, unknown>;
+      };
+    };
+
+/**
+ * タイル化は続けたが知らせる問題
+ */
+export interface TileWarning {
+  /** `quality_ignored`（エンコーダーが品質を使わない）、`exif_orientation_ignored`（EXIFの向きを適用していない） */
+  code: 'quality_ignored' | 'exif_orientation_ignored';
+  message: string;
+}
+
+/**
+ * `tile_pamphlet` がファイルを読み書きするコールバック（Node.jsでは `fs` に渡す）
+ */
+export interface PamphletIo {
+  /** 入力のファイルを読み込む */
+  read(path: string): Uint8Array | Promise<Uint8Array>;
+  /** タイル・metadata.jsonを書き出す（親ディレクトリが無ければ作ること） */
+  write(path: string, data: Uint8Array): void | Promise<void>;
+  /** 出力先に既にあるか（あれば書き出さない。省略した場合はすべて書き出す） */
+  exists?(path: string): boolean | Promise<boolean>;
+}
+
+/**
+ * `tile_pamphlet` の結果
+ */
+export interface PamphletOutput {
+  /** 書き出したmetadata.jsonの内容 */
+  metadata: Metadata;
+  pages: number;
+  tiles: number;
+  /** 新しく書き出したタイルの数 */
+  written: number;
+  /** タイル化は続けた問題（入力のパス付き） */
+  warnings: Array<TileWarning & { input: string }>;
+}
+
+/**
+ * タイル化の処理時間の内訳（ミリ秒）
+ */
+export interface TileTimings {
+  decode_ms: number;
+  /** タイルの切り出し（全タイルの合計） */
+  crop_ms: number;
+  /** WebPのエンコード（全タイルの合計） */
+  encode_ms: number;
+  /** ハッシュの計算（全タイルの合計） */
+  hash_ms: number;
+  /** タイルのデータの合計バイト数 */
+  total_bytes: number;
+  /** タイルごとの内訳 */
+  tiles: Array<{ x: number; y: number; crop_ms: number; encode_ms: number; hash_ms: number; bytes: number }>;
+}
+
+/**
+ * ビルドのバージョンと機能（`version_info`）
+ */
+export interface VersionInfo {
+  /** クレートのバージョン */
+  version: string;
+  /** ビルドしたコミット */
+  git_hash: string | null;
+  /** 有効なCargoのフィーチャー */
+  features: string[];
+  simd: boolean;
+  threads: boolean;
+  allocator: 'system' | 'dlmalloc' | 'talc' | 'wee_alloc';
+  /** タイル化できる入力の形式 */
+  input_formats: string[];
+  /** タイルの形式 */
+  output_formats: string[];
+  /** 品質を指定して非可逆でエンコードできるか */
+  lossy_webp: boolean;
 }
 
 /**
@@ -49,13 +309,134 @@ export interface WasmModule {
    */
   tile_image(imageData: Uint8Array, tileSize: number, quality?: number): JsTileResult;
 
+  /**
+   * 設定オブジェクトを指定して画像をタイル化
+   * @param imageData 元画像のバイトデータ（JPEG/PNG等）
+   * @param options タイル化の設定
+   * @returns タイル化結果
+   */
+  tile_image_with_options(imageData: Uint8Array, options?: TileOptions): JsTileResult;
+
+  /**
+   * タイルの合間にイベントループへ戻りながら画像をタイル化（引数は `tile_image` と同じ）
+   * @returns タイル化結果のPromise
+   */
+  tile_image_async(imageData: Uint8Array, tileSize: number, quality?: number): Promise<JsTileResult>;
+
+  /**
+   * タイルの合間にイベントループへ戻りながら、設定オブジェクトを指定して画像をタイル化
+   * @returns タイル化結果のPromise
+   */
+  tile_image_with_options_async(imageData: Uint8Array, options?: TileOptions): Promise<JsTileResult>;
+
+  /**
+   * 専用ワーカーで受け取ったメッセージを処理
+   * @param message メインスレッドからのメッセージ
+   * @param post 応答を送る関数（`transfer` は `postMessage` に渡す転送リスト）
+   */
+  handle_worker_message(
+    message: WorkerCommand,
+    post: (message: WorkerEvent, transfer: ArrayBuffer[]) => void
+  ): Promise<void>;
+
+  /**
+   * ページ画像・PDFを読み順にタイル化し、タイルとmetadata.jsonを書き出す（Node.jsなど）
+   * @param inputs 入力のパス（ページの順）
+   * @param output 出力先（`{output}/tiles/{hash}.webp` と `{output}/metadata.json`）
+   * @param io ファイルを読み書きするコールバック
+   * @param options タイル化の設定
+   * @param dpi PDFをラスタライズする解像度（デフォルト150）
+   * @param layout タイルの出力の配置（デフォルト `flat`）
+   */
+  tile_pamphlet(
+    inputs: string[],
+    output: string,
+    io: PamphletIo,
+    options?: TileOptions,
+    dpi?: number,
+    layout?: TileLayout
+  ): Promise<PamphletOutput>;
+
+  /**
+   * タイルのパス（出力先・キーの接頭辞からの相対パス）
+   * @param layout metadataの `tile_layout`（省略時は `flat`）
+   * @param page ページ番号
+   * @param x タイルのX座標
+   * @param y タイルのY座標
+   * @param hash タイルのハッシュ
+   */
+  tile_path(layout: TileLayout | undefined, page: number, x: number, y: number, hash: string): string;
+
+  /**
+   * ファイル名の一覧からページ画像・PDFを選び、自然順に並べる
+   * @param names ファイル名の一覧（`fs.readdir` の結果など）
+   */
+  sort_page_files(names: string[]): string[];
+
   /**
    * metadata.jsonを生成
-   * @param pagesJson ページ情報のJSON文字列
+   * @param pages ページ情報の配列（またはそのJSON文字列）
    * @param tileSize タイルサイズ
+   * @param toc 目次の配列（またはそのJSON文字列、省略可）
    * @returns metadata.jsonの文字列
    */
-  generate_metadata(pagesJson: string, tileSize: number): string;
+  generate_metadata(pages: PageInfo[] | string, tileSize: number, toc?: TocEntry[] | string): string;
+
+  /**
+   * metadataをオブジェクトとして生成（引数は `generate_metadata` と同じ）
+   * @returns metadata
+   */
+  generate_metadata_value(pages: PageInfo[] | string, tileSize: number, toc?: TocEntry[] | string): Metadata;
+
+  /**
+   * metadataのバージョンを固定（`undefined` で現在時刻に戻す）
+   * @param version バージョン（UNIXエポックからのミリ秒など、0以上の整数）
+   */
+  set_metadata_version(version?: number): void;
+
+  /**
+   * ページ情報・目次の厳密な読み込みを有効・無効にする（既定は無効）
+   * @param strict 知らない項目・足りない項目・型の誤りを場所付きのエラーにするか
+   */
+  set_metadata_strict(strict: boolean): void;
+
+  /**
+   * デコードを許可する入力の形式を設定（`undefined` ですべて許可）
+   * @param formats 形式の名前の配列（`jpeg`・`png`・`webp` など）
+   */
+  set_allowed_formats(formats?: InputFormatName[] | null): void;
+
+  /**
+   * デコードを許可する入力の形式
+   */
+  allowed_formats(): InputFormatName[];
+
+  /**
+   * 組み込みのエラーメッセージ（対応していない言語は `undefined`）
+   * @param locale `ja`・`en`（`ja-JP` のような地域付きも可）
+   */
+  error_messages(locale: string): Record<string, string> | undefined;
+
+  /**
+   * APIが投げたエラーを `locale` のメッセージにする（対応していない場合は `message`）
+   * @param error APIが投げたエラー
+   * @param locale `ja`・`en`（`ja-JP` のような地域付きも可）
+   */
+  localize_error(error: unknown, locale: string): string;
+
+  /**
+   * ログのレベルを設定（既定は `warn`）
+   * @param level ログのレベル
+   */
+  set_log_level(level: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace'): void;
+
+  /** 現在のログのレベル */
+  log_level(): string;
+
+  /**
+   * このビルドのバージョンと機能を取得
+   */
+  version_info(): VersionInfo;
 
   /**
    * SHA256ハッシュを計算
@@ -81,6 +462,29 @@ export interface TileMetadata {
   hash: string;
 }
 
+/**
+ * テキストレイヤーの単語（OCR結果）
+ */
+export interface WordBox {
+  /** 単語のテキスト */
+  text: string;
+  /** 位置とサイズ（ページの原寸ピクセル） */
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/**
+ * 自動切り抜きの範囲（切り抜く前のスキャンのピクセル座標）
+ */
+export interface CropRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
 /**
  * ページ情報
  */
@@ -93,8 +497,39 @@ export interface PageInfo {
   height: number;
   /** ページ内のタイル配列 */
   tiles: TileMetadata[];
+  /** ページの表示名（ノンブルなど、`MetadataBuilder` で指定した場合） */
+  label?: string;
+  /** テキストレイヤー（OCR結果がある場合） */
+  words?: WordBox[];
+  /** ページの解像度（実寸の計測に使用） */
+  dpi?: number;
+  /** 自動切り抜きした範囲（元のスキャンの座標への変換に使用） */
+  crop?: CropRect;
+  /** 前処理で拡大した倍率（ページの座標を割ると元の画像の座標になる） */
+  scale?: number;
 }
 
+/**
+ * 目次の項目
+ */
+export interface TocEntry {
+  /** 見出し */
+  title: string;
+  /** 移動先のページ番号（0始まり） */
+  page: number;
+  /** 下位の項目 */
+  children?: TocEntry[];
+}
+
+/**
+ * タイルの出力の配置
+ * - `flat`: `tiles/{hash}.webp`
+ * - `sharded`: `tiles/{hash[0..2]}/{hash[2..4]}/{hash}.webp`
+ * - `page`: `tiles/{page}/{hash}.webp`
+ * - `zxy`: `tiles/{page}/{x}/{y}.webp`
+ */
+export type TileLayout = 'flat' | 'sharded' | 'page' | 'zxy';
+
 /**
  * パンフレットのメタデータ
  */
@@ -105,6 +540,12 @@ export interface Metadata {
   tile_size: number;
   /** ページ配列 */
   pages: PageInfo[];
+  /** 読み方向（`MetadataBuilder` で設定した場合） */
+  reading_direction?: 'ltr' | 'rtl';
+  /** 目次（PDFのしおりなど） */
+  toc?: TocEntry[];
+  /** タイルの出力の配置（`flat` の場合は省略） */
+  tile_layout?: TileLayout;
 }
 
 // ============================================
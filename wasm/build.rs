@@ -0,0 +1,28 @@
+use std::process::Command;
+
+/// ビルドしたコミットを `TILE_WASM_GIT_HASH` に設定する（`version_info` で返す）
+///
+/// 環境変数で指定した場合はそれを使い、gitがない・リポジトリの外でビルドした場合は設定しない
+fn main() {
+    println!("cargo:rerun-if-env-changed=TILE_WASM_GIT_HASH");
+    if std::env::var_os("TILE_WASM_GIT_HASH").is_some() {
+        return;
+    }
+
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).output().ok()?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        (output.status.success() && !text.trim().is_empty()).then(|| text.trim().to_string())
+    };
+    let Some(hash) = git(&["rev-parse", "--short=12", "HEAD"]) else {
+        return;
+    };
+    println!("cargo:rustc-env=TILE_WASM_GIT_HASH={}", hash);
+
+    // コミットやブランチの切り替えで設定し直す
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        for path in ["HEAD", "refs/heads", "packed-refs"] {
+            println!("cargo:rerun-if-changed={}/{}", git_dir, path);
+        }
+    }
+}
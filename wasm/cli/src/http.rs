@@ -0,0 +1,423 @@
+//! S3への送信に使う最小限のHTTP/1.1クライアント
+//!
+//! エンドポイントへの接続を使い回して直接送る。`https://` の接続は `tls` フィーチャーで
+//! システムのOpenSSLを使って暗号化する（無効な場合、`https://` のエンドポイントはエラー）
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use crate::tls::{TlsConnector, TlsStream};
+
+/// 接続のタイムアウト
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 送受信のタイムアウト
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 使い回す接続の上限
+const MAX_IDLE: usize = 32;
+
+/// 送信先（`{scheme}://{host}[:{port}][/{path}]`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    /// パスの前置き（末尾の `/` なし。ない場合は空）
+    pub base_path: String,
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid endpoint: {}", url);
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(invalid());
+        };
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let default_port = if tls { 443 } else { 80 };
+        // IPv6のアドレス（`[::1]:9000`）はコロンを含むため、最後の `]` より後のコロンで分ける
+        let port_start = authority
+            .rfind(':')
+            .filter(|&colon| authority.rfind(']').is_none_or(|bracket| colon > bracket));
+        let (host, port) = match port_start {
+            Some(colon) => (
+                &authority[..colon],
+                authority[colon + 1..].parse().map_err(|_| invalid())?,
+            ),
+            None => (authority, default_port),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            tls,
+            host: host.to_string(),
+            port,
+            base_path: path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// `Host` ヘッダーの値（既定のポートは付けない）
+    pub fn host_header(&self) -> String {
+        if self.port == if self.tls { 443 } else { 80 } {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// 送るリクエスト
+///
+/// `Host`・`Content-Length` はクライアントが付ける
+#[derive(Debug, Clone, Copy)]
+pub struct Request<'a> {
+    pub method: &'a str,
+    /// URIエンコードした、`base_path` を含むパス
+    pub path: &'a str,
+    pub headers: &'a [(String, String)],
+    pub body: Option<&'a [u8]>,
+}
+
+/// 受け取ったレスポンス
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// エンドポイントへの接続
+#[derive(Debug)]
+enum Stream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// エンドポイントへの接続を使い回すクライアント
+#[derive(Debug)]
+pub struct HttpClient {
+    endpoint: Endpoint,
+    /// `https://` のエンドポイントの場合のTLSの設定
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConnector>,
+    idle: Mutex<Vec<BufReader<Stream>>>,
+}
+
+impl HttpClient {
+    /// # Errors
+    /// `https://` のエンドポイントで、TLSの初期化に失敗した場合か `tls` フィーチャーが無効な場合
+    pub fn new(endpoint: Endpoint) -> Result<Self, String> {
+        #[cfg(feature = "tls")]
+        let tls = if endpoint.tls {
+            let connector =
+                TlsConnector::new().map_err(|e| format!("Failed to initialize TLS: {}", e))?;
+            Some(connector)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "tls"))]
+        if endpoint.tls {
+            return Err("https:// endpoints require the `tls` feature".to_string());
+        }
+        Ok(Self {
+            endpoint,
+            #[cfg(feature = "tls")]
+            tls,
+            idle: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    pub fn send(&self, request: &Request) -> Result<Response, String> {
+        // 使い回した接続はサーバーが閉じている場合があるため、失敗したら新しい接続で送り直す
+        let idle = self.idle.lock().unwrap().pop();
+        if let Some(connection) = idle {
+            if let Ok(response) = self.exchange(connection, request) {
+                return Ok(response);
+            }
+        }
+        self.exchange(self.connect()?, request)
+            .map_err(|e| format!("{} {}: {}", request.method, request.path, e))
+    }
+
+    fn connect(&self) -> Result<BufReader<Stream>, String> {
+        let failed = |e: io::Error| format!("Failed to connect to {}: {}", self.endpoint.host, e);
+        let host = self.endpoint.host.trim_matches(['[', ']']);
+        let addrs = (host, self.endpoint.port)
+            .to_socket_addrs()
+            .map_err(failed)?;
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no address");
+        for addr in addrs {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(failed)?;
+                    stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(failed)?;
+                    stream.set_nodelay(true).map_err(failed)?;
+                    #[cfg(feature = "tls")]
+                    if let Some(connector) = &self.tls {
+                        let stream = connector.connect(host, stream).map_err(failed)?;
+                        return Ok(BufReader::new(Stream::Tls(stream)));
+                    }
+                    return Ok(BufReader::new(Stream::Plain(stream)));
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(failed(last_error))
+    }
+
+    /// 1つの接続でリクエストを送ってレスポンスを読み、続けて使える接続は戻す
+    fn exchange(
+        &self,
+        mut connection: BufReader<Stream>,
+        request: &Request,
+    ) -> io::Result<Response> {
+        let body = request.body.unwrap_or_default();
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n",
+            request.method,
+            request.path,
+            self.endpoint.host_header(),
+            body.len()
+        );
+        for (name, value) in request.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let stream = connection.get_mut();
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        let (status, headers) = read_head(&mut connection)?;
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let mut reusable = !header("Connection").is_some_and(|v| v.eq_ignore_ascii_case("close"));
+        let body = if request.method == "HEAD" || status == 204 || status == 304 {
+            Vec::new()
+        } else if header("Transfer-Encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+            read_chunked(&mut connection)?
+        } else if let Some(length) = header("Content-Length") {
+            let length: u64 = length
+                .trim()
+                .parse()
+                .map_err(|_| invalid_data("Invalid Content-Length"))?;
+            let mut body = Vec::new();
+            (&mut connection).take(length).read_to_end(&mut body)?;
+            if (body.len() as u64) < length {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            body
+        } else {
+            // 長さのない本文は接続が閉じるまで続く
+            reusable = false;
+            let mut body = Vec::new();
+            connection.read_to_end(&mut body)?;
+            body
+        };
+
+        if reusable {
+            let mut idle = self.idle.lock().unwrap();
+            if idle.len() < MAX_IDLE {
+                idle.push(connection);
+            }
+        }
+        Ok(Response { status, body })
+    }
+}
+
+/// ステータス行とヘッダーを読む（`100 Continue` などの途中のレスポンスは読み飛ばす）
+fn read_head(reader: &mut impl BufRead) -> io::Result<(u16, Vec<(String, String)>)> {
+    loop {
+        let line = read_line(reader)?;
+        let status: u16 = line
+            .strip_prefix("HTTP/")
+            .and_then(|rest| rest.split_whitespace().nth(1))
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| invalid_data(&format!("Invalid status line: {}", line)))?;
+        let mut headers = Vec::new();
+        loop {
+            let line = read_line(reader)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        if !(100..200).contains(&status) {
+            return Ok((status, headers));
+        }
+    }
+}
+
+/// `Transfer-Encoding: chunked` の本文を読む
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = u64::from_str_radix(size, 16).map_err(|_| invalid_data("Invalid chunk size"))?;
+        if size == 0 {
+            // トレーラーを読み飛ばす
+            while !read_line(reader)?.is_empty() {}
+            return Ok(body);
+        }
+        let start = body.len();
+        reader.by_ref().take(size).read_to_end(&mut body)?;
+        if ((body.len() - start) as u64) < size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        read_line(reader)?;
+    }
+}
+
+/// 改行を除いた1行を読む（接続が閉じていたらエラー）
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_endpoint() {
+        let endpoint = Endpoint::parse("https://example.r2.cloudflarestorage.com/").unwrap();
+        assert_eq!(
+            endpoint,
+            Endpoint {
+                tls: true,
+                host: "example.r2.cloudflarestorage.com".to_string(),
+                port: 443,
+                base_path: String::new(),
+            }
+        );
+        assert_eq!(endpoint.host_header(), "example.r2.cloudflarestorage.com");
+
+        let endpoint = Endpoint::parse("http://[::1]:9000/storage").unwrap();
+        assert_eq!(endpoint.host, "[::1]");
+        assert_eq!(endpoint.port, 9000);
+        assert_eq!(endpoint.base_path, "/storage");
+        assert_eq!(endpoint.host_header(), "[::1]:9000");
+
+        assert!(Endpoint::parse("ftp://example.com").is_err());
+        assert!(Endpoint::parse("http://example.com:port").is_err());
+        assert!(Endpoint::parse("http://").is_err());
+    }
+
+    #[test]
+    fn test_read_head() {
+        let mut data =
+            &b"HTTP/1.1 100 Continue\r\n\r\nHTTP/2 200\r\ncontent-length: 2\r\n\r\nok"[..];
+        let (status, headers) = read_head(&mut data).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(
+            headers,
+            vec![("content-length".to_string(), "2".to_string())]
+        );
+        assert_eq!(data, b"ok");
+
+        assert!(read_head(&mut &b"garbage\r\n\r\n"[..]).is_err());
+        assert!(read_head(&mut &b""[..]).is_err());
+    }
+
+    #[test]
+    fn test_read_chunked() {
+        let mut data = &b"5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nTrailer: x\r\n\r\n"[..];
+        assert_eq!(read_chunked(&mut data).unwrap(), b"hello world");
+        assert!(read_chunked(&mut &b"5\r\nhel"[..]).is_err());
+    }
+
+    #[test]
+    fn test_reconnect() {
+        // 1回目のレスポンスの後に接続を閉じるサーバー
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                while !read_line(&mut reader).unwrap().is_empty() {}
+                reader
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n")
+                    .unwrap();
+            }
+        });
+
+        let client =
+            HttpClient::new(Endpoint::parse(&format!("http://127.0.0.1:{}", port)).unwrap())
+                .unwrap();
+        let request = Request {
+            method: "GET",
+            path: "/",
+            headers: &[],
+            body: None,
+        };
+        for _ in 0..2 {
+            let response = client.send(&request).unwrap();
+            assert_eq!(
+                response,
+                Response {
+                    status: 200,
+                    body: b"ok".to_vec()
+                }
+            );
+        }
+        server.join().unwrap();
+    }
+}
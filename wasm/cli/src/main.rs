@@ -0,0 +1,708 @@
+//! パンフレットのタイル化のCLI（`pamphlet-tiler`）
+//!
+//! ページ画像・PDFをWASMと同じタイル化の処理でタイルにし、`tiles/{hash}.webp` と
+//! `metadata.json` を出力する。出力先の配置は既定ではR2の `pamphlets/{id}/` と同じで、
+//! そのままアップロードできる。ビルドサーバーでヘッドレスブラウザを使わずに出力するために使う
+
+mod http;
+mod sigv4;
+#[cfg(feature = "tls")]
+mod tls;
+mod upload;
+
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "serve")]
+use std::net::{SocketAddr, TcpListener};
+
+use tile_wasm::native::{
+    page_files, pamphlet_prefix, plan_files, tile_files, upload_plan, DirectoryOptions,
+    IncrementalTiler, PamphletConfig, Plan, Publication, TileLayout, TileOptions,
+    DEFAULT_RASTER_DPI,
+};
+#[cfg(feature = "serve")]
+use tile_wasm::native::{PreviewServer, Settings};
+
+/// `--config` を指定しなかった場合に、カレントディレクトリにあれば読む設定ファイル
+const DEFAULT_CONFIG: &str = "pamphlet.toml";
+
+const HELP: &str = "\
+pamphlet-tiler: ページ画像・PDFをタイル化し、tiles/{hash}.webp と metadata.json を出力する
+
+USAGE:
+  pamphlet-tiler [OPTIONS] <INPUT>...
+  pamphlet-tiler serve [OPTIONS] <INPUT>...   タイルを要求されたときにエンコードしてプレビューする
+  pamphlet-tiler watch [OPTIONS] <INPUT>...   入力を監視し、変わったページだけをタイル化し直す
+
+ARGS:
+  <INPUT>...              ページ画像・PDF、またはそれらを含むディレクトリ（ファイル名の自然順）
+
+OPTIONS:
+  -c, --config <FILE>     設定ファイル（TOML、拡張子が .json ならJSON。既定: ./pamphlet.toml が
+                          あれば読む）。引数で指定した項目は設定ファイルより優先する
+  -o, --output <DIR>      出力先のディレクトリ（既定: out）
+  -s, --tile-size <N>     タイルサイズ（16-4096、既定: 512）
+  -q, --quality <Q>       WebP品質（1-100、既定: 80）
+      --dpi <DPI>         PDFをラスタライズする解像度（既定: 150）
+  -j, --jobs <N>          並列にタイル化するスレッドの数（既定: CPUの数）
+      --layout <LAYOUT>   タイルの配置（flat | sharded | page | zxy、既定: flat）
+      --dry-run           エンコード・書き出し・アップロードをせず、書き出すはずのmetadataと
+                          アップロード・削除の計画をJSONで標準出力に書く
+      --addr <ADDR>       serve: 待ち受けるアドレス（既定: 127.0.0.1:8080）
+      --interval <MS>     watch: 入力を確かめる間隔（ミリ秒、1以上、既定: 500）
+      --bucket <NAME>     出力をS3互換のストレージ（R2など）のバケットにアップロードする
+      --id <ID>           アップロード先のパンフレットID（pamphlets/<ID>/、--bucket と一緒に指定）
+      --endpoint <URL>    S3のエンドポイント（既定: 環境変数 S3_ENDPOINT）
+      --region <REGION>   署名のリージョン（既定: 環境変数 AWS_REGION、無ければ auto）
+      --concurrency <N>   同時に送るタイルの数（既定: 16）
+  -h, --help              このヘルプを表示する
+  -V, --version           バージョンを表示する
+
+ENVIRONMENT:
+  AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY   アップロードの認証情報
+";
+
+/// コマンドライン引数
+#[derive(Debug, Clone, PartialEq)]
+struct Args {
+    inputs: Vec<PathBuf>,
+    output: PathBuf,
+    tile_size: u32,
+    quality: f32,
+    dpi: f32,
+    /// 並列にタイル化するスレッドの数（`None` はCPUの数）
+    jobs: Option<usize>,
+    /// タイルの出力の配置
+    layout: TileLayout,
+    /// 設定ファイルの内容（形式・端の埋め方・前処理・ファイルごとの設定に使う）
+    config: PamphletConfig,
+    /// 出力後のアップロード（`--bucket`）
+    upload: Option<UploadArgs>,
+}
+
+/// アップロードの引数
+#[derive(Debug, Clone, PartialEq)]
+struct UploadArgs {
+    bucket: String,
+    id: String,
+    endpoint: Option<String>,
+    region: Option<String>,
+    concurrency: usize,
+}
+
+/// 実行すること
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Help,
+    Version,
+    Tile(Args),
+    /// 出力せずに、書き出すはずのmetadataと計画を表示する（`--dry-run`）
+    Plan(Args),
+    /// 出力せずに、HTTPでタイルとmetadataを返す（`output` は使わない）
+    #[cfg(feature = "serve")]
+    Serve {
+        args: Args,
+        addr: SocketAddr,
+    },
+    /// 入力を監視し、変わるたびに出力し直す
+    Watch {
+        args: Args,
+        interval: Duration,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args_os().skip(1).collect()) {
+        Ok(Command::Help) => {
+            print!("{}", HELP);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("pamphlet-tiler {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Tile(args)) => args,
+        Ok(Command::Plan(args)) => return plan(&args),
+        #[cfg(feature = "serve")]
+        Ok(Command::Serve { args, addr }) => return serve(&args, addr),
+        Ok(Command::Watch { args, interval }) => return watch(&args, interval),
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, HELP);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(publication) => {
+            for (file, warning) in &publication.warnings {
+                eprintln!("warning: {}: {}", file.display(), warning.message);
+            }
+            eprintln!(
+                "{} pages, {} tiles ({} written) -> {}",
+                publication.pages,
+                publication.tiles,
+                publication.written,
+                args.output.display()
+            );
+            let Some(upload) = &args.upload else {
+                return ExitCode::SUCCESS;
+            };
+            match upload_publication(&args.output, upload, &publication) {
+                Ok(summary) => {
+                    eprintln!(
+                        "{} uploaded, {} skipped -> {}/{}/",
+                        summary.uploaded,
+                        summary.skipped,
+                        upload.bucket,
+                        pamphlet_prefix(&upload.id)
+                    );
+                    ExitCode::SUCCESS
+                }
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// コマンドライン引数を読み込む（最初の引数が `serve`・`watch` の場合はそのサブコマンド）
+fn parse_args(mut args: Vec<OsString>) -> Result<Command, String> {
+    let subcommand = match args.first().and_then(|arg| arg.to_str()) {
+        Some("serve") if cfg!(feature = "serve") => Some("serve"),
+        Some("watch") => Some("watch"),
+        _ => None,
+    };
+    if subcommand.is_some() {
+        args.remove(0);
+    }
+    let mut args = pico_args::Arguments::from_vec(args);
+    if args.contains(["-h", "--help"]) {
+        return Ok(Command::Help);
+    }
+    if args.contains(["-V", "--version"]) {
+        return Ok(Command::Version);
+    }
+
+    let error = |e: pico_args::Error| e.to_string();
+    let dry_run = args.contains("--dry-run");
+    let config = match args
+        .opt_value_from_str::<_, PathBuf>(["-c", "--config"])
+        .map_err(error)?
+    {
+        Some(path) => PamphletConfig::load(&path)?,
+        None if Path::new(DEFAULT_CONFIG).is_file() => {
+            PamphletConfig::load(Path::new(DEFAULT_CONFIG))?
+        }
+        None => PamphletConfig::default(),
+    };
+    // 引数 > 設定ファイル > 既定値
+    let output = args
+        .opt_value_from_str(["-o", "--output"])
+        .map_err(error)?
+        .or_else(|| config.output.clone())
+        .unwrap_or_else(|| PathBuf::from("out"));
+    let tile_size = args
+        .opt_value_from_str(["-s", "--tile-size"])
+        .map_err(error)?
+        .or(config.tile_size)
+        .unwrap_or(512);
+    let quality = args
+        .opt_value_from_str(["-q", "--quality"])
+        .map_err(error)?
+        .or(config.quality)
+        .unwrap_or(80.0);
+    let dpi = args
+        .opt_value_from_str("--dpi")
+        .map_err(error)?
+        .or(config.dpi)
+        .unwrap_or(DEFAULT_RASTER_DPI);
+    let jobs = args
+        .opt_value_from_str(["-j", "--jobs"])
+        .map_err(error)?
+        .or(config.jobs);
+    if jobs == Some(0) {
+        return Err("--jobs must be at least 1".to_string());
+    }
+    let layout = args
+        .opt_value_from_str("--layout")
+        .map_err(error)?
+        .or(config.layout)
+        .unwrap_or_default();
+    #[cfg(feature = "serve")]
+    let addr = args
+        .opt_value_from_str("--addr")
+        .map_err(error)?
+        .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], 8080)));
+    let interval = args
+        .opt_value_from_str("--interval")
+        .map_err(error)?
+        .unwrap_or(500);
+    if interval == 0 {
+        return Err("--interval must be at least 1".to_string());
+    }
+    let interval = Duration::from_millis(interval);
+    let bucket: Option<String> = args.opt_value_from_str("--bucket").map_err(error)?;
+    let id: Option<String> = args.opt_value_from_str("--id").map_err(error)?;
+    let endpoint = args.opt_value_from_str("--endpoint").map_err(error)?;
+    let region = args.opt_value_from_str("--region").map_err(error)?;
+    let concurrency = args
+        .opt_value_from_str("--concurrency")
+        .map_err(error)?
+        .unwrap_or(16);
+    let upload = match (bucket, id) {
+        (Some(bucket), Some(id)) => Some(UploadArgs {
+            bucket,
+            id,
+            endpoint,
+            region,
+            concurrency,
+        }),
+        (Some(_), None) => return Err("--bucket requires --id".to_string()),
+        (None, Some(_)) => return Err("--id requires --bucket".to_string()),
+        (None, None) => None,
+    };
+
+    let inputs: Vec<PathBuf> = args
+        .finish()
+        .into_iter()
+        .map(|arg| match arg.to_str() {
+            Some(flag) if flag.starts_with('-') && flag.len() > 1 => {
+                Err(format!("Unknown option: {}", flag))
+            }
+            _ => Ok(PathBuf::from(arg)),
+        })
+        .collect::<Result<_, _>>()?;
+    if inputs.is_empty() {
+        return Err("No input files".to_string());
+    }
+
+    let args = Args {
+        inputs,
+        output,
+        tile_size,
+        quality,
+        dpi,
+        jobs,
+        layout,
+        config,
+        upload,
+    };
+    match subcommand {
+        Some(subcommand) if dry_run => Err(format!("--dry-run cannot be used with {}", subcommand)),
+        #[cfg(feature = "serve")]
+        Some("serve") => Ok(Command::Serve { args, addr }),
+        Some("watch") => Ok(Command::Watch { args, interval }),
+        _ if dry_run => Ok(Command::Plan(args)),
+        _ => Ok(Command::Tile(args)),
+    }
+}
+
+/// 入力を読み順のファイルの一覧にする（ディレクトリは直下のページ画像・PDFをファイル名の自然順に）
+fn collect_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            files.extend(page_files(input)?);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// 出力のオプション（引数で決まらない項目は設定ファイルから）
+fn directory_options(args: &Args) -> DirectoryOptions {
+    let options = DirectoryOptions::new(&args.output).with_config(&args.config);
+    DirectoryOptions {
+        tile: TileOptions {
+            tile_size: args.tile_size,
+            quality: Some(args.quality),
+            ..options.tile.clone()
+        },
+        dpi: args.dpi,
+        jobs: args.jobs,
+        layout: args.layout,
+        ..options
+    }
+}
+
+/// 入力をタイル化し、タイルとmetadata.jsonを書き出す
+fn run(args: &Args) -> Result<Publication, String> {
+    tile_files(&collect_inputs(&args.inputs)?, &directory_options(args))
+}
+
+/// 入力のタイルの配置と画素のハッシュだけを計算し、書き出すはずのmetadataと計画を返す
+///
+/// キーは `--id` があれば `pamphlets/{id}/` から始まる（無ければ出力先からのパス）
+fn run_plan(args: &Args) -> Result<Plan, String> {
+    let prefix = args
+        .upload
+        .as_ref()
+        .map_or_else(String::new, |upload| pamphlet_prefix(&upload.id));
+    plan_files(
+        &collect_inputs(&args.inputs)?,
+        &directory_options(args),
+        &prefix,
+    )
+}
+
+/// 計画をJSONで標準出力に書き、概要を標準エラー出力に書く
+fn plan(args: &Args) -> ExitCode {
+    let plan = match run_plan(args) {
+        Ok(plan) => plan,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+    for (file, warning) in &plan.warnings {
+        eprintln!("warning: {}: {}", file.display(), warning.message);
+    }
+    let written = serde_json::to_string_pretty(&plan)
+        .map_err(|e| e.to_string())
+        .and_then(|json| writeln!(std::io::stdout(), "{}", json).map_err(|e| e.to_string()));
+    if let Err(message) = written {
+        eprintln!("error: Failed to write plan: {}", message);
+        return ExitCode::FAILURE;
+    }
+    eprintln!(
+        "{} pages, {} tiles ({} to encode, {} unused) -> {} (dry run)",
+        plan.pages,
+        plan.tiles,
+        plan.encode.len(),
+        plan.unused.len(),
+        args.output.display()
+    );
+    ExitCode::SUCCESS
+}
+
+/// 入力を `interval` ごとに確かめ、変わったページだけをタイル化し直してmetadata.jsonを書き直す
+///
+/// 保存の途中のファイルなどで失敗した場合は、エラーを表示して次の確認でやり直す
+fn watch(args: &Args, interval: Duration) -> ExitCode {
+    let mut tiler = IncrementalTiler::new(directory_options(args));
+    let mut last_error = None;
+    loop {
+        match collect_inputs(&args.inputs).and_then(|files| tiler.update(&files)) {
+            Ok(Some(update)) => {
+                last_error = None;
+                let publication = &update.publication;
+                for (file, warning) in &publication.warnings {
+                    if update.retiled.contains(file) {
+                        eprintln!("warning: {}: {}", file.display(), warning.message);
+                    }
+                }
+                eprintln!(
+                    "{} files retiled: {} pages, {} tiles ({} written) -> {}",
+                    update.retiled.len(),
+                    publication.pages,
+                    publication.tiles,
+                    publication.written,
+                    args.output.display()
+                );
+                if let Some(upload) = &args.upload {
+                    match upload_publication(&args.output, upload, publication) {
+                        Ok(summary) => {
+                            eprintln!("{} uploaded, {} skipped", summary.uploaded, summary.skipped)
+                        }
+                        Err(message) => eprintln!("error: {}", message),
+                    }
+                }
+            }
+            Ok(None) => last_error = None,
+            // 同じエラーは続けて表示しない
+            Err(message) => {
+                if last_error.as_ref() != Some(&message) {
+                    eprintln!("error: {}", message);
+                    last_error = Some(message);
+                }
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// 出力したタイルとmetadata.jsonを `pamphlets/{id}/` にアップロードする
+fn upload_publication(
+    output: &Path,
+    args: &UploadArgs,
+    publication: &Publication,
+) -> Result<upload::UploadSummary, String> {
+    let endpoint = match &args.endpoint {
+        Some(endpoint) => endpoint.clone(),
+        None => std::env::var("S3_ENDPOINT")
+            .map_err(|_| "--endpoint or S3_ENDPOINT is required to upload".to_string())?,
+    };
+    let region = args
+        .region
+        .clone()
+        .or_else(|| std::env::var("AWS_REGION").ok())
+        .unwrap_or_else(|| "auto".to_string());
+    let store = upload::S3Store::from_env(&endpoint, args.bucket.clone(), region)?;
+    let plan = upload_plan(&pamphlet_prefix(&args.id), &publication.metadata)?;
+    upload::upload(&plan, output, &store, args.concurrency)
+}
+
+/// 入力を読み込み、`addr` でプレビューのタイルとmetadataを返し続ける
+#[cfg(feature = "serve")]
+fn serve(args: &Args, addr: SocketAddr) -> ExitCode {
+    let options = directory_options(args);
+    let server = match collect_inputs(&args.inputs)
+        .and_then(|files| PreviewServer::open(&files, &options, Settings::default()))
+    {
+        Ok(server) => server,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: Failed to listen on {}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let pages = server.metadata()["pages"].as_array().map_or(0, Vec::len);
+    eprintln!(
+        "{} pages -> http://{}/metadata.json (/pamphlet/preview/metadata)",
+        pages, addr
+    );
+    server.serve(listener);
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tile_wasm::test_util::png;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(Into::into).collect())
+    }
+
+    /// テストごとの作業用ディレクトリ（前回の実行の残りは消す）
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pamphlet-tiler-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&["pages", "-o", "dist", "--tile-size", "256", "-q", "90"]),
+            Ok(Command::Tile(Args {
+                inputs: vec![PathBuf::from("pages")],
+                output: PathBuf::from("dist"),
+                tile_size: 256,
+                quality: 90.0,
+                dpi: DEFAULT_RASTER_DPI,
+                jobs: None,
+                layout: TileLayout::Flat,
+                config: PamphletConfig::default(),
+                upload: None,
+            }))
+        );
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse(&[]), Err("No input files".to_string()));
+        assert_eq!(
+            parse(&["pages", "--tiles", "256"]),
+            Err("Unknown option: --tiles".to_string())
+        );
+        assert!(parse(&["pages", "-s", "big"]).is_err());
+        let Ok(Command::Tile(args)) = parse(&["pages", "--bucket", "assets", "--id", "demo"])
+        else {
+            panic!("expected tile");
+        };
+        assert_eq!(
+            args.upload,
+            Some(UploadArgs {
+                bucket: "assets".to_string(),
+                id: "demo".to_string(),
+                endpoint: None,
+                region: None,
+                concurrency: 16,
+            })
+        );
+        let Ok(Command::Tile(args)) = parse(&["pages", "--layout", "sharded"]) else {
+            panic!("expected tile");
+        };
+        assert_eq!(args.layout, TileLayout::Sharded);
+        assert!(parse(&["pages", "--layout", "nested"]).is_err());
+        assert!(matches!(
+            parse(&["pages", "--dry-run"]),
+            Ok(Command::Plan(_))
+        ));
+        assert_eq!(
+            parse(&["watch", "pages", "--dry-run"]),
+            Err("--dry-run cannot be used with watch".to_string())
+        );
+        assert_eq!(
+            parse(&["pages", "-j", "0"]),
+            Err("--jobs must be at least 1".to_string())
+        );
+        assert_eq!(
+            parse(&["watch", "pages", "--interval", "0"]),
+            Err("--interval must be at least 1".to_string())
+        );
+        assert_eq!(
+            parse(&["pages", "--bucket", "assets"]),
+            Err("--bucket requires --id".to_string())
+        );
+        #[cfg(feature = "serve")]
+        {
+            let Ok(Command::Serve { args, addr }) =
+                parse(&["serve", "--addr", "0.0.0.0:3000", "pages"])
+            else {
+                panic!("expected serve");
+            };
+            assert_eq!(args.inputs, [PathBuf::from("pages")]);
+            assert_eq!(addr, SocketAddr::from(([0, 0, 0, 0], 3000)));
+        }
+        assert_eq!(
+            parse(&["watch", "pages", "--interval", "100"]),
+            Ok(Command::Watch {
+                args: Args {
+                    inputs: vec![PathBuf::from("pages")],
+                    output: PathBuf::from("out"),
+                    tile_size: 512,
+                    quality: 80.0,
+                    dpi: DEFAULT_RASTER_DPI,
+                    jobs: None,
+                    layout: TileLayout::Flat,
+                    config: PamphletConfig::default(),
+                    upload: None,
+                },
+                interval: Duration::from_millis(100),
+            })
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let dir = temp_dir("config");
+        let config = dir.join("pamphlet.toml");
+        fs::write(
+            &config,
+            "tile_size = 256\nquality = 90\nlayout = \"page\"\n\n[[pages]]\nmatch = \"cover.*\"\nquality = 95\n",
+        )
+        .unwrap();
+        let config = config.to_str().unwrap();
+
+        // 引数で指定した項目は設定ファイルより優先する
+        let Ok(Command::Tile(args)) = parse(&["pages", "-c", config, "-q", "70"]) else {
+            panic!("expected tile");
+        };
+        assert_eq!((args.tile_size, args.quality), (256, 70.0));
+        assert_eq!(args.layout, TileLayout::Page);
+        let options = directory_options(&args);
+        assert_eq!(options.tile.quality, Some(70.0));
+        assert_eq!(
+            options.for_file(Path::new("pages/cover.png")).tile.quality,
+            Some(95.0)
+        );
+
+        fs::write(dir.join("broken.toml"), "tile_size = 4").unwrap();
+        let broken = dir.join("broken.toml");
+        let error = parse(&["pages", "-c", broken.to_str().unwrap()]).unwrap_err();
+        assert!(error.contains("Invalid config"), "{}", error);
+        assert!(parse(&["pages", "-c", "missing.toml"]).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_collect_inputs() {
+        let dir = temp_dir("collect");
+        for name in ["10.png", "2.JPG", "notes.txt", "1.pdf"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let files = collect_inputs(&[dir.clone(), PathBuf::from("cover.png")]).unwrap();
+        assert_eq!(
+            files,
+            [
+                dir.join("1.pdf"),
+                dir.join("2.JPG"),
+                dir.join("10.png"),
+                PathBuf::from("cover.png"),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_run() {
+        let dir = temp_dir("run");
+        let pages = dir.join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("01.png"), png(40, 20, 0)).unwrap();
+        // 同じ画像のページはタイルを共有する
+        fs::write(pages.join("02.png"), png(40, 20, 0)).unwrap();
+        fs::write(pages.join("03.png"), png(20, 20, 9)).unwrap();
+
+        let args = Args {
+            inputs: vec![pages],
+            output: dir.join("out"),
+            tile_size: 32,
+            quality: 80.0,
+            dpi: DEFAULT_RASTER_DPI,
+            jobs: Some(2),
+            layout: TileLayout::Flat,
+            config: PamphletConfig::default(),
+            upload: None,
+        };
+        // 計画では何も書き出さない
+        let plan = run_plan(&args).unwrap();
+        assert_eq!((plan.pages, plan.tiles, plan.encode.len()), (3, 5, 3));
+        assert!(!dir.join("out").exists());
+
+        let publication = run(&args).unwrap();
+        assert_eq!(
+            (publication.pages, publication.tiles, publication.written),
+            (3, 5, 3)
+        );
+
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("out/metadata.json")).unwrap()).unwrap();
+        assert_eq!(metadata["tile_size"], 32);
+        assert_eq!(metadata["pages"][2]["page"], 2);
+        assert_eq!(metadata["pages"][0]["width"], 40);
+        let hash = metadata["pages"][2]["tiles"][0]["hash"].as_str().unwrap();
+        let tile = fs::read(dir.join("out/tiles").join(format!("{}.webp", hash))).unwrap();
+        assert_eq!(&tile[..4], b"RIFF");
+
+        // 出力した後の計画は、エンコードするタイルが無く、キーは `--id` の接頭辞から始まる
+        let args = Args {
+            upload: Some(UploadArgs {
+                bucket: "assets".to_string(),
+                id: "demo".to_string(),
+                endpoint: None,
+                region: None,
+                concurrency: 16,
+            }),
+            ..args
+        };
+        let plan = run_plan(&args).unwrap();
+        assert!(plan.encode.is_empty());
+        assert_eq!(plan.metadata["pages"], metadata["pages"]);
+        assert_eq!(
+            plan.upload.last().map(|object| object.key.as_str()),
+            Some("pamphlets/demo/metadata.json")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
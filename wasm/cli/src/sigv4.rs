@@ -0,0 +1,246 @@
+//! AWS署名バージョン4（S3互換のストレージへのリクエストの署名）
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+/// 署名のアルゴリズム
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// HMAC-SHA256のブロックの長さ（バイト）
+const BLOCK_SIZE: usize = 64;
+
+/// 認証情報と署名のスコープ（リージョン・サービス）
+#[derive(Debug, Clone)]
+pub struct Signer {
+    pub access_key: String,
+    pub secret_key: String,
+    /// リージョン（R2は `auto`）
+    pub region: String,
+    pub service: String,
+}
+
+/// 署名するリクエスト
+#[derive(Debug, Clone)]
+pub struct CanonicalRequest<'a> {
+    pub method: &'a str,
+    /// URIエンコードしたパス
+    pub path: &'a str,
+    /// 名前の順に並べた、URIエンコードしたクエリ（ない場合は空）
+    pub query: &'a str,
+    /// 署名するヘッダー（`host` を含む。名前は大文字・小文字を区別しない）
+    pub headers: &'a [(String, String)],
+    /// 本文のSHA256（16進数）
+    pub payload_hash: &'a str,
+}
+
+impl Signer {
+    /// `timestamp`（`20150830T123600Z` の形式）に署名した `Authorization` ヘッダーの値
+    pub fn authorization(&self, request: &CanonicalRequest, timestamp: &str) -> String {
+        let date = &timestamp[..timestamp.len().min(8)];
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+
+        let mut headers: Vec<(String, String)> = request
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), canonical_value(value)))
+            .collect();
+        headers.sort();
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method,
+            request.path,
+            request.query,
+            canonical_headers,
+            signed_headers,
+            request.payload_hash
+        );
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            timestamp,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let signature = hex::encode(hmac_sha256(
+            &self.signing_key(date),
+            string_to_sign.as_bytes(),
+        ));
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, self.access_key, scope, signed_headers, signature
+        )
+    }
+
+    /// 日付・リージョン・サービスの署名の鍵
+    fn signing_key(&self, date: &str) -> [u8; 32] {
+        let key = hmac_sha256(
+            format!("AWS4{}", self.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, self.service.as_bytes());
+        hmac_sha256(&key, b"aws4_request")
+    }
+}
+
+/// ヘッダーの値の前後の空白を除き、続いた空白を1つにする
+fn canonical_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// SHA256（16進数）
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// HMAC-SHA256（RFC 2104）
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(data)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// パスをURIエンコードする（英数字と `-._~/` 以外を `%XX` にする）
+pub fn uri_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// 現在時刻の署名のタイムスタンプ（UTC、`20150830T123600Z` の形式）
+pub fn timestamp_now() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    format_timestamp(seconds)
+}
+
+/// UNIX時刻（秒）を署名のタイムスタンプにする
+fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    // 1970-01-01からの日数をグレゴリオ暦の年月日にする（400年周期で数える）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231のテストケース2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_authorization() {
+        // AWSのドキュメントの署名の例（IAMのListUsers）
+        let signer = Signer {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            region: "us-east-1".to_string(),
+            service: "iam".to_string(),
+        };
+        assert_eq!(
+            hex::encode(signer.signing_key("20150830")),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+        let headers = [
+            (
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+            ),
+            ("Host".to_string(), "iam.amazonaws.com".to_string()),
+            ("X-Amz-Date".to_string(), "20150830T123600Z".to_string()),
+        ];
+        let request = CanonicalRequest {
+            method: "GET",
+            path: "/",
+            query: "Action=ListUsers&Version=2010-05-08",
+            headers: &headers,
+            payload_hash: &sha256_hex(b""),
+        };
+        assert_eq!(
+            signer.authorization(&request, "20150830T123600Z"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "19700101T000000Z");
+        assert_eq!(format_timestamp(1_440_938_160), "20150830T123600Z");
+        assert_eq!(format_timestamp(951_782_400), "20000229T000000Z");
+        assert_eq!(timestamp_now().len(), 16);
+    }
+
+    #[test]
+    fn test_uri_encode_path() {
+        assert_eq!(
+            uri_encode_path("/bucket/pamphlets/a b/tiles/ab.webp"),
+            "/bucket/pamphlets/a%20b/tiles/ab.webp"
+        );
+        assert_eq!(
+            uri_encode_path("/カタログ"),
+            "/%E3%82%AB%E3%82%BF%E3%83%AD%E3%82%B0"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! HTTPSの接続（`tls` フィーチャー。システムのOpenSSL 3の `libssl` を使う）
+//!
+//! サーバーの証明書はシステムの既定の証明書で検証し、ホスト名（IPアドレスの場合はアドレス）が
+//! 証明書と一致することを確かめる。ソケットは呼び出し側で設定したタイムアウトのまま使う
+
+use std::ffi::{c_char, c_int, c_long, c_ulong, c_void, CStr, CString};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::os::fd::AsRawFd;
+use std::ptr;
+
+#[repr(C)]
+struct SslMethod {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct SslCtx {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct Ssl {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct X509VerifyParam {
+    _private: [u8; 0],
+}
+
+const SSL_VERIFY_PEER: c_int = 0x01;
+const SSL_OP_IGNORE_UNEXPECTED_EOF: u64 = 1 << 7;
+const SSL_CTRL_SET_TLSEXT_HOSTNAME: c_int = 55;
+const TLSEXT_NAMETYPE_HOST_NAME: c_long = 0;
+const SSL_ERROR_WANT_READ: c_int = 2;
+const SSL_ERROR_WANT_WRITE: c_int = 3;
+const SSL_ERROR_SYSCALL: c_int = 5;
+const SSL_ERROR_ZERO_RETURN: c_int = 6;
+const X509_V_OK: c_long = 0;
+
+#[link(name = "ssl")]
+#[link(name = "crypto")]
+extern "C" {
+    fn TLS_client_method() -> *const SslMethod;
+    fn SSL_CTX_new(method: *const SslMethod) -> *mut SslCtx;
+    fn SSL_CTX_free(ctx: *mut SslCtx);
+    fn SSL_CTX_set_default_verify_paths(ctx: *mut SslCtx) -> c_int;
+    fn SSL_CTX_set_verify(ctx: *mut SslCtx, mode: c_int, callback: *const c_void);
+    fn SSL_CTX_set_options(ctx: *mut SslCtx, options: u64) -> u64;
+    fn SSL_new(ctx: *mut SslCtx) -> *mut Ssl;
+    fn SSL_free(ssl: *mut Ssl);
+    fn SSL_set_fd(ssl: *mut Ssl, fd: c_int) -> c_int;
+    fn SSL_ctrl(ssl: *mut Ssl, cmd: c_int, larg: c_long, parg: *mut c_void) -> c_long;
+    fn SSL_set1_host(ssl: *mut Ssl, hostname: *const c_char) -> c_int;
+    fn SSL_get0_param(ssl: *mut Ssl) -> *mut X509VerifyParam;
+    fn X509_VERIFY_PARAM_set1_ip_asc(param: *mut X509VerifyParam, ip: *const c_char) -> c_int;
+    fn SSL_connect(ssl: *mut Ssl) -> c_int;
+    fn SSL_read(ssl: *mut Ssl, buf: *mut c_void, num: c_int) -> c_int;
+    fn SSL_write(ssl: *mut Ssl, buf: *const c_void, num: c_int) -> c_int;
+    fn SSL_shutdown(ssl: *mut Ssl) -> c_int;
+    fn SSL_get_error(ssl: *const Ssl, ret: c_int) -> c_int;
+    fn SSL_get_verify_result(ssl: *const Ssl) -> c_long;
+    fn X509_verify_cert_error_string(code: c_long) -> *const c_char;
+    fn ERR_get_error() -> c_ulong;
+    fn ERR_error_string_n(code: c_ulong, buf: *mut c_char, len: usize);
+    fn ERR_clear_error();
+}
+
+/// 接続に共通の設定（`SSL_CTX`）
+#[derive(Debug)]
+pub struct TlsConnector {
+    ctx: *mut SslCtx,
+}
+
+// `SSL_CTX` は設定し終えた後は複数のスレッドから `SSL_new` できる
+unsafe impl Send for TlsConnector {}
+unsafe impl Sync for TlsConnector {}
+
+impl TlsConnector {
+    /// システムの既定の証明書でサーバーを検証する設定
+    ///
+    /// # Errors
+    /// OpenSSLの初期化に失敗した場合
+    pub fn new() -> io::Result<Self> {
+        unsafe {
+            ERR_clear_error();
+            let ctx = SSL_CTX_new(TLS_client_method());
+            if ctx.is_null() {
+                return Err(ssl_error());
+            }
+            let connector = Self { ctx };
+            if SSL_CTX_set_default_verify_paths(ctx) != 1 {
+                return Err(ssl_error());
+            }
+            SSL_CTX_set_verify(ctx, SSL_VERIFY_PEER, ptr::null());
+            // close_notifyを送らずに閉じるサーバーでも、長さのない本文を最後まで読めるようにする
+            SSL_CTX_set_options(ctx, SSL_OP_IGNORE_UNEXPECTED_EOF);
+            Ok(connector)
+        }
+    }
+
+    /// 接続済みの `stream` でハンドシェイクする（`host` は角括弧のないホスト名かIPアドレス）
+    ///
+    /// # Errors
+    /// ハンドシェイクや証明書の検証に失敗した場合
+    pub fn connect(&self, host: &str, stream: TcpStream) -> io::Result<TlsStream> {
+        let name = CString::new(host)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid host name"))?;
+        unsafe {
+            ERR_clear_error();
+            let ssl = SSL_new(self.ctx);
+            if ssl.is_null() {
+                return Err(ssl_error());
+            }
+            let tls = TlsStream {
+                ssl,
+                stream,
+                connected: false,
+            };
+            let configured = SSL_set_fd(ssl, tls.stream.as_raw_fd()) == 1
+                && if host.parse::<IpAddr>().is_ok() {
+                    X509_VERIFY_PARAM_set1_ip_asc(SSL_get0_param(ssl), name.as_ptr()) == 1
+                } else {
+                    // SNIで送るホスト名と、証明書で確かめるホスト名
+                    SSL_ctrl(
+                        ssl,
+                        SSL_CTRL_SET_TLSEXT_HOSTNAME,
+                        TLSEXT_NAMETYPE_HOST_NAME,
+                        name.as_ptr() as *mut c_void,
+                    ) == 1
+                        && SSL_set1_host(ssl, name.as_ptr()) == 1
+                };
+            if !configured {
+                return Err(ssl_error());
+            }
+            tls.handshake()
+        }
+    }
+}
+
+impl Drop for TlsConnector {
+    fn drop(&mut self) {
+        // 接続は `SSL_CTX` の参照を持つため、接続より先に解放してよい
+        unsafe { SSL_CTX_free(self.ctx) }
+    }
+}
+
+/// TLSの接続
+#[derive(Debug)]
+pub struct TlsStream {
+    ssl: *mut Ssl,
+    stream: TcpStream,
+    /// ハンドシェイクを終えたか（終えた接続だけ閉じるときにclose_notifyを送る）
+    connected: bool,
+}
+
+// `SSL` は同時に1つのスレッドからしか使わない（`&mut self` でのみ読み書きする）
+unsafe impl Send for TlsStream {}
+
+impl TlsStream {
+    fn handshake(mut self) -> io::Result<Self> {
+        unsafe {
+            ERR_clear_error();
+            let ret = SSL_connect(self.ssl);
+            if ret != 1 {
+                let verify = SSL_get_verify_result(self.ssl);
+                if verify != X509_V_OK {
+                    let reason = CStr::from_ptr(X509_verify_cert_error_string(verify));
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Certificate verification failed: {}",
+                            reason.to_string_lossy()
+                        ),
+                    ));
+                }
+                return Err(self.error(ret));
+            }
+        }
+        self.connected = true;
+        Ok(self)
+    }
+
+    /// `SSL_read`・`SSL_write` などが `ret` を返したときのエラー
+    fn error(&self, ret: c_int) -> io::Error {
+        match unsafe { SSL_get_error(self.ssl, ret) } {
+            SSL_ERROR_WANT_READ | SSL_ERROR_WANT_WRITE => io::ErrorKind::TimedOut.into(),
+            SSL_ERROR_SYSCALL => {
+                if let Some(message) = error_queue() {
+                    return io::Error::other(message);
+                }
+                // エラーのキューが空ならソケットのエラー（errnoが0なら途中で閉じられた）
+                let os = io::Error::last_os_error();
+                match os.raw_os_error() {
+                    Some(0) | None => io::ErrorKind::UnexpectedEof.into(),
+                    Some(_) => os,
+                }
+            }
+            _ => ssl_error(),
+        }
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = buf.len().min(c_int::MAX as usize) as c_int;
+        unsafe {
+            ERR_clear_error();
+            let ret = SSL_read(self.ssl, buf.as_mut_ptr().cast(), len);
+            if ret > 0 {
+                return Ok(ret as usize);
+            }
+            if SSL_get_error(self.ssl, ret) == SSL_ERROR_ZERO_RETURN {
+                return Ok(0);
+            }
+            Err(self.error(ret))
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = buf.len().min(c_int::MAX as usize) as c_int;
+        unsafe {
+            ERR_clear_error();
+            let ret = SSL_write(self.ssl, buf.as_ptr().cast(), len);
+            if ret > 0 {
+                return Ok(ret as usize);
+            }
+            Err(self.error(ret))
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // `SSL_write` はレコードを書き終えてから戻る
+        Ok(())
+    }
+}
+
+impl Drop for TlsStream {
+    fn drop(&mut self) {
+        unsafe {
+            if self.connected {
+                SSL_shutdown(self.ssl);
+            }
+            SSL_free(self.ssl);
+            ERR_clear_error();
+        }
+    }
+}
+
+/// OpenSSLのエラーのキューを取り出したエラー
+fn ssl_error() -> io::Error {
+    io::Error::other(error_queue().unwrap_or_else(|| "TLS error".to_string()))
+}
+
+/// OpenSSLのエラーのキューを取り出す（空なら `None`）
+fn error_queue() -> Option<String> {
+    let mut messages = Vec::new();
+    loop {
+        let code = unsafe { ERR_get_error() };
+        if code == 0 {
+            break;
+        }
+        let mut buf = [0 as c_char; 256];
+        unsafe {
+            ERR_error_string_n(code, buf.as_mut_ptr(), buf.len());
+            messages.push(CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned());
+        }
+    }
+    (!messages.is_empty()).then(|| messages.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_handshake_failure() {
+        // TLSでないレスポンスを返して閉じるサーバー
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                // 先に閉じた接続には書けない
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n");
+            }
+        });
+
+        let connector = TlsConnector::new().unwrap();
+        let error = connector
+            .connect("bad\0host", TcpStream::connect(addr).unwrap())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(connector
+            .connect("127.0.0.1", TcpStream::connect(addr).unwrap())
+            .is_err());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,499 @@
+//! 出力したパンフレットのS3互換のストレージ（R2など）へのアップロード
+//!
+//! 送る順・Content-Type・Cache-Controlは `upload_plan` に従う。リクエストはプロセスの中で
+//! 署名バージョン4で署名して送る
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use tile_wasm::native::UploadObject;
+
+use crate::http::{Endpoint, HttpClient, Request, Response};
+use crate::sigv4::{sha256_hex, timestamp_now, uri_encode_path, CanonicalRequest, Signer};
+
+/// アップロード先のストレージ
+pub trait ObjectStore: Sync {
+    /// キーのオブジェクトが既にあるか
+    fn exists(&self, key: &str) -> Result<bool, String>;
+    /// ファイルの内容をキーに書き込む
+    fn put(&self, object: &UploadObject, file: &Path) -> Result<(), String>;
+}
+
+/// アップロードの結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadSummary {
+    pub uploaded: usize,
+    /// 既にあったため送らなかったオブジェクト
+    pub skipped: usize,
+}
+
+/// `objects` を `dir` のファイルから送る
+///
+/// 最後のオブジェクト（metadata.json）以外を `concurrency` 並列に送り、すべて送れてから
+/// 最後のオブジェクトを送る。1つでも失敗したら残りは送らない
+pub fn upload(
+    objects: &[UploadObject],
+    dir: &Path,
+    store: &impl ObjectStore,
+    concurrency: usize,
+) -> Result<UploadSummary, String> {
+    let Some((last, rest)) = objects.split_last() else {
+        return Ok(UploadSummary {
+            uploaded: 0,
+            skipped: 0,
+        });
+    };
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let uploaded = AtomicUsize::new(0);
+    let skipped = AtomicUsize::new(0);
+    let send = |object: &UploadObject| -> Result<(), String> {
+        if object.skip_if_exists && store.exists(&object.key)? {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        store.put(object, &dir.join(&object.path))?;
+        uploaded.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    };
+
+    let errors: Vec<String> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..concurrency.clamp(1, rest.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    while !failed.load(Ordering::Relaxed) {
+                        let Some(object) = rest.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        if let Err(e) = send(object) {
+                            failed.store(true, Ordering::Relaxed);
+                            return Some(format!("{}: {}", object.key, e));
+                        }
+                    }
+                    None
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().expect("upload worker panicked"))
+            .collect()
+    });
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    send(last).map_err(|e| format!("{}: {}", last.key, e))?;
+
+    Ok(UploadSummary {
+        uploaded: uploaded.into_inner(),
+        skipped: skipped.into_inner(),
+    })
+}
+
+/// S3互換のストレージ（パス形式のURL `{endpoint}/{bucket}/{key}`）
+#[derive(Debug)]
+pub struct S3Store {
+    client: HttpClient,
+    bucket: String,
+    signer: Signer,
+}
+
+impl S3Store {
+    /// `region` は署名のリージョン（R2は `auto`）
+    pub fn new(
+        endpoint: &str,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            client: HttpClient::new(Endpoint::parse(endpoint)?)?,
+            bucket,
+            signer: Signer {
+                access_key,
+                secret_key,
+                region,
+                service: "s3".to_string(),
+            },
+        })
+    }
+
+    /// 認証情報を環境変数 `AWS_ACCESS_KEY_ID`・`AWS_SECRET_ACCESS_KEY` から読む
+    pub fn from_env(endpoint: &str, bucket: String, region: String) -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).map_err(|_| format!("{} is not set", name));
+        Self::new(
+            endpoint,
+            bucket,
+            region,
+            var("AWS_ACCESS_KEY_ID")?,
+            var("AWS_SECRET_ACCESS_KEY")?,
+        )
+    }
+
+    /// 署名したリクエストを送る
+    fn send(
+        &self,
+        method: &str,
+        key: &str,
+        mut headers: Vec<(String, String)>,
+        body: Option<&[u8]>,
+    ) -> Result<Response, String> {
+        let endpoint = self.client.endpoint();
+        let path = uri_encode_path(&format!("{}/{}/{}", endpoint.base_path, self.bucket, key));
+        let payload_hash = sha256_hex(body.unwrap_or_default());
+        let timestamp = timestamp_now();
+        headers.push(("x-amz-content-sha256".to_string(), payload_hash.clone()));
+        headers.push(("x-amz-date".to_string(), timestamp.clone()));
+        let mut signed = vec![("host".to_string(), endpoint.host_header())];
+        signed.extend(headers.iter().cloned());
+        let authorization = self.signer.authorization(
+            &CanonicalRequest {
+                method,
+                path: &path,
+                query: "",
+                headers: &signed,
+                payload_hash: &payload_hash,
+            },
+            &timestamp,
+        );
+        headers.push(("Authorization".to_string(), authorization));
+        self.client.send(&Request {
+            method,
+            path: &path,
+            headers: &headers,
+            body,
+        })
+    }
+}
+
+impl ObjectStore for S3Store {
+    fn exists(&self, key: &str) -> Result<bool, String> {
+        match self.send("HEAD", key, Vec::new(), None)?.status {
+            200 => Ok(true),
+            404 => Ok(false),
+            status => Err(format!("HEAD returned {}", status)),
+        }
+    }
+
+    fn put(&self, object: &UploadObject, file: &Path) -> Result<(), String> {
+        let data =
+            std::fs::read(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let headers = vec![
+            ("content-type".to_string(), object.content_type.to_string()),
+            (
+                "cache-control".to_string(),
+                object.cache_control.to_string(),
+            ),
+        ];
+        let response = self.send("PUT", &object.key, headers, Some(&data))?;
+        match response.status {
+            200..=299 => Ok(()),
+            status => Err(format!(
+                "PUT returned {}: {}",
+                status,
+                String::from_utf8_lossy(&response.body).trim()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use tile_wasm::native::{pamphlet_prefix, upload_plan};
+
+    /// 書き込んだキーと内容を順に記録するストレージ
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: Mutex<Vec<(String, &'static str, Vec<u8>)>>,
+    }
+
+    impl ObjectStore for MemoryStore {
+        fn exists(&self, key: &str) -> Result<bool, String> {
+            let objects = self.objects.lock().unwrap();
+            Ok(objects.iter().any(|(stored, _, _)| stored == key))
+        }
+
+        fn put(&self, object: &UploadObject, file: &Path) -> Result<(), String> {
+            let data = std::fs::read(file).map_err(|e| e.to_string())?;
+            self.objects
+                .lock()
+                .unwrap()
+                .push((object.key.clone(), object.content_type, data));
+            Ok(())
+        }
+    }
+
+    /// 3枚のタイルとmetadata.jsonを書いたディレクトリとその送る順
+    fn publication(name: &str) -> (PathBuf, Vec<UploadObject>) {
+        let dir =
+            std::env::temp_dir().join(format!("pamphlet-tiler-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("tiles")).unwrap();
+        let hashes = ["aa", "bb", "cc"];
+        for hash in hashes {
+            std::fs::write(dir.join(format!("tiles/{}.webp", hash)), hash).unwrap();
+        }
+        std::fs::write(dir.join("metadata.json"), "{}").unwrap();
+        let metadata = serde_json::json!({
+            "pages": [{ "tiles": hashes.map(|hash| serde_json::json!({ "hash": hash })) }],
+        });
+        let plan = upload_plan(&pamphlet_prefix("demo"), &metadata).unwrap();
+        (dir, plan)
+    }
+
+    #[test]
+    fn test_upload() {
+        let (dir, plan) = publication("upload");
+
+        let store = MemoryStore::default();
+        let summary = upload(&plan, &dir, &store, 2).unwrap();
+        assert_eq!(
+            summary,
+            UploadSummary {
+                uploaded: 4,
+                skipped: 0
+            }
+        );
+        {
+            let objects = store.objects.lock().unwrap();
+            // metadata.jsonはタイルの後
+            assert_eq!(
+                objects
+                    .last()
+                    .map(|(key, content_type, _)| (key.as_str(), *content_type)),
+                Some(("pamphlets/demo/metadata.json", "application/json"))
+            );
+            assert!(objects
+                .iter()
+                .any(|(key, _, data)| key == "pamphlets/demo/tiles/bb.webp" && data == b"bb"));
+        }
+
+        // 既にあるタイルは送らず、metadata.jsonは送り直す
+        let summary = upload(&plan, &dir, &store, 8).unwrap();
+        assert_eq!(
+            summary,
+            UploadSummary {
+                uploaded: 1,
+                skipped: 3
+            }
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_upload_errors() {
+        let object = UploadObject {
+            key: "pamphlets/demo/tiles/missing.webp".to_string(),
+            path: "tiles/missing.webp".to_string(),
+            content_type: "image/webp",
+            cache_control: "no-cache",
+            skip_if_exists: true,
+        };
+        let metadata = UploadObject {
+            key: "pamphlets/demo/metadata.json".to_string(),
+            path: "metadata.json".to_string(),
+            ..object.clone()
+        };
+        let store = MemoryStore::default();
+        let error = upload(&[object, metadata], Path::new("missing"), &store, 4).unwrap_err();
+        assert!(
+            error.starts_with("pamphlets/demo/tiles/missing.webp: "),
+            "{}",
+            error
+        );
+        // タイルを送れなかった場合はmetadata.jsonを送らない
+        assert!(store.objects.lock().unwrap().is_empty());
+    }
+
+    const ACCESS_KEY: &str = "AKIDEXAMPLE";
+    const SECRET_KEY: &str = "secret";
+
+    /// 署名を検証し、オブジェクトをメモリーに保存するS3のモック
+    #[derive(Default)]
+    struct MockS3 {
+        objects: Mutex<HashMap<String, (String, Vec<u8>)>>,
+        connections: AtomicUsize,
+        requests: AtomicUsize,
+    }
+
+    impl MockS3 {
+        /// `127.0.0.1` で待ち受け、エンドポイントのURLを返す
+        fn start(self: &Arc<Self>) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+            let mock = Arc::clone(self);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    mock.connections.fetch_add(1, Ordering::Relaxed);
+                    let mock = Arc::clone(&mock);
+                    thread::spawn(move || mock.serve(stream));
+                }
+            });
+            endpoint
+        }
+
+        /// 接続が閉じるまでリクエストに応える
+        fn serve(&self, stream: TcpStream) {
+            let mut reader = BufReader::new(stream);
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = parts.next().unwrap().to_string();
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(':').unwrap();
+                    headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+                }
+                let header = |name: &str| {
+                    headers
+                        .iter()
+                        .find(|(key, _)| key == name)
+                        .map_or("", |(_, value)| value.as_str())
+                };
+                let length = header("content-length").parse().unwrap_or(0);
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                self.requests.fetch_add(1, Ordering::Relaxed);
+
+                let status = if !self.verify(&method, &path, &headers, &body) {
+                    403
+                } else if method == "PUT" {
+                    let content_type = header("content-type").to_string();
+                    self.objects
+                        .lock()
+                        .unwrap()
+                        .insert(path, (content_type, body));
+                    200
+                } else if self.objects.lock().unwrap().contains_key(&path) {
+                    200
+                } else {
+                    404
+                };
+                let response = format!("HTTP/1.1 {} Mock\r\nContent-Length: 0\r\n\r\n", status);
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        }
+
+        /// 本文のハッシュと署名を確かめる
+        fn verify(
+            &self,
+            method: &str,
+            path: &str,
+            headers: &[(String, String)],
+            body: &[u8],
+        ) -> bool {
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map_or("", |(_, value)| value.as_str())
+            };
+            let payload_hash = header("x-amz-content-sha256");
+            if payload_hash != sha256_hex(body) {
+                return false;
+            }
+            let Some(signed_headers) = header("authorization")
+                .split(", ")
+                .find_map(|part| part.strip_prefix("SignedHeaders="))
+            else {
+                return false;
+            };
+            let signed: Vec<(String, String)> = signed_headers
+                .split(';')
+                .map(|name| (name.to_string(), header(name).to_string()))
+                .collect();
+            let signer = Signer {
+                access_key: ACCESS_KEY.to_string(),
+                secret_key: SECRET_KEY.to_string(),
+                region: "auto".to_string(),
+                service: "s3".to_string(),
+            };
+            let expected = signer.authorization(
+                &CanonicalRequest {
+                    method,
+                    path,
+                    query: "",
+                    headers: &signed,
+                    payload_hash,
+                },
+                header("x-amz-date"),
+            );
+            header("authorization") == expected
+        }
+    }
+
+    #[test]
+    fn test_s3_store() {
+        let (dir, plan) = publication("s3");
+        let mock = Arc::new(MockS3::default());
+        let endpoint = mock.start();
+        let store = |secret_key: &str| {
+            S3Store::new(
+                &endpoint,
+                "bucket".to_string(),
+                "auto".to_string(),
+                ACCESS_KEY.to_string(),
+                secret_key.to_string(),
+            )
+            .unwrap()
+        };
+
+        let s3 = store(SECRET_KEY);
+        assert_eq!(
+            upload(&plan, &dir, &s3, 2).unwrap(),
+            UploadSummary {
+                uploaded: 4,
+                skipped: 0
+            }
+        );
+        {
+            let objects = mock.objects.lock().unwrap();
+            assert_eq!(
+                objects.get("/bucket/pamphlets/demo/tiles/bb.webp"),
+                Some(&("image/webp".to_string(), b"bb".to_vec()))
+            );
+            assert_eq!(
+                objects
+                    .get("/bucket/pamphlets/demo/metadata.json")
+                    .map(|(content_type, _)| content_type.as_str()),
+                Some("application/json")
+            );
+        }
+        // HEADとPUTを1つのプロセスから送り、接続は並列の数までしか開かない
+        assert_eq!(mock.requests.load(Ordering::Relaxed), 7);
+        assert!(mock.connections.load(Ordering::Relaxed) <= 2);
+
+        // 既にあるタイルは送らない
+        assert_eq!(
+            upload(&plan, &dir, &s3, 2).unwrap(),
+            UploadSummary {
+                uploaded: 1,
+                skipped: 3
+            }
+        );
+
+        // 署名が合わなければ送れない
+        let error = upload(&plan, &dir, &store("wrong"), 1).unwrap_err();
+        assert!(error.contains("HEAD returned 403"), "{}", error);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,46 @@
+//! `tile_image_banded` と `tile_image_with` のタイル（座標とハッシュ）が一致することを確かめる
+//!
+//! 帯に分けたデコードは通常のデコード（zune-jpeg）と同じ画素を返すか、通常のデコードに
+//! 切り替える。どちらかだけが失敗する入力や、ハッシュが異なる入力を見つける。
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tile_wasm::native::{DecodeLimits, Settings, TileOptions, TileResult, TilerContext};
+
+const TILE_SIZE: u32 = 32;
+
+fn tiles(result: &TileResult) -> Vec<(u32, u32, &str)> {
+    let tiles = result.tiles.iter();
+    tiles
+        .map(|tile| (tile.x, tile.y, tile.hash.as_str()))
+        .collect()
+}
+
+fuzz_target!(|data: &[u8]| {
+    // 大きな画像はデコードに時間がかかるだけなので制限する
+    let settings = Settings {
+        decode_limits: Some(DecodeLimits {
+            max_dimension: 1024,
+            max_pixels: 1 << 18,
+        }),
+        ..Settings::default()
+    };
+    let mut context = TilerContext::with_settings(settings);
+    let banded = context.tile_image_banded(data, TILE_SIZE, None);
+    let expected = context.tile_image_with(data, &TileOptions::new(TILE_SIZE, None));
+    match (&banded, &expected) {
+        (Ok(banded), Ok(expected)) => {
+            assert_eq!(
+                (banded.width, banded.height),
+                (expected.width, expected.height)
+            );
+            assert_eq!(tiles(banded), tiles(expected));
+        }
+        (Err(_), Err(_)) => {}
+        _ => panic!(
+            "banded: {:?}, expected: {:?}",
+            banded.as_ref().err(),
+            expected.as_ref().err()
+        ),
+    }
+});
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+
+use crate::binary::{self, Reader};
+use crate::geometry::{Point, Rect};
+
+/// シリアライズ形式の識別子
+const MAGIC: &[u8; 4] = b"PANN";
+
+/// シリアライズ形式のバージョン
+const FORMAT_VERSION: u8 = 1;
+
+/// 手書きの座標の量子化単位（1ピクセルあたりの分割数）
+const POINT_PRECISION: f64 = 4.0;
+
+/// 注釈の内容（座標はページの原寸ピクセル）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AnnotationKind {
+    /// マーカー（複数行にまたがる場合は行ごとの矩形）
+    Highlight { rects: Vec<Rect>, color: String },
+    /// 付箋
+    Note { x: f64, y: f64, text: String },
+    /// 手書き（折れ線）
+    Drawing {
+        points: Vec<Point>,
+        color: String,
+        /// 線の太さ
+        width: f64,
+    },
+}
+
+impl AnnotationKind {
+    fn tag(&self) -> u8 {
+        match self {
+            AnnotationKind::Highlight { .. } => 0,
+            AnnotationKind::Note { .. } => 1,
+            AnnotationKind::Drawing { .. } => 2,
+        }
+    }
+}
+
+/// 注釈
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// 作成時に割り当てられ、編集しても変わらないID
+    pub id: String,
+    pub page: u32,
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+}
+
+/// 読者が作成した注釈の集合
+///
+/// 注釈は作成順（描画順）に保持する
+#[derive(Debug, Clone, Default)]
+pub struct AnnotationStore {
+    annotations: Vec<Annotation>,
+    /// 次に割り当てるID（削除済みのIDは再利用しない）
+    next_id: u64,
+}
+
+impl AnnotationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注釈を作成し、割り当てたIDを返す
+    pub fn create(&mut self, page: u32, kind: AnnotationKind) -> String {
+        let id = to_base36(self.next_id);
+        self.next_id += 1;
+        self.annotations.push(Annotation {
+            id: id.clone(),
+            page,
+            kind,
+        });
+        id
+    }
+
+    /// 注釈の内容を置き換える
+    pub fn update(&mut self, id: &str, kind: AnnotationKind) -> Result<(), String> {
+        let annotation = self
+            .annotations
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or_else(|| format!("Annotation not found: {}", id))?;
+        annotation.kind = kind;
+        Ok(())
+    }
+
+    /// 注釈を削除する（存在しない場合は `false`）
+    pub fn remove(&mut self, id: &str) -> bool {
+        let len = self.annotations.len();
+        self.annotations.retain(|a| a.id != id);
+        self.annotations.len() != len
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.id == id)
+    }
+
+    /// ページの注釈（描画順）
+    pub fn for_page(&self, page: u32) -> Vec<&Annotation> {
+        self.annotations.iter().filter(|a| a.page == page).collect()
+    }
+
+    pub fn all(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    /// 保存用のバイト列に変換する
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        binary::write_varint(&mut bytes, self.next_id);
+        binary::write_varint(&mut bytes, self.annotations.len() as u64);
+
+        for annotation in &self.annotations {
+            binary::write_str(&mut bytes, &annotation.id);
+            binary::write_varint(&mut bytes, annotation.page as u64);
+            bytes.push(annotation.kind.tag());
+
+            match &annotation.kind {
+                AnnotationKind::Highlight { rects, color } => {
+                    binary::write_str(&mut bytes, color);
+                    binary::write_varint(&mut bytes, rects.len() as u64);
+                    for rect in rects {
+                        for value in [rect.x, rect.y, rect.width, rect.height] {
+                            binary::write_f32(&mut bytes, value);
+                        }
+                    }
+                }
+                AnnotationKind::Note { x, y, text } => {
+                    binary::write_f32(&mut bytes, *x);
+                    binary::write_f32(&mut bytes, *y);
+                    binary::write_str(&mut bytes, text);
+                }
+                AnnotationKind::Drawing {
+                    points,
+                    color,
+                    width,
+                } => {
+                    binary::write_str(&mut bytes, color);
+                    binary::write_f32(&mut bytes, *width);
+                    binary::write_varint(&mut bytes, points.len() as u64);
+                    // 手書きは点数が多いため、量子化した座標を直前の点との差分で保存する
+                    let (mut last_x, mut last_y) = (0, 0);
+                    for point in points {
+                        let x = (point.x * POINT_PRECISION).round() as i64;
+                        let y = (point.y * POINT_PRECISION).round() as i64;
+                        binary::write_varint(&mut bytes, binary::zigzag(x - last_x));
+                        binary::write_varint(&mut bytes, binary::zigzag(y - last_y));
+                        (last_x, last_y) = (x, y);
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// `to_bytes` で生成したバイト列から復元する
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(bytes, "annotation data");
+        reader.header(MAGIC, FORMAT_VERSION)?;
+
+        let next_id = reader.varint()?;
+        let count = reader.varint()?;
+        let mut annotations = Vec::new();
+
+        for _ in 0..count {
+            let id = reader.str()?.to_string();
+            let page =
+                u32::try_from(reader.varint()?).map_err(|_| reader.error("page out of range"))?;
+
+            let kind = match reader.u8()? {
+                0 => {
+                    let color = reader.str()?.to_string();
+                    let count = reader.varint()?;
+                    let rects = (0..count)
+                        .map(|_| {
+                            Ok(Rect::new(
+                                reader.f32()?,
+                                reader.f32()?,
+                                reader.f32()?,
+                                reader.f32()?,
+                            ))
+                        })
+                        .collect::<Result<_, String>>()?;
+                    AnnotationKind::Highlight { rects, color }
+                }
+                1 => AnnotationKind::Note {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    text: reader.str()?.to_string(),
+                },
+                2 => {
+                    let color = reader.str()?.to_string();
+                    let width = reader.f32()?;
+                    let count = reader.varint()?;
+                    let (mut x, mut y) = (0, 0);
+                    let points = (0..count)
+                        .map(|_| {
+                            x += binary::unzigzag(reader.varint()?);
+                            y += binary::unzigzag(reader.varint()?);
+                            Ok(Point::new(
+                                x as f64 / POINT_PRECISION,
+                                y as f64 / POINT_PRECISION,
+                            ))
+                        })
+                        .collect::<Result<_, String>>()?;
+                    AnnotationKind::Drawing {
+                        points,
+                        color,
+                        width,
+                    }
+                }
+                tag => return Err(reader.error(&format!("unknown annotation type {}", tag))),
+            };
+
+            annotations.push(Annotation { id, page, kind });
+        }
+
+        Ok(Self {
+            annotations,
+            next_id,
+        })
+    }
+}
+
+fn to_base36(mut value: u64) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> AnnotationStore {
+        let mut store = AnnotationStore::new();
+        store.create(
+            0,
+            AnnotationKind::Highlight {
+                rects: vec![Rect::new(10.0, 20.0, 300.0, 24.0)],
+                color: "#ffeb3b".to_string(),
+            },
+        );
+        store.create(
+            2,
+            AnnotationKind::Note {
+                x: 120.0,
+                y: 80.0,
+                text: "ここを確認".to_string(),
+            },
+        );
+        store.create(
+            0,
+            AnnotationKind::Drawing {
+                points: vec![
+                    Point::new(100.0, 100.0),
+                    Point::new(100.25, 98.5),
+                    Point::new(90.0, 130.75),
+                ],
+                color: "red".to_string(),
+                width: 3.0,
+            },
+        );
+        store
+    }
+
+    #[test]
+    fn test_ids_are_stable() {
+        let mut store = store();
+        assert_eq!(
+            store
+                .for_page(0)
+                .iter()
+                .map(|a| a.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["0", "2"]
+        );
+
+        store
+            .update(
+                "1",
+                AnnotationKind::Note {
+                    x: 0.0,
+                    y: 0.0,
+                    text: "edited".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(store.get("1").unwrap().page, 2);
+        assert!(store
+            .update("z", store.get("1").unwrap().kind.clone())
+            .is_err());
+
+        // 削除したIDは再利用しない
+        assert!(store.remove("2"));
+        assert!(!store.remove("2"));
+        assert_eq!(store.create(0, store.get("0").unwrap().kind.clone()), "3");
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let store = store();
+        let bytes = store.to_bytes();
+        let mut restored = AnnotationStore::from_bytes(&bytes).unwrap();
+
+        assert_eq!(restored.all(), store.all());
+        assert_eq!(
+            restored.create(1, store.get("1").unwrap().kind.clone()),
+            "3"
+        );
+
+        assert!(AnnotationStore::from_bytes(b"PSIX\x01").is_err());
+        assert!(AnnotationStore::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let store = store();
+        let json = serde_json::to_value(store.get("1").unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": "1", "page": 2, "type": "note", "x": 120.0, "y": 80.0, "text": "ここを確認" })
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::{Cursor, Read};
+
+use serde::Deserialize;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::error::PamphletError;
+use crate::pages::{is_page_image, natural_cmp};
+use crate::tiler::{TileOptions, TileResult, TilerContext};
+use crate::{measure, PageInfo, TileMetadata};
+
+/// ZIPのタイル化のオプション
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    /// タイルサイズ（ピクセル）
+    pub tile_size: u32,
+    /// WebP品質（1-100）
+    pub quality: Option<f32>,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 512,
+            quality: None,
+        }
+    }
+}
+
+/// ZIPの1ページ分の結果
+#[derive(Debug)]
+pub struct ArchivePage {
+    /// ZIP内のファイル名
+    pub name: String,
+    pub result: TileResult,
+    /// 画像に記録された解像度
+    pub dpi: Option<f64>,
+}
+
+impl ArchivePage {
+    /// metadataのページ情報
+    pub fn page_info(&self, page: u32) -> PageInfo {
+        PageInfo {
+            page,
+            width: self.result.width,
+            height: self.result.height,
+            tiles: self
+                .result
+                .tiles
+                .iter()
+                .map(|tile| TileMetadata {
+                    x: tile.x,
+                    y: tile.y,
+                    hash: tile.hash.clone(),
+                })
+                .collect(),
+            dpi: self.dpi,
+            ..Default::default()
+        }
+    }
+}
+
+/// ページ画像をまとめたZIPをタイル化する
+///
+/// 画像の拡張子を持つファイルをファイル名の自然順（`2.jpg` が `10.jpg` より前）に並べ、
+/// 1ファイルを1ページとしてタイル化する。ディレクトリ、隠しファイル、macOSが作る
+/// `__MACOSX/` 以下のファイルは無視する。各ページは `context` の設定に従ってデコードする。
+///
+/// # Errors
+/// ZIPとして読めない場合、画像が1枚も無い場合、いずれかのページのタイル化に失敗した場合
+pub fn tile_archive(
+    zip_data: &[u8],
+    options: &ArchiveOptions,
+    context: &mut TilerContext,
+) -> Result<Vec<ArchivePage>, PamphletError> {
+    let zip_error = |e: ZipError| PamphletError::DecodeFailed(format!("Failed to read ZIP: {}", e));
+    let mut archive = ZipArchive::new(Cursor::new(zip_data)).map_err(zip_error)?;
+
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive.by_index(index).map_err(zip_error)?;
+        let name = file.name().map_err(zip_error)?.into_owned();
+        if !file.is_dir() && is_page_image(&name) {
+            entries.push((index, name));
+        }
+    }
+    if entries.is_empty() {
+        return Err(PamphletError::NotFound(
+            "ZIP contains no page images".to_string(),
+        ));
+    }
+    entries.sort_by(|(_, a), (_, b)| natural_cmp(a, b).then_with(|| a.cmp(b)));
+
+    // 展開したデータはページごとに捨てる
+    entries
+        .into_iter()
+        .map(|(index, name)| {
+            let mut file = archive.by_index(index).map_err(zip_error)?;
+            let mut data = Vec::with_capacity(file.size().min(1 << 28) as usize);
+            file.read_to_end(&mut data).map_err(|e| {
+                PamphletError::DecodeFailed(format!("Failed to read {}: {}", name, e))
+            })?;
+
+            let result = context
+                .tile_image_with(&data, &TileOptions::new(options.tile_size, options.quality))
+                .map_err(|e| e.with_context(&name))?;
+            Ok(ArchivePage {
+                dpi: measure::detect_dpi(&data),
+                name,
+                result,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::*;
+    use crate::test_util::png;
+
+    fn zip(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            let options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_tile_archive() {
+        let data = zip(&[
+            ("scans/10.png", png(30, 10, 0)),
+            ("scans/2.png", png(20, 10, 0)),
+            ("scans/", Vec::new()),
+            ("scans/readme.txt", b"not a page".to_vec()),
+            ("__MACOSX/scans/._2.png", b"resource fork".to_vec()),
+            ("scans/1.PNG", png(10, 10, 0)),
+        ]);
+
+        let pages =
+            tile_archive(&data, &ArchiveOptions::default(), &mut TilerContext::new()).unwrap();
+        let names: Vec<&str> = pages.iter().map(|page| page.name.as_str()).collect();
+        assert_eq!(names, vec!["scans/1.PNG", "scans/2.png", "scans/10.png"]);
+        assert_eq!(pages[2].result.width, 30);
+
+        let info = pages[1].page_info(1);
+        assert_eq!((info.page, info.width, info.height), (1, 20, 10));
+        assert_eq!(info.tiles.len(), 1);
+    }
+
+    #[test]
+    fn test_tile_archive_errors() {
+        assert!(tile_archive(
+            b"not a zip",
+            &ArchiveOptions::default(),
+            &mut TilerContext::new()
+        )
+        .is_err());
+
+        let empty = zip(&[("readme.txt", b"no pages".to_vec())]);
+        assert!(
+            tile_archive(&empty, &ArchiveOptions::default(), &mut TilerContext::new()).is_err()
+        );
+
+        // 壊れたページはファイル名付きのエラーになる
+        let broken = zip(&[("1.png", png(4, 4, 0)), ("2.png", b"broken".to_vec())]);
+        let error = tile_archive(
+            &broken,
+            &ArchiveOptions::default(),
+            &mut TilerContext::new(),
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), "decode_failed");
+        assert!(error.to_string().starts_with("2.png: "), "{}", error);
+    }
+}
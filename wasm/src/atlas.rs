@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+/// テクスチャ座標の範囲（0.0〜1.0）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+/// アトラス内のスロット
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasSlot {
+    /// アトラス（テクスチャ）のインデックス
+    pub atlas: usize,
+    /// スロットの左上（アトラスのピクセル、`texSubImage2D` の転送先）
+    pub x: u32,
+    pub y: u32,
+    pub uv: UvRect,
+}
+
+/// スロットの割り当て結果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasAllocation {
+    #[serde(flatten)]
+    pub slot: AtlasSlot,
+    /// 新しく割り当てたスロットか（タイルの画素を転送する必要がある）
+    pub upload: bool,
+    /// 空きがなく追い出したタイルのキー
+    pub evicted: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    slot: usize,
+    /// 最終使用時のティック（LRU順序のキー）
+    tick: u64,
+}
+
+/// タイルをテクスチャアトラスの固定サイズのスロットに割り当てる
+///
+/// 1枚のアトラスを `tile_size` 四方のスロットに分割し、タイルを1スロットずつ割り当てる。
+/// アトラスは必要になった時点で `max_atlases` 枚まで増やし、全て埋まった場合は
+/// 最も長く使われていないタイルを追い出す。タイルキャッシュから破棄したタイルは
+/// `release` でスロットを解放する。
+#[derive(Debug)]
+pub struct AtlasPacker {
+    atlas_size: u32,
+    tile_size: u32,
+    /// 1辺あたりのスロット数
+    slots_per_side: u32,
+    max_atlases: usize,
+    entries: HashMap<String, Entry>,
+    /// ティック → キー（古い順）
+    lru: BTreeMap<u64, String>,
+    /// 解放済みのスロット
+    free: Vec<usize>,
+    /// 一度も使っていない最初のスロット
+    next_slot: usize,
+    tick: u64,
+}
+
+impl AtlasPacker {
+    /// # Arguments
+    /// * `atlas_size` - アトラスの1辺のピクセル数（例: 4096）
+    /// * `tile_size` - タイルサイズ（スロットの1辺）
+    /// * `max_atlases` - アトラスの最大枚数
+    pub fn new(atlas_size: u32, tile_size: u32, max_atlases: usize) -> Self {
+        let tile_size = tile_size.clamp(1, atlas_size.max(1));
+
+        Self {
+            atlas_size: atlas_size.max(1),
+            tile_size,
+            slots_per_side: atlas_size.max(1) / tile_size,
+            max_atlases: max_atlases.max(1),
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            free: Vec::new(),
+            next_slot: 0,
+            tick: 0,
+        }
+    }
+
+    /// タイルにスロットを割り当てる（割り当て済みの場合は最近使用したものとして記録する）
+    ///
+    /// # Errors
+    /// 追い出すタイルの記録が壊れている場合（通常は起きない）
+    pub fn allocate(&mut self, key: &str) -> Result<AtlasAllocation, String> {
+        let tick = self.next_tick();
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.lru.remove(&entry.tick);
+            self.lru.insert(tick, key.to_string());
+            entry.tick = tick;
+            let slot = entry.slot;
+            return Ok(AtlasAllocation {
+                slot: self.slot(slot),
+                upload: false,
+                evicted: None,
+            });
+        }
+
+        let mut evicted = None;
+        let slot = if let Some(slot) = self.free.pop() {
+            slot
+        } else if self.next_slot < self.capacity() {
+            self.next_slot += 1;
+            self.next_slot - 1
+        } else {
+            let entry = self
+                .lru
+                .pop_first()
+                .and_then(|(_, oldest)| Some((self.entries.remove(&oldest)?, oldest)));
+            let Some((entry, oldest)) = entry else {
+                return Err("Internal error: atlas has no tile to evict".to_string());
+            };
+            evicted = Some(oldest);
+            entry.slot
+        };
+
+        self.lru.insert(tick, key.to_string());
+        self.entries.insert(key.to_string(), Entry { slot, tick });
+
+        Ok(AtlasAllocation {
+            slot: self.slot(slot),
+            upload: true,
+            evicted,
+        })
+    }
+
+    /// 割り当て済みのスロット（使用記録は更新しない）
+    pub fn get(&self, key: &str) -> Option<AtlasSlot> {
+        self.entries.get(key).map(|entry| self.slot(entry.slot))
+    }
+
+    /// スロットを解放する
+    pub fn release(&mut self, key: &str) -> bool {
+        let Some(entry) = self.entries.remove(key) else {
+            return false;
+        };
+        self.lru.remove(&entry.tick);
+        self.free.push(entry.slot);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.free.clear();
+        self.next_slot = 0;
+    }
+
+    /// 割り当て済みのタイル数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 使用中のアトラスの枚数（作成が必要なテクスチャ数）
+    pub fn atlas_count(&self) -> usize {
+        self.next_slot.div_ceil(self.slots_per_atlas())
+    }
+
+    fn slots_per_atlas(&self) -> usize {
+        (self.slots_per_side * self.slots_per_side) as usize
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots_per_atlas() * self.max_atlases
+    }
+
+    fn slot(&self, index: usize) -> AtlasSlot {
+        let local = (index % self.slots_per_atlas()) as u32;
+        let x = (local % self.slots_per_side) * self.tile_size;
+        let y = (local / self.slots_per_side) * self.tile_size;
+
+        // 隣のスロットの画素が線形補間で滲まないよう、半テクセル内側を指す
+        let size = self.atlas_size as f32;
+        let inset = 0.5;
+        AtlasSlot {
+            atlas: index / self.slots_per_atlas(),
+            x,
+            y,
+            uv: UvRect {
+                u0: (x as f32 + inset) / size,
+                v0: (y as f32 + inset) / size,
+                u1: ((x + self.tile_size) as f32 - inset) / size,
+                v1: ((y + self.tile_size) as f32 - inset) / size,
+            },
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocates_slots_across_atlases() {
+        // 1枚に2x2スロット、最大2枚
+        let mut packer = AtlasPacker::new(1024, 512, 2);
+
+        let first = packer.allocate("a").unwrap();
+        assert!(first.upload);
+        assert_eq!((first.slot.atlas, first.slot.x, first.slot.y), (0, 0, 0));
+        assert_eq!(first.slot.uv.u0, 0.5 / 1024.0);
+        assert_eq!(first.slot.uv.u1, 511.5 / 1024.0);
+
+        let second = packer.allocate("b").unwrap();
+        assert_eq!((second.slot.x, second.slot.y), (512, 0));
+
+        // 割り当て済みのタイルは転送不要
+        assert!(!packer.allocate("a").unwrap().upload);
+
+        for key in ["c", "d", "e"] {
+            packer.allocate(key).unwrap();
+        }
+        assert_eq!(packer.get("e").unwrap().atlas, 1);
+        assert_eq!(packer.atlas_count(), 2);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_and_reuses_released() {
+        let mut packer = AtlasPacker::new(512, 256, 1);
+        for key in ["a", "b", "c", "d"] {
+            packer.allocate(key).unwrap();
+        }
+        packer.allocate("a").unwrap();
+
+        let e = packer.allocate("e").unwrap();
+        assert_eq!(e.evicted.as_deref(), Some("b"));
+        assert_eq!((e.slot.x, e.slot.y), (256, 0));
+        assert!(packer.get("b").is_none());
+
+        // タイルキャッシュから破棄されたタイルのスロットを再利用する
+        let c = packer.get("c").unwrap();
+        assert!(packer.release("c"));
+        assert!(!packer.release("c"));
+        let f = packer.allocate("f").unwrap();
+        assert_eq!(f.evicted, None);
+        assert_eq!(f.slot, c);
+        assert_eq!(packer.len(), 4);
+    }
+
+    #[test]
+    fn test_broken_records_are_errors() {
+        let mut packer = AtlasPacker::new(512, 256, 1);
+        for key in ["a", "b", "c", "d"] {
+            packer.allocate(key).unwrap();
+        }
+        // 追い出すタイルの記録がなくてもパニックしない
+        packer.entries.remove("a");
+        assert!(packer.allocate("e").is_err());
+        assert!(packer.get("e").is_none());
+        assert!(!packer.allocate("b").unwrap().upload);
+    }
+}
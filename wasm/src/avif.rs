@@ -0,0 +1,318 @@
+use std::io::Cursor;
+use std::ptr::NonNull;
+
+use image::{DynamicImage, RgbaImage};
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::{Dav1dContext, Dav1dSettings};
+use rav1d::include::dav1d::headers::{
+    DAV1D_MC_BT2020_CL, DAV1D_MC_BT2020_NCL, DAV1D_MC_BT709, DAV1D_MC_IDENTITY,
+    DAV1D_PIXEL_LAYOUT_I400, DAV1D_PIXEL_LAYOUT_I420, DAV1D_PIXEL_LAYOUT_I422,
+};
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::src::lib::{
+    dav1d_close, dav1d_data_create, dav1d_data_unref, dav1d_default_settings, dav1d_get_picture,
+    dav1d_open, dav1d_picture_unref, dav1d_send_data,
+};
+
+/// AVIFをデコードする
+///
+/// 色はAV1のシーケンスヘッダーの行列係数と範囲に従ってsRGBに変換する（未指定はBT.601として扱う）。
+/// アルファ画像があれば透明度として使い、乗算済みの場合は元に戻す。
+///
+/// # Errors
+/// AVIFとして解釈できない場合や、AV1のデコードに失敗した場合
+pub fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+    let avif = avif_parse::read_avif(&mut Cursor::new(data))
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    let color = decode_av1(&avif.primary_item)?;
+    let alpha = match &avif.alpha_item {
+        Some(item) => Some(decode_av1(item)?),
+        None => None,
+    };
+
+    let mut image = RgbaImage::new(color.width as u32, color.height as u32);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (x, y) = (x as usize, y as usize);
+        let [r, g, b] = color.rgb(x, y);
+        let a = alpha.as_ref().map_or(255, |alpha| {
+            let (x, y) = (x.min(alpha.width - 1), y.min(alpha.height - 1));
+            to_u8(alpha.luma(x, y))
+        });
+        pixel.0 = [r, g, b, a];
+    }
+
+    if avif.premultiplied_alpha {
+        for pixel in image.pixels_mut() {
+            let alpha = pixel[3] as u32;
+            if alpha > 0 && alpha < 255 {
+                for channel in &mut pixel.0[..3] {
+                    *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                }
+            }
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+/// デコードしたAV1のフレーム（YCbCrの各プレーン）
+struct Frame {
+    width: usize,
+    height: usize,
+    bit_depth: u32,
+    /// 色差の間引き（水平・垂直のシフト量、モノクロは `None`）
+    chroma_shift: Option<(u32, u32)>,
+    chroma_width: usize,
+    /// 行列係数（`DAV1D_MC_*`）
+    matrix: u32,
+    full_range: bool,
+    /// Y, Cb, Cr
+    planes: [Vec<u16>; 3],
+}
+
+impl Frame {
+    /// 輝度（0.0〜1.0）
+    fn luma(&self, x: usize, y: usize) -> f32 {
+        let value = self.planes[0][y * self.width + x] as f32;
+        if self.full_range {
+            value / self.max()
+        } else {
+            (value - 16.0 * self.scale()) / (219.0 * self.scale())
+        }
+    }
+
+    /// 色差（-0.5〜0.5）
+    fn chroma(&self, plane: usize, x: usize, y: usize) -> f32 {
+        let Some((shift_x, shift_y)) = self.chroma_shift else {
+            return 0.0;
+        };
+        let value = self.planes[plane][(y >> shift_y) * self.chroma_width + (x >> shift_x)] as f32;
+        if self.full_range {
+            (value - (self.max() + 1.0) / 2.0) / self.max()
+        } else {
+            (value - 128.0 * self.scale()) / (224.0 * self.scale())
+        }
+    }
+
+    fn rgb(&self, x: usize, y: usize) -> [u8; 3] {
+        let luma = self.luma(x, y);
+        let cb = self.chroma(1, x, y);
+        let cr = self.chroma(2, x, y);
+
+        if self.matrix == DAV1D_MC_IDENTITY && self.chroma_shift.is_some() {
+            // GBRの順に格納されている
+            return [to_u8(cr + 0.5), to_u8(luma), to_u8(cb + 0.5)];
+        }
+
+        let (kr, kb) = match self.matrix {
+            DAV1D_MC_BT709 => (0.2126, 0.0722),
+            DAV1D_MC_BT2020_NCL | DAV1D_MC_BT2020_CL => (0.2627, 0.0593),
+            _ => (0.299, 0.114),
+        };
+        let r = luma + 2.0 * (1.0 - kr) * cr;
+        let b = luma + 2.0 * (1.0 - kb) * cb;
+        let g = (luma - kr * r - kb * b) / (1.0 - kr - kb);
+
+        [to_u8(r), to_u8(g), to_u8(b)]
+    }
+
+    fn max(&self) -> f32 {
+        ((1u32 << self.bit_depth) - 1) as f32
+    }
+
+    /// 8bitに対する倍率
+    fn scale(&self) -> f32 {
+        (1u32 << (self.bit_depth - 8)) as f32
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// AV1のデコーダー（破棄時に閉じる）
+struct Decoder(Option<Dav1dContext>);
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        // SAFETY: コンテキストは `dav1d_open` で開いたもので、閉じるのはここだけ
+        unsafe { dav1d_close(Some(NonNull::from(&mut self.0))) };
+    }
+}
+
+/// AV1のOBU列を1フレームだけデコードする
+fn decode_av1(obu: &[u8]) -> Result<Frame, String> {
+    let error =
+        |stage: &str, code: i32| format!("Failed to decode image: AV1 {} failed ({})", stage, code);
+
+    let mut settings = std::mem::MaybeUninit::<Dav1dSettings>::uninit();
+    // SAFETY: `dav1d_default_settings` は渡した領域を全て初期化する
+    let mut settings = unsafe {
+        dav1d_default_settings(NonNull::new_unchecked(settings.as_mut_ptr()));
+        settings.assume_init()
+    };
+    // 静止画なのでスレッドやフレームの遅延は不要
+    settings.n_threads = 1;
+    settings.max_frame_delay = 1;
+
+    let mut decoder = Decoder(None);
+    // SAFETY: 出力先と設定はどちらも有効な参照
+    let result = unsafe {
+        dav1d_open(
+            Some(NonNull::from(&mut decoder.0)),
+            Some(NonNull::from(&mut settings)),
+        )
+    };
+    if result.0 != 0 {
+        return Err(error("open", result.0));
+    }
+
+    let mut data = Dav1dData::default();
+    // SAFETY: `data` は書き込み可能で、確保された領域は `obu.len()` バイト
+    unsafe {
+        let buffer = dav1d_data_create(Some(NonNull::from(&mut data)), obu.len());
+        if buffer.is_null() {
+            return Err(error("allocation", -1));
+        }
+        std::ptr::copy_nonoverlapping(obu.as_ptr(), buffer, obu.len());
+    }
+
+    // SAFETY: `decoder` は開いたままで、`data` は `dav1d_data_create` で作ったもの
+    let sent = unsafe { dav1d_send_data(decoder.0, Some(NonNull::from(&mut data))) };
+    // SAFETY: 送り残したデータを解放する（送り切った場合は何もしない）
+    unsafe { dav1d_data_unref(Some(NonNull::from(&mut data))) };
+
+    let mut picture = Dav1dPicture::default();
+    // SAFETY: `decoder` は開いたままで、`picture` は書き込み可能
+    let result = unsafe { dav1d_get_picture(decoder.0, Some(NonNull::from(&mut picture))) };
+    if result.0 != 0 {
+        let code = if sent.0 != 0 { sent.0 } else { result.0 };
+        return Err(error("decode", code));
+    }
+
+    // SAFETY: `picture` は `dav1d_get_picture` が返したもので、読み終えてから解放する
+    let frame = unsafe { read_picture(&picture) };
+    unsafe { dav1d_picture_unref(Some(NonNull::from(&mut picture))) };
+    frame
+}
+
+/// デコードしたピクチャーから各プレーンを読み出す
+///
+/// # Safety
+/// `picture` は `dav1d_get_picture` が返し、まだ解放していないものであること
+unsafe fn read_picture(picture: &Dav1dPicture) -> Result<Frame, String> {
+    let width = picture.p.w as usize;
+    let height = picture.p.h as usize;
+    let bit_depth = picture.p.bpc as u32;
+
+    let chroma_shift = match picture.p.layout {
+        DAV1D_PIXEL_LAYOUT_I400 => None,
+        DAV1D_PIXEL_LAYOUT_I420 => Some((1, 1)),
+        DAV1D_PIXEL_LAYOUT_I422 => Some((1, 0)),
+        _ => Some((0, 0)),
+    };
+    let (chroma_width, chroma_height) = chroma_shift.map_or((0, 0), |(sx, sy)| {
+        ((width + sx as usize) >> sx, (height + sy as usize) >> sy)
+    });
+
+    let (matrix, full_range) = match picture.seq_hdr {
+        // SAFETY: シーケンスヘッダーはピクチャーと同じ期間有効
+        Some(header) => {
+            let header = unsafe { header.as_ref() };
+            (header.mtrx, header.color_range != 0)
+        }
+        None => (DAV1D_MC_BT709, false),
+    };
+
+    let read_plane = |index: usize, plane_width: usize, plane_height: usize| {
+        let stride = picture.stride[index.min(1)];
+        let Some(base) = picture.data[index] else {
+            return Err("Failed to decode image: AV1 picture has no data".to_string());
+        };
+
+        let mut samples = Vec::with_capacity(plane_width * plane_height);
+        for row in 0..plane_height {
+            // SAFETY: 各行は `stride` バイトおきに並び、`plane_width` 個のサンプルを持つ
+            unsafe {
+                let row = (base.as_ptr() as *const u8).offset(row as isize * stride);
+                if bit_depth > 8 {
+                    let row = std::slice::from_raw_parts(row as *const u16, plane_width);
+                    samples.extend_from_slice(row);
+                } else {
+                    let row = std::slice::from_raw_parts(row, plane_width);
+                    samples.extend(row.iter().map(|&v| v as u16));
+                }
+            }
+        }
+        Ok(samples)
+    };
+
+    let planes = if chroma_shift.is_some() {
+        [
+            read_plane(0, width, height)?,
+            read_plane(1, chroma_width, chroma_height)?,
+            read_plane(2, chroma_width, chroma_height)?,
+        ]
+    } else {
+        [read_plane(0, width, height)?, Vec::new(), Vec::new()]
+    };
+
+    Ok(Frame {
+        width,
+        height,
+        bit_depth,
+        chroma_shift,
+        chroma_width,
+        matrix,
+        full_range,
+        planes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(y: u16, cb: u16, cr: u16, matrix: u32, full_range: bool) -> Frame {
+        Frame {
+            width: 2,
+            height: 2,
+            bit_depth: 8,
+            chroma_shift: Some((1, 1)),
+            chroma_width: 1,
+            matrix,
+            full_range,
+            planes: [vec![y; 4], vec![cb], vec![cr]],
+        }
+    }
+
+    #[test]
+    fn test_ycbcr_to_rgb() {
+        // BT.709の限定範囲の白・黒・赤
+        assert_eq!(
+            frame(235, 128, 128, DAV1D_MC_BT709, false).rgb(1, 1),
+            [255, 255, 255]
+        );
+        assert_eq!(
+            frame(16, 128, 128, DAV1D_MC_BT709, false).rgb(0, 0),
+            [0, 0, 0]
+        );
+        let [r, g, b] = frame(63, 102, 240, DAV1D_MC_BT709, false).rgb(0, 0);
+        assert!(r >= 253 && g <= 2 && b <= 2);
+
+        // BT.601の全範囲の赤
+        let [r, g, b] = frame(76, 85, 255, 6, true).rgb(0, 0);
+        assert!(r >= 253 && g <= 2 && b <= 2);
+
+        // 10bitの全範囲の灰色
+        let mut gray = frame(512, 512, 512, DAV1D_MC_BT709, true);
+        gray.bit_depth = 10;
+        assert_eq!(gray.rgb(0, 0), [128, 128, 128]);
+    }
+
+    #[test]
+    fn test_invalid_avif() {
+        assert!(decode(b"not an avif").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! バイナリ形式の読み書き（可変長整数など）
+
+pub fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub fn write_f32(out: &mut Vec<u8>, value: f64) {
+    out.extend_from_slice(&(value as f32).to_le_bytes());
+}
+
+/// 長さ付きの文字列を書き込む
+pub fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// URLセーフなBase64（パディングなし）に変換する
+pub fn base64url_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    encode_base64(bytes, BASE64URL, false, &mut out);
+    out
+}
+
+/// 標準のBase64（パディングあり）に変換し、入力の `chunk_bytes` バイトごとに `emit` に渡す
+///
+/// 変換した文字列の全体を一度に持たず、1つの区切り分のバッファを使い回す。
+/// 区切りは3バイト単位に切り下げるため、各区切りをつなげると全体を変換した結果と同じになる
+///
+/// # Errors
+/// `emit` が失敗した場合（以降の区切りは変換しない）
+pub fn base64_encode_chunks<E>(
+    bytes: &[u8],
+    chunk_bytes: usize,
+    mut emit: impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    let chunk_bytes = (chunk_bytes / 3).max(1) * 3;
+    let mut out = String::with_capacity(chunk_bytes.min(bytes.len()).div_ceil(3) * 4);
+    for chunk in bytes.chunks(chunk_bytes) {
+        out.clear();
+        encode_base64(chunk, BASE64, true, &mut out);
+        emit(&out)?;
+    }
+    Ok(())
+}
+
+fn encode_base64(bytes: &[u8], alphabet: &[u8; 64], pad: bool, out: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(alphabet[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+        if pad {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// URLセーフなBase64（パディングなし）を復元する
+pub fn base64url_decode(text: &str) -> Result<Vec<u8>, String> {
+    let values = text
+        .bytes()
+        .map(|c| {
+            BASE64URL
+                .iter()
+                .position(|b| *b == c)
+                .map(|v| v as u32)
+                .ok_or_else(|| format!("Invalid base64url character: {:?}", c as char))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() % 4 == 1 {
+        return Err("Invalid base64url length".to_string());
+    }
+
+    let mut out = Vec::with_capacity(values.len() * 3 / 4);
+    for chunk in values.chunks(4) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, v)| n | v << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// バイト列の読み出し
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// エラーメッセージに使うデータの名前
+    name: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], name: &'static str) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            name,
+        }
+    }
+
+    /// 形式の識別子とバージョンを確認し、バージョンを返す
+    pub fn header(&mut self, magic: &[u8], max_version: u8) -> Result<u8, String> {
+        if self.take(magic.len())? != magic {
+            return Err(self.error("bad magic"));
+        }
+        let version = self.u8()?;
+        if version == 0 || version > max_version {
+            return Err(format!("Unsupported {} version: {}", self.name, version));
+        }
+        Ok(version)
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).ok_or_else(|| self.truncated())?;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| self.truncated())?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("varint overflow"))
+    }
+
+    pub fn f32(&mut self) -> Result<f64, String> {
+        let raw: [u8; 4] = self.take(4)?.try_into().map_err(|_| self.truncated())?;
+        Ok(f32::from_le_bytes(raw) as f64)
+    }
+
+    pub fn str(&mut self) -> Result<&'a str, String> {
+        let len = self.varint()? as usize;
+        std::str::from_utf8(self.take(len)?).map_err(|e| self.error(&e.to_string()))
+    }
+
+    pub fn error(&self, message: &str) -> String {
+        format!("Invalid {}: {}", self.name, message)
+    }
+
+    fn truncated(&self) -> String {
+        self.error("unexpected end of data")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, 300);
+        write_varint(&mut bytes, zigzag(-5));
+        write_f32(&mut bytes, 1.5);
+        write_str(&mut bytes, "新商品");
+
+        let mut reader = Reader::new(&bytes, "test data");
+        assert_eq!(reader.varint().unwrap(), 300);
+        assert_eq!(unzigzag(reader.varint().unwrap()), -5);
+        assert_eq!(reader.f32().unwrap(), 1.5);
+        assert_eq!(reader.str().unwrap(), "新商品");
+        assert_eq!(
+            reader.u8().unwrap_err(),
+            "Invalid test data: unexpected end of data"
+        );
+    }
+
+    #[test]
+    fn test_base64url() {
+        for (bytes, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (&[0xfb, 0xff, 0xbf], "-_-_"),
+        ] {
+            assert_eq!(base64url_encode(bytes), text);
+            assert_eq!(base64url_decode(text).unwrap(), bytes);
+        }
+        assert!(base64url_decode("Z").is_err());
+        assert!(base64url_decode("Zm9v+").is_err());
+    }
+
+    #[test]
+    fn test_base64_encode_chunks() {
+        let encode = |bytes: &[u8], chunk_bytes| {
+            let mut chunks = Vec::new();
+            base64_encode_chunks(bytes, chunk_bytes, |chunk| {
+                chunks.push(chunk.to_string());
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+            chunks
+        };
+        assert_eq!(encode(b"f", 3), ["Zg=="]);
+        assert_eq!(encode(b"fo", 3), ["Zm8="]);
+        assert_eq!(encode(&[0xfb, 0xff, 0xbf], 3), ["+/+/"]);
+        assert!(encode(b"", 3).is_empty());
+
+        // 区切りは3バイト単位に切り下げる
+        assert_eq!(encode(b"foobar!", 5), ["Zm9v", "YmFy", "IQ=="]);
+        assert_eq!(encode(b"foobar", 1), ["Zm9v", "YmFy"]);
+
+        let result = base64_encode_chunks(b"foobar", 3, |_| Err("stop"));
+        assert_eq!(result, Err("stop"));
+    }
+}
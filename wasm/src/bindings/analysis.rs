@@ -0,0 +1,140 @@
+//! 画像の解析（解像度・大きさ・統計・主な色）と長さの計測
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::error::PamphletError;
+use crate::*;
+
+/// 計測に必要なページ情報（metadataのページ情報をそのまま渡せる）
+#[derive(Debug, Deserialize)]
+struct MeasuredPage {
+    page: u32,
+    dpi: Option<f64>,
+}
+
+/// ページ情報の解像度を取り出す
+fn page_dpi(page: JsValue) -> Result<f64, JsValue> {
+    let page: MeasuredPage = serde_wasm_bindgen::from_value(page)?;
+    match page.dpi {
+        Some(dpi) if dpi > 0.0 => Ok(dpi),
+        _ => Err(PamphletError::InvalidArgument(format!("Page {} has no DPI", page.page)).into()),
+    }
+}
+
+/// ピクセル数をページの解像度に基づく実寸に変換（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `page` - metadataのページ情報（`dpi` が必要）
+/// * `px` - ピクセル数（ページの原寸）
+/// * `unit` - `"mm"` | `"cm"` | `"m"` | `"in"` | `"pt"`
+#[wasm_bindgen]
+pub fn pixels_to_physical(page: JsValue, px: f64, unit: JsValue) -> Result<f64, JsValue> {
+    let dpi = page_dpi(page)?;
+    let unit: measure::Unit = serde_wasm_bindgen::from_value(unit)?;
+    Ok(measure::pixels_to_physical(px, dpi, unit))
+}
+
+/// 折れ線（または多角形）の実寸の長さと面積を計測（JavaScriptから呼び出し可能）
+///
+/// 間取り図や地図のパンフレットで、2点間の距離や部屋の面積を測るために使う
+///
+/// # Arguments
+/// * `page` - metadataのページ情報（`dpi` が必要）
+/// * `points` - 頂点 `[{ x, y }]`（ページの原寸ピクセル）
+/// * `unit` - `"mm"` | `"cm"` | `"m"` | `"in"` | `"pt"`
+/// * `closed` - 多角形として閉じるか（デフォルト: false）
+///
+/// # Returns
+/// `{ length, area }`（`area` は単位の2乗、閉じていない場合は `null`）
+#[wasm_bindgen]
+pub fn measure_path(
+    page: JsValue,
+    points: JsValue,
+    unit: JsValue,
+    closed: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let dpi = page_dpi(page)?;
+    let points: Vec<geometry::Point> = serde_wasm_bindgen::from_value(points)?;
+    let unit: measure::Unit = serde_wasm_bindgen::from_value(unit)?;
+
+    let measurement = measure::measure_path(&points, dpi, unit, closed.unwrap_or(false));
+    Ok(serde_wasm_bindgen::to_value(&measurement)?)
+}
+
+/// 画像のヘッダーから解像度（DPI）を読み取り（JavaScriptから呼び出し可能）
+///
+/// PNGの `pHYs` とJPEGのJFIFヘッダーに対応。戻り値をmetadataのページ情報の `dpi` に設定する。
+///
+/// # Returns
+/// DPI（記録されていない場合は `undefined`）
+#[wasm_bindgen]
+pub fn detect_dpi(image_data: &[u8]) -> Option<f64> {
+    measure::detect_dpi(image_data)
+}
+
+/// 入力画像のヘッダーを調べる（JavaScriptから呼び出し可能）
+///
+/// 画素データをデコードせずに形式・サイズ・ページ数などを返す。時間のかかるタイル化の前に、
+/// アップロードの検証や処理量の見積もり、タイル化のオプションの選択に使う。
+///
+/// # Returns
+/// `{ format, width, height, pages, color_space, dpi?, exif_orientation? }`
+///
+/// # Example (JavaScript)
+/// ```js
+/// const info = probe_image(bytes);
+/// if (info.width * info.height > 200_000_000) {
+///   throw new Error('画像が大きすぎます');
+/// }
+/// const animation = info.pages > 1 ? 'pages' : 'first_frame';
+/// ```
+#[wasm_bindgen]
+pub fn probe_image(image_data: &[u8]) -> Result<JsValue, JsValue> {
+    let probe = probe::probe_image(image_data)?;
+    Ok(serde_wasm_bindgen::to_value(&probe)?)
+}
+
+/// ページ画像の統計を求める（JavaScriptから呼び出し可能）
+///
+/// 公開前の確認で、露出不足・露出過多のスキャンを自動で見つけるために使う
+///
+/// # Returns
+/// `{ width, height, luminance_histogram, red_histogram, green_histogram, blue_histogram,
+/// mean_brightness, contrast, low_percentile, high_percentile, clipped_shadows,
+/// clipped_highlights, exposure }`（`exposure` は `"under"`・`"normal"`・`"over"`）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const stats = page_stats(bytes);
+/// if (stats.exposure !== 'normal') {
+///   warnings.push(`${name}: ${stats.exposure}-exposed (mean ${stats.mean_brightness.toFixed(0)})`);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn page_stats(image_data: &[u8]) -> Result<JsValue, JsValue> {
+    let stats = stats::page_stats(image_data)?;
+    Ok(serde_wasm_bindgen::to_value(&stats)?)
+}
+
+/// 画像の代表色を求める（JavaScriptから呼び出し可能）
+///
+/// 表紙に合わせてビューアーの配色を変えるために使う（メディアンカット）
+///
+/// # Arguments
+/// * `image_data` - 画像のバイトデータ
+/// * `k` - 求める色の数（1-16）
+///
+/// # Returns
+/// 多い順の `{ r, g, b, hex, ratio }[]`（`ratio` は画像に占める割合）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const [main, accent] = dominant_colors(coverBytes, 5);
+/// document.documentElement.style.setProperty('--pamphlet-color', main.hex);
+/// ```
+#[wasm_bindgen]
+pub fn dominant_colors(image_data: &[u8], k: usize) -> Result<JsValue, JsValue> {
+    let colors = colors::dominant_colors(image_data, k)?;
+    Ok(serde_wasm_bindgen::to_value(&colors)?)
+}
@@ -0,0 +1,269 @@
+//! PDF・SVG・ZIPの入力と、PDFの出力
+
+#[cfg(feature = "zip")]
+use js_sys::Array;
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "zip")]
+use super::metadata::metadata_json;
+#[cfg(any(feature = "pdf", feature = "svg", feature = "zip"))]
+use super::tiling::tile_options;
+use crate::error::PamphletError;
+use crate::*;
+
+/// 選択したページをPDFとして出力（JavaScriptから呼び出し可能）
+///
+/// ページごとにタイルをつなぎ合わせてJPEGとして埋め込む。ページの物理サイズは
+/// ページ情報の `dpi`（ない場合は `options.default_dpi`）から求める。
+///
+/// # Arguments
+/// * `pages` - 出力するページ（metadataのページ情報の配列、この順に出力）
+/// * `tiles` - `Map<ハッシュ, Uint8Array>`（取得済みのタイル画像）
+/// * `options` - `{ quality?: number, default_dpi?: number, title?: string }`（省略可）
+///
+/// # Returns
+/// PDFのバイトデータ
+///
+/// # Example (JavaScript)
+/// ```js
+/// const pdf = export_pdf(selectedPages, tileBytesByHash, { title: '春のカタログ' });
+/// const url = URL.createObjectURL(new Blob([pdf], { type: 'application/pdf' }));
+/// ```
+#[wasm_bindgen]
+pub fn export_pdf(
+    #[wasm_bindgen(unchecked_param_type = "PageInfo[]")] pages: JsValue,
+    tiles: js_sys::Map,
+    options: JsValue,
+) -> Result<Uint8Array, JsValue> {
+    let pages: Vec<PageInfo> = serde_wasm_bindgen::from_value(pages)?;
+    let options: Option<pdf::PdfOptions> = serde_wasm_bindgen::from_value(options)?;
+
+    // 出力するページのタイルだけをコピーする
+    let mut fetched = std::collections::HashMap::new();
+    for tile in pages.iter().flat_map(|page| &page.tiles) {
+        let data = tiles.get(&JsValue::from_str(&tile.hash));
+        if let Some(data) = data.dyn_ref::<Uint8Array>() {
+            fetched.insert(tile.hash.clone(), data.to_vec());
+        }
+    }
+
+    let bytes = pdf::export_pdf(&pages, &fetched, &options.unwrap_or_default())
+        .map_err(PamphletError::EncodeFailed)?;
+    Ok(Uint8Array::from(&bytes[..]))
+}
+
+/// PDF文書（JavaScriptから呼び出し可能、`pdf` フィーチャーが必要）
+///
+/// ページを指定した解像度でラスタライズしてタイル化する
+///
+/// # Example (JavaScript)
+/// ```js
+/// let doc;
+/// try {
+///   doc = new PdfDocument(pdfBytes);
+/// } catch (e) {
+///   if (e.code !== 'password_required') throw e;
+///   // 入力されたパスワードで開き直す（正しくない場合は 'wrong_password'）
+///   doc = new PdfDocument(pdfBytes, await promptPassword());
+/// }
+/// for (let i = 0; i < doc.page_count; i++) {
+///   const result = doc.tile_page(i, 150, 512, 80);
+///   // result.dpi をmetadataのページの dpi に設定できる
+///   const words = doc.page_words(i, 150);
+///   index.add_page({ page: i, words });
+/// }
+/// ```
+#[cfg(feature = "pdf")]
+#[wasm_bindgen(js_name = PdfDocument)]
+pub struct JsPdfDocument {
+    inner: pdf::PdfDocument,
+}
+
+#[cfg(feature = "pdf")]
+#[wasm_bindgen(js_class = PdfDocument)]
+impl JsPdfDocument {
+    /// PDFを読み込む
+    ///
+    /// 開けない場合は `{ code }` を投げる（`"password_required"` / `"wrong_password"` /
+    /// `"invalid_pdf"`（`message` 付き））
+    ///
+    /// # Arguments
+    /// * `data` - PDFのバイト列
+    /// * `password` - パスワード（省略可）
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: Vec<u8>, password: Option<String>) -> Result<JsPdfDocument, JsValue> {
+        let inner = pdf::PdfDocument::open(data, password.as_deref()).map_err(|e| {
+            serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| e.to_string().into())
+        })?;
+        Ok(JsPdfDocument { inner })
+    }
+
+    /// ページ数
+    #[wasm_bindgen(getter)]
+    pub fn page_count(&self) -> usize {
+        self.inner.page_count()
+    }
+
+    /// しおり（アウトライン）を目次として返す
+    ///
+    /// 戻り値をJSONにして `generate_metadata` の `toc_json` に渡すと、metadataの `toc` になる
+    ///
+    /// # Returns
+    /// `[{ title, page, children? }]`（しおりが無い場合は空配列）
+    pub fn toc(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.outline())?)
+    }
+
+    /// ページのテキストを単語単位で取り出す
+    ///
+    /// 座標は同じ `dpi` で `tile_page` した画像のピクセル。戻り値をmetadataのページ情報の
+    /// `words` に設定したり、`SearchIndex.add_page` に渡したりできる（OCRは不要）。
+    ///
+    /// # Arguments
+    /// * `index` - ページ番号（0始まり）
+    /// * `dpi` - 解像度（省略時150、`tile_page` と同じ値を指定する）
+    ///
+    /// # Returns
+    /// `[{ text, x, y, width, height }]`
+    pub fn page_words(&self, index: usize, dpi: Option<f32>) -> Result<JsValue, JsValue> {
+        let text = self
+            .inner
+            .page_text(index, dpi.unwrap_or(pdf::DEFAULT_RASTER_DPI))?;
+        Ok(serde_wasm_bindgen::to_value(&text.words)?)
+    }
+
+    /// ページをラスタライズしてタイル化する
+    ///
+    /// # Arguments
+    /// * `index` - ページ番号（0始まり）
+    /// * `dpi` - 解像度（省略時150）
+    /// * `tile_size` - タイルサイズ（ピクセル）
+    /// * `quality` - WebP品質（1-100、省略時80）
+    pub fn tile_page(
+        &self,
+        index: usize,
+        dpi: Option<f32>,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        let mut context = tiler::TilerContext::new();
+        tile_pdf_page(&mut context, self, index, dpi, tile_size, quality)
+    }
+}
+
+/// `context` の設定でPDFのページをラスタライズしてタイル化する
+#[cfg(feature = "pdf")]
+pub(super) fn tile_pdf_page(
+    context: &mut tiler::TilerContext,
+    document: &JsPdfDocument,
+    index: usize,
+    dpi: Option<f32>,
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let dpi = dpi.unwrap_or(pdf::DEFAULT_RASTER_DPI);
+    let (result, dpi) = document
+        .inner
+        .tile_page(index, dpi, tile_size, quality, context)?;
+
+    Ok(JsTileResult {
+        dpi: Some(dpi),
+        ..result.into()
+    })
+}
+
+/// SVGをラスタライズしてタイル化する（JavaScriptから呼び出し可能、`svg` フィーチャーが必要）
+///
+/// # Arguments
+/// * `svg_data` - SVGのバイトデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+/// * `dpi` - 解像度（省略時96 = SVGの `px` と等倍）
+#[cfg(feature = "svg")]
+#[wasm_bindgen]
+pub fn tile_svg(
+    svg_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    dpi: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    svg_with(&mut context, svg_data, tile_size, quality, dpi)
+}
+
+/// `context` の設定でSVGをラスタライズしてタイル化する
+#[cfg(feature = "svg")]
+pub(super) fn svg_with(
+    context: &mut tiler::TilerContext,
+    svg_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    dpi: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let dpi = dpi.unwrap_or(svg::DEFAULT_SVG_DPI);
+    let result = svg::tile_svg(svg_data, dpi, tile_size, quality, context)?;
+
+    Ok(JsTileResult {
+        dpi: Some(dpi),
+        ..result.into()
+    })
+}
+
+/// ページ画像をまとめたZIPをタイル化する（JavaScriptから呼び出し可能、`zip` フィーチャーが必要）
+///
+/// 画像ファイルをファイル名の自然順（`2.jpg` が `10.jpg` より前）に並べて1ファイル1ページとし、
+/// 各ページのタイル化結果とmetadata.jsonをまとめて返す
+///
+/// # Arguments
+/// * `zip_data` - ZIPのバイトデータ
+/// * `options` - `{ tile_size?: number, quality?: number }`（省略時はタイルサイズ512）
+///
+/// # Returns
+/// `{ pages: JsTileResult[], names: string[], metadata: string }`
+///
+/// # Example (JavaScript)
+/// ```js
+/// const { pages, names, metadata } = tile_archive(zipBytes, { tile_size: 512 });
+/// pages.forEach((result, i) => console.log(names[i], result.tile_count()));
+/// ```
+#[cfg(feature = "zip")]
+#[wasm_bindgen]
+pub fn tile_archive(zip_data: &[u8], options: JsValue) -> Result<JsValue, JsValue> {
+    archive_with(&mut tiler::TilerContext::new(), zip_data, options)
+}
+
+/// `context` の設定でZIPのページをタイル化し、metadata.jsonを組み立てる
+#[cfg(feature = "zip")]
+pub(super) fn archive_with(
+    context: &mut tiler::TilerContext,
+    zip_data: &[u8],
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options: Option<archive::ArchiveOptions> = serde_wasm_bindgen::from_value(options)?;
+    let mut options = options.unwrap_or_default();
+    options.quality = tile_options(options.tile_size, options.quality)?;
+    let pages = archive::tile_archive(zip_data, &options, context)?;
+
+    let infos: Vec<PageInfo> = (0u32..)
+        .zip(&pages)
+        .map(|(index, page)| page.page_info(index))
+        .collect();
+    let settings = context.settings().resolve();
+    let metadata = metadata_json(&infos, options.tile_size, &[], &settings)?;
+
+    let names = Array::new();
+    let results = Array::new();
+    for page in pages {
+        names.push(&JsValue::from_str(&page.name));
+        results.push(&JsValue::from(JsTileResult::from(page.result)));
+    }
+
+    let output = js_sys::Object::new();
+    js_sys::Reflect::set(&output, &"pages".into(), &results)?;
+    js_sys::Reflect::set(&output, &"names".into(), &names)?;
+    js_sys::Reflect::set(&output, &"metadata".into(), &metadata.into())?;
+    Ok(output.into())
+}
@@ -0,0 +1,92 @@
+//! WASMのメモリの使用状況と、入力画像を直接書き込むバッファ
+
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use super::tiling::{read_tile_options, tile_with_options};
+use crate::error::PamphletError;
+use crate::*;
+
+/// WASMのメモリの使用状況を取得
+///
+/// 戻り値は `{ linear_memory_bytes, allocated_bytes, peak_allocated_bytes, pinned_tile_bytes, live_results, input_buffer_bytes }`。
+/// `pinned_tile_bytes` は解放されていない `JsTileResult` が保持しているタイルのデータのバイト数で、
+/// 次のページを処理するか、結果の解放を待つかの判断に使える
+#[wasm_bindgen]
+pub fn memory_stats() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&memory::memory_stats())?)
+}
+
+/// 入力用のバッファをWASMのメモリに確保する（JavaScriptから呼び出し可能）
+///
+/// `&[u8]` の引数は呼び出しのたびにWASMのメモリにコピーされるため、50-200MBの入力では
+/// 一時的にメモリが2倍必要になる。このバッファにJavaScriptからファイルの内容を直接書き込み、
+/// `tile_from_buffer` に渡すとコピーせずにタイル化できる。
+///
+/// # Returns
+/// バッファの先頭のアドレス（WASMのメモリの中の位置）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const ptr = alloc_input_buffer(file.size);
+/// let offset = 0;
+/// for await (const chunk of file.stream()) {
+///   // WASMの関数を呼ぶとメモリの拡張で `buffer` が切り離されることがあるので、毎回ビューを作る
+///   new Uint8Array(wasm.memory.buffer, ptr + offset, chunk.length).set(chunk);
+///   offset += chunk.length;
+/// }
+/// const result = tile_from_buffer(ptr, file.size, { tile_size: 512, quality: 80 });
+/// ```
+#[wasm_bindgen]
+pub fn alloc_input_buffer(len: usize) -> Result<usize, JsValue> {
+    Ok(memory::alloc_input_buffer(len)?)
+}
+
+/// 入力用のバッファを指すビューを取得（`wasm.memory` を参照できない場合の書き込みに使う）
+///
+/// `tile_data_view` と同じく、WASMの関数を呼び出すと無効になることがあるため、
+/// 取得したらすぐに同期的に書き込むこと
+#[wasm_bindgen]
+pub fn input_buffer_view(ptr: usize) -> Result<Uint8Array, JsValue> {
+    memory::with_input_buffer(ptr, |data| {
+        // SAFETY: ビューの寿命はJavaScript側の責任（上記の無効になる条件を参照）
+        unsafe { Uint8Array::view(data) }
+    })
+    .ok_or_else(|| PamphletError::NotFound(format!("No input buffer at {:#x}", ptr)).into())
+}
+
+/// `alloc_input_buffer` で確保したバッファの内容をタイル化する（JavaScriptから呼び出し可能）
+///
+/// 結果は `tile_image` と同じ。バッファはタイル化に失敗した場合も解放され、以降は使えない
+///
+/// # Arguments
+/// * `ptr` - `alloc_input_buffer` が返したアドレス
+/// * `len` - バッファの長さ（確保した長さと同じ）
+/// * `options` - `tile_image_with_options` と同じ設定（省略時はタイルサイズ512）
+#[wasm_bindgen]
+pub fn tile_from_buffer(ptr: usize, len: usize, options: JsValue) -> Result<JsTileResult, JsValue> {
+    let options = read_tile_options(options);
+    let image_data = memory::take_input_buffer(ptr, len)?;
+    tile_with_options(&mut tiler::TilerContext::new(), &image_data, options?)
+}
+
+/// `alloc_input_buffer` で確保したバッファを使わずに解放する
+///
+/// # Returns
+/// 解放した場合は `true`（確保していない・使用済みのアドレスは `false`）
+#[wasm_bindgen]
+pub fn free_input_buffer(ptr: usize) -> bool {
+    memory::free_input_buffer(ptr)
+}
+
+/// グローバルアロケータの使用状況を取得
+///
+/// 戻り値は `{ allocator, allocated_bytes, peak_allocated_bytes, live_allocations, total_allocations, reallocations, heap? }`。
+/// `allocator` はビルド時にフィーチャーで選んだアロケータ（`system`・`dlmalloc`・`talc`・`wee_alloc`）で、
+/// `heap`（`{ claimed_bytes, available_bytes, fragment_count }`）はtalcの場合のみ。
+/// タイル化の負荷でアロケータを比べるのに使う
+#[wasm_bindgen]
+pub fn allocator_stats() -> Result<JsValue, JsValue> {
+    let stats = memory::allocator_stats(ALLOCATOR, heap_stats());
+    Ok(serde_wasm_bindgen::to_value(&stats)?)
+}
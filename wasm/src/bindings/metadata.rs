@@ -0,0 +1,379 @@
+//! metadata.jsonの生成とタイルのハッシュ
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use super::pamphlet::read_tile_layout;
+use crate::error::PamphletError;
+use crate::*;
+
+/// metadataの型のTypeScript定義（`metadata.d.ts` を生成される `.d.ts` に出力する）
+///
+/// `PageInfo`・`TileMetadata`・`TocEntry` などを受け取る・返すAPIの引数と戻り値の型に使う。
+/// 構造体のフィールドを変えたら `metadata.d.ts` も揃えること（`test_metadata_typescript` で確かめる）
+#[wasm_bindgen(typescript_custom_section)]
+const METADATA_TYPESCRIPT: &str = include_str!("../metadata.d.ts");
+
+/// JSONの文字列、またはJavaScriptの値を読み込む
+///
+/// `generate_metadata` などは以前はJSONの文字列だけを受け取っていたため、両方を受け付ける
+fn json_or_value<T: serde::de::DeserializeOwned>(value: JsValue) -> Result<T, JsValue> {
+    let result = match value.as_string() {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => serde_wasm_bindgen::from_value(value).map_err(|e| e.to_string()),
+    };
+    result.map_err(|e| PamphletError::InvalidArgument(e).into())
+}
+
+/// ページ情報の配列を読み込む（厳密な読み込みが有効なら、知らない項目もエラーにする）
+fn read_pages(pages: JsValue, settings: &settings::Settings) -> Result<Vec<PageInfo>, JsValue> {
+    if !settings.is_strict() {
+        return json_or_value(pages);
+    }
+    metadata::strict_pages(json_or_value(pages)?)
+        .map_err(|e| PamphletError::InvalidArgument(e).into())
+}
+
+/// 目次（省略・`null` の場合は空）を読み込む（厳密な読み込みは `read_pages` と同じ）
+pub(super) fn read_toc(
+    toc: Option<JsValue>,
+    settings: &settings::Settings,
+) -> Result<Vec<TocEntry>, JsValue> {
+    match toc {
+        Some(toc) if !toc.is_null() && settings.is_strict() => {
+            metadata::strict_toc(json_or_value(toc)?)
+                .map_err(|e| PamphletError::InvalidArgument(e).into())
+        }
+        Some(toc) if !toc.is_null() => json_or_value(toc),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// metadata.jsonを生成する（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `pages` - ページ情報の配列（`PageInfo[]`）、またはそのJSON文字列
+/// * `tile_size` - タイルサイズ
+/// * `toc` - 目次の配列（`TocEntry[]`）、またはそのJSON文字列（省略可、空の場合は出力しない）
+///
+/// # Returns
+/// metadata.jsonの文字列
+///
+/// # Example (JavaScript)
+/// ```js
+/// const pages = [
+///   {
+///     page: 0,
+///     width: 2480,
+///     height: 3508,
+///     tiles: [
+///       { x: 0, y: 0, hash: "abc123..." },
+///       { x: 1, y: 0, hash: "def456..." },
+///     ]
+///   }
+/// ];
+///
+/// const metadata = generate_metadata(pages, 512);
+/// console.log(metadata);
+///
+/// // PDFのしおりを目次として含める
+/// const withToc = generate_metadata(pages, 512, doc.toc());
+/// ```
+#[wasm_bindgen]
+pub fn generate_metadata(
+    #[wasm_bindgen(unchecked_param_type = "PageInfo[] | string")] pages: JsValue,
+    tile_size: u32,
+    #[wasm_bindgen(unchecked_param_type = "TocEntry[] | string")] toc: Option<JsValue>,
+) -> Result<String, JsValue> {
+    metadata_string(pages, tile_size, toc, &settings::Settings::global())
+}
+
+/// metadataをJavaScriptのオブジェクトとして生成する（JavaScriptから呼び出し可能）
+///
+/// 引数は `generate_metadata` と同じ。metadataを加工してから保存する場合に、
+/// 文字列を `JSON.parse` し直さずに済む
+///
+/// # Example (JavaScript)
+/// ```js
+/// const metadata = generate_metadata_value(pages, 512);
+/// metadata.pages = metadata.pages.filter((page) => !hiddenPages.has(page.page));
+/// await put('metadata.json', JSON.stringify(metadata));
+/// ```
+#[wasm_bindgen(unchecked_return_type = "PamphletMetadata")]
+pub fn generate_metadata_value(
+    #[wasm_bindgen(unchecked_param_type = "PageInfo[] | string")] pages: JsValue,
+    tile_size: u32,
+    #[wasm_bindgen(unchecked_param_type = "TocEntry[] | string")] toc: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    let metadata = read_metadata(pages, tile_size, toc, &settings::Settings::global())?;
+    metadata_object(&metadata)
+}
+
+/// 設定に従ってページ情報・目次を読み込み、metadata.jsonの文字列を組み立てる
+pub(super) fn metadata_string(
+    pages: JsValue,
+    tile_size: u32,
+    toc: Option<JsValue>,
+    settings: &settings::Settings,
+) -> Result<String, JsValue> {
+    let metadata = read_metadata(pages, tile_size, toc, settings)?;
+    Ok(pretty_metadata(&metadata)?)
+}
+
+/// 設定に従ってページ情報・目次を読み込み、metadataを組み立てる
+pub(super) fn read_metadata(
+    pages: JsValue,
+    tile_size: u32,
+    toc: Option<JsValue>,
+    settings: &settings::Settings,
+) -> Result<serde_json::Value, JsValue> {
+    let pages = read_pages(pages, settings)?;
+    let toc = read_toc(toc, settings)?;
+    Ok(metadata::metadata_value(
+        settings.version(),
+        tile_size,
+        &pages,
+        &toc,
+        None,
+    ))
+}
+
+/// metadata.jsonの文字列を組み立てる
+pub(super) fn metadata_json(
+    pages: &[PageInfo],
+    tile_size: u32,
+    toc: &[TocEntry],
+    settings: &settings::Settings,
+) -> Result<String, PamphletError> {
+    let metadata = metadata::metadata_value(settings.version(), tile_size, pages, toc, None);
+    pretty_metadata(&metadata)
+}
+
+/// metadata.jsonの内容を整形した文字列にする
+pub(super) fn pretty_metadata(metadata: &serde_json::Value) -> Result<String, PamphletError> {
+    serde_json::to_string_pretty(metadata)
+        .map_err(|e| PamphletError::Internal(format!("Failed to serialize metadata: {}", e)))
+}
+
+/// metadata.jsonの内容をJavaScriptのオブジェクトにする（`JSON.parse` した場合と同じ形）
+pub(super) fn metadata_object(metadata: &serde_json::Value) -> Result<JsValue, JsValue> {
+    Ok(metadata.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// タイル化結果からmetadata.jsonを組み立てる（JavaScriptから利用可能）
+///
+/// ページ情報のJSONを組み立てて `generate_metadata` に渡す代わりに、タイル化結果を
+/// 読み順に追加していく。ページ番号は追加した順に0から振り、タイルサイズは
+/// 最初に追加した結果に揃える（異なるタイルサイズの結果はエラー）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const builder = new MetadataBuilder();
+/// for (const [i, file] of files.entries()) {
+///   const result = tile_image(new Uint8Array(await file.arrayBuffer()), 512, 80);
+///   builder.add_page(result, i === 0 ? '表紙' : String(i));
+///   await upload(result.into_tiles());
+/// }
+/// builder.set_reading_direction('rtl');
+/// builder.set_toc(doc.toc());
+/// const metadataJson = builder.build();
+/// ```
+#[wasm_bindgen(js_name = MetadataBuilder)]
+pub struct JsMetadataBuilder {
+    inner: metadata::MetadataBuilder,
+}
+
+#[wasm_bindgen(js_class = MetadataBuilder)]
+impl JsMetadataBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsMetadataBuilder {
+        JsMetadataBuilder {
+            inner: metadata::MetadataBuilder::new(),
+        }
+    }
+
+    /// タイル化結果をページとして追加し、振ったページ番号を返す
+    ///
+    /// `into_tiles()` などでタイルを取り出した後の結果も渡せる（ハッシュは残るため）
+    ///
+    /// # Arguments
+    /// * `result` - タイル化結果
+    /// * `label` - ページの表示名（ノンブルなど、省略可）
+    pub fn add_page(
+        &mut self,
+        result: &JsTileResult,
+        label: Option<String>,
+    ) -> Result<u32, JsValue> {
+        let page = result.to_page_info(0)?;
+        self.inner
+            .add_page(page, result.tile_size(), label)
+            .map_err(|e| PamphletError::InvalidArgument(e).into())
+    }
+
+    /// 読み方向（`"ltr"`（左綴じ）| `"rtl"`（右綴じ）、設定しない場合は出力しない）
+    pub fn set_reading_direction(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "'ltr' | 'rtl'")] reading_direction: JsValue,
+    ) -> Result<(), JsValue> {
+        let direction: spread::ReadingDirection =
+            serde_wasm_bindgen::from_value(reading_direction)?;
+        self.inner.set_reading_direction(direction);
+        Ok(())
+    }
+
+    /// タイルの出力の配置（`tile_path` の配置、`"flat"` の場合は出力しない）
+    pub fn set_tile_layout(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "TileLayout")] layout: String,
+    ) -> Result<(), JsValue> {
+        self.inner.set_tile_layout(read_tile_layout(Some(layout))?);
+        Ok(())
+    }
+
+    /// 目次（配列、またはそのJSON文字列）
+    pub fn set_toc(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "TocEntry[] | string")] toc: JsValue,
+    ) -> Result<(), JsValue> {
+        self.inner
+            .set_toc(read_toc(Some(toc), &settings::Settings::global())?);
+        Ok(())
+    }
+
+    /// 追加したページ数
+    #[wasm_bindgen(getter)]
+    pub fn page_count(&self) -> usize {
+        self.inner.page_count()
+    }
+
+    /// metadata.jsonの文字列（`generate_metadata` と同じ形式）
+    ///
+    /// # Errors
+    /// ページを追加していない場合、目次が存在しないページを指している場合
+    pub fn build(&self) -> Result<String, JsValue> {
+        let metadata = self
+            .inner
+            .build(settings::Settings::global().version())
+            .map_err(PamphletError::InvalidArgument)?;
+        Ok(pretty_metadata(&metadata)?)
+    }
+
+    /// metadataのオブジェクト（`build` の結果を `JSON.parse` した場合と同じ）
+    #[wasm_bindgen(unchecked_return_type = "PamphletMetadata")]
+    pub fn build_value(&self) -> Result<JsValue, JsValue> {
+        let metadata = self
+            .inner
+            .build(settings::Settings::global().version())
+            .map_err(PamphletError::InvalidArgument)?;
+        metadata_object(&metadata)
+    }
+}
+
+impl Default for JsMetadataBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SHA256ハッシュを計算（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `data` - ハッシュ化するバイトデータ
+///
+/// # Returns
+/// SHA256ハッシュの16進数文字列
+#[wasm_bindgen]
+pub fn calculate_hash(data: &[u8]) -> String {
+    hasher::calculate_hash(data)
+}
+
+/// 取得したタイルの内容がmetadataのハッシュと一致するか検証（JavaScriptから呼び出し可能）
+///
+/// CDNのキャッシュが古い・破損している場合に、誤ったタイルを表示しないために使う
+///
+/// # Arguments
+/// * `data` - 取得したタイルのバイトデータ
+/// * `tile` - metadataのタイル情報 `{ x, y, hash }`
+///
+/// # Errors
+/// 一致しない場合は `{ code: 'hash_mismatch', x, y, expected, actual, byte_length }` を投げる
+///
+/// # Example (JavaScript)
+/// ```js
+/// const bytes = new Uint8Array(await (await fetch(tileUrl)).arrayBuffer());
+/// try {
+///   verify_fetched_tile(bytes, tile);
+/// } catch (e) {
+///   if (e.code === 'hash_mismatch') return refetch(tileUrl, { cache: 'reload' });
+///   throw e;
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn verify_fetched_tile(data: &[u8], tile: JsValue) -> Result<(), JsValue> {
+    let tile: TileMetadata = serde_wasm_bindgen::from_value(tile)?;
+
+    integrity::verify_tile(data, &tile).map_err(|mismatch| {
+        serde_wasm_bindgen::to_value(&mismatch).unwrap_or_else(|_| mismatch.to_string().into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_metadata() {
+        let pages = vec![PageInfo {
+            page: 0,
+            width: 1000,
+            height: 1000,
+            tiles: vec![
+                TileMetadata {
+                    x: 0,
+                    y: 0,
+                    hash: "abc123".to_string(),
+                },
+                TileMetadata {
+                    x: 1,
+                    y: 0,
+                    hash: "def456".to_string(),
+                },
+            ],
+            ..Default::default()
+        }];
+
+        let metadata = metadata_json(&pages, 512, &[], &settings::Settings::global()).unwrap();
+
+        assert!(metadata.contains("version"));
+        assert!(metadata.contains("tile_size"));
+        assert!(metadata.contains("pages"));
+        assert!(!metadata.contains("toc"));
+
+        let toc_json = r#"[{"title":"表紙","page":0},{"title":"商品","page":1,"children":[{"title":"新商品","page":2}]}]"#;
+        let toc: Vec<TocEntry> = serde_json::from_str(toc_json).unwrap();
+        let metadata = metadata_json(&pages, 512, &toc, &settings::Settings::global()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(value["toc"][1]["children"][0]["title"], "新商品");
+        assert!(value["toc"][0].get("children").is_none());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_generate_metadata_value() {
+        let pages = JsValue::from_str(
+            r#"[{"page":0,"width":90,"height":70,"tiles":[{"x":0,"y":0,"hash":"abc"}]}]"#,
+        );
+        let toc = Some(JsValue::from_str(r#"[{"title":"表紙","page":0}]"#));
+        let value = generate_metadata_value(pages.clone(), 32, toc.clone()).unwrap();
+
+        // `JSON.parse` した場合と同じく、Mapでない普通のオブジェクトになる
+        assert!(value.is_object());
+        assert!(!value.is_instance_of::<js_sys::Map>());
+        let json: String = js_sys::JSON::stringify(&value).unwrap().into();
+        let string = generate_metadata(pages, 32, toc).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&string).unwrap()
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! JavaScriptから呼び出すAPI（`wasm` フィーチャー）
+//!
+//! wasm-bindgenのバインディングはここにまとめる。`wasm` を無効にすると、タイル化・ハッシュ・
+//! metadataなどのモジュールはJavaScriptに依存せずにネイティブのライブラリとして使える。
+//! 機能ごとのファイルに分け、JavaScriptに公開するものはすべてここから再公開する
+
+use wasm_bindgen::prelude::*;
+
+use crate::logging;
+
+mod analysis;
+mod documents;
+mod memory;
+mod metadata;
+mod pamphlet;
+mod render;
+mod result;
+mod search;
+mod settings;
+mod tiling;
+mod viewer;
+mod worker;
+
+pub use analysis::*;
+pub use documents::*;
+pub use memory::*;
+pub use metadata::*;
+pub use pamphlet::*;
+pub use render::*;
+pub use result::*;
+pub use search::*;
+pub use settings::*;
+pub use tiling::*;
+pub use viewer::*;
+pub use worker::*;
+
+/// WASMモジュール初期化時に呼ばれる
+/// パニックフックを設定してエラーログを改善し、ログをコンソールに出すロガーを設定する
+#[wasm_bindgen(start)]
+pub fn init() {
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+    logging::init();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::PamphletError;
+    use crate::*;
+
+    /// TypeScript定義の `interface` のフィールド名
+    fn typescript_fields(typescript: &str, name: &str) -> Vec<String> {
+        let start = typescript
+            .find(&format!("export interface {} {{", name))
+            .unwrap();
+        let body = &typescript[start..];
+        let body = &body[..body.find("\n}").unwrap()];
+        let mut fields: Vec<String> = body
+            .lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .filter(|(field, _)| !field.starts_with('/'))
+            .map(|(field, _)| field.trim_end_matches('?').to_string())
+            .collect();
+        fields.sort();
+        fields
+    }
+
+    /// シリアライズしたJSONのオブジェクトのキー
+    fn json_fields(value: &serde_json::Value) -> Vec<String> {
+        let mut fields: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_metadata_typescript() {
+        let page = PageInfo {
+            page: 0,
+            width: 1000,
+            height: 800,
+            tiles: vec![TileMetadata {
+                x: 0,
+                y: 0,
+                hash: "abc123".to_string(),
+            }],
+            words: vec![search::WordBox {
+                text: "春".to_string(),
+                x: 1.0,
+                y: 2.0,
+                width: 3.0,
+                height: 4.0,
+            }],
+            dpi: Some(300.0),
+            crop: Some(preprocess::CropRect {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+            }),
+            scale: Some(2.0),
+            ..Default::default()
+        };
+        let toc = vec![TocEntry {
+            title: "表紙".to_string(),
+            page: 0,
+            children: vec![TocEntry {
+                title: "商品".to_string(),
+                page: 0,
+                children: Vec::new(),
+            }],
+        }];
+        let mut builder = metadata::MetadataBuilder::new();
+        builder
+            .add_page(page, 512, Some("表紙".to_string()))
+            .unwrap();
+        builder.set_reading_direction(spread::ReadingDirection::Rtl);
+        builder.set_toc(toc);
+        builder.set_tile_layout(layout::TileLayout::Zxy);
+        let metadata = builder.build(1).unwrap();
+
+        let page = &metadata["pages"][0];
+        for (name, value) in [
+            ("PamphletMetadata", &metadata),
+            ("PageInfo", page),
+            ("TileMetadata", &page["tiles"][0]),
+            ("WordBox", &page["words"][0]),
+            ("CropRect", &page["crop"]),
+            ("TocEntry", &metadata["toc"][0]),
+        ] {
+            assert_eq!(
+                typescript_fields(include_str!("../metadata.d.ts"), name),
+                json_fields(value),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_worker_typescript() {
+        let typescript = include_str!("../worker.d.ts");
+        let tile = worker::ReadyTile {
+            x: 0,
+            y: 0,
+            hash: "abc123".to_string(),
+            data: vec![1, 2, 3],
+        };
+        let page = PageInfo {
+            page: 0,
+            width: 10,
+            height: 10,
+            tiles: Vec::new(),
+            ..Default::default()
+        };
+        let events = [
+            (
+                "Progress",
+                worker::WorkerEvent::Progress {
+                    id: 1,
+                    done: 0,
+                    total: 4,
+                },
+            ),
+            (
+                "TileReady",
+                worker::WorkerEvent::TileReady {
+                    id: 1,
+                    tile_size: 512,
+                    page,
+                    tiles: vec![tile.clone()],
+                    warnings: Vec::new(),
+                },
+            ),
+            (
+                "TileError",
+                worker::WorkerEvent::Error {
+                    id: None,
+                    error: PamphletError::DecodeFailed(
+                        "Failed to decode image: truncated".to_string(),
+                    ),
+                },
+            ),
+        ];
+        for (name, event) in events {
+            let value = serde_json::to_value(&event).unwrap();
+            assert_eq!(
+                typescript_fields(typescript, name),
+                json_fields(&value),
+                "{}",
+                name
+            );
+        }
+
+        let error = serde_json::to_value(PamphletError::InvalidArgument(
+            "Invalid DPI: -1".to_string(),
+        ))
+        .unwrap();
+        assert_eq!(
+            typescript_fields(typescript, "PamphletError"),
+            json_fields(&error)
+        );
+        let tile = serde_json::to_value(&tile).unwrap();
+        assert_eq!(
+            typescript_fields(typescript, "ReadyTile"),
+            json_fields(&tile)
+        );
+        let options = serde_json::to_value(tiler::TileOptions::default()).unwrap();
+        assert_eq!(
+            typescript_fields(typescript, "TileOptions"),
+            json_fields(&options)
+        );
+    }
+}
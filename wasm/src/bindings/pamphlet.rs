@@ -0,0 +1,283 @@
+//! パンフレット全体のタイル化と出力のパス
+
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use super::metadata::{metadata_object, pretty_metadata};
+use super::tiling::{read_tile_options, tile_options};
+use crate::error::PamphletError;
+use crate::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const PAMPHLET_IO_TYPESCRIPT: &str = r#"
+/** `tile_pamphlet` がファイルを読み書きするコールバック（Node.jsでは `fs` に渡す） */
+export interface PamphletIo {
+  /** 入力のファイルを読み込む */
+  read(path: string): Uint8Array | Promise<Uint8Array>;
+  /** タイル・metadata.jsonを書き出す（親ディレクトリが無ければ作ること） */
+  write(path: string, data: Uint8Array): void | Promise<void>;
+  /** 出力先に既にあるか（あれば書き出さない。省略した場合はすべて書き出す） */
+  exists?(path: string): boolean | Promise<boolean>;
+}
+
+/** `tile_pamphlet` の結果 */
+export interface PamphletOutput {
+  /** 書き出したmetadata.jsonの内容 */
+  metadata: PamphletMetadata;
+  pages: number;
+  tiles: number;
+  /** 新しく書き出したタイルの数 */
+  written: number;
+  /** タイル化は続けた問題（入力のパス付き） */
+  warnings: Array<TileWarning & { input: string }>;
+}
+"#;
+
+/// ページ画像・PDFを読み順にタイル化し、タイルとmetadata.jsonを書き出す（JavaScriptから呼び出し可能）
+///
+/// ファイルの読み書きは `io` のコールバックで行うため、バンドラーやブラウザに依存せずに
+/// Node.jsのバックエンドから使える。出力の配置は既定ではR2の `pamphlets/{id}/` と同じ
+/// （`{output}/tiles/{hash}.webp` と `{output}/metadata.json`）。`layout` を指定した場合は
+/// タイルのパスが `tile_path` に従い、metadataの `tile_layout` に配置を書く
+///
+/// # Example (JavaScript)
+/// ```js
+/// const fs = require('node:fs/promises');
+/// const path = require('node:path');
+/// const { tile_pamphlet, sort_page_files } = require('./pkg-node/tile_wasm.js');
+///
+/// const inputs = sort_page_files(await fs.readdir('pages')).map((name) => path.join('pages', name));
+/// const output = await tile_pamphlet(inputs, 'out', {
+///   read: (file) => fs.readFile(file),
+///   write: async (file, data) => {
+///     await fs.mkdir(path.dirname(file), { recursive: true });
+///     await fs.writeFile(file, data);
+///   },
+/// }, { tile_size: 512, quality: 80 });
+/// ```
+///
+/// # Arguments
+/// * `dpi` - PDFをラスタライズする解像度（省略時は150）
+/// * `layout` - タイルの出力の配置（省略時は `"flat"`）
+#[wasm_bindgen(unchecked_return_type = "Promise<PamphletOutput>")]
+pub fn tile_pamphlet(
+    inputs: Vec<String>,
+    output: String,
+    #[wasm_bindgen(unchecked_param_type = "PamphletIo")] io: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "TileOptions | undefined")] options: JsValue,
+    dpi: Option<f32>,
+    #[wasm_bindgen(unchecked_param_type = "TileLayout | undefined")] layout: Option<String>,
+) -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        let layout = read_tile_layout(layout)?;
+        let options = read_tile_options(options)?;
+        let options = tiler::TileOptions {
+            quality: tile_options(options.tile_size, options.quality)?,
+            ..options
+        };
+        let io = PamphletIo::new(&io)?;
+        if inputs.is_empty() {
+            return Err(PamphletError::NotFound("No page images or PDFs found".to_string()).into());
+        }
+
+        let output = output.trim_end_matches('/');
+        let mut context = tiler::TilerContext::new();
+        let mut assembler = pamphlet::PamphletAssembler::new();
+        assembler.set_tile_layout(layout);
+        let mut written_tiles = std::collections::HashSet::new();
+        let mut written = 0;
+        let mut warnings = Vec::new();
+        for input in &inputs {
+            let data = io.read(input).await?;
+            let mut pages = pamphlet::tile_input(
+                data,
+                &mut context,
+                &options,
+                dpi.unwrap_or(pdf::DEFAULT_RASTER_DPI),
+            )
+            .map_err(|e| e.with_context(input))?;
+
+            let first_page = assembler.page_count() as u32;
+            for (number, (page, _)) in (first_page..).zip(&mut pages.pages) {
+                for tile in &mut page.tiles {
+                    let data = std::mem::take(&mut tile.data);
+                    let path = format!(
+                        "{}/{}",
+                        output,
+                        layout.tile_path(number, tile.x, tile.y, &tile.hash)
+                    );
+                    if !written_tiles.insert(path.clone()) {
+                        continue;
+                    }
+                    // パスにハッシュを含まない配置では、既にあっても書き直す
+                    if !layout.is_content_addressed() || !io.exists(&path).await? {
+                        io.write(&path, &data).await?;
+                        written += 1;
+                    }
+                }
+                warnings.extend(page.warnings.iter().map(|warning| {
+                    serde_json::json!({
+                        "input": input,
+                        "code": warning.code,
+                        "message": warning.message,
+                    })
+                }));
+            }
+            assembler
+                .add(pages)
+                .map_err(|e| PamphletError::InvalidArgument(e).with_context(input))?;
+        }
+
+        let (pages, tiles) = (assembler.page_count(), assembler.tile_count());
+        let metadata = assembler.build().map_err(PamphletError::InvalidArgument)?;
+        let json = pretty_metadata(&metadata)?;
+        io.write(&format!("{}/metadata.json", output), json.as_bytes())
+            .await?;
+
+        let summary = serde_json::json!({
+            "metadata": metadata,
+            "pages": pages,
+            "tiles": tiles,
+            "written": written,
+            "warnings": warnings,
+        });
+        metadata_object(&summary)
+    })
+}
+
+/// ファイル名の一覧からページ画像・PDFを選び、自然順（`2.jpg` が `10.jpg` より前）に並べる（JavaScriptから呼び出し可能）
+///
+/// `fs.readdir` の結果を `tile_pamphlet` の入力の順に並べるために使う。隠しファイルと
+/// `__MACOSX` の中のファイルは除く
+#[wasm_bindgen]
+pub fn sort_page_files(names: Vec<String>) -> Vec<String> {
+    let mut names: Vec<String> = names
+        .into_iter()
+        .filter(|name| pages::is_page_file(name))
+        .collect();
+    names.sort_by(|a, b| pages::natural_cmp(a, b).then_with(|| a.cmp(b)));
+    names
+}
+
+/// タイルのパス（出力先・キーの接頭辞からの相対パス）を返す（JavaScriptから呼び出し可能）
+///
+/// metadataの `tile_layout`（無い場合は `"flat"`）とページ番号・タイルの位置・ハッシュから、
+/// 静的にホスティングしたパンフレットのタイルのURLを組み立てるために使う
+///
+/// # Example (JavaScript)
+/// ```js
+/// const metadata = await (await fetch(`${base}/metadata.json`)).json();
+/// const tile = metadata.pages[page].tiles[0];
+/// const url = `${base}/${tile_path(metadata.tile_layout, page, tile.x, tile.y, tile.hash)}`;
+/// ```
+///
+/// # Errors
+/// 知らない配置の場合
+#[wasm_bindgen]
+pub fn tile_path(
+    #[wasm_bindgen(unchecked_param_type = "TileLayout | undefined")] layout: Option<String>,
+    page: u32,
+    x: u32,
+    y: u32,
+    hash: &str,
+) -> Result<String, JsValue> {
+    Ok(read_tile_layout(layout)?.tile_path(page, x, y, hash))
+}
+
+/// タイルの出力の配置の名前を読む（省略時は `flat`）
+pub(super) fn read_tile_layout(layout: Option<String>) -> Result<layout::TileLayout, JsValue> {
+    layout.map_or(Ok(layout::TileLayout::Flat), |layout| {
+        layout
+            .parse()
+            .map_err(|e| PamphletError::InvalidArgument(e).into())
+    })
+}
+
+/// `PamphletIo` のコールバック
+struct PamphletIo {
+    read: js_sys::Function,
+    write: js_sys::Function,
+    exists: Option<js_sys::Function>,
+}
+
+impl PamphletIo {
+    fn new(io: &JsValue) -> Result<Self, JsValue> {
+        let callback = |name: &str| -> Result<Option<js_sys::Function>, JsValue> {
+            let value = js_sys::Reflect::get(io, &name.into())?;
+            if value.is_undefined() || value.is_null() {
+                return Ok(None);
+            }
+            value.dyn_into().map(Some).map_err(|_| {
+                PamphletError::InvalidArgument(format!("io.{} must be a function", name)).into()
+            })
+        };
+        let required = |name: &str| {
+            callback(name)?.ok_or_else(|| -> JsValue {
+                PamphletError::InvalidArgument(format!("io.{} is required", name)).into()
+            })
+        };
+        if !io.is_object() {
+            return Err(PamphletError::InvalidArgument(
+                "io must be an object with read and write".to_string(),
+            )
+            .into());
+        }
+        Ok(Self {
+            read: required("read")?,
+            write: required("write")?,
+            exists: callback("exists")?,
+        })
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, JsValue> {
+        let data = settle(self.read.call1(&JsValue::NULL, &path.into())?).await?;
+        if !data.is_instance_of::<Uint8Array>() {
+            return Err(PamphletError::InvalidArgument(format!(
+                "io.read did not return a Uint8Array for {}",
+                path
+            ))
+            .into());
+        }
+        Ok(Uint8Array::new(&data).to_vec())
+    }
+
+    async fn write(&self, path: &str, data: &[u8]) -> Result<(), JsValue> {
+        let data = Uint8Array::from(data);
+        settle(self.write.call2(&JsValue::NULL, &path.into(), &data)?).await?;
+        Ok(())
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, JsValue> {
+        match &self.exists {
+            Some(exists) => Ok(settle(exists.call1(&JsValue::NULL, &path.into())?)
+                .await?
+                .is_truthy()),
+            None => Ok(false),
+        }
+    }
+}
+
+/// コールバックの戻り値がPromiseなら待つ
+async fn settle(value: JsValue) -> Result<JsValue, JsValue> {
+    wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&value)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_page_files() {
+        let names = [
+            "10.png",
+            "notes.txt",
+            "2.JPG",
+            "book.pdf",
+            ".1.png",
+            "__MACOSX/3.png",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(sort_page_files(names), ["2.JPG", "10.png", "book.pdf"]);
+    }
+}
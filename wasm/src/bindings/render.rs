@@ -0,0 +1,374 @@
+//! ページの配置と描画の補助（見開き・めくり・ミニマップ・合成など）
+
+use js_sys::Uint8ClampedArray;
+use wasm_bindgen::prelude::*;
+
+use crate::error::PamphletError;
+use crate::*;
+
+/// 見開きのレイアウトを計算（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `page_a` - 読み順で先のページ（`{ page, width, height }`、metadataのページをそのまま渡せる）
+/// * `page_b` - 読み順で後のページ（表紙など単独表示の場合は `null`）
+/// * `reading_direction` - `"ltr"`（左綴じ）| `"rtl"`（右綴じ）
+/// * `gutter` - ページ間の余白
+///
+/// # Returns
+/// `{ width, height, placements: [{ page, x, y, width, height, scale }] }`
+/// （`placements` は画面上の左から順）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const layout = layout_spread(metadata.pages[1], metadata.pages[2], 'rtl', 16);
+/// const fit = Math.min(canvas.width / layout.width, canvas.height / layout.height);
+/// for (const p of layout.placements) {
+///   drawPage(p.page, p.x * fit, p.y * fit, p.scale * fit);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn layout_spread(
+    page_a: JsValue,
+    page_b: JsValue,
+    reading_direction: JsValue,
+    gutter: f64,
+) -> Result<JsValue, JsValue> {
+    let page_a: spread::SpreadPage = serde_wasm_bindgen::from_value(page_a)?;
+    let page_b: Option<spread::SpreadPage> = serde_wasm_bindgen::from_value(page_b)?;
+    let direction: spread::ReadingDirection = serde_wasm_bindgen::from_value(reading_direction)?;
+
+    let layout = spread::layout_spread(&page_a, page_b.as_ref(), direction, gutter);
+    Ok(serde_wasm_bindgen::to_value(&layout)?)
+}
+
+/// 見開きの組み合わせを読み順で取得（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `page_count` - 総ページ数
+/// * `cover_alone` - 表紙を単独で表示するか
+///
+/// # Returns
+/// `[先のページ, 後のページ | null]` の配列
+#[wasm_bindgen]
+pub fn spread_pairs(page_count: u32, cover_alone: bool) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&spread::spread_pairs(
+        page_count,
+        cover_alone,
+    ))?)
+}
+
+/// ページめくりのメッシュ（JavaScriptから利用可能）
+#[wasm_bindgen(js_name = CurlMesh)]
+pub struct JsCurlMesh {
+    inner: curl::CurlMesh,
+}
+
+#[wasm_bindgen(js_class = CurlMesh)]
+impl JsCurlMesh {
+    /// 頂点データ（x, y, z, u, v のインターリーブ、1頂点20バイト）
+    #[wasm_bindgen(getter)]
+    pub fn vertices(&self) -> js_sys::Float32Array {
+        js_sys::Float32Array::from(&self.inner.vertices[..])
+    }
+
+    /// 三角形のインデックス
+    #[wasm_bindgen(getter)]
+    pub fn indices(&self) -> js_sys::Uint32Array {
+        js_sys::Uint32Array::from(&self.inner.indices[..])
+    }
+
+    /// 1頂点あたりの要素数
+    #[wasm_bindgen(getter)]
+    pub fn stride(&self) -> usize {
+        curl::VERTEX_STRIDE
+    }
+}
+
+/// ページめくり（カール）のメッシュを生成（JavaScriptから呼び出し可能）
+///
+/// 座標はページの高さを1とし、綴じ側の辺が x=0（右綴じでは x≦0 側にページを置く）。
+/// `progress = 1.0` でページは綴じ側を軸に裏返った位置に来る。
+///
+/// # Arguments
+/// * `progress` - めくりの進捗（0.0〜1.0）
+/// * `aspect` - ページの縦横比（幅 / 高さ）
+/// * `columns` - 横方向の分割数（カールを滑らかにするには32程度）
+/// * `rows` - 縦方向の分割数
+/// * `radius` - 開始時のカールの半径（ページの高さを1とした値、例: 0.1）
+/// * `reading_direction` - `"ltr"` | `"rtl"`
+///
+/// # Example (JavaScript)
+/// ```js
+/// const mesh = page_curl_mesh(progress, page.width / page.height, 32, 1, 0.1, 'ltr');
+/// gl.bufferData(gl.ARRAY_BUFFER, mesh.vertices, gl.DYNAMIC_DRAW);
+/// gl.bufferData(gl.ELEMENT_ARRAY_BUFFER, mesh.indices, gl.DYNAMIC_DRAW);
+/// gl.drawElements(gl.TRIANGLES, mesh.indices.length, gl.UNSIGNED_INT, 0);
+/// ```
+#[wasm_bindgen]
+pub fn page_curl_mesh(
+    progress: f64,
+    aspect: f64,
+    columns: u32,
+    rows: u32,
+    radius: f64,
+    reading_direction: JsValue,
+) -> Result<JsCurlMesh, JsValue> {
+    let direction: spread::ReadingDirection = serde_wasm_bindgen::from_value(reading_direction)?;
+
+    Ok(JsCurlMesh {
+        inner: curl::page_curl_mesh(progress, aspect, columns, rows, radius, direction),
+    })
+}
+
+/// ページのミニマップ（ナビゲーター画像、JavaScriptから利用可能）
+///
+/// サムネイルの縮小は作成時に一度だけ行い、表示範囲が変わるたびに `render` で枠線を重ねる
+///
+/// # Example (JavaScript)
+/// ```js
+/// const thumbnail = new Uint8Array(await (await fetch(thumbnailUrl)).arrayBuffer());
+/// const minimap = new Minimap(thumbnail, 160, page.width, page.height);
+/// canvas.width = minimap.width;
+/// canvas.height = minimap.height;
+///
+/// const pixels = minimap.render({ x, y, width, height });
+/// ctx.putImageData(new ImageData(pixels, minimap.width, minimap.height), 0, 0);
+/// ```
+#[wasm_bindgen(js_name = Minimap)]
+pub struct JsMinimap {
+    inner: minimap::Minimap,
+}
+
+#[wasm_bindgen(js_class = Minimap)]
+impl JsMinimap {
+    /// # Arguments
+    /// * `image_data` - サムネイル（またはピラミッドの最小レベル）の画像データ
+    /// * `max_size` - ミニマップの長辺の最大サイズ
+    /// * `page_width` - ページの原寸の幅
+    /// * `page_height` - ページの原寸の高さ
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        image_data: &[u8],
+        max_size: u32,
+        page_width: u32,
+        page_height: u32,
+    ) -> Result<JsMinimap, JsValue> {
+        let inner = minimap::Minimap::new(image_data, max_size, page_width, page_height)?;
+        Ok(JsMinimap { inner })
+    }
+
+    /// 表示範囲を重ねたRGBA画素（`ImageData` にそのまま渡せる）
+    ///
+    /// # Arguments
+    /// * `viewport` - 表示範囲 `{ x, y, width, height }`（ページの原寸ピクセル）、省略時はミニマップのみ
+    pub fn render(&self, viewport: JsValue) -> Result<Uint8ClampedArray, JsValue> {
+        let viewport: Option<geometry::Rect> = serde_wasm_bindgen::from_value(viewport)?;
+        let pixels = self.inner.render(viewport.as_ref());
+        Ok(Uint8ClampedArray::from(&pixels[..]))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.inner.height()
+    }
+}
+
+/// 低解像度のタイルを下に敷きながら高解像度のタイルをフェードインさせる合成処理（JavaScriptから利用可能）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const compositor = new Compositor(levels, 512, 200);
+/// // タイルのデコード完了時
+/// compositor.mark_loaded(level, x, y, performance.now());
+///
+/// function frame(time) {
+///   for (const cmd of compositor.plan(level, visibleTiles, time)) {
+///     ctx.globalAlpha = cmd.opacity;
+///     const { src, dest } = cmd;
+///     ctx.drawImage(bitmaps.get(`${cmd.level}/${cmd.x}/${cmd.y}`),
+///       src.x, src.y, src.width, src.height, dest.x, dest.y, dest.width, dest.height);
+///   }
+///   if (compositor.is_animating(time)) requestAnimationFrame(frame);
+/// }
+/// ```
+#[wasm_bindgen(js_name = Compositor)]
+pub struct JsCompositor {
+    inner: compositor::Compositor,
+}
+
+#[wasm_bindgen(js_class = Compositor)]
+impl JsCompositor {
+    /// # Arguments
+    /// * `levels` - 各レベルの縮尺（原寸に対する割合、例: [1.0, 0.5, 0.25]）
+    /// * `tile_size` - タイルサイズ
+    /// * `fade_duration` - フェードインの長さ（ミリ秒、デフォルト: 200）
+    #[wasm_bindgen(constructor)]
+    pub fn new(levels: &[f32], tile_size: u32, fade_duration: Option<f64>) -> JsCompositor {
+        JsCompositor {
+            inner: compositor::Compositor::new(
+                levels,
+                tile_size,
+                fade_duration.unwrap_or(compositor::DEFAULT_FADE_DURATION),
+            ),
+        }
+    }
+
+    /// タイルの読み込み（デコード）完了を記録
+    pub fn mark_loaded(&mut self, level: usize, x: u32, y: u32, time: f64) {
+        self.inner
+            .mark_loaded(compositor::TileKey { level, x, y }, time);
+    }
+
+    /// タイルの記録を削除（キャッシュから破棄した場合）
+    pub fn forget(&mut self, level: usize, x: u32, y: u32) {
+        self.inner.forget(&compositor::TileKey { level, x, y });
+    }
+
+    /// 描画命令を作成
+    ///
+    /// # Arguments
+    /// * `level` - 表示するレベル
+    /// * `tiles` - 表示範囲内のタイル `[{ x, y }]`（`tiles_for_viewport` の戻り値をそのまま渡せる）
+    /// * `time` - 現在時刻（ミリ秒）
+    ///
+    /// # Returns
+    /// `{ level, x, y, src, dest, opacity }` の配列（この順に描画する）
+    pub fn plan(&self, level: usize, tiles: JsValue, time: f64) -> Result<JsValue, JsValue> {
+        let tiles: Vec<geometry::Point> = serde_wasm_bindgen::from_value(tiles)?;
+        let tiles: Vec<(u32, u32)> = tiles.iter().map(|t| (t.x as u32, t.y as u32)).collect();
+        Ok(serde_wasm_bindgen::to_value(
+            &self.inner.plan(level, &tiles, time),
+        )?)
+    }
+
+    /// フェードイン中のタイルがあるか
+    pub fn is_animating(&self, time: f64) -> bool {
+        self.inner.is_animating(time)
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+/// WebGL用テクスチャアトラスのスロット割り当て（JavaScriptから利用可能）
+///
+/// タイルを少数の大きなテクスチャにまとめ、描画呼び出しをまとめられるようにする
+///
+/// # Example (JavaScript)
+/// ```js
+/// const packer = new AtlasPacker(4096, 256, 4);
+///
+/// const evicted = cache.insert(tile.hash, pixels);
+/// packer.release(evicted);
+///
+/// const slot = packer.allocate(tile.hash);
+/// if (slot.evicted) cache.evict(slot.evicted);
+/// if (slot.upload) {
+///   gl.bindTexture(gl.TEXTURE_2D, atlases[slot.atlas]);
+///   gl.texSubImage2D(gl.TEXTURE_2D, 0, slot.x, slot.y, 256, 256, gl.RGBA, gl.UNSIGNED_BYTE, pixels);
+/// }
+/// pushQuad(slot.atlas, slot.uv, dest);
+/// ```
+#[wasm_bindgen(js_name = AtlasPacker)]
+pub struct JsAtlasPacker {
+    inner: atlas::AtlasPacker,
+}
+
+#[wasm_bindgen(js_class = AtlasPacker)]
+impl JsAtlasPacker {
+    /// # Arguments
+    /// * `atlas_size` - アトラスの1辺のピクセル数（`gl.MAX_TEXTURE_SIZE` 以下）
+    /// * `tile_size` - タイルサイズ
+    /// * `max_atlases` - アトラスの最大枚数
+    #[wasm_bindgen(constructor)]
+    pub fn new(atlas_size: u32, tile_size: u32, max_atlases: usize) -> JsAtlasPacker {
+        JsAtlasPacker {
+            inner: atlas::AtlasPacker::new(atlas_size, tile_size, max_atlases),
+        }
+    }
+
+    /// タイルにスロットを割り当て
+    ///
+    /// # Returns
+    /// `{ atlas, x, y, uv: { u0, v0, u1, v1 }, upload, evicted }`
+    /// （`upload` が `true` の場合は画素の転送が必要、`evicted` は追い出したタイルのキー）
+    pub fn allocate(&mut self, key: &str) -> Result<JsValue, JsValue> {
+        let allocation = self.inner.allocate(key).map_err(PamphletError::Internal)?;
+        Ok(serde_wasm_bindgen::to_value(&allocation)?)
+    }
+
+    /// 割り当て済みのスロット（存在しない場合は `undefined`）
+    pub fn get(&self, key: &str) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.get(key))?)
+    }
+
+    /// スロットを解放（`TileCache.insert` が返した破棄済みキーをそのまま渡せる）
+    pub fn release(&mut self, keys: Vec<String>) {
+        for key in &keys {
+            self.inner.release(key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// 割り当て済みのタイル数
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// 使用中のアトラスの枚数
+    #[wasm_bindgen(getter)]
+    pub fn atlas_count(&self) -> usize {
+        self.inner.atlas_count()
+    }
+}
+
+/// 表示フィルター（明るさ・コントラスト・セピア・反転、JavaScriptから利用可能）
+///
+/// CSSの `filter` が効かないWebGLキャンバスでも読書モードを提供できるよう、デコード済みの画素に直接適用する
+///
+/// # Example (JavaScript)
+/// ```js
+/// // 夜間モード
+/// const filter = new DisplayFilter({ invert: true, contrast: -0.2 });
+/// const imageData = ctx.getImageData(0, 0, width, height);
+/// filter.apply(new Uint8Array(imageData.data.buffer));
+/// ```
+#[wasm_bindgen(js_name = DisplayFilter)]
+pub struct JsDisplayFilter {
+    inner: filters::Filter,
+}
+
+#[wasm_bindgen(js_class = DisplayFilter)]
+impl JsDisplayFilter {
+    /// # Arguments
+    /// * `settings` - `{ brightness?, contrast?, sepia?, invert? }`
+    ///   （明るさ・コントラストは-1.0〜1.0、セピアは0.0〜1.0、省略時は変更なし）
+    #[wasm_bindgen(constructor)]
+    pub fn new(settings: JsValue) -> Result<JsDisplayFilter, JsValue> {
+        let settings: Option<filters::FilterSettings> = serde_wasm_bindgen::from_value(settings)?;
+        Ok(JsDisplayFilter {
+            inner: filters::Filter::new(&settings.unwrap_or_default()),
+        })
+    }
+
+    /// RGBA画素に適用（引数の配列を書き換える、アルファは変更しない）
+    pub fn apply(&self, pixels: &mut [u8]) {
+        self.inner.apply(pixels);
+    }
+
+    /// 何も変更しない設定か（`true` の場合は `apply` を呼ぶ必要がない）
+    #[wasm_bindgen(getter)]
+    pub fn is_identity(&self) -> bool {
+        self.inner.is_identity()
+    }
+}
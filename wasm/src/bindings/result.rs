@@ -0,0 +1,673 @@
+//! タイル化の結果（`JsTileResult`）
+
+use js_sys::{Array, Uint8Array};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use wasm_bindgen::prelude::*;
+
+use crate::error::PamphletError;
+use crate::*;
+
+/// JavaScriptに返すタイル情報
+#[wasm_bindgen]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsTileInfo {
+    pub(super) x: u32,
+    pub(super) y: u32,
+    pub(super) hash: String,
+}
+
+#[wasm_bindgen]
+impl JsTileInfo {
+    #[wasm_bindgen(getter)]
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn hash(&self) -> String {
+        self.hash.clone()
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TILE_WARNING_TYPESCRIPT: &str = r#"
+/** タイル化は続けたが知らせる問題（`JsTileResult.warnings`） */
+export interface TileWarning {
+  code: 'quality_ignored' | 'exif_orientation_ignored';
+  message: string;
+}
+"#;
+
+/// JavaScriptに返すタイル化結果
+#[wasm_bindgen]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsTileResult {
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) tile_size: u32,
+    pub(super) tiles: memory::PinnedTiles,
+    /// ラスタライズ時の解像度（PDF・SVG入力）、または前処理で拡大した後の解像度
+    pub(super) dpi: Option<f32>,
+    /// 前処理の自動切り抜きで切り抜いた範囲
+    pub(super) crop: Option<preprocess::CropRect>,
+    /// 前処理の拡大の倍率
+    pub(super) scale: Option<f64>,
+    /// `tiles` ゲッターが返す配列（初回に作って使い回す）
+    #[serde(skip)]
+    pub(super) tiles_cache: OnceCell<Array>,
+    /// エンコードを後回しにしたタイルの元の画像（`tile_image_lazy`）
+    #[serde(skip)]
+    pub(super) lazy: Option<tiler::LazyTiles>,
+    /// 処理時間の内訳（`set_timing_enabled(true)` の後にタイル化した場合）
+    pub(super) timings: Option<timing::Timings>,
+    /// タイル化は続けたが、呼び出し側に知らせる問題
+    pub(super) warnings: Vec<tiler::TileWarning>,
+    /// `dispose` を呼んだか
+    #[serde(skip)]
+    pub(super) disposed: bool,
+}
+
+impl From<tiler::TileResult> for JsTileResult {
+    fn from(result: tiler::TileResult) -> Self {
+        Self {
+            width: result.width,
+            height: result.height,
+            tile_size: result.tile_size,
+            tiles: result.tiles.into(),
+            timings: result.timings,
+            warnings: result.warnings,
+            dpi: None,
+            crop: None,
+            scale: None,
+            tiles_cache: OnceCell::new(),
+            lazy: None,
+            disposed: false,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl JsTileResult {
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// ラスタライズ時の解像度（PDF・SVG入力）または前処理で拡大した後の解像度（metadataのページの `dpi` に使える）
+    #[wasm_bindgen(getter)]
+    pub fn dpi(&self) -> Option<f32> {
+        self.dpi
+    }
+
+    /// 自動切り抜きで切り抜いた範囲（`{ x, y, width, height }`、切り抜かなかった場合は `undefined`）
+    ///
+    /// metadataのページ情報の `crop` に設定すると、元のスキャンの座標に対応付けられる
+    #[wasm_bindgen(getter)]
+    pub fn crop(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.crop)?)
+    }
+
+    /// 前処理で拡大した倍率（拡大しなかった場合は `undefined`、metadataのページの `scale` に使える）
+    #[wasm_bindgen(getter)]
+    pub fn scale(&self) -> Option<f64> {
+        self.scale
+    }
+
+    /// 処理時間の内訳（`set_timing_enabled(true)` で記録を有効にしていない場合は `undefined`）
+    ///
+    /// `{ decode_ms, crop_ms, encode_ms, hash_ms, total_bytes, tiles }` の形式で、`tiles` は
+    /// タイルごとの `{ x, y, crop_ms, encode_ms, hash_ms, bytes }`
+    #[wasm_bindgen(getter)]
+    pub fn timings(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.timings)?)
+    }
+
+    /// タイル化は続けたが知らせる問題の配列（`{ code, message }`、ない場合は空）
+    ///
+    /// `code` は `"quality_ignored"`（このビルドのエンコーダーは品質を使わない）、
+    /// `"exif_orientation_ignored"`（EXIFの向きを適用していない）
+    #[wasm_bindgen(getter, unchecked_return_type = "TileWarning[]")]
+    pub fn warnings(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.warnings)?)
+    }
+
+    /// タイル情報の配列を取得
+    ///
+    /// 要素は `{ x, y, hash }` のオブジェクト。配列は初回に作り、以降は同じ配列を返す
+    /// （書き換えると次の呼び出しにも反映される）
+    #[wasm_bindgen(getter)]
+    pub fn tiles(&self) -> Result<Array, JsValue> {
+        if let Some(tiles) = self.tiles_cache.get() {
+            return Ok(tiles.clone());
+        }
+        let tiles = self
+            .tiles
+            .iter()
+            .map(|tile| tile_object(tile, None))
+            .collect::<Result<Array, JsValue>>()?;
+        Ok(self.tiles_cache.get_or_init(|| tiles).clone())
+    }
+
+    /// 指定したインデックスのタイルデータを取得
+    ///
+    /// `tile_image_lazy` の結果では、まだエンコードしていないタイルをここでエンコードする
+    #[wasm_bindgen]
+    pub fn get_tile_data(&mut self, index: usize) -> Result<Uint8Array, JsValue> {
+        let data = Uint8Array::from(self.tile_data(index)?);
+        self.tiles.mark_retrieved(index);
+        Ok(data)
+    }
+
+    /// 指定したインデックスのタイルデータをJavaScriptに移し、WASM側のバッファを解放する
+    ///
+    /// アップロードしながら1タイルずつ取り出すと、ページ全体のタイルを保持せずに済む。
+    /// 取り出した後、そのタイルのデータは取得できない（タイル情報は残る）
+    #[wasm_bindgen]
+    pub fn take_tile_data(&mut self, index: usize) -> Result<Uint8Array, JsValue> {
+        Ok(Uint8Array::from(self.take_data(index)?.as_slice()))
+    }
+
+    /// 指定したインデックスのタイルデータのバイト数を取得（`read_tile_data_into` の書き込み先の確保に使う）
+    #[wasm_bindgen]
+    pub fn tile_data_length(&mut self, index: usize) -> Result<usize, JsValue> {
+        Ok(self.tile_data(index)?.len())
+    }
+
+    /// 指定したインデックスのタイルデータを、コピーせずにWASMのメモリを直接指すビューとして取得
+    ///
+    /// ビューは次のいずれかが起きると無効になる（中身が壊れるか、長さ0になる）ため、
+    /// すぐに `fetch` の本文に渡す・`set` で書き写すなど、同期的に使い切ること。
+    /// - WASMの関数を呼び出したとき（メモリが拡張されると元の `ArrayBuffer` が切り離される）
+    /// - この結果を `free()` したとき
+    ///
+    /// `set_release_after_read(true)` の場合、このタイルのデータは次にタイルを読み出すときに解放する
+    #[wasm_bindgen]
+    pub fn tile_data_view(&mut self, index: usize) -> Result<Uint8Array, JsValue> {
+        // SAFETY: ビューの寿命はJavaScript側の責任（上記の無効になる条件を参照）
+        Ok(unsafe { Uint8Array::view(self.viewed_tile_data(index)?) })
+    }
+
+    /// 指定したインデックスのタイルデータを、呼び出し側の `target` の `offset` 以降に書き込む
+    ///
+    /// 新しい `ArrayBuffer` を作らないので、同じバッファを使い回して複数のタイルを読める
+    ///
+    /// # Returns
+    /// 書き込んだバイト数
+    ///
+    /// # Errors
+    /// インデックスが範囲外・取り出し済みの場合、`target` に入りきらない場合
+    #[wasm_bindgen]
+    pub fn read_tile_data_into(
+        &mut self,
+        index: usize,
+        target: &Uint8Array,
+        offset: Option<u32>,
+    ) -> Result<usize, JsValue> {
+        let data = self.tile_data(index)?;
+        let offset = offset.unwrap_or(0);
+        let end = target_end(offset, data.len(), target.length())?;
+        let len = data.len();
+        target.subarray(offset, end).copy_from(data);
+        self.tiles.mark_retrieved(index);
+        Ok(len)
+    }
+
+    /// 指定したインデックスのタイルデータを `data:image/webp;base64,...` のデータURLに変換し、区切りごとに `chunk_callback` に渡す
+    ///
+    /// 変換したURLの全体をWASM側で持たず、区切りの文字列だけを順に渡す。最初の呼び出しは
+    /// `data:image/webp;base64,` で、以降はBase64の文字列（各区切りは `chunk_size` バイトの
+    /// タイルデータ分、省略時は48KiBで64KiBの文字列）。すべてをつなげるとデータURLになる
+    ///
+    /// # Returns
+    /// データURLの長さ（文字数）
+    ///
+    /// # Errors
+    /// インデックスが範囲外・取り出し済みの場合、`chunk_callback` が例外を投げた場合
+    #[wasm_bindgen]
+    pub fn encode_data_url(
+        &mut self,
+        index: usize,
+        chunk_callback: &js_sys::Function,
+        chunk_size: Option<usize>,
+    ) -> Result<usize, JsValue> {
+        const PREFIX: &str = "data:image/webp;base64,";
+        let data = self.tile_data(index)?;
+        let length = PREFIX.len() + data.len().div_ceil(3) * 4;
+        chunk_callback.call1(&JsValue::NULL, &JsValue::from_str(PREFIX))?;
+        binary::base64_encode_chunks(data, chunk_size.unwrap_or(48 * 1024), |chunk| {
+            chunk_callback
+                .call1(&JsValue::NULL, &JsValue::from_str(chunk))
+                .map(drop)
+        })?;
+        self.tiles.mark_retrieved(index);
+        Ok(length)
+    }
+
+    /// タイル情報とデータを `{ x, y, hash, data }` の配列として取り出し、この結果を解放する
+    ///
+    /// 各タイルのデータは一度だけJavaScriptにコピーされ、WASM側のバッファはすぐに解放される。
+    /// 呼び出した後、この結果は使えない（`take_tile_data` で取り出し済みのタイルの `data` は空）
+    #[wasm_bindgen]
+    pub fn into_tiles(mut self) -> Result<Array, JsValue> {
+        self.encode_all()?;
+        self.tiles
+            .into_inner()
+            .into_iter()
+            .map(|tile| tile_object(&tile, Some(&tile.data)))
+            .collect()
+    }
+
+    /// まだエンコードしていないタイルをすべてエンコードし、元の画像を解放する
+    ///
+    /// `tile_image_lazy` の結果で使う（それ以外の結果では何もしない）
+    #[wasm_bindgen]
+    pub fn encode_all(&mut self) -> Result<(), JsValue> {
+        for index in 0..self.tiles.len() {
+            self.materialize(index)?;
+        }
+        Ok(())
+    }
+
+    /// タイルのデータをすべて解放する（タイル情報と大きさは残る）
+    ///
+    /// wasm-bindgenが生成する `free()` は結果そのものを解放し、以降のアクセスはエラーになる。
+    /// ファイナライザーを待たずに、ページを処理し終えたらどちらかを呼ぶこと
+    #[wasm_bindgen]
+    pub fn dispose(&mut self) {
+        self.tiles.release_all();
+        self.lazy = None;
+        self.disposed = true;
+    }
+
+    /// `dispose` を呼んだか（タイルを `take_tile_data` ですべて取り出しただけでは `false`）
+    #[wasm_bindgen]
+    pub fn is_disposed(&self) -> bool {
+        self.disposed
+    }
+
+    /// WASM側で保持しているタイルのデータ（`tile_image_lazy` の結果ではエンコード前の画像も含む）のバイト数
+    #[wasm_bindgen]
+    pub fn retained_bytes(&self) -> usize {
+        let lazy = self.lazy.as_ref().map_or(0, |lazy| lazy.retained_bytes());
+        self.tiles.iter().map(|tile| tile.data.len()).sum::<usize>() + lazy
+    }
+
+    /// metadataのページ情報（`{ page, width, height, tiles, dpi?, crop?, scale? }`）
+    ///
+    /// `generate_metadata` に渡すページ情報や、`PipelineCoordinator` のワーカーが返す
+    /// `chunk_done` のページに使う
+    ///
+    /// `tile_image_lazy` の結果では、すべてのタイルをエンコードするまでエラー（`encode_all` を先に呼ぶ）
+    #[wasm_bindgen(unchecked_return_type = "PageInfo")]
+    pub fn page_info(&self, page: u32) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.to_page_info(page)?)?)
+    }
+
+    /// 指定したインデックスのタイルのハッシュ（範囲外の場合は `undefined`）
+    ///
+    /// `tile_image_lazy` の結果では、エンコードしたタイルはWebPのデータのハッシュ、
+    /// まだエンコードしていないタイルは画素のハッシュ
+    #[wasm_bindgen]
+    pub fn tile_hash(&self, index: usize) -> Option<String> {
+        self.tiles.get(index).map(|tile| tile.hash.clone())
+    }
+
+    /// タイル数を取得
+    #[wasm_bindgen]
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// 読み出したタイルのデータを、その場でWASM側から解放するかを設定する（既定では解放しない）
+    ///
+    /// 有効にすると `get_tile_data`・`read_tile_data_into`・`encode_data_url` で読み出したタイルのデータをすぐに解放し、
+    /// アップロードの進みに合わせて `pinned_tile_bytes` が減っていく。それまでに読み出したタイルも
+    /// この時点で解放する。解放したタイルをもう一度読み出すとエラーになる
+    #[wasm_bindgen]
+    pub fn set_release_after_read(&mut self, enabled: bool) {
+        self.tiles.set_release_after_read(enabled);
+    }
+
+    /// 指定したインデックスのタイルのデータを読み出したか
+    #[wasm_bindgen]
+    pub fn is_retrieved(&self, index: usize) -> bool {
+        self.tiles.is_retrieved(index)
+    }
+
+    /// データを読み出したタイルの数（アップロードの進捗の表示に使える）
+    #[wasm_bindgen(getter)]
+    pub fn retrieved_count(&self) -> usize {
+        self.tiles.retrieved_count()
+    }
+}
+
+impl JsTileResult {
+    /// metadataのページ情報
+    ///
+    /// # Errors
+    /// `tile_image_lazy` の結果で、まだエンコードしていない（ハッシュが画素のハッシュの）タイルがある場合
+    pub(super) fn to_page_info(&self, page: u32) -> Result<PageInfo, PamphletError> {
+        if self.lazy.is_some() {
+            return Err(PamphletError::InvalidArgument(
+                "Some tiles are not encoded yet and have pixel hashes; call encode_all() first"
+                    .to_string(),
+            ));
+        }
+        Ok(PageInfo {
+            page,
+            width: self.width,
+            height: self.height,
+            tiles: self
+                .tiles
+                .iter()
+                .map(|tile| TileMetadata {
+                    x: tile.x,
+                    y: tile.y,
+                    hash: tile.hash.clone(),
+                })
+                .collect(),
+            dpi: self.dpi.map(f64::from),
+            crop: self.crop,
+            scale: self.scale,
+            ..Default::default()
+        })
+    }
+
+    /// 指定したインデックスのタイルデータ
+    ///
+    /// # Errors
+    /// インデックスが範囲外の場合、`take_tile_data` で取り出し済み・読み出した後に解放済みの場合
+    fn tile_data(&mut self, index: usize) -> Result<&[u8], JsValue> {
+        self.tiles.release_deferred();
+        self.materialize(index)?;
+        let tile = self
+            .tiles
+            .get(index)
+            .ok_or_else(|| PamphletError::NotFound("Tile index out of bounds".to_string()))?;
+        if tile.data.is_empty() {
+            return Err(PamphletError::NotFound(format!(
+                "Tile data at index {} has already been taken or released",
+                index
+            ))
+            .into());
+        }
+        Ok(&tile.data)
+    }
+
+    /// `take_tile_data` で渡すタイルデータ（WASM側からは取り除く）
+    fn take_data(&mut self, index: usize) -> Result<Vec<u8>, JsValue> {
+        self.tile_data(index)?;
+        Ok(self.tiles.take(index))
+    }
+
+    /// `tile_data_view` で渡すタイルデータ（次にタイルを読み出すまで手放さない）
+    fn viewed_tile_data(&mut self, index: usize) -> Result<&[u8], JsValue> {
+        self.tile_data(index)?;
+        self.tiles.mark_viewed(index);
+        Ok(&self.tiles[index].data)
+    }
+
+    /// エンコードを後回しにしたタイルをエンコードする（エンコード済みなら何もしない）
+    fn materialize(&mut self, index: usize) -> Result<(), JsValue> {
+        let Some(lazy) = &mut self.lazy else {
+            return Ok(());
+        };
+        if let Some(data) = lazy.encode(index)? {
+            // 画素のハッシュを `tile_image` と同じWebPのデータのハッシュに置き換える
+            let hash = hasher::calculate_hash(&data);
+            self.tiles.set_data(index, data, hash);
+            self.tiles_cache.take();
+        }
+        if lazy.is_complete() {
+            self.lazy = None;
+        }
+        Ok(())
+    }
+}
+
+/// `len` バイトを `offset` から書き込む先の終わり（`read_tile_data_into`）
+///
+/// # Errors
+/// 長さ `target_len` の書き込み先に入りきらない場合
+fn target_end(offset: u32, len: usize, target_len: u32) -> Result<u32, PamphletError> {
+    let end = offset as usize + len;
+    if end > target_len as usize {
+        return Err(PamphletError::InvalidArgument(format!(
+            "Target buffer too small: need {} bytes, got {}",
+            end, target_len
+        )));
+    }
+    Ok(end as u32)
+}
+
+/// タイル情報を `{ x, y, hash }`（`data` を渡した場合は `data` も）のオブジェクトにする
+fn tile_object(tile: &tiler::TileInfo, data: Option<&[u8]>) -> Result<JsValue, JsValue> {
+    let object = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| js_sys::Reflect::set(&object, &key.into(), &value);
+    set("x", tile.x.into())?;
+    set("y", tile.y.into())?;
+    set("hash", tile.hash.as_str().into())?;
+    if let Some(data) = data {
+        set("data", Uint8Array::from(data).into())?;
+    }
+    Ok(object.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::tiling::tile_with_options;
+    use crate::test_util::png;
+
+    /// 90x70の画像を32pxでタイル化した結果（9タイル）
+    fn tile_result() -> JsTileResult {
+        tile_with_options(
+            &mut tiler::TilerContext::new(),
+            &png(90, 70, 0),
+            tiler::TileOptions::new(32, None),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_tile_data() {
+        let _lock = memory::PINNED_TESTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut result = tile_result();
+        assert_eq!(result.tile_count(), 9);
+        let len = result.tile_data_length(4).unwrap();
+        assert_eq!(len, result.tiles[4].data.len());
+        assert_eq!(&result.tile_data(4).unwrap()[..4], b"RIFF");
+
+        // 書き込み先に入りきるか
+        assert_eq!(target_end(0, len, len as u32).unwrap(), len as u32);
+        assert_eq!(target_end(8, len, 100_000).unwrap(), len as u32 + 8);
+        assert!(target_end(1, len, len as u32).is_err());
+
+        // ビューで読み出したデータは、次にタイルを読み出すまで残る
+        result.set_release_after_read(true);
+        assert_eq!(
+            result.viewed_tile_data(0).unwrap().len(),
+            result.tiles[0].data.len()
+        );
+        assert!(result.is_retrieved(0));
+        assert!(!result.tiles[0].data.is_empty());
+        result.tile_data_length(1).unwrap();
+        assert!(result.tiles[0].data.is_empty());
+        assert!(!result.tiles[1].data.is_empty());
+        assert_eq!(result.retrieved_count(), 1);
+    }
+
+    #[test]
+    fn test_take_tile_data() {
+        let _lock = memory::PINNED_TESTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut result = tile_image_lazy(&png(90, 70, 0), 32, None).unwrap();
+        let data = result.take_data(2).unwrap();
+        assert_eq!(&data[..4], b"RIFF");
+
+        // データだけを手放し、タイル情報は残す
+        assert!(result.is_retrieved(2));
+        assert_eq!(result.retrieved_count(), 1);
+        assert!(result.tiles[2].data.is_empty());
+        assert!(!result.tiles[2].hash.is_empty());
+        result.encode_all().unwrap();
+        let bytes = result.retained_bytes();
+        assert_eq!(
+            result.take_data(3).unwrap().len(),
+            bytes - result.retained_bytes()
+        );
+    }
+
+    #[test]
+    fn test_dispose() {
+        let _lock = memory::PINNED_TESTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let before = memory::memory_stats().pinned_tile_bytes;
+        let mut result = tile_result();
+        assert!(!result.is_disposed());
+        assert!(result.retained_bytes() > 0);
+
+        // データだけを解放し、タイル情報と大きさは残す
+        result.dispose();
+        assert!(result.is_disposed());
+        assert_eq!(result.retained_bytes(), 0);
+        assert_eq!(memory::memory_stats().pinned_tile_bytes, before);
+        assert_eq!(result.tile_count(), 9);
+        assert_eq!((result.width(), result.height()), (90, 70));
+
+        // エンコード前の画像も解放する
+        let mut lazy = tile_image_lazy(&png(90, 70, 0), 32, None).unwrap();
+        assert!(!lazy.is_disposed());
+        lazy.dispose();
+        assert!(lazy.is_disposed());
+        assert!(lazy.lazy.is_none());
+
+        // 保持しているデータがなくても、`dispose` を呼ぶまでは解放済みではない
+        let mut empty = JsTileResult::from(tiler::TileResult {
+            width: 0,
+            height: 0,
+            tile_size: 32,
+            tiles: Vec::new(),
+            timings: None,
+            warnings: Vec::new(),
+        });
+        assert_eq!(empty.retained_bytes(), 0);
+        assert!(!empty.is_disposed());
+        empty.dispose();
+        assert!(empty.is_disposed());
+
+        let mut taken = tile_result();
+        for index in 0..taken.tile_count() {
+            taken.take_data(index).unwrap();
+        }
+        assert_eq!(taken.retained_bytes(), 0);
+        assert!(!taken.is_disposed());
+    }
+
+    #[test]
+    fn test_encode_all() {
+        let _lock = memory::PINNED_TESTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let pinned = || memory::memory_stats().pinned_tile_bytes;
+        let before = pinned();
+        let mut result = tile_image_lazy(&png(90, 70, 0), 32, None).unwrap();
+        assert_eq!(result.tile_count(), 9);
+        assert!(result.tiles.iter().all(|tile| tile.data.is_empty()));
+        assert!(result.retained_bytes() > 0);
+        assert_eq!(pinned(), before);
+
+        // 画素のハッシュのままではページ情報を作らない
+        let pixel_hash = result.tile_hash(0).unwrap();
+        assert!(result.to_page_info(0).is_err());
+
+        // エンコードすると元の画像を手放し、タイルのデータだけが残る
+        result.encode_all().unwrap();
+        assert!(result.lazy.is_none());
+        assert!(result.tiles.iter().all(|tile| !tile.data.is_empty()));
+        let bytes = result
+            .tiles
+            .iter()
+            .map(|tile| tile.data.len())
+            .sum::<usize>();
+        assert_eq!(result.retained_bytes(), bytes);
+        assert_eq!(pinned() - before, bytes);
+
+        // ハッシュは `tile_image` と同じWebPのデータのハッシュに置き換わる
+        let eager = tile_result();
+        assert_ne!(result.tile_hash(0).unwrap(), pixel_hash);
+        for (lazy, eager) in result.tiles.iter().zip(eager.tiles.iter()) {
+            assert_eq!(lazy.hash, eager.hash);
+            assert_eq!(lazy.hash, hasher::calculate_hash(&lazy.data));
+        }
+        let page = result.to_page_info(3).unwrap();
+        assert_eq!(page.tiles[8].hash, eager.tiles[8].hash);
+        assert_eq!(result.tile_hash(9), None);
+        drop(eager);
+
+        // `into_tiles` と同じく取り出すとWASM側の数から外れる
+        let tiles = result.tiles.into_inner();
+        assert_eq!(tiles.len(), 9);
+        assert_eq!(pinned(), before);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_tiles_getter() {
+        let result = tile_result();
+        let tiles = result.tiles().unwrap();
+        assert_eq!(tiles.length(), 9);
+        // 2回目以降は同じ配列を返す
+        assert!(js_sys::Object::is(&tiles, &result.tiles().unwrap()));
+
+        let tile = tiles.get(4);
+        let get = |key: &str| js_sys::Reflect::get(&tile, &key.into()).unwrap();
+        assert_eq!(get("x").as_f64(), Some(1.0));
+        assert_eq!(get("y").as_f64(), Some(1.0));
+        assert_eq!(get("hash").as_string().unwrap(), result.tiles[4].hash);
+        assert!(get("data").is_undefined());
+
+        // 後からエンコードしたタイルはハッシュが変わるため作り直す
+        let mut lazy = tile_image_lazy(&png(90, 70, 0), 32, None).unwrap();
+        let pixel = lazy.tiles().unwrap();
+        lazy.encode_all().unwrap();
+        let encoded = lazy.tiles().unwrap();
+        assert!(!js_sys::Object::is(&pixel, &encoded));
+        let hash = js_sys::Reflect::get(&encoded.get(4), &"hash".into()).unwrap();
+        assert_eq!(hash.as_string(), lazy.tile_hash(4));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_into_tiles() {
+        let mut result = tile_image_lazy(&png(90, 70, 0), 32, None).unwrap();
+        let data = result.tile_data(4).unwrap().to_vec();
+        let tiles = result.into_tiles().unwrap();
+        assert_eq!(tiles.length(), 9);
+
+        let get =
+            |index: u32, key: &str| js_sys::Reflect::get(&tiles.get(index), &key.into()).unwrap();
+        assert_eq!(Uint8Array::new(&get(4, "data")).to_vec(), data);
+        assert!(get(0, "hash").as_string().is_some());
+    }
+}
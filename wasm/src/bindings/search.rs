@@ -0,0 +1,125 @@
+//! ページのテキストの検索とOCRの結果の扱い
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::PamphletError;
+use crate::*;
+
+/// 全文検索用の索引（JavaScriptから利用可能）
+///
+/// # Example (JavaScript)
+/// ```js
+/// // 公開時: OCR結果から索引を作成してCDNに配置
+/// const index = new SearchIndex();
+/// index.add_page({ page: 0, words: [{ text: '新商品', x: 120, y: 80, width: 200, height: 40 }] });
+/// await upload('search.bin', index.to_bytes());
+///
+/// // 閲覧時
+/// const index = SearchIndex.from_bytes(new Uint8Array(await res.arrayBuffer()));
+/// for (const { page, rects } of index.query('新商品')) {
+///   highlight(page, rects);
+/// }
+/// ```
+#[wasm_bindgen(js_name = SearchIndex)]
+pub struct JsSearchIndex {
+    inner: search::SearchIndex,
+}
+
+#[wasm_bindgen(js_class = SearchIndex)]
+impl JsSearchIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsSearchIndex {
+        JsSearchIndex {
+            inner: search::SearchIndex::new(),
+        }
+    }
+
+    /// `to_bytes` で生成したバイト列から索引を復元
+    pub fn from_bytes(bytes: &[u8]) -> Result<JsSearchIndex, JsValue> {
+        let inner =
+            search::SearchIndex::from_bytes(bytes).map_err(PamphletError::InvalidArgument)?;
+        Ok(JsSearchIndex { inner })
+    }
+
+    /// ページのテキストを追加
+    ///
+    /// # Arguments
+    /// * `page_text` - `{ page, words: [{ text, x, y, width, height }] }`（座標はページの原寸ピクセル）
+    pub fn add_page(&mut self, page_text: JsValue) -> Result<(), JsValue> {
+        let page_text: search::PageText = serde_wasm_bindgen::from_value(page_text)?;
+        self.inner.add_page(&page_text);
+        Ok(())
+    }
+
+    /// 語句を検索（空白区切りはAND検索）
+    ///
+    /// # Returns
+    /// `{ page, rects: [{ x, y, width, height }] }` の配列（ページ番号順）
+    pub fn query(&self, term: &str) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.query(term))?)
+    }
+
+    /// CDN配信用のバイト列に変換
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    /// 登録されている単語数
+    #[wasm_bindgen(getter)]
+    pub fn word_count(&self) -> usize {
+        self.inner.word_count()
+    }
+}
+
+impl Default for JsSearchIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// OCR結果（hOCR / ALTO XML）をページの原寸ピクセル座標に合わせる
+///
+/// 戻り値をmetadataのページ情報の `words` に設定すると、テキスト選択や検索索引の作成に使える
+///
+/// # Arguments
+/// * `source` - OCR結果の文字列
+/// * `format` - `"hocr"` | `"alto"`
+/// * `page_width` - タイル化したページの幅（ピクセル）
+/// * `page_height` - タイル化したページの高さ（ピクセル）
+///
+/// # Returns
+/// `{ text, x, y, width, height }` の配列
+///
+/// # Example (JavaScript)
+/// ```js
+/// const result = tile_image(imageData, 512, 80);
+/// page.words = align_ocr_words(await hocr.text(), 'hocr', result.width, result.height);
+/// ```
+#[wasm_bindgen]
+pub fn align_ocr_words(
+    source: &str,
+    format: JsValue,
+    page_width: u32,
+    page_height: u32,
+) -> Result<JsValue, JsValue> {
+    let format: ocr::OcrFormat = serde_wasm_bindgen::from_value(format)?;
+    let page = ocr::parse(source, format).map_err(PamphletError::InvalidArgument)?;
+    let words = ocr::align_to_page(&page, page_width, page_height);
+
+    Ok(serde_wasm_bindgen::to_value(&words)?)
+}
+
+/// タイルと重なる単語を、タイル左上を原点とする座標で取得
+///
+/// # Arguments
+/// * `words` - ページの `words`（原寸ピクセル座標）
+/// * `tile_size` - タイルサイズ
+/// * `x` - タイルのX座標
+/// * `y` - タイルのY座標
+#[wasm_bindgen]
+pub fn words_for_tile(words: JsValue, tile_size: u32, x: u32, y: u32) -> Result<JsValue, JsValue> {
+    let words: Vec<search::WordBox> = serde_wasm_bindgen::from_value(words)?;
+    let in_tile = ocr::words_for_tile(&words, tile_size, x, y);
+
+    Ok(serde_wasm_bindgen::to_value(&in_tile)?)
+}
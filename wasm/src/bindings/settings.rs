@@ -0,0 +1,228 @@
+//! モジュール全体の設定（デコードの上限・許可する形式・ログなど）とエラーのメッセージ
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::error::PamphletError;
+use crate::*;
+
+/// デコードする画像の大きさの上限を設定する（JavaScriptから呼び出し可能）
+///
+/// ヘッダーの大きさが上限を超える画像は、画素を確保する前に `image_too_large` のエラーにする。
+/// `limits` は `{ max_dimension?, max_pixels? }`（省略した項目は既定の32768ピクセル・2^28画素）
+#[wasm_bindgen]
+pub fn set_decode_limits(limits: JsValue) -> Result<(), JsValue> {
+    let limits: Option<decode::DecodeLimits> = serde_wasm_bindgen::from_value(limits)?;
+    settings::Settings::update_global(|settings| settings.decode_limits = limits);
+    Ok(())
+}
+
+/// デコードする画像の大きさの上限（`{ max_dimension, max_pixels }`）
+#[wasm_bindgen]
+pub fn decode_limits() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(
+        &settings::Settings::global().limits(),
+    )?)
+}
+
+/// デコードを許可する入力の形式を設定する（JavaScriptから呼び出し可能）
+///
+/// `formats` は `"jpeg"`・`"png"`・`"webp"`・`"gif"`・`"heic"`・`"avif"`・`"jxl"`・`"jpeg2000"`・`"psd"`・`"raw"` の配列。
+/// 許可していない形式の画像は、デコーダーに渡す前に `unsupported_format` のエラーにする。
+/// `undefined` / `null` ですべて許可する（既定）
+///
+/// # Example (JavaScript)
+/// ```js
+/// // 信頼できないアップロードを受け付けるエンドポイント
+/// set_allowed_formats(['jpeg', 'png', 'webp']);
+/// ```
+#[wasm_bindgen]
+pub fn set_allowed_formats(
+    #[wasm_bindgen(unchecked_param_type = "string[] | null | undefined")] formats: JsValue,
+) -> Result<(), JsValue> {
+    let formats: Option<Vec<decode::SourceFormat>> = serde_wasm_bindgen::from_value(formats)
+        .map_err(|e| PamphletError::InvalidArgument(format!("Invalid input formats: {}", e)))?;
+    settings::Settings::update_global(|settings| settings.allowed_formats = formats);
+    Ok(())
+}
+
+/// デコードを許可する入力の形式の配列
+#[wasm_bindgen(unchecked_return_type = "string[]")]
+pub fn allowed_formats() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(
+        settings::Settings::global().formats(),
+    )?)
+}
+
+/// 組み込みのエラーメッセージを取得する（JavaScriptから呼び出し可能）
+///
+/// 戻り値はエラーの `key`（`"errors.decode_failed"` など）からメッセージの文へのオブジェクト。
+/// 文の `{name}` はエラーの `params` の値で置き換える。i18nのライブラリに読み込ませる場合に使う
+///
+/// # Arguments
+/// * `locale` - `"ja"`・`"en"`（`"ja-JP"` のような地域付きも可）
+///
+/// # Returns
+/// 対応していない言語の場合は `undefined`
+#[wasm_bindgen(unchecked_return_type = "Record<string, string> | undefined")]
+pub fn error_messages(locale: &str) -> Result<JsValue, JsValue> {
+    let Some(messages) = error::messages(locale) else {
+        return Ok(JsValue::UNDEFINED);
+    };
+    let messages: std::collections::BTreeMap<_, _> = messages.iter().copied().collect();
+    Ok(messages.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// APIが投げたエラーを `locale` のメッセージにする（JavaScriptから呼び出し可能）
+///
+/// エラーの `key` と `params` から組み込みのメッセージを作る。`key` を持たないエラーや
+/// 対応していない言語の場合は `message` を返す
+///
+/// # Example (JavaScript)
+/// ```js
+/// try {
+///   tile_image(data, 8, 80);
+/// } catch (e) {
+///   showError(localize_error(e, navigator.language));
+///   // "タイルサイズ8は指定できません（16から4096まで）"
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn localize_error(error: &JsValue, locale: &str) -> String {
+    let property = |name: &str| js_sys::Reflect::get(error, &name.into()).ok();
+    let message = property("message")
+        .and_then(|message| message.as_string())
+        .or_else(|| error.as_string())
+        .unwrap_or_default();
+    let Some(key) = property("key").and_then(|key| key.as_string()) else {
+        return message;
+    };
+    let params = property("params")
+        .and_then(|params| serde_wasm_bindgen::from_value(params).ok())
+        .unwrap_or_default();
+    error::localize(locale, &key, &params).unwrap_or(message)
+}
+
+/// ログのレベルを設定する（JavaScriptから呼び出し可能）
+///
+/// ログはレベルに対応する `console.error`・`console.warn`・`console.info`・`console.debug` に出る。
+/// 既定は `"warn"`。現場で問題を調べるときに `"debug"` にすると、デコード・タイル化の詳細を出す
+///
+/// # Arguments
+/// * `level` - `"off"` | `"error"` | `"warn"` | `"info"` | `"debug"` | `"trace"`
+///
+/// # Errors
+/// レベルの名前が不正な場合
+#[wasm_bindgen]
+pub fn set_log_level(
+    #[wasm_bindgen(unchecked_param_type = "'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace'")]
+    level: &str,
+) -> Result<(), JsValue> {
+    logging::set_level(level).map_err(|e| PamphletError::InvalidArgument(e).into())
+}
+
+/// 現在のログのレベル（`"warn"` など）
+#[wasm_bindgen]
+pub fn log_level() -> String {
+    logging::level()
+}
+
+/// このビルドのバージョンと機能を取得（JavaScriptから呼び出し可能）
+///
+/// 戻り値は `{ version, git_hash, features, simd, threads, allocator, input_formats, output_formats, lossy_webp }`。
+/// フロントエンドは形式や機能に対応しているかを、呼び出して失敗するかを試さずに判別できる
+///
+/// # Example (JavaScript)
+/// ```js
+/// const info = version_info();
+/// const accept = info.input_formats.includes('heic') ? 'image/*,.heic' : 'image/*';
+/// ```
+#[wasm_bindgen(unchecked_return_type = "VersionInfo")]
+pub fn version_info() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&build_info::version_info(
+        ALLOCATOR,
+    ))?)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const VERSION_INFO_TYPESCRIPT: &str = r#"
+/** ビルドのバージョンと機能（`version_info`） */
+export interface VersionInfo {
+  version: string;
+  /** ビルドしたコミット（gitのない環境でビルドした場合は `null`） */
+  git_hash: string | null;
+  /** 有効なCargoのフィーチャー */
+  features: string[];
+  simd: boolean;
+  threads: boolean;
+  allocator: 'system' | 'dlmalloc' | 'talc' | 'wee_alloc';
+  /** タイル化できる入力の形式 */
+  input_formats: string[];
+  output_formats: string[];
+  /** 品質を指定して非可逆でエンコードできるか */
+  lossy_webp: boolean;
+}
+"#;
+
+/// タイル化の処理時間の記録を有効・無効にする（JavaScriptから呼び出し可能）
+///
+/// 有効にすると、以降のタイル化でデコード・タイルの切り出し・エンコード・ハッシュの計算の
+/// 時間をタイルごとに記録し、結果の `timings` で取得できる。既定では無効（時刻を読まない）
+///
+/// # Example (JavaScript)
+/// ```js
+/// set_timing_enabled(true);
+/// const result = tile_image(imageData, 512, 80);
+/// const { decode_ms, encode_ms, total_bytes } = result.timings;
+/// ```
+#[wasm_bindgen]
+pub fn set_timing_enabled(enabled: bool) {
+    settings::Settings::update_global(|settings| settings.timing = Some(enabled));
+}
+
+/// ページ情報・目次の厳密な読み込みを有効・無効にする（JavaScriptから呼び出し可能）
+///
+/// 既定では `generate_metadata` などはページ情報・目次の知らない項目を読み飛ばすため、
+/// `widht` のような綴りの誤りに気付かずに壊れたmetadataを出力してしまう。有効にすると、
+/// 知らない項目・足りない項目・型の誤りを `pages[0].widht` のような場所付きのエラー（`invalid_argument`）にする
+///
+/// # Example (JavaScript)
+/// ```js
+/// set_metadata_strict(true);
+/// generate_metadata([{ page: 0, widht: 1000, height: 1400, tiles: [] }], 512);
+/// // Error: Invalid page info at pages[0]: unknown field "widht" (expected one of page, width, ...)
+/// ```
+#[wasm_bindgen]
+pub fn set_metadata_strict(strict: bool) {
+    settings::Settings::update_global(|settings| settings.strict_metadata = Some(strict));
+}
+
+/// metadataのバージョンを固定する（JavaScriptから呼び出し可能）
+///
+/// 既定ではmetadataの `version` は生成した時刻（`Date.now()`）になる。テストやCIのビルドで
+/// 同じ入力から同じmetadata.jsonを出力したい場合に固定する。`undefined` で現在時刻に戻す
+///
+/// # Errors
+/// 負の数・整数でない値の場合
+///
+/// # Example (JavaScript)
+/// ```js
+/// set_metadata_version(1700000000000);
+/// expect(generate_metadata(pages, 512)).toMatchSnapshot();
+/// set_metadata_version(undefined);
+/// ```
+#[wasm_bindgen]
+pub fn set_metadata_version(version: Option<f64>) -> Result<(), JsValue> {
+    let version = match version {
+        Some(version) if !version.is_finite() || version < 0.0 || version.fract() != 0.0 => {
+            return Err(PamphletError::InvalidArgument(format!(
+                "Invalid metadata version: {}",
+                version
+            ))
+            .into());
+        }
+        version => version.map(|version| version as u64),
+    };
+    settings::Settings::update_global(|settings| settings.metadata_version = version);
+    Ok(())
+}
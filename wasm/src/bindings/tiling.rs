@@ -0,0 +1,962 @@
+//! 画像のタイル化（`tile_image` などの関数と `TilerContext`・`Tiler`）
+
+use js_sys::{Array, Uint8ClampedArray};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "zip")]
+use super::documents::archive_with;
+#[cfg(feature = "svg")]
+use super::documents::svg_with;
+#[cfg(feature = "pdf")]
+use super::documents::tile_pdf_page;
+use super::metadata::{metadata_object, metadata_string, read_metadata};
+use crate::error::PamphletError;
+use crate::*;
+
+/// タイルサイズを確かめ、WebP品質を1-100に収める
+///
+/// タイルサイズが2の累乗でない場合は、ズームのレベルごとのタイルと境界が揃わないため
+/// コンソールに警告を出す（タイル化はそのまま行う）
+///
+/// # Errors
+/// タイルサイズが16-4096の範囲外の場合（`invalid_tile_size`）
+pub(super) fn tile_options(tile_size: u32, quality: Option<f32>) -> Result<Option<f32>, JsValue> {
+    tiler::validate_tile_size(tile_size)?;
+    if !tile_size.is_power_of_two() {
+        log::warn!("tile_size {} is not a power of two", tile_size);
+    }
+    Ok(tiler::clamp_quality(quality))
+}
+
+/// JavaScriptのタイル化の設定を読み込む（`undefined`・`null` は既定値）
+pub(super) fn read_tile_options(options: JsValue) -> Result<tiler::TileOptions, JsValue> {
+    let options: Option<tiler::TileOptions> = serde_wasm_bindgen::from_value(options)?;
+    Ok(options.unwrap_or_default())
+}
+
+/// 設定を `tile_options` で確かめ、画像をデコードしてタイル化する
+pub(super) fn tile_with_options(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    options: tiler::TileOptions,
+) -> Result<JsTileResult, JsValue> {
+    let options = tiler::TileOptions {
+        quality: tile_options(options.tile_size, options.quality)?,
+        ..options
+    };
+    let result = context.tile_image_with(image_data, &options)?;
+
+    Ok(JsTileResult::from(result))
+}
+
+/// 画像をタイル化する（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ（JPEG/PNG等）
+/// * `tile_size` - タイルサイズ（ピクセル、例: 512）
+/// * `quality` - WebP品質（1-100、省略時80）
+///
+/// # Returns
+/// タイル化結果（JsTileResult）
+///
+/// # Example (JavaScript)
+/// ```js
+/// import init, { tile_image } from './pkg/tile_wasm.js';
+///
+/// await init();
+///
+/// const imageData = new Uint8Array([...]); // 画像ファイルのバイナリ
+/// const result = tile_image(imageData, 512, 80);
+///
+/// console.log(`Width: ${result.width}, Height: ${result.height}`);
+/// console.log(`Tile count: ${result.tile_count()}`);
+///
+/// for (let i = 0; i < result.tile_count(); i++) {
+///   const tileData = result.get_tile_data(i);
+///   // tileData: Uint8Array (WebP形式)
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn tile_image(
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    tile_with_options(
+        &mut tiler::TilerContext::new(),
+        image_data,
+        tiler::TileOptions::new(tile_size, quality),
+    )
+}
+
+/// 設定オブジェクトを指定して画像をタイル化する（JavaScriptから呼び出し可能）
+///
+/// `options` は `{ tile_size?, quality?, format?, padding? }`（省略した項目は既定値）。
+/// `format` は `"webp"` のみ、`padding` は端のタイルをタイルサイズまで透明で埋める
+/// `"transparent"`（既定）か、画像の端までの大きさにする `"none"`。
+/// `tile_image(data, 512, 80)` は `tile_image_with_options(data, { tile_size: 512, quality: 80 })` と同じ
+///
+/// # Example (JavaScript)
+/// ```js
+/// const result = tile_image_with_options(imageData, { tile_size: 256, padding: 'none' });
+/// ```
+#[wasm_bindgen]
+pub fn tile_image_with_options(
+    image_data: &[u8],
+    options: JsValue,
+) -> Result<JsTileResult, JsValue> {
+    tile_with_options(
+        &mut tiler::TilerContext::new(),
+        image_data,
+        read_tile_options(options)?,
+    )
+}
+
+/// タイルの合間にイベントループへ戻りながら画像をタイル化する（JavaScriptから呼び出し可能）
+///
+/// `tile_image` と同じ結果を返すPromise。メインスレッドから呼んでも、数十ミリ秒ごとに
+/// `setTimeout` で処理を譲るため、描画や入力の処理が止まり続けない。
+/// 全体の処理時間は少し延びるため、ワーカーで処理する場合は `tile_image` を使う
+///
+/// # Example (JavaScript)
+/// ```js
+/// const result = await tile_image_async(imageData, 512, 80);
+/// ```
+#[wasm_bindgen]
+pub async fn tile_image_async(
+    image_data: Vec<u8>,
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let options = tiler::TileOptions::new(tile_size, quality);
+    tile_with_options_async(image_data, options, settings::Settings::default()).await
+}
+
+/// 設定オブジェクトを指定し、タイルの合間にイベントループへ戻りながら画像をタイル化する
+///
+/// `tile_image_with_options` と同じ結果を返すPromise（`options` も同じ）
+#[wasm_bindgen]
+pub async fn tile_image_with_options_async(
+    image_data: Vec<u8>,
+    options: JsValue,
+) -> Result<JsTileResult, JsValue> {
+    let options = read_tile_options(options)?;
+    tile_with_options_async(image_data, options, settings::Settings::default()).await
+}
+
+/// 設定を検証し、タイルの合間にイベントループへ戻りながらタイル化する
+async fn tile_with_options_async(
+    image_data: Vec<u8>,
+    options: tiler::TileOptions,
+    settings: settings::Settings,
+) -> Result<JsTileResult, JsValue> {
+    let options = tiler::TileOptions {
+        quality: tile_options(options.tile_size, options.quality)?,
+        ..options
+    };
+    let result = tiler::tile_image_yielding(&image_data, &options, &settings, |_, _| {
+        yield_to_event_loop()
+    })
+    .await?;
+
+    Ok(JsTileResult::from(result))
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+/// イベントループに戻る（`setTimeout(0)` の後に再開する）
+///
+/// `Promise` の解決（マイクロタスク）では描画が挟まらないため、タスクとして再開する
+async fn yield_to_event_loop() {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        set_timeout(&resolve, 0);
+    });
+    // setTimeoutで解決するだけのPromiseは失敗しない
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// ページをまたいで作業用バッファを使い回すタイル化（JavaScriptから呼び出し可能）
+///
+/// 100ページを超えるパンフレットを1つのセッションで処理するとき、ページごとの
+/// 数MBの確保とWASMのメモリの拡張を繰り返さないようにする。結果は `tile_image` と同じ
+///
+/// # Example (JavaScript)
+/// ```js
+/// const context = new TilerContext();
+/// for (const file of files) {
+///   const result = context.tile_image(new Uint8Array(await file.arrayBuffer()), 512, 80);
+///   await upload(result.into_tiles());
+/// }
+/// context.free();
+/// ```
+#[wasm_bindgen(js_name = TilerContext)]
+pub struct JsTilerContext {
+    inner: tiler::TilerContext,
+}
+
+#[wasm_bindgen(js_class = TilerContext)]
+impl JsTilerContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsTilerContext {
+        JsTilerContext {
+            inner: tiler::TilerContext::new(),
+        }
+    }
+
+    /// 画像をタイル化する（`tile_image` と同じ）
+    pub fn tile_image(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        tile_with_options(
+            &mut self.inner,
+            image_data,
+            tiler::TileOptions::new(tile_size, quality),
+        )
+    }
+
+    /// 設定オブジェクトを指定して画像をタイル化する（`tile_image_with_options` と同じ）
+    pub fn tile_image_with_options(
+        &mut self,
+        image_data: &[u8],
+        options: JsValue,
+    ) -> Result<JsTileResult, JsValue> {
+        tile_with_options(&mut self.inner, image_data, read_tile_options(options)?)
+    }
+
+    /// 保持している作業用バッファのバイト数
+    pub fn retained_bytes(&self) -> usize {
+        self.inner.retained_bytes()
+    }
+
+    /// 作業用バッファを解放する（以降のタイル化で必要になれば確保し直す）
+    pub fn dispose(&mut self) {
+        self.inner.release();
+    }
+}
+
+impl Default for JsTilerContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TILER_SETTINGS_TYPESCRIPT: &str = r#"
+/** `Tiler` のインスタンスの設定（省略した項目はモジュール全体の設定に従う） */
+export interface TilerSettings {
+  decode_limits?: { max_dimension?: number; max_pixels?: number } | null;
+  allowed_formats?: string[] | null;
+  timing?: boolean | null;
+  metadata_version?: number | null;
+  strict_metadata?: boolean | null;
+  log_level?: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace' | null;
+}
+"#;
+
+/// 設定と作業用バッファを持つタイル化のインスタンス（JavaScriptから呼び出し可能）
+///
+/// `set_decode_limits`・`set_allowed_formats`・`set_timing_enabled`・`set_metadata_version`・
+/// `set_metadata_strict`・`set_log_level` はモジュール全体の設定を変えるため、1つのWASMの
+/// インスタンスで複数の処理を並行すると互いに影響する。`Tiler` は設定をインスタンスに持ち、
+/// 処理に引数で渡すため、並行する処理が互いの設定を使うことがない。
+/// 設定していない項目は、処理を始めたときのモジュール全体の設定に従う
+///
+/// # Example (JavaScript)
+/// ```js
+/// const uploads = new Tiler({ allowed_formats: ['jpeg', 'png'], decode_limits: { max_pixels: 50_000_000 } });
+/// const archive = new Tiler({ metadata_version: 1700000000000, timing: true });
+/// const [a, b] = await Promise.all([
+///   uploads.tile_image_async(upload, 512, 80),
+///   archive.tile_image_async(scan, 512, 90),
+/// ]);
+/// ```
+#[wasm_bindgen(js_name = Tiler)]
+pub struct JsTiler {
+    context: tiler::TilerContext,
+}
+
+#[wasm_bindgen(js_class = Tiler)]
+impl JsTiler {
+    /// # Errors
+    /// 設定に知らない項目や不正な値がある場合
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        #[wasm_bindgen(unchecked_param_type = "TilerSettings")] settings: Option<JsValue>,
+    ) -> Result<JsTiler, JsValue> {
+        let settings = settings.unwrap_or(JsValue::UNDEFINED);
+        Ok(JsTiler {
+            context: tiler::TilerContext::with_settings(read_tiler_settings(settings)?),
+        })
+    }
+
+    /// インスタンスの設定（設定していない項目は `null`）
+    #[wasm_bindgen(getter, unchecked_return_type = "TilerSettings")]
+    pub fn settings(&self) -> Result<JsValue, JsValue> {
+        Ok(self
+            .context
+            .settings()
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+    }
+
+    /// 設定を置き換える（処理中の非同期のタイル化は元の設定のまま続ける）
+    pub fn set_settings(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "TilerSettings")] settings: JsValue,
+    ) -> Result<(), JsValue> {
+        self.context.set_settings(read_tiler_settings(settings)?);
+        Ok(())
+    }
+
+    /// 画像をタイル化する（`tile_image` と同じ）
+    pub fn tile_image(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        let options = tiler::TileOptions::new(tile_size, quality);
+        tile_with_options(&mut self.context, image_data, options)
+    }
+
+    /// 設定オブジェクトを指定して画像をタイル化する（`tile_image_with_options` と同じ）
+    pub fn tile_image_with_options(
+        &mut self,
+        image_data: &[u8],
+        options: JsValue,
+    ) -> Result<JsTileResult, JsValue> {
+        let options = read_tile_options(options)?;
+        tile_with_options(&mut self.context, image_data, options)
+    }
+
+    /// タイルの合間にイベントループへ戻りながら画像をタイル化する（`tile_image_async` と同じ）
+    #[wasm_bindgen(unchecked_return_type = "Promise<JsTileResult>")]
+    pub fn tile_image_async(
+        &self,
+        image_data: Vec<u8>,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> js_sys::Promise {
+        let options = tiler::TileOptions::new(tile_size, quality);
+        let settings = self.context.settings().clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            tile_with_options_async(image_data, options, settings)
+                .await
+                .map(JsValue::from)
+        })
+    }
+
+    /// 設定オブジェクトを指定し、タイルの合間にイベントループへ戻りながら画像をタイル化する
+    #[wasm_bindgen(unchecked_return_type = "Promise<JsTileResult>")]
+    pub fn tile_image_with_options_async(
+        &self,
+        image_data: Vec<u8>,
+        options: JsValue,
+    ) -> Result<js_sys::Promise, JsValue> {
+        let options = read_tile_options(options)?;
+        let settings = self.context.settings().clone();
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            tile_with_options_async(image_data, options, settings)
+                .await
+                .map(JsValue::from)
+        }))
+    }
+
+    /// 前処理を適用してからタイル化する（`tile_image_preprocessed` と同じ）
+    pub fn tile_image_preprocessed(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        options: JsValue,
+    ) -> Result<JsTileResult, JsValue> {
+        preprocessed_with(&mut self.context, image_data, tile_size, quality, options)
+    }
+
+    /// 見開きを分割してタイル化する（`tile_image_split` と同じ）
+    pub fn tile_image_split(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        options: JsValue,
+    ) -> Result<Array, JsValue> {
+        split_with(&mut self.context, image_data, tile_size, quality, options)
+    }
+
+    /// 画像を縮小してタイル化する（`tile_image_level` と同じ）
+    pub fn tile_image_level(
+        &mut self,
+        image_data: &[u8],
+        level: u32,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        level_with(&mut self.context, image_data, level, tile_size, quality)
+    }
+
+    /// 画像をページ単位でタイル化する（`tile_image_pages` と同じ）
+    pub fn tile_image_pages(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        animation: JsValue,
+    ) -> Result<Array, JsValue> {
+        pages_with(&mut self.context, image_data, tile_size, quality, animation)
+    }
+
+    /// JPEGを帯に分けてデコードしながらタイル化する（`tile_image_banded` と同じ）
+    pub fn tile_image_banded(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        banded_with(&mut self.context, image_data, tile_size, quality)
+    }
+
+    /// エンコードを後回しにしてタイル化する（`tile_image_lazy` と同じ）
+    ///
+    /// 後からエンコードするタイルも、この呼び出しのときのインスタンスの設定に従う
+    pub fn tile_image_lazy(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        lazy_with(&mut self.context, image_data, tile_size, quality)
+    }
+
+    /// デコード済みのRGBA画素をタイル化する（`tile_rgba` と同じ）
+    pub fn tile_rgba(
+        &mut self,
+        pixels: Vec<u8>,
+        width: u32,
+        height: u32,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        rgba_with(&mut self.context, pixels, width, height, tile_size, quality)
+    }
+
+    /// SVGをラスタライズしてタイル化する（`tile_svg` と同じ、`svg` フィーチャーが必要）
+    #[cfg(feature = "svg")]
+    pub fn tile_svg(
+        &mut self,
+        svg_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        dpi: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        svg_with(&mut self.context, svg_data, tile_size, quality, dpi)
+    }
+
+    /// PDFのページをラスタライズしてタイル化する（`PdfDocument.tile_page` と同じ、`pdf` フィーチャーが必要）
+    #[cfg(feature = "pdf")]
+    pub fn tile_pdf_page(
+        &mut self,
+        document: &JsPdfDocument,
+        index: usize,
+        dpi: Option<f32>,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<JsTileResult, JsValue> {
+        tile_pdf_page(&mut self.context, document, index, dpi, tile_size, quality)
+    }
+
+    /// ページ画像をまとめたZIPをタイル化する（`tile_archive` と同じ、`zip` フィーチャーが必要）
+    ///
+    /// metadataの `version` もインスタンスの設定に従う
+    #[cfg(feature = "zip")]
+    pub fn tile_archive(&mut self, zip_data: &[u8], options: JsValue) -> Result<JsValue, JsValue> {
+        archive_with(&mut self.context, zip_data, options)
+    }
+
+    /// 超解像などの処理を差し込んでタイル化する（`tile_image_with_hook` と同じ）
+    #[wasm_bindgen(unchecked_return_type = "Promise<JsTileResult>")]
+    pub fn tile_image_with_hook(
+        &self,
+        image_data: Vec<u8>,
+        tile_size: u32,
+        quality: Option<f32>,
+        hook: js_sys::Function,
+        mode: JsValue,
+    ) -> js_sys::Promise {
+        let settings = self.context.settings().clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            hooked_with(settings, image_data, tile_size, quality, hook, mode)
+                .await
+                .map(JsValue::from)
+        })
+    }
+
+    /// metadata.jsonを生成する（`generate_metadata` と同じ）
+    pub fn generate_metadata(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "PageInfo[] | string")] pages: JsValue,
+        tile_size: u32,
+        #[wasm_bindgen(unchecked_param_type = "TocEntry[] | string")] toc: Option<JsValue>,
+    ) -> Result<String, JsValue> {
+        metadata_string(pages, tile_size, toc, &self.context.settings().resolve())
+    }
+
+    /// metadataをオブジェクトとして生成する（`generate_metadata_value` と同じ）
+    #[wasm_bindgen(unchecked_return_type = "PamphletMetadata")]
+    pub fn generate_metadata_value(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "PageInfo[] | string")] pages: JsValue,
+        tile_size: u32,
+        #[wasm_bindgen(unchecked_param_type = "TocEntry[] | string")] toc: Option<JsValue>,
+    ) -> Result<JsValue, JsValue> {
+        let settings = self.context.settings().resolve();
+        let metadata = read_metadata(pages, tile_size, toc, &settings)?;
+        metadata_object(&metadata)
+    }
+
+    /// 保持している作業用バッファのバイト数
+    pub fn retained_bytes(&self) -> usize {
+        self.context.retained_bytes()
+    }
+
+    /// 作業用バッファを解放する（以降のタイル化で必要になれば確保し直す）
+    pub fn dispose(&mut self) {
+        self.context.release();
+    }
+}
+
+/// `Tiler` の設定を読み込む（`undefined`・`null` はすべてモジュール全体の設定に従う）
+fn read_tiler_settings(settings: JsValue) -> Result<settings::Settings, JsValue> {
+    let settings: Option<settings::Settings> = serde_wasm_bindgen::from_value(settings)
+        .map_err(|e| PamphletError::InvalidArgument(format!("Invalid tiler settings: {}", e)))?;
+    Ok(settings.unwrap_or_default())
+}
+
+/// デコード済みのRGBA画素をタイル化する（JavaScriptから呼び出し可能）
+///
+/// WASM版が対応していない形式（AVIFなど）を、ブラウザでデコードしてからタイル化するために使う
+///
+/// # Arguments
+/// * `pixels` - RGBA画素（`ImageData.data` など、`width * height * 4` バイト）
+/// * `width` / `height` - 画像のサイズ（ピクセル）
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+#[wasm_bindgen]
+pub fn tile_rgba(
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    rgba_with(&mut context, pixels, width, height, tile_size, quality)
+}
+
+/// `context` の設定でRGBA画素をタイル化する
+fn rgba_with(
+    context: &mut tiler::TilerContext,
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let image = image::RgbaImage::from_raw(width, height, pixels).ok_or_else(|| {
+        PamphletError::InvalidArgument("Pixel data does not match width * height * 4".to_string())
+    })?;
+    let result =
+        context.tile_decoded(&image::DynamicImage::ImageRgba8(image), tile_size, quality)?;
+
+    Ok(JsTileResult::from(result))
+}
+
+/// WebPのエンコードを後回しにして画像をタイル化する（JavaScriptから呼び出し可能）
+///
+/// 配置とタイルの画素のハッシュだけをすぐに計算し、タイルのデータは `get_tile_data`
+/// などで取得したとき（または `encode_all` を呼んだとき）にエンコードする。
+/// エンコードしたタイルのハッシュは `tile_image` と同じWebPのデータのハッシュに置き換わる
+/// （`tiles` は作り直す）。`page_info` はすべてのタイルをエンコードするまでエラー。
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const result = tile_image_lazy(imageData, 512, 80);
+/// result.tiles.forEach((tile, i) => {
+///   if (tile.hash !== previousPixelHashes[i]) {
+///     const data = result.take_tile_data(i); // 変わったタイルだけエンコード
+///     upload(result.tile_hash(i), data);
+///   }
+/// });
+/// result.encode_all();
+/// pages.push(result.page_info(page));
+/// result.free();
+/// ```
+#[wasm_bindgen]
+pub fn tile_image_lazy(
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    lazy_with(&mut context, image_data, tile_size, quality)
+}
+
+/// `context` の設定でエンコードを後回しにしてタイル化する
+fn lazy_with(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let (result, lazy) = context.tile_image_lazy(image_data, tile_size, quality)?;
+
+    Ok(JsTileResult {
+        lazy: Some(lazy),
+        ..result.into()
+    })
+}
+
+/// JPEGを帯に分けてデコードしながらタイル化する（JavaScriptから呼び出し可能）
+///
+/// タイル1行分の高さの帯だけをデコードし、縦長のページのメモリの使用量の最大値を抑える。
+/// ベースラインでないJPEGやJPEG以外の画像は `tile_image` と同じ。
+/// デコーダーが異なるため、タイルのハッシュは `tile_image` と一致しない。
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+#[wasm_bindgen]
+pub fn tile_image_banded(
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    banded_with(&mut context, image_data, tile_size, quality)
+}
+
+/// `context` の設定でJPEGを帯に分けてデコードしながらタイル化する
+fn banded_with(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let result = context.tile_image_banded(image_data, tile_size, quality)?;
+
+    Ok(JsTileResult::from(result))
+}
+
+/// 画像を縮小してタイル化する（JavaScriptから呼び出し可能）
+///
+/// ズームレベルごとのピラミッドを作るために使う。JPEG 2000の入力は必要な解像度までしか復号しない。
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ
+/// * `level` - 縮小レベル（0 = 原寸、1 = 1/2、2 = 1/4 ...）
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+#[wasm_bindgen]
+pub fn tile_image_level(
+    image_data: &[u8],
+    level: u32,
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    level_with(&mut context, image_data, level, tile_size, quality)
+}
+
+/// `context` の設定で画像を縮小してタイル化する
+fn level_with(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    level: u32,
+    tile_size: u32,
+    quality: Option<f32>,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let result = context.tile_image_level(image_data, level, tile_size, quality)?;
+
+    Ok(JsTileResult::from(result))
+}
+
+/// 画像をページ単位でタイル化する（JavaScriptから呼び出し可能）
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+/// * `animation` - アニメーションの扱い（`"first_frame"`（デフォルト）または `"pages"`）
+///
+/// # Returns
+/// ページごとのタイル化結果（JsTileResult）の配列
+#[wasm_bindgen]
+pub fn tile_image_pages(
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    animation: JsValue,
+) -> Result<Array, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    pages_with(&mut context, image_data, tile_size, quality, animation)
+}
+
+/// `context` の設定で画像をページ単位でタイル化する
+fn pages_with(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    animation: JsValue,
+) -> Result<Array, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let animation: Option<decode::AnimationMode> = serde_wasm_bindgen::from_value(animation)?;
+    let results = context.tile_image_pages(
+        image_data,
+        tile_size,
+        quality,
+        animation.unwrap_or_default(),
+    )?;
+
+    Ok(results
+        .into_iter()
+        .map(|result| JsValue::from(JsTileResult::from(result)))
+        .collect())
+}
+
+/// 画像に前処理を適用してからタイル化する（JavaScriptから呼び出し可能）
+///
+/// スキャンの補正をアップロード前の別ツールに頼らず、タイル化の中で行う
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+/// * `options` - 前処理の設定（`{ rotate?: { degrees?, background? }, deskew?: { max_angle? }, auto_crop?: { black_threshold?, white_threshold?, padding? }, upscale?: { target_dpi?, source_dpi?, max_scale? }, denoise?: { method?, radius?, sigma_color? }, descreen?: { radius?, restore? }, white_balance?: { method?, percentile? }, auto_contrast?: { clip? }, gamma?, unsharp?: { amount?, radius?, threshold? }, binarize?: { window?, k?, r? }, remove_background?: { tolerance?, feather? }, quantize?: { colors?, dither? } }`）
+///
+/// # Example (JavaScript)
+/// ```js
+/// // 5度までの傾きを検出して水平にし、黒い縁と余白を切り抜く
+/// const result = tile_image_preprocessed(imageData, 512, 80, {
+///   deskew: { max_angle: 5 },
+///   auto_crop: {},
+/// });
+/// page.crop = result.crop;
+/// ```
+#[wasm_bindgen]
+pub fn tile_image_preprocessed(
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    options: JsValue,
+) -> Result<JsTileResult, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    preprocessed_with(&mut context, image_data, tile_size, quality, options)
+}
+
+/// `context` の設定で前処理を適用してからタイル化する
+fn preprocessed_with(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    options: JsValue,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let options: Option<preprocess::PreprocessOptions> = serde_wasm_bindgen::from_value(options)?;
+    let (result, geometry) = context.tile_image_preprocessed(
+        image_data,
+        tile_size,
+        quality,
+        &options.unwrap_or_default(),
+    )?;
+
+    Ok(JsTileResult {
+        dpi: geometry.dpi.map(|dpi| dpi as f32),
+        crop: geometry.crop,
+        scale: geometry.scale,
+        ..result.into()
+    })
+}
+
+/// 見開きのスキャンを左右のページに分割してタイル化する（JavaScriptから呼び出し可能）
+///
+/// 中央付近の列の明るさからのど（影または隙間）を検出して分割する。見開きでない画像
+/// （縦横比が `split.min_aspect` 未満）は1ページとして返す。
+///
+/// # Arguments
+/// * `image_data` - 元画像のバイトデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100、省略時80）
+/// * `options` - 前処理の設定（`tile_image_preprocessed` と同じ。`split: { min_aspect?, search_range?, remove_shadow? }` で分割を設定する）
+///
+/// # Returns
+/// ページ（左 → 右）ごとのタイル化結果（JsTileResult）の配列。分割したページの `crop` は元の画像での範囲
+///
+/// # Example (JavaScript)
+/// ```js
+/// const [left, right] = tile_image_split(imageData, 512, 80, {
+///   split: { remove_shadow: true },
+/// });
+/// ```
+#[wasm_bindgen]
+pub fn tile_image_split(
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    options: JsValue,
+) -> Result<Array, JsValue> {
+    let mut context = tiler::TilerContext::new();
+    split_with(&mut context, image_data, tile_size, quality, options)
+}
+
+/// `context` の設定で見開きを分割してタイル化する
+fn split_with(
+    context: &mut tiler::TilerContext,
+    image_data: &[u8],
+    tile_size: u32,
+    quality: Option<f32>,
+    options: JsValue,
+) -> Result<Array, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let options: Option<preprocess::PreprocessOptions> = serde_wasm_bindgen::from_value(options)?;
+    let mut options = options.unwrap_or_default();
+    options.split.get_or_insert_with(Default::default);
+    let pages = context.tile_image_split(image_data, tile_size, quality, &options)?;
+
+    Ok(pages
+        .into_iter()
+        .map(|(result, geometry)| {
+            JsValue::from(JsTileResult {
+                dpi: geometry.dpi.map(|dpi| dpi as f32),
+                crop: geometry.crop,
+                scale: geometry.scale,
+                ..result.into()
+            })
+        })
+        .collect())
+}
+
+/// 超解像などの処理をJavaScriptから差し込んでタイル化する
+///
+/// `hook` は `{ data: Uint8ClampedArray, width, height }`（タイル単位の場合は `x`, `y` も）を受け取り、
+/// 同じ形の結果（`ImageData` でもよい）か、そのPromiseを返す。`undefined` / `null` を返すと処理しない。
+///
+/// # Arguments
+/// * `mode` - `"page"`（ページ全体を1回、デフォルト）または `"tile"`（タイルごと）
+#[wasm_bindgen]
+pub async fn tile_image_with_hook(
+    image_data: Vec<u8>,
+    tile_size: u32,
+    quality: Option<f32>,
+    hook: js_sys::Function,
+    mode: JsValue,
+) -> Result<JsTileResult, JsValue> {
+    let settings = settings::Settings::default();
+    hooked_with(settings, image_data, tile_size, quality, hook, mode).await
+}
+
+/// `settings` に従い、フックを挟んでタイル化する
+///
+/// フックを待つ間にインスタンスの設定が変わっても影響しないよう、設定は値で受け取る
+async fn hooked_with(
+    settings: settings::Settings,
+    image_data: Vec<u8>,
+    tile_size: u32,
+    quality: Option<f32>,
+    hook: js_sys::Function,
+    mode: JsValue,
+) -> Result<JsTileResult, JsValue> {
+    let quality = tile_options(tile_size, quality)?;
+    let mode: Option<hook::HookMode> = serde_wasm_bindgen::from_value(mode)?;
+    let mut context = tiler::TilerContext::with_settings(settings);
+    let image = decode::decode_image(&image_data, &context.settings().resolve())?;
+
+    let (result, scale) = match mode.unwrap_or_default() {
+        hook::HookMode::Page => {
+            let input = image.to_rgba8();
+            let processed = call_hook(&hook, &input, None).await?.unwrap_or(input);
+            hook::tile_hooked_page(&image, processed, tile_size, quality, &mut context)?
+        }
+        hook::HookMode::Tile => {
+            let mut tiler = hook::HookedTiler::new(image, tile_size, quality)?;
+            while let Some((tx, ty, tile)) = tiler.next_input()? {
+                let processed = call_hook(&hook, &tile, Some((tx, ty)))
+                    .await?
+                    .unwrap_or(tile);
+                tiler.push(tx, ty, processed)?;
+            }
+            tiler.finish()
+        }
+    };
+
+    Ok(JsTileResult {
+        scale,
+        ..result.into()
+    })
+}
+
+/// フックに画像を渡して結果を待つ（`undefined` / `null` が返った場合は `None`）
+async fn call_hook(
+    hook: &js_sys::Function,
+    image: &image::RgbaImage,
+    tile: Option<(u32, u32)>,
+) -> Result<Option<image::RgbaImage>, JsValue> {
+    let input = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &input,
+        &"data".into(),
+        &Uint8ClampedArray::from(image.as_raw().as_slice()),
+    )?;
+    js_sys::Reflect::set(&input, &"width".into(), &image.width().into())?;
+    js_sys::Reflect::set(&input, &"height".into(), &image.height().into())?;
+    if let Some((x, y)) = tile {
+        js_sys::Reflect::set(&input, &"x".into(), &x.into())?;
+        js_sys::Reflect::set(&input, &"y".into(), &y.into())?;
+    }
+
+    let output = hook.call1(&JsValue::NULL, &input)?;
+    let output = wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&output)).await?;
+    if output.is_undefined() || output.is_null() {
+        return Ok(None);
+    }
+
+    let dimension = |key: &str| -> Result<u32, JsValue> {
+        js_sys::Reflect::get(&output, &key.into())?
+            .as_f64()
+            .filter(|value| *value >= 0.0 && value.fract() == 0.0)
+            .map(|value| value as u32)
+            .ok_or_else(|| {
+                PamphletError::InvalidArgument(format!("Hook result has no valid {}", key)).into()
+            })
+    };
+    let (width, height) = (dimension("width")?, dimension("height")?);
+    let data = Uint8ClampedArray::new(&js_sys::Reflect::get(&output, &"data".into())?).to_vec();
+    let length = data.len();
+    image::RgbaImage::from_raw(width, height, data)
+        .map(Some)
+        .ok_or_else(|| {
+            PamphletError::InvalidArgument(format!(
+                "Hook result has {} bytes of data for {}x{} pixels",
+                length, width, height
+            ))
+            .into()
+        })
+}
@@ -0,0 +1,762 @@
+//! ビューアの表示の計算（表示するタイル・先読み・キャッシュ・ズームなど）
+
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::error::PamphletError;
+use crate::*;
+
+/// 表示倍率に応じたピラミッドレベルを選択（JavaScriptから呼び出し可能）
+///
+/// 必要解像度（`viewport_scale * device_pixel_ratio`）以上のレベルのうち最小のものを選ぶ。
+/// `current_level` を渡すと、境界付近でレベルが頻繁に切り替わらないようヒステリシスを適用する。
+///
+/// # Arguments
+/// * `viewport_scale` - 表示倍率（1.0 = 原寸）
+/// * `device_pixel_ratio` - `window.devicePixelRatio`
+/// * `levels` - 各レベルの縮尺（原寸に対する割合、例: [1.0, 0.5, 0.25]）
+/// * `current_level` - 現在表示中のレベル（省略可）
+///
+/// # Returns
+/// 選択されたレベルのインデックス（`levels` が空の場合は `undefined`）
+///
+/// # Example (JavaScript)
+/// ```js
+/// let level = select_level(scale, window.devicePixelRatio, new Float32Array([1, 0.5, 0.25]));
+/// // ピンチ中は現在のレベルを渡してちらつきを防ぐ
+/// level = select_level(scale, window.devicePixelRatio, levels, level);
+/// ```
+#[wasm_bindgen]
+pub fn select_level(
+    viewport_scale: f32,
+    device_pixel_ratio: f32,
+    levels: &[f32],
+    current_level: Option<usize>,
+) -> Option<usize> {
+    zoom::select_level(
+        viewport_scale,
+        device_pixel_ratio,
+        levels,
+        current_level,
+        zoom::DEFAULT_HYSTERESIS,
+    )
+}
+
+/// viewportに表示されるタイルを取得（JavaScriptから呼び出し可能）
+///
+/// viewportと交差するタイルだけを、viewport中心に近い順（center-out）に返す。
+/// 返り値の順にフェッチすれば、画面中央から先に描画される。
+///
+/// # Arguments
+/// * `page` - ページ情報（`{ page, width, height, tiles }`）
+/// * `tile_size` - タイルサイズ
+/// * `viewport` - 表示領域（`{ x, y, width, height }`、画面座標でページ左上が原点）
+/// * `zoom` - 表示倍率（1.0 = 原寸）
+///
+/// # Returns
+/// タイル情報（`{ x, y, hash }`）の配列
+///
+/// # Example (JavaScript)
+/// ```js
+/// const viewport = { x: panX, y: panY, width: canvas.width, height: canvas.height };
+/// for (const tile of tiles_for_viewport(metadata.pages[0], metadata.tile_size, viewport, scale)) {
+///   loader.loadTile(tile, 10);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn tiles_for_viewport(
+    #[wasm_bindgen(unchecked_param_type = "PageInfo")] page: JsValue,
+    tile_size: u32,
+    viewport: JsValue,
+    zoom: f64,
+) -> Result<Array, JsValue> {
+    let page: PageInfo = serde_wasm_bindgen::from_value(page)?;
+    let viewport: geometry::Rect = serde_wasm_bindgen::from_value(viewport)?;
+
+    viewport::tiles_for_viewport(&page, tile_size, &viewport, zoom)
+        .into_iter()
+        .map(|tile| {
+            let js_tile = JsTileInfo {
+                x: tile.x,
+                y: tile.y,
+                hash: tile.hash.clone(),
+            };
+            serde_wasm_bindgen::to_value(&js_tile).map_err(JsValue::from)
+        })
+        .collect()
+}
+
+/// パン速度に基づくタイルのプリフェッチ予測（JavaScriptから利用可能）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const prefetcher = new Prefetcher();
+///
+/// // パンのたびにviewportを記録
+/// prefetcher.record(performance.now(), { x: panX, y: panY, width, height });
+///
+/// // 300ms先までに必要になりそうなタイルを最大8件取得
+/// for (const { hash, confidence } of prefetcher.predict(page, tileSize, scale, 300, 8)) {
+///   loader.loadTile({ x, y, hash }, confidence);
+/// }
+/// ```
+#[wasm_bindgen(js_name = Prefetcher)]
+pub struct JsPrefetcher {
+    inner: prefetch::Prefetcher,
+}
+
+#[wasm_bindgen(js_class = Prefetcher)]
+impl JsPrefetcher {
+    /// プリフェッチャーを作成
+    ///
+    /// # Arguments
+    /// * `max_samples` - 速度計算に使うviewport履歴の件数（省略時8）
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_samples: Option<usize>) -> JsPrefetcher {
+        JsPrefetcher {
+            inner: prefetch::Prefetcher::new(max_samples.unwrap_or(prefetch::DEFAULT_MAX_SAMPLES)),
+        }
+    }
+
+    /// viewportの位置を記録
+    ///
+    /// # Arguments
+    /// * `time` - 時刻（ミリ秒、`performance.now()`）
+    /// * `viewport` - 表示領域（`{ x, y, width, height }`、画面座標）
+    pub fn record(&mut self, time: f64, viewport: JsValue) -> Result<(), JsValue> {
+        let viewport: geometry::Rect = serde_wasm_bindgen::from_value(viewport)?;
+        self.inner.record(time, viewport);
+        Ok(())
+    }
+
+    /// 履歴をクリア（ページ切り替え時など）
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    /// 次に必要になるタイルを確信度の高い順に予測
+    ///
+    /// # Returns
+    /// `{ x, y, hash, confidence }` の配列（現在表示中のタイルは含まない）
+    pub fn predict(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "PageInfo")] page: JsValue,
+        tile_size: u32,
+        zoom: f64,
+        lookahead_ms: f64,
+        limit: usize,
+    ) -> Result<Array, JsValue> {
+        let page: PageInfo = serde_wasm_bindgen::from_value(page)?;
+
+        self.inner
+            .predict(&page, tile_size, zoom, lookahead_ms, limit)
+            .iter()
+            .map(|prediction| serde_wasm_bindgen::to_value(prediction).map_err(JsValue::from))
+            .collect()
+    }
+}
+
+/// WASMメモリ上で管理するLRUタイルキャッシュ（JavaScriptから利用可能）
+///
+/// デコード済みタイルをWASM側に保持し、ズーム操作のたびに
+/// 大量のArrayBufferを生成・破棄することによるGC負荷を避ける。
+///
+/// # Example (JavaScript)
+/// ```js
+/// const cache = new TileCache(64 * 1024 * 1024); // 64MB
+///
+/// const evicted = cache.insert(tile.hash, pixels);
+/// evicted.forEach((hash) => textures.delete(hash));
+///
+/// // 表示中のタイルは破棄されないようにする
+/// cache.set_pinned(visibleTiles.map((t) => t.hash));
+///
+/// // WASMのメモリを直接指すビュー（Uint8Array | undefined）。WASMの関数を呼ぶ前に使い切る
+/// const data = cache.get(tile.hash);
+/// if (data) uploadTexture(tile.hash, data);
+/// ```
+#[wasm_bindgen(js_name = TileCache)]
+pub struct JsTileCache {
+    inner: tile_cache::TileCache,
+}
+
+#[wasm_bindgen(js_class = TileCache)]
+impl JsTileCache {
+    /// キャッシュを作成
+    ///
+    /// # Arguments
+    /// * `budget_bytes` - 保持するデータの合計バイト数の上限
+    #[wasm_bindgen(constructor)]
+    pub fn new(budget_bytes: usize) -> JsTileCache {
+        JsTileCache {
+            inner: tile_cache::TileCache::new(budget_bytes),
+        }
+    }
+
+    /// データを追加し、上限を守るために破棄したキーを返す
+    ///
+    /// 上限を単独で超えるデータはキャッシュされない
+    pub fn insert(&mut self, key: String, data: &[u8]) -> Vec<String> {
+        self.inner.insert(key, data.to_vec())
+    }
+
+    /// データを、コピーせずにWASMのメモリを直接指すビューとして取得（存在しない場合は `undefined`）
+    ///
+    /// ビューは次のいずれかが起きると無効になる（中身が壊れるか、長さ0になる）ため、
+    /// テクスチャへのアップロードや `slice()` での複製など、同期的に使い切ること。
+    /// - WASMの関数を呼び出したとき（メモリが拡張されると元の `ArrayBuffer` が切り離される）
+    /// - このキーを `insert`・`evict` したとき、`set_pinned`・`set_budget` で破棄されたとき
+    /// - `clear()`・`free()` したとき
+    pub fn get(&mut self, key: &str) -> Option<Uint8Array> {
+        // SAFETY: ビューの寿命はJavaScript側の責任（上記の無効になる条件を参照）
+        self.inner
+            .get(key)
+            .map(|data| unsafe { Uint8Array::view(data) })
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.inner.contains(key)
+    }
+
+    /// 指定したキーを破棄（ピン留めされていても破棄する）
+    pub fn evict(&mut self, key: &str) -> bool {
+        self.inner.remove(key)
+    }
+
+    /// ピン留めするキーを置き換え、上限超過により破棄したキーを返す
+    pub fn set_pinned(&mut self, keys: Vec<String>) -> Vec<String> {
+        self.inner.set_pinned(keys)
+    }
+
+    /// 上限を変更し、破棄したキーを返す
+    pub fn set_budget(&mut self, budget_bytes: usize) -> Vec<String> {
+        self.inner.set_budget(budget_bytes)
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// エントリ数
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn used_bytes(&self) -> usize {
+        self.inner.used_bytes()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn budget(&self) -> usize {
+        self.inner.budget()
+    }
+}
+
+/// タイル取得のスケジューラ（JavaScriptから利用可能）
+///
+/// 取得待ちのタイルを優先度（visible > adjacent > prefetch）とviewport中心からの
+/// 距離で並べ、viewportの移動に合わせて並べ替える。
+///
+/// # Example (JavaScript)
+/// ```js
+/// const scheduler = new FetchScheduler(metadata.tile_size);
+/// scheduler.set_viewport({ x: panX, y: panY, width, height }, scale);
+///
+/// page.tiles.forEach((tile) => scheduler.push(tile, 'prefetch'));
+///
+/// let request;
+/// while (running < 6 && (request = scheduler.pop_next())) {
+///   fetchTile(request.hash);
+/// }
+///
+/// // パン後
+/// scheduler.set_viewport(newViewport, scale);
+/// scheduler.cancel_offscreen().forEach((hash) => abortControllers.get(hash)?.abort());
+/// ```
+#[wasm_bindgen(js_name = FetchScheduler)]
+pub struct JsFetchScheduler {
+    inner: scheduler::FetchScheduler,
+}
+
+#[wasm_bindgen(js_class = FetchScheduler)]
+impl JsFetchScheduler {
+    #[wasm_bindgen(constructor)]
+    pub fn new(tile_size: u32) -> JsFetchScheduler {
+        JsFetchScheduler {
+            inner: scheduler::FetchScheduler::new(tile_size),
+        }
+    }
+
+    /// viewportを更新し、取得待ちのタイルを再優先度付け
+    ///
+    /// # Arguments
+    /// * `viewport` - 表示領域（`{ x, y, width, height }`、画面座標）
+    /// * `zoom` - 表示倍率
+    pub fn set_viewport(&mut self, viewport: JsValue, zoom: f64) -> Result<(), JsValue> {
+        let viewport: geometry::Rect = serde_wasm_bindgen::from_value(viewport)?;
+        self.inner.set_viewport(viewport, zoom);
+        Ok(())
+    }
+
+    /// タイルを取得待ちに追加
+    ///
+    /// # Arguments
+    /// * `tile` - タイル情報（`{ x, y, hash }`）
+    /// * `priority` - `"visible"` | `"adjacent"` | `"prefetch"`
+    ///   （viewport設定後は位置関係から再計算される）
+    pub fn push(&mut self, tile: JsValue, priority: JsValue) -> Result<(), JsValue> {
+        let tile: TileMetadata = serde_wasm_bindgen::from_value(tile)?;
+        let priority: scheduler::FetchPriority = serde_wasm_bindgen::from_value(priority)?;
+        self.inner.push(&tile, priority);
+        Ok(())
+    }
+
+    /// 次に取得すべきタイル（`{ x, y, hash, priority }`）を取り出す
+    ///
+    /// 取得待ちがない場合は `undefined`
+    pub fn pop_next(&mut self) -> Result<JsValue, JsValue> {
+        match self.inner.pop_next() {
+            Some(request) => Ok(serde_wasm_bindgen::to_value(&request)?),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    /// viewportにも隣接範囲にも入らなくなったタイルを取り消し、そのハッシュを返す
+    ///
+    /// `"prefetch"` として追加したタイルは取り消さない
+    pub fn cancel_offscreen(&mut self) -> Vec<String> {
+        self.inner.cancel_offscreen()
+    }
+
+    /// 指定したタイルを取り消す
+    pub fn cancel(&mut self, hash: &str) -> bool {
+        self.inner.cancel(hash)
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// 取得待ちのタイル数
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+/// ホットスポット・注釈のヒットテスト用空間インデックス（JavaScriptから利用可能）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const index = new SpatialIndex([
+///   { id: 'product-1', page: 0, x: 120, y: 300, width: 200, height: 150 },
+///   { id: 'product-2', page: 0, x: 400, y: 300, width: 200, height: 150 },
+/// ]);
+///
+/// // タップ位置（ページ座標）にあるホットスポットID（上に重なっているものが先頭）
+/// const [hit] = index.hit_test(0, pageX, pageY);
+/// ```
+#[wasm_bindgen(js_name = SpatialIndex)]
+pub struct JsSpatialIndex {
+    inner: spatial::SpatialIndex,
+}
+
+#[wasm_bindgen(js_class = SpatialIndex)]
+impl JsSpatialIndex {
+    /// インデックスを構築
+    ///
+    /// # Arguments
+    /// * `items` - `{ id, page, x, y, width, height }` の配列（座標はページの原寸ピクセル）
+    #[wasm_bindgen(constructor)]
+    pub fn new(items: JsValue) -> Result<JsSpatialIndex, JsValue> {
+        let items: Vec<spatial::SpatialItem> = serde_wasm_bindgen::from_value(items)?;
+        Ok(JsSpatialIndex {
+            inner: spatial::SpatialIndex::new(items),
+        })
+    }
+
+    /// 指定した点を含むアイテムのIDを返す（上に重なっているものが先頭）
+    pub fn hit_test(&self, page: u32, x: f64, y: f64) -> Vec<String> {
+        self.inner
+            .hit_test(page, geometry::Point::new(x, y))
+            .into_iter()
+            .map(|item| item.id.clone())
+            .collect()
+    }
+
+    /// 指定した矩形（`{ x, y, width, height }`）と交差するアイテムのIDを登録順に返す
+    pub fn query_rect(&self, page: u32, rect: JsValue) -> Result<Vec<String>, JsValue> {
+        let rect: geometry::Rect = serde_wasm_bindgen::from_value(rect)?;
+        Ok(self
+            .inner
+            .query_rect(page, &rect)
+            .into_iter()
+            .map(|item| item.id.clone())
+            .collect())
+    }
+
+    /// 登録されているアイテム数
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+/// 指定した点を中心にズームするアニメーション（JavaScriptから利用可能）
+///
+/// # Example (JavaScript)
+/// ```js
+/// // ダブルタップ位置を中心に2倍へズーム
+/// const animation = new ZoomAnimation(
+///   { x: panX, y: panY, zoom: scale }, scale * 2, tapX, tapY, performance.now(), 300,
+/// );
+///
+/// function frame(now) {
+///   const { x, y, zoom } = animation.state_at(now);
+///   render(x, y, zoom);
+///   if (!animation.is_finished(now)) requestAnimationFrame(frame);
+/// }
+/// requestAnimationFrame(frame);
+/// ```
+#[wasm_bindgen(js_name = ZoomAnimation)]
+pub struct JsZoomAnimation {
+    inner: camera::ZoomAnimation,
+}
+
+#[wasm_bindgen(js_class = ZoomAnimation)]
+impl JsZoomAnimation {
+    /// ズームアニメーションを作成
+    ///
+    /// # Arguments
+    /// * `start` - 開始時のカメラ状態（`{ x, y, zoom }`、x/yは画面ピクセルのパン量）
+    /// * `end_zoom` - 終了時の倍率
+    /// * `focus_x`, `focus_y` - ズームの中心（画面座標、この点の下のページ座標は動かない）
+    /// * `start_time` - 開始時刻（ミリ秒）
+    /// * `duration` - アニメーション時間（ミリ秒）
+    /// * `easing` - `"linear"` | `"ease-out-cubic"` | `"ease-in-out-cubic"`（省略時 ease-in-out-cubic）
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        start: JsValue,
+        end_zoom: f64,
+        focus_x: f64,
+        focus_y: f64,
+        start_time: f64,
+        duration: f64,
+        easing: JsValue,
+    ) -> Result<JsZoomAnimation, JsValue> {
+        let start: camera::CameraState = serde_wasm_bindgen::from_value(start)?;
+        let easing: Option<camera::Easing> = serde_wasm_bindgen::from_value(easing)?;
+
+        Ok(JsZoomAnimation {
+            inner: camera::ZoomAnimation::new(
+                start,
+                end_zoom,
+                geometry::Point::new(focus_x, focus_y),
+                start_time,
+                duration,
+                easing.unwrap_or_default(),
+            ),
+        })
+    }
+
+    /// 指定時刻のカメラ状態（`{ x, y, zoom }`）
+    pub fn state_at(&self, time: f64) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.state_at(time))?)
+    }
+
+    /// 終了時のカメラ状態（`{ x, y, zoom }`）
+    pub fn end_state(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.end_state())?)
+    }
+
+    pub fn is_finished(&self, time: f64) -> bool {
+        self.inner.is_finished(time)
+    }
+}
+
+/// 摩擦で減速するフリング（JavaScriptから利用可能）
+///
+/// # Example (JavaScript)
+/// ```js
+/// // 指を離した時点の速度（px/ms）で慣性スクロール
+/// const fling = new FlingAnimation({ x: panX, y: panY, zoom: scale }, vx, vy, performance.now());
+/// ```
+#[wasm_bindgen(js_name = FlingAnimation)]
+pub struct JsFlingAnimation {
+    inner: camera::Fling,
+}
+
+#[wasm_bindgen(js_class = FlingAnimation)]
+impl JsFlingAnimation {
+    /// フリングを作成
+    ///
+    /// # Arguments
+    /// * `start` - 開始時のカメラ状態（`{ x, y, zoom }`）
+    /// * `velocity_x`, `velocity_y` - 初速（画面ピクセル/ミリ秒）
+    /// * `start_time` - 開始時刻（ミリ秒）
+    /// * `friction` - 減衰係数（1ミリ秒あたり、省略時0.005）
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        start: JsValue,
+        velocity_x: f64,
+        velocity_y: f64,
+        start_time: f64,
+        friction: Option<f64>,
+    ) -> Result<JsFlingAnimation, JsValue> {
+        let start: camera::CameraState = serde_wasm_bindgen::from_value(start)?;
+
+        Ok(JsFlingAnimation {
+            inner: camera::Fling::new(
+                start,
+                geometry::Point::new(velocity_x, velocity_y),
+                start_time,
+                friction.unwrap_or(camera::DEFAULT_FRICTION),
+            ),
+        })
+    }
+
+    /// 指定時刻のカメラ状態（`{ x, y, zoom }`）
+    pub fn state_at(&self, time: f64) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.state_at(time))?)
+    }
+
+    /// 停止時のカメラ状態（`{ x, y, zoom }`）
+    pub fn end_state(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.end_state())?)
+    }
+
+    /// 停止するまでの時間（ミリ秒）
+    #[wasm_bindgen(getter)]
+    pub fn duration(&self) -> f64 {
+        self.inner.duration()
+    }
+
+    pub fn is_finished(&self, time: f64) -> bool {
+        self.inner.is_finished(time)
+    }
+}
+
+/// 読者の注釈（マーカー・付箋・手書き）を管理するストア（JavaScriptから利用可能）
+///
+/// 注釈の座標はページの原寸ピクセル。IDは作成時に割り当てられ、編集・保存・復元しても変わらない。
+///
+/// # Example (JavaScript)
+/// ```js
+/// const saved = localStorage.getItem(key);
+/// const store = saved ? AnnotationStore.from_bytes(base64ToBytes(saved)) : new AnnotationStore();
+///
+/// const id = store.create(3, { type: 'highlight', rects: [{ x: 120, y: 80, width: 400, height: 24 }], color: '#ffeb3b' });
+/// store.update(id, { type: 'highlight', rects, color: '#81d4fa' });
+/// for (const annotation of store.for_page(3)) draw(annotation);
+///
+/// localStorage.setItem(key, bytesToBase64(store.to_bytes()));
+/// ```
+#[wasm_bindgen(js_name = AnnotationStore)]
+pub struct JsAnnotationStore {
+    inner: annotations::AnnotationStore,
+}
+
+#[wasm_bindgen(js_class = AnnotationStore)]
+impl JsAnnotationStore {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsAnnotationStore {
+        JsAnnotationStore {
+            inner: annotations::AnnotationStore::new(),
+        }
+    }
+
+    /// `to_bytes` で生成したバイト列から復元
+    pub fn from_bytes(bytes: &[u8]) -> Result<JsAnnotationStore, JsValue> {
+        let inner = annotations::AnnotationStore::from_bytes(bytes)
+            .map_err(PamphletError::InvalidArgument)?;
+        Ok(JsAnnotationStore { inner })
+    }
+
+    /// 注釈を作成
+    ///
+    /// # Arguments
+    /// * `page` - ページ番号
+    /// * `content` - 以下のいずれか
+    ///   - `{ type: 'highlight', rects: [{ x, y, width, height }], color }`
+    ///   - `{ type: 'note', x, y, text }`
+    ///   - `{ type: 'drawing', points: [{ x, y }], color, width }`
+    ///
+    /// # Returns
+    /// 割り当てたID
+    pub fn create(&mut self, page: u32, content: JsValue) -> Result<String, JsValue> {
+        let kind: annotations::AnnotationKind = serde_wasm_bindgen::from_value(content)?;
+        Ok(self.inner.create(page, kind))
+    }
+
+    /// 注釈の内容を置き換え（`content` は `create` と同じ形式）
+    pub fn update(&mut self, id: &str, content: JsValue) -> Result<(), JsValue> {
+        let kind: annotations::AnnotationKind = serde_wasm_bindgen::from_value(content)?;
+        self.inner
+            .update(id, kind)
+            .map_err(|e| PamphletError::NotFound(e).into())
+    }
+
+    /// 注釈を削除（存在しない場合は `false`）
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.inner.remove(id)
+    }
+
+    /// 注釈を取得（`{ id, page, type, ... }`、存在しない場合は `undefined`）
+    pub fn get(&self, id: &str) -> Result<JsValue, JsValue> {
+        match self.inner.get(id) {
+            Some(annotation) => Ok(serde_wasm_bindgen::to_value(annotation)?),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    /// ページの注釈（描画順）
+    pub fn for_page(&self, page: u32) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.for_page(page))?)
+    }
+
+    /// 全ての注釈（作成順）
+    pub fn all(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self.inner.all())?)
+    }
+
+    /// 保存用のバイト列に変換
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    /// 注釈の数
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl Default for JsAnnotationStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 表示状態を共有リンク用の短い文字列に変換
+///
+/// # Arguments
+/// * `page` - ページ番号
+/// * `x` - 表示中心のX座標（ページの原寸ピクセル）
+/// * `y` - 表示中心のY座標（ページの原寸ピクセル）
+/// * `zoom` - 表示倍率
+///
+/// # Example (JavaScript)
+/// ```js
+/// url.hash = encode_view_state(page, centerX, centerY, zoom);
+/// ```
+#[wasm_bindgen]
+pub fn encode_view_state(page: u32, x: f64, y: f64, zoom: f64) -> String {
+    view_state::encode(&view_state::ViewState { page, x, y, zoom })
+}
+
+/// `encode_view_state` で生成した文字列から表示状態を復元
+///
+/// 古いバージョンの形式で作られたリンクも復元できる
+///
+/// # Returns
+/// `{ page, x, y, zoom }`
+#[wasm_bindgen]
+pub fn decode_view_state(text: &str) -> Result<JsValue, JsValue> {
+    let state = view_state::decode(text).map_err(PamphletError::InvalidArgument)?;
+    Ok(serde_wasm_bindgen::to_value(&state)?)
+}
+
+/// ページ座標と画面座標の変換（JavaScriptから利用可能）
+///
+/// ピンチ・パン操作の計算をまとめ、画面座標 `s` とページ座標 `p` を `p = (s + (x, y)) / zoom` で対応付ける
+///
+/// # Example (JavaScript)
+/// ```js
+/// const view = new ViewTransform(0, 0, 1, 0.25, 8);
+///
+/// // ピンチ中（前回のイベントからの拡大率）
+/// view.apply_pinch(midX, midY, distance / lastDistance);
+/// view.apply_pan(midX - lastMidX, midY - lastMidY);
+///
+/// ctx.setTransform(...view.matrix());
+/// const { x, y } = view.screen_to_page(event.offsetX, event.offsetY);
+/// ```
+#[wasm_bindgen(js_name = ViewTransform)]
+pub struct JsViewTransform {
+    inner: transform::ViewTransform,
+}
+
+#[wasm_bindgen(js_class = ViewTransform)]
+impl JsViewTransform {
+    /// # Arguments
+    /// * `x` / `y` - パン量（画面ピクセル）
+    /// * `zoom` - 表示倍率
+    /// * `min_zoom` - 最小倍率（デフォルト: 0.1）
+    /// * `max_zoom` - 最大倍率（デフォルト: 10.0）
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        x: f64,
+        y: f64,
+        zoom: f64,
+        min_zoom: Option<f64>,
+        max_zoom: Option<f64>,
+    ) -> JsViewTransform {
+        JsViewTransform {
+            inner: transform::ViewTransform::new(
+                camera::CameraState { x, y, zoom },
+                min_zoom.unwrap_or(0.1),
+                max_zoom.unwrap_or(10.0),
+            ),
+        }
+    }
+
+    /// 画面座標 → ページ座標（`{ x, y }`）
+    pub fn screen_to_page(&self, x: f64, y: f64) -> Result<JsValue, JsValue> {
+        let point = self.inner.screen_to_page(geometry::Point::new(x, y));
+        Ok(serde_wasm_bindgen::to_value(&point)?)
+    }
+
+    /// ページ座標 → 画面座標（`{ x, y }`）
+    pub fn page_to_screen(&self, x: f64, y: f64) -> Result<JsValue, JsValue> {
+        let point = self.inner.page_to_screen(geometry::Point::new(x, y));
+        Ok(serde_wasm_bindgen::to_value(&point)?)
+    }
+
+    /// ピンチ操作を適用（`center_x`, `center_y` の下の点を固定して `scale` 倍）
+    pub fn apply_pinch(&mut self, center_x: f64, center_y: f64, scale: f64) {
+        self.inner
+            .apply_pinch(geometry::Point::new(center_x, center_y), scale);
+    }
+
+    /// パン操作を適用（指の移動量、画面ピクセル）
+    pub fn apply_pan(&mut self, dx: f64, dy: f64) {
+        self.inner.apply_pan(dx, dy);
+    }
+
+    /// 状態を置き換え（アニメーションの結果を反映する場合など）
+    pub fn set(&mut self, x: f64, y: f64, zoom: f64) {
+        self.inner.set_state(camera::CameraState { x, y, zoom });
+    }
+
+    /// ページ座標 → 画面座標の変換行列 `[a, b, c, d, e, f]`（`setTransform` の引数順）
+    pub fn matrix(&self) -> Vec<f64> {
+        self.inner.matrix().to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn x(&self) -> f64 {
+        self.inner.state().x
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn y(&self) -> f64 {
+        self.inner.state().y
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn zoom(&self) -> f64 {
+        self.inner.state().zoom
+    }
+}
@@ -0,0 +1,208 @@
+//! Web Workerとの間のメッセージと、複数のワーカーでのタイル化の調整
+
+use js_sys::{Array, Uint8Array};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use super::metadata::{metadata_json, read_toc};
+use super::tiling::tile_options;
+use crate::error::PamphletError;
+use crate::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const WORKER_TYPESCRIPT: &str = include_str!("../worker.d.ts");
+
+/// 専用ワーカーで受け取ったメッセージを処理する（JavaScriptから呼び出し可能）
+///
+/// `message` は `{ type: "tile_request", id, data, options?, page? }`。処理の合間に
+/// `{ type: "progress", id, done, total }` を、最後に `{ type: "tile_ready", id, tile_size, page, tiles, warnings }`
+/// か `{ type: "error", id, error }` を `post(message, transfer)` で送る。`transfer` は
+/// `tile_ready` のタイルのデータの `ArrayBuffer` の配列で、`postMessage` にそのまま渡すとコピーせずに移せる
+///
+/// # Example (JavaScript)
+/// ```js
+/// // worker.js
+/// import init, { handle_worker_message } from './pkg/tile_wasm.js';
+///
+/// const ready = init();
+/// self.onmessage = async ({ data }) => {
+///   await ready;
+///   await handle_worker_message(data, (message, transfer) => self.postMessage(message, transfer));
+/// };
+/// ```
+#[wasm_bindgen]
+pub async fn handle_worker_message(
+    #[wasm_bindgen(unchecked_param_type = "WorkerCommand")] message: JsValue,
+    #[wasm_bindgen(
+        unchecked_param_type = "(message: WorkerEvent, transfer: ArrayBuffer[]) => void"
+    )]
+    post: js_sys::Function,
+) -> Result<(), JsValue> {
+    let event = match serde_wasm_bindgen::from_value::<worker::WorkerCommand>(message) {
+        Ok(command) => {
+            let mut posted = Ok(());
+            let event = worker::respond(command, |event| {
+                if posted.is_ok() {
+                    posted = post_worker_event(&post, &event);
+                }
+            })
+            .await;
+            posted?;
+            event
+        }
+        Err(error) => worker::WorkerEvent::Error {
+            id: None,
+            error: PamphletError::InvalidArgument(format!("Invalid worker message: {}", error)),
+        },
+    };
+    post_worker_event(&post, &event)
+}
+
+/// ワーカーのメッセージを `post(message, transfer)` に渡す（タイルのデータのバッファは移す）
+fn post_worker_event(post: &js_sys::Function, event: &worker::WorkerEvent) -> Result<(), JsValue> {
+    let message = event.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+    let transfer = Array::new();
+    if let worker::WorkerEvent::TileReady { .. } = event {
+        let tiles: Array = js_sys::Reflect::get(&message, &"tiles".into())?.unchecked_into();
+        for tile in tiles.iter() {
+            let data: Uint8Array = js_sys::Reflect::get(&tile, &"data".into())?.unchecked_into();
+            transfer.push(&data.buffer());
+        }
+    }
+    post.call2(&JsValue::NULL, &message, &transfer)?;
+    Ok(())
+}
+
+/// 複数ページのタイル化をWeb Workerに分けるコーディネーター（JavaScriptから利用可能）
+///
+/// ページを連続したまとまりに分けて空いたワーカーに割り当て、ワーカーから返った
+/// ページ情報をページ順に並べてmetadataを組み立てる。ワーカーとのやり取りは次のメッセージで行う。
+/// - コーディネーター → ワーカー: `{ type: "tile_pages", chunk, pages, tile_size, quality? }`
+/// - ワーカー → コーディネーター: `{ type: "chunk_done", chunk, pages: [ページ情報] }`
+///   または `{ type: "chunk_failed", chunk, error }`（他のワーカーで試し直す）
+///
+/// # Example (JavaScript)
+/// ```js
+/// const coordinator = new PipelineCoordinator(files.length, workers.length, 512, 80);
+/// workers.forEach((worker, id) => {
+///   const dispatch = () => {
+///     const message = coordinator.next_message(id);
+///     if (message) worker.postMessage(message);
+///     else if (coordinator.is_complete()) resolve(coordinator.metadata());
+///   };
+///   worker.onmessage = ({ data }) => {
+///     coordinator.handle_message(id, data);
+///     dispatch();
+///   };
+///   dispatch();
+/// });
+///
+/// // ワーカー側
+/// onmessage = ({ data }) => {
+///   const pages = data.pages.map((page) => {
+///     const result = tile_image(files[page], data.tile_size, data.quality);
+///     upload(result);
+///     return result.page_info(page);
+///   });
+///   postMessage({ type: 'chunk_done', chunk: data.chunk, pages });
+/// };
+/// ```
+#[wasm_bindgen(js_name = PipelineCoordinator)]
+pub struct JsPipelineCoordinator {
+    inner: pipeline::PipelineCoordinator,
+}
+
+#[wasm_bindgen(js_class = PipelineCoordinator)]
+impl JsPipelineCoordinator {
+    /// # Arguments
+    /// * `page_count` - ページ数
+    /// * `workers` - ワーカーの数
+    /// * `tile_size` - タイルサイズ（ピクセル）
+    /// * `quality` - WebP品質（1-100、省略時80）
+    /// * `chunk_size` - まとまりのページ数（省略時はワーカーあたり4つのまとまりになる大きさ）
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        page_count: u32,
+        workers: u32,
+        tile_size: u32,
+        quality: Option<f32>,
+        chunk_size: Option<u32>,
+    ) -> Result<JsPipelineCoordinator, JsValue> {
+        let quality = tile_options(tile_size, quality)?;
+        Ok(JsPipelineCoordinator {
+            inner: pipeline::PipelineCoordinator::new(
+                page_count, workers, tile_size, quality, chunk_size,
+            ),
+        })
+    }
+
+    /// ワーカー `worker` に送る次のメッセージ
+    ///
+    /// 割り当て済みのまとまりが終わっていない、残りがない、処理を諦めた場合は `undefined`
+    pub fn next_message(&mut self, worker: u32) -> Result<JsValue, JsValue> {
+        match self.inner.next_request(worker) {
+            Some(request) => Ok(serde_wasm_bindgen::to_value(&request)?),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    /// ワーカー `worker` からのメッセージを受け取る
+    ///
+    /// # Errors
+    /// メッセージの形式が正しくない場合、割り当てていないまとまりの結果の場合、
+    /// まとまりが3回失敗して処理を諦めた場合
+    pub fn handle_message(&mut self, worker: u32, message: JsValue) -> Result<(), JsValue> {
+        let response: pipeline::WorkerResponse = serde_wasm_bindgen::from_value(message)?;
+        self.inner
+            .handle_response(worker, response)
+            .map_err(|e| PamphletError::InvalidArgument(e).into())
+    }
+
+    /// ワーカーが落ちた（`terminate` した・`error` イベントが起きた）ときに、割り当てていたまとまりを戻す
+    pub fn worker_lost(&mut self, worker: u32) {
+        self.inner.worker_lost(worker);
+    }
+
+    /// すべてのページの結果が揃ったか
+    pub fn is_complete(&self) -> bool {
+        self.inner.is_complete()
+    }
+
+    /// 結果が揃ったページ数（進捗の表示に使う）
+    #[wasm_bindgen(getter)]
+    pub fn completed_pages(&self) -> usize {
+        self.inner.completed_pages()
+    }
+
+    /// 処理を諦めた理由（諦めていなければ `undefined`）
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.inner.error().map(str::to_string)
+    }
+
+    /// ページ順に並べたページ情報の配列
+    #[wasm_bindgen(unchecked_return_type = "PageInfo[]")]
+    pub fn pages(&self) -> Result<JsValue, JsValue> {
+        let pages = self.inner.pages().map_err(PamphletError::NotFound)?;
+        Ok(serde_wasm_bindgen::to_value(&pages)?)
+    }
+
+    /// ページ順に並べたmetadata.jsonの文字列（`generate_metadata` と同じ形式）
+    ///
+    /// # Arguments
+    /// * `toc` - 目次の配列、またはそのJSON文字列（省略可）
+    pub fn metadata(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "TocEntry[] | string")] toc: Option<JsValue>,
+    ) -> Result<String, JsValue> {
+        let pages = self.inner.pages().map_err(PamphletError::NotFound)?;
+        let toc = read_toc(toc, &settings::Settings::global())?;
+        let settings = settings::Settings::global();
+        Ok(metadata_json(
+            &pages,
+            self.inner.tile_size(),
+            &toc,
+            &settings,
+        )?)
+    }
+}
@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+use crate::tiler;
+
+/// ビルドの情報（フロントエンドが機能の有無を試さずに判別するため）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VersionInfo {
+    /// クレートのバージョン
+    pub version: &'static str,
+    /// ビルドしたコミット（gitのない環境でビルドした場合は `None`）
+    pub git_hash: Option<&'static str>,
+    /// 有効なCargoのフィーチャー
+    pub features: Vec<&'static str>,
+    /// WASMのSIMD（`simd128`）を有効にしてビルドしたか
+    pub simd: bool,
+    /// WASMのスレッド（`atomics`）を有効にしてビルドしたか
+    pub threads: bool,
+    /// グローバルアロケータ（`system`・`dlmalloc`・`talc`・`wee_alloc`）
+    pub allocator: &'static str,
+    /// タイル化できる入力の形式
+    pub input_formats: Vec<&'static str>,
+    /// タイルの出力の形式
+    pub output_formats: Vec<&'static str>,
+    /// タイルのWebPを品質を指定して非可逆でエンコードできるか
+    pub lossy_webp: bool,
+}
+
+/// このビルドの情報
+pub fn version_info(allocator: &'static str) -> VersionInfo {
+    let features = [
+        ("wasm", cfg!(feature = "wasm")),
+        (
+            "console_error_panic_hook",
+            cfg!(feature = "console_error_panic_hook"),
+        ),
+        ("offscreen", cfg!(feature = "offscreen")),
+        ("pdf", cfg!(feature = "pdf")),
+        ("svg", cfg!(feature = "svg")),
+        ("heic", cfg!(feature = "heic")),
+        ("jxl", cfg!(feature = "jxl")),
+        ("jpeg2000", cfg!(feature = "jpeg2000")),
+        ("psd", cfg!(feature = "psd")),
+        ("avif", cfg!(feature = "avif")),
+        ("raw", cfg!(feature = "raw")),
+        ("zip", cfg!(feature = "zip")),
+        ("dlmalloc", cfg!(feature = "dlmalloc")),
+        ("talc", cfg!(feature = "talc")),
+        ("wee_alloc", cfg!(feature = "wee_alloc")),
+        ("libwebp", cfg!(feature = "libwebp")),
+        ("serve", cfg!(feature = "serve")),
+        ("parallel", cfg!(feature = "parallel")),
+        ("toml", cfg!(feature = "toml")),
+    ];
+    // フィーチャーが有効でもターゲットによって使えない形式（AVIFはネイティブのみ）は除く
+    let input_formats = [
+        ("jpeg", true),
+        ("png", true),
+        ("webp", true),
+        ("gif", true),
+        ("heic", cfg!(feature = "heic")),
+        (
+            "avif",
+            cfg!(all(feature = "avif", not(target_arch = "wasm32"))),
+        ),
+        ("jxl", cfg!(feature = "jxl")),
+        ("jpeg2000", cfg!(feature = "jpeg2000")),
+        ("psd", cfg!(feature = "psd")),
+        ("raw", cfg!(feature = "raw")),
+        ("pdf", cfg!(feature = "pdf")),
+        ("svg", cfg!(feature = "svg")),
+        ("zip", cfg!(feature = "zip")),
+    ];
+    let enabled = |list: &[(&'static str, bool)]| {
+        list.iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    };
+
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("TILE_WASM_GIT_HASH"),
+        features: enabled(&features),
+        simd: cfg!(target_feature = "simd128"),
+        threads: cfg!(target_feature = "atomics"),
+        allocator,
+        input_formats: enabled(&input_formats),
+        output_formats: vec!["webp"],
+        lossy_webp: tiler::LOSSY_ENCODER,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_info() {
+        let info = version_info("system");
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            info.features.contains(&"console_error_panic_hook"),
+            cfg!(feature = "console_error_panic_hook")
+        );
+        assert_eq!(info.input_formats[..4], ["jpeg", "png", "webp", "gif"]);
+        assert_eq!(info.input_formats.contains(&"pdf"), cfg!(feature = "pdf"));
+        assert_eq!(info.output_formats, ["webp"]);
+
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(value["allocator"], "system");
+        assert!(value["git_hash"].is_string() || value["git_hash"].is_null());
+    }
+}
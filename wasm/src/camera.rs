@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Point;
+
+/// フリングの減衰係数のデフォルト（1ミリ秒あたり、速度は約140msで半減）
+pub const DEFAULT_FRICTION: f64 = 0.005;
+
+/// フリングを停止とみなす速度（画面ピクセル/ミリ秒）
+pub const MIN_FLING_VELOCITY: f64 = 0.01;
+
+/// カメラの状態
+///
+/// 画面座標 `s` とページ座標 `p` は `p = (s + (x, y)) / zoom` の関係にある
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraState {
+    /// パン量（画面ピクセル）
+    pub x: f64,
+    pub y: f64,
+    /// 表示倍率
+    pub zoom: f64,
+}
+
+/// イージング関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    EaseOutCubic,
+    #[default]
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// 進捗 `t`（0.0〜1.0）にイージングを適用する
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// 指定した点を中心にズームするアニメーション
+///
+/// 倍率は対数空間で補間し（拡大・縮小どちらも同じ速さに見える）、
+/// 各フレームで `focus` の下にあるページ座標が動かないようにパン量を求める。
+#[derive(Debug, Clone)]
+pub struct ZoomAnimation {
+    start: CameraState,
+    end_zoom: f64,
+    /// ズームの中心（画面座標）
+    focus: Point,
+    /// `focus` の下にあるページ座標
+    anchor: Point,
+    start_time: f64,
+    duration: f64,
+    easing: Easing,
+}
+
+impl ZoomAnimation {
+    pub fn new(
+        start: CameraState,
+        end_zoom: f64,
+        focus: Point,
+        start_time: f64,
+        duration: f64,
+        easing: Easing,
+    ) -> Self {
+        let anchor = Point::new(
+            (focus.x + start.x) / start.zoom,
+            (focus.y + start.y) / start.zoom,
+        );
+
+        Self {
+            start,
+            end_zoom,
+            focus,
+            anchor,
+            start_time,
+            duration,
+            easing,
+        }
+    }
+
+    /// 指定時刻のカメラ状態
+    pub fn state_at(&self, time: f64) -> CameraState {
+        let progress = if self.duration > 0.0 {
+            (time - self.start_time) / self.duration
+        } else {
+            1.0
+        };
+        let t = self.easing.apply(progress);
+
+        let zoom = self.start.zoom * (self.end_zoom / self.start.zoom).powf(t);
+        self.state_with_zoom(zoom)
+    }
+
+    /// 終了時のカメラ状態
+    pub fn end_state(&self) -> CameraState {
+        self.state_with_zoom(self.end_zoom)
+    }
+
+    pub fn is_finished(&self, time: f64) -> bool {
+        time >= self.start_time + self.duration
+    }
+
+    fn state_with_zoom(&self, zoom: f64) -> CameraState {
+        CameraState {
+            x: self.anchor.x * zoom - self.focus.x,
+            y: self.anchor.y * zoom - self.focus.y,
+            zoom,
+        }
+    }
+}
+
+/// 摩擦で減速するフリング（慣性スクロール）
+///
+/// 速度は `v(t) = v0 * e^(-friction * t)` で指数的に減衰する
+#[derive(Debug, Clone)]
+pub struct Fling {
+    start: CameraState,
+    /// 初速（画面ピクセル/ミリ秒）
+    velocity: Point,
+    start_time: f64,
+    friction: f64,
+}
+
+impl Fling {
+    pub fn new(start: CameraState, velocity: Point, start_time: f64, friction: f64) -> Self {
+        Self {
+            start,
+            velocity,
+            start_time,
+            friction: friction.max(f64::EPSILON),
+        }
+    }
+
+    /// 速度が `MIN_FLING_VELOCITY` を下回るまでの時間（ミリ秒）
+    pub fn duration(&self) -> f64 {
+        let speed = self.velocity.x.hypot(self.velocity.y);
+        if speed <= MIN_FLING_VELOCITY {
+            return 0.0;
+        }
+        (speed / MIN_FLING_VELOCITY).ln() / self.friction
+    }
+
+    /// 指定時刻のカメラ状態
+    pub fn state_at(&self, time: f64) -> CameraState {
+        let t = (time - self.start_time).clamp(0.0, self.duration());
+        let distance = (1.0 - (-self.friction * t).exp()) / self.friction;
+
+        CameraState {
+            x: self.start.x + self.velocity.x * distance,
+            y: self.start.y + self.velocity.y * distance,
+            zoom: self.start.zoom,
+        }
+    }
+
+    /// 停止時のカメラ状態
+    pub fn end_state(&self) -> CameraState {
+        self.state_at(self.start_time + self.duration())
+    }
+
+    pub fn is_finished(&self, time: f64) -> bool {
+        time >= self.start_time + self.duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_zoom_keeps_focus_point_fixed() {
+        let start = CameraState {
+            x: 100.0,
+            y: 50.0,
+            zoom: 1.0,
+        };
+        let focus = Point::new(200.0, 150.0);
+        let animation = ZoomAnimation::new(start, 4.0, focus, 1000.0, 300.0, Easing::default());
+
+        assert_eq!(animation.state_at(1000.0), start);
+        assert_close(animation.state_at(1150.0).zoom, 2.0);
+
+        // 途中・終了時ともに、focusの下のページ座標(300, 200)が動かない
+        for time in [1075.0, 1150.0, 1300.0, 2000.0] {
+            let state = animation.state_at(time);
+            assert_close((focus.x + state.x) / state.zoom, 300.0);
+            assert_close((focus.y + state.y) / state.zoom, 200.0);
+        }
+        assert_eq!(animation.state_at(1300.0), animation.end_state());
+        assert!(animation.is_finished(1300.0));
+    }
+
+    #[test]
+    fn test_fling_decelerates_and_stops() {
+        let start = CameraState {
+            x: 0.0,
+            y: 0.0,
+            zoom: 1.0,
+        };
+        let fling = Fling::new(start, Point::new(2.0, 0.0), 0.0, DEFAULT_FRICTION);
+
+        let early = fling.state_at(50.0).x;
+        let late = fling.state_at(100.0).x - early;
+        assert!(early > late, "速度は減衰する");
+
+        // 停止位置は v0 / friction に漸近する
+        let end = fling.end_state();
+        assert!(end.x < 2.0 / DEFAULT_FRICTION);
+        assert_close(
+            end.x,
+            2.0 / DEFAULT_FRICTION - MIN_FLING_VELOCITY / DEFAULT_FRICTION,
+        );
+        assert_eq!(fling.state_at(fling.duration() + 500.0), end);
+        assert_eq!(end.y, 0.0);
+    }
+}
@@ -0,0 +1,185 @@
+use std::cmp::Reverse;
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::Serialize;
+
+use crate::decode;
+use crate::error::PamphletError;
+use crate::settings::Settings;
+
+/// 色の抽出に使う縮小画像の長辺（ピクセル）
+const SAMPLE_SIZE: u32 = 128;
+
+/// 抽出する色の数の上限
+const MAX_COLORS: usize = 16;
+
+/// 画像の代表色
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DominantColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// `#rrggbb` 形式
+    pub hex: String,
+    /// 画像に占める割合（0-1）
+    pub ratio: f64,
+}
+
+/// 画像をデコードして代表色を求める
+///
+/// # Errors
+/// 画像のデコードに失敗した場合
+pub fn dominant_colors(image_data: &[u8], k: usize) -> Result<Vec<DominantColor>, PamphletError> {
+    let image = decode::decode_image(image_data, &Settings::global())?;
+    Ok(image_dominant_colors(&image, k))
+}
+
+/// デコード済みの画像の代表色を、多い順に最大 `k` 色求める（メディアンカット）
+///
+/// 縮小した画像の、完全に透明でない画素を使う
+pub fn image_dominant_colors(image: &DynamicImage, k: usize) -> Vec<DominantColor> {
+    let sample = if image.width().max(image.height()) > SAMPLE_SIZE {
+        image.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+    } else {
+        image.clone()
+    };
+    let pixels: Vec<[u8; 3]> = sample
+        .to_rgba8()
+        .pixels()
+        .filter(|pixel| pixel[3] > 0)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    let total = pixels.len() as f64;
+    let mut colors = median_cut(pixels, k.clamp(1, MAX_COLORS));
+    colors.sort_by_key(|(_, count)| Reverse(*count));
+    colors
+        .into_iter()
+        .map(|([r, g, b], count)| DominantColor {
+            r,
+            g,
+            b,
+            hex: format!("#{:02x}{:02x}{:02x}", r, g, b),
+            ratio: count as f64 / total,
+        })
+        .collect()
+}
+
+/// メディアンカットで画素を最大 `k` 個の箱に分け、箱ごとの平均色と画素数を返す
+///
+/// 色の範囲が最も広い箱を、その範囲が最も広いチャンネルの中央値で分けることを繰り返す
+pub fn median_cut(pixels: Vec<[u8; 3]>, k: usize) -> Vec<([u8; 3], usize)> {
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < k {
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (index, channel, range)
+            })
+            .filter(|(_, _, range)| *range > 0)
+            .max_by_key(|(_, _, range)| *range)
+        else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = pixels.split_off(split_point(&pixels, channel));
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    boxes
+        .into_iter()
+        .map(|pixels| {
+            let mut sums = [0u64; 3];
+            for pixel in &pixels {
+                for (sum, value) in sums.iter_mut().zip(pixel) {
+                    *sum += *value as u64;
+                }
+            }
+            let count = pixels.len();
+            let mean = sums.map(|sum| ((sum as f64 / count as f64).round()) as u8);
+            (mean, count)
+        })
+        .collect()
+}
+
+/// `channel` の値で並べた画素を分ける位置
+///
+/// 中央値に近い、値が変わる境目で分ける（同じ色が2つの箱に分かれないようにする）
+fn split_point(sorted: &[[u8; 3]], channel: usize) -> usize {
+    let middle = sorted.len() / 2;
+    let value = sorted[middle][channel];
+    let start = sorted.partition_point(|pixel| pixel[channel] < value);
+    let end = sorted.partition_point(|pixel| pixel[channel] <= value);
+    if start == 0 || (end < sorted.len() && end - middle < middle - start) {
+        end
+    } else {
+        start
+    }
+}
+
+/// 値の範囲が最も広いチャンネルとその範囲
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+                (min.min(pixel[channel]), max.max(pixel[channel]))
+            });
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_dominant_colors() {
+        // 3/4が紺、1/4が黄色の表紙
+        let cover = RgbaImage::from_fn(40, 40, |x, _| {
+            if x < 30 {
+                Rgba([20, 30, 90, 255])
+            } else {
+                Rgba([240, 200, 40, 255])
+            }
+        });
+        let colors = image_dominant_colors(&DynamicImage::ImageRgba8(cover), 3);
+
+        // 色が2色しかなければ2色だけ返す
+        assert_eq!(colors.len(), 2);
+        assert_eq!((colors[0].r, colors[0].g, colors[0].b), (20, 30, 90));
+        assert_eq!(colors[0].hex, "#141e5a");
+        assert!((colors[0].ratio - 0.75).abs() < 1e-9);
+        assert_eq!(colors[1].hex, "#f0c828");
+    }
+
+    #[test]
+    fn test_median_cut() {
+        let pixels = vec![[0, 0, 0], [10, 0, 0], [200, 0, 0], [210, 0, 0]];
+        let mut boxes = median_cut(pixels.clone(), 2);
+        boxes.sort();
+        assert_eq!(boxes, vec![([5, 0, 0], 2), ([205, 0, 0], 2)]);
+
+        assert_eq!(median_cut(pixels, 1), vec![([105, 0, 0], 4)]);
+        assert!(median_cut(Vec::new(), 4).is_empty());
+
+        // 透明な画像には代表色が無い
+        let transparent = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+        assert!(image_dominant_colors(&transparent, 4).is_empty());
+    }
+}
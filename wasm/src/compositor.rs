@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Rect;
+
+/// フェードインのデフォルトの長さ（ミリ秒）
+pub const DEFAULT_FADE_DURATION: f64 = 200.0;
+
+/// ピラミッドのタイルの識別子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TileKey {
+    /// レベルのインデックス
+    pub level: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// 描画命令
+///
+/// 配列の順に描画する（下に敷く低解像度のタイルが先、フェードイン中のタイルが後）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawCommand {
+    pub level: usize,
+    pub x: u32,
+    pub y: u32,
+    /// タイル画像から切り出す範囲（タイルのピクセル）
+    pub src: Rect,
+    /// 描画先（ページの原寸ピクセル）
+    pub dest: Rect,
+    /// 不透明度（0.0〜1.0）
+    pub opacity: f64,
+}
+
+/// 低解像度のタイルを下に敷きながら高解像度のタイルをフェードインさせる合成処理
+///
+/// 拡大直後は高解像度のタイルが届くまで何も描画されず白く抜けるため、
+/// 読み込み済みの低解像度レベルの該当部分を引き伸ばして下に敷く。
+/// タイルごとの読み込み時刻を保持し、フェードインの不透明度を求める。
+#[derive(Debug, Clone)]
+pub struct Compositor {
+    /// 各レベルの縮尺（原寸に対する割合）
+    levels: Vec<f64>,
+    tile_size: u32,
+    fade_duration: f64,
+    /// タイル → 読み込み完了時刻
+    loaded: HashMap<TileKey, f64>,
+}
+
+impl Compositor {
+    pub fn new(levels: &[f32], tile_size: u32, fade_duration: f64) -> Self {
+        Self {
+            levels: levels.iter().map(|s| *s as f64).collect(),
+            tile_size: tile_size.max(1),
+            fade_duration: fade_duration.max(0.0),
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// タイルの読み込み完了を記録する（既に記録済みの場合は何もしない）
+    pub fn mark_loaded(&mut self, key: TileKey, time: f64) {
+        self.loaded.entry(key).or_insert(time);
+    }
+
+    /// タイルの記録を削除する（キャッシュから破棄した場合など）
+    pub fn forget(&mut self, key: &TileKey) {
+        self.loaded.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.loaded.clear();
+    }
+
+    /// 指定時刻のタイルの不透明度（未読み込みは0.0）
+    pub fn opacity(&self, key: &TileKey, time: f64) -> f64 {
+        match self.loaded.get(key) {
+            None => 0.0,
+            Some(_) if self.fade_duration == 0.0 => 1.0,
+            Some(loaded) => ((time - loaded) / self.fade_duration).clamp(0.0, 1.0),
+        }
+    }
+
+    /// フェードイン中のタイルがあるか（描画ループを続ける必要があるか）
+    pub fn is_animating(&self, time: f64) -> bool {
+        self.loaded
+            .values()
+            .any(|loaded| time >= *loaded && time - loaded < self.fade_duration)
+    }
+
+    /// 表示するタイルの描画命令を作成する
+    ///
+    /// # Arguments
+    /// * `level` - 表示するレベル
+    /// * `tiles` - 表示範囲内のタイル座標（`level` のもの）
+    /// * `time` - 現在時刻（ミリ秒）
+    pub fn plan(&self, level: usize, tiles: &[(u32, u32)], time: f64) -> Vec<DrawCommand> {
+        let mut under = Vec::new();
+        let mut over = Vec::new();
+
+        for &(x, y) in tiles {
+            let key = TileKey { level, x, y };
+            let opacity = self.opacity(&key, time);
+
+            if opacity < 1.0 {
+                if let Some(region) = self.tile_rect(&key) {
+                    under.extend(self.fallback(level, &region, time));
+                }
+            }
+            if opacity > 0.0 {
+                if let Some(dest) = self.tile_rect(&key) {
+                    let size = self.tile_size as f64;
+                    over.push(DrawCommand {
+                        level,
+                        x,
+                        y,
+                        src: Rect::new(0.0, 0.0, size, size),
+                        dest,
+                        opacity,
+                    });
+                }
+            }
+        }
+
+        under.extend(over);
+        under
+    }
+
+    /// タイルが覆うページ上の範囲（原寸ピクセル）
+    fn tile_rect(&self, key: &TileKey) -> Option<Rect> {
+        let scale = *self.levels.get(key.level)?;
+        if scale <= 0.0 {
+            return None;
+        }
+        let size = self.tile_size as f64 / scale;
+        Some(Rect::new(
+            key.x as f64 * size,
+            key.y as f64 * size,
+            size,
+            size,
+        ))
+    }
+
+    /// `region` を覆う、`level` より低解像度で読み込み済みのタイル
+    ///
+    /// 解像度の近いレベルから順に探し、範囲全体を表示し終えたタイルで覆えるレベルを使う
+    fn fallback(&self, level: usize, region: &Rect, time: f64) -> Vec<DrawCommand> {
+        let current = self.levels[level];
+        let mut coarser: Vec<usize> = (0..self.levels.len())
+            .filter(|l| self.levels[*l] > 0.0 && self.levels[*l] < current)
+            .collect();
+        coarser.sort_by(|a, b| self.levels[*b].total_cmp(&self.levels[*a]));
+
+        for candidate in coarser {
+            let scale = self.levels[candidate];
+            let size = self.tile_size as f64 / scale;
+            let x0 = (region.x / size).floor() as u32;
+            let y0 = (region.y / size).floor() as u32;
+            let x1 = ((region.right() / size).ceil() as u32).max(x0 + 1);
+            let y1 = ((region.bottom() / size).ceil() as u32).max(y0 + 1);
+
+            let keys: Vec<TileKey> = (y0..y1)
+                .flat_map(|y| {
+                    (x0..x1).map(move |x| TileKey {
+                        level: candidate,
+                        x,
+                        y,
+                    })
+                })
+                .collect();
+            // フェードイン中のタイルを下に敷くと背景が透けるため、表示し終えたものだけを使う
+            if !keys.iter().all(|key| self.opacity(key, time) >= 1.0) {
+                continue;
+            }
+
+            return keys
+                .into_iter()
+                .filter_map(|key| {
+                    let tile = self.tile_rect(&key)?;
+                    let dest = clip(&tile, region)?;
+                    Some(DrawCommand {
+                        level: key.level,
+                        x: key.x,
+                        y: key.y,
+                        src: Rect::new(
+                            (dest.x - tile.x) * scale,
+                            (dest.y - tile.y) * scale,
+                            dest.width * scale,
+                            dest.height * scale,
+                        ),
+                        dest,
+                        opacity: 1.0,
+                    })
+                })
+                .collect();
+        }
+
+        Vec::new()
+    }
+}
+
+/// 2つの矩形の共通部分
+fn clip(a: &Rect, b: &Rect) -> Option<Rect> {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = a.right().min(b.right());
+    let bottom = a.bottom().min(b.bottom());
+    (right > x && bottom > y).then(|| Rect::new(x, y, right - x, bottom - y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(level: usize, x: u32, y: u32) -> TileKey {
+        TileKey { level, x, y }
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let mut compositor = Compositor::new(&[1.0, 0.5], 256, 200.0);
+        assert_eq!(compositor.opacity(&key(0, 0, 0), 0.0), 0.0);
+
+        compositor.mark_loaded(key(0, 0, 0), 1000.0);
+        compositor.mark_loaded(key(0, 0, 0), 1100.0);
+        assert_eq!(compositor.opacity(&key(0, 0, 0), 1100.0), 0.5);
+        assert!(compositor.is_animating(1100.0));
+        assert_eq!(compositor.opacity(&key(0, 0, 0), 1300.0), 1.0);
+        assert!(!compositor.is_animating(1300.0));
+    }
+
+    #[test]
+    fn test_plan_draws_coarser_level_underneath() {
+        // レベル0: 原寸、レベル1: 1/2（タイル1枚がレベル0の2x2枚分）
+        let mut compositor = Compositor::new(&[1.0, 0.5], 256, 200.0);
+        compositor.mark_loaded(key(1, 0, 0), 0.0);
+        compositor.mark_loaded(key(0, 1, 1), 1000.0);
+
+        let plan = compositor.plan(0, &[(0, 0), (1, 1)], 1100.0);
+        assert_eq!(plan.len(), 3);
+
+        // 未読み込みの (0,0) にはレベル1の左上1/4を敷く
+        assert_eq!(plan[0].level, 1);
+        assert_eq!(plan[0].src, Rect::new(0.0, 0.0, 128.0, 128.0));
+        assert_eq!(plan[0].dest, Rect::new(0.0, 0.0, 256.0, 256.0));
+        // フェードイン中の (1,1) にはレベル1の右下1/4を敷いて上に重ねる
+        assert_eq!(plan[1].src, Rect::new(128.0, 128.0, 128.0, 128.0));
+        assert_eq!((plan[2].level, plan[2].x, plan[2].y), (0, 1, 1));
+        assert_eq!(plan[2].opacity, 0.5);
+
+        // 表示し終えたら下敷きは不要
+        let plan = compositor.plan(0, &[(1, 1)], 1200.0);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].opacity, 1.0);
+    }
+
+    #[test]
+    fn test_fading_coarse_tiles_are_not_used_as_fallback() {
+        let mut compositor = Compositor::new(&[1.0, 0.5, 0.25], 256, 200.0);
+        compositor.mark_loaded(key(2, 0, 0), 0.0);
+        compositor.mark_loaded(key(1, 0, 0), 1000.0);
+
+        let plan = compositor.plan(0, &[(0, 0)], 1100.0);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].level, 2);
+        assert_eq!(plan[0].src, Rect::new(0.0, 0.0, 64.0, 64.0));
+
+        compositor.forget(&key(2, 0, 0));
+        assert!(compositor.plan(0, &[(0, 0)], 1100.0).is_empty());
+    }
+}
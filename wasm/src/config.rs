@@ -0,0 +1,342 @@
+//! タイル化の設定ファイル（`pamphlet.toml`、またはJSON）
+//!
+//! タイルサイズ・品質・形式・前処理・出力の配置と、ファイルごとの設定をまとめて書き、
+//! CLIの引数を毎回並べずに済むようにする。書かなかった項目は既定値（CLIでは引数）を使う
+//!
+//! ```toml
+//! tile_size = 512
+//! quality = 80
+//! layout = "sharded"
+//!
+//! [preprocess]
+//! deskew = {}
+//! auto_crop = {}
+//!
+//! [[pages]]
+//! match = "cover.*"
+//! quality = 95
+//! ```
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::TileLayout;
+use crate::preprocess::PreprocessOptions;
+use crate::tiler::{validate_tile_size, EdgePadding, TileFormat, TileOptions};
+
+/// 設定ファイルの内容
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PamphletConfig {
+    pub tile_size: Option<u32>,
+    /// WebP品質（1-100）
+    pub quality: Option<f32>,
+    pub format: Option<TileFormat>,
+    pub padding: Option<EdgePadding>,
+    /// PDFをラスタライズする解像度（DPI）
+    pub dpi: Option<f32>,
+    /// タイルの出力の配置
+    pub layout: Option<TileLayout>,
+    /// 出力先のディレクトリ
+    pub output: Option<PathBuf>,
+    /// 並列にタイル化するスレッドの数
+    pub jobs: Option<usize>,
+    /// タイル化の前の前処理
+    pub preprocess: PreprocessOptions,
+    /// ファイルごとの設定（ファイル名が最初に合ったものを使う）
+    pub pages: Vec<PageOverride>,
+}
+
+/// ファイルごとの設定（書かなかった項目は全体の設定を使う）
+///
+/// タイルサイズ・形式はすべてのページで揃える必要があるため、ファイルごとには変えられない
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageOverride {
+    /// ファイル名のパターン（`*` は任意の文字列、`?` は任意の1文字。ディレクトリは含めない）
+    #[serde(rename = "match")]
+    pub pattern: String,
+    pub quality: Option<f32>,
+    pub dpi: Option<f32>,
+    /// 全体の前処理の代わりに使う前処理
+    pub preprocess: Option<PreprocessOptions>,
+}
+
+impl PamphletConfig {
+    /// TOMLの設定を読む（`toml` フィーチャー）
+    ///
+    /// # Errors
+    /// 構文の誤り・知らない項目がある場合、値が範囲外の場合
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| format!("Invalid config: {}", e))?;
+        config.validate()
+    }
+
+    /// TOMLの設定を読む（`toml` フィーチャーが無効なため読めない）
+    ///
+    /// # Errors
+    /// 常に（JSONの設定を使う）
+    #[cfg(not(feature = "toml"))]
+    pub fn from_toml(_text: &str) -> Result<Self, String> {
+        Err("TOML config requires the `toml` feature (use a JSON config)".to_string())
+    }
+
+    /// JSONの設定を読む
+    ///
+    /// # Errors
+    /// 構文の誤り・知らない項目がある場合、値が範囲外の場合
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let config: Self =
+            serde_json::from_str(text).map_err(|e| format!("Invalid config: {}", e))?;
+        config.validate()
+    }
+
+    /// 設定を読む（`{` で始まればJSON、それ以外はTOML）
+    ///
+    /// # Errors
+    /// 構文の誤り・知らない項目がある場合、値が範囲外の場合
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim_start().starts_with('{') {
+            true => Self::from_json(text),
+            false => Self::from_toml(text),
+        }
+    }
+
+    /// 設定ファイルを読む（拡張子が `.json` ならJSON、それ以外はTOML）
+    ///
+    /// # Errors
+    /// ファイルを読めない場合、設定の誤り（ファイル名付き）
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let error = |e: String| format!("{}: {}", path.display(), e);
+        let text =
+            std::fs::read_to_string(path).map_err(|e| error(format!("Failed to read: {}", e)))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        match is_json {
+            true => Self::from_json(&text),
+            false => Self::from_toml(&text),
+        }
+        .map_err(error)
+    }
+
+    /// タイル化の設定（書かなかった項目は既定値）
+    pub fn tile_options(&self) -> TileOptions {
+        let defaults = TileOptions::default();
+        TileOptions {
+            tile_size: self.tile_size.unwrap_or(defaults.tile_size),
+            quality: self.quality.or(defaults.quality),
+            format: self.format.unwrap_or(defaults.format),
+            padding: self.padding.unwrap_or(defaults.padding),
+        }
+    }
+
+    /// ファイル名に合う最初のファイルごとの設定
+    pub fn page_override(&self, name: &str) -> Option<&PageOverride> {
+        self.pages.iter().find(|page| page.matches(name))
+    }
+
+    /// 値が範囲内か確かめる
+    fn validate(self) -> Result<Self, String> {
+        if let Some(tile_size) = self.tile_size {
+            validate_tile_size(tile_size).map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        if self.jobs == Some(0) {
+            return Err("Invalid config: jobs must be at least 1".to_string());
+        }
+        let pages = self.pages.iter().map(|page| {
+            (
+                format!("pages \"{}\" ", page.pattern),
+                page.quality,
+                page.dpi,
+            )
+        });
+        for (name, quality, dpi) in [(String::new(), self.quality, self.dpi)]
+            .into_iter()
+            .chain(pages)
+        {
+            if let Some(quality) = quality.filter(|quality| !(1.0..=100.0).contains(quality)) {
+                return Err(format!(
+                    "Invalid config: {}quality must be 1-100 (got {})",
+                    name, quality
+                ));
+            }
+            if let Some(dpi) = dpi.filter(|dpi| !(dpi.is_finite() && *dpi > 0.0)) {
+                return Err(format!(
+                    "Invalid config: {}dpi must be positive (got {})",
+                    name, dpi
+                ));
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl PageOverride {
+    /// ファイル名（ディレクトリを除く）がパターンに合うか
+    pub fn matches(&self, name: &str) -> bool {
+        matches_pattern(&self.pattern, name)
+    }
+}
+
+/// ファイル名がパターンに合うか（`*` は任意の文字列、`?` は任意の1文字）
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // 最後の `*` の位置と、そこで合わせ始めた名前の位置（合わなければ1文字ずつずらす）
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml() {
+        let config = PamphletConfig::from_toml(
+            r#"
+            tile_size = 256
+            quality = 90
+            padding = "none"
+            layout = "zxy"
+            output = "dist"
+
+            [preprocess]
+            gamma = 1.2
+            deskew = {}
+
+            [[pages]]
+            match = "cover.*"
+            quality = 95
+
+            [[pages]]
+            match = "*.pdf"
+            dpi = 300
+            preprocess = {}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.tile_options(),
+            TileOptions {
+                tile_size: 256,
+                quality: Some(90.0),
+                format: TileFormat::Webp,
+                padding: EdgePadding::None,
+            }
+        );
+        assert_eq!(config.layout, Some(TileLayout::Zxy));
+        assert_eq!(config.output, Some(PathBuf::from("dist")));
+        assert_eq!(config.preprocess.gamma, Some(1.2));
+        assert!(config.preprocess.deskew.is_some());
+        assert_eq!(
+            config
+                .page_override("cover.jpg")
+                .and_then(|page| page.quality),
+            Some(95.0)
+        );
+        let pdf = config.page_override("catalog.pdf").unwrap();
+        assert_eq!(
+            (pdf.dpi, pdf.preprocess.clone()),
+            (Some(300.0), Some(PreprocessOptions::default()))
+        );
+        assert!(config.page_override("1.png").is_none());
+
+        // 書かなかった項目は既定値
+        let config = PamphletConfig::from_toml("").unwrap();
+        assert_eq!(config.tile_options(), TileOptions::default());
+        assert_eq!(config.layout, None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let json = r#"{ "tile_size": 128, "pages": [{ "match": "1.png", "quality": 50 }] }"#;
+        let config = PamphletConfig::parse(json).unwrap();
+        assert_eq!(config, PamphletConfig::from_json(json).unwrap());
+        assert_eq!(config.tile_size, Some(128));
+        #[cfg(feature = "toml")]
+        assert_eq!(
+            TileOptions::from_config("tile_size = 128").unwrap(),
+            TileOptions::new(128, None)
+        );
+        // `toml` フィーチャーが無効ならTOMLの設定は読めない
+        #[cfg(not(feature = "toml"))]
+        assert!(TileOptions::from_config("tile_size = 128")
+            .unwrap_err()
+            .contains("requires the `toml` feature"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_invalid_config() {
+        for (text, expected) in [
+            ("tile_sise = 256", "unknown field `tile_sise`"),
+            ("tile_size = 4", "Invalid config: "),
+            ("quality = 0", "quality must be 1-100"),
+            ("jobs = 0", "jobs must be at least 1"),
+            (
+                "[[pages]]\nmatch = \"*.pdf\"\ndpi = -1",
+                "pages \"*.pdf\" dpi must be positive",
+            ),
+            ("[[pages]]\nquality = 90", "missing field `match`"),
+            (
+                "[[pages]]\nmatch = \"*\"\ntile_size = 256",
+                "unknown field `tile_size`",
+            ),
+            ("layout = \"nested\"", "unknown variant `nested`"),
+        ] {
+            let error = PamphletConfig::from_toml(text).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", text, error);
+        }
+        assert!(PamphletConfig::parse("{ broken").is_err());
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        for (pattern, name, expected) in [
+            ("cover.*", "cover.jpg", true),
+            ("cover.*", "back-cover.jpg", false),
+            ("*.pdf", "catalog.pdf", true),
+            ("*.pdf", "catalog.pdf.png", false),
+            ("p??.png", "p10.png", true),
+            ("p??.png", "p1.png", false),
+            ("*a*b", "xaxxab", true),
+            ("*", "", true),
+            ("", "a", false),
+        ] {
+            assert_eq!(
+                matches_pattern(pattern, name),
+                expected,
+                "{} {}",
+                pattern,
+                name
+            );
+        }
+    }
+}
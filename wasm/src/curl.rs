@@ -0,0 +1,138 @@
+use std::f64::consts::PI;
+
+use crate::spread::ReadingDirection;
+
+/// 1頂点あたりの要素数（x, y, z, u, v）
+pub const VERTEX_STRIDE: usize = 5;
+
+/// ページめくりのメッシュ
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurlMesh {
+    /// 頂点データ（x, y, z, u, v のインターリーブ）
+    ///
+    /// x/y はページの高さを1とした座標（綴じ側の辺が x=0、上端が y=0、
+    /// 左綴じのページは x≧0、右綴じのページは x≦0 の範囲に置かれる）、
+    /// z は画面手前方向への浮き上がり量、u/v はテクスチャ座標
+    pub vertices: Vec<f32>,
+    /// 三角形のインデックス（反時計回り）
+    pub indices: Vec<u32>,
+}
+
+/// ページめくり（カール）のメッシュを生成する
+///
+/// 綴じ側と平行な折り目を円柱に巻き付けるモデルで変形させる。
+/// 折り目は進捗に応じて小口側から綴じ側へ移動し、円柱の半径は進捗とともに小さくなるため、
+/// `progress = 1.0` でページは綴じ側を軸にちょうど裏返った位置に来る。
+///
+/// # Arguments
+/// * `progress` - めくりの進捗（0.0 = 平置き、1.0 = めくり終わり）
+/// * `aspect` - ページの縦横比（幅 / 高さ）
+/// * `columns` - 横方向の分割数
+/// * `rows` - 縦方向の分割数
+/// * `radius` - 開始時のカールの半径（ページの高さを1とした値）
+/// * `direction` - 読み方向（右綴じの場合は左右反転）
+pub fn page_curl_mesh(
+    progress: f64,
+    aspect: f64,
+    columns: u32,
+    rows: u32,
+    radius: f64,
+    direction: ReadingDirection,
+) -> CurlMesh {
+    let progress = progress.clamp(0.0, 1.0);
+    let columns = columns.max(1);
+    let rows = rows.max(1);
+
+    let fold = aspect * (1.0 - progress);
+    let radius = radius.max(0.0) * (1.0 - progress);
+
+    let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize * VERTEX_STRIDE);
+    for row in 0..=rows {
+        let v = row as f64 / rows as f64;
+        for column in 0..=columns {
+            let u = column as f64 / columns as f64;
+            let (x, z) = curl_point(u * aspect, fold, radius);
+            let x = match direction {
+                ReadingDirection::Ltr => x,
+                ReadingDirection::Rtl => -x,
+            };
+
+            vertices.extend([x as f32, v as f32, z as f32, u as f32, v as f32]);
+        }
+    }
+
+    let stride = columns + 1;
+    let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = row * stride + column;
+            let bottom_left = top_left + stride;
+            indices.extend([
+                top_left,
+                bottom_left,
+                top_left + 1,
+                top_left + 1,
+                bottom_left,
+                bottom_left + 1,
+            ]);
+        }
+    }
+
+    CurlMesh { vertices, indices }
+}
+
+/// 折り目の位置 `fold` と半径 `radius` の円柱に巻き付けたときの (x, z)
+fn curl_point(x: f64, fold: f64, radius: f64) -> (f64, f64) {
+    let distance = x - fold;
+    if distance <= 0.0 {
+        return (x, 0.0);
+    }
+
+    let half_turn = PI * radius;
+    if distance < half_turn {
+        // 円柱の表面に沿って巻き上がる
+        let angle = distance / radius;
+        (fold + radius * angle.sin(), radius * (1.0 - angle.cos()))
+    } else {
+        // 半周を超えた部分は裏返って平らに戻る
+        (fold - (distance - half_turn), 2.0 * radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xs(mesh: &CurlMesh) -> Vec<f32> {
+        mesh.vertices.chunks(VERTEX_STRIDE).map(|v| v[0]).collect()
+    }
+
+    #[test]
+    fn test_flat_and_fully_turned() {
+        let flat = page_curl_mesh(0.0, 0.7, 4, 2, 0.1, ReadingDirection::Ltr);
+        assert_eq!(flat.vertices.len(), 5 * 3 * VERTEX_STRIDE);
+        assert_eq!(flat.indices.len(), 4 * 2 * 6);
+        assert!(flat.vertices.chunks(VERTEX_STRIDE).all(|v| v[2] == 0.0));
+
+        // めくり終わりでは綴じ側を軸に反転している
+        let turned = page_curl_mesh(1.0, 0.7, 4, 2, 0.1, ReadingDirection::Ltr);
+        for (flat_x, turned_x) in xs(&flat).iter().zip(xs(&turned)) {
+            assert!((flat_x + turned_x).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_midway_curl_lifts_free_edge() {
+        let mesh = page_curl_mesh(0.5, 1.0, 16, 1, 0.1, ReadingDirection::Ltr);
+        let vertices: Vec<&[f32]> = mesh.vertices.chunks(VERTEX_STRIDE).collect();
+
+        // 綴じ側は平らなまま、小口側は浮き上がる
+        assert_eq!(vertices[0][2], 0.0);
+        assert!(vertices[16][2] > 0.0);
+        // 巻き上がった小口側は折り目より綴じ側へ戻る
+        assert!(vertices[16][0] < 0.5);
+
+        let rtl = page_curl_mesh(0.5, 1.0, 16, 1, 0.1, ReadingDirection::Rtl);
+        assert_eq!(rtl.vertices[16 * VERTEX_STRIDE], -vertices[16][0]);
+    }
+}
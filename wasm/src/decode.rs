@@ -0,0 +1,965 @@
+use std::fmt;
+use std::io::Cursor;
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::FilterType;
+use image::{
+    AnimationDecoder, ColorType, DynamicImage, Frames, ImageBuffer, ImageDecoder, ImageFormat,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PamphletError;
+use crate::logging::log_with;
+use crate::probe;
+use crate::settings::Settings;
+
+/// 入力画像の形式
+///
+/// `image` クレートが判別できない（または対応していない）形式を、専用のデコーダーに振り分けるために使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// HEIC/HEIF（iPhoneの写真など）
+    Heif,
+    /// AVIF
+    Avif,
+    /// JPEG XL（コードストリームまたはコンテナ）
+    Jxl,
+    /// JPEG 2000（JP2コンテナまたはコードストリーム）
+    Jpeg2000,
+    /// Photoshop（PSD）
+    Psd,
+    /// TIFFベースのカメラRAW（CR2/NEF/ARWなど）
+    Raw,
+    /// `image` クレートでデコードする形式（JPEG/PNG/WebP/GIF）
+    Other,
+}
+
+/// デコードを許可する入力の形式の名前（`set_allowed_formats` で指定する）
+///
+/// 名前は `version_info` の `input_formats` と同じ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Gif,
+    Heic,
+    Avif,
+    Jxl,
+    Jpeg2000,
+    Psd,
+    Raw,
+}
+
+impl SourceFormat {
+    pub const ALL: [SourceFormat; 10] = [
+        Self::Jpeg,
+        Self::Png,
+        Self::Webp,
+        Self::Gif,
+        Self::Heic,
+        Self::Avif,
+        Self::Jxl,
+        Self::Jpeg2000,
+        Self::Psd,
+        Self::Raw,
+    ];
+
+    /// 許可する形式のビット
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// 先頭のバイト列から判別する（どの形式でもない場合は `None`）
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match detect_format(data) {
+            InputFormat::Heif => Some(Self::Heic),
+            InputFormat::Avif => Some(Self::Avif),
+            InputFormat::Jxl => Some(Self::Jxl),
+            InputFormat::Jpeg2000 => Some(Self::Jpeg2000),
+            InputFormat::Psd => Some(Self::Psd),
+            InputFormat::Raw => Some(Self::Raw),
+            InputFormat::Other => match image::guess_format(data) {
+                Ok(ImageFormat::Jpeg) => Some(Self::Jpeg),
+                Ok(ImageFormat::Png) => Some(Self::Png),
+                Ok(ImageFormat::WebP) => Some(Self::Webp),
+                Ok(ImageFormat::Gif) => Some(Self::Gif),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for SourceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+            Self::Gif => "gif",
+            Self::Heic => "heic",
+            Self::Avif => "avif",
+            Self::Jxl => "jxl",
+            Self::Jpeg2000 => "jpeg2000",
+            Self::Psd => "psd",
+            Self::Raw => "raw",
+        };
+        f.write_str(name)
+    }
+}
+
+/// アニメーション（GIF・APNG・アニメーションWebP）の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationMode {
+    /// 最初のフレームだけを使う
+    #[default]
+    FirstFrame,
+    /// 各フレームを別のページにする
+    Pages,
+}
+
+/// HEVCで符号化されたHEIFのブランド
+const HEIF_BRANDS: [&[u8; 4]; 6] = [b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx"];
+
+/// AVIFのブランド（静止画・シーケンス）
+const AVIF_BRANDS: [&[u8; 4]; 2] = [b"avif", b"avis"];
+
+/// JPEG XLのコードストリームの先頭
+const JXL_CODESTREAM: &[u8] = &[0xFF, 0x0A];
+
+/// JPEG XLのコンテナの先頭（`JXL ` ボックス）
+const JXL_CONTAINER: &[u8] = b"\0\0\0\x0cJXL \r\n\x87\n";
+
+/// JP2コンテナの先頭（署名ボックス）
+const JP2_CONTAINER: &[u8] = b"\0\0\0\x0cjP  ";
+
+/// JPEG 2000のコードストリームの先頭（SOC・SIZマーカー）
+const JP2_CODESTREAM: &[u8] = &[0xFF, 0x4F, 0xFF, 0x51];
+
+/// PSDの先頭（シグネチャとバージョン1、PSBはバージョン2のため対象外）
+const PSD_SIGNATURE: &[u8] = b"8BPS\0\x01";
+
+/// TIFFの先頭（リトルエンディアン・ビッグエンディアン）
+///
+/// TIFFは `image` クレートで有効にしていないため、カメラRAWとして扱う
+const TIFF_HEADERS: [&[u8]; 2] = [b"II*\0", b"MM\0*"];
+
+/// デコードする画像の大きさの上限
+///
+/// 展開すると巨大になる画像（解凍爆弾）や誤って渡された巨大な画像を、画素を確保する前に
+/// ヘッダーの大きさで断り、WASMのメモリ不足で止まらないようにする
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodeLimits {
+    /// 幅・高さの最大（ピクセル）
+    pub max_dimension: u32,
+    /// 画素数（幅×高さ）の最大
+    pub max_pixels: u64,
+}
+
+impl DecodeLimits {
+    const DEFAULT_MAX_DIMENSION: u32 = 32768;
+    /// RGBAで1GiB
+    const DEFAULT_MAX_PIXELS: u64 = 1 << 28;
+
+    /// 画像の大きさが上限以内か確かめる
+    ///
+    /// # Errors
+    /// 幅・高さ・画素数のいずれかが上限を超える場合
+    pub fn check(&self, width: u32, height: u32) -> Result<(), PamphletError> {
+        let pixels = width as u64 * height as u64;
+        if width > self.max_dimension || height > self.max_dimension || pixels > self.max_pixels {
+            return Err(PamphletError::ImageTooLarge {
+                width,
+                height,
+                limits: *self,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_dimension: Self::DEFAULT_MAX_DIMENSION,
+            max_pixels: Self::DEFAULT_MAX_PIXELS,
+        }
+    }
+}
+
+/// 形式のビットの和
+fn bits_of(formats: &[SourceFormat]) -> u32 {
+    formats.iter().fold(0, |bits, format| bits | format.bit())
+}
+
+/// ビットの和に含まれる形式
+fn formats_in(bits: u32) -> Vec<SourceFormat> {
+    SourceFormat::ALL
+        .into_iter()
+        .filter(|format| bits & format.bit() != 0)
+        .collect()
+}
+
+/// 入力の形式のデコードが許可されているか確かめる
+///
+/// 形式を判別できない場合は確かめない（デコーダーのエラーに任せる）
+///
+/// # Errors
+/// 設定の `allowed_formats` で許可していない形式の場合
+pub fn check_format(data: &[u8], settings: &Settings) -> Result<(), PamphletError> {
+    check_format_in(data, bits_of(settings.formats()))
+}
+
+/// 入力の形式がビットの和に含まれるか確かめる
+fn check_format_in(data: &[u8], bits: u32) -> Result<(), PamphletError> {
+    let Some(format) = SourceFormat::detect(data) else {
+        return Ok(());
+    };
+    if bits & format.bit() != 0 {
+        return Ok(());
+    }
+    let allowed: Vec<String> = formats_in(bits).iter().map(ToString::to_string).collect();
+    Err(PamphletError::UnsupportedFormat(format!(
+        "Unsupported input format: {} is not allowed (allowed formats: {})",
+        format,
+        if allowed.is_empty() {
+            "none".to_string()
+        } else {
+            allowed.join(", ")
+        }
+    )))
+}
+
+/// ヘッダーから読み取った大きさ（`2^level` 分の1に縮小してデコードする場合は縮小後）が上限以内か確かめる
+///
+/// ヘッダーを読めない場合は確かめない（デコーダーのエラーに任せる）。
+/// 先に形式のデコードが許可されているかを `check_format` で確かめる
+fn check_header(data: &[u8], level: u32, settings: &Settings) -> Result<(), PamphletError> {
+    check_format(data, settings)?;
+    let Ok(header) = probe::probe_image(data) else {
+        return Ok(());
+    };
+    let (width, height) = match header.format {
+        // JPEG 2000だけは縮小後の解像度までしか復号しない
+        probe::ProbeFormat::Jpeg2000 => reduced_size(header.width, header.height, level),
+        _ => (header.width, header.height),
+    };
+    log_with!(
+        settings,
+        log::Level::Debug,
+        "decoding {:?} image: {}x{} pixels, {} bytes",
+        header.format,
+        width,
+        height,
+        data.len()
+    );
+    settings.limits().check(width, height)
+}
+
+/// 先頭のバイト列から入力画像の形式を判別する
+pub fn detect_format(data: &[u8]) -> InputFormat {
+    if data.starts_with(PSD_SIGNATURE) {
+        return InputFormat::Psd;
+    }
+    if data.starts_with(JP2_CONTAINER) || data.starts_with(JP2_CODESTREAM) {
+        return InputFormat::Jpeg2000;
+    }
+    if data.starts_with(JXL_CODESTREAM) || data.starts_with(JXL_CONTAINER) {
+        return InputFormat::Jxl;
+    }
+    if TIFF_HEADERS.iter().any(|header| data.starts_with(header)) {
+        return InputFormat::Raw;
+    }
+
+    let brands = ftyp_brands(data);
+    if brands.iter().any(|brand| AVIF_BRANDS.contains(brand)) {
+        return InputFormat::Avif;
+    }
+    if brands.iter().any(|brand| HEIF_BRANDS.contains(brand)) {
+        return InputFormat::Heif;
+    }
+
+    InputFormat::Other
+}
+
+/// 入力画像をデコードする
+///
+/// # Errors
+/// 画像のデコードに失敗した場合や、形式に対応するフィーチャーが無効な場合
+pub fn decode_image(data: &[u8], settings: &Settings) -> Result<DynamicImage, PamphletError> {
+    decode_image_reduced(data, 0, settings)
+}
+
+/// 入力画像を縮小してデコードする（ピラミッドの下位レベル用）
+///
+/// 縮小後のサイズは元のサイズを `2^level` で割って切り上げたもの。
+/// JPEG 2000は解像度レベルの構造を使い、必要な解像度までしか復号しない。
+/// それ以外の形式は原寸でデコードしてから縮小する。
+///
+/// # Arguments
+/// * `data` - 元画像のバイトデータ
+/// * `level` - 縮小レベル（0 = 原寸、1 = 1/2、2 = 1/4 ...）
+pub fn decode_image_reduced(
+    data: &[u8],
+    level: u32,
+    settings: &Settings,
+) -> Result<DynamicImage, PamphletError> {
+    check_header(data, level, settings)?;
+    let image = match detect_format(data) {
+        InputFormat::Heif => decode_heif(data)?,
+        InputFormat::Avif => decode_avif(data)?,
+        InputFormat::Jxl => decode_jxl(data)?,
+        InputFormat::Jpeg2000 => return decode_jpeg2000(data, level),
+        InputFormat::Psd => decode_psd(data)?,
+        InputFormat::Raw => decode_raw(data)?,
+        InputFormat::Other => match decode_animation(data, AnimationMode::FirstFrame)? {
+            Some(mut frames) => frames.swap_remove(0),
+            None => image::load_from_memory(data).map_err(|e| {
+                PamphletError::DecodeFailed(format!("Failed to decode image: {}", e))
+            })?,
+        },
+    };
+
+    let (width, height) = reduced_size(image.width(), image.height(), level);
+    Ok(resize_to(image, width, height))
+}
+
+/// 入力画像を、呼び出し側のバッファを使い回してデコードする
+///
+/// 静止画のJPEG・PNG・WebPで8bitの画素形式の場合は `buffer` の領域にデコードする。
+/// それ以外は `decode_image` と同じく新しく確保する。結果は `decode_image` と同じ。
+/// 使い終わった画像は `into_buffer` でバッファに戻せる。
+pub fn decode_image_into(
+    data: &[u8],
+    buffer: Vec<u8>,
+    settings: &Settings,
+) -> Result<DynamicImage, PamphletError> {
+    let error = |e: image::ImageError| {
+        PamphletError::DecodeFailed(format!("Failed to decode image: {}", e))
+    };
+    if detect_format(data) != InputFormat::Other {
+        return decode_image(data, settings);
+    }
+    check_header(data, 0, settings)?;
+
+    match image::guess_format(data) {
+        Ok(ImageFormat::Jpeg) => {
+            read_into(JpegDecoder::new(Cursor::new(data)).map_err(error)?, buffer)
+        }
+        Ok(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(Cursor::new(data)).map_err(error)?;
+            if decoder.is_apng().map_err(error)? {
+                return decode_image(data, settings);
+            }
+            read_into(decoder, buffer)
+        }
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(Cursor::new(data)).map_err(error)?;
+            if decoder.has_animation() {
+                return decode_image(data, settings);
+            }
+            read_into(decoder, buffer)
+        }
+        _ => decode_image(data, settings),
+    }
+}
+
+/// 8bitの画素形式なら `buffer` の領域にデコードする
+fn read_into(
+    decoder: impl ImageDecoder,
+    mut buffer: Vec<u8>,
+) -> Result<DynamicImage, PamphletError> {
+    let error = |e: image::ImageError| {
+        PamphletError::DecodeFailed(format!("Failed to decode image: {}", e))
+    };
+    let (width, height) = decoder.dimensions();
+    let color = decoder.color_type();
+    if !matches!(
+        color,
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+    ) {
+        return DynamicImage::from_decoder(decoder).map_err(error);
+    }
+
+    let size = usize::try_from(decoder.total_bytes()).map_err(|_| {
+        PamphletError::DecodeFailed("Failed to decode image: image is too large".to_string())
+    })?;
+    buffer.clear();
+    buffer.resize(size, 0);
+    decoder.read_image(&mut buffer).map_err(error)?;
+
+    let image = match color {
+        ColorType::L8 => ImageBuffer::from_raw(width, height, buffer).map(DynamicImage::ImageLuma8),
+        ColorType::La8 => {
+            ImageBuffer::from_raw(width, height, buffer).map(DynamicImage::ImageLumaA8)
+        }
+        ColorType::Rgb8 => {
+            ImageBuffer::from_raw(width, height, buffer).map(DynamicImage::ImageRgb8)
+        }
+        _ => ImageBuffer::from_raw(width, height, buffer).map(DynamicImage::ImageRgba8),
+    };
+    image.ok_or_else(|| {
+        PamphletError::DecodeFailed("Failed to decode image: unexpected buffer size".to_string())
+    })
+}
+
+/// 画像の画素の領域をバッファとして取り出す（`decode_image_into` で使い回す）
+///
+/// 8bitの画素形式でない場合は空のバッファを返す
+pub fn into_buffer(image: DynamicImage) -> Vec<u8> {
+    match image {
+        DynamicImage::ImageLuma8(buffer) => buffer.into_raw(),
+        DynamicImage::ImageLumaA8(buffer) => buffer.into_raw(),
+        DynamicImage::ImageRgb8(buffer) => buffer.into_raw(),
+        DynamicImage::ImageRgba8(buffer) => buffer.into_raw(),
+        _ => Vec::new(),
+    }
+}
+
+/// 入力画像をページ単位でデコードする
+///
+/// アニメーションは `mode` に従って最初のフレームだけ、または全フレームを返す。
+/// 各フレームは前のフレームに重ねた状態（表示される見た目）になる。
+/// アニメーションでない画像は1ページとして返す。
+pub fn decode_pages(
+    data: &[u8],
+    mode: AnimationMode,
+    settings: &Settings,
+) -> Result<Vec<DynamicImage>, PamphletError> {
+    check_header(data, 0, settings)?;
+    match decode_animation(data, mode)? {
+        Some(frames) => Ok(frames),
+        None => Ok(vec![decode_image(data, settings)?]),
+    }
+}
+
+/// アニメーションのフレームをデコードする（アニメーションでない場合は `None`）
+fn decode_animation(
+    data: &[u8],
+    mode: AnimationMode,
+) -> Result<Option<Vec<DynamicImage>>, PamphletError> {
+    let error = |e: image::ImageError| {
+        PamphletError::DecodeFailed(format!("Failed to decode image: {}", e))
+    };
+
+    let frames: Frames = match image::guess_format(data) {
+        Ok(ImageFormat::Gif) => GifDecoder::new(Cursor::new(data))
+            .map_err(error)?
+            .into_frames(),
+        Ok(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(Cursor::new(data)).map_err(error)?;
+            if !decoder.is_apng().map_err(error)? {
+                return Ok(None);
+            }
+            decoder.apng().map_err(error)?.into_frames()
+        }
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(Cursor::new(data)).map_err(error)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+
+    let limit = match mode {
+        AnimationMode::FirstFrame => 1,
+        AnimationMode::Pages => usize::MAX,
+    };
+    let frames = frames
+        .take(limit)
+        .map(|frame| {
+            frame
+                .map(|frame| DynamicImage::ImageRgba8(frame.into_buffer()))
+                .map_err(error)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if frames.is_empty() {
+        return Err(PamphletError::DecodeFailed(
+            "Failed to decode image: animation has no frames".to_string(),
+        ));
+    }
+    Ok(Some(frames))
+}
+
+/// 画像を指定したサイズにする（既にそのサイズの場合はそのまま）
+fn resize_to(image: DynamicImage, width: u32, height: u32) -> DynamicImage {
+    if (image.width(), image.height()) == (width, height) {
+        return image;
+    }
+    image.resize_exact(width, height, FilterType::Triangle)
+}
+
+/// `2^level` 分の1のサイズ（切り上げ、最小1ピクセル）
+fn reduced_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    let divisor = 1u64 << level.min(32);
+    let reduce = |size: u32| (size as u64).div_ceil(divisor).max(1) as u32;
+    (reduce(width), reduce(height))
+}
+
+/// ISOBMFFの `ftyp` ボックスに記載されたブランド（主ブランドと互換ブランド）
+fn ftyp_brands(data: &[u8]) -> Vec<&[u8; 4]> {
+    if data.len() < 16 || &data[4..8] != b"ftyp" {
+        return Vec::new();
+    }
+
+    let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    let end = size.clamp(16, data.len());
+
+    // 主ブランド、マイナーバージョン、互換ブランドの順
+    std::iter::once(&data[8..12])
+        .chain(data[16..end].chunks_exact(4))
+        .filter_map(|brand| brand.try_into().ok())
+        .collect()
+}
+
+#[cfg(feature = "heic")]
+fn decode_heif(data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    let error = |e: heic_decoder::HeifError| {
+        PamphletError::DecodeFailed(format!("Failed to decode image: {}", e))
+    };
+
+    let decoded = heic_decoder::decode(data).map_err(error)?;
+    let rgba = decoded.to_rgba8().map_err(error)?;
+
+    let mut pixels = rgba.data;
+    if rgba.premultiplied {
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            if alpha > 0 && alpha < 255 {
+                for channel in &mut pixel[..3] {
+                    *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                }
+            }
+        }
+    }
+
+    image::RgbaImage::from_raw(rgba.width as u32, rgba.height as u32, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| {
+            PamphletError::DecodeFailed(
+                "Failed to decode image: invalid HEIF dimensions".to_string(),
+            )
+        })
+}
+
+#[cfg(not(feature = "heic"))]
+fn decode_heif(_data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat("Failed to decode image: HEIC/HEIF is not supported in this build (enable the `heic` feature)".to_string()))
+}
+
+#[cfg(feature = "jxl")]
+fn decode_jxl(data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    let decoder = jxl_oxide::integration::JxlDecoder::new(std::io::Cursor::new(data))
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to decode image: {}", e)))?;
+    DynamicImage::from_decoder(decoder)
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to decode image: {}", e)))
+}
+
+#[cfg(not(feature = "jxl"))]
+fn decode_jxl(_data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat(
+        "Failed to decode image: JPEG XL is not supported in this build (enable the `jxl` feature)"
+            .to_string(),
+    ))
+}
+
+#[cfg(feature = "jpeg2000")]
+fn decode_jpeg2000(data: &[u8], level: u32) -> Result<DynamicImage, PamphletError> {
+    use hayro_jpeg2000::{DecodeSettings, Image};
+
+    let error = |e: hayro_jpeg2000::DecodeError| {
+        PamphletError::DecodeFailed(format!("Failed to decode image: {}", e))
+    };
+
+    // ヘッダーだけ読んで原寸を求め、目標の解像度を指定して開き直す
+    let full = Image::new(data, &DecodeSettings::default()).map_err(error)?;
+    let (width, height) = reduced_size(full.width(), full.height(), level);
+    let settings = DecodeSettings {
+        target_resolution: (level > 0).then_some((width, height)),
+        ..DecodeSettings::default()
+    };
+    let image = Image::new(data, &settings).map_err(error)?;
+
+    // 解像度レベルが足りない場合は、復号した最小のレベルから縮小する
+    let image = DynamicImage::from_decoder(image)
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to decode image: {}", e)))?;
+    Ok(resize_to(image, width, height))
+}
+
+#[cfg(not(feature = "jpeg2000"))]
+fn decode_jpeg2000(_data: &[u8], _level: u32) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat("Failed to decode image: JPEG 2000 is not supported in this build (enable the `jpeg2000` feature)".to_string()))
+}
+
+/// PSDの統合済みの画像（保存時の見た目）を読む
+///
+/// レイヤーは合成しないため、「互換性を優先」を有効にして保存したファイルが必要
+#[cfg(feature = "psd")]
+fn decode_psd(data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    use psd::{ColorMode, Psd, PsdDepth};
+
+    let psd = Psd::from_bytes(data)
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to decode image: {}", e)))?;
+    if !matches!(psd.color_mode(), ColorMode::Rgb | ColorMode::Grayscale)
+        || !matches!(psd.depth(), PsdDepth::Eight)
+    {
+        return Err(PamphletError::UnsupportedFormat(format!(
+            "Failed to decode image: unsupported PSD color mode {:?} ({}-bit), save as 8-bit RGB",
+            psd.color_mode(),
+            psd.depth() as u8
+        )));
+    }
+
+    image::RgbaImage::from_raw(psd.width(), psd.height(), psd.rgba())
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| {
+            PamphletError::DecodeFailed(
+                "Failed to decode image: invalid PSD image data".to_string(),
+            )
+        })
+}
+
+#[cfg(not(feature = "psd"))]
+fn decode_psd(_data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat(
+        "Failed to decode image: PSD is not supported in this build (enable the `psd` feature)"
+            .to_string(),
+    ))
+}
+
+#[cfg(feature = "raw")]
+fn decode_raw(data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    crate::raw::decode(data).map_err(PamphletError::DecodeFailed)
+}
+
+#[cfg(not(feature = "raw"))]
+fn decode_raw(_data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat("Failed to decode image: camera RAW is not supported in this build (enable the `raw` feature)".to_string()))
+}
+
+#[cfg(all(feature = "avif", not(target_arch = "wasm32")))]
+fn decode_avif(data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    crate::avif::decode(data).map_err(PamphletError::DecodeFailed)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decode_avif(_data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat("Failed to decode image: AVIF is not supported in the WebAssembly build (decode it with createImageBitmap and pass the pixels to `tile_rgba`)".to_string()))
+}
+
+#[cfg(all(not(feature = "avif"), not(target_arch = "wasm32")))]
+fn decode_avif(_data: &[u8]) -> Result<DynamicImage, PamphletError> {
+    Err(PamphletError::UnsupportedFormat(
+        "Failed to decode image: AVIF is not supported in this build (enable the `avif` feature)"
+            .to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ftyp` ボックスだけのISOBMFF
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let size = 16 + compatible.len() * 4;
+        let mut data = (size as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(b"ftyp");
+        data.extend_from_slice(major);
+        data.extend_from_slice(&[0; 4]);
+        for brand in compatible {
+            data.extend_from_slice(*brand);
+        }
+        data
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(&ftyp(b"heic", &[b"mif1"])), InputFormat::Heif);
+        // 主ブランドが汎用の mif1 でも、互換ブランドで判別する
+        assert_eq!(detect_format(&ftyp(b"mif1", &[b"heic"])), InputFormat::Heif);
+        assert_eq!(
+            detect_format(&ftyp(b"avif", &[b"mif1", b"miaf"])),
+            InputFormat::Avif
+        );
+        assert_eq!(
+            detect_format(&ftyp(b"isom", &[b"mp41"])),
+            InputFormat::Other
+        );
+        assert_eq!(detect_format(&[0xFF, 0x0A, 0xFA]), InputFormat::Jxl);
+        assert_eq!(detect_format(JXL_CONTAINER), InputFormat::Jxl);
+        assert_eq!(detect_format(JP2_CONTAINER), InputFormat::Jpeg2000);
+        assert_eq!(detect_format(b"8BPS\0\x01\0\0"), InputFormat::Psd);
+        assert_eq!(detect_format(b"II*\0\x10\0\0\0CR"), InputFormat::Raw);
+        assert_eq!(detect_format(b"MM\0*\0\0\0\x08"), InputFormat::Raw);
+        assert_eq!(
+            detect_format(&[0xFF, 0x4F, 0xFF, 0x51, 0x00]),
+            InputFormat::Jpeg2000
+        );
+        assert_eq!(detect_format(b"\x89PNG\r\n\x1a\n"), InputFormat::Other);
+        assert_eq!(detect_format(&[]), InputFormat::Other);
+    }
+
+    #[test]
+    fn test_decode_errors_instead_of_panicking() {
+        let error = decode_image(&ftyp(b"heic", &[b"mif1"]), &Settings::default()).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to decode image"));
+
+        let error = decode_image(&[0xFF, 0x0A, 0x00, 0x00], &Settings::default()).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to decode image"));
+    }
+
+    /// 画素データが空のPNG（ヘッダーだけが正しい）
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        // 8bit RGB、圧縮・フィルター・インターレースは既定
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, body) in [(b"IHDR", &ihdr[..]), (b"IDAT", &[]), (b"IEND", &[])] {
+            let mut chunk = kind.to_vec();
+            chunk.extend_from_slice(body);
+            let crc = chunk.iter().fold(!0u32, |crc, &byte| {
+                (0..8).fold(crc ^ byte as u32, |crc, _| {
+                    (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+                })
+            });
+            data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            data.extend_from_slice(&chunk);
+            data.extend_from_slice(&(!crc).to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let limits = DecodeLimits::default();
+        assert!(limits.check(32768, 8192).is_ok());
+        assert!(limits.check(32769, 1).is_err());
+        assert!(limits.check(20000, 20000).is_err());
+        let limits = DecodeLimits {
+            max_dimension: 100,
+            max_pixels: 5000,
+        };
+        assert!(limits.check(100, 50).is_ok());
+        assert!(limits.check(100, 51).is_err());
+
+        // 画素を確保する前にヘッダーの大きさで断る
+        let bomb = png_header(100_000, 100_000);
+        for error in [
+            decode_image(&bomb, &Settings::default()).unwrap_err(),
+            decode_image_into(&bomb, Vec::new(), &Settings::default()).unwrap_err(),
+            decode_pages(&bomb, AnimationMode::Pages, &Settings::default()).unwrap_err(),
+        ] {
+            assert_eq!(
+                error,
+                PamphletError::ImageTooLarge {
+                    width: 100_000,
+                    height: 100_000,
+                    limits: DecodeLimits::default(),
+                }
+            );
+        }
+        // 上限以内ならデコーダーのエラー（画素データがない）になる
+        let error = decode_image(&png_header(10, 10), &Settings::default()).unwrap_err();
+        assert!(matches!(error, PamphletError::DecodeFailed(_)), "{}", error);
+    }
+
+    #[test]
+    fn test_allowed_formats() {
+        let png = png_header(10, 10);
+        let psd = PSD_SIGNATURE.to_vec();
+        assert_eq!(SourceFormat::detect(&png), Some(SourceFormat::Png));
+        assert_eq!(SourceFormat::detect(&psd), Some(SourceFormat::Psd));
+        assert_eq!(SourceFormat::detect(b"not an image"), None);
+
+        let web = SourceFormat::Jpeg.bit() | SourceFormat::Png.bit() | SourceFormat::Webp.bit();
+        assert!(check_format_in(&png, web).is_ok());
+        assert!(check_format_in(b"not an image", web).is_ok());
+        let error = check_format_in(&psd, web).unwrap_err();
+        assert_eq!(
+            error,
+            PamphletError::UnsupportedFormat(
+                "Unsupported input format: psd is not allowed (allowed formats: jpeg, png, webp)"
+                    .to_string()
+            )
+        );
+        assert!(check_format_in(&png, 0)
+            .unwrap_err()
+            .to_string()
+            .ends_with("(allowed formats: none)"));
+
+        // 既定ではすべて許可する
+        assert_eq!(Settings::default().formats(), SourceFormat::ALL);
+        let settings = Settings {
+            allowed_formats: Some(vec![SourceFormat::Jpeg]),
+            ..Settings::default()
+        };
+        assert!(check_format(&psd, &settings).is_err());
+        assert!(check_format(&psd, &Settings::default()).is_ok());
+        let names: Vec<String> = SourceFormat::ALL.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            serde_json::to_value(SourceFormat::ALL).unwrap(),
+            serde_json::json!(names)
+        );
+    }
+
+    #[test]
+    fn test_decode_image_reduced() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::new(5, 3));
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let reduced = decode_image_reduced(&png, 1, &Settings::default()).unwrap();
+        assert_eq!((reduced.width(), reduced.height()), (3, 2));
+        let reduced = decode_image_reduced(&png, 8, &Settings::default()).unwrap();
+        assert_eq!((reduced.width(), reduced.height()), (1, 1));
+
+        assert_eq!(reduced_size(512, 300, 2), (128, 75));
+        assert_eq!(reduced_size(512, 300, 0), (512, 300));
+    }
+
+    #[test]
+    fn test_decode_image_into() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(6, 4, |x, y| {
+            image::Rgb([x as u8 * 40, y as u8 * 60, 100])
+        }));
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+            let mut data = Vec::new();
+            image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+
+            // 大きすぎるバッファを渡しても、結果は `decode_image` と同じ
+            let buffer = Vec::with_capacity(1024);
+            let decoded = decode_image_into(&data, buffer, &Settings::default()).unwrap();
+            assert_eq!(
+                decoded,
+                decode_image(&data, &Settings::default()).unwrap(),
+                "{:?}",
+                format
+            );
+
+            let buffer = into_buffer(decoded);
+            assert_eq!(buffer.len(), 6 * 4 * 3);
+            assert!(buffer.capacity() >= 1024);
+        }
+    }
+
+    /// 統合済みの画像だけを持つ、8bit RGBの非圧縮PSD
+    #[cfg(feature = "psd")]
+    fn psd(width: u32, height: u32, channels: [&[u8]; 3]) -> Vec<u8> {
+        let mut data = b"8BPS\0\x01".to_vec();
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&3u16.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&8u16.to_be_bytes());
+        data.extend_from_slice(&3u16.to_be_bytes());
+        // カラーモードデータ・画像リソース・レイヤーとマスクはいずれも空
+        data.extend_from_slice(&[0; 12]);
+        // 非圧縮、チャンネルごとの平面
+        data.extend_from_slice(&0u16.to_be_bytes());
+        for channel in channels {
+            data.extend_from_slice(channel);
+        }
+        data
+    }
+
+    /// JPEG XLのコードストリーム（jxl-oxideのドキュメントの例）
+    const JXL: [u8; 42] = [
+        0xff, 0x0a, 0x30, 0x54, 0x10, 0x09, 0x08, 0x06, 0x01, 0x00, 0x78, 0x00, 0x4b, 0x38, 0x41,
+        0x3c, 0xb6, 0x3a, 0x51, 0xfe, 0x00, 0x47, 0x1e, 0xa0, 0x85, 0xb8, 0x27, 0x1a, 0x48, 0x45,
+        0x84, 0x1b, 0x71, 0x4f, 0xa8, 0x3e, 0x8e, 0x30, 0x03, 0x92, 0x84, 0x01,
+    ];
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_decode_jxl() {
+        let image = decode_image(&JXL, &Settings::default()).unwrap();
+        assert!(image.width() > 0 && image.height() > 0);
+
+        assert!(matches!(
+            decode_image(&JXL[..20], &Settings::default()),
+            Err(PamphletError::DecodeFailed(_))
+        ));
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    #[test]
+    fn test_decode_jxl_unsupported() {
+        assert!(matches!(
+            decode_image(&JXL, &Settings::default()),
+            Err(PamphletError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[cfg(feature = "psd")]
+    #[test]
+    fn test_decode_psd_composite() {
+        let data = psd(2, 1, [&[255, 0], &[0, 255], &[0, 0]]);
+        let image = decode_image(&data, &Settings::default())
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 255, 0, 255]);
+    }
+
+    /// 2x1ピクセルの3フレームのGIF（フレームごとに左のピクセルの色が変わる）
+    fn animated_gif() -> Vec<u8> {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            for red in [0u8, 128, 255] {
+                let buffer = image::RgbaImage::from_pixel(2, 1, image::Rgba([red, 0, 0, 255]));
+                encoder.encode_frame(image::Frame::new(buffer)).unwrap();
+            }
+        }
+        gif
+    }
+
+    #[test]
+    fn test_animation_mode() {
+        let gif = animated_gif();
+
+        let first = decode_pages(&gif, AnimationMode::FirstFrame, &Settings::default()).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].to_rgba8().get_pixel(0, 0)[0], 0);
+
+        let pages = decode_pages(&gif, AnimationMode::Pages, &Settings::default()).unwrap();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[2].to_rgba8().get_pixel(0, 0)[0], 255);
+
+        // 通常のデコードは最初のフレーム
+        assert_eq!(
+            decode_image(&gif, &Settings::default())
+                .unwrap()
+                .to_rgba8()
+                .get_pixel(0, 0)[0],
+            0
+        );
+
+        // アニメーションでない画像は1ページ
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(image::RgbImage::new(1, 1))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert_eq!(
+            decode_pages(&png, AnimationMode::Pages, &Settings::default())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! JavaScriptに返すエラー（機械で判別できるコードと詳細付き）
+
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::JsValue;
+
+use crate::decode::DecodeLimits;
+
+/// タイル化・ビューアの処理のエラー
+///
+/// JavaScriptには `{ code, message, details, key, params }` のプロパティを持つ `Error` として投げる。
+/// フロントエンドは `code` で分岐するか、`key` と `params` で翻訳したメッセージを表示できる
+#[derive(Debug, Clone, PartialEq)]
+pub enum PamphletError {
+    /// 画像の読み込み・デコードに失敗した
+    DecodeFailed(String),
+    /// 対応していない形式（このビルドで無効な形式を含む）
+    UnsupportedFormat(String),
+    /// 画像がデコードの上限（`set_decode_limits`）より大きい（`limits` はそのときの上限）
+    ImageTooLarge {
+        width: u32,
+        height: u32,
+        limits: DecodeLimits,
+    },
+    /// タイルのエンコードに失敗した
+    EncodeFailed(String),
+    /// タイルサイズが範囲外
+    InvalidTileSize { tile_size: u32, min: u32, max: u32 },
+    /// メモリを確保できなかった
+    OutOfMemory { bytes: usize },
+    /// 引数が不正
+    InvalidArgument(String),
+    /// 指定したタイル・ページ・バッファなどが存在しない（解放済みを含む）
+    NotFound(String),
+    /// その他のエラー
+    Internal(String),
+}
+
+impl PamphletError {
+    /// 機械で判別するためのコード（`"decode_failed"` など）
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DecodeFailed(_) => "decode_failed",
+            Self::UnsupportedFormat(_) => "unsupported_format",
+            Self::ImageTooLarge { .. } => "image_too_large",
+            Self::EncodeFailed(_) => "encode_failed",
+            Self::InvalidTileSize { .. } => "invalid_tile_size",
+            Self::OutOfMemory { .. } => "out_of_memory",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::NotFound(_) => "not_found",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// エラーの詳細（コードごとの値、ない場合は `None`）
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::InvalidTileSize {
+                tile_size,
+                min,
+                max,
+            } => Some(serde_json::json!({ "tile_size": tile_size, "min": min, "max": max })),
+            Self::OutOfMemory { bytes } => Some(serde_json::json!({ "bytes": bytes })),
+            Self::ImageTooLarge { limits, .. } => serde_json::to_value(limits).ok(),
+            _ => None,
+        }
+    }
+
+    /// 翻訳のキー（`"errors.decode_failed"` など、コードごとに変わらない）
+    pub fn key(&self) -> String {
+        format!("errors.{}", self.code())
+    }
+
+    /// 翻訳したメッセージに埋め込む値
+    ///
+    /// `details` の値に加えて、`image_too_large` は画像の大きさ（`width`・`height`）、
+    /// メッセージだけのエラーは元のメッセージ（`detail`）を持つ
+    pub fn params(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut params = match self.details() {
+            Some(serde_json::Value::Object(details)) => details,
+            _ => serde_json::Map::new(),
+        };
+        match self {
+            Self::InvalidTileSize { .. } | Self::OutOfMemory { .. } => {}
+            Self::ImageTooLarge { width, height, .. } => {
+                params.insert("width".into(), (*width).into());
+                params.insert("height".into(), (*height).into());
+                params.insert("detail".into(), self.to_string().into());
+            }
+            Self::DecodeFailed(message)
+            | Self::UnsupportedFormat(message)
+            | Self::EncodeFailed(message)
+            | Self::InvalidArgument(message)
+            | Self::NotFound(message)
+            | Self::Internal(message) => {
+                params.insert("detail".into(), message.as_str().into());
+            }
+        }
+        params
+    }
+
+    /// メッセージの前に `context`（ファイル名など）を付ける（値だけのエラーはそのまま）
+    pub fn with_context(self, context: &str) -> Self {
+        let prefix = |message: String| format!("{}: {}", context, message);
+        match self {
+            Self::DecodeFailed(message) => Self::DecodeFailed(prefix(message)),
+            Self::UnsupportedFormat(message) => Self::UnsupportedFormat(prefix(message)),
+            Self::EncodeFailed(message) => Self::EncodeFailed(prefix(message)),
+            Self::InvalidArgument(message) => Self::InvalidArgument(prefix(message)),
+            Self::NotFound(message) => Self::NotFound(prefix(message)),
+            Self::Internal(message) => Self::Internal(prefix(message)),
+            error => error,
+        }
+    }
+
+    /// `locale` のメッセージ（対応していない言語の場合は英語の `message`）
+    pub fn localize(&self, locale: &str) -> String {
+        localize(locale, &self.key(), &self.params()).unwrap_or_else(|| self.to_string())
+    }
+}
+
+/// 英語のメッセージ
+const MESSAGES_EN: &[(&str, &str)] = &[
+    ("errors.decode_failed", "Failed to read the image ({detail})"),
+    ("errors.unsupported_format", "This image format is not supported ({detail})"),
+    (
+        "errors.image_too_large",
+        "The image is too large (up to {max_dimension} pixels per side and {max_pixels} pixels in total)",
+    ),
+    ("errors.encode_failed", "Failed to encode the tiles ({detail})"),
+    (
+        "errors.invalid_tile_size",
+        "Invalid tile size: {tile_size} (must be between {min} and {max})",
+    ),
+    ("errors.out_of_memory", "Not enough memory ({bytes} bytes)"),
+    ("errors.invalid_argument", "Invalid argument ({detail})"),
+    ("errors.not_found", "Not found ({detail})"),
+    ("errors.internal", "An unexpected error occurred ({detail})"),
+];
+
+/// 日本語のメッセージ
+const MESSAGES_JA: &[(&str, &str)] = &[
+    (
+        "errors.decode_failed",
+        "画像を読み込めませんでした（{detail}）",
+    ),
+    (
+        "errors.unsupported_format",
+        "対応していない画像の形式です（{detail}）",
+    ),
+    (
+        "errors.image_too_large",
+        "画像が大きすぎます（1辺{max_dimension}ピクセル、合計{max_pixels}ピクセルまで）",
+    ),
+    (
+        "errors.encode_failed",
+        "タイルを作成できませんでした（{detail}）",
+    ),
+    (
+        "errors.invalid_tile_size",
+        "タイルサイズ{tile_size}は指定できません（{min}から{max}まで）",
+    ),
+    (
+        "errors.out_of_memory",
+        "メモリが足りません（{bytes}バイト）",
+    ),
+    (
+        "errors.invalid_argument",
+        "引数が正しくありません（{detail}）",
+    ),
+    ("errors.not_found", "見つかりません（{detail}）"),
+    (
+        "errors.internal",
+        "予期しないエラーが発生しました（{detail}）",
+    ),
+];
+
+/// 組み込みのメッセージのキーと文（`"ja"`・`"en"`、`"ja-JP"` のような地域付きも可）
+pub fn messages(locale: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    let language = locale.split(['-', '_']).next().unwrap_or_default();
+    match language.to_ascii_lowercase().as_str() {
+        "ja" => Some(MESSAGES_JA),
+        "en" => Some(MESSAGES_EN),
+        _ => None,
+    }
+}
+
+/// `key` の組み込みのメッセージに `params` を埋め込む（言語かキーがない場合は `None`）
+pub fn localize(
+    locale: &str,
+    key: &str,
+    params: &serde_json::Map<String, serde_json::Value>,
+) -> Option<String> {
+    messages(locale)?
+        .iter()
+        .find(|(message_key, _)| *message_key == key)
+        .map(|(_, template)| format_message(template, params))
+}
+
+/// メッセージの `{name}` を `params` の値で置き換える（ない値はそのまま残す）
+pub fn format_message(
+    template: &str,
+    params: &serde_json::Map<String, serde_json::Value>,
+) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        message.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + end + 1];
+        match params.get(&placeholder[1..placeholder.len() - 1]) {
+            Some(serde_json::Value::String(value)) => message.push_str(value),
+            Some(value) => message.push_str(&value.to_string()),
+            None => message.push_str(placeholder),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    message.push_str(rest);
+    message
+}
+
+impl fmt::Display for PamphletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DecodeFailed(message)
+            | Self::UnsupportedFormat(message)
+            | Self::EncodeFailed(message)
+            | Self::InvalidArgument(message)
+            | Self::NotFound(message)
+            | Self::Internal(message) => f.write_str(message),
+            Self::InvalidTileSize {
+                tile_size,
+                min,
+                max,
+            } => write!(
+                f,
+                "Invalid tile size: {} (must be between {} and {})",
+                tile_size, min, max
+            ),
+            Self::ImageTooLarge {
+                width,
+                height,
+                limits,
+            } => write!(
+                f,
+                "Image is too large: {}x{} pixels (limit: {} pixels per side, {} pixels in total)",
+                width, height, limits.max_dimension, limits.max_pixels
+            ),
+            Self::OutOfMemory { bytes } => write!(f, "Failed to allocate {} bytes", bytes),
+        }
+    }
+}
+
+impl std::error::Error for PamphletError {}
+
+/// 文字列のエラーを返す各モジュール（PDF・ZIP・書き出しなど）から `?` で使う
+impl From<PamphletError> for String {
+    fn from(error: PamphletError) -> Self {
+        error.to_string()
+    }
+}
+
+impl Serialize for PamphletError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PamphletError", 5)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.serialize_field("key", &self.key())?;
+        state.serialize_field("params", &self.params())?;
+        state.end()
+    }
+}
+
+/// `{ code, message, details, key, params }` のプロパティを持つJavaScriptの `Error` にする
+#[cfg(feature = "wasm")]
+impl From<PamphletError> for JsValue {
+    fn from(error: PamphletError) -> Self {
+        let js_error = js_sys::Error::new(&error.to_string());
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        let details = error
+            .details()
+            .and_then(|details| details.serialize(&serializer).ok())
+            .unwrap_or(JsValue::NULL);
+        let params = error
+            .params()
+            .serialize(&serializer)
+            .unwrap_or(JsValue::NULL);
+        // プロパティの設定は通常のオブジェクトでは失敗しない
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &error.code().into());
+        let _ = js_sys::Reflect::set(&js_error, &"details".into(), &details);
+        let _ = js_sys::Reflect::set(&js_error, &"key".into(), &error.key().into());
+        let _ = js_sys::Reflect::set(&js_error, &"params".into(), &params);
+        js_error.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_too_large() {
+        let limits = DecodeLimits {
+            max_dimension: 1000,
+            max_pixels: 500_000,
+        };
+        let error = PamphletError::ImageTooLarge {
+            width: 2000,
+            height: 100,
+            limits,
+        };
+        assert_eq!(error.code(), "image_too_large");
+        assert_eq!(
+            error.to_string(),
+            "Image is too large: 2000x100 pixels (limit: 1000 pixels per side, 500000 pixels in total)"
+        );
+        // 詳細はエラーになったときの上限（現在の上限ではない）
+        assert_eq!(
+            error.details().unwrap(),
+            serde_json::json!({ "max_dimension": 1000, "max_pixels": 500000 })
+        );
+        assert_eq!(String::from(error.clone()), error.to_string());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let error = PamphletError::InvalidTileSize {
+            tile_size: 0,
+            min: 16,
+            max: 4096,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "invalid_tile_size",
+                "message": "Invalid tile size: 0 (must be between 16 and 4096)",
+                "details": { "tile_size": 0, "min": 16, "max": 4096 },
+                "key": "errors.invalid_tile_size",
+                "params": { "tile_size": 0, "min": 16, "max": 4096 },
+            })
+        );
+
+        let error = PamphletError::DecodeFailed("Failed to decode image: truncated".to_string());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "decode_failed",
+                "message": "Failed to decode image: truncated",
+                "details": null,
+                "key": "errors.decode_failed",
+                "params": { "detail": "Failed to decode image: truncated" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_localize() {
+        let error = PamphletError::InvalidTileSize {
+            tile_size: 0,
+            min: 16,
+            max: 4096,
+        };
+        assert_eq!(
+            error.localize("ja-JP"),
+            "タイルサイズ0は指定できません（16から4096まで）"
+        );
+        assert_eq!(error.localize("en"), error.to_string());
+        // 対応していない言語は英語のメッセージ
+        assert_eq!(error.localize("fr"), error.to_string());
+
+        let error = PamphletError::ImageTooLarge {
+            width: 40000,
+            height: 30000,
+            limits: DecodeLimits::default(),
+        };
+        assert_eq!(error.params()["width"], 40000);
+        assert_eq!(error.params()["height"], 30000);
+        assert_eq!(
+            error.localize("ja"),
+            "画像が大きすぎます（1辺32768ピクセル、合計268435456ピクセルまで）"
+        );
+
+        // どの言語もすべてのコードのメッセージを持つ
+        for messages in [messages("ja").unwrap(), messages("en").unwrap()] {
+            let keys: Vec<_> = messages.iter().map(|(key, _)| *key).collect();
+            assert_eq!(
+                keys,
+                MESSAGES_EN.iter().map(|(key, _)| *key).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_message() {
+        let params = serde_json::json!({ "name": "page", "count": 3 });
+        let params = params.as_object().unwrap();
+        assert_eq!(format_message("{count} {name}s", params), "3 pages");
+        assert_eq!(format_message("{missing} {", params), "{missing} {");
+    }
+}
@@ -0,0 +1,141 @@
+use std::f64::consts::FRAC_PI_4;
+
+use serde::{Deserialize, Serialize};
+
+/// 表示フィルターの設定
+///
+/// 夜間モードは `{ invert: true, contrast: -0.2 }` のように組み合わせて指定する
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterSettings {
+    /// 明るさ（-1.0〜1.0、0.0 = 変更なし）
+    pub brightness: f64,
+    /// コントラスト（-1.0〜1.0、0.0 = 変更なし）
+    pub contrast: f64,
+    /// セピアの強さ（0.0〜1.0）
+    pub sepia: f64,
+    /// 色を反転する
+    pub invert: bool,
+}
+
+/// 表示フィルター
+///
+/// 明るさ・コントラストは256段階のルックアップテーブルにまとめ、画素ごとの計算を表引きにする。
+/// 適用順は 明るさ・コントラスト → セピア → 反転。
+#[derive(Debug, Clone)]
+pub struct Filter {
+    lut: [u8; 256],
+    sepia: f64,
+    invert: bool,
+}
+
+impl Filter {
+    pub fn new(settings: &FilterSettings) -> Self {
+        let brightness = settings.brightness.clamp(-1.0, 1.0) * 255.0;
+        // -1.0 → 0倍（灰一色）、0.0 → 1倍、1.0 → 無限大（二値化）に近づく
+        let contrast = ((settings.contrast.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4).tan();
+
+        let mut lut = [0u8; 256];
+        for (value, entry) in lut.iter_mut().enumerate() {
+            let v = (value as f64 + brightness - 128.0) * contrast + 128.0;
+            *entry = v.round().clamp(0.0, 255.0) as u8;
+        }
+
+        Self {
+            lut,
+            sepia: settings.sepia.clamp(0.0, 1.0),
+            invert: settings.invert,
+        }
+    }
+
+    /// 何も変更しない設定か
+    pub fn is_identity(&self) -> bool {
+        !self.invert
+            && self.sepia == 0.0
+            && self.lut.iter().enumerate().all(|(i, v)| *v as usize == i)
+    }
+
+    /// RGBA画素に適用する（アルファは変更しない）
+    pub fn apply(&self, pixels: &mut [u8]) {
+        if self.is_identity() {
+            return;
+        }
+
+        for pixel in pixels.chunks_exact_mut(4) {
+            let mut rgb = [
+                self.lut[pixel[0] as usize],
+                self.lut[pixel[1] as usize],
+                self.lut[pixel[2] as usize],
+            ];
+
+            if self.sepia > 0.0 {
+                let [r, g, b] = rgb.map(|c| c as f64);
+                let toned = [
+                    0.393 * r + 0.769 * g + 0.189 * b,
+                    0.349 * r + 0.686 * g + 0.168 * b,
+                    0.272 * r + 0.534 * g + 0.131 * b,
+                ];
+                for (channel, toned) in rgb.iter_mut().zip(toned) {
+                    let mixed = *channel as f64 + (toned - *channel as f64) * self.sepia;
+                    *channel = mixed.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+
+            if self.invert {
+                rgb = rgb.map(|c| 255 - c);
+            }
+
+            pixel[..3].copy_from_slice(&rgb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(settings: FilterSettings, rgba: [u8; 4]) -> [u8; 4] {
+        let mut pixels = rgba;
+        Filter::new(&settings).apply(&mut pixels);
+        pixels
+    }
+
+    #[test]
+    fn test_default_is_identity() {
+        let filter = Filter::new(&FilterSettings::default());
+        assert!(filter.is_identity());
+        assert_eq!(
+            apply(FilterSettings::default(), [12, 34, 56, 78]),
+            [12, 34, 56, 78]
+        );
+    }
+
+    #[test]
+    fn test_adjustments() {
+        let brighter = FilterSettings {
+            brightness: 0.2,
+            ..Default::default()
+        };
+        assert_eq!(apply(brighter, [100, 250, 0, 255]), [151, 255, 51, 255]);
+
+        let flat = FilterSettings {
+            contrast: -1.0,
+            ..Default::default()
+        };
+        assert_eq!(apply(flat, [0, 128, 255, 255]), [128, 128, 128, 255]);
+
+        // 夜間モード: 白い紙面が暗くなり、アルファは保持
+        let night = FilterSettings {
+            invert: true,
+            ..Default::default()
+        };
+        assert_eq!(apply(night, [255, 255, 255, 200]), [0, 0, 0, 200]);
+
+        let sepia = FilterSettings {
+            sepia: 1.0,
+            ..Default::default()
+        };
+        let [r, g, b, _] = apply(sepia, [128, 128, 128, 255]);
+        assert!(r > g && g > b);
+    }
+}
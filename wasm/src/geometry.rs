@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+/// 2次元座標
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    /// 2点間の距離
+    pub fn distance(&self, other: &Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// 矩形（左上座標とサイズ）
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    /// 面積を持つ範囲で交差しているか（辺が接しているだけの場合は含まない）
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// 点が矩形内にあるか（右端・下端を含む）
+    pub fn contains(&self, point: &Point) -> bool {
+        point.x >= self.x
+            && point.x <= self.right()
+            && point.y >= self.y
+            && point.y <= self.bottom()
+    }
+
+    /// 矩形 `other` が完全に内側にあるか
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.right() <= self.right()
+            && other.y >= self.y
+            && other.bottom() <= self.bottom()
+    }
+
+    /// 2つの矩形を囲む最小の矩形
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    /// 全座標を `factor` 倍した矩形
+    pub fn scale(&self, factor: f64) -> Rect {
+        Rect::new(
+            self.x * factor,
+            self.y * factor,
+            self.width * factor,
+            self.height * factor,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_intersects() {
+        let a = Rect::new(0.0, 0.0, 100.0, 100.0);
+
+        assert!(a.intersects(&Rect::new(50.0, 50.0, 100.0, 100.0)));
+        // 辺が接しているだけの場合は交差しない
+        assert!(!a.intersects(&Rect::new(100.0, 0.0, 100.0, 100.0)));
+    }
+
+    #[test]
+    fn test_rect_contains_and_union() {
+        let rect = Rect::new(10.0, 10.0, 20.0, 20.0);
+
+        assert!(rect.contains(&Point::new(30.0, 30.0)));
+        assert!(!rect.contains(&Point::new(31.0, 15.0)));
+        assert!(rect.contains_rect(&Rect::new(15.0, 15.0, 5.0, 5.0)));
+
+        let union = rect.union(&Rect::new(0.0, 20.0, 5.0, 40.0));
+        assert_eq!(union, Rect::new(0.0, 10.0, 30.0, 50.0));
+    }
+
+    #[test]
+    fn test_rect_center_and_scale() {
+        let rect = Rect::new(10.0, 10.0, 20.0, 20.0);
+
+        assert_eq!(rect.center(), Point::new(20.0, 20.0));
+        assert_eq!(rect.scale(0.5), Rect::new(5.0, 5.0, 10.0, 10.0));
+    }
+}
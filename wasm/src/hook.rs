@@ -0,0 +1,218 @@
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PamphletError;
+use crate::hasher;
+use crate::tiler::{self, TileInfo, TileResult, TilerContext};
+
+/// 外部の処理（超解像など）を呼び出す単位
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookMode {
+    /// ページ全体を1回で処理する
+    #[default]
+    Page,
+    /// タイルごとに処理する（大きなページでもモデルの入力を小さく保てる）
+    Tile,
+}
+
+/// ページ全体を処理した結果からタイル化する
+///
+/// # Returns
+/// タイル化結果と、処理による拡大の倍率（大きさが変わらなければ `None`）
+///
+/// # Errors
+/// 処理結果の縦横比が元の画像と違う場合、タイルのエンコードに失敗した場合
+pub fn tile_hooked_page(
+    original: &DynamicImage,
+    processed: RgbaImage,
+    tile_size: u32,
+    quality: Option<f32>,
+    context: &mut TilerContext,
+) -> Result<(TileResult, Option<f64>), PamphletError> {
+    let scale = processed.width() as f64 / original.width() as f64;
+    let expected_height = (original.height() as f64 * scale).round() as u32;
+    if processed.width() == 0 || processed.height().abs_diff(expected_height) > 1 {
+        return Err(PamphletError::InvalidArgument(format!(
+            "Hook returned {}x{} for a {}x{} page; the aspect ratio must be kept",
+            processed.width(),
+            processed.height(),
+            original.width(),
+            original.height()
+        )));
+    }
+
+    let result = context.tile_decoded(&DynamicImage::ImageRgba8(processed), tile_size, quality)?;
+    Ok((result, (scale != 1.0).then_some(scale)))
+}
+
+/// タイルごとに外部の処理を挟んでタイル化する
+///
+/// `next_input` で処理するタイル（端のタイルは透明で埋めた `tile_size` 四方）を取り出し、
+/// 処理結果を `push` で渡す。すべてのタイルの処理結果は同じ大きさの正方形でなければならず、
+/// 大きさが変わった場合はその倍率でページ全体を拡大したものとして扱う。
+pub struct HookedTiler {
+    image: DynamicImage,
+    tile_size: u32,
+    quality: Option<f32>,
+    tiles_x: u32,
+    tiles_y: u32,
+    next: u32,
+    output_size: Option<u32>,
+    tiles: Vec<TileInfo>,
+}
+
+impl HookedTiler {
+    pub fn new(
+        image: DynamicImage,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<Self, PamphletError> {
+        tiler::validate_tile_size(tile_size)?;
+        Ok(Self {
+            tiles_x: image.width().div_ceil(tile_size),
+            tiles_y: image.height().div_ceil(tile_size),
+            image,
+            tile_size,
+            quality,
+            next: 0,
+            output_size: None,
+            tiles: Vec::new(),
+        })
+    }
+
+    /// 次に処理するタイルの座標（タイル単位）と画像（すべて処理した場合は `None`）
+    pub fn next_input(&mut self) -> Result<Option<(u32, u32, RgbaImage)>, PamphletError> {
+        if self.next >= self.tiles_x * self.tiles_y {
+            return Ok(None);
+        }
+        let (tx, ty) = (self.next % self.tiles_x, self.next / self.tiles_x);
+        self.next += 1;
+
+        let (x, y) = (tx * self.tile_size, ty * self.tile_size);
+        let w = self.tile_size.min(self.image.width() - x);
+        let h = self.tile_size.min(self.image.height() - y);
+        let tile = tiler::crop_and_pad(&self.image, x, y, w, h, self.tile_size)?;
+        Ok(Some((tx, ty, tile.to_rgba8())))
+    }
+
+    /// タイルの処理結果をエンコードする
+    ///
+    /// # Errors
+    /// 処理結果が正方形でない場合、前のタイルと大きさが違う場合、エンコードに失敗した場合
+    pub fn push(&mut self, tx: u32, ty: u32, processed: RgbaImage) -> Result<(), PamphletError> {
+        let (width, height) = processed.dimensions();
+        if width != height || width == 0 {
+            return Err(PamphletError::InvalidArgument(format!(
+                "Hook returned {}x{} for tile ({}, {}); tiles must stay square",
+                width, height, tx, ty
+            )));
+        }
+        match self.output_size {
+            Some(size) if size != width => {
+                return Err(PamphletError::InvalidArgument(format!(
+                    "Hook returned {}x{} for tile ({}, {}) but {}x{} for earlier tiles",
+                    width, height, tx, ty, size, size
+                )));
+            }
+            _ => self.output_size = Some(width),
+        }
+
+        let data = tiler::encode_webp(
+            &DynamicImage::ImageRgba8(processed),
+            self.quality.unwrap_or(80.0),
+        )?;
+        self.tiles.push(TileInfo {
+            x: tx,
+            y: ty,
+            hash: hasher::calculate_hash(&data),
+            data,
+        });
+        Ok(())
+    }
+
+    /// タイル化結果と、処理による拡大の倍率（大きさが変わらなければ `None`）
+    pub fn finish(self) -> (TileResult, Option<f64>) {
+        let output_size = self.output_size.unwrap_or(self.tile_size);
+        let scale = output_size as f64 / self.tile_size as f64;
+        let scaled = |size: u32| (size as f64 * scale).round() as u32;
+        let result = TileResult {
+            width: scaled(self.image.width()),
+            height: scaled(self.image.height()),
+            tile_size: output_size,
+            tiles: self.tiles,
+            timings: None,
+            warnings: Vec::new(),
+        };
+        (result, (scale != 1.0).then_some(scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::imageops::{self, FilterType};
+    use image::Rgba;
+
+    fn page() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 60, Rgba([10, 20, 30, 255])))
+    }
+
+    #[test]
+    fn test_hooked_tiler() {
+        let mut tiler = HookedTiler::new(page(), 64, None).unwrap();
+        let mut seen = Vec::new();
+        while let Some((tx, ty, tile)) = tiler.next_input().unwrap() {
+            assert_eq!(tile.dimensions(), (64, 64));
+            seen.push((tx, ty));
+            // 2倍に拡大するモデル
+            let upscaled = imageops::resize(&tile, 128, 128, FilterType::Nearest);
+            tiler.push(tx, ty, upscaled).unwrap();
+        }
+        assert_eq!(seen, vec![(0, 0), (1, 0)]);
+
+        let (result, scale) = tiler.finish();
+        assert_eq!(scale, Some(2.0));
+        assert_eq!(
+            (result.width, result.height, result.tile_size),
+            (200, 120, 128)
+        );
+        assert_eq!(result.tiles.len(), 2);
+    }
+
+    #[test]
+    fn test_hooked_tiler_errors() {
+        let mut tiler = HookedTiler::new(page(), 64, None).unwrap();
+        let (tx, ty, tile) = tiler.next_input().unwrap().unwrap();
+        assert!(tiler.push(tx, ty, RgbaImage::new(64, 32)).is_err());
+        tiler.push(tx, ty, tile).unwrap();
+
+        // 前のタイルと大きさが違う
+        let (tx, ty, _) = tiler.next_input().unwrap().unwrap();
+        assert!(tiler.push(tx, ty, RgbaImage::new(128, 128)).is_err());
+
+        assert!(HookedTiler::new(page(), 0, None).is_err());
+    }
+
+    #[test]
+    fn test_tile_hooked_page() {
+        let processed = RgbaImage::from_pixel(300, 180, Rgba([0, 0, 0, 255]));
+        let (result, scale) =
+            tile_hooked_page(&page(), processed, 256, None, &mut TilerContext::new()).unwrap();
+        assert_eq!(scale, Some(3.0));
+        assert_eq!((result.width, result.height), (300, 180));
+
+        let (_, scale) = tile_hooked_page(
+            &page(),
+            page().to_rgba8(),
+            256,
+            None,
+            &mut TilerContext::new(),
+        )
+        .unwrap();
+        assert_eq!(scale, None);
+
+        let squashed = RgbaImage::new(200, 60);
+        assert!(tile_hooked_page(&page(), squashed, 256, None, &mut TilerContext::new()).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use serde::Serialize;
+
+use crate::hasher;
+use crate::TileMetadata;
+
+/// 取得したタイルの内容がmetadataのハッシュと一致しない
+///
+/// CDNのキャッシュが古い・破損している場合に発生する
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TileMismatch {
+    /// エラーの種類（JavaScript側での判別用、常に `"hash_mismatch"`）
+    pub code: &'static str,
+    pub x: u32,
+    pub y: u32,
+    /// metadataに記録されたハッシュ
+    pub expected: String,
+    /// 取得した内容のハッシュ
+    pub actual: String,
+    /// 取得した内容のバイト数
+    pub byte_length: usize,
+}
+
+impl std::fmt::Display for TileMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Tile ({}, {}) hash mismatch: expected {}, got {} ({} bytes)",
+            self.x, self.y, self.expected, self.actual, self.byte_length
+        )
+    }
+}
+
+/// 取得したタイルの内容がmetadataのハッシュと一致するか検証する
+pub fn verify_tile(data: &[u8], tile: &TileMetadata) -> Result<(), TileMismatch> {
+    let actual = hasher::calculate_hash(data);
+    if actual.eq_ignore_ascii_case(&tile.hash) {
+        return Ok(());
+    }
+
+    Err(TileMismatch {
+        code: "hash_mismatch",
+        x: tile.x,
+        y: tile.y,
+        expected: tile.hash.clone(),
+        actual,
+        byte_length: data.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_tile() {
+        let data = b"tile bytes";
+        let tile = TileMetadata {
+            x: 2,
+            y: 1,
+            hash: hasher::calculate_hash(data),
+        };
+        assert_eq!(verify_tile(data, &tile), Ok(()));
+
+        let mismatch = verify_tile(b"stale", &tile).unwrap_err();
+        assert_eq!((mismatch.x, mismatch.y), (2, 1));
+        assert_eq!(mismatch.expected, tile.hash);
+        assert_eq!(mismatch.actual, hasher::calculate_hash(b"stale"));
+        assert_eq!(mismatch.byte_length, 5);
+    }
+}
@@ -0,0 +1,1186 @@
+use std::num::Wrapping;
+
+use image::{DynamicImage, ImageBuffer};
+
+use crate::error::PamphletError;
+
+/// ジグザグ順の係数の位置（自然順のインデックス）
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// ハフマン符号の表引きに使うビット数
+const LOOKUP_BITS: u32 = 8;
+
+fn error(message: &str) -> PamphletError {
+    PamphletError::DecodeFailed(format!("Failed to decode JPEG: {}", message))
+}
+
+/// ベースラインJPEGを上から帯（数行ずつ）に分けてデコードする
+///
+/// ページ全体の画素を持たずにタイルの行ごとの帯だけをデコードし、縦長のページの
+/// タイル化でメモリの使用量の最大値を抑える。帯の外に持つのはMCUの2行分だけ。
+/// 逆DCT・色差の拡大・色の変換は `image` クレートのデコーダー（zune-jpeg）と同じ
+/// 整数の計算で行い、画素は通常のデコードの結果と完全に一致する。zune-jpegは画像
+/// 全体をまとめてデコードするAPIしかないため、エントロピー符号の復号から自前で行う。
+///
+/// 通常のデコードと結果が変わりうる入力（壊れたデータ、対応していない標本化係数など）
+/// はエラーか `None` にし、呼び出し側で通常のデコードに切り替える。
+pub struct BandDecoder<'a> {
+    width: u32,
+    height: u32,
+    /// スキャンの順の成分
+    components: Vec<Component>,
+    /// 色差の拡大の方向
+    upsampling: Upsampling,
+    /// MCUの横の数と縦の数
+    mcus_x: usize,
+    mcus_y: usize,
+    quant: [Option<[u16; 64]>; 4],
+    dc_tables: [Option<Huffman>; 4],
+    ac_tables: [Option<Huffman>; 4],
+    /// リスタートマーカーの間隔（MCU単位、0 = なし）
+    restart_interval: usize,
+    /// 3成分をYCbCrでなくRGBとして扱う
+    rgb: bool,
+    reader: BitReader<'a>,
+    /// 次にデコードするMCUの行
+    mcu_row: usize,
+    /// デコード済みのMCUの数
+    mcus_decoded: usize,
+    /// 変換済みの行の数
+    rows_decoded: u32,
+    /// 返した行の数
+    rows_returned: u32,
+    /// デコード済みでまだ返していない行の画素
+    pending: Vec<u8>,
+}
+
+/// 色差の拡大の方向（輝度の標本化係数で決まり、色差は常に1x1）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Upsampling {
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+/// フレームの成分
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    dc_table: usize,
+    ac_table: usize,
+    /// 直前のブロックのDC係数
+    prediction: i32,
+    /// MCUの1行分の画素
+    plane: Vec<u8>,
+    /// 1つ前のMCUの行の画素（縦の拡大で次の行と合わせて変換する）
+    previous: Vec<u8>,
+    stride: usize,
+}
+
+impl Component {
+    /// `plane` の `y` 行目（パディングを含む幅）
+    fn row<'p>(&self, plane: &'p [u8], y: usize) -> &'p [u8] {
+        &plane[y * self.stride..(y + 1) * self.stride]
+    }
+}
+
+impl<'a> BandDecoder<'a> {
+    /// JPEGのヘッダーを読んで帯のデコードを始める
+    ///
+    /// プログレッシブ・算術符号・12bit・CMYK・4:1:1など、対応していないJPEGは `None` を返す
+    /// （呼び出し側は通常のデコードに切り替える）。
+    ///
+    /// # Errors
+    /// JPEGでない場合、ヘッダーが壊れている場合
+    pub fn new(data: &'a [u8]) -> Result<Option<Self>, PamphletError> {
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return Err(error("not a JPEG"));
+        }
+
+        let mut frame: Option<(u32, u32, Vec<Component>)> = None;
+        let mut quant = [None; 4];
+        let mut dc_tables: [Option<Huffman>; 4] = Default::default();
+        let mut ac_tables: [Option<Huffman>; 4] = Default::default();
+        let mut restart_interval = 0;
+        let mut adobe_transform = None;
+        let mut pos = 2;
+        loop {
+            if data.get(pos) != Some(&0xFF) {
+                return Err(error("invalid marker"));
+            }
+            let marker = *data
+                .get(pos + 1)
+                .ok_or_else(|| error("unexpected end of data"))?;
+            if marker == 0xFF {
+                pos += 1;
+                continue;
+            }
+            let length = read_u16(data, pos + 2)? as usize;
+            if length < 2 {
+                return Err(error("invalid segment length"));
+            }
+            let segment = data
+                .get(pos + 4..pos + 2 + length)
+                .ok_or_else(|| error("unexpected end of data"))?;
+            pos += 2 + length;
+
+            match marker {
+                // フレームヘッダーが複数あるJPEGは扱わない
+                0xC0 | 0xC1 if frame.is_some() => return Ok(None),
+                0xC0 | 0xC1 => match parse_frame(segment)? {
+                    Some(parsed) => frame = Some(parsed),
+                    None => return Ok(None),
+                },
+                // プログレッシブ・可逆・階層・算術符号
+                0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCC..=0xCF => return Ok(None),
+                0xC4 => parse_huffman(segment, &mut dc_tables, &mut ac_tables)?,
+                0xDB => parse_quant(segment, &mut quant)?,
+                0xDD => restart_interval = read_u16(segment, 0)? as usize,
+                0xEE if segment.starts_with(b"Adobe") => match segment.get(11) {
+                    Some(&transform) => adobe_transform = Some(transform),
+                    None => return Ok(None),
+                },
+                0xDA => {
+                    let (width, height, components) =
+                        frame.ok_or_else(|| error("scan before frame header"))?;
+                    return Self::start(
+                        data,
+                        pos,
+                        segment,
+                        (width, height),
+                        components,
+                        quant,
+                        dc_tables,
+                        ac_tables,
+                        restart_interval,
+                        adobe_transform,
+                    );
+                }
+                0xD9 => return Err(error("no image data")),
+                _ => {}
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn start(
+        data: &'a [u8],
+        pos: usize,
+        scan: &[u8],
+        (width, height): (u32, u32),
+        mut components: Vec<Component>,
+        quant: [Option<[u16; 64]>; 4],
+        dc_tables: [Option<Huffman>; 4],
+        ac_tables: [Option<Huffman>; 4],
+        restart_interval: usize,
+        adobe_transform: Option<u8>,
+    ) -> Result<Option<Self>, PamphletError> {
+        // 最初のスキャンにすべての成分がフレームと同じ順で入り、スペクトル選択と逐次近似が
+        // ベースラインの値（0-63、0）の場合だけ扱う
+        let count = *scan.first().ok_or_else(|| error("invalid scan header"))? as usize;
+        if count != components.len() || scan.get(1 + count * 2..) != Some(&[0, 63, 0]) {
+            return Ok(None);
+        }
+        for (index, component) in components.iter_mut().enumerate() {
+            let entry = scan
+                .get(1 + index * 2..3 + index * 2)
+                .ok_or_else(|| error("invalid scan header"))?;
+            if entry[0] != component.id {
+                return Ok(None);
+            }
+            component.dc_table = (entry[1] >> 4) as usize;
+            component.ac_table = (entry[1] & 15) as usize;
+        }
+
+        // 色空間の判定は `image` クレートのデコーダーと同じ（Adobeの変換が0、または
+        // 成分のIDが 'R' 'G' 'B' ならRGB）
+        let rgb =
+            components.iter().map(|c| c.id).eq([b'R', b'G', b'B']) || adobe_transform == Some(0);
+        let sampling: Vec<_> = components.iter().map(|c| (c.h, c.v)).collect();
+        let upsampling = match (sampling.as_slice(), rgb) {
+            ([(1, 1)], false) if adobe_transform.is_none() => Upsampling::None,
+            ([(1, 1), (1, 1), (1, 1)], _) if adobe_transform.is_none_or(|t| t <= 1) => {
+                Upsampling::None
+            }
+            ([luma, (1, 1), (1, 1)], false) if adobe_transform.is_none_or(|t| t == 1) => match luma
+            {
+                (2, 1) => Upsampling::Horizontal,
+                (1, 2) => Upsampling::Vertical,
+                (2, 2) => Upsampling::Both,
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        let max_h = components.iter().map(|c| c.h).max().unwrap_or(1);
+        let max_v = components.iter().map(|c| c.v).max().unwrap_or(1);
+        let mcus_x = (width as usize).div_ceil(8 * max_h);
+        let mcus_y = (height as usize).div_ceil(8 * max_v);
+        for component in &mut components {
+            if quant.get(component.quant).is_none_or(Option::is_none)
+                || dc_tables
+                    .get(component.dc_table)
+                    .is_none_or(Option::is_none)
+                || ac_tables
+                    .get(component.ac_table)
+                    .is_none_or(Option::is_none)
+            {
+                return Err(error("missing table"));
+            }
+            component.stride = mcus_x * component.h * 8;
+            component.plane = vec![0; component.stride * component.v * 8];
+            if matches!(upsampling, Upsampling::Vertical | Upsampling::Both) {
+                component.previous = component.plane.clone();
+            }
+        }
+
+        Ok(Some(Self {
+            width,
+            height,
+            components,
+            upsampling,
+            mcus_x,
+            mcus_y,
+            quant,
+            dc_tables,
+            ac_tables,
+            restart_interval,
+            rgb,
+            reader: BitReader::new(data, pos),
+            mcu_row: 0,
+            mcus_decoded: 0,
+            rows_decoded: 0,
+            rows_returned: 0,
+            pending: Vec::new(),
+        }))
+    }
+
+    /// 画像の幅と高さ
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// 次の `rows` 行（残りが少なければ残りの行）をデコードする
+    ///
+    /// グレースケールは `Luma8`、それ以外は `Rgb8` の画像を返す。画素は `buffer` の
+    /// 領域に書き込む（前の帯を `decode::into_buffer` で戻して使い回せる）。
+    ///
+    /// # Errors
+    /// 符号化されたデータが壊れている場合
+    pub fn next_band(
+        &mut self,
+        rows: u32,
+        mut buffer: Vec<u8>,
+    ) -> Result<DynamicImage, PamphletError> {
+        let rows = rows.min(self.height - self.rows_returned);
+        let row_bytes = self.width as usize * self.channels();
+        let needed = rows as usize * row_bytes;
+
+        buffer.clear();
+        buffer.append(&mut self.pending);
+        while buffer.len() < needed {
+            self.decode_mcu_row(&mut buffer)?;
+        }
+        self.pending.extend_from_slice(&buffer[needed..]);
+        buffer.truncate(needed);
+        self.rows_returned += rows;
+
+        let image = if self.channels() == 1 {
+            ImageBuffer::from_raw(self.width, rows, buffer).map(DynamicImage::ImageLuma8)
+        } else {
+            ImageBuffer::from_raw(self.width, rows, buffer).map(DynamicImage::ImageRgb8)
+        };
+        image.ok_or_else(|| error("unexpected buffer size"))
+    }
+
+    fn channels(&self) -> usize {
+        if self.components.len() == 1 {
+            1
+        } else {
+            3
+        }
+    }
+
+    /// MCUの1行をデコードし、変換できた行の画素を `out` に追加する
+    ///
+    /// 縦に拡大する場合、MCUの行の最後の色差の行は次のMCUの行の最初の行と合わせて
+    /// 変換するため、その2行は次の呼び出しで追加する。
+    fn decode_mcu_row(&mut self, out: &mut Vec<u8>) -> Result<(), PamphletError> {
+        if self.mcu_row >= self.mcus_y {
+            return Err(error("unexpected end of image data"));
+        }
+
+        let mut coefficients = [0i32; 64];
+        for mcu_x in 0..self.mcus_x {
+            if self.restart_interval > 0
+                && self.mcus_decoded > 0
+                && self.mcus_decoded.is_multiple_of(self.restart_interval)
+            {
+                let index = self.mcus_decoded / self.restart_interval - 1;
+                self.reader.restart(index)?;
+                for component in &mut self.components {
+                    component.prediction = 0;
+                }
+            }
+
+            for component in &mut self.components {
+                let missing = || error("missing table");
+                let quant = self.quant[component.quant].as_ref().ok_or_else(missing)?;
+                let dc = self.dc_tables[component.dc_table]
+                    .as_ref()
+                    .ok_or_else(missing)?;
+                let ac = self.ac_tables[component.ac_table]
+                    .as_ref()
+                    .ok_or_else(missing)?;
+                for block_y in 0..component.v {
+                    for block_x in 0..component.h {
+                        let length = decode_block(
+                            &mut self.reader,
+                            dc,
+                            ac,
+                            quant,
+                            &mut component.prediction,
+                            &mut coefficients,
+                        )?;
+                        let x = (mcu_x * component.h + block_x) * 8;
+                        let offset = block_y * 8 * component.stride + x;
+                        idct(
+                            &mut coefficients,
+                            length,
+                            &mut component.plane[offset..],
+                            component.stride,
+                        );
+                    }
+                }
+            }
+            self.mcus_decoded += 1;
+        }
+        let first = self.mcu_row == 0;
+        self.mcu_row += 1;
+        let last = self.mcu_row == self.mcus_y;
+        // 符号化されたデータの終わりを越えて読んだ場合や、データがEOIで終わらない場合は
+        // エラー（通常のデコードは途中までの画像を返す）
+        if self.reader.overread {
+            return Err(error("unexpected end of image data"));
+        }
+        if last {
+            self.reader.expect_marker(0xD9)?;
+        }
+        let start = out.len();
+        match self.components.as_slice() {
+            [gray] => {
+                for y in 0..8 {
+                    out.extend_from_slice(&gray.row(&gray.plane, y)[..self.width as usize]);
+                }
+            }
+            [c1, c2, c3] if self.rgb => {
+                for y in 0..8 {
+                    let rows = [c1, c2, c3].map(|c| c.row(&c.plane, y));
+                    for x in 0..self.width as usize {
+                        out.extend_from_slice(&rows.map(|row| row[x]));
+                    }
+                }
+            }
+            [luma, cb, cr] => self.convert_ycbcr([luma, cb, cr], first, last, out),
+            _ => {}
+        }
+        if matches!(self.upsampling, Upsampling::Vertical | Upsampling::Both) {
+            for component in &mut self.components {
+                std::mem::swap(&mut component.plane, &mut component.previous);
+            }
+        }
+
+        // 画像の下端のパディングの行は捨てる
+        let row_bytes = self.width as usize * self.channels();
+        self.rows_decoded += ((out.len() - start) / row_bytes) as u32;
+        if self.rows_decoded > self.height {
+            out.truncate(out.len() - (self.rows_decoded - self.height) as usize * row_bytes);
+            self.rows_decoded = self.height;
+        }
+        Ok(())
+    }
+
+    /// デコードしたMCUの行をYCbCrからRGBに変換する
+    fn convert_ycbcr(
+        &self,
+        [luma, cb, cr]: [&Component; 3],
+        first: bool,
+        last: bool,
+        out: &mut Vec<u8>,
+    ) {
+        let width = self.width as usize;
+        if matches!(self.upsampling, Upsampling::None | Upsampling::Horizontal) {
+            for y in 0..8 {
+                let chroma = |c: &Component| self.upsample_horizontal(c.row(&c.plane, y));
+                ycbcr_row(
+                    luma.row(&luma.plane, y),
+                    &chroma(cb),
+                    &chroma(cr),
+                    width,
+                    out,
+                );
+            }
+            return;
+        }
+
+        // 色差の1行が輝度の2行になる。上下の隣の行は前後のMCUの行にまたがり、画像の
+        // 上端と下端では自分自身を使う
+        let pair = |c: &Component, plane: &[u8], row: usize, up: &[u8], down: &[u8]| {
+            let current = c.row(plane, row);
+            [up, down].map(|near| {
+                let blended: Vec<u8> = current
+                    .iter()
+                    .zip(near)
+                    .map(|(&a, &b)| blend(a, b))
+                    .collect();
+                self.upsample_horizontal(&blended)
+            })
+        };
+        let mut emit = |luma_plane: &[u8], row: usize, cb: [Vec<u8>; 2], cr: [Vec<u8>; 2]| {
+            for i in 0..2 {
+                let y = luma.row(luma_plane, row * 2 + i);
+                ycbcr_row(y, &cb[i], &cr[i], width, out);
+            }
+        };
+
+        // 前のMCUの行の最後の色差の行
+        if !first {
+            let chroma =
+                |c: &Component| pair(c, &c.previous, 7, c.row(&c.previous, 6), c.row(&c.plane, 0));
+            emit(&luma.previous, 7, chroma(cb), chroma(cr));
+        }
+        for row in 0..if last { 8 } else { 7 } {
+            let chroma = |c: &Component| {
+                let up = match row {
+                    0 if first => c.row(&c.plane, 0),
+                    0 => c.row(&c.previous, 7),
+                    _ => c.row(&c.plane, row - 1),
+                };
+                let down = c.row(&c.plane, (row + 1).min(7));
+                pair(c, &c.plane, row, up, down)
+            };
+            emit(&luma.plane, row, chroma(cb), chroma(cr));
+        }
+    }
+
+    /// 色差の行を横に2倍に拡大する（横に拡大しない場合はそのまま）
+    fn upsample_horizontal(&self, row: &[u8]) -> Vec<u8> {
+        if !matches!(self.upsampling, Upsampling::Horizontal | Upsampling::Both) {
+            return row.to_vec();
+        }
+        let n = row.len();
+        let mut out = Vec::with_capacity(n * 2);
+        for i in 0..n {
+            match i {
+                0 => out.extend_from_slice(&[row[0], blend(row[0], row[1])]),
+                _ if i == n - 1 => out.extend_from_slice(&[blend(row[i], row[i - 1]), row[i]]),
+                _ => out.extend_from_slice(&[blend(row[i], row[i - 1]), blend(row[i], row[i + 1])]),
+            }
+        }
+        out
+    }
+}
+
+/// 色差の拡大で、近い標本 `near` と遠い標本 `far` を3:1で混ぜる
+fn blend(near: u8, far: u8) -> u8 {
+    ((3 * near as u16 + far as u16 + 2) >> 2) as u8
+}
+
+/// 1行分のYCbCrをRGBに変換して `out` に追加する（先頭の `width` 画素）
+fn ycbcr_row(y: &[u8], cb: &[u8], cr: &[u8], width: usize, out: &mut Vec<u8>) {
+    for ((&y, &cb), &cr) in y.iter().zip(cb).zip(cr).take(width) {
+        out.extend_from_slice(&ycbcr_to_rgb(y, cb, cr));
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, PamphletError> {
+    data.get(pos..pos + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| error("unexpected end of data"))
+}
+
+/// フレームヘッダー（SOF0/SOF1）を読む（8bitの1成分・3成分以外は `None`）
+fn parse_frame(segment: &[u8]) -> Result<Option<(u32, u32, Vec<Component>)>, PamphletError> {
+    let header = segment
+        .get(..6)
+        .ok_or_else(|| error("invalid frame header"))?;
+    let precision = header[0];
+    let height = read_u16(header, 1)? as u32;
+    let width = read_u16(header, 3)? as u32;
+    let count = header[5] as usize;
+    if precision != 8 || !matches!(count, 1 | 3) {
+        return Ok(None);
+    }
+    if width == 0 || height == 0 {
+        return Err(error("invalid image size"));
+    }
+
+    let components = segment
+        .get(6..6 + count * 3)
+        .ok_or_else(|| error("invalid frame header"))?
+        .chunks_exact(3)
+        .map(|entry| {
+            let (h, v) = ((entry[1] >> 4) as usize, (entry[1] & 15) as usize);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) {
+                return Err(error("invalid sampling factor"));
+            }
+            Ok(Component {
+                id: entry[0],
+                h,
+                v,
+                quant: entry[2] as usize,
+                dc_table: 0,
+                ac_table: 0,
+                prediction: 0,
+                plane: Vec::new(),
+                previous: Vec::new(),
+                stride: 0,
+            })
+        })
+        .collect::<Result<_, PamphletError>>()?;
+    Ok(Some((width, height, components)))
+}
+
+/// 量子化テーブル（DQT）を読む（値はジグザグ順のまま）
+fn parse_quant(
+    mut segment: &[u8],
+    tables: &mut [Option<[u16; 64]>; 4],
+) -> Result<(), PamphletError> {
+    while let Some(&info) = segment.first() {
+        if info >> 4 > 1 || info & 15 > 3 {
+            return Err(error("invalid quantization table"));
+        }
+        let wide = info >> 4 != 0;
+        let size = if wide { 128 } else { 64 };
+        let values = segment
+            .get(1..1 + size)
+            .ok_or_else(|| error("invalid quantization table"))?;
+        tables[(info & 15) as usize] = Some(std::array::from_fn(|k| {
+            if wide {
+                u16::from_be_bytes([values[k * 2], values[k * 2 + 1]])
+            } else {
+                values[k] as u16
+            }
+        }));
+        segment = &segment[1 + size..];
+    }
+    Ok(())
+}
+
+/// ハフマンテーブル（DHT）を読む
+fn parse_huffman(
+    mut segment: &[u8],
+    dc_tables: &mut [Option<Huffman>; 4],
+    ac_tables: &mut [Option<Huffman>; 4],
+) -> Result<(), PamphletError> {
+    while let Some(&info) = segment.first() {
+        let counts: [u8; 16] = segment
+            .get(1..17)
+            .and_then(|counts| counts.try_into().ok())
+            .ok_or_else(|| error("invalid Huffman table"))?;
+        let total: usize = counts.iter().map(|&n| n as usize).sum();
+        let values = segment
+            .get(17..17 + total)
+            .filter(|_| total <= 256)
+            .ok_or_else(|| error("invalid Huffman table"))?;
+        // DC係数の値は差分のビット数（0-15）
+        if info >> 4 == 0 && values.iter().any(|&value| value > 15) {
+            return Err(error("invalid Huffman table"));
+        }
+        let table = Huffman::new(&counts, values)?;
+        let index = (info & 15) as usize;
+        match info >> 4 {
+            0 if index < 4 => dc_tables[index] = Some(table),
+            1 if index < 4 => ac_tables[index] = Some(table),
+            _ => return Err(error("invalid Huffman table")),
+        }
+        segment = &segment[17 + total..];
+    }
+    Ok(())
+}
+
+/// ハフマン符号の表
+struct Huffman {
+    /// 先頭 `LOOKUP_BITS` ビットで決まる符号（符号の長さ << 8 | 値、0 = 表にない）
+    lookup: [u16; 1 << LOOKUP_BITS],
+    /// 符号の長さごとの最大の符号（なければ-1）
+    max_code: [i32; 17],
+    /// 符号の長さごとの最小の符号と、その値の位置
+    min_code: [i32; 17],
+    value_offset: [usize; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8; 16], values: &[u8]) -> Result<Self, PamphletError> {
+        let mut table = Self {
+            lookup: [0; 1 << LOOKUP_BITS],
+            max_code: [-1; 17],
+            min_code: [0; 17],
+            value_offset: [0; 17],
+            values: values.to_vec(),
+        };
+
+        let mut code = 0u32;
+        let mut index = 0;
+        for length in 1..=16u32 {
+            let count = counts[length as usize - 1] as usize;
+            table.min_code[length as usize] = code as i32;
+            table.value_offset[length as usize] = index;
+            // 符号が長さに収まらない表（すべてのビットが1の符号も使えない）
+            if code + count as u32 >= 1 << length {
+                return Err(error("invalid Huffman table"));
+            }
+            for _ in 0..count {
+                if length <= LOOKUP_BITS {
+                    let shift = LOOKUP_BITS - length;
+                    let entry = (length as u16) << 8 | values[index] as u16;
+                    let start = (code << shift) as usize;
+                    table.lookup[start..start + (1 << shift)].fill(entry);
+                }
+                code += 1;
+                index += 1;
+            }
+            if count > 0 {
+                table.max_code[length as usize] = code as i32 - 1;
+            }
+            code <<= 1;
+        }
+        Ok(table)
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u8, PamphletError> {
+        reader.fill();
+        let entry = self.lookup[reader.peek(LOOKUP_BITS) as usize];
+        if entry != 0 {
+            reader.consume((entry >> 8) as u32);
+            return Ok(entry as u8);
+        }
+
+        for length in LOOKUP_BITS + 1..=16 {
+            let code = reader.peek(length) as i32;
+            if code <= self.max_code[length as usize] {
+                reader.consume(length);
+                let index = self.value_offset[length as usize]
+                    + (code - self.min_code[length as usize]) as usize;
+                return self
+                    .values
+                    .get(index)
+                    .copied()
+                    .ok_or_else(|| error("invalid Huffman code"));
+            }
+        }
+        Err(error("invalid Huffman code"))
+    }
+}
+
+/// 符号化されたデータをビット単位で読む（0xFF 0x00 の詰め物を取り除く）
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// 読み込んだビット（上位から詰める）
+    bits: u64,
+    count: u32,
+    /// `bits` のうちデータから読んだビットの数（残りはマーカーの後の0）
+    real: u32,
+    /// マーカーかデータの終わりに達した（以降は0を読む）
+    at_marker: bool,
+    /// マーカーかデータの終わりを越えて読んだ
+    overread: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            data,
+            pos,
+            bits: 0,
+            count: 0,
+            real: 0,
+            at_marker: false,
+            overread: false,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let mut byte = 0;
+            if !self.at_marker && self.pos < self.data.len() {
+                byte = self.data[self.pos];
+                if byte == 0xFF && self.data.get(self.pos + 1) != Some(&0) {
+                    self.at_marker = true;
+                    byte = 0;
+                } else {
+                    self.pos += if byte == 0xFF { 2 } else { 1 };
+                    self.real += 8;
+                }
+            }
+            self.bits |= (byte as u64) << (56 - self.count);
+            self.count += 8;
+        }
+    }
+
+    /// 先頭の `n` ビット（1-16、`fill` の後に呼ぶ）
+    fn peek(&self, n: u32) -> u32 {
+        (self.bits >> (64 - n)) as u32
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.bits <<= n;
+        self.count -= n;
+        if n > self.real {
+            self.overread = true;
+        }
+        self.real = self.real.saturating_sub(n);
+    }
+
+    fn read(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        self.fill();
+        let value = self.peek(n);
+        self.consume(n);
+        value
+    }
+
+    /// 読みかけのバイトの残りを捨てた後が `marker` か確かめる（余分なデータがあればエラー）
+    fn expect_marker(&mut self, marker: u8) -> Result<(), PamphletError> {
+        self.fill();
+        if self.overread
+            || self.real >= 8
+            || self.data.get(self.pos..self.pos + 2) != Some(&[0xFF, marker])
+        {
+            return Err(error("unexpected marker"));
+        }
+        Ok(())
+    }
+
+    /// `index` 番目（0から）のリスタートマーカーの後から読み直す
+    ///
+    /// マーカーの番号が順番どおりでない場合はエラー
+    fn restart(&mut self, index: usize) -> Result<(), PamphletError> {
+        self.expect_marker(0xD0 + (index % 8) as u8)?;
+        self.pos += 2;
+        self.bits = 0;
+        self.count = 0;
+        self.real = 0;
+        self.at_marker = false;
+        Ok(())
+    }
+}
+
+/// 差分の符号を値に戻す
+fn extend(value: u32, size: u32) -> i32 {
+    if size == 0 {
+        0
+    } else if value < 1 << (size - 1) {
+        value as i32 - (1 << size) + 1
+    } else {
+        value as i32
+    }
+}
+
+/// 1ブロックの係数をデコードし、逆量子化して自然順で `coefficients` に書き込む
+///
+/// デコードした係数の長さ（ジグザグ順で最後の係数の次の位置、DC係数だけなら1）を返す。
+/// 値の計算は通常のデコーダーと同じく桁あふれで折り返す。
+fn decode_block(
+    reader: &mut BitReader,
+    dc: &Huffman,
+    ac: &Huffman,
+    quant: &[u16; 64],
+    prediction: &mut i32,
+    coefficients: &mut [i32; 64],
+) -> Result<usize, PamphletError> {
+    coefficients.fill(0);
+
+    let size = dc.decode(reader)? as u32;
+    if size > 11 {
+        return Err(error("invalid DC coefficient"));
+    }
+    *prediction = prediction.wrapping_add(extend(reader.read(size), size));
+    coefficients[0] = prediction.wrapping_mul(quant[0] as i32);
+
+    let mut k = 1;
+    while k < 64 {
+        let symbol = ac.decode(reader)?;
+        let (run, size) = ((symbol >> 4) as usize, (symbol & 15) as u32);
+        if size == 0 {
+            if run != 15 {
+                return Ok(k);
+            }
+            k += 16;
+            continue;
+        }
+        k += run;
+        if k > 63 {
+            return Err(error("invalid AC coefficient"));
+        }
+        coefficients[ZIGZAG[k]] = extend(reader.read(size), size).wrapping_mul(quant[k] as i32);
+        k += 1;
+    }
+    Ok(64)
+}
+
+/// 8x8の係数を逆DCTし、レベルシフトして `out`（行の間隔 `stride`）に書き込む
+///
+/// zune-jpegと同じ整数の逆DCT（stb_image由来）。DC係数だけのブロックは別の式で計算し、
+/// どちらを使うかも `length`（`decode_block` の戻り値）で同じように決める。
+fn idct(coefficients: &mut [i32; 64], length: usize, out: &mut [u8], stride: usize) {
+    if length <= 1 || (length > 10 && coefficients[1..].iter().all(|&c| c == 0)) {
+        let value = (coefficients[0].wrapping_add(4 + 1024) >> 3).clamp(0, 255) as u8;
+        for y in 0..8 {
+            out[y * stride..y * stride + 8].fill(value);
+        }
+        return;
+    }
+
+    // 縦方向
+    for x in 0..8 {
+        let column = idct_1d(std::array::from_fn(|y| coefficients[y * 8 + x]), 512);
+        for (y, value) in column.into_iter().enumerate() {
+            coefficients[y * 8 + x] = (value >> 10).0;
+        }
+    }
+    // 横方向（128のレベルシフトと丸めを含む）
+    for y in 0..8 {
+        let row = idct_1d(
+            std::array::from_fn(|x| coefficients[y * 8 + x]),
+            IDCT_ROW_BIAS,
+        );
+        for (x, value) in row.into_iter().enumerate() {
+            out[y * stride + x] = (value >> 17).0.clamp(0, 255) as u8;
+        }
+    }
+}
+
+/// 横方向の逆DCTで加える値（丸め・縦方向の丸めの補正・レベルシフト）
+const IDCT_ROW_BIAS: i32 = 512 + 65536 + (128 << 17);
+
+/// 1次元の逆DCT（係数は12bitの固定小数点、`bias` を加えてシフトする前の値を返す）
+fn idct_1d(s: [i32; 8], bias: i32) -> [Wrapping<i32>; 8] {
+    let s = s.map(Wrapping);
+    let (bias, fsh) = (Wrapping(bias), |x: Wrapping<i32>| x << 12);
+
+    let p1 = (s[2] + s[6]) * Wrapping(2217);
+    let t2 = p1 + s[6] * Wrapping(-7567);
+    let t3 = p1 + s[2] * Wrapping(3135);
+    let t0 = fsh(s[0] + s[4]);
+    let t1 = fsh(s[0] - s[4]);
+    let x0 = t0 + t3 + bias;
+    let x3 = t0 - t3 + bias;
+    let x1 = t1 + t2 + bias;
+    let x2 = t1 - t2 + bias;
+
+    let (t0, t1, t2, t3) = (s[7], s[5], s[3], s[1]);
+    let p3 = t0 + t2;
+    let p4 = t1 + t3;
+    let p1 = t0 + t3;
+    let p2 = t1 + t2;
+    let p5 = (p3 + p4) * Wrapping(4816);
+    let p1 = p5 + p1 * Wrapping(-3685);
+    let p2 = p5 + p2 * Wrapping(-10497);
+    let p3 = p3 * Wrapping(-8034);
+    let p4 = p4 * Wrapping(-1597);
+    let t0 = t0 * Wrapping(1223) + p1 + p3;
+    let t1 = t1 * Wrapping(8410) + p2 + p4;
+    let t2 = t2 * Wrapping(12586) + p2 + p3;
+    let t3 = t3 * Wrapping(6149) + p1 + p4;
+
+    [
+        x0 + t3,
+        x1 + t2,
+        x2 + t1,
+        x3 + t0,
+        x3 - t0,
+        x2 - t1,
+        x1 - t2,
+        x0 - t3,
+    ]
+}
+
+/// YCbCr（JFIF）をRGBに変換する（zune-jpegと同じ14bitの固定小数点）
+fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let (cb, cr) = (cb as i32 - 128, cr as i32 - 128);
+    let y = y as i32 * 16384 + 8191;
+    let to_u8 = |value: i32| (value >> 14).clamp(0, 255) as u8;
+    [
+        to_u8(y + cr * 22970),
+        to_u8(y + cr * -11700 + cb * -5638),
+        to_u8(y + cb * 29032),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+    use std::io::Cursor;
+
+    /// 写真のような滑らかな変化と細かい模様の画像
+    fn pixels(width: u32, height: u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| {
+                (0..width).flat_map(move |x| {
+                    let wave = ((x as f32 / 7.0).sin() * 40.0) as i32;
+                    let noise = (x * 7 + y * 13) % 23;
+                    [
+                        (x * 255 / width) as u8,
+                        (y * 255 / height) as u8,
+                        (128 + wave + noise as i32) as u8,
+                    ]
+                })
+            })
+            .collect()
+    }
+
+    fn encode(
+        pixels: &[u8],
+        size: (u16, u16),
+        color: ColorType,
+        sampling: SamplingFactor,
+        (restart, quality): (u16, u8),
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = Encoder::new(&mut data, quality);
+        encoder.set_sampling_factor(sampling);
+        if restart > 0 {
+            encoder.set_restart_interval(restart);
+        }
+        encoder.encode(pixels, size.0, size.1, color).unwrap();
+        data
+    }
+
+    /// 帯に分けてデコードし、つなげた画素
+    fn decode_banded(data: &[u8], rows: u32) -> Vec<u8> {
+        let mut decoder = BandDecoder::new(data).unwrap().unwrap();
+        let (_, height) = decoder.dimensions();
+        let mut pixels = Vec::new();
+        let mut buffer = Vec::new();
+        for _ in 0..height.div_ceil(rows) {
+            let band = decoder.next_band(rows, buffer).unwrap();
+            assert!(band.height() <= rows);
+            pixels.extend_from_slice(band.as_bytes());
+            buffer = band.into_bytes();
+        }
+        pixels
+    }
+
+    /// 通常のデコードと、いくつかの帯の高さで帯に分けたデコードの画素が一致する
+    fn assert_identical(data: &[u8], label: &str) {
+        let expected = image::load_from_memory(data).unwrap();
+        let expected = match expected {
+            DynamicImage::ImageLuma8(_) => expected.into_bytes(),
+            _ => expected.to_rgb8().into_raw(),
+        };
+        for rows in [1, 7, 16, 33, 200] {
+            assert!(
+                decode_banded(data, rows) == expected,
+                "{} rows={}",
+                label,
+                rows
+            );
+        }
+    }
+
+    #[test]
+    fn test_band_decode() {
+        // 標本化係数・リスタートマーカー・品質・MCUで割り切れない大きさの組み合わせで、
+        // 通常のデコードと画素が完全に一致する
+        for (width, height) in [(1, 1), (7, 5), (15, 17), (100, 75), (33, 130)] {
+            let source = pixels(width, height);
+            let size = (width as u16, height as u16);
+            for sampling in [
+                SamplingFactor::F_1_1,
+                SamplingFactor::F_2_1,
+                SamplingFactor::F_1_2,
+                SamplingFactor::F_2_2,
+            ] {
+                for options in [(0, 90), (1, 50), (3, 100), (0, 10)] {
+                    let data = encode(&source, size, ColorType::Rgb, sampling, options);
+                    let label = format!("{}x{} {:?} {:?}", width, height, sampling, options);
+                    assert_identical(&data, &label);
+                }
+            }
+
+            // `image` クレートのエンコーダー（別の量子化・ハフマン表）
+            for quality in [30, 75, 95] {
+                let img = image::RgbImage::from_raw(width, height, source.clone()).unwrap();
+                let mut data = Cursor::new(Vec::new());
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut data, quality)
+                    .encode_image(&img)
+                    .unwrap();
+                assert_identical(
+                    data.get_ref(),
+                    &format!("{}x{} image {}", width, height, quality),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_band_decode_gray() {
+        let source: Vec<u8> = pixels(50, 40)
+            .chunks_exact(3)
+            .map(|p| p[0] / 2 + p[2] / 2)
+            .collect();
+        let data = encode(
+            &source,
+            (50, 40),
+            ColorType::Luma,
+            SamplingFactor::F_1_1,
+            (0, 90),
+        );
+        let mut decoder = BandDecoder::new(&data).unwrap().unwrap();
+        let band = decoder.next_band(32, Vec::new()).unwrap();
+        assert!(matches!(band, DynamicImage::ImageLuma8(_)));
+        assert_eq!((band.width(), band.height()), (50, 32));
+        assert_eq!(decoder.next_band(32, Vec::new()).unwrap().height(), 8);
+
+        assert_identical(&data, "gray");
+        let data = encode(
+            &source,
+            (50, 40),
+            ColorType::Luma,
+            SamplingFactor::F_1_1,
+            (2, 40),
+        );
+        assert_identical(&data, "gray restart");
+    }
+
+    #[test]
+    fn test_idct_dc_only() {
+        // DC係数だけのブロックは通常の逆DCTと同じ値になる
+        for dc in [-2048, -1030, -1, 0, 3, 4, 5, 1019, 1020, 2047] {
+            let mut full = [0i32; 64];
+            full[0] = dc;
+            let (mut a, mut b) = ([0u8; 64], [0u8; 64]);
+            idct(&mut full.clone(), 1, &mut a, 8);
+            // 0のAC係数があっても `length` が10以下なら通常の逆DCTを使う
+            idct(&mut full, 10, &mut b, 8);
+            assert_eq!(a, b, "{}", dc);
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let source = pixels(16, 16);
+        let mut data = Vec::new();
+        let mut encoder = Encoder::new(&mut data, 90);
+        encoder.set_progressive(true);
+        encoder.encode(&source, 16, 16, ColorType::Rgb).unwrap();
+        assert!(BandDecoder::new(&data).unwrap().is_none());
+
+        let cmyk: Vec<u8> = source
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0])
+            .collect();
+        let data = encode(
+            &cmyk,
+            (16, 16),
+            ColorType::Cmyk,
+            SamplingFactor::F_1_1,
+            (0, 90),
+        );
+        assert!(BandDecoder::new(&data).unwrap().is_none());
+
+        // 4:1:1（通常のデコードと同じ拡大の計算をしていない）
+        let data = encode(
+            &source,
+            (16, 16),
+            ColorType::Rgb,
+            SamplingFactor::F_4_1,
+            (0, 90),
+        );
+        assert!(BandDecoder::new(&data).unwrap().is_none());
+
+        assert!(BandDecoder::new(b"not a jpeg").is_err());
+    }
+
+    #[test]
+    fn test_corrupt_data() {
+        let source = pixels(40, 40);
+        let data = encode(
+            &source,
+            (40, 40),
+            ColorType::Rgb,
+            SamplingFactor::F_2_2,
+            (2, 90),
+        );
+        let scan = data.windows(2).position(|w| w == [0xFF, 0xDA]).unwrap();
+
+        // 途中で切れたデータは、足りない部分を0として読まずにエラーにする
+        let truncated = &data[..data.len() - 40];
+        let mut decoder = BandDecoder::new(truncated).unwrap().unwrap();
+        assert!(decoder.next_band(40, Vec::new()).is_err());
+
+        // リスタートマーカーの番号が順番どおりでない
+        let mut reordered = data.clone();
+        let marker = scan
+            + reordered[scan..]
+                .windows(2)
+                .position(|w| w == [0xFF, 0xD0])
+                .unwrap();
+        reordered[marker + 1] = 0xD3;
+        let mut decoder = BandDecoder::new(&reordered).unwrap().unwrap();
+        assert!(decoder.next_band(40, Vec::new()).is_err());
+
+        // データがEOIで終わらない（通常のデコードは知らないマーカーで止まる）
+        let mut unknown = data.clone();
+        let end = unknown.len() - 1;
+        unknown[end] = 0xF9;
+        let mut decoder = BandDecoder::new(&unknown).unwrap().unwrap();
+        assert!(decoder.next_band(40, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_huffman_table() {
+        // 符号が長さに収まらない表はパニックせずにエラーになる
+        let values: Vec<u8> = (0..5).collect();
+        let mut counts = [0; 16];
+        counts[1] = 5;
+        assert!(Huffman::new(&counts, &values).is_err());
+        // すべてのビットが1の符号
+        counts[1] = 4;
+        assert!(Huffman::new(&counts, &values[..4]).is_err());
+        counts[1] = 3;
+        assert!(Huffman::new(&counts, &values[..3]).is_ok());
+    }
+
+    #[test]
+    fn test_missing_table() {
+        let data = encode(
+            &pixels(16, 16),
+            (16, 16),
+            ColorType::Rgb,
+            SamplingFactor::F_1_1,
+            (0, 90),
+        );
+
+        // ハフマン表（DHT）を取り除くとエラーになる
+        let mut stripped = data[..2].to_vec();
+        let mut pos = 2;
+        while data[pos + 1] != 0xDA {
+            let length = 2 + read_u16(&data, pos + 2).unwrap() as usize;
+            if data[pos + 1] != 0xC4 {
+                stripped.extend_from_slice(&data[pos..pos + length]);
+            }
+            pos += length;
+        }
+        stripped.extend_from_slice(&data[pos..]);
+        assert!(BandDecoder::new(&stripped).is_err());
+
+        // デコード中に表がなくてもパニックしない
+        let mut decoder = BandDecoder::new(&data).unwrap().unwrap();
+        decoder.ac_tables = Default::default();
+        assert!(decoder.next_band(16, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! タイルの出力の配置（出力先のディレクトリの中のタイルのパス）
+//!
+//! 既定の `flat` はR2・Workersと同じ `tiles/{hash}.webp`。それ以外の配置はmetadataの
+//! `tile_layout` に書き、ビューアがタイルのURLを組み立てられるようにする
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// タイルの出力の配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileLayout {
+    /// `tiles/{hash}.webp`
+    #[default]
+    Flat,
+    /// `tiles/{hash[0..2]}/{hash[2..4]}/{hash}.webp`（1つのディレクトリのファイルを減らす）
+    Sharded,
+    /// `tiles/{page}/{hash}.webp`
+    Page,
+    /// `tiles/{page}/{x}/{y}.webp`（ハッシュを知らなくても位置からURLを組み立てられる）
+    Zxy,
+}
+
+impl TileLayout {
+    pub const ALL: [TileLayout; 4] = [Self::Flat, Self::Sharded, Self::Page, Self::Zxy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::Sharded => "sharded",
+            Self::Page => "page",
+            Self::Zxy => "zxy",
+        }
+    }
+
+    /// タイルのパス（`tiles/` から始まる、出力先のディレクトリ・キーの接頭辞からの相対パス）
+    pub fn tile_path(self, page: u32, x: u32, y: u32, hash: &str) -> String {
+        match self {
+            Self::Flat => format!("tiles/{}.webp", hash),
+            Self::Sharded => {
+                let shard = |start: usize| hash.get(start..start + 2).unwrap_or("_");
+                format!("tiles/{}/{}/{}.webp", shard(0), shard(2), hash)
+            }
+            Self::Page => format!("tiles/{}/{}.webp", page, hash),
+            Self::Zxy => format!("tiles/{}/{}/{}.webp", page, x, y),
+        }
+    }
+
+    /// パスにハッシュを含むか（同じパスのタイルは内容も同じで、書き直さなくてよい）
+    pub fn is_content_addressed(self) -> bool {
+        self != Self::Zxy
+    }
+
+    /// パスにページ番号を含むか（パンフレット全体のページ番号が決まるまで書き出せない）
+    pub fn uses_page(self) -> bool {
+        matches!(self, Self::Page | Self::Zxy)
+    }
+
+    /// metadataの `tile_layout`（無ければ `flat`）
+    ///
+    /// # Errors
+    /// 知らない配置の場合
+    pub fn from_metadata(metadata: &serde_json::Value) -> Result<Self, String> {
+        match &metadata["tile_layout"] {
+            serde_json::Value::Null => Ok(Self::Flat),
+            value => value
+                .as_str()
+                .ok_or_else(|| format!("Invalid tile layout: {}", value))?
+                .parse(),
+        }
+    }
+}
+
+impl FromStr for TileLayout {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.name() == value)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid tile layout: {} (expected flat, sharded, page or zxy)",
+                    value
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_path() {
+        let hash = "abcdef0123";
+        let paths: Vec<_> = TileLayout::ALL
+            .into_iter()
+            .map(|layout| layout.tile_path(3, 1, 2, hash))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "tiles/abcdef0123.webp",
+                "tiles/ab/cd/abcdef0123.webp",
+                "tiles/3/abcdef0123.webp",
+                "tiles/3/1/2.webp",
+            ]
+        );
+        assert_eq!(
+            TileLayout::Sharded.tile_path(0, 0, 0, "a"),
+            "tiles/_/_/a.webp"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        for layout in TileLayout::ALL {
+            assert_eq!(layout.name().parse(), Ok(layout));
+            assert_eq!(
+                serde_json::to_value(layout).unwrap(),
+                serde_json::json!(layout.name())
+            );
+        }
+        assert!("nested".parse::<TileLayout>().is_err());
+
+        assert_eq!(
+            TileLayout::from_metadata(&serde_json::json!({})),
+            Ok(TileLayout::Flat)
+        );
+        assert_eq!(
+            TileLayout::from_metadata(&serde_json::json!({ "tile_layout": "zxy" })),
+            Ok(TileLayout::Zxy)
+        );
+        assert!(TileLayout::from_metadata(&serde_json::json!({ "tile_layout": 1 })).is_err());
+    }
+}
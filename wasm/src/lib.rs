@@ -1,163 +1,186 @@
+// `wasm` を無効にしたビルドでは、JavaScriptのAPIからだけ使う機能（ビューアの計算など）が
+// 使われないため警告しない
+#![cfg_attr(not(feature = "wasm"), allow(dead_code, unused_imports))]
+
+mod annotations;
+#[cfg(feature = "zip")]
+mod archive;
+mod atlas;
+#[cfg(all(feature = "avif", not(target_arch = "wasm32")))]
+mod avif;
+mod binary;
+#[cfg(feature = "wasm")]
+mod bindings;
+mod build_info;
+mod camera;
+mod colors;
+mod compositor;
+mod config;
+mod curl;
+mod decode;
+mod error;
+mod filters;
+mod geometry;
 mod hasher;
+mod hook;
+mod integrity;
+mod jpeg;
+mod layout;
+#[cfg(all(feature = "libwebp", not(target_arch = "wasm32")))]
+mod libwebp;
+// libwebpはWASMにビルドできず、無視すると品質を指定しても可逆のタイルになるため、ビルドを止める
+#[cfg(all(feature = "libwebp", target_arch = "wasm32"))]
+compile_error!("the `libwebp` feature is native-only; build for wasm32 without it");
+mod logging;
+mod measure;
+mod memory;
+mod metadata;
+mod minimap;
+pub mod native;
+mod ocr;
+#[cfg(feature = "offscreen")]
+mod offscreen;
+mod pages;
+mod pamphlet;
+mod pdf;
+mod pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+mod plan;
+mod prefetch;
+mod preprocess;
+mod probe;
+#[cfg(not(target_arch = "wasm32"))]
+mod publish;
+#[cfg(feature = "raw")]
+mod raw;
+mod scheduler;
+mod search;
+#[cfg(all(feature = "serve", not(target_arch = "wasm32")))]
+mod serve;
+mod settings;
+mod spatial;
+mod spread;
+mod stats;
+#[cfg(feature = "svg")]
+mod svg;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod test_util;
+mod tile_cache;
 mod tiler;
+mod timing;
+mod transform;
+mod upload;
+mod view_state;
+mod viewport;
+mod worker;
+mod zoom;
+
+#[cfg(feature = "wasm")]
+pub use bindings::*;
 
-use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
-use js_sys::{Array, Uint8Array};
 
-// wee_allocをグローバルアロケータとして使用（メモリ最適化）
-#[cfg(feature = "wee_alloc")]
+// グローバルアロケータはフィーチャーで選ぶ（複数有効な場合は talc → dlmalloc → wee_alloc の順）
+// どのアロケータも `memory_stats` のために確保中のバイト数を数える
+#[cfg(all(feature = "talc", target_arch = "wasm32"))]
 #[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
-/// WASMモジュール初期化時に呼ばれる
-/// パニックフックを設定してエラーログを改善
-#[wasm_bindgen(start)]
-pub fn init() {
-    #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
-}
-
-/// JavaScriptに返すタイル情報
-#[wasm_bindgen]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct JsTileInfo {
-    x: u32,
-    y: u32,
-    hash: String,
-}
-
-#[wasm_bindgen]
-impl JsTileInfo {
-    #[wasm_bindgen(getter)]
-    pub fn x(&self) -> u32 {
-        self.x
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn y(&self) -> u32 {
-        self.y
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn hash(&self) -> String {
-        self.hash.clone()
-    }
-}
-
-/// JavaScriptに返すタイル化結果
-#[wasm_bindgen]
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JsTileResult {
-    width: u32,
-    height: u32,
-    tile_size: u32,
-    tiles: Vec<tiler::TileInfo>,
-}
-
-#[wasm_bindgen]
-impl JsTileResult {
-    #[wasm_bindgen(getter)]
-    pub fn width(&self) -> u32 {
-        self.width
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn height(&self) -> u32 {
-        self.height
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn tile_size(&self) -> u32 {
-        self.tile_size
-    }
-
-    /// タイル情報の配列を取得
-    #[wasm_bindgen(getter)]
-    pub fn tiles(&self) -> Array {
-        self.tiles
-            .iter()
-            .map(|tile| {
-                let js_tile = JsTileInfo {
-                    x: tile.x,
-                    y: tile.y,
-                    hash: tile.hash.clone(),
-                };
-                serde_wasm_bindgen::to_value(&js_tile).unwrap()
-            })
-            .collect()
-    }
-
-    /// 指定したインデックスのタイルデータを取得
-    #[wasm_bindgen]
-    pub fn get_tile_data(&self, index: usize) -> Result<Uint8Array, JsValue> {
-        if index >= self.tiles.len() {
-            return Err(JsValue::from_str("Tile index out of bounds"));
-        }
-
-        let data = &self.tiles[index].data;
-        Ok(Uint8Array::from(&data[..]))
-    }
+// SAFETY: wasm32-unknown-unknownはシングルスレッド
+static ALLOC: memory::CountingAllocator<talc::TalckWasm> =
+    memory::CountingAllocator::new(unsafe { talc::TalckWasm::new_global() });
+
+#[cfg(all(
+    feature = "dlmalloc",
+    not(all(feature = "talc", target_arch = "wasm32"))
+))]
+#[global_allocator]
+static ALLOC: memory::CountingAllocator<dlmalloc::GlobalDlmalloc> =
+    memory::CountingAllocator::new(dlmalloc::GlobalDlmalloc);
 
-    /// タイル数を取得
-    #[wasm_bindgen]
-    pub fn tile_count(&self) -> usize {
-        self.tiles.len()
-    }
+// wee_allocをグローバルアロケータとして使用（メモリ最適化）
+#[cfg(all(
+    feature = "wee_alloc",
+    not(feature = "dlmalloc"),
+    not(all(feature = "talc", target_arch = "wasm32"))
+))]
+#[global_allocator]
+static ALLOC: memory::CountingAllocator<wee_alloc::WeeAlloc> =
+    memory::CountingAllocator::new(wee_alloc::WeeAlloc::INIT);
+
+// 標準のアロケータを包むのはWASMと `count-alloc` の場合だけ（ライブラリとして使うネイティブの
+// バイナリが自分の `#[global_allocator]` を設定できるようにする）
+#[cfg(all(
+    any(target_arch = "wasm32", feature = "count-alloc"),
+    not(any(
+        feature = "wee_alloc",
+        feature = "dlmalloc",
+        all(feature = "talc", target_arch = "wasm32")
+    ))
+))]
+#[global_allocator]
+static ALLOC: memory::CountingAllocator<std::alloc::System> =
+    memory::CountingAllocator::new(std::alloc::System);
+
+/// 使用中のグローバルアロケータの名前
+#[cfg(feature = "wasm")]
+const ALLOCATOR: &str = if cfg!(all(feature = "talc", target_arch = "wasm32")) {
+    "talc"
+} else if cfg!(feature = "dlmalloc") {
+    "dlmalloc"
+} else if cfg!(feature = "wee_alloc") {
+    "wee_alloc"
+} else {
+    "system"
+};
+
+/// アロケータが管理している領域の内訳（talcのみ読める）
+#[cfg(all(feature = "wasm", feature = "talc", target_arch = "wasm32"))]
+fn heap_stats() -> Option<memory::HeapStats> {
+    let counters = *ALLOC.inner().lock().get_counters();
+    Some(memory::HeapStats {
+        claimed_bytes: counters.claimed_bytes,
+        available_bytes: counters.available_bytes,
+        fragment_count: counters.fragment_count,
+    })
 }
 
-/// 画像をタイル化する（JavaScriptから呼び出し可能）
-///
-/// # Arguments
-/// * `image_data` - 元画像のバイトデータ（JPEG/PNG等）
-/// * `tile_size` - タイルサイズ（ピクセル、例: 512）
-/// * `quality` - WebP品質（1-100、省略時80）
-///
-/// # Returns
-/// タイル化結果（JsTileResult）
-///
-/// # Example (JavaScript)
-/// ```js
-/// import init, { tile_image } from './pkg/tile_wasm.js';
-///
-/// await init();
-///
-/// const imageData = new Uint8Array([...]); // 画像ファイルのバイナリ
-/// const result = tile_image(imageData, 512, 80);
-///
-/// console.log(`Width: ${result.width}, Height: ${result.height}`);
-/// console.log(`Tile count: ${result.tile_count()}`);
-///
-/// for (let i = 0; i < result.tile_count(); i++) {
-///   const tileData = result.get_tile_data(i);
-///   // tileData: Uint8Array (WebP形式)
-/// }
-/// ```
-#[wasm_bindgen]
-pub fn tile_image(
-    image_data: &[u8],
-    tile_size: u32,
-    quality: Option<f32>,
-) -> Result<JsTileResult, JsValue> {
-    // Rustのタイル化関数を呼び出し
-    let result = tiler::tile_image(image_data, tile_size, quality)
-        .map_err(|e| JsValue::from_str(&e))?;
-
-    Ok(JsTileResult {
-        width: result.width,
-        height: result.height,
-        tile_size: result.tile_size,
-        tiles: result.tiles,
-    })
+#[cfg(all(feature = "wasm", not(all(feature = "talc", target_arch = "wasm32"))))]
+fn heap_stats() -> Option<memory::HeapStats> {
+    None
 }
 
 /// ページ情報（metadata生成用）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PageInfo {
     pub page: u32,
     pub width: u32,
     pub height: u32,
     pub tiles: Vec<TileMetadata>,
+    /// ページの表示名（ノンブルなど、`MetadataBuilder` の `add_page` で指定）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// テキストレイヤー（OCR結果をページの原寸ピクセル座標に合わせたもの）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<search::WordBox>,
+    /// ページの解像度（実寸の計測に使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dpi: Option<f64>,
+    /// 前処理で切り抜いた範囲（元のスキャンの座標、ページの座標に `x`・`y` を足すと元の座標になる）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop: Option<preprocess::CropRect>,
+    /// 前処理で拡大した倍率（ページの座標を `scale` で割ると元の画像の座標になる）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+}
+
+/// 目次の項目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TocEntry {
+    pub title: String,
+    /// 移動先のページ番号（0始まり）
+    pub page: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TocEntry>,
 }
 
 /// タイルのメタデータ
@@ -167,89 +190,3 @@ pub struct TileMetadata {
     pub y: u32,
     pub hash: String,
 }
-
-/// metadata.jsonを生成する（JavaScriptから呼び出し可能）
-///
-/// # Arguments
-/// * `pages_json` - ページ情報のJSON文字列
-/// * `tile_size` - タイルサイズ
-///
-/// # Returns
-/// metadata.jsonの文字列
-///
-/// # Example (JavaScript)
-/// ```js
-/// const pages = [
-///   {
-///     page: 0,
-///     width: 2480,
-///     height: 3508,
-///     tiles: [
-///       { x: 0, y: 0, hash: "abc123..." },
-///       { x: 1, y: 0, hash: "def456..." },
-///     ]
-///   }
-/// ];
-///
-/// const metadata = generate_metadata(JSON.stringify(pages), 512);
-/// console.log(metadata);
-/// ```
-#[wasm_bindgen]
-pub fn generate_metadata(pages_json: &str, tile_size: u32) -> Result<String, JsValue> {
-    let pages: Vec<PageInfo> =
-        serde_json::from_str(pages_json).map_err(|e| JsValue::from_str(&format!("{}", e)))?;
-
-    let metadata = serde_json::json!({
-        "version": js_sys::Date::now() as u64,
-        "tile_size": tile_size,
-        "pages": pages
-    });
-
-    serde_json::to_string_pretty(&metadata)
-        .map_err(|e| JsValue::from_str(&format!("Failed to serialize metadata: {}", e)))
-}
-
-/// SHA256ハッシュを計算（JavaScriptから呼び出し可能）
-///
-/// # Arguments
-/// * `data` - ハッシュ化するバイトデータ
-///
-/// # Returns
-/// SHA256ハッシュの16進数文字列
-#[wasm_bindgen]
-pub fn calculate_hash(data: &[u8]) -> String {
-    hasher::calculate_hash(data)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_generate_metadata() {
-        let pages = vec![PageInfo {
-            page: 0,
-            width: 1000,
-            height: 1000,
-            tiles: vec![
-                TileMetadata {
-                    x: 0,
-                    y: 0,
-                    hash: "abc123".to_string(),
-                },
-                TileMetadata {
-                    x: 1,
-                    y: 0,
-                    hash: "def456".to_string(),
-                },
-            ],
-        }];
-
-        let pages_json = serde_json::to_string(&pages).unwrap();
-        let metadata = generate_metadata(&pages_json, 512).unwrap();
-
-        assert!(metadata.contains("version"));
-        assert!(metadata.contains("tile_size"));
-        assert!(metadata.contains("pages"));
-    }
-}
@@ -0,0 +1,103 @@
+use std::borrow::Cow;
+
+use image::ExtendedColorType;
+use webp::{Encoder, PixelLayout};
+
+use crate::error::PamphletError;
+
+/// この品質以上は可逆でエンコードする
+const LOSSLESS_QUALITY: f32 = 100.0;
+
+/// 画素をlibwebpでWebP形式にエンコードし、`out` に書き込む
+///
+/// `quality` が100以上なら可逆、それ未満は非可逆（アルファは非可逆に圧縮して保持する）。
+/// グレースケールはRGBに広げてからエンコードする。
+///
+/// # Errors
+/// 8bit以外の画素形式の場合、エンコードに失敗した場合
+pub fn encode(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    color: ExtendedColorType,
+    quality: f32,
+    out: &mut Vec<u8>,
+) -> Result<(), PamphletError> {
+    let (pixels, layout) = match color {
+        ExtendedColorType::Rgb8 => (Cow::Borrowed(pixels), PixelLayout::Rgb),
+        ExtendedColorType::Rgba8 => (Cow::Borrowed(pixels), PixelLayout::Rgba),
+        ExtendedColorType::L8 => (
+            Cow::Owned(pixels.iter().flat_map(|&l| [l, l, l]).collect()),
+            PixelLayout::Rgb,
+        ),
+        ExtendedColorType::La8 => (
+            Cow::Owned(
+                pixels
+                    .chunks_exact(2)
+                    .flat_map(|la| [la[0], la[0], la[0], la[1]])
+                    .collect(),
+            ),
+            PixelLayout::Rgba,
+        ),
+        other => {
+            return Err(PamphletError::EncodeFailed(format!(
+                "Failed to encode WebP: unsupported color {:?}",
+                other
+            )))
+        }
+    };
+
+    let lossless = quality >= LOSSLESS_QUALITY;
+    let encoded = Encoder::new(&pixels, layout, width, height)
+        .encode_simple(lossless, quality.clamp(0.0, 100.0))
+        .map_err(|e| PamphletError::EncodeFailed(format!("Failed to encode WebP: {:?}", e)))?;
+    out.extend_from_slice(&encoded);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> image::DynamicImage {
+        image::load_from_memory_with_format(data, image::ImageFormat::WebP).unwrap()
+    }
+
+    fn encode_to_vec(
+        pixels: &[u8],
+        size: (u32, u32),
+        color: ExtendedColorType,
+        quality: f32,
+    ) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        encode(pixels, size.0, size.1, color, quality, &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_encode() {
+        // 可逆では圧縮しにくい、写真のような細かい模様
+        let mut state = 0x2545_f491u32;
+        let pixels: Vec<u8> = (0..64 * 64)
+            .flat_map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let [r, g, b, _] = state.to_le_bytes();
+                [r, g, b, 255]
+            })
+            .collect();
+
+        let lossy = encode_to_vec(&pixels, (64, 64), ExtendedColorType::Rgba8, 50.0).unwrap();
+        let lossless = encode_to_vec(&pixels, (64, 64), ExtendedColorType::Rgba8, 100.0).unwrap();
+        assert!(lossy.len() < lossless.len());
+        assert_eq!(decode(&lossless).to_rgba8().into_raw(), pixels);
+        assert_eq!(decode(&lossy).width(), 64);
+
+        // グレースケールはRGBに広げる
+        let gray = encode_to_vec(&[0, 255, 0, 255], (2, 2), ExtendedColorType::L8, 100.0).unwrap();
+        assert_eq!(decode(&gray).to_rgb8().get_pixel(1, 0).0, [255, 255, 255]);
+
+        assert!(encode_to_vec(&[0; 12], (2, 1), ExtendedColorType::Rgb16, 80.0).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::settings::Settings;
+
+/// 既定のログのレベル（警告とエラーのみ）
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+/// ログをブラウザのコンソール（ネイティブでは標準エラー出力）に出す
+///
+/// レベルは `log::max_level`（`set_level` で設定したもの）に従う
+struct ConsoleLogger;
+
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            write(
+                record.level(),
+                &format!("[tile-wasm] {}: {}", record.target(), record.args()),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// レベルに対応する `console.error`・`console.warn`・`console.info`・`console.debug` に出す
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn write(level: Level, message: &str) {
+    let message = wasm_bindgen::JsValue::from_str(message);
+    match level {
+        Level::Error => web_sys::console::error_1(&message),
+        Level::Warn => web_sys::console::warn_1(&message),
+        Level::Info => web_sys::console::info_1(&message),
+        Level::Debug | Level::Trace => web_sys::console::debug_1(&message),
+    }
+}
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+fn write(level: Level, message: &str) {
+    eprintln!("{:<5} {}", level, message);
+}
+
+/// ロガーを設定する（設定済みの場合は何もしない）
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(DEFAULT_LEVEL);
+    }
+}
+
+/// レベルの名前（`off`・`error`・`warn`・`info`・`debug`・`trace`、大文字小文字は問わない）を読む
+///
+/// # Errors
+/// レベルの名前が不正な場合
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .parse()
+        .map_err(|_| format!("Invalid log level: {}", level))
+}
+
+/// ログのレベルを名前で設定する
+///
+/// # Errors
+/// レベルの名前が不正な場合
+pub fn set_level(level: &str) -> Result<(), String> {
+    let filter = parse_level(level)?;
+    init();
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// 現在のログのレベルの名前（`set_level` で設定したもの）
+pub fn level() -> String {
+    init();
+    log::max_level().as_str().to_ascii_lowercase()
+}
+
+/// 設定のレベルでログを出すか（設定していなければ `set_level` のレベルに従う）
+pub fn enabled(settings: &Settings, level: Level) -> bool {
+    level <= settings.log_level.unwrap_or_else(log::max_level)
+}
+
+/// 設定のレベルに従ってログを出す（`log_with!` から呼ぶ）
+pub fn log_at(settings: &Settings, level: Level, target: &str, args: fmt::Arguments) {
+    match settings.log_level {
+        // `Tiler` のレベルは `log::max_level` より詳しい場合があるため、直接出す
+        Some(_) if enabled(settings, level) => {
+            write(level, &format!("[tile-wasm] {}: {}", target, args))
+        }
+        Some(_) => {}
+        None => log::log!(target: target, level, "{}", args),
+    }
+}
+
+/// 設定（`Settings`）のレベルに従ってログを出す（`log_with!(settings, Level::Debug, "...", ...)`）
+macro_rules! log_with {
+    ($settings:expr, $level:expr, $($arg:tt)+) => {
+        $crate::logging::log_at($settings, $level, module_path!(), format_args!($($arg)+))
+    };
+}
+
+pub(crate) use log_with;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_level() {
+        assert!(set_level("verbose").is_err());
+        set_level("DEBUG").unwrap();
+        assert_eq!(level(), "debug");
+        assert!(log::log_enabled!(Level::Debug));
+        set_level("warn").unwrap();
+        assert!(!log::log_enabled!(Level::Info));
+        assert_eq!(level(), "warn");
+    }
+
+    #[test]
+    fn test_instance_level() {
+        let settings = Settings {
+            log_level: Some(LevelFilter::Trace),
+            ..Settings::default()
+        };
+        // `Tiler` の設定のレベルは `set_level` のレベルより詳しくできる
+        assert!(enabled(&settings, Level::Trace));
+        assert!(!enabled(&Settings::default(), Level::Trace));
+        let quiet = Settings {
+            log_level: Some(LevelFilter::Off),
+            ..Settings::default()
+        };
+        assert!(!enabled(&quiet, Level::Error));
+    }
+}
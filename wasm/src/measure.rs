@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Point;
+
+/// 長さの単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Mm,
+    Cm,
+    M,
+    In,
+    /// ポイント（1/72インチ）
+    Pt,
+}
+
+impl Unit {
+    /// 1インチあたりの値
+    fn per_inch(self) -> f64 {
+        match self {
+            Unit::Mm => 25.4,
+            Unit::Cm => 2.54,
+            Unit::M => 0.0254,
+            Unit::In => 1.0,
+            Unit::Pt => 72.0,
+        }
+    }
+}
+
+/// 計測結果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// 折れ線の長さ（閉じた図形の場合は周長）
+    pub length: f64,
+    /// 閉じた図形の面積（単位の2乗、閉じていない場合は `None`）
+    pub area: Option<f64>,
+}
+
+/// ピクセル数を実寸に変換する
+pub fn pixels_to_physical(px: f64, dpi: f64, unit: Unit) -> f64 {
+    px / dpi * unit.per_inch()
+}
+
+/// 折れ線（または多角形）の実寸の長さと面積を求める
+///
+/// # Arguments
+/// * `points` - 頂点（ページの原寸ピクセル）
+/// * `dpi` - ページの解像度
+/// * `unit` - 結果の単位
+/// * `closed` - 最後の頂点と最初の頂点を結んだ多角形として扱うか
+pub fn measure_path(points: &[Point], dpi: f64, unit: Unit, closed: bool) -> Measurement {
+    let mut length: f64 = points.windows(2).map(|w| w[0].distance(&w[1])).sum();
+    let mut area = None;
+
+    if closed && points.len() >= 3 {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        length += last.distance(&first);
+
+        // 靴紐公式
+        let twice: f64 = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.x * b.y - b.x * a.y)
+            .sum();
+        let scale = pixels_to_physical(1.0, dpi, unit);
+        area = Some(twice.abs() / 2.0 * scale * scale);
+    }
+
+    Measurement {
+        length: pixels_to_physical(length, dpi, unit),
+        area,
+    }
+}
+
+/// 画像のヘッダーから解像度（DPI）を読み取る
+///
+/// PNGの `pHYs` チャンクとJPEGのJFIFヘッダーに対応する。記録されていない場合は `None`。
+pub fn detect_dpi(data: &[u8]) -> Option<f64> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_dpi(data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        jfif_dpi(data)
+    } else {
+        None
+    }
+}
+
+fn png_dpi(data: &[u8]) -> Option<f64> {
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let len = u32::from_be_bytes(data[pos..pos + 4].try_into().ok()?) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let body = data.get(pos + 8..pos + 8 + len)?;
+
+        match kind {
+            b"pHYs" if body.len() >= 9 => {
+                let x_ppu = u32::from_be_bytes(body[0..4].try_into().ok()?);
+                // 単位が1（メートル）の場合のみ絶対的な解像度を表す
+                return (body[8] == 1 && x_ppu > 0).then_some(x_ppu as f64 * 0.0254);
+            }
+            // pHYsはIDATより前に置かれる
+            b"IDAT" | b"IEND" => return None,
+            _ => {}
+        }
+        pos += 12 + len;
+    }
+    None
+}
+
+fn jfif_dpi(data: &[u8]) -> Option<f64> {
+    // SOIの直後のAPP0セグメント
+    if data.get(2..4)? != [0xFF, 0xE0] {
+        return None;
+    }
+    let segment = data.get(6..)?;
+    if !segment.starts_with(b"JFIF\0") {
+        return None;
+    }
+
+    let units = *segment.get(7)?;
+    let x_density = u16::from_be_bytes(segment.get(8..10)?.try_into().ok()?) as f64;
+    match units {
+        1 if x_density > 0.0 => Some(x_density),
+        2 if x_density > 0.0 => Some(x_density * 2.54),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_measure_path() {
+        assert_close(pixels_to_physical(300.0, 300.0, Unit::Mm), 25.4);
+        assert_close(pixels_to_physical(150.0, 300.0, Unit::Pt), 36.0);
+
+        // 300dpiで1インチ四方の正方形
+        let square = [
+            Point::new(0.0, 0.0),
+            Point::new(300.0, 0.0),
+            Point::new(300.0, 300.0),
+            Point::new(0.0, 300.0),
+        ];
+        let open = measure_path(&square, 300.0, Unit::Cm, false);
+        assert_close(open.length, 3.0 * 2.54);
+        assert_eq!(open.area, None);
+
+        let closed = measure_path(&square, 300.0, Unit::Cm, true);
+        assert_close(closed.length, 4.0 * 2.54);
+        assert_close(closed.area.unwrap(), 2.54 * 2.54);
+    }
+
+    #[test]
+    fn test_detect_dpi() {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+        let mut jpeg = Vec::new();
+        img.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        // JFIFヘッダー: 単位=1（dpi）、密度=300
+        jpeg[13] = 1;
+        jpeg[14..16].copy_from_slice(&300u16.to_be_bytes());
+        assert_eq!(detect_dpi(&jpeg), Some(300.0));
+
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert_eq!(detect_dpi(&png), None);
+
+        // IHDRの直後にpHYs（11811ピクセル/メートル ≒ 300dpi）を挿入
+        let mut phys = Vec::new();
+        phys.extend_from_slice(&9u32.to_be_bytes());
+        phys.extend_from_slice(b"pHYs");
+        phys.extend_from_slice(&11811u32.to_be_bytes());
+        phys.extend_from_slice(&11811u32.to_be_bytes());
+        phys.push(1);
+        phys.extend_from_slice(&[0; 4]);
+        png.splice(33..33, phys);
+        assert_close(detect_dpi(&png).unwrap().round(), 300.0);
+    }
+}
@@ -0,0 +1,70 @@
+/** タイルのメタデータ */
+export interface TileMetadata {
+  /** タイルのX座標（タイル単位） */
+  x: number;
+  /** タイルのY座標（タイル単位） */
+  y: number;
+  /** タイルのSHA256ハッシュ */
+  hash: string;
+}
+
+/** テキストレイヤーの単語（ページの原寸ピクセル） */
+export interface WordBox {
+  text: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** 前処理で切り抜いた範囲（元のスキャンのピクセル座標） */
+export interface CropRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** ページ情報 */
+export interface PageInfo {
+  /** ページ番号（0始まり） */
+  page: number;
+  width: number;
+  height: number;
+  tiles: TileMetadata[];
+  /** ページの表示名（ノンブルなど） */
+  label?: string;
+  /** テキストレイヤー */
+  words?: WordBox[];
+  /** ページの解像度（実寸の計測に使用） */
+  dpi?: number;
+  /** 前処理で切り抜いた範囲 */
+  crop?: CropRect;
+  /** 前処理で拡大した倍率 */
+  scale?: number;
+}
+
+/** 目次の項目 */
+export interface TocEntry {
+  title: string;
+  /** 移動先のページ番号（0始まり） */
+  page: number;
+  children?: TocEntry[];
+}
+
+/** タイルの出力の配置（`tile_path` でタイルのパスにする） */
+export type TileLayout = 'flat' | 'sharded' | 'page' | 'zxy';
+
+/** metadata.jsonの内容 */
+export interface PamphletMetadata {
+  /** バージョン（生成した時刻のミリ秒） */
+  version: number;
+  tile_size: number;
+  pages: PageInfo[];
+  /** 読み方向（`MetadataBuilder` で設定した場合） */
+  reading_direction?: 'ltr' | 'rtl';
+  /** 目次（空の場合は省略） */
+  toc?: TocEntry[];
+  /** タイルの出力の配置（`flat` の場合は省略） */
+  tile_layout?: TileLayout;
+}
@@ -0,0 +1,505 @@
+use serde_json::Value;
+
+use crate::layout::TileLayout;
+use crate::spread::ReadingDirection;
+use crate::{PageInfo, TocEntry};
+
+/// 固定していない場合のmetadataのバージョン（現在時刻、UNIXエポックからのミリ秒）
+///
+/// wasm32ではJavaScriptの`Date.now()`を使用し、ネイティブ（cargo test等）ではシステム時刻を使用。
+/// ネイティブでは環境変数 `SOURCE_DATE_EPOCH`（秒）があればその時刻を使う（再現可能なビルドの
+/// 慣習に合わせる）
+pub fn current_timestamp() -> u64 {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    {
+        js_sys::Date::now() as u64
+    }
+
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    {
+        if let Some(seconds) = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+        {
+            return seconds.saturating_mul(1000);
+        }
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// 厳密な読み込みで確かめる値の種類
+enum Kind {
+    /// 0以上の整数（`u32`）
+    Index,
+    Number,
+    Text,
+    /// `null` も許す
+    Nullable(&'static Kind),
+    List(&'static Kind),
+    Object(&'static [Field]),
+}
+
+/// オブジェクトの項目
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    /// 省略できない
+    required: bool,
+}
+
+const fn field(name: &'static str, kind: Kind, required: bool) -> Field {
+    Field {
+        name,
+        kind,
+        required,
+    }
+}
+
+static TILE: Kind = Kind::Object(&[
+    field("x", Kind::Index, true),
+    field("y", Kind::Index, true),
+    field("hash", Kind::Text, true),
+]);
+
+static WORD: Kind = Kind::Object(&[
+    field("text", Kind::Text, true),
+    field("x", Kind::Number, true),
+    field("y", Kind::Number, true),
+    field("width", Kind::Number, true),
+    field("height", Kind::Number, true),
+]);
+
+static CROP: Kind = Kind::Object(&[
+    field("x", Kind::Index, true),
+    field("y", Kind::Index, true),
+    field("width", Kind::Index, true),
+    field("height", Kind::Index, true),
+]);
+
+/// ページ情報（`PageInfo`）
+static PAGE: Kind = Kind::Object(&[
+    field("page", Kind::Index, true),
+    field("width", Kind::Index, true),
+    field("height", Kind::Index, true),
+    field("tiles", Kind::List(&TILE), true),
+    field("label", Kind::Nullable(&Kind::Text), false),
+    field("words", Kind::List(&WORD), false),
+    field("dpi", Kind::Nullable(&Kind::Number), false),
+    field("crop", Kind::Nullable(&CROP), false),
+    field("scale", Kind::Nullable(&Kind::Number), false),
+]);
+
+/// 目次の項目（`TocEntry`）
+static TOC_ENTRY: Kind = Kind::Object(&TOC_FIELDS);
+
+static TOC_FIELDS: [Field; 3] = [
+    field("title", Kind::Text, true),
+    field("page", Kind::Index, true),
+    field("children", Kind::List(&TOC_ENTRY), false),
+];
+
+/// ページ情報の配列を厳密に読み込む
+///
+/// # Errors
+/// 知らない項目・足りない項目・型の誤りがある場合（`pages[0].widht` のように場所を示す）
+pub fn strict_pages(value: Value) -> Result<Vec<PageInfo>, String> {
+    check(&value, &Kind::List(&PAGE), "pages")
+        .map_err(|e| format!("Invalid page info at {}", e))?;
+    serde_json::from_value(value).map_err(|e| format!("Invalid page info: {}", e))
+}
+
+/// 目次の配列を厳密に読み込む
+///
+/// # Errors
+/// 知らない項目・足りない項目・型の誤りがある場合（`toc[1].children[0].page` のように場所を示す）
+pub fn strict_toc(value: Value) -> Result<Vec<TocEntry>, String> {
+    check(&value, &Kind::List(&TOC_ENTRY), "toc")
+        .map_err(|e| format!("Invalid table of contents at {}", e))?;
+    serde_json::from_value(value).map_err(|e| format!("Invalid table of contents: {}", e))
+}
+
+/// 値が種類に合うか確かめる（誤りは `場所: 内容`）
+fn check(value: &Value, kind: &Kind, path: &str) -> Result<(), String> {
+    let mismatch = |expected: &str| {
+        Err(format!(
+            "{}: expected {}, found {}",
+            path,
+            expected,
+            describe(value)
+        ))
+    };
+    match kind {
+        Kind::Index => match value.as_u64() {
+            Some(index) if index <= u64::from(u32::MAX) => Ok(()),
+            _ => mismatch("a non-negative integer"),
+        },
+        Kind::Number if value.is_number() => Ok(()),
+        Kind::Number => mismatch("a number"),
+        Kind::Text if value.is_string() => Ok(()),
+        Kind::Text => mismatch("a string"),
+        Kind::Nullable(_) if value.is_null() => Ok(()),
+        Kind::Nullable(kind) => check(value, kind, path),
+        Kind::List(item) => {
+            let Some(items) = value.as_array() else {
+                return mismatch("an array");
+            };
+            items
+                .iter()
+                .enumerate()
+                .try_for_each(|(index, value)| check(value, item, &format!("{}[{}]", path, index)))
+        }
+        Kind::Object(fields) => {
+            let Some(object) = value.as_object() else {
+                return mismatch("an object");
+            };
+            if let Some(unknown) = object
+                .keys()
+                .find(|key| !fields.iter().any(|field| field.name == key.as_str()))
+            {
+                let expected: Vec<&str> = fields.iter().map(|field| field.name).collect();
+                return Err(format!(
+                    "{}: unknown field \"{}\" (expected one of {})",
+                    path,
+                    unknown,
+                    expected.join(", ")
+                ));
+            }
+            for field in fields.iter() {
+                match object.get(field.name) {
+                    Some(value) => check(value, &field.kind, &format!("{}.{}", path, field.name))?,
+                    None if field.required => {
+                        return Err(format!("{}: missing field \"{}\"", path, field.name));
+                    }
+                    None => {}
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// エラーに入れる値の説明（配列・オブジェクトは中身を出さない）
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(_) => "an object".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// metadata.jsonの内容を組み立てる
+///
+/// 目次が空の場合・読み方向を指定しない場合は出力しない
+pub fn metadata_value(
+    version: u64,
+    tile_size: u32,
+    pages: &[PageInfo],
+    toc: &[TocEntry],
+    reading_direction: Option<ReadingDirection>,
+) -> Value {
+    let mut metadata = serde_json::json!({
+        "version": version,
+        "tile_size": tile_size,
+        "pages": pages
+    });
+    if let Some(direction) = reading_direction {
+        metadata["reading_direction"] = serde_json::json!(direction);
+    }
+    if !toc.is_empty() {
+        metadata["toc"] = serde_json::json!(toc);
+    }
+    metadata
+}
+
+/// ページを追加していき、metadata.jsonを組み立てる
+///
+/// ページ番号は追加した順に0から振り、タイルサイズは最初に追加したページに揃える
+#[derive(Debug, Clone, Default)]
+pub struct MetadataBuilder {
+    tile_size: Option<u32>,
+    pages: Vec<PageInfo>,
+    reading_direction: Option<ReadingDirection>,
+    toc: Vec<TocEntry>,
+    tile_layout: TileLayout,
+}
+
+impl MetadataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ページを追加し、振ったページ番号を返す（`label` を省略した場合はページ情報のまま）
+    ///
+    /// # Errors
+    /// タイルサイズが先に追加したページと異なる場合
+    pub fn add_page(
+        &mut self,
+        mut page: PageInfo,
+        tile_size: u32,
+        label: Option<String>,
+    ) -> Result<u32, String> {
+        if let Some(expected) = self.tile_size.filter(|&size| size != tile_size) {
+            return Err(format!(
+                "Invalid tile size for page {}: {} (other pages use {})",
+                self.pages.len(),
+                tile_size,
+                expected
+            ));
+        }
+        let number = self.pages.len() as u32;
+        self.tile_size = Some(tile_size);
+        page.page = number;
+        page.label = label.or(page.label);
+        self.pages.push(page);
+        Ok(number)
+    }
+
+    pub fn set_reading_direction(&mut self, direction: ReadingDirection) {
+        self.reading_direction = Some(direction);
+    }
+
+    pub fn set_toc(&mut self, toc: Vec<TocEntry>) {
+        self.toc = toc;
+    }
+
+    /// タイルの出力の配置（`flat` の場合は出力しない）
+    pub fn set_tile_layout(&mut self, layout: TileLayout) {
+        self.tile_layout = layout;
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// metadata.jsonの内容
+    ///
+    /// # Errors
+    /// ページを1つも追加していない場合、目次が存在しないページを指している場合
+    pub fn build(&self, version: u64) -> Result<Value, String> {
+        let Some(tile_size) = self.tile_size else {
+            return Err("Invalid metadata: no pages have been added".to_string());
+        };
+        let mut entries: Vec<&TocEntry> = self.toc.iter().collect();
+        while let Some(entry) = entries.pop() {
+            if entry.page as usize >= self.pages.len() {
+                return Err(format!(
+                    "Invalid table of contents: \"{}\" points to page {} of {}",
+                    entry.title,
+                    entry.page,
+                    self.pages.len()
+                ));
+            }
+            entries.extend(&entry.children);
+        }
+        let mut metadata = metadata_value(
+            version,
+            tile_size,
+            &self.pages,
+            &self.toc,
+            self.reading_direction,
+        );
+        if self.tile_layout != TileLayout::Flat {
+            metadata["tile_layout"] = serde_json::json!(self.tile_layout);
+        }
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use crate::TileMetadata;
+
+    fn page(width: u32) -> PageInfo {
+        PageInfo {
+            page: 99,
+            width,
+            height: 1400,
+            tiles: vec![TileMetadata {
+                x: 0,
+                y: 0,
+                hash: format!("hash{}", width),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut builder = MetadataBuilder::new();
+        assert!(builder.build(1).is_err());
+
+        assert_eq!(
+            builder.add_page(page(1000), 512, Some("表紙".to_string())),
+            Ok(0)
+        );
+        assert_eq!(builder.add_page(page(1001), 512, None), Ok(1));
+        assert!(builder.add_page(page(1002), 256, None).is_err());
+        assert_eq!(builder.page_count(), 2);
+
+        builder.set_reading_direction(ReadingDirection::Rtl);
+        builder.set_toc(vec![TocEntry {
+            title: "表紙".to_string(),
+            page: 0,
+            children: Vec::new(),
+        }]);
+        let metadata = builder.build(42).unwrap();
+        assert_eq!(metadata["version"], 42);
+        assert_eq!(metadata["tile_size"], 512);
+        assert_eq!(metadata["reading_direction"], "rtl");
+        assert_eq!(metadata["pages"][1]["page"], 1);
+        assert_eq!(metadata["pages"][0]["label"], "表紙");
+        assert!(metadata["pages"][1].get("label").is_none());
+        assert_eq!(metadata["toc"][0]["title"], "表紙");
+        assert!(metadata.get("tile_layout").is_none());
+
+        builder.set_tile_layout(TileLayout::Sharded);
+        assert_eq!(builder.build(42).unwrap()["tile_layout"], "sharded");
+
+        // 目次が存在しないページを指している
+        builder.set_toc(vec![TocEntry {
+            title: "裏表紙".to_string(),
+            page: 0,
+            children: vec![TocEntry {
+                title: "奥付".to_string(),
+                page: 2,
+                children: Vec::new(),
+            }],
+        }]);
+        assert!(builder.build(42).is_err());
+    }
+
+    #[test]
+    fn test_pinned_version() {
+        let before = current_timestamp();
+        let settings = Settings {
+            metadata_version: Some(1_700_000_000_000),
+            ..Settings::default()
+        };
+        let metadata = metadata_value(settings.version(), 512, &[page(1000)], &[], None);
+        assert_eq!(metadata["version"], 1_700_000_000_000u64);
+        assert!(Settings::default().version() >= before);
+    }
+
+    #[test]
+    fn test_metadata_value() {
+        let metadata = metadata_value(1, 512, &[page(1000)], &[], None);
+        assert!(metadata.get("toc").is_none());
+        assert!(metadata.get("reading_direction").is_none());
+    }
+
+    #[test]
+    fn test_strict_pages() {
+        let pages = serde_json::json!([{
+            "page": 0,
+            "width": 1000,
+            "height": 1400,
+            "tiles": [{ "x": 0, "y": 0, "hash": "abc" }],
+            "label": null,
+            "crop": { "x": 1, "y": 2, "width": 3, "height": 4 },
+        }]);
+        assert_eq!(strict_pages(pages).unwrap()[0].width, 1000);
+
+        let error = |pages: Value| strict_pages(pages).unwrap_err();
+        assert_eq!(
+            error(serde_json::json!([{ "page": 0, "widht": 1000, "height": 1400, "tiles": [] }])),
+            "Invalid page info at pages[0]: unknown field \"widht\" \
+             (expected one of page, width, height, tiles, label, words, dpi, crop, scale)"
+        );
+        assert_eq!(
+            error(serde_json::json!([{ "page": 0, "height": 1400, "tiles": [] }])),
+            "Invalid page info at pages[0]: missing field \"width\""
+        );
+        assert_eq!(
+            error(serde_json::json!([{
+                "page": 0,
+                "width": 1000,
+                "height": 1400,
+                "tiles": [{ "x": 0, "y": 0, "hash": "abc" }, { "x": -1, "y": 0, "hash": "def" }],
+            }])),
+            "Invalid page info at pages[0].tiles[1].x: expected a non-negative integer, found -1"
+        );
+        assert_eq!(
+            error(serde_json::json!({ "pages": [] })),
+            "Invalid page info at pages: expected an array, found an object"
+        );
+    }
+
+    #[test]
+    fn test_strict_toc() {
+        let toc = serde_json::json!([{ "title": "表紙", "page": 0, "children": [] }]);
+        assert_eq!(strict_toc(toc).unwrap()[0].title, "表紙");
+
+        let toc = serde_json::json!([{
+            "title": "表紙",
+            "page": 0,
+            "children": [{ "title": "商品", "page": "2" }],
+        }]);
+        assert_eq!(
+            strict_toc(toc).unwrap_err(),
+            "Invalid table of contents at toc[0].children[0].page: \
+             expected a non-negative integer, found \"2\""
+        );
+    }
+
+    #[test]
+    fn test_strict_fields() {
+        // 厳密な読み込みの項目が、ページ情報・目次のシリアライズした項目と揃っているか
+        let fields = |kind: &Kind| {
+            let Kind::Object(fields) = kind else {
+                unreachable!()
+            };
+            let mut names: Vec<String> =
+                fields.iter().map(|field| field.name.to_string()).collect();
+            names.sort();
+            names
+        };
+        let mut page = page(1000);
+        page.label = Some("表紙".to_string());
+        page.dpi = Some(300.0);
+        page.scale = Some(2.0);
+        page.crop = Some(crate::preprocess::CropRect {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        });
+        page.words = vec![crate::search::WordBox {
+            text: "春".to_string(),
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        }];
+        let value = serde_json::to_value(&page).unwrap();
+        let keys = |value: &Value| {
+            value
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fields(&PAGE), keys(&value));
+        assert_eq!(fields(&TILE), keys(&value["tiles"][0]));
+        assert_eq!(fields(&WORD), keys(&value["words"][0]));
+        assert_eq!(fields(&CROP), keys(&value["crop"]));
+        let toc = TocEntry {
+            title: "表紙".to_string(),
+            page: 0,
+            children: vec![TocEntry {
+                title: "商品".to_string(),
+                page: 0,
+                children: Vec::new(),
+            }],
+        };
+        assert_eq!(
+            fields(&TOC_ENTRY),
+            keys(&serde_json::to_value(&toc).unwrap())
+        );
+    }
+}
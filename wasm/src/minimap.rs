@@ -0,0 +1,151 @@
+use image::imageops::FilterType;
+use image::RgbaImage;
+
+use crate::error::PamphletError;
+use crate::geometry::Rect;
+
+/// 表示範囲の枠線の色
+const OUTLINE_COLOR: [u8; 4] = [255, 64, 64, 255];
+
+/// 枠線の太さ（ミニマップのピクセル）
+const OUTLINE_WIDTH: u32 = 2;
+
+/// 表示範囲外の明るさ（255 = 元の明るさ）
+const DIM_FACTOR: u16 = 128;
+
+/// ページ全体を縮小表示するナビゲーター画像
+#[derive(Debug, Clone)]
+pub struct Minimap {
+    base: RgbaImage,
+    /// ページの原寸ピクセル → ミニマップのピクセルの倍率
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl Minimap {
+    /// サムネイル（またはピラミッドの最小レベル）からミニマップを作成する
+    ///
+    /// # Arguments
+    /// * `image_data` - 元画像のバイトデータ（WebP/PNG/JPEG）
+    /// * `max_size` - ミニマップの長辺の最大サイズ（縦横比は保持）
+    /// * `page_width` - ページの原寸の幅
+    /// * `page_height` - ページの原寸の高さ
+    pub fn new(
+        image_data: &[u8],
+        max_size: u32,
+        page_width: u32,
+        page_height: u32,
+    ) -> Result<Self, PamphletError> {
+        let img = image::load_from_memory(image_data)
+            .map_err(|e| PamphletError::DecodeFailed(format!("Failed to decode image: {}", e)))?;
+
+        let max_size = max_size.max(1);
+        let base = if img.width() > max_size || img.height() > max_size {
+            img.resize(max_size, max_size, FilterType::Triangle)
+        } else {
+            img
+        }
+        .to_rgba8();
+
+        Ok(Self {
+            scale_x: base.width() as f64 / page_width.max(1) as f64,
+            scale_y: base.height() as f64 / page_height.max(1) as f64,
+            base,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.base.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.base.height()
+    }
+
+    /// 表示範囲を重ねたRGBA画素を返す
+    ///
+    /// 表示範囲の外側を暗くし、範囲を枠線で囲む。`viewport` がない場合はミニマップのみ。
+    ///
+    /// # Arguments
+    /// * `viewport` - 表示範囲（ページの原寸ピクセル）
+    pub fn render(&self, viewport: Option<&Rect>) -> Vec<u8> {
+        let mut img = self.base.clone();
+        let Some(viewport) = viewport else {
+            return img.into_raw();
+        };
+
+        // 表示範囲がミニマップより大きい場合も枠線が見えるよう、画像の端で止める
+        let (width, height) = (img.width() as f64, img.height() as f64);
+        let left = (viewport.x * self.scale_x).floor().clamp(0.0, width);
+        let top = (viewport.y * self.scale_y).floor().clamp(0.0, height);
+        let right = (viewport.right() * self.scale_x).ceil().clamp(0.0, width);
+        let bottom = (viewport.bottom() * self.scale_y).ceil().clamp(0.0, height);
+        let outline = OUTLINE_WIDTH as f64;
+
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let (x, y) = (x as f64, y as f64);
+            let inside = x >= left && x < right && y >= top && y < bottom;
+
+            if !inside {
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as u16 * DIM_FACTOR / 255) as u8;
+                }
+            } else if x < left + outline
+                || x >= right - outline
+                || y < top + outline
+                || y >= bottom - outline
+            {
+                pixel.0 = OUTLINE_COLOR;
+            }
+        }
+
+        img.into_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, Rgba};
+    use std::io::Cursor;
+
+    fn thumbnail() -> Vec<u8> {
+        let img = RgbaImage::from_pixel(200, 100, Rgba([200, 200, 200, 255]));
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * width + x) * 4) as usize;
+        pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_fits_within_max_size() {
+        let minimap = Minimap::new(&thumbnail(), 100, 4000, 2000).unwrap();
+        assert_eq!((minimap.width(), minimap.height()), (100, 50));
+
+        let pixels = minimap.render(None);
+        assert_eq!(pixels.len(), 100 * 50 * 4);
+        assert_eq!(pixel(&pixels, 100, 50, 25), [200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn test_viewport_overlay() {
+        let minimap = Minimap::new(&thumbnail(), 100, 4000, 2000).unwrap();
+        // ページ座標 (1000,500)-(2000,1500) → ミニマップ (25,12.5)-(50,37.5)
+        let pixels = minimap.render(Some(&Rect::new(1000.0, 500.0, 1000.0, 1000.0)));
+
+        assert_eq!(pixel(&pixels, 100, 25, 20), OUTLINE_COLOR);
+        assert_eq!(pixel(&pixels, 100, 35, 25), [200, 200, 200, 255]);
+        assert_eq!(pixel(&pixels, 100, 80, 25), [100, 100, 100, 255]);
+
+        // ページ全体より大きい表示範囲でも端に枠線が描かれる
+        let pixels = minimap.render(Some(&Rect::new(-500.0, -500.0, 5000.0, 3000.0)));
+        assert_eq!(pixel(&pixels, 100, 0, 25), OUTLINE_COLOR);
+        assert_eq!(pixel(&pixels, 100, 50, 25), [200, 200, 200, 255]);
+    }
+}
@@ -0,0 +1,32 @@
+//! ネイティブのツール（`cli` の `pamphlet-tiler` など）から使うタイル化の機能
+//!
+//! WASMのバインディングを介さずに、ブラウザと同じタイル化・ハッシュ・metadataの生成を呼び出す。
+//! ビルドサーバーでヘッドレスブラウザを使わずにパンフレットを出力するために使う
+
+pub use crate::config::{PageOverride, PamphletConfig};
+pub use crate::decode::{decode_pages, AnimationMode, DecodeLimits, SourceFormat};
+pub use crate::hasher::calculate_hash;
+pub use crate::layout::TileLayout;
+pub use crate::memory::CountingAllocator;
+pub use crate::metadata::{current_timestamp as metadata_version, MetadataBuilder};
+#[cfg(feature = "pdf")]
+pub use crate::pdf::PdfDocument;
+pub use crate::pdf::DEFAULT_RASTER_DPI;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::plan::{plan_files, Plan};
+pub use crate::preprocess::PreprocessOptions;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::publish::{
+    page_files, tile_directory, tile_files, DirectoryOptions, IncrementalTiler, Publication, Update,
+};
+#[cfg(all(feature = "serve", not(target_arch = "wasm32")))]
+pub use crate::serve::{PreviewServer, Response};
+pub use crate::settings::Settings;
+pub use crate::tiler::{
+    TileInfo, TileOptions, TileResult, TileWarning, TilerContext, MAX_TILE_SIZE, MIN_TILE_SIZE,
+};
+pub use crate::upload::{
+    pamphlet_prefix, upload_plan, UploadObject, METADATA_CACHE_CONTROL,
+    POSITIONAL_TILE_CACHE_CONTROL, TILE_CACHE_CONTROL,
+};
+pub use crate::{PageInfo, TileMetadata, TocEntry};
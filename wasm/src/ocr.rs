@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Rect;
+use crate::search::WordBox;
+
+/// OCR結果の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OcrFormat {
+    /// hOCR（Tesseract等のHTML出力）
+    Hocr,
+    /// ALTO XML（図書館・アーカイブ向けのOCR形式）
+    Alto,
+}
+
+/// OCR結果（OCR対象画像の座標系）
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrPage {
+    /// OCR対象画像の幅（ALTOの場合は `MeasurementUnit` の単位）
+    pub width: f64,
+    /// OCR対象画像の高さ
+    pub height: f64,
+    pub words: Vec<WordBox>,
+}
+
+/// OCR結果を解析する
+pub fn parse(source: &str, format: OcrFormat) -> Result<OcrPage, String> {
+    match format {
+        OcrFormat::Hocr => parse_hocr(source),
+        OcrFormat::Alto => parse_alto(source),
+    }
+}
+
+/// hOCRを解析する
+///
+/// `ocr_page` の `bbox` をページサイズとし、`ocrx_word`（または `ocr_word`）要素を単語として読み取る
+pub fn parse_hocr(html: &str) -> Result<OcrPage, String> {
+    let mut page_size = None;
+    let mut words = Vec::new();
+
+    for tag in Tags::new(html) {
+        if tag.closing {
+            continue;
+        }
+        let attrs = tag.attributes();
+        let Some(class) = attrs.get("class") else {
+            continue;
+        };
+        let classes: Vec<&str> = class.split_whitespace().collect();
+        let bbox = attrs.get("title").and_then(|title| hocr_bbox(title));
+
+        if classes.contains(&"ocr_page") {
+            if let Some(bbox) = bbox {
+                page_size = Some((bbox.right(), bbox.bottom()));
+            }
+        } else if classes.contains(&"ocrx_word") || classes.contains(&"ocr_word") {
+            let Some(bbox) = bbox else {
+                continue;
+            };
+            let text = decode_entities(strip_tags(tag.inner_text(html, "span")).trim());
+            if text.is_empty() {
+                continue;
+            }
+
+            words.push(WordBox {
+                text,
+                x: bbox.x,
+                y: bbox.y,
+                width: bbox.width,
+                height: bbox.height,
+            });
+        }
+    }
+
+    let (width, height) = page_size.ok_or("hOCR has no ocr_page bbox")?;
+    Ok(OcrPage {
+        width,
+        height,
+        words,
+    })
+}
+
+/// ALTO XMLを解析する
+///
+/// `Page` の `WIDTH`/`HEIGHT` をページサイズとし、`String` 要素の `CONTENT` と位置を単語として読み取る
+pub fn parse_alto(xml: &str) -> Result<OcrPage, String> {
+    let mut page_size = None;
+    let mut words = Vec::new();
+
+    for tag in Tags::new(xml) {
+        if tag.closing {
+            continue;
+        }
+        let attrs = tag.attributes();
+        let number = |name: &str| attrs.get(name).and_then(|v| v.parse::<f64>().ok());
+
+        match tag.local_name() {
+            "Page" if page_size.is_none() => {
+                if let (Some(width), Some(height)) = (number("WIDTH"), number("HEIGHT")) {
+                    page_size = Some((width, height));
+                }
+            }
+            "String" => {
+                let text = attrs
+                    .get("CONTENT")
+                    .map(|c| decode_entities(c.trim()))
+                    .unwrap_or_default();
+                let rect = (
+                    number("HPOS"),
+                    number("VPOS"),
+                    number("WIDTH"),
+                    number("HEIGHT"),
+                );
+                if let (false, (Some(x), Some(y), Some(width), Some(height))) =
+                    (text.is_empty(), rect)
+                {
+                    words.push(WordBox {
+                        text,
+                        x,
+                        y,
+                        width,
+                        height,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (width, height) = page_size.ok_or("ALTO has no Page WIDTH/HEIGHT")?;
+    Ok(OcrPage {
+        width,
+        height,
+        words,
+    })
+}
+
+/// OCR結果をページの原寸ピクセル座標に変換する
+///
+/// OCRは別解像度のスキャン画像に対して行われることが多いため、
+/// OCR上のページサイズとタイル化したページサイズの比で拡大縮小する
+pub fn align_to_page(ocr: &OcrPage, page_width: u32, page_height: u32) -> Vec<WordBox> {
+    if ocr.width <= 0.0 || ocr.height <= 0.0 {
+        return Vec::new();
+    }
+
+    let sx = page_width as f64 / ocr.width;
+    let sy = page_height as f64 / ocr.height;
+
+    ocr.words
+        .iter()
+        .map(|word| WordBox {
+            text: word.text.clone(),
+            x: word.x * sx,
+            y: word.y * sy,
+            width: word.width * sx,
+            height: word.height * sy,
+        })
+        .collect()
+}
+
+/// 指定したタイルと交差する単語を、タイル左上を原点とする座標で返す
+pub fn words_for_tile(words: &[WordBox], tile_size: u32, tile_x: u32, tile_y: u32) -> Vec<WordBox> {
+    let size = tile_size as f64;
+    let origin_x = tile_x as f64 * size;
+    let origin_y = tile_y as f64 * size;
+    let tile = Rect::new(origin_x, origin_y, size, size);
+
+    words
+        .iter()
+        .filter(|w| Rect::new(w.x, w.y, w.width, w.height).intersects(&tile))
+        .map(|w| WordBox {
+            text: w.text.clone(),
+            x: w.x - origin_x,
+            y: w.y - origin_y,
+            width: w.width,
+            height: w.height,
+        })
+        .collect()
+}
+
+/// hOCRの `title` 属性から `bbox x0 y0 x1 y1` を取り出す
+fn hocr_bbox(title: &str) -> Option<Rect> {
+    let bbox = title
+        .split(';')
+        .map(str::trim)
+        .find_map(|prop| prop.strip_prefix("bbox "))?;
+    let values: Vec<f64> = bbox
+        .split_whitespace()
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match values[..] {
+        [x0, y0, x1, y1] => Some(Rect::new(x0, y0, x1 - x0, y1 - y0)),
+        _ => None,
+    }
+}
+
+/// タグを除去する
+fn strip_tags(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// XML/HTMLの文字参照をデコードする
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 開始・終了タグ
+#[derive(Debug)]
+struct Tag<'a> {
+    /// タグ名と属性（`<` `>` を除いた部分）
+    body: &'a str,
+    /// タグの直後の位置
+    end: usize,
+    closing: bool,
+}
+
+impl<'a> Tag<'a> {
+    fn name(&self) -> &'a str {
+        let body = self.body.trim_start_matches('/');
+        let end = body
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(body.len());
+        &body[..end]
+    }
+
+    /// 名前空間接頭辞を除いたタグ名
+    fn local_name(&self) -> &'a str {
+        let name = self.name();
+        name.rsplit(':').next().unwrap_or(name)
+    }
+
+    fn attributes(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        let mut rest = &self.body[self.name().len()..];
+
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].trim().trim_start_matches('/').to_string();
+            let value = rest[eq + 1..].trim_start();
+            let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                break;
+            };
+            let Some(close) = value[1..].find(quote) else {
+                break;
+            };
+            attrs.insert(key, value[1..close + 1].to_string());
+            rest = &value[close + 2..];
+        }
+
+        attrs
+    }
+
+    /// 要素の内容（同名の入れ子要素を考慮して対応する終了タグまで）
+    fn inner_text(&self, source: &'a str, name: &str) -> &'a str {
+        let mut depth = 1;
+        for tag in Tags::new(&source[self.end..]) {
+            if tag.name() != name {
+                continue;
+            }
+            depth += if tag.closing { -1 } else { 1 };
+            if depth == 0 {
+                let inner_end = self.end + tag.end - tag.body.len() - 2;
+                return &source[self.end..inner_end];
+            }
+        }
+        &source[self.end..]
+    }
+}
+
+/// タグを順に取り出すイテレータ（コメント・宣言・処理命令は読み飛ばす）
+struct Tags<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { source, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Tag<'a>> {
+        loop {
+            let start = self.pos + self.source[self.pos..].find('<')?;
+            let rest = &self.source[start..];
+
+            if rest.starts_with("<!--") {
+                self.pos = start + rest.find("-->").map_or(rest.len(), |e| e + 3);
+                continue;
+            }
+
+            let end = start + rest.find('>')?;
+            self.pos = end + 1;
+
+            let body = &self.source[start + 1..end];
+            if body.starts_with('!') || body.starts_with('?') {
+                continue;
+            }
+
+            let body = body.trim_end_matches('/');
+            return Some(Tag {
+                body,
+                end: end + 1,
+                closing: body.starts_with('/'),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOCR: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<html><body>
+  <div class='ocr_page' id='page_1' title='image "scan.png"; bbox 0 0 2000 3000; ppageno 0'>
+    <span class='ocr_line' title="bbox 100 200 900 260">
+      <span class='ocrx_word' id='word_1' title='bbox 100 200 400 260; x_wconf 95'>新商品</span>
+      <span class='ocrx_word' id='word_2' title='bbox 450 200 900 260; x_wconf 91'><strong>A&amp;B</strong></span>
+    </span>
+  </div>
+</body></html>"#;
+
+    const ALTO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<alto xmlns="http://www.loc.gov/standards/alto/ns-v4#">
+  <Description><MeasurementUnit>pixel</MeasurementUnit></Description>
+  <Layout>
+    <Page ID="P1" WIDTH="1000" HEIGHT="1500" PHYSICAL_IMG_NR="1">
+      <PrintSpace>
+        <TextLine>
+          <String CONTENT="Spring" HPOS="50" VPOS="100" WIDTH="200" HEIGHT="30"/>
+          <SP/>
+          <String CONTENT="&quot;Sale&quot;" HPOS="260" VPOS="100" WIDTH="150" HEIGHT="30"/>
+        </TextLine>
+      </PrintSpace>
+    </Page>
+  </Layout>
+</alto>"#;
+
+    #[test]
+    fn test_parse_hocr() {
+        let page = parse_hocr(HOCR).unwrap();
+
+        assert_eq!((page.width, page.height), (2000.0, 3000.0));
+        assert_eq!(page.words.len(), 2);
+        assert_eq!(page.words[0].text, "新商品");
+        assert_eq!(page.words[0].width, 300.0);
+        assert_eq!(page.words[1].text, "A&B");
+
+        assert!(parse_hocr("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_parse_alto_and_align() {
+        let page = parse_alto(ALTO).unwrap();
+
+        assert_eq!((page.width, page.height), (1000.0, 1500.0));
+        assert_eq!(page.words[1].text, "\"Sale\"");
+
+        // 2倍の解像度でタイル化したページに合わせる
+        let words = align_to_page(&page, 2000, 3000);
+        assert_eq!(
+            (words[0].x, words[0].y, words[0].width),
+            (100.0, 200.0, 400.0)
+        );
+
+        // タイル座標系（256pxタイルの (1, 0)）
+        let in_tile = words_for_tile(&words, 256, 1, 0);
+        assert_eq!(in_tile.len(), 1);
+        assert_eq!(in_tile[0].x, 100.0 - 256.0);
+        assert!(words_for_tile(&words, 256, 0, 3).is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{ImageData, OffscreenCanvas, OffscreenCanvasRenderingContext2d};
+
+use crate::camera::CameraState;
+use crate::compositor::{DrawCommand, TileKey};
+use crate::error::PamphletError;
+
+/// OffscreenCanvasにタイルを直接描画するレンダラー（JavaScriptから利用可能）
+///
+/// タイルのデコードと描画をWASM内で完結させ、画素データをJavaScriptとの間で受け渡さない。
+/// デコードしたタイルはタイルごとのOffscreenCanvasに保持し、`draw` で拡大縮小しながら合成する。
+///
+/// # Example (JavaScript, Worker)
+/// ```js
+/// const renderer = new OffscreenRenderer(event.data.canvas);
+/// renderer.upload_tile(level, x, y, new Uint8Array(await res.arrayBuffer()));
+/// compositor.mark_loaded(level, x, y, performance.now());
+///
+/// function frame(time) {
+///   renderer.set_camera(camera.x, camera.y, camera.zoom);
+///   renderer.clear();
+///   renderer.draw(compositor.plan(level, visibleTiles, time));
+///   requestAnimationFrame(frame);
+/// }
+/// ```
+#[wasm_bindgen]
+pub struct OffscreenRenderer {
+    canvas: OffscreenCanvas,
+    context: OffscreenCanvasRenderingContext2d,
+    camera: CameraState,
+    tiles: HashMap<TileKey, OffscreenCanvas>,
+}
+
+#[wasm_bindgen]
+impl OffscreenRenderer {
+    /// # Arguments
+    /// * `canvas` - 描画先（`canvas.transferControlToOffscreen()` でワーカーに渡したもの）
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: OffscreenCanvas) -> Result<OffscreenRenderer, JsValue> {
+        let context = context_2d(&canvas)?;
+
+        Ok(OffscreenRenderer {
+            canvas,
+            context,
+            camera: CameraState {
+                x: 0.0,
+                y: 0.0,
+                zoom: 1.0,
+            },
+            tiles: HashMap::new(),
+        })
+    }
+
+    /// タイル画像（WebP/PNG/JPEG）をデコードして保持
+    pub fn upload_tile(
+        &mut self,
+        level: usize,
+        x: u32,
+        y: u32,
+        data: &[u8],
+    ) -> Result<(), JsValue> {
+        let img = decode_tile(data)?;
+        let (width, height) = img.dimensions();
+
+        let image_data =
+            ImageData::new_with_u8_clamped_array_and_sh(Clamped(img.as_raw()), width, height)?;
+        let tile = OffscreenCanvas::new(width, height)?;
+        context_2d(&tile)?.put_image_data(&image_data, 0.0, 0.0)?;
+
+        self.tiles.insert(TileKey { level, x, y }, tile);
+        Ok(())
+    }
+
+    pub fn has_tile(&self, level: usize, x: u32, y: u32) -> bool {
+        self.tiles.contains_key(&TileKey { level, x, y })
+    }
+
+    /// 保持しているタイルを破棄
+    pub fn remove_tile(&mut self, level: usize, x: u32, y: u32) {
+        self.tiles.remove(&TileKey { level, x, y });
+    }
+
+    /// 保持している全てのタイルを破棄
+    pub fn clear_tiles(&mut self) {
+        self.tiles.clear();
+    }
+
+    /// 保持しているタイル数
+    #[wasm_bindgen(getter)]
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// カメラを設定（画面座標 `s` とページ座標 `p` は `p = (s + (x, y)) / zoom`）
+    pub fn set_camera(&mut self, x: f64, y: f64, zoom: f64) -> Result<(), JsValue> {
+        self.camera = CameraState { x, y, zoom };
+        self.apply_camera()
+    }
+
+    /// 描画先のサイズを変更（デバイスピクセル）
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        self.canvas.set_width(width);
+        self.canvas.set_height(height);
+        // サイズ変更でコンテキストの状態がリセットされるため再設定する
+        self.apply_camera()
+    }
+
+    /// 描画先全体を消去
+    pub fn clear(&self) -> Result<(), JsValue> {
+        self.context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)?;
+        self.context.clear_rect(
+            0.0,
+            0.0,
+            self.canvas.width() as f64,
+            self.canvas.height() as f64,
+        );
+        self.apply_camera()
+    }
+
+    /// `Compositor.plan` の描画命令を実行
+    ///
+    /// # Returns
+    /// 描画したタイル数（保持していないタイルの命令は読み飛ばす）
+    pub fn draw(&self, plan: JsValue) -> Result<usize, JsValue> {
+        let plan: Vec<DrawCommand> = serde_wasm_bindgen::from_value(plan)?;
+        let mut drawn = 0;
+
+        for command in &plan {
+            let key = TileKey {
+                level: command.level,
+                x: command.x,
+                y: command.y,
+            };
+            let Some(tile) = self.tiles.get(&key) else {
+                continue;
+            };
+
+            let (src, dest) = (&command.src, &command.dest);
+            self.context.set_global_alpha(command.opacity);
+            self.context
+                .draw_image_with_offscreen_canvas_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    tile,
+                    src.x,
+                    src.y,
+                    src.width,
+                    src.height,
+                    dest.x,
+                    dest.y,
+                    dest.width,
+                    dest.height,
+                )?;
+            drawn += 1;
+        }
+
+        self.context.set_global_alpha(1.0);
+        Ok(drawn)
+    }
+
+    fn apply_camera(&self) -> Result<(), JsValue> {
+        let [a, b, c, d, e, f] = camera_transform(&self.camera);
+        self.context.set_transform(a, b, c, d, e, f)
+    }
+}
+
+/// タイル画像をデコードしてRGBAにする
+fn decode_tile(data: &[u8]) -> Result<image::RgbaImage, PamphletError> {
+    Ok(image::load_from_memory(data)
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to decode image: {}", e)))?
+        .to_rgba8())
+}
+
+/// ページ座標を画面座標に移す `setTransform` の引数（`[a, b, c, d, e, f]`）
+fn camera_transform(camera: &CameraState) -> [f64; 6] {
+    let CameraState { x, y, zoom } = *camera;
+    [zoom, 0.0, 0.0, zoom, -x, -y]
+}
+
+fn context_2d(canvas: &OffscreenCanvas) -> Result<OffscreenCanvasRenderingContext2d, JsValue> {
+    canvas
+        .get_context("2d")?
+        .ok_or_else(|| PamphletError::UnsupportedFormat("2D context is not available".into()))?
+        .dyn_into::<OffscreenCanvasRenderingContext2d>()
+        .map_err(|_| PamphletError::UnsupportedFormat("2D context is not available".into()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_transform() {
+        let camera = CameraState {
+            x: 120.0,
+            y: -40.0,
+            zoom: 2.5,
+        };
+        let [a, b, c, d, e, f] = camera_transform(&camera);
+        // 画面座標 `s` に移したページ座標 `p` は `p = (s + (x, y)) / zoom` を満たす
+        let (px, py) = (300.0, 80.0);
+        let (sx, sy) = (a * px + c * py + e, b * px + d * py + f);
+        assert_eq!((sx + camera.x) / camera.zoom, px);
+        assert_eq!((sy + camera.y) / camera.zoom, py);
+    }
+
+    #[test]
+    fn test_decode_tile() {
+        let mut png = Vec::new();
+        image::GrayImage::new(20, 10)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let tile = decode_tile(&png).unwrap();
+        assert_eq!(tile.dimensions(), (20, 10));
+        assert_eq!(tile.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+        assert!(matches!(
+            decode_tile(b"not an image"),
+            Err(PamphletError::DecodeFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+//! ページ画像のファイル名（ZIPやディレクトリからページを集めるときの判定と並べ方）
+
+use std::cmp::Ordering;
+
+/// ページ画像として扱う拡張子
+const IMAGE_EXTENSIONS: [&str; 19] = [
+    "jpg", "jpeg", "png", "webp", "gif", "heic", "heif", "avif", "jxl", "jp2", "j2k", "jpx", "psd",
+    "tif", "tiff", "dng", "cr2", "nef", "arw",
+];
+
+/// ページ画像として扱うファイルか（`/` 区切りのパス）
+pub fn is_page_image(name: &str) -> bool {
+    if name.starts_with("__MACOSX/") {
+        return false;
+    }
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    if file_name.starts_with('.') {
+        return false;
+    }
+    file_name.rsplit_once('.').is_some_and(|(_, extension)| {
+        IMAGE_EXTENSIONS
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known))
+    })
+}
+
+/// ページ画像かPDFのファイルか（`/` 区切りのパス）
+pub fn is_page_file(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    is_page_image(name)
+        || (!name.starts_with("__MACOSX/")
+            && !file_name.starts_with('.')
+            && file_name
+                .rsplit_once('.')
+                .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case("pdf")))
+}
+
+/// ファイル名の自然順の比較
+///
+/// 数字の並びは数値として比較し（値が同じ場合は桁数の少ない方が前）、それ以外は大文字と小文字を区別せずに比較する
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (digits_a, rest_a) = split_digits(a);
+            let (digits_b, rest_b) = split_digits(b);
+            let (trimmed_a, trimmed_b) = (
+                digits_a.trim_start_matches('0'),
+                digits_b.trim_start_matches('0'),
+            );
+            let ordering = trimmed_a
+                .len()
+                .cmp(&trimmed_b.len())
+                .then_with(|| trimmed_a.cmp(trimmed_b))
+                .then_with(|| digits_a.len().cmp(&digits_b.len()));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (a, b) = (rest_a, rest_b);
+        } else {
+            let ordering = x.to_lowercase().cmp(y.to_lowercase());
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+}
+
+/// 先頭の数字の並びと残りに分ける
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec![
+            "page10.jpg",
+            "page2.jpg",
+            "Page1.jpg",
+            "page02.jpg",
+            "cover.jpg",
+            "page1b.jpg",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b).then_with(|| a.cmp(b)));
+        assert_eq!(
+            names,
+            vec![
+                "cover.jpg",
+                "Page1.jpg",
+                "page1b.jpg",
+                "page2.jpg",
+                "page02.jpg",
+                "page10.jpg"
+            ]
+        );
+        assert_eq!(natural_cmp("a/2.png", "a/10.png"), Ordering::Less);
+        assert_eq!(natural_cmp("p1", "p1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_is_page_image() {
+        assert!(is_page_image("scans/1.PNG"));
+        assert!(is_page_image("cover.jpeg"));
+        assert!(!is_page_image("scans/readme.txt"));
+        assert!(!is_page_image("scans/.thumb.jpg"));
+        assert!(!is_page_image("__MACOSX/scans/._2.png"));
+        assert!(!is_page_image("scans/"));
+
+        assert!(is_page_file("book.PDF"));
+        assert!(is_page_file("scans/1.png"));
+        assert!(!is_page_file("scans/.book.pdf"));
+    }
+}
@@ -0,0 +1,344 @@
+//! ページ画像・PDFを読み順に集めて、1つのパンフレット（タイルとmetadata）にする
+//!
+//! 入力の読み込みとタイルの書き出しは呼び出し側が行う（ネイティブでは `publish`、
+//! Node.jsなどJavaScriptからは `tile_pamphlet`）
+
+use image::DynamicImage;
+
+use crate::decode::{self, AnimationMode};
+use crate::error::PamphletError;
+use crate::layout::TileLayout;
+use crate::metadata::MetadataBuilder;
+use crate::preprocess::PageGeometry;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::preprocess::{self, PreprocessOptions};
+use crate::settings::Settings;
+use crate::tiler::{TileOptions, TileResult, TilerContext};
+use crate::{measure, PageInfo, TocEntry};
+
+/// 1つの入力（画像・PDF）をタイル化したページ
+#[derive(Debug, Clone)]
+pub struct InputPages {
+    /// ページのタイル化結果と、解像度（DPI）・前処理で切り抜いた範囲・拡大の倍率
+    pub pages: Vec<(TileResult, PageGeometry)>,
+    /// PDFのしおり（ページ番号は入力の中の番号）
+    pub toc: Vec<TocEntry>,
+}
+
+/// 入力をタイル化する
+///
+/// PDFは各ページを `dpi` でラスタライズし、しおりを目次にする。画像はアニメーションの
+/// 最初のフレームを1ページとし、ファイルに記録された解像度を使う
+///
+/// # Errors
+/// デコード・タイル化に失敗した場合、PDFに対応していないビルドでPDFを渡した場合
+pub fn tile_input(
+    data: Vec<u8>,
+    context: &mut TilerContext,
+    options: &TileOptions,
+    dpi: f32,
+) -> Result<InputPages, PamphletError> {
+    let settings = context.settings().resolve();
+    let (pages, toc) = map_input_pages(data, dpi, &settings, |image, dpi| {
+        let geometry = PageGeometry {
+            dpi,
+            ..PageGeometry::default()
+        };
+        Ok((context.tile_decoded_with(&image, options)?, geometry))
+    })?;
+    Ok(InputPages { pages, toc })
+}
+
+/// 入力のページを読み順にデコードして前処理を適用し、1ページずつ `page` に渡す
+///
+/// 見開きの分割で1ページが2ページになった場合は、PDFのしおりのページ番号を分割後の
+/// 最初のページにずらす。前処理が空なら `map_input_pages` と同じ
+///
+/// # Errors
+/// `map_input_pages` と同じ
+#[cfg(not(target_arch = "wasm32"))]
+pub fn map_preprocessed_pages<T>(
+    data: Vec<u8>,
+    dpi: f32,
+    options: &PreprocessOptions,
+    settings: &Settings,
+    mut page: impl FnMut(DynamicImage, PageGeometry) -> Result<T, PamphletError>,
+) -> Result<(Vec<T>, Vec<TocEntry>), PamphletError> {
+    let (pages, toc) = map_input_pages(data, dpi, settings, |image, dpi| {
+        preprocess_page(image, dpi, options)
+            .into_iter()
+            .map(|(image, geometry)| page(image, geometry))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    // 入力のページ番号 -> 前処理後の最初のページ番号
+    let mut first_pages = Vec::with_capacity(pages.len());
+    let mut count = 0;
+    for split in &pages {
+        first_pages.push(count);
+        count += split.len() as u32;
+    }
+    let toc = toc
+        .into_iter()
+        .map(|entry| renumber_toc(entry, &first_pages))
+        .collect();
+    Ok((pages.into_iter().flatten().collect(), toc))
+}
+
+/// 目次のページ番号を前処理後のページ番号にする
+#[cfg(not(target_arch = "wasm32"))]
+fn renumber_toc(mut entry: TocEntry, first_pages: &[u32]) -> TocEntry {
+    if let Some(&page) = first_pages.get(entry.page as usize) {
+        entry.page = page;
+    }
+    entry.children = entry
+        .children
+        .into_iter()
+        .map(|child| renumber_toc(child, first_pages))
+        .collect();
+    entry
+}
+
+/// デコードしたページに前処理を適用する（見開きの分割で2ページになることがある）
+///
+/// 拡大の元画像の解像度が指定されていなければ `dpi`（ファイルに記録された解像度・PDFを
+/// ラスタライズした解像度）を使う。ページの解像度は拡大した場合は拡大後、しなければ `dpi`
+#[cfg(not(target_arch = "wasm32"))]
+fn preprocess_page(
+    image: DynamicImage,
+    dpi: Option<f64>,
+    options: &PreprocessOptions,
+) -> Vec<(DynamicImage, PageGeometry)> {
+    let mut options = options.clone();
+    if let Some(upscale) = &mut options.upscale {
+        upscale.source_dpi = upscale.source_dpi.or(dpi);
+    }
+    preprocess::preprocess_pages(image, &options)
+        .into_iter()
+        .map(|page| {
+            let geometry = PageGeometry {
+                dpi: page.geometry.dpi.or(dpi),
+                ..page.geometry
+            };
+            (page.image, geometry)
+        })
+        .collect()
+}
+
+/// 入力のページを読み順にデコードし、1ページずつ `page` に渡す
+///
+/// ページの画像と解像度（DPI）を受け取った `page` の結果と、PDFのしおりを返す。
+/// PDFは1ページずつラスタライズして渡すため、すべてのページの画素を同時には持たない。
+/// 画像は `settings`（デコードの上限・許可する形式）に従ってデコードする
+///
+/// # Errors
+/// デコードに失敗した場合、`page` が失敗した場合、PDFに対応していないビルドでPDFを渡した場合
+pub fn map_input_pages<T>(
+    data: Vec<u8>,
+    dpi: f32,
+    settings: &Settings,
+    mut page: impl FnMut(DynamicImage, Option<f64>) -> Result<T, PamphletError>,
+) -> Result<(Vec<T>, Vec<TocEntry>), PamphletError> {
+    if data.starts_with(b"%PDF") {
+        return map_pdf_pages(data, dpi, page);
+    }
+    let dpi = measure::detect_dpi(&data);
+    let pages = decode::decode_pages(&data, AnimationMode::FirstFrame, settings)?
+        .into_iter()
+        .map(|image| page(image, dpi))
+        .collect::<Result<_, PamphletError>>()?;
+    Ok((pages, Vec::new()))
+}
+
+#[cfg(feature = "pdf")]
+fn map_pdf_pages<T>(
+    data: Vec<u8>,
+    dpi: f32,
+    mut page: impl FnMut(DynamicImage, Option<f64>) -> Result<T, PamphletError>,
+) -> Result<(Vec<T>, Vec<TocEntry>), PamphletError> {
+    let document = crate::pdf::PdfDocument::open(data, None)
+        .map_err(|e| PamphletError::DecodeFailed(e.to_string()))?;
+    let pages = (0..document.page_count())
+        .map(|index| {
+            let (image, dpi) = document.render_page(index, dpi)?;
+            page(DynamicImage::ImageRgba8(image), Some(dpi as f64))
+        })
+        .collect::<Result<_, PamphletError>>()?;
+    Ok((pages, document.outline()))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn map_pdf_pages<T>(
+    _data: Vec<u8>,
+    _dpi: f32,
+    _page: impl FnMut(DynamicImage, Option<f64>) -> Result<T, PamphletError>,
+) -> Result<(Vec<T>, Vec<TocEntry>), PamphletError> {
+    Err(PamphletError::UnsupportedFormat(
+        "PDF input is not supported in this build (enable the `pdf` feature)".to_string(),
+    ))
+}
+
+/// 入力のページを読み順に追加して、metadataを組み立てる
+///
+/// PDFのしおりのページ番号は、パンフレット全体のページ番号にずらす
+#[derive(Debug, Default)]
+pub struct PamphletAssembler {
+    builder: MetadataBuilder,
+    toc: Vec<TocEntry>,
+    tiles: usize,
+}
+
+impl PamphletAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 入力のページを追加する
+    ///
+    /// # Errors
+    /// タイルサイズが先に追加したページと異なる場合
+    pub fn add(&mut self, input: InputPages) -> Result<(), String> {
+        let first_page = self.builder.page_count() as u32;
+        for (result, geometry) in input.pages {
+            self.tiles += result.tiles.len();
+            let page = PageInfo {
+                dpi: geometry.dpi,
+                crop: geometry.crop,
+                scale: geometry.scale,
+                ..result.page_info(0)
+            };
+            self.builder.add_page(page, result.tile_size, None)?;
+        }
+        self.toc.extend(
+            input
+                .toc
+                .into_iter()
+                .map(|entry| offset_toc(entry, first_page)),
+        );
+        Ok(())
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.builder.page_count()
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles
+    }
+
+    /// タイルの出力の配置（metadataの `tile_layout`）
+    pub fn set_tile_layout(&mut self, layout: TileLayout) {
+        self.builder.set_tile_layout(layout);
+    }
+
+    /// metadata.jsonの内容
+    ///
+    /// # Errors
+    /// ページを1つも追加していない場合
+    pub fn build(mut self) -> Result<serde_json::Value, String> {
+        self.builder.set_toc(self.toc);
+        self.builder.build(Settings::global().version())
+    }
+}
+
+/// 入力の中の目次のページ番号を、パンフレット全体のページ番号にする
+fn offset_toc(mut entry: TocEntry, first_page: u32) -> TocEntry {
+    entry.page += first_page;
+    entry.children = entry
+        .children
+        .into_iter()
+        .map(|child| offset_toc(child, first_page))
+        .collect();
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::png;
+
+    #[test]
+    fn test_assemble() {
+        let options = TileOptions::new(32, None);
+        let mut context = TilerContext::new();
+        let mut assembler = PamphletAssembler::new();
+        for size in [40, 20] {
+            let input = tile_input(png(size, 20, 0), &mut context, &options, 150.0).unwrap();
+            assembler.add(input).unwrap();
+        }
+        // しおりはパンフレット全体のページ番号にずらす
+        let toc = vec![TocEntry {
+            title: "第1章".to_string(),
+            page: 0,
+            children: vec![TocEntry {
+                title: "1.1".to_string(),
+                page: 0,
+                children: Vec::new(),
+            }],
+        }];
+        let input = InputPages {
+            pages: vec![(
+                tile_input(png(20, 20, 0), &mut context, &options, 150.0)
+                    .unwrap()
+                    .pages
+                    .remove(0)
+                    .0,
+                PageGeometry {
+                    dpi: Some(300.0),
+                    scale: Some(2.0),
+                    ..PageGeometry::default()
+                },
+            )],
+            toc,
+        };
+        assembler.add(input).unwrap();
+        assert_eq!((assembler.page_count(), assembler.tile_count()), (3, 4));
+
+        let metadata = assembler.build().unwrap();
+        assert_eq!(metadata["tile_size"], 32);
+        assert_eq!(metadata["pages"][1]["page"], 1);
+        assert_eq!(metadata["pages"][2]["dpi"], 300.0);
+        assert_eq!(metadata["pages"][2]["scale"], 2.0);
+        assert!(metadata["pages"][1].get("scale").is_none());
+        assert_eq!(metadata["toc"][0]["page"], 2);
+        assert_eq!(metadata["toc"][0]["children"][0]["page"], 2);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_map_preprocessed_pages() {
+        // 見開きを分割し、分割したページの範囲を返す
+        let options = PreprocessOptions {
+            split: Some(Default::default()),
+            ..PreprocessOptions::default()
+        };
+        let (pages, _) = map_preprocessed_pages(
+            png(40, 20, 0),
+            150.0,
+            &options,
+            &Settings::default(),
+            |image, geometry| Ok((image.width(), geometry.crop.map(|crop| crop.x))),
+        )
+        .unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].1, Some(0));
+        assert!(pages.iter().all(|&(width, _)| width < 40));
+
+        // しおりのページ番号は分割後の最初のページ
+        let toc = TocEntry {
+            title: "第2章".to_string(),
+            page: 1,
+            children: Vec::new(),
+        };
+        assert_eq!(renumber_toc(toc, &[0, 2, 3]).page, 2);
+    }
+
+    #[test]
+    fn test_tile_input_errors() {
+        let options = TileOptions::default();
+        let mut context = TilerContext::new();
+        assert!(tile_input(b"broken".to_vec(), &mut context, &options, 150.0).is_err());
+        let error = tile_input(b"%PDF-1.4 broken".to_vec(), &mut context, &options, 150.0);
+        assert!(error.is_err());
+        assert!(PamphletAssembler::new().build().is_err());
+    }
+}
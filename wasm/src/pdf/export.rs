@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{ExtendedColorType, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::PageInfo;
+
+/// PDF出力のオプション
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PdfOptions {
+    /// ページ画像のJPEG品質（1-100）
+    pub quality: u8,
+    /// `dpi` のないページの解像度（ページの物理サイズの算出に使用）
+    pub default_dpi: f64,
+    /// 文書のタイトル
+    pub title: Option<String>,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            quality: 85,
+            default_dpi: 96.0,
+            title: None,
+        }
+    }
+}
+
+/// 選択したページをPDFとして出力する
+///
+/// ページごとにタイルをつなぎ合わせて1枚の画像にし、JPEGとして埋め込む。
+/// ページの物理サイズはページの `dpi`（ない場合は `default_dpi`）から求める。
+///
+/// # Arguments
+/// * `pages` - 出力するページ（metadataのページ情報、この順に出力）
+/// * `tiles` - タイルのハッシュ → 取得済みのタイル画像
+/// * `options` - 出力オプション
+pub fn export_pdf(
+    pages: &[PageInfo],
+    tiles: &HashMap<String, Vec<u8>>,
+    options: &PdfOptions,
+) -> Result<Vec<u8>, String> {
+    if pages.is_empty() {
+        return Err("No pages to export".to_string());
+    }
+
+    let mut pdf = PdfWriter::new();
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 3 + i * 3).collect();
+    let info_id = 3 + pages.len() * 3;
+
+    pdf.object(1, b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    pdf.object(
+        2,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .as_bytes(),
+    );
+
+    for (page, &id) in pages.iter().zip(&page_ids) {
+        let image = stitch_page(page, tiles)?;
+        let jpeg = encode_jpeg(&image, options.quality)?;
+
+        let dpi = page.dpi.filter(|d| *d > 0.0).unwrap_or(options.default_dpi);
+        let width = page.width as f64 / dpi * 72.0;
+        let height = page.height as f64 / dpi * 72.0;
+
+        pdf.object(
+            id,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                width,
+                height,
+                id + 2,
+                id + 1
+            )
+            .as_bytes(),
+        );
+        pdf.stream(
+            id + 1,
+            "",
+            format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", width, height).as_bytes(),
+        );
+        pdf.stream(
+            id + 2,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode",
+                image.width(),
+                image.height()
+            ),
+            &jpeg,
+        );
+    }
+
+    let title = options.title.as_deref().unwrap_or("");
+    pdf.object(
+        info_id,
+        format!("<< /Title {} /Producer (tile-wasm) >>", text_string(title)).as_bytes(),
+    );
+
+    Ok(pdf.finish(1, info_id))
+}
+
+/// ページのタイルをつなぎ合わせる（透明部分は白で塗る）
+fn stitch_page(page: &PageInfo, tiles: &HashMap<String, Vec<u8>>) -> Result<RgbImage, String> {
+    let mut canvas = RgbImage::from_pixel(page.width, page.height, Rgb([255, 255, 255]));
+
+    for tile in &page.tiles {
+        let data = tiles
+            .get(&tile.hash)
+            .ok_or_else(|| format!("Tile not fetched: {}", tile.hash))?;
+        let img = image::load_from_memory(data)
+            .map_err(|e| format!("Failed to decode tile {}: {}", tile.hash, e))?
+            .to_rgba8();
+
+        // タイルは右端・下端もタイルサイズまでパディングされている
+        let tile_size = img.width();
+        let origin_x = tile.x * tile_size;
+        let origin_y = tile.y * tile_size;
+
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let (px, py) = (origin_x + x, origin_y + y);
+            if px >= page.width || py >= page.height {
+                continue;
+            }
+            let [r, g, b, a] = pixel.0;
+            let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+            canvas.put_pixel(px, py, Rgb([blend(r), blend(g), blend(b)]));
+        }
+    }
+
+    Ok(canvas)
+}
+
+fn encode_jpeg(image: &RgbImage, quality: u8) -> Result<Vec<u8>, String> {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality.clamp(1, 100))
+        .encode(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+    Ok(jpeg)
+}
+
+/// PDFのテキスト文字列（UTF-16BEの16進文字列）
+fn text_string(text: &str) -> String {
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        out.push_str(&format!("{:04X}", unit));
+    }
+    out.push('>');
+    out
+}
+
+/// PDFのオブジェクトを順に書き出す
+struct PdfWriter {
+    out: Vec<u8>,
+    /// オブジェクト番号 - 1 → ファイル先頭からの位置
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        let mut out = Vec::new();
+        // バイナリを含むことを示すコメント
+        out.extend_from_slice(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        Self {
+            out,
+            offsets: Vec::new(),
+        }
+    }
+
+    fn begin(&mut self, id: usize) {
+        if self.offsets.len() < id {
+            self.offsets.resize(id, 0);
+        }
+        self.offsets[id - 1] = self.out.len();
+        let _ = writeln!(self.out, "{} 0 obj", id);
+    }
+
+    fn object(&mut self, id: usize, body: &[u8]) {
+        self.begin(id);
+        self.out.extend_from_slice(body);
+        self.out.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        self.begin(id);
+        let _ = write!(self.out, "<< {} /Length {} >>\nstream\n", dict, data.len());
+        self.out.extend_from_slice(data);
+        self.out.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> Vec<u8> {
+        let xref = self.out.len();
+        let size = self.offsets.len() + 1;
+
+        let _ = write!(self.out, "xref\n0 {}\n0000000000 65535 f \n", size);
+        for offset in &self.offsets {
+            let _ = writeln!(self.out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            self.out,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            size, root, info, xref
+        );
+
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TileMetadata;
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn tile(color: [u8; 4]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba(color)))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn page(page: u32, dpi: Option<f64>) -> PageInfo {
+        PageInfo {
+            page,
+            width: 100,
+            height: 50,
+            tiles: vec![
+                TileMetadata {
+                    x: 0,
+                    y: 0,
+                    hash: "red".to_string(),
+                },
+                TileMetadata {
+                    x: 1,
+                    y: 0,
+                    hash: "blue".to_string(),
+                },
+            ],
+            dpi,
+            ..Default::default()
+        }
+    }
+
+    fn tiles() -> HashMap<String, Vec<u8>> {
+        HashMap::from([
+            ("red".to_string(), tile([255, 0, 0, 255])),
+            ("blue".to_string(), tile([0, 0, 255, 255])),
+        ])
+    }
+
+    #[test]
+    fn test_stitch_page() {
+        let image = stitch_page(&page(0, None), &tiles()).unwrap();
+        assert_eq!(image.dimensions(), (100, 50));
+        assert_eq!(image.get_pixel(10, 10).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(90, 10).0, [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_export_pdf() {
+        let options = PdfOptions {
+            title: Some("春のカタログ".to_string()),
+            ..Default::default()
+        };
+        let pdf = export_pdf(&[page(0, Some(72.0)), page(3, None)], &tiles(), &options).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 2"));
+        // 72dpiは1ピクセル = 1pt、96dpiは0.75pt
+        assert!(text.contains("/MediaBox [0 0 100.00 50.00]"));
+        assert!(text.contains("/MediaBox [0 0 75.00 37.50]"));
+
+        // xrefの位置が各オブジェクトの先頭を指している
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|s| s.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref"));
+        let first_offset: usize = std::str::from_utf8(&pdf[startxref..])
+            .unwrap()
+            .lines()
+            .nth(3)
+            .unwrap()[..10]
+            .parse()
+            .unwrap();
+        assert!(pdf[first_offset..].starts_with(b"1 0 obj"));
+
+        let mut missing = tiles();
+        missing.remove("blue");
+        assert_eq!(
+            export_pdf(&[page(0, None)], &missing, &options).unwrap_err(),
+            "Tile not fetched: blue"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+mod export;
+#[cfg(feature = "pdf")]
+mod outline;
+#[cfg(feature = "pdf")]
+mod raster;
+#[cfg(feature = "pdf")]
+mod text;
+
+pub use export::{export_pdf, PdfOptions};
+#[cfg(feature = "pdf")]
+pub use raster::PdfDocument;
+
+/// ラスタライズの既定の解像度（DPI）
+pub const DEFAULT_RASTER_DPI: f32 = 150.0;
@@ -0,0 +1,260 @@
+use hayro::hayro_syntax::object::{
+    Array, Dict, Name, Object, ObjectIdentifier, String as PdfString,
+};
+use hayro::hayro_syntax::Pdf;
+
+use crate::TocEntry;
+
+/// 読み込む項目数の上限（壊れたPDFの循環参照対策）
+const MAX_ENTRIES: usize = 10_000;
+
+/// 階層の深さの上限
+const MAX_DEPTH: usize = 32;
+
+/// 名前付きの宛先を探すときの名前ツリーの深さの上限
+const MAX_NAME_TREE_DEPTH: usize = 16;
+
+/// PDFのしおり（アウトライン）を目次にする
+///
+/// 宛先（`/Dest` または `/A` のGoToアクション、名前付きの宛先を含む）をページ番号に変換する。
+/// 宛先の無い項目は最初の子項目のページを使い、子項目も無ければ除く。
+pub fn read_outline(pdf: &Pdf) -> Vec<TocEntry> {
+    let xref = pdf.xref();
+    let Some(catalog) = xref.get::<Dict<'_>>(xref.root_id()) else {
+        return Vec::new();
+    };
+    let Some(first) = catalog
+        .get::<Dict<'_>>(b"Outlines")
+        .and_then(|outlines| outlines.get::<Dict<'_>>(b"First"))
+    else {
+        return Vec::new();
+    };
+
+    let pages: Vec<Option<ObjectIdentifier>> =
+        pdf.pages().iter().map(|page| page.raw().obj_id()).collect();
+    let mut reader = OutlineReader {
+        catalog: &catalog,
+        pages: &pages,
+        remaining: MAX_ENTRIES,
+    };
+    reader.read_siblings(first, 0)
+}
+
+struct OutlineReader<'r, 'a> {
+    catalog: &'r Dict<'a>,
+    /// ページ番号 → ページのオブジェクト番号
+    pages: &'r [Option<ObjectIdentifier>],
+    remaining: usize,
+}
+
+impl<'a> OutlineReader<'_, 'a> {
+    /// `item` から `/Next` でつながる項目を順に読む
+    fn read_siblings(&mut self, first: Dict<'a>, depth: usize) -> Vec<TocEntry> {
+        let mut entries = Vec::new();
+        let mut item = Some(first);
+
+        while let Some(current) = item {
+            if self.remaining == 0 {
+                break;
+            }
+            self.remaining -= 1;
+
+            let children = match current.get::<Dict<'_>>(b"First") {
+                Some(child) if depth + 1 < MAX_DEPTH => self.read_siblings(child, depth + 1),
+                _ => Vec::new(),
+            };
+            let page = self
+                .destination_page(&current)
+                .or_else(|| children.first().map(|child| child.page));
+
+            if let Some(page) = page {
+                entries.push(TocEntry {
+                    title: current
+                        .get::<PdfString<'_>>(b"Title")
+                        .map(|title| decode_text(title.as_bytes()))
+                        .unwrap_or_default(),
+                    page,
+                    children,
+                });
+            }
+
+            item = current.get::<Dict<'_>>(b"Next");
+        }
+
+        entries
+    }
+
+    /// 項目の宛先のページ番号
+    fn destination_page(&self, item: &Dict<'a>) -> Option<u32> {
+        let destination = item.get::<Object<'_>>(b"Dest").or_else(|| {
+            let action = item.get::<Dict<'_>>(b"A")?;
+            let is_goto = action
+                .get::<Name<'_>>(b"S")
+                .is_some_and(|name| name.as_ref() == b"GoTo");
+            if is_goto {
+                action.get::<Object<'_>>(b"D")
+            } else {
+                None
+            }
+        })?;
+
+        self.resolve(destination, 0)
+    }
+
+    /// 宛先（配列・名前・文字列）をページ番号にする
+    fn resolve(&self, destination: Object<'a>, depth: usize) -> Option<u32> {
+        if depth > 2 {
+            return None;
+        }
+        match destination {
+            Object::Array(array) => self.page_of_array(&array),
+            // `/D` を持つ辞書（名前付きの宛先の値として使われる）
+            Object::Dict(dict) => self.resolve(dict.get::<Object<'_>>(b"D")?, depth + 1),
+            // PDF 1.1形式（カタログの `/Dests` 辞書）
+            Object::Name(name) => {
+                let dests = self.catalog.get::<Dict<'_>>(b"Dests")?;
+                self.resolve(dests.get::<Object<'_>>(name.as_ref())?, depth + 1)
+            }
+            // PDF 1.2以降の名前ツリー（`/Names` の `/Dests`）
+            Object::String(name) => {
+                let tree = self
+                    .catalog
+                    .get::<Dict<'_>>(b"Names")?
+                    .get::<Dict<'_>>(b"Dests")?;
+                let value = find_in_name_tree(&tree, name.as_bytes(), 0)?;
+                self.resolve(value, depth + 1)
+            }
+            _ => None,
+        }
+    }
+
+    /// `[page /XYZ ...]` の先頭のページを番号にする
+    fn page_of_array(&self, array: &Array<'a>) -> Option<u32> {
+        match array.iter::<Object<'_>>().next()? {
+            Object::Dict(page) => {
+                let id = page.obj_id()?;
+                let index = self.pages.iter().position(|p| *p == Some(id))?;
+                u32::try_from(index).ok()
+            }
+            // 他の文書への宛先ではページ番号で指定される
+            Object::Number(number) => {
+                let index = u32::try_from(number.as_i64()).ok()?;
+                ((index as usize) < self.pages.len()).then_some(index)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 名前ツリーから値を探す
+fn find_in_name_tree<'a>(node: &Dict<'a>, key: &[u8], depth: usize) -> Option<Object<'a>> {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return None;
+    }
+
+    if let Some(names) = node.get::<Array<'_>>(b"Names") {
+        let mut items = names.flex_iter();
+        while let Some(name) = items.next::<PdfString<'_>>() {
+            let value = items.next::<Object<'_>>()?;
+            if name.as_bytes() == key {
+                return Some(value);
+            }
+        }
+    }
+
+    node.get::<Array<'_>>(b"Kids")?
+        .iter::<Dict<'_>>()
+        .find_map(|kid| find_in_name_tree(&kid, key, depth + 1))
+}
+
+/// PDFのテキスト文字列をデコードする
+///
+/// UTF-16BE（BOM付き）とUTF-8（BOM付き）に対応し、それ以外はPDFDocEncodingとして
+/// Latin-1の範囲で解釈する
+fn decode_text(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(utf8).into_owned();
+    }
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2ページのPDF（しおり: Cover → 1ページ目、目次 > Back → 名前付きの宛先で2ページ目）
+    const OUTLINE_PDF: &str = "%PDF-1.4
+1 0 obj
+<< /Type /Catalog /Pages 2 0 R /Outlines 5 0 R /Dests << /back [4 0 R /Fit] >> >>
+endobj
+2 0 obj
+<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>
+endobj
+3 0 obj
+<< /Type /Page /Parent 2 0 R /MediaBox [0 0 72 72] >>
+endobj
+4 0 obj
+<< /Type /Page /Parent 2 0 R /MediaBox [0 0 72 72] >>
+endobj
+5 0 obj
+<< /Type /Outlines /First 6 0 R /Last 7 0 R /Count 3 >>
+endobj
+6 0 obj
+<< /Title (Cover) /Parent 5 0 R /Next 7 0 R /Dest [3 0 R /XYZ 0 72 0] >>
+endobj
+7 0 obj
+<< /Title <feff76ee6b21> /Parent 5 0 R /Prev 6 0 R /First 8 0 R /Last 8 0 R /Count 1 >>
+endobj
+8 0 obj
+<< /Title (Back) /Parent 7 0 R /A << /S /GoTo /D /back >> >>
+endobj
+xref
+0 9
+0000000000 65535 f 
+0000000009 00000 n 
+0000000106 00000 n 
+0000000169 00000 n 
+0000000238 00000 n 
+0000000307 00000 n 
+0000000378 00000 n 
+0000000466 00000 n 
+0000000569 00000 n 
+trailer
+<< /Size 9 /Root 1 0 R >>
+startxref
+645
+%%EOF
+";
+
+    #[test]
+    fn test_read_outline() {
+        let pdf = Pdf::new(OUTLINE_PDF.as_bytes().to_vec()).unwrap();
+        let toc = read_outline(&pdf);
+
+        assert_eq!(toc.len(), 2);
+        assert_eq!((toc[0].title.as_str(), toc[0].page), ("Cover", 0));
+        assert!(toc[0].children.is_empty());
+
+        // 宛先の無い項目は最初の子項目のページ
+        assert_eq!((toc[1].title.as_str(), toc[1].page), ("目次", 1));
+        assert_eq!(toc[1].children.len(), 1);
+        assert_eq!(
+            (toc[1].children[0].title.as_str(), toc[1].children[0].page),
+            ("Back", 1)
+        );
+    }
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text(b"Cover"), "Cover");
+        assert_eq!(decode_text(&[0xFE, 0xFF, 0x76, 0xEE, 0x6B, 0x21]), "目次");
+        assert_eq!(decode_text("\u{FEFF}春".as_bytes()), "春");
+    }
+}
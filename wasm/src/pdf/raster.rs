@@ -0,0 +1,395 @@
+use hayro::hayro_interpret::{InterpreterSettings, TransformExt};
+use hayro::hayro_syntax::page::Page;
+use hayro::hayro_syntax::{DecryptionError, LoadPdfError, Pdf};
+use hayro::kurbo::Affine;
+use hayro::vello_cpu::color::palette::css::WHITE;
+use hayro::{PixmapSettings, RenderCache, RenderSettings};
+use image::{DynamicImage, RgbaImage};
+use serde::Serialize;
+
+use super::{outline, text};
+use crate::error::PamphletError;
+use crate::search::PageText;
+use crate::tiler::{TileResult, TilerContext};
+use crate::TocEntry;
+
+/// PDFの1ポイント（1/72インチ）あたりのピクセル数を1とする解像度
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// ラスタライズ後の1辺の上限（ピクセル）
+const MAX_DIMENSION: f32 = u16::MAX as f32;
+
+/// PDFを開けない理由
+///
+/// JavaScript側では `code` で判別し、パスワードの入力を促すことができる
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum PdfOpenError {
+    /// パスワードで保護されているが、パスワードが指定されていない
+    PasswordRequired,
+    /// 指定したパスワードが正しくない
+    WrongPassword,
+    /// PDFとして解釈できない（対応していない暗号化方式を含む）
+    InvalidPdf { message: String },
+}
+
+impl std::fmt::Display for PdfOpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdfOpenError::PasswordRequired => write!(f, "PDF is password protected"),
+            PdfOpenError::WrongPassword => write!(f, "Wrong PDF password"),
+            PdfOpenError::InvalidPdf { message } => write!(f, "Failed to load PDF: {}", message),
+        }
+    }
+}
+
+/// 読み込んだPDF文書
+///
+/// ページを指定した解像度でラスタライズし、そのままタイル化に渡す
+pub struct PdfDocument {
+    pdf: Pdf,
+}
+
+impl PdfDocument {
+    /// PDFのバイト列を読み込む
+    ///
+    /// 暗号化されたPDFはパスワードで開く。AES-256（PDF 2.0・Acrobat 9以降）は
+    /// ユーザーパスワードと所有者パスワードのどちらでも開けるが、それより古い方式は
+    /// ユーザーパスワード（文書を開くパスワード）が必要。
+    ///
+    /// # Arguments
+    /// * `data` - PDFのバイト列
+    /// * `password` - パスワード（暗号化されていない場合や、開くパスワードが空の場合は不要）
+    ///
+    /// # Errors
+    /// PDFとして解釈できない場合や、パスワードが無い・正しくない場合
+    pub fn open(data: Vec<u8>, password: Option<&str>) -> Result<Self, PdfOpenError> {
+        let pdf =
+            Pdf::new_with_password(data, password.unwrap_or_default()).map_err(|e| match e {
+                LoadPdfError::Decryption(DecryptionError::PasswordProtected) => {
+                    if password.is_some_and(|p| !p.is_empty()) {
+                        PdfOpenError::WrongPassword
+                    } else {
+                        PdfOpenError::PasswordRequired
+                    }
+                }
+                e => PdfOpenError::InvalidPdf {
+                    message: format!("{:?}", e),
+                },
+            })?;
+        Ok(Self { pdf })
+    }
+
+    /// ページ数
+    pub fn page_count(&self) -> usize {
+        self.pdf.pages().len()
+    }
+
+    /// しおり（アウトライン）を目次として読む
+    ///
+    /// しおりが無い場合は空
+    pub fn outline(&self) -> Vec<TocEntry> {
+        outline::read_outline(&self.pdf)
+    }
+
+    /// ページをラスタライズする
+    ///
+    /// 背景は白で塗りつぶす。非常に大きなページは1辺が65535ピクセルに収まるよう解像度を下げる。
+    ///
+    /// # Arguments
+    /// * `index` - ページ番号（0始まり）
+    /// * `dpi` - 解像度（DPI）
+    ///
+    /// # Returns
+    /// ラスタライズした画像と、実際に使用した解像度
+    pub fn render_page(&self, index: usize, dpi: f32) -> Result<(RgbaImage, f32), PamphletError> {
+        let page = self.page(index)?;
+        let scale = raster_scale(page, dpi)?;
+
+        let pixmap = hayro::render(
+            page,
+            &RenderCache::new(),
+            &InterpreterSettings::default(),
+            &RenderSettings::default(),
+            &PixmapSettings {
+                x_scale: scale,
+                y_scale: scale,
+                bg_color: WHITE,
+            },
+        );
+
+        // 背景が不透明なので、乗算済みアルファのままで通常のRGBAと同じ値になる
+        let image = RgbaImage::from_raw(
+            pixmap.width() as u32,
+            pixmap.height() as u32,
+            pixmap.data_as_u8_slice().to_vec(),
+        )
+        .ok_or_else(|| PamphletError::Internal("Failed to read rendered page".to_string()))?;
+
+        Ok((image, scale * POINTS_PER_INCH))
+    }
+
+    /// ページをラスタライズしたときのサイズ（ピクセル）を、描画せずに求める
+    ///
+    /// # Returns
+    /// 幅・高さと、実際に使用する解像度
+    pub fn page_dimensions(
+        &self,
+        index: usize,
+        dpi: f32,
+    ) -> Result<(u32, u32, f32), PamphletError> {
+        let page = self.page(index)?;
+        let scale = raster_scale(page, dpi)?;
+        let (width, height) = page.render_dimensions();
+
+        // `hayro::render` と同じく切り捨てる
+        Ok((
+            (width * scale) as u32,
+            (height * scale) as u32,
+            scale * POINTS_PER_INCH,
+        ))
+    }
+
+    /// ページのテキストを単語単位で取り出す
+    ///
+    /// 座標は同じ解像度で `render_page` した画像のピクセルで、metadataのページ情報の
+    /// `words` や検索索引にそのまま使える。テキストを持たない（スキャン画像だけの）ページは空になる。
+    ///
+    /// # Arguments
+    /// * `index` - ページ番号（0始まり）
+    /// * `dpi` - 解像度（DPI）
+    pub fn page_text(&self, index: usize, dpi: f32) -> Result<PageText, PamphletError> {
+        let page = self.page(index)?;
+        let scale = raster_scale(page, dpi)?;
+        let transform = Affine::scale(scale as f64) * page.initial_transform(true).to_kurbo();
+
+        Ok(PageText {
+            page: index as u32,
+            words: text::extract_words(page, transform),
+        })
+    }
+
+    fn page(&self, index: usize) -> Result<&Page<'_>, PamphletError> {
+        self.pdf
+            .pages()
+            .get(index)
+            .ok_or_else(|| PamphletError::NotFound(format!("Page index out of bounds: {}", index)))
+    }
+
+    /// ページをラスタライズしてタイル化する
+    ///
+    /// # Arguments
+    /// * `index` - ページ番号（0始まり）
+    /// * `dpi` - 解像度（DPI）
+    /// * `tile_size` - タイルサイズ（ピクセル）
+    /// * `quality` - WebP品質（1-100）
+    /// * `context` - タイル化の設定と作業用バッファ
+    pub fn tile_page(
+        &self,
+        index: usize,
+        dpi: f32,
+        tile_size: u32,
+        quality: Option<f32>,
+        context: &mut TilerContext,
+    ) -> Result<(TileResult, f32), PamphletError> {
+        let (image, dpi) = self.render_page(index, dpi)?;
+        let result = context.tile_decoded(&DynamicImage::ImageRgba8(image), tile_size, quality)?;
+        Ok((result, dpi))
+    }
+}
+
+/// 解像度からPDFのポイントあたりのピクセル数を求める（1辺の上限に収まるよう下げる）
+fn raster_scale(page: &Page<'_>, dpi: f32) -> Result<f32, PamphletError> {
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err(PamphletError::InvalidArgument(format!(
+            "Invalid DPI: {}",
+            dpi
+        )));
+    }
+
+    let (width, height) = page.render_dimensions();
+    Ok((dpi / POINTS_PER_INCH).min(MAX_DIMENSION / width.max(height).max(1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::pdf::{export_pdf, PdfOptions};
+    use crate::{PageInfo, TileMetadata};
+
+    /// ユーザーパスワード `secret`・所有者パスワード `owner` で暗号化した空白の1ページのPDF（RC4 40bit）
+    const ENCRYPTED_PDF: &str = "%PDF-1.4
+1 0 obj
+<< /Type /Catalog /Pages 2 0 R >>
+endobj
+2 0 obj
+<< /Type /Pages /Kids [3 0 R] /Count 1 >>
+endobj
+3 0 obj
+<< /Type /Page /Parent 2 0 R /MediaBox [0 0 144 72] >>
+endobj
+4 0 obj
+<< /Filter /Standard /V 1 /R 2 /O <92fe0f4454ad4c9644693f33c07cb54f587dce1e2682fe9ecea6107a1ef630dd> /U <f4c3199cb21e35b4bd6c22a97a2acca13b084d9016256900cd312e0758df7e6f> /P -4 >>
+endobj
+xref
+0 5
+0000000000 65535 f 
+0000000009 00000 n 
+0000000058 00000 n 
+0000000115 00000 n 
+0000000185 00000 n 
+trailer
+<< /Size 5 /Root 1 0 R /Encrypt 4 0 R /ID [<00112233445566778899aabbccddeeff> <00112233445566778899aabbccddeeff>] >>
+startxref
+380
+%%EOF
+";
+
+    /// Helveticaのテキストを含む200x72ポイントの1ページのPDF
+    const TEXT_PDF: &str = "%PDF-1.4
+1 0 obj
+<< /Type /Catalog /Pages 2 0 R >>
+endobj
+2 0 obj
+<< /Type /Pages /Kids [3 0 R] /Count 1 >>
+endobj
+3 0 obj
+<< /Type /Page /Parent 2 0 R /MediaBox [0 0 200 72] /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>
+endobj
+4 0 obj
+<< /Length 87 >>
+stream
+BT /F1 24 Tf 10 20 Td (Hello World) Tj ET
+BT /F1 12 Tf 10 50 Td [(New)-250(Sale)] TJ ET
+endstream
+endobj
+5 0 obj
+<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>
+endobj
+xref
+0 6
+0000000000 65535 f 
+0000000009 00000 n 
+0000000058 00000 n 
+0000000115 00000 n 
+0000000240 00000 n 
+0000000377 00000 n 
+trailer
+<< /Size 6 /Root 1 0 R >>
+startxref
+447
+%%EOF
+";
+
+    /// 72DPI・144x72ピクセル（2x1インチ）の1ページのPDF
+    fn sample_pdf() -> Vec<u8> {
+        let mut tile = image::RgbImage::from_pixel(144, 72, image::Rgb([200, 30, 30]));
+        tile.put_pixel(0, 0, image::Rgb([0, 0, 0]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(tile)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let page = PageInfo {
+            page: 0,
+            width: 144,
+            height: 72,
+            tiles: vec![TileMetadata {
+                x: 0,
+                y: 0,
+                hash: "a".to_string(),
+            }],
+            dpi: Some(72.0),
+            ..Default::default()
+        };
+        let tiles = HashMap::from([("a".to_string(), png)]);
+        export_pdf(&[page], &tiles, &PdfOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_render_page_at_dpi() {
+        let document = PdfDocument::open(sample_pdf(), None).unwrap();
+        assert_eq!(document.page_count(), 1);
+
+        let (image, dpi) = document.render_page(0, 144.0).unwrap();
+        assert_eq!(dpi, 144.0);
+        assert_eq!(image.dimensions(), (288, 144));
+        assert_eq!(
+            document.page_dimensions(0, 144.0).unwrap(),
+            (288, 144, 144.0)
+        );
+
+        // ページ中央は埋め込んだ画像の色
+        let center = image.get_pixel(144, 72);
+        assert!(center[0] > 150 && center[1] < 80 && center[3] == 255);
+
+        assert!(document.render_page(1, 144.0).is_err());
+        assert!(document.render_page(0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_tile_page() {
+        let document = PdfDocument::open(sample_pdf(), None).unwrap();
+        let (result, _) = document
+            .tile_page(0, 72.0, 64, Some(80.0), &mut TilerContext::new())
+            .unwrap();
+
+        assert_eq!((result.width, result.height), (144, 72));
+        assert_eq!(result.tiles.len(), 3 * 2);
+
+        assert!(matches!(
+            PdfDocument::open(b"not a pdf".to_vec(), None),
+            Err(PdfOpenError::InvalidPdf { .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_text() {
+        let document = PdfDocument::open(TEXT_PDF.as_bytes().to_vec(), None).unwrap();
+        let text = document.page_text(0, 144.0).unwrap();
+
+        let words: Vec<&str> = text.words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, vec!["Hello", "World", "New", "Sale"]);
+
+        // 144DPIのピクセル座標（"Hello" は x=10pt、ベースラインは下から20pt）
+        let hello = &text.words[0];
+        assert!((hello.x - 20.0).abs() < 0.01);
+        assert!(hello.y < 104.0 && hello.y + hello.height > 104.0);
+
+        let mut index = crate::search::SearchIndex::new();
+        index.add_page(&text);
+        assert_eq!(index.query("sale").len(), 1);
+
+        // テキストの無いページ
+        let document = PdfDocument::open(sample_pdf(), None).unwrap();
+        assert!(document.page_text(0, 72.0).unwrap().words.is_empty());
+        assert!(document.page_text(1, 72.0).is_err());
+    }
+
+    #[test]
+    fn test_open_with_password() {
+        let data = ENCRYPTED_PDF.as_bytes().to_vec();
+
+        assert_eq!(
+            PdfDocument::open(data.clone(), None).err(),
+            Some(PdfOpenError::PasswordRequired)
+        );
+        assert_eq!(
+            PdfDocument::open(data.clone(), Some("wrong")).err(),
+            Some(PdfOpenError::WrongPassword)
+        );
+
+        let document = PdfDocument::open(data, Some("secret")).unwrap();
+        assert_eq!(document.page_count(), 1);
+        let (image, _) = document.render_page(0, 72.0).unwrap();
+        assert_eq!(image.dimensions(), (144, 72));
+
+        // JavaScript側で判別するための形式
+        assert_eq!(
+            serde_json::to_value(PdfOpenError::PasswordRequired).unwrap(),
+            serde_json::json!({ "code": "password_required" })
+        );
+    }
+}
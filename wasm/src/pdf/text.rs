@@ -0,0 +1,196 @@
+use hayro::hayro_interpret::font::{Glyph, GlyphRun};
+use hayro::hayro_interpret::hayro_cmap::BfString;
+use hayro::hayro_interpret::{
+    interpret_page, BlendMode, ClipPath, Context, Device, DrawMode, DrawProps, Image,
+    ImageDrawProps, InterpreterCache, InterpreterSettings, SoftMask,
+};
+use hayro::hayro_syntax::page::Page;
+use hayro::kurbo::{Affine, BezPath, Point, Rect};
+
+use crate::search::WordBox;
+
+/// グリフ座標系の1emの大きさ
+const UNITS_PER_EM: f64 = 1000.0;
+
+/// グリフの高さの範囲（ベースラインからのディセンダー・アセンダー、グリフ座標系）
+const DESCENT: f64 = -200.0;
+const ASCENT: f64 = 800.0;
+
+/// 送り幅が分からないグリフ（Type3フォント）の幅
+const DEFAULT_ADVANCE: f64 = 500.0;
+
+/// 前のグリフとの間隔がこれ（em）を超えたら別の単語とする
+const WORD_GAP: f64 = 0.2;
+
+/// 配置済みのグリフ（デバイス座標）
+#[derive(Debug, Clone)]
+struct PlacedGlyph {
+    text: String,
+    /// ベースライン上の始点と、送り幅だけ進めた終点
+    start: Point,
+    end: Point,
+    /// 1emの大きさ
+    em: f64,
+    bounds: Rect,
+}
+
+/// 描画命令からテキストのグリフだけを集める
+#[derive(Default)]
+struct TextCollector {
+    glyphs: Vec<PlacedGlyph>,
+}
+
+impl<'a> Device<'a> for TextCollector {
+    fn draw_path(&mut self, _: &BezPath, _: DrawProps<'a>, _: &DrawMode) {}
+    fn push_clip_path(&mut self, _: &ClipPath) {}
+    fn push_transparency_group(&mut self, _: f32, _: Option<SoftMask<'a>>, _: BlendMode) {}
+    fn draw_image(&mut self, _: Image<'a, '_>, _: ImageDrawProps<'a>) {}
+    fn pop_clip(&mut self) {}
+    fn pop_transparency_group(&mut self) {}
+
+    fn draw_glyph_run(&mut self, run: &GlyphRun<'_, 'a>, props: DrawProps<'a>, _: &DrawMode) {
+        for glyph in run.glyphs() {
+            let text = match glyph.as_unicode() {
+                Some(BfString::Char(c)) => c.to_string(),
+                Some(BfString::String(s)) => s,
+                // Unicodeに対応付けられないグリフは検索できないので捨てる
+                None => continue,
+            };
+            let advance = match &**glyph {
+                Glyph::Outline(outline) => outline.advance_width().map(f64::from),
+                Glyph::Type3(_) => None,
+            }
+            .filter(|advance| *advance > 0.0)
+            .unwrap_or(DEFAULT_ADVANCE);
+
+            let transform = props.transform * glyph.transform();
+            let start = transform * Point::ORIGIN;
+            let corners = [
+                Point::new(0.0, DESCENT),
+                Point::new(advance, DESCENT),
+                Point::new(0.0, ASCENT),
+                Point::new(advance, ASCENT),
+            ]
+            .map(|corner| transform * corner);
+            let bounds = corners[1..]
+                .iter()
+                .fold(Rect::from_points(corners[0], corners[0]), |rect, p| {
+                    rect.union_pt(*p)
+                });
+
+            self.glyphs.push(PlacedGlyph {
+                text,
+                start,
+                end: transform * Point::new(advance, 0.0),
+                em: (transform * Point::new(0.0, UNITS_PER_EM) - start).hypot(),
+                bounds,
+            });
+        }
+    }
+}
+
+/// ページのテキストを単語単位で取り出す
+///
+/// 空白のグリフと、前のグリフから離れた位置（改行や字間の大きな空き）で単語を区切る。
+/// 日本語のように空白を含まない行は、行全体が1つの単語になる（検索索引は部分一致なので問題ない）。
+///
+/// # Arguments
+/// * `page` - ページ
+/// * `transform` - ページ座標から出力座標（ラスタライズした画像のピクセル）への変換
+pub fn extract_words(page: &Page<'_>, transform: Affine) -> Vec<WordBox> {
+    let cache = InterpreterCache::new();
+    let (width, height) = page.render_dimensions();
+    let mut context = Context::new(
+        transform,
+        Rect::new(0.0, 0.0, width as f64, height as f64),
+        &cache,
+        page.xref(),
+        InterpreterSettings::default(),
+    );
+    let mut collector = TextCollector::default();
+    interpret_page(page, &mut context, &mut collector);
+
+    group_words(&collector.glyphs)
+}
+
+/// グリフを描画順に単語にまとめる
+fn group_words(glyphs: &[PlacedGlyph]) -> Vec<WordBox> {
+    let mut words = Vec::new();
+    let mut current: Option<(String, Rect)> = None;
+    let mut previous: Option<&PlacedGlyph> = None;
+
+    for glyph in glyphs {
+        let separated = previous.is_some_and(|previous| {
+            (glyph.start - previous.end).hypot() > WORD_GAP * previous.em.max(glyph.em)
+        });
+        if separated || glyph.text.trim().is_empty() {
+            words.extend(current.take().map(to_word_box));
+        }
+        previous = Some(glyph);
+
+        if glyph.text.trim().is_empty() {
+            continue;
+        }
+        match &mut current {
+            Some((text, bounds)) => {
+                text.push_str(&glyph.text);
+                *bounds = bounds.union(glyph.bounds);
+            }
+            None => current = Some((glyph.text.clone(), glyph.bounds)),
+        }
+    }
+    words.extend(current.map(to_word_box));
+
+    words
+}
+
+fn to_word_box((text, bounds): (String, Rect)) -> WordBox {
+    WordBox {
+        text,
+        x: bounds.x0,
+        y: bounds.y0,
+        width: bounds.width(),
+        height: bounds.height(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(text: &str, x: f64, y: f64) -> PlacedGlyph {
+        PlacedGlyph {
+            text: text.to_string(),
+            start: Point::new(x, y),
+            end: Point::new(x + 10.0, y),
+            em: 20.0,
+            bounds: Rect::new(x, y - 16.0, x + 10.0, y + 4.0),
+        }
+    }
+
+    #[test]
+    fn test_group_words() {
+        let glyphs = [
+            glyph("N", 0.0, 100.0),
+            glyph("e", 10.0, 100.0),
+            glyph("w", 20.0, 100.0),
+            // 空白のグリフ
+            glyph(" ", 30.0, 100.0),
+            glyph("S", 40.0, 100.0),
+            // 字間の大きな空き
+            glyph("a", 60.0, 100.0),
+            // 改行
+            glyph("新", 0.0, 130.0),
+            glyph("商", 10.0, 130.0),
+            glyph("品", 20.0, 130.0),
+        ];
+
+        let words = group_words(&glyphs);
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, vec!["New", "S", "a", "新商品"]);
+        assert_eq!(
+            (words[0].x, words[0].y, words[0].width, words[0].height),
+            (0.0, 84.0, 30.0, 20.0)
+        );
+    }
+}
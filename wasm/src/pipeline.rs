@@ -0,0 +1,362 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::PageInfo;
+
+/// ワーカー1つあたりのまとまりの数（既定のまとまりの大きさを決める）
+///
+/// 1つのワーカーに何度かに分けて渡すことで、ページごとの処理時間の差をならす
+const CHUNKS_PER_WORKER: u32 = 4;
+
+/// まとまりの処理に失敗したときに試す回数の上限
+const MAX_ATTEMPTS: u32 = 3;
+
+/// コーディネーターからワーカーへのメッセージ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerRequest {
+    /// ページのまとまりをタイル化する
+    TilePages {
+        chunk: u32,
+        /// ページ番号（0始まり）
+        pages: Vec<u32>,
+        tile_size: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quality: Option<f32>,
+    },
+}
+
+/// ワーカーからコーディネーターへのメッセージ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerResponse {
+    /// まとまりのページをタイル化した
+    ChunkDone { chunk: u32, pages: Vec<PageInfo> },
+    /// まとまりの処理に失敗した（他のワーカーで試し直す）
+    ChunkFailed { chunk: u32, error: String },
+}
+
+/// まとまりの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    Pending,
+    Assigned { worker: u32 },
+    Done,
+}
+
+#[derive(Debug)]
+struct Chunk {
+    /// ページ番号の範囲（`start..end`）
+    start: u32,
+    end: u32,
+    state: ChunkState,
+    attempts: u32,
+}
+
+/// 複数ページのタイル化を複数のワーカー（それぞれWASMのインスタンスを持つWeb Worker）に分ける
+///
+/// ページを連続したまとまりに分け、空いたワーカーに1つずつ割り当てる。ワーカーから
+/// 返ったページ情報を集め、ページ順に並べてmetadataを組み立てる。失敗したまとまりや
+/// 落ちたワーカーのまとまりは、他のワーカーに割り当て直す。
+#[derive(Debug)]
+pub struct PipelineCoordinator {
+    tile_size: u32,
+    quality: Option<f32>,
+    chunks: Vec<Chunk>,
+    /// 割り当てを待っているまとまり
+    queue: VecDeque<usize>,
+    /// ページ番号ごとのページ情報
+    pages: Vec<Option<PageInfo>>,
+    /// 処理を諦めた理由
+    error: Option<String>,
+}
+
+impl PipelineCoordinator {
+    /// # Arguments
+    /// * `page_count` - ページ数
+    /// * `workers` - ワーカーの数（既定のまとまりの大きさに使う）
+    /// * `tile_size` - タイルサイズ（ワーカーに渡す）
+    /// * `quality` - WebP品質（ワーカーに渡す）
+    /// * `chunk_size` - まとまりのページ数（省略時はワーカーあたり4つのまとまりになる大きさ）
+    pub fn new(
+        page_count: u32,
+        workers: u32,
+        tile_size: u32,
+        quality: Option<f32>,
+        chunk_size: Option<u32>,
+    ) -> Self {
+        let chunk_size = chunk_size
+            .unwrap_or_else(|| page_count.div_ceil(workers.max(1) * CHUNKS_PER_WORKER))
+            .max(1);
+        let chunks: Vec<Chunk> = (0..page_count)
+            .step_by(chunk_size as usize)
+            .map(|start| Chunk {
+                start,
+                end: (start + chunk_size).min(page_count),
+                state: ChunkState::Pending,
+                attempts: 0,
+            })
+            .collect();
+
+        Self {
+            tile_size,
+            quality,
+            queue: (0..chunks.len()).collect(),
+            chunks,
+            pages: vec![None; page_count as usize],
+            error: None,
+        }
+    }
+
+    /// ワーカーに送る次のメッセージ
+    ///
+    /// ワーカーにはまとまりを1つずつ割り当てる。割り当て済みのまとまりが終わっていない、
+    /// 残りのまとまりがない、処理を諦めた場合は `None`
+    pub fn next_request(&mut self, worker: u32) -> Option<WorkerRequest> {
+        if self.error.is_some() || self.assigned_to(worker).is_some() {
+            return None;
+        }
+        let index = self.queue.pop_front()?;
+        let chunk = &mut self.chunks[index];
+        chunk.state = ChunkState::Assigned { worker };
+        chunk.attempts += 1;
+
+        Some(WorkerRequest::TilePages {
+            chunk: index as u32,
+            pages: (chunk.start..chunk.end).collect(),
+            tile_size: self.tile_size,
+            quality: self.quality,
+        })
+    }
+
+    /// ワーカーからのメッセージを受け取る
+    ///
+    /// # Errors
+    /// 割り当てていないまとまりの結果の場合、ページがまとまりと一致しない場合、
+    /// 失敗したまとまりが試す回数の上限に達した場合（以降は割り当てない）
+    pub fn handle_response(&mut self, worker: u32, response: WorkerResponse) -> Result<(), String> {
+        match response {
+            WorkerResponse::ChunkDone { chunk, pages } => {
+                let index = self.expect_assigned(worker, chunk)?;
+                let (start, end) = (self.chunks[index].start, self.chunks[index].end);
+                let mut numbers: Vec<u32> = pages.iter().map(|page| page.page).collect();
+                numbers.sort_unstable();
+                if !numbers.iter().copied().eq(start..end) {
+                    return Err(format!(
+                        "Chunk {} must contain pages {}..{}, got {:?}",
+                        chunk, start, end, numbers
+                    ));
+                }
+
+                for page in pages {
+                    let number = page.page as usize;
+                    self.pages[number] = Some(page);
+                }
+                self.chunks[index].state = ChunkState::Done;
+                Ok(())
+            }
+            WorkerResponse::ChunkFailed { chunk, error } => {
+                let index = self.expect_assigned(worker, chunk)?;
+                if self.chunks[index].attempts >= MAX_ATTEMPTS {
+                    let message = format!(
+                        "Chunk {} failed after {} attempts: {}",
+                        chunk, MAX_ATTEMPTS, error
+                    );
+                    self.error = Some(message.clone());
+                    return Err(message);
+                }
+                self.requeue(index);
+                Ok(())
+            }
+        }
+    }
+
+    /// ワーカーが落ちた（終了した）ときに、割り当てていたまとまりを戻す
+    pub fn worker_lost(&mut self, worker: u32) {
+        if let Some(index) = self.assigned_to(worker) {
+            self.requeue(index);
+        }
+    }
+
+    /// すべてのページの結果が揃ったか
+    pub fn is_complete(&self) -> bool {
+        self.pages.iter().all(Option::is_some)
+    }
+
+    /// 結果が揃ったページ数
+    pub fn completed_pages(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+
+    /// 処理を諦めた理由
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// ページ順に並べたページ情報
+    ///
+    /// # Errors
+    /// 結果が揃っていない場合
+    pub fn pages(&self) -> Result<Vec<PageInfo>, String> {
+        self.pages
+            .iter()
+            .enumerate()
+            .map(|(number, page)| {
+                page.clone()
+                    .ok_or_else(|| format!("Page {} has not been processed", number))
+            })
+            .collect()
+    }
+
+    fn assigned_to(&self, worker: u32) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|chunk| chunk.state == ChunkState::Assigned { worker })
+    }
+
+    fn expect_assigned(&self, worker: u32, chunk: u32) -> Result<usize, String> {
+        let index = chunk as usize;
+        match self.chunks.get(index) {
+            Some(c) if c.state == ChunkState::Assigned { worker } => Ok(index),
+            _ => Err(format!(
+                "Chunk {} is not assigned to worker {}",
+                chunk, worker
+            )),
+        }
+    }
+
+    /// まとまりを割り当て待ちの先頭に戻す（先に終わらせて順番を保つ）
+    fn requeue(&mut self, index: usize) {
+        self.chunks[index].state = ChunkState::Pending;
+        self.queue.push_front(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TileMetadata;
+
+    fn page(number: u32) -> PageInfo {
+        PageInfo {
+            page: number,
+            width: 100,
+            height: 100,
+            tiles: vec![TileMetadata {
+                x: 0,
+                y: 0,
+                hash: format!("hash{}", number),
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// ワーカーとしてまとまりを処理した結果（ページを逆順に返す）
+    fn done(request: &WorkerRequest) -> WorkerResponse {
+        let WorkerRequest::TilePages { chunk, pages, .. } = request;
+        WorkerResponse::ChunkDone {
+            chunk: *chunk,
+            pages: pages.iter().rev().map(|&number| page(number)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_distribute() {
+        // 10ページを2つのワーカーに、既定では 10 / (2 * 4) → 2ページずつ分ける
+        let mut coordinator = PipelineCoordinator::new(10, 2, 512, Some(80.0), None);
+        let first = coordinator.next_request(0).unwrap();
+        let second = coordinator.next_request(1).unwrap();
+        let WorkerRequest::TilePages {
+            pages, tile_size, ..
+        } = &first;
+        assert_eq!((pages.as_slice(), *tile_size), (&[0, 1][..], 512));
+
+        // 割り当て済みのワーカーには次を渡さない
+        assert!(coordinator.next_request(0).is_none());
+
+        // 後のまとまりが先に終わっても、ページ順に並ぶ
+        coordinator.handle_response(1, done(&second)).unwrap();
+        coordinator.handle_response(0, done(&first)).unwrap();
+        while let Some(request) = coordinator.next_request(0) {
+            coordinator.handle_response(0, done(&request)).unwrap();
+        }
+        assert!(coordinator.is_complete());
+        let numbers: Vec<u32> = coordinator
+            .pages()
+            .unwrap()
+            .iter()
+            .map(|p| p.page)
+            .collect();
+        assert_eq!(numbers, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut coordinator = PipelineCoordinator::new(4, 2, 256, None, Some(2));
+        let request = coordinator.next_request(0).unwrap();
+
+        // 落ちたワーカーのまとまりは他のワーカーに割り当て直す
+        coordinator.worker_lost(0);
+        assert_eq!(coordinator.next_request(1).unwrap(), request);
+        assert!(coordinator
+            .handle_response(0, done(&request))
+            .unwrap_err()
+            .contains("not assigned"));
+
+        // 失敗したまとまりは試し直し、上限に達したら諦める
+        let failed = |chunk| WorkerResponse::ChunkFailed {
+            chunk,
+            error: "decode error".to_string(),
+        };
+        coordinator.handle_response(1, failed(0)).unwrap();
+        coordinator.next_request(1).unwrap();
+        let error = coordinator.handle_response(1, failed(0)).unwrap_err();
+        assert!(error.contains("3 attempts"));
+        assert!(coordinator.next_request(0).is_none());
+        assert_eq!(coordinator.error(), Some(error.as_str()));
+        assert!(!coordinator.is_complete());
+        assert!(coordinator.pages().is_err());
+    }
+
+    #[test]
+    fn test_wrong_pages() {
+        let mut coordinator = PipelineCoordinator::new(4, 1, 256, None, Some(2));
+        coordinator.next_request(0).unwrap();
+        let response = WorkerResponse::ChunkDone {
+            chunk: 0,
+            pages: vec![page(0), page(3)],
+        };
+        assert!(coordinator.handle_response(0, response).is_err());
+        assert_eq!(coordinator.completed_pages(), 0);
+    }
+
+    #[test]
+    fn test_message_format() {
+        let request = WorkerRequest::TilePages {
+            chunk: 1,
+            pages: vec![2, 3],
+            tile_size: 512,
+            quality: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({ "type": "tile_pages", "chunk": 1, "pages": [2, 3], "tile_size": 512 })
+        );
+
+        let response: WorkerResponse = serde_json::from_value(serde_json::json!({
+            "type": "chunk_done",
+            "chunk": 0,
+            "pages": [{ "page": 0, "width": 10, "height": 10, "tiles": [] }]
+        }))
+        .unwrap();
+        assert!(matches!(
+            response,
+            WorkerResponse::ChunkDone { chunk: 0, .. }
+        ));
+    }
+}
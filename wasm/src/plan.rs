@@ -0,0 +1,235 @@
+//! 出力の計画（`pamphlet-tiler --dry-run`、ネイティブのみ）
+//!
+//! デコード・タイルの配置・画素のハッシュまでを行い、エンコードと書き出しは行わない。
+//! 前回の出力の `.tile-index.json` に画素のハッシュがあるタイルは、エンコードした場合と
+//! 同じハッシュにする。CIで公開の前に、書き出す・アップロードする・使われなくなるタイルを確かめる
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::pamphlet::{self, InputPages, PamphletAssembler};
+use crate::publish::{map_jobs, read_tile_index, tile_index_key, DirectoryOptions};
+use crate::settings::Settings;
+use crate::tiler::{LazyTiles, TileWarning};
+use crate::upload::{upload_plan, UploadObject};
+
+/// `tile_files` で書き出すはずの内容
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Plan {
+    /// 書き出すはずのmetadata（`encode` のタイルのハッシュは画素のハッシュ）
+    pub metadata: serde_json::Value,
+    pub pages: usize,
+    pub tiles: usize,
+    /// エンコードして書き出すタイルの画素のハッシュ（前回の出力に無いタイル、重複は除く）
+    pub encode: Vec<String>,
+    /// アップロードする順のオブジェクト（`upload_plan`）
+    pub upload: Vec<UploadObject>,
+    /// 出力先にあって、書き出すはずのmetadataから参照されないタイルのキー（消してよいタイル）
+    pub unused: Vec<String>,
+    /// タイル化は続けた問題（ファイル名付き）
+    #[serde(skip)]
+    pub warnings: Vec<(PathBuf, TileWarning)>,
+}
+
+/// ファイルを並べた順にページとしてタイルの配置と画素のハッシュを計算し、`tile_files` で
+/// 書き出すはずのmetadataとアップロード・削除の計画を返す（タイルはエンコードせず、何も書き出さない）
+///
+/// タイルの画素のハッシュが `options.output` の前回の出力（`.tile-index.json`、タイル化の設定が
+/// 同じ場合）にあれば、そのタイルのハッシュを使う。キーは `prefix` から始まる（`upload_plan` と同じ）
+///
+/// # Errors
+/// `files` が空の場合、いずれかのファイルの読み込み・デコードに失敗した場合
+pub fn plan_files(
+    files: &[PathBuf],
+    options: &DirectoryOptions,
+    prefix: &str,
+) -> Result<Plan, String> {
+    if files.is_empty() {
+        return Err("No page images or PDFs found".to_string());
+    }
+    let index = read_tile_index(&options.output, &options.tile);
+    let inputs: Vec<(InputPages, Vec<String>)> = map_jobs(
+        options.jobs,
+        files,
+        || (),
+        |_, file| plan_file(file, options).map_err(|e| format!("{}: {}", file.display(), e)),
+    )?
+    .into_iter()
+    .collect::<Result<_, _>>()?;
+
+    let mut assembler = PamphletAssembler::new();
+    assembler.set_tile_layout(options.layout);
+    let mut encode = Vec::new();
+    let mut seen = HashSet::new();
+    let mut warnings = Vec::new();
+    for (file, (mut input, keys)) in files.iter().zip(inputs) {
+        let tiles = input.pages.iter_mut().flat_map(|(page, _)| &mut page.tiles);
+        for (tile, key) in tiles.zip(keys) {
+            match index.get(&key) {
+                Some(hash) => tile.hash = hash.clone(),
+                None if seen.insert(key) => encode.push(tile.hash.clone()),
+                None => {}
+            }
+        }
+        for (page, _) in &input.pages {
+            warnings.extend(
+                page.warnings
+                    .iter()
+                    .map(|warning| (file.clone(), warning.clone())),
+            );
+        }
+        assembler.add(input)?;
+    }
+    let (pages, tiles) = (assembler.page_count(), assembler.tile_count());
+    let metadata = assembler.build()?;
+
+    let upload = upload_plan(prefix, &metadata)?;
+    let used: HashSet<&str> = upload.iter().map(|object| object.path.as_str()).collect();
+    let mut unused: Vec<String> = tile_paths(&options.output)?
+        .into_iter()
+        .filter(|path| !used.contains(path.as_str()))
+        .map(|path| match prefix.trim_end_matches('/') {
+            "" => path,
+            prefix => format!("{}/{}", prefix, path),
+        })
+        .collect();
+    unused.sort();
+
+    Ok(Plan {
+        metadata,
+        pages,
+        tiles,
+        encode,
+        upload,
+        unused,
+        warnings,
+    })
+}
+
+/// 1ファイルのページを前処理し、タイルの配置と画素のハッシュを計算する（タイルのデータは空）
+///
+/// タイルを読み順に並べた `.tile-index.json` のキーも返す
+fn plan_file(file: &Path, options: &DirectoryOptions) -> Result<(InputPages, Vec<String>), String> {
+    let data = fs::read(file).map_err(|e| format!("Failed to read: {}", e))?;
+    let file_options = options.for_file(file);
+    let tile = &file_options.tile;
+    let (pages, toc) = pamphlet::map_preprocessed_pages(
+        data,
+        file_options.dpi,
+        &file_options.preprocess,
+        &Settings::global(),
+        |image, geometry| {
+            let (result, _) = LazyTiles::new(image, tile.tile_size, tile.quality)?;
+            Ok((result, geometry))
+        },
+    )?;
+    let keys = pages
+        .iter()
+        .flat_map(|(result, _)| &result.tiles)
+        .map(|info| tile_index_key(&info.hash, &options.tile, tile))
+        .collect();
+    Ok((InputPages { pages, toc }, keys))
+}
+
+/// 出力先の `tiles/` の中のファイル（`tiles/{hash}.webp` のような出力先からの相対パス）
+fn tile_paths(output: &Path) -> Result<Vec<String>, String> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![output.join("tiles")];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+                .path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Ok(relative) = path.strip_prefix(output) {
+                let parts: Vec<_> = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect();
+                paths.push(parts.join("/"));
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::TileLayout;
+    use crate::publish::tile_files;
+    use crate::test_util::png;
+    use crate::tiler::TileOptions;
+
+    #[test]
+    fn test_plan_files() {
+        let dir = std::env::temp_dir().join(format!("tile-wasm-plan-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let files = [dir.join("1.png"), dir.join("2.png")];
+        fs::write(&files[0], png(40, 20, 0)).unwrap();
+        fs::write(&files[1], png(40, 20, 0)).unwrap();
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, None),
+            ..DirectoryOptions::new(dir.join("out"))
+        };
+
+        // 前回の出力が無ければすべてのタイルをエンコードする（同じ画素のタイルは1つ）
+        let plan = plan_files(&files, &options, "pamphlets/demo").unwrap();
+        assert_eq!((plan.pages, plan.tiles, plan.encode.len()), (2, 4, 2));
+        assert_eq!(plan.upload.len(), 3);
+        assert!(plan.unused.is_empty());
+        assert!(!dir.join("out").exists());
+
+        // 出力した後は、書き出したmetadataと同じになる
+        let publication = tile_files(&files, &options).unwrap();
+        let plan = plan_files(&files, &options, "pamphlets/demo").unwrap();
+        assert_eq!(plan.metadata["pages"], publication.metadata["pages"]);
+        assert!(plan.encode.is_empty());
+        assert!(plan.unused.is_empty());
+
+        // 変わったページのタイルだけをエンコードし、使われなくなるタイルを返す
+        fs::write(&files[1], png(40, 20, 9)).unwrap();
+        let plan = plan_files(&files, &options, "pamphlets/demo").unwrap();
+        assert_eq!(plan.encode.len(), 2);
+        assert!(plan.unused.is_empty());
+        fs::write(&files[0], png(40, 20, 9)).unwrap();
+        let plan = plan_files(&files, &options, "pamphlets/demo").unwrap();
+        assert_eq!(plan.encode.len(), 2);
+        let mut expected: Vec<_> = publication.metadata["pages"][0]["tiles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tile| {
+                format!(
+                    "pamphlets/demo/tiles/{}.webp",
+                    tile["hash"].as_str().unwrap()
+                )
+            })
+            .collect();
+        expected.sort();
+        assert_eq!(plan.unused, expected);
+
+        // タイル化の設定が変わった場合は前回の出力を使わない
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, Some(50.0)),
+            layout: TileLayout::Sharded,
+            ..options
+        };
+        let plan = plan_files(&files[..1], &options, "").unwrap();
+        assert_eq!(plan.encode.len(), 2);
+        assert_eq!(plan.unused.len(), 2);
+        assert!(plan.upload[0].key.starts_with("tiles/"));
+        assert_eq!(plan.metadata["tile_layout"], "sharded");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
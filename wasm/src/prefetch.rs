@@ -0,0 +1,250 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{Point, Rect};
+use crate::viewport;
+use crate::PageInfo;
+
+/// 保持するviewport履歴のデフォルト件数
+pub const DEFAULT_MAX_SAMPLES: usize = 8;
+
+/// 予測時にviewportを投影するステップ数
+const PROJECTION_STEPS: u32 = 4;
+
+/// viewport履歴の1サンプル
+#[derive(Debug, Clone, Copy)]
+struct ViewportSample {
+    /// 記録時刻（ミリ秒）
+    time: f64,
+    /// 表示領域（画面座標）
+    viewport: Rect,
+}
+
+/// プリフェッチ候補のタイル
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchPrediction {
+    pub x: u32,
+    pub y: u32,
+    pub hash: String,
+    /// 確信度（0.0〜1.0）
+    pub confidence: f64,
+}
+
+/// パン速度から次に必要になるタイルを予測するプリフェッチャー
+#[derive(Debug, Clone)]
+pub struct Prefetcher {
+    samples: VecDeque<ViewportSample>,
+    max_samples: usize,
+}
+
+impl Prefetcher {
+    pub fn new(max_samples: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(max_samples),
+            max_samples: max_samples.max(2),
+        }
+    }
+
+    /// viewportの位置を記録する
+    ///
+    /// 時刻が巻き戻った場合（ページ切り替え等）は履歴をリセットする
+    pub fn record(&mut self, time: f64, viewport: Rect) {
+        if self.samples.back().is_some_and(|last| time < last.time) {
+            self.samples.clear();
+        }
+
+        if self.samples.len() == self.max_samples {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ViewportSample { time, viewport });
+    }
+
+    /// 履歴をクリアする
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    /// 現在のパン速度（画面ピクセル/ミリ秒）
+    pub fn velocity(&self) -> Point {
+        let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) else {
+            return Point::default();
+        };
+
+        let dt = last.time - first.time;
+        if dt <= 0.0 {
+            return Point::default();
+        }
+
+        Point::new(
+            (last.viewport.x - first.viewport.x) / dt,
+            (last.viewport.y - first.viewport.y) / dt,
+        )
+    }
+
+    /// 移動方向の一貫性（0.0〜1.0）
+    ///
+    /// 各区間の移動量の合計に対する、始点から終点までの移動量の割合。
+    /// まっすぐなスワイプほど1.0に近く、行ったり来たりするほど小さくなる。
+    fn direction_consistency(&self) -> f64 {
+        let path: f64 = self
+            .samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|(a, b)| a.viewport.center().distance(&b.viewport.center()))
+            .sum();
+
+        match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if path > 0.0 => {
+                first.viewport.center().distance(&last.viewport.center()) / path
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// 次に必要になるタイルを予測する
+    ///
+    /// 最新のviewportを現在の速度で `lookahead_ms` 先まで投影し、
+    /// 現在表示中でないタイルを確信度の高い順に返す。
+    ///
+    /// # Arguments
+    /// * `page` - ページのメタデータ
+    /// * `tile_size` - タイルサイズ
+    /// * `zoom` - 表示倍率
+    /// * `lookahead_ms` - 予測する時間幅（ミリ秒）
+    /// * `limit` - 返す最大タイル数
+    pub fn predict(
+        &self,
+        page: &PageInfo,
+        tile_size: u32,
+        zoom: f64,
+        lookahead_ms: f64,
+        limit: usize,
+    ) -> Vec<PrefetchPrediction> {
+        let Some(current) = self.samples.back().map(|s| s.viewport) else {
+            return Vec::new();
+        };
+
+        let velocity = self.velocity();
+        if velocity == Point::default() || lookahead_ms <= 0.0 {
+            return Vec::new();
+        }
+
+        let consistency = self.direction_consistency();
+        let visible: Vec<(u32, u32)> =
+            viewport::tiles_for_viewport(page, tile_size, &current, zoom)
+                .iter()
+                .map(|tile| (tile.x, tile.y))
+                .collect();
+
+        let mut candidates: HashMap<(u32, u32), PrefetchPrediction> = HashMap::new();
+
+        for step in 1..=PROJECTION_STEPS {
+            let t = lookahead_ms * step as f64 / PROJECTION_STEPS as f64;
+            let projected = Rect::new(
+                current.x + velocity.x * t,
+                current.y + velocity.y * t,
+                current.width,
+                current.height,
+            );
+            // 遠い未来ほど確信度を下げる
+            let confidence = consistency * (1.0 - (step - 1) as f64 / PROJECTION_STEPS as f64);
+
+            for tile in viewport::tiles_for_viewport(page, tile_size, &projected, zoom) {
+                if visible.contains(&(tile.x, tile.y)) {
+                    continue;
+                }
+
+                candidates
+                    .entry((tile.x, tile.y))
+                    .and_modify(|p| p.confidence = p.confidence.max(confidence))
+                    .or_insert_with(|| PrefetchPrediction {
+                        x: tile.x,
+                        y: tile.y,
+                        hash: tile.hash.clone(),
+                        confidence,
+                    });
+            }
+        }
+
+        let mut predictions: Vec<PrefetchPrediction> = candidates.into_values().collect();
+        predictions.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.y.cmp(&b.y))
+                .then_with(|| a.x.cmp(&b.x))
+        });
+        predictions.truncate(limit);
+
+        predictions
+    }
+}
+
+impl Default for Prefetcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SAMPLES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TileMetadata;
+
+    fn page() -> PageInfo {
+        let tiles = (0..4)
+            .flat_map(|y| {
+                (0..8).map(move |x| TileMetadata {
+                    x,
+                    y,
+                    hash: format!("{}-{}", x, y),
+                })
+            })
+            .collect();
+
+        PageInfo {
+            page: 0,
+            width: 800,
+            height: 400,
+            tiles,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_predicts_tiles_ahead_of_pan() {
+        let mut prefetcher = Prefetcher::default();
+        // 右方向へ 1px/ms でパン
+        for i in 0..4 {
+            let t = i as f64 * 16.0;
+            prefetcher.record(t, Rect::new(t, 0.0, 200.0, 200.0));
+        }
+
+        let predictions = prefetcher.predict(&page(), 100, 1.0, 200.0, 10);
+
+        assert!(!predictions.is_empty());
+        // 進行方向（右側）のタイルのみが予測される
+        assert!(predictions.iter().all(|p| p.x >= 2 && p.y <= 2));
+        // 100ms後に初めて見えるx=3の列が先頭（4ステップ中2ステップ目 → 0.75）
+        assert_eq!(predictions[0].x, 3);
+        assert!((predictions[0].confidence - 0.75).abs() < 1e-9);
+        assert!(predictions
+            .windows(2)
+            .all(|w| w[0].confidence >= w[1].confidence));
+    }
+
+    #[test]
+    fn test_no_prediction_without_motion() {
+        let mut prefetcher = Prefetcher::default();
+        assert!(prefetcher.predict(&page(), 100, 1.0, 200.0, 10).is_empty());
+
+        prefetcher.record(0.0, Rect::new(0.0, 0.0, 200.0, 200.0));
+        prefetcher.record(16.0, Rect::new(0.0, 0.0, 200.0, 200.0));
+        assert!(prefetcher.predict(&page(), 100, 1.0, 200.0, 10).is_empty());
+
+        // 時刻が巻き戻ると履歴はリセットされる
+        prefetcher.record(32.0, Rect::new(50.0, 0.0, 200.0, 200.0));
+        prefetcher.record(0.0, Rect::new(0.0, 0.0, 200.0, 200.0));
+        assert_eq!(prefetcher.velocity(), Point::default());
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// 背景の除去の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundOptions {
+    /// 四隅の色と同じ背景とみなす色の差（RGBの各チャンネルの差の最大、0-255）
+    pub tolerance: u8,
+    /// 切り抜いた輪郭をぼかす幅（ピクセル、0でぼかさない）
+    pub feather: u32,
+}
+
+impl Default for BackgroundOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 24,
+            feather: 1,
+        }
+    }
+}
+
+/// 四隅からつながる背景を透明にする（商品写真の切り抜き用）
+///
+/// 四隅それぞれの色から塗りつぶし（4近傍）で、色の差が `tolerance` 以内の画素を背景とする。
+/// 画像全体が背景と判定された場合は何もしない。
+pub fn remove_background(mut image: RgbaImage, options: &BackgroundOptions) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return image;
+    }
+
+    let background = flood_background(&image, options.tolerance);
+    if background.iter().all(|is_background| *is_background) {
+        return image;
+    }
+    let distances = distance_to_background(&background, width, height, options.feather);
+
+    for (index, pixel) in image.pixels_mut().enumerate() {
+        if background[index] {
+            pixel[3] = 0;
+        } else if let Some(distance) = distances[index] {
+            // 輪郭から離れるほど不透明にする
+            let ratio = distance as f64 / (options.feather + 1) as f64;
+            pixel[3] = (pixel[3] as f64 * ratio).round() as u8;
+        }
+    }
+    image
+}
+
+/// 四隅から塗りつぶした背景の画素
+fn flood_background(image: &RgbaImage, tolerance: u8) -> Vec<bool> {
+    let (width, height) = image.dimensions();
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+    let mut background = vec![false; (width * height) as usize];
+
+    let corners = [
+        (0, 0),
+        (width - 1, 0),
+        (0, height - 1),
+        (width - 1, height - 1),
+    ];
+    for (cx, cy) in corners {
+        if background[index(cx, cy)] {
+            continue;
+        }
+        let seed = *image.get_pixel(cx, cy);
+        let matches = |x: u32, y: u32| {
+            let pixel = image.get_pixel(x, y);
+            (0..4).all(|channel| pixel[channel].abs_diff(seed[channel]) <= tolerance)
+        };
+
+        let mut queue = VecDeque::from([(cx, cy)]);
+        background[index(cx, cy)] = true;
+        while let Some((x, y)) = queue.pop_front() {
+            let neighbours = [
+                (x.checked_sub(1), Some(y)),
+                ((x + 1 < width).then_some(x + 1), Some(y)),
+                (Some(x), y.checked_sub(1)),
+                (Some(x), (y + 1 < height).then_some(y + 1)),
+            ];
+            for (nx, ny) in neighbours {
+                let (Some(nx), Some(ny)) = (nx, ny) else {
+                    continue;
+                };
+                if !background[index(nx, ny)] && matches(nx, ny) {
+                    background[index(nx, ny)] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+    }
+    background
+}
+
+/// 背景から `feather` ピクセル以内の前景の画素の、背景までの距離（チェビシェフ距離）
+fn distance_to_background(
+    background: &[bool],
+    width: u32,
+    height: u32,
+    feather: u32,
+) -> Vec<Option<u32>> {
+    let mut distances = vec![None; background.len()];
+    let mut frontier: Vec<(u32, u32)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| background[(y * width + x) as usize])
+        .collect();
+
+    for distance in 1..=feather {
+        let mut next = Vec::new();
+        for (x, y) in frontier {
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let index = (ny * width + nx) as usize;
+                    if !background[index] && distances[index].is_none() {
+                        distances[index] = Some(distance);
+                        next.push((nx, ny));
+                    }
+                }
+            }
+        }
+        frontier = next;
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 白い背景（わずかなむらあり）の中央に赤い商品、その中に白い部分がある写真
+    fn product() -> RgbaImage {
+        RgbaImage::from_fn(20, 20, |x, y| {
+            let inside = (5..15).contains(&x) && (5..15).contains(&y);
+            if inside && (9..11).contains(&x) && (9..11).contains(&y) {
+                Rgba([255, 255, 255, 255])
+            } else if inside {
+                Rgba([200, 30, 30, 255])
+            } else if (x + y) % 3 == 0 {
+                Rgba([250, 248, 250, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_remove_background() {
+        let options = BackgroundOptions {
+            feather: 0,
+            ..Default::default()
+        };
+        let removed = remove_background(product(), &options);
+        assert_eq!(removed.get_pixel(0, 0)[3], 0);
+        assert_eq!(removed.get_pixel(3, 17)[3], 0);
+        assert_eq!(removed.get_pixel(5, 5).0, [200, 30, 30, 255]);
+        // 背景とつながっていない白は残る
+        assert_eq!(removed.get_pixel(10, 10).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_feather() {
+        let options = BackgroundOptions {
+            feather: 1,
+            ..Default::default()
+        };
+        let removed = remove_background(product(), &options);
+        assert_eq!(removed.get_pixel(5, 5)[3], 128);
+        assert_eq!(removed.get_pixel(6, 6)[3], 255);
+    }
+
+    #[test]
+    fn test_uniform_image_is_unchanged() {
+        let plain = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+        assert_eq!(
+            remove_background(plain.clone(), &BackgroundOptions::default()),
+            plain
+        );
+    }
+}
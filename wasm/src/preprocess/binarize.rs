@@ -0,0 +1,137 @@
+use image::{imageops, GrayImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 二値化の設定（Sauvolaの方法）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinarizeOptions {
+    /// しきい値を求める窓の一辺（ピクセル、文字の大きさの2〜3倍程度）
+    pub window: u32,
+    /// 標準偏差の効き具合（大きいほど薄い線が白になる）
+    pub k: f64,
+    /// 標準偏差の最大値の目安
+    pub r: f64,
+}
+
+impl Default for BinarizeOptions {
+    fn default() -> Self {
+        Self {
+            window: 31,
+            k: 0.2,
+            r: 128.0,
+        }
+    }
+}
+
+/// 白黒に二値化する
+///
+/// 窓の中の平均 `m` と標準偏差 `s` から画素ごとのしきい値 `m * (1 + k * (s / r - 1))` を求める。
+/// 紙の色むらや影があっても文字だけが黒く残る。アルファは変えない。
+pub fn binarize(image: RgbaImage, options: &BinarizeOptions) -> RgbaImage {
+    let gray = imageops::grayscale(&image);
+    let (width, height) = gray.dimensions();
+    let integral = Integral::new(&gray);
+    let half = (options.window.max(3) / 2) as i64;
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let left = (x as i64 - half).max(0) as usize;
+        let top = (y as i64 - half).max(0) as usize;
+        let right = (x as i64 + half + 1).min(width as i64) as usize;
+        let bottom = (y as i64 + half + 1).min(height as i64) as usize;
+        let (mean, deviation) = integral.mean_deviation(left, top, right, bottom);
+
+        let threshold = mean * (1.0 + options.k * (deviation / options.r - 1.0));
+        let value = if gray.get_pixel(x, y)[0] as f64 > threshold {
+            255
+        } else {
+            0
+        };
+        Rgba([value, value, value, image.get_pixel(x, y)[3]])
+    })
+}
+
+/// 画素値と、その2乗の積分画像（窓の平均と分散を定数時間で求める）
+struct Integral {
+    stride: usize,
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+}
+
+impl Integral {
+    fn new(gray: &GrayImage) -> Self {
+        let (width, height) = (gray.width() as usize, gray.height() as usize);
+        let stride = width + 1;
+        let mut sums = vec![0.0; stride * (height + 1)];
+        let mut squares = vec![0.0; stride * (height + 1)];
+
+        for y in 0..height {
+            let (mut row_sum, mut row_square) = (0.0, 0.0);
+            for x in 0..width {
+                let value = gray.get_pixel(x as u32, y as u32)[0] as f64;
+                row_sum += value;
+                row_square += value * value;
+                let index = (y + 1) * stride + x + 1;
+                sums[index] = sums[index - stride] + row_sum;
+                squares[index] = squares[index - stride] + row_square;
+            }
+        }
+
+        Self {
+            stride,
+            sums,
+            squares,
+        }
+    }
+
+    /// `[left, right) × [top, bottom)` の平均と標準偏差
+    fn mean_deviation(&self, left: usize, top: usize, right: usize, bottom: usize) -> (f64, f64) {
+        let area = |table: &[f64]| {
+            table[bottom * self.stride + right]
+                - table[top * self.stride + right]
+                - table[bottom * self.stride + left]
+                + table[top * self.stride + left]
+        };
+        let count = ((right - left) * (bottom - top)) as f64;
+        let mean = area(&self.sums) / count;
+        let variance = (area(&self.squares) / count - mean * mean).max(0.0);
+        (mean, variance.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binarize() {
+        // 左から右に暗くなる紙（影）に、濃い文字の線
+        let mut image = RgbaImage::from_fn(120, 60, |x, _| {
+            let value = 240 - (x as u8 / 2);
+            Rgba([value, value, value - 20, 255])
+        });
+        for x in 10..110 {
+            for y in 28..32 {
+                let value = image.get_pixel(x, y)[0] - 120;
+                image.put_pixel(x, y, Rgba([value, value, value, 255]));
+            }
+        }
+
+        let binary = binarize(image, &BinarizeOptions::default());
+        assert!(binary
+            .pixels()
+            .all(|pixel| matches!(pixel.0, [0, 0, 0, 255] | [255, 255, 255, 255])));
+        // 影の中でも文字は黒、紙は白
+        for x in [15, 60, 105] {
+            assert_eq!(binary.get_pixel(x, 30)[0], 0);
+            assert_eq!(binary.get_pixel(x, 10)[0], 255);
+            assert_eq!(binary.get_pixel(x, 50)[0], 255);
+        }
+    }
+
+    #[test]
+    fn test_binarize_keeps_alpha() {
+        let image = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 0]));
+        let binary = binarize(image, &BinarizeOptions::default());
+        assert_eq!(binary.get_pixel(1, 1).0, [255, 255, 255, 0]);
+    }
+}
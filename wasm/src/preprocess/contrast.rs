@@ -0,0 +1,107 @@
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// 明るさの範囲がこれより狭い画像は引き伸ばさない（白紙のページのむらを強調しないため）
+const MIN_RANGE: u8 = 32;
+
+/// 自動コントラストの設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoContrastOptions {
+    /// 暗い側・明るい側それぞれで切り捨てる画素の割合（%）
+    pub clip: f64,
+}
+
+impl Default for AutoContrastOptions {
+    fn default() -> Self {
+        Self { clip: 0.5 }
+    }
+}
+
+/// 明るさの範囲を引き伸ばす（退色した古いパンフレットのコントラストを戻す）
+///
+/// 輝度のヒストグラムの両端 `clip` %を除いた範囲が0-255になるように、RGBに同じ変換をかける
+/// （色相は変えない）。完全に透明な画素は数えない。アルファは変えない。
+pub fn auto_contrast(mut image: RgbaImage, options: &AutoContrastOptions) -> RgbaImage {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels().filter(|pixel| pixel[3] > 0) {
+        let luma =
+            (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114 + 500) / 1000;
+        histogram[luma as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return image;
+    }
+
+    let clip = if options.clip.is_finite() {
+        options.clip.clamp(0.0, 49.0) / 100.0
+    } else {
+        0.0
+    };
+    let clipped = (total as f64 * clip).floor() as u64;
+    let low = percentile_from(histogram.iter().enumerate(), clipped);
+    let high = percentile_from(histogram.iter().enumerate().rev(), clipped);
+    if high <= low || high - low < MIN_RANGE {
+        return image;
+    }
+
+    let scale = 255.0 / (high - low) as f64;
+    let mut table = [0u8; 256];
+    for (value, out) in table.iter_mut().enumerate() {
+        *out = ((value as f64 - low as f64) * scale)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    for pixel in image.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = table[pixel[channel] as usize];
+        }
+    }
+    image
+}
+
+/// ヒストグラムを端から数え、`clipped` 個を超えたところの値
+fn percentile_from<'a>(buckets: impl Iterator<Item = (usize, &'a u64)>, clipped: u64) -> u8 {
+    let mut count = 0;
+    for (value, bucket) in buckets {
+        count += bucket;
+        if count > clipped {
+            return value as u8;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_auto_contrast() {
+        // 60-200の範囲に収まった退色したページと、1画素だけの外れ値
+        let mut image = RgbaImage::from_fn(20, 10, |x, _| {
+            let value = 60 + (x * 140 / 19) as u8;
+            Rgba([value, value, value, 255])
+        });
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+
+        let stretched = auto_contrast(image, &AutoContrastOptions::default());
+        assert_eq!(stretched.get_pixel(0, 5).0, [0, 0, 0, 255]);
+        assert_eq!(stretched.get_pixel(19, 5).0, [255, 255, 255, 255]);
+        assert_eq!(stretched.get_pixel(10, 5)[0], 133);
+    }
+
+    #[test]
+    fn test_narrow_range_is_unchanged() {
+        let page = RgbaImage::from_fn(10, 10, |x, _| {
+            let value = 230 + x as u8;
+            Rgba([value, value, value, 255])
+        });
+        assert_eq!(
+            auto_contrast(page.clone(), &AutoContrastOptions::default()),
+            page
+        );
+    }
+}
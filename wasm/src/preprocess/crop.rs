@@ -0,0 +1,257 @@
+use std::ops::Range;
+
+use image::{imageops, GrayImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 行・列の画素のうちこの割合以上が暗い場合、スキャナーの黒い縁とみなす
+const BORDER_RATIO: f64 = 0.5;
+
+/// 行・列の画素のうちこの割合（かつ `MIN_CONTENT_PIXELS`）を超えて余白より暗い画素がある場合、
+/// 内容があるとみなす（ごみや汚れの点を無視するため）
+const CONTENT_RATIO: f64 = 0.005;
+const MIN_CONTENT_PIXELS: f64 = 2.0;
+
+/// 切り抜いた範囲（切り抜く前の画像のピクセル座標）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 自動切り抜きの設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoCropOptions {
+    /// スキャナーの黒い縁とみなす明るさの上限（0-255）
+    pub black_threshold: u8,
+    /// 余白（紙の白）とみなす明るさの下限（0-255）
+    pub white_threshold: u8,
+    /// 検出した内容の周りに残す余白（ピクセル）
+    pub padding: u32,
+}
+
+impl Default for AutoCropOptions {
+    fn default() -> Self {
+        Self {
+            black_threshold: 60,
+            white_threshold: 235,
+            padding: 16,
+        }
+    }
+}
+
+/// スキャナーの黒い縁と余分な白い余白を切り抜く
+///
+/// # Returns
+/// 切り抜いた画像と、切り抜いた範囲（切り抜かなかった場合は `None`）
+pub fn auto_crop(image: RgbaImage, options: &AutoCropOptions) -> (RgbaImage, Option<CropRect>) {
+    match detect_crop(&image, options) {
+        Some(rect) => {
+            let cropped =
+                imageops::crop_imm(&image, rect.x, rect.y, rect.width, rect.height).to_image();
+            (cropped, Some(rect))
+        }
+        None => (image, None),
+    }
+}
+
+/// 切り抜く範囲を検出する（画像全体の場合は `None`）
+///
+/// 1. 外側から、大半が暗い行・列（スキャナーの黒い縁）を取り除く
+/// 2. 残りの範囲で余白より暗い画素を含む行・列を内容とし、その外接矩形に `padding` を加える
+///
+/// 白紙のページは黒い縁だけを取り除く。全体が暗いページは切り抜かない。
+pub fn detect_crop(image: &RgbaImage, options: &AutoCropOptions) -> Option<CropRect> {
+    let gray = imageops::grayscale(image);
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let (mut left, mut top, mut right, mut bottom) = (0, 0, width, height);
+    let is_dark = |value: u8| value <= options.black_threshold;
+    let is_border = |count: usize, len: u32| count as f64 >= len as f64 * BORDER_RATIO;
+    loop {
+        let before = (left, top, right, bottom);
+        while top < bottom && is_border(row_count(&gray, top, left..right, is_dark), right - left) {
+            top += 1;
+        }
+        while bottom > top
+            && is_border(
+                row_count(&gray, bottom - 1, left..right, is_dark),
+                right - left,
+            )
+        {
+            bottom -= 1;
+        }
+        while left < right
+            && is_border(
+                column_count(&gray, left, top..bottom, is_dark),
+                bottom - top,
+            )
+        {
+            left += 1;
+        }
+        while right > left
+            && is_border(
+                column_count(&gray, right - 1, top..bottom, is_dark),
+                bottom - top,
+            )
+        {
+            right -= 1;
+        }
+        if (left, top, right, bottom) == before {
+            break;
+        }
+    }
+    if left >= right || top >= bottom {
+        return None;
+    }
+
+    let has_content = |value: u8| value < options.white_threshold;
+    let is_content = |count: usize, len: u32| {
+        count as f64 > (len as f64 * CONTENT_RATIO).max(MIN_CONTENT_PIXELS)
+    };
+    let content_rows: Vec<u32> = (top..bottom)
+        .filter(|&y| is_content(row_count(&gray, y, left..right, has_content), right - left))
+        .collect();
+    let content_columns: Vec<u32> = (left..right)
+        .filter(|&x| {
+            is_content(
+                column_count(&gray, x, top..bottom, has_content),
+                bottom - top,
+            )
+        })
+        .collect();
+
+    if let (Some(first_row), Some(last_row), Some(first_column), Some(last_column)) = (
+        content_rows.first(),
+        content_rows.last(),
+        content_columns.first(),
+        content_columns.last(),
+    ) {
+        let padding = options.padding;
+        left = first_column.saturating_sub(padding).max(left);
+        top = first_row.saturating_sub(padding).max(top);
+        right = (last_column + 1).saturating_add(padding).min(right);
+        bottom = (last_row + 1).saturating_add(padding).min(bottom);
+    }
+
+    let rect = CropRect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    };
+    (rect.width != width || rect.height != height).then_some(rect)
+}
+
+/// `y` 行目のうち `columns` の範囲で `predicate` を満たす画素の数
+fn row_count(
+    gray: &GrayImage,
+    y: u32,
+    columns: Range<u32>,
+    predicate: impl Fn(u8) -> bool,
+) -> usize {
+    columns
+        .filter(|&x| predicate(gray.get_pixel(x, y)[0]))
+        .count()
+}
+
+/// `x` 列目のうち `rows` の範囲で `predicate` を満たす画素の数
+fn column_count(
+    gray: &GrayImage,
+    x: u32,
+    rows: Range<u32>,
+    predicate: impl Fn(u8) -> bool,
+) -> usize {
+    rows.filter(|&y| predicate(gray.get_pixel(x, y)[0])).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    /// 左に10px・上に5pxの黒い縁がある白い200x100のスキャン
+    fn scan() -> RgbaImage {
+        RgbaImage::from_fn(200, 100, |x, y| if x < 10 || y < 5 { BLACK } else { WHITE })
+    }
+
+    fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+        for py in y..y + height {
+            for px in x..x + width {
+                image.put_pixel(px, py, color);
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_crop() {
+        let options = AutoCropOptions {
+            padding: 4,
+            ..Default::default()
+        };
+
+        // 内容の外接矩形に余白を加える
+        let mut image = scan();
+        fill(&mut image, 60, 40, 20, 20, Rgba([90, 90, 90, 255]));
+        fill(&mut image, 150, 30, 10, 4, BLACK);
+        // 内容とみなさない小さな汚れ
+        image.put_pixel(190, 90, BLACK);
+        assert_eq!(
+            detect_crop(&image, &options),
+            Some(CropRect {
+                x: 56,
+                y: 26,
+                width: 108,
+                height: 38,
+            })
+        );
+
+        // 余白は黒い縁の内側に収める
+        let mut image = scan();
+        fill(&mut image, 11, 6, 5, 5, BLACK);
+        let rect = detect_crop(&image, &options).unwrap();
+        assert_eq!((rect.x, rect.y), (10, 5));
+
+        // 白紙のページは黒い縁だけを取り除く
+        assert_eq!(
+            detect_crop(&scan(), &options),
+            Some(CropRect {
+                x: 10,
+                y: 5,
+                width: 190,
+                height: 95,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nothing_to_crop() {
+        let options = AutoCropOptions::default();
+
+        // 全体が暗いページ
+        let dark = RgbaImage::from_pixel(50, 50, BLACK);
+        assert_eq!(detect_crop(&dark, &options), None);
+
+        // 端まで内容がある
+        let mut full = RgbaImage::from_pixel(50, 50, WHITE);
+        fill(&mut full, 0, 0, 50, 1, Rgba([128, 128, 128, 255]));
+        fill(&mut full, 0, 49, 50, 1, Rgba([128, 128, 128, 255]));
+        fill(&mut full, 0, 0, 1, 50, Rgba([128, 128, 128, 255]));
+        fill(&mut full, 49, 0, 1, 50, Rgba([128, 128, 128, 255]));
+        let (image, rect) = auto_crop(full.clone(), &options);
+        assert_eq!(rect, None);
+        assert_eq!(image, full);
+
+        let (image, rect) = auto_crop(scan(), &options);
+        assert_eq!(image.dimensions(), (190, 95));
+        assert_eq!(rect.map(|rect| (rect.x, rect.y)), Some((10, 5)));
+    }
+}
@@ -0,0 +1,190 @@
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 窓の半径の上限（ピクセル）
+const MAX_RADIUS: u32 = 5;
+
+/// ノイズ除去の方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DenoiseMethod {
+    /// メディアンフィルタ（ごま塩ノイズ・ごみの点に強い）
+    #[default]
+    Median,
+    /// バイラテラルフィルタ（輪郭を保ったまま、ざらつきや圧縮ノイズをならす）
+    Bilateral,
+}
+
+/// ノイズ除去の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DenoiseOptions {
+    pub method: DenoiseMethod,
+    /// 窓の半径（ピクセル、1-5）
+    pub radius: u32,
+    /// バイラテラルフィルタで平均する色の差の目安（0-255、大きいほど強くならす）
+    pub sigma_color: f32,
+}
+
+impl Default for DenoiseOptions {
+    fn default() -> Self {
+        Self {
+            method: DenoiseMethod::Median,
+            radius: 1,
+            sigma_color: 30.0,
+        }
+    }
+}
+
+/// スキャンのノイズを除去する
+///
+/// 色の各チャンネルにフィルタをかける。アルファは変えない。
+pub fn denoise(image: RgbaImage, options: &DenoiseOptions) -> RgbaImage {
+    let radius = options.radius.min(MAX_RADIUS);
+    if radius == 0 {
+        return image;
+    }
+    match options.method {
+        DenoiseMethod::Median => median(&image, radius),
+        DenoiseMethod::Bilateral => bilateral(&image, radius, options.sigma_color),
+    }
+}
+
+/// 窓の中の画素（画像の外は端の画素で埋める）の位置
+fn window(
+    image: &RgbaImage,
+    x: u32,
+    y: u32,
+    radius: u32,
+) -> impl Iterator<Item = (i64, i64, u32, u32)> {
+    let (width, height) = image.dimensions();
+    let r = radius as i64;
+    (-r..=r).flat_map(move |dy| {
+        (-r..=r).map(move |dx| {
+            let sx = (x as i64 + dx).clamp(0, width as i64 - 1) as u32;
+            let sy = (y as i64 + dy).clamp(0, height as i64 - 1) as u32;
+            (dx, dy, sx, sy)
+        })
+    })
+}
+
+fn median(image: &RgbaImage, radius: u32) -> RgbaImage {
+    let side = (radius * 2 + 1) as usize;
+    let mut values = [
+        Vec::with_capacity(side * side),
+        Vec::with_capacity(side * side),
+        Vec::with_capacity(side * side),
+    ];
+
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        for channel in values.iter_mut() {
+            channel.clear();
+        }
+        for (_, _, sx, sy) in window(image, x, y, radius) {
+            let pixel = image.get_pixel(sx, sy);
+            for (channel, list) in values.iter_mut().enumerate() {
+                list.push(pixel[channel]);
+            }
+        }
+
+        let mut out = *image.get_pixel(x, y);
+        for (channel, list) in values.iter_mut().enumerate() {
+            let middle = list.len() / 2;
+            out[channel] = *list.select_nth_unstable(middle).1;
+        }
+        out
+    })
+}
+
+fn bilateral(image: &RgbaImage, radius: u32, sigma_color: f32) -> RgbaImage {
+    let sigma_color = if sigma_color.is_finite() {
+        sigma_color.max(1.0)
+    } else {
+        1.0
+    };
+    let sigma_space = (radius as f32 / 2.0).max(0.5);
+
+    // 色の差（RGBのユークリッド距離、最大442）ごとの重み
+    let color_weights: Vec<f32> = (0..=442)
+        .map(|distance| (-(distance as f32).powi(2) / (2.0 * sigma_color.powi(2))).exp())
+        .collect();
+    let space_weight =
+        |dx: i64, dy: i64| (-((dx * dx + dy * dy) as f32) / (2.0 * sigma_space.powi(2))).exp();
+
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let center = image.get_pixel(x, y);
+        let mut sums = [0.0f32; 3];
+        let mut total = 0.0f32;
+        for (dx, dy, sx, sy) in window(image, x, y, radius) {
+            let pixel = image.get_pixel(sx, sy);
+            let distance = (0..3)
+                .map(|channel| (pixel[channel] as f32 - center[channel] as f32).powi(2))
+                .sum::<f32>()
+                .sqrt()
+                .round() as usize;
+            let weight = space_weight(dx, dy) * color_weights[distance.min(442)];
+            for (channel, sum) in sums.iter_mut().enumerate() {
+                *sum += pixel[channel] as f32 * weight;
+            }
+            total += weight;
+        }
+
+        let value = |channel: usize| (sums[channel] / total).round().clamp(0.0, 255.0) as u8;
+        Rgba([value(0), value(1), value(2), center[3]])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Rgba<u8> = Rgba([128, 128, 128, 255]);
+
+    #[test]
+    fn test_median_removes_speckles() {
+        let mut image = RgbaImage::from_pixel(9, 9, GRAY);
+        image.put_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+
+        let denoised = denoise(image, &DenoiseOptions::default());
+        assert!(denoised.pixels().all(|pixel| *pixel == GRAY));
+    }
+
+    #[test]
+    fn test_bilateral_keeps_edges() {
+        // 左半分が黒、右半分が白で、白に少しざらつきがある
+        let image = RgbaImage::from_fn(10, 10, |x, y| {
+            if x < 5 {
+                Rgba([0, 0, 0, 255])
+            } else if (x + y) % 2 == 0 {
+                Rgba([240, 240, 240, 255])
+            } else {
+                Rgba([250, 250, 250, 255])
+            }
+        });
+        let options = DenoiseOptions {
+            method: DenoiseMethod::Bilateral,
+            radius: 2,
+            ..Default::default()
+        };
+
+        let denoised = denoise(image, &options);
+        // 輪郭はぼけない
+        assert_eq!(denoised.get_pixel(4, 5).0, [0, 0, 0, 255]);
+        assert!(denoised.get_pixel(5, 5)[0] >= 235);
+        // ざらつきはならされる
+        let (a, b) = (denoised.get_pixel(7, 5)[0], denoised.get_pixel(8, 5)[0]);
+        assert!(a.abs_diff(b) < 10);
+    }
+
+    #[test]
+    fn test_zero_radius() {
+        let mut image = RgbaImage::from_pixel(3, 3, GRAY);
+        image.put_pixel(1, 1, Rgba([0, 0, 0, 255]));
+        let options = DenoiseOptions {
+            radius: 0,
+            ..Default::default()
+        };
+        assert_eq!(denoise(image.clone(), &options), image);
+    }
+}
@@ -0,0 +1,87 @@
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::unsharp::{self, UnsharpOptions};
+
+/// 網点除去の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DescreenOptions {
+    /// 網点をならすガウスぼかしの半径（標準偏差、ピクセル）。網点の間隔の半分程度にする
+    pub radius: f32,
+    /// ぼかした後に輪郭を戻すアンシャープマスクの強さ（0で戻さない）
+    pub restore: f32,
+}
+
+impl Default for DescreenOptions {
+    fn default() -> Self {
+        Self {
+            radius: 1.5,
+            restore: 0.6,
+        }
+    }
+}
+
+/// 印刷物のスキャンの網点（モアレの原因）を取り除く
+///
+/// 網点の細かい周期をガウスぼかしで消し、網点より大きい半径のアンシャープマスクで
+/// 文字や輪郭のくっきりさを戻す（網点の周期は戻さない）。アルファは変えない。
+pub fn descreen(image: RgbaImage, options: &DescreenOptions) -> RgbaImage {
+    if !options.radius.is_finite() || options.radius <= 0.0 {
+        return image;
+    }
+
+    let mut blurred = imageops::blur(&image, options.radius);
+    for (pixel, original) in blurred.pixels_mut().zip(image.pixels()) {
+        pixel[3] = original[3];
+    }
+    unsharp::unsharp(
+        blurred,
+        &UnsharpOptions {
+            amount: options.restore,
+            radius: options.radius * 2.0,
+            threshold: 0,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 左半分が2ピクセル周期の網点（平均で灰色）、右半分が白
+    fn halftone() -> RgbaImage {
+        RgbaImage::from_fn(40, 20, |x, y| {
+            if x >= 20 || (x / 2 + y / 2) % 2 == 0 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_descreen() {
+        let descreened = descreen(halftone(), &DescreenOptions::default());
+
+        // 網点はならされて、ほぼ一様な灰色になる
+        let values: Vec<u8> = (4..14).map(|x| descreened.get_pixel(x, 10)[0]).collect();
+        let (min, max) = (values.iter().min().unwrap(), values.iter().max().unwrap());
+        assert!(max - min < 24, "{:?}", values);
+        assert!((100..156).contains(min));
+
+        // 白い部分は白のまま
+        assert!(descreened.get_pixel(35, 10)[0] >= 250);
+        assert_eq!(descreened.get_pixel(35, 10)[3], 255);
+    }
+
+    #[test]
+    fn test_zero_radius() {
+        let options = DescreenOptions {
+            radius: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(descreen(halftone(), &options), halftone());
+    }
+}
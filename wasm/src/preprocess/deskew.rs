@@ -0,0 +1,214 @@
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::{rotate, PAPER};
+
+/// 傾きの検出に使う縮小画像の長辺（ピクセル）
+const ANALYSIS_SIZE: u32 = 1024;
+
+/// 粗い探索と、その周りの細かい探索の刻み（度）
+const COARSE_STEP: f64 = 0.5;
+const FINE_STEP: f64 = 0.05;
+
+/// これより小さい傾きは補正しない（度）
+const MIN_ANGLE: f64 = 0.05;
+
+/// 傾きの検出に必要な暗い画素の数（白紙のページなどは補正しない）
+const MIN_DARK_PIXELS: usize = 100;
+
+/// 0度と比べて投影のスコアがこの倍率以上に良くならなければ補正しない（写真のページ対策）
+const MIN_IMPROVEMENT: f64 = 1.05;
+
+/// 傾き補正の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeskewOptions {
+    /// 検出する傾きの最大値（度、最大45）
+    pub max_angle: f64,
+}
+
+impl Default for DeskewOptions {
+    fn default() -> Self {
+        Self { max_angle: 5.0 }
+    }
+}
+
+/// スキャンの傾きを検出して水平にする
+///
+/// 回転で空いた角は紙の白で埋める。キャンバスの大きさは変えない。
+pub fn deskew(image: RgbaImage, options: &DeskewOptions) -> RgbaImage {
+    let angle = detect_skew(&image, options.max_angle);
+    if angle.abs() < MIN_ANGLE {
+        return image;
+    }
+    rotate::rotate_same_size(&image, -angle, PAPER)
+}
+
+/// 文字の行の傾き（度、時計回りが正）を投影プロファイルで検出する
+///
+/// 暗い画素を各角度で行方向に投影し、隣り合う行の差の2乗和が最大になる角度を選ぶ。
+/// 行が水平にそろうと、文字のある行と行間の差がはっきりするため値が大きくなる。
+pub fn detect_skew(image: &RgbaImage, max_angle: f64) -> f64 {
+    let max_angle = if max_angle.is_finite() {
+        max_angle.abs().min(45.0)
+    } else {
+        0.0
+    };
+    if max_angle < MIN_ANGLE {
+        return 0.0;
+    }
+
+    let points = dark_points(&analysis_image(image));
+    if points.len() < MIN_DARK_PIXELS {
+        return 0.0;
+    }
+
+    let score = |degrees: f64| projection_score(&points, degrees);
+    let best_between = |from: f64, to: f64, step: f64| {
+        let steps = ((to - from) / step).round() as i64;
+        (0..=steps)
+            .map(|i| from + i as f64 * step)
+            .map(|degrees| (degrees, score(degrees)))
+            .fold((0.0, f64::MIN), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+    };
+
+    let (coarse, _) = best_between(-max_angle, max_angle, COARSE_STEP.min(max_angle));
+    let (angle, best) = best_between(
+        (coarse - COARSE_STEP).max(-max_angle),
+        (coarse + COARSE_STEP).min(max_angle),
+        FINE_STEP,
+    );
+
+    if best < score(0.0) * MIN_IMPROVEMENT {
+        return 0.0;
+    }
+    angle
+}
+
+/// 傾きの検出用に縮小したグレースケール画像
+fn analysis_image(image: &RgbaImage) -> GrayImage {
+    let gray = imageops::grayscale(image);
+    let (width, height) = gray.dimensions();
+    let longest = width.max(height);
+    if longest <= ANALYSIS_SIZE {
+        return gray;
+    }
+
+    let scale = ANALYSIS_SIZE as f64 / longest as f64;
+    let scaled = |size: u32| ((size as f64 * scale).round() as u32).max(1);
+    imageops::resize(&gray, scaled(width), scaled(height), FilterType::Triangle)
+}
+
+/// 判別分析法（大津の方法）で求めたしきい値より暗い画素の、画像の中心からの位置
+fn dark_points(gray: &GrayImage) -> Vec<(f64, f64)> {
+    let threshold = otsu_threshold(gray);
+    let (cx, cy) = (gray.width() as f64 / 2.0, gray.height() as f64 / 2.0);
+    gray.enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel[0] <= threshold)
+        .map(|(x, y, _)| (x as f64 - cx, y as f64 - cy))
+        .collect()
+}
+
+/// 判別分析法（大津の方法）のしきい値（この値以下が暗い側）
+fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| value as f64 * *count as f64)
+        .sum();
+
+    let (mut dark_count, mut dark_sum) = (0u64, 0.0);
+    let (mut threshold, mut best) = (0u8, -1.0);
+    for (value, count) in histogram.iter().enumerate() {
+        dark_count += count;
+        dark_sum += value as f64 * *count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0 || light_count == 0 {
+            continue;
+        }
+
+        let dark_mean = dark_sum / dark_count as f64;
+        let light_mean = (sum - dark_sum) / light_count as f64;
+        let variance = dark_count as f64 * light_count as f64 * (dark_mean - light_mean).powi(2);
+        if variance > best {
+            best = variance;
+            threshold = value as u8;
+        }
+    }
+    threshold
+}
+
+/// 暗い画素を `degrees` だけ戻した向きで行に投影したときの、隣り合う行の差の2乗和
+fn projection_score(points: &[(f64, f64)], degrees: f64) -> f64 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let extent = points
+        .iter()
+        .map(|(x, y)| x.hypot(*y))
+        .fold(0.0, f64::max)
+        .ceil() as usize;
+
+    let mut rows = vec![0u32; extent * 2 + 2];
+    let last = rows.len() - 1;
+    for (x, y) in points {
+        let row = (y * cos - x * sin + extent as f64).round() as usize;
+        rows[row.min(last)] += 1;
+    }
+    rows.windows(2)
+        .map(|pair| (pair[1] as f64 - pair[0] as f64).powi(2))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 白地に `degrees` だけ傾いた黒い横線を並べた画像
+    fn skewed_lines(degrees: f64) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(400, 300, PAPER);
+        let slope = degrees.to_radians().tan();
+        for line in 0..10 {
+            let base = 50.0 + line as f64 * 20.0;
+            for x in 40..360 {
+                let y = (base + (x as f64 - 200.0) * slope).round() as u32;
+                for dy in 0..3 {
+                    image.put_pixel(x, y + dy, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_detect_skew() {
+        assert!((detect_skew(&skewed_lines(2.0), 5.0) - 2.0).abs() < 0.15);
+        assert!((detect_skew(&skewed_lines(-1.3), 5.0) + 1.3).abs() < 0.15);
+        assert!(detect_skew(&skewed_lines(0.0), 5.0).abs() < 0.1);
+
+        // 白紙や、探索しない設定では補正しない
+        assert_eq!(detect_skew(&RgbaImage::from_pixel(50, 50, PAPER), 5.0), 0.0);
+        assert_eq!(detect_skew(&skewed_lines(2.0), 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_deskew() {
+        let straightened = deskew(skewed_lines(2.5), &DeskewOptions::default());
+        assert_eq!(straightened.dimensions(), (400, 300));
+        assert!(detect_skew(&straightened, 5.0).abs() < 0.15);
+        // 空いた角は白
+        assert_eq!(straightened.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+}
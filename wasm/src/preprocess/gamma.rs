@@ -0,0 +1,51 @@
+use image::RgbaImage;
+
+/// ガンマ補正する
+///
+/// ImageMagickの `-gamma` と同じく `gamma` が1より大きいと明るく、小さいと暗くなる
+/// （出力 = 入力 ^ (1 / gamma)）。アルファは変えない。
+pub fn gamma(mut image: RgbaImage, gamma: f64) -> RgbaImage {
+    if !gamma.is_finite() || gamma <= 0.0 || gamma == 1.0 {
+        return image;
+    }
+
+    let mut table = [0u8; 256];
+    for (value, out) in table.iter_mut().enumerate() {
+        let corrected = (value as f64 / 255.0).powf(1.0 / gamma) * 255.0;
+        *out = corrected.round().clamp(0.0, 255.0) as u8;
+    }
+    for pixel in image.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = table[pixel[channel] as usize];
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_gamma() {
+        let image = RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([0, 0, 0, 255]),
+            1 => Rgba([64, 128, 192, 100]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+
+        let brighter = gamma(image.clone(), 2.0);
+        assert_eq!(brighter.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(brighter.get_pixel(1, 0).0, [128, 181, 221, 100]);
+        assert_eq!(brighter.get_pixel(2, 0).0, [255, 255, 255, 255]);
+
+        let darker = gamma(image.clone(), 0.5);
+        assert_eq!(darker.get_pixel(1, 0).0, [16, 64, 145, 100]);
+
+        // 1や不正な値では変えない
+        assert_eq!(gamma(image.clone(), 1.0), image);
+        assert_eq!(gamma(image.clone(), 0.0), image);
+        assert_eq!(gamma(image.clone(), f64::NAN), image);
+    }
+}
@@ -0,0 +1,242 @@
+mod background;
+mod binarize;
+mod contrast;
+mod crop;
+mod denoise;
+mod descreen;
+mod deskew;
+mod gamma;
+mod quantize;
+mod rotate;
+mod split;
+mod unsharp;
+mod upscale;
+mod white_balance;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+pub use background::BackgroundOptions;
+pub use binarize::BinarizeOptions;
+pub use contrast::AutoContrastOptions;
+pub use crop::{AutoCropOptions, CropRect};
+pub use denoise::DenoiseOptions;
+pub use descreen::DescreenOptions;
+pub use deskew::DeskewOptions;
+pub use quantize::QuantizeOptions;
+pub use rotate::RotateOptions;
+pub use split::SplitOptions;
+pub use unsharp::UnsharpOptions;
+pub use upscale::UpscaleOptions;
+pub use white_balance::WhiteBalanceOptions;
+
+/// 補正で空いた部分を埋める色（スキャンの紙の白）
+const PAPER: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// タイル化の前処理の設定
+///
+/// 指定された補正だけを、決まった順（回転 → 傾き補正 → 見開きの分割 → 自動切り抜き → 拡大 →
+/// ノイズ除去 → 網点除去 → ホワイトバランス → 自動コントラスト → ガンマ補正 →
+/// アンシャープマスク → 二値化 → 背景の除去 → 減色）に適用する
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreprocessOptions {
+    /// 回転（横向きの差し込みページや上下逆のスキャンを直す）
+    pub rotate: Option<RotateOptions>,
+    /// 傾き補正（スキャンのわずかな傾きを検出して水平にする）
+    pub deskew: Option<DeskewOptions>,
+    /// 見開きの分割（`preprocess_pages` でのみ使う。以降の補正はページごとに適用する）
+    pub split: Option<SplitOptions>,
+    /// 自動切り抜き（スキャナーの黒い縁と余分な白い余白を取り除く）
+    pub auto_crop: Option<AutoCropOptions>,
+    /// 拡大（解像度の低い画像を目標の解像度までLanczos法で拡大する）
+    pub upscale: Option<UpscaleOptions>,
+    /// ノイズ除去（スキャナーのノイズや圧縮ノイズをならし、タイルのサイズを抑える）
+    pub denoise: Option<DenoiseOptions>,
+    /// 網点除去（印刷物のスキャンのモアレを取り除き、WebPでの劣化を防ぐ）
+    pub descreen: Option<DescreenOptions>,
+    /// ホワイトバランス（スキャンの色かぶりを補正し、ページ間の色をそろえる）
+    pub white_balance: Option<WhiteBalanceOptions>,
+    /// 自動コントラスト（退色したページの明るさの範囲を引き伸ばす）
+    pub auto_contrast: Option<AutoContrastOptions>,
+    /// ガンマ補正（1より大きいと明るく、小さいと暗くする）
+    pub gamma: Option<f64>,
+    /// アンシャープマスク（ぼやけたスキャン全体をくっきりさせる）
+    pub unsharp: Option<UnsharpOptions>,
+    /// 二値化（文字だけのページをくっきりした白黒にし、タイルのサイズを大きく減らす）
+    pub binarize: Option<BinarizeOptions>,
+    /// 背景の除去（商品写真の四隅からつながる背景を透明にする）
+    pub remove_background: Option<BackgroundOptions>,
+    /// 減色（平坦な色のインフォグラフィックを少ない色に減らし、タイルを小さく正確にする）
+    pub quantize: Option<QuantizeOptions>,
+}
+
+/// 前処理の結果
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+    pub image: DynamicImage,
+    pub geometry: PageGeometry,
+}
+
+/// 前処理による元の画像との位置関係
+///
+/// 前処理後のページの座標 `(x, y)` は、回転・傾き補正の後の画像の
+/// `(crop.x + x / scale, crop.y + y / scale)` にあたる
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PageGeometry {
+    /// 自動切り抜き・見開きの分割で切り抜いた範囲
+    pub crop: Option<CropRect>,
+    /// 拡大の倍率
+    pub scale: Option<f64>,
+    /// 拡大後の解像度
+    pub dpi: Option<f64>,
+}
+
+/// 前処理を適用する
+///
+/// 何も指定されていない場合は、画素を変換せずにそのまま返す。`split` は使わない。
+pub fn preprocess(image: DynamicImage, options: &PreprocessOptions) -> Preprocessed {
+    if *options == PreprocessOptions::default() {
+        return Preprocessed {
+            image,
+            geometry: PageGeometry::default(),
+        };
+    }
+
+    let rgba = orient(image.into_rgba8(), options);
+    correct(rgba, options)
+}
+
+/// 見開きの分割を含めて前処理を適用する
+///
+/// `split` が指定され見開きと判定された場合は、左 → 右の2ページを返す。
+/// 分割したページの `crop` には、分割と自動切り抜きを合わせた範囲が入る。
+pub fn preprocess_pages(image: DynamicImage, options: &PreprocessOptions) -> Vec<Preprocessed> {
+    let Some(split) = &options.split else {
+        return vec![preprocess(image, options)];
+    };
+
+    let rgba = orient(image.into_rgba8(), options);
+    let pages = split::split_spread(rgba, split);
+    let is_spread = pages.len() > 1;
+
+    pages
+        .into_iter()
+        .map(|(page, half)| {
+            let mut page = correct(page, options);
+            page.geometry.crop = match page.geometry.crop {
+                Some(rect) => Some(CropRect {
+                    x: half.x + rect.x,
+                    y: half.y + rect.y,
+                    ..rect
+                }),
+                None => is_spread.then_some(half),
+            };
+            page
+        })
+        .collect()
+}
+
+/// 画像の向きを直す（回転と傾き補正）
+fn orient(mut rgba: RgbaImage, options: &PreprocessOptions) -> RgbaImage {
+    if let Some(rotate) = &options.rotate {
+        rgba = rotate::rotate(rgba, rotate);
+    }
+    if let Some(deskew) = &options.deskew {
+        rgba = deskew::deskew(rgba, deskew);
+    }
+    rgba
+}
+
+/// 向きの補正と見開きの分割より後の補正を適用する
+fn correct(mut rgba: RgbaImage, options: &PreprocessOptions) -> Preprocessed {
+    let mut geometry = PageGeometry::default();
+    if let Some(auto_crop) = &options.auto_crop {
+        (rgba, geometry.crop) = crop::auto_crop(rgba, auto_crop);
+    }
+    if let Some(upscale) = &options.upscale {
+        (rgba, geometry.scale) = upscale::upscale(rgba, upscale);
+        geometry.dpi = geometry
+            .scale
+            .map(|scale| upscale.effective_source_dpi() * scale);
+    }
+    if let Some(denoise) = &options.denoise {
+        rgba = denoise::denoise(rgba, denoise);
+    }
+    if let Some(descreen) = &options.descreen {
+        rgba = descreen::descreen(rgba, descreen);
+    }
+    if let Some(white_balance) = &options.white_balance {
+        rgba = white_balance::white_balance(rgba, white_balance);
+    }
+    if let Some(auto_contrast) = &options.auto_contrast {
+        rgba = contrast::auto_contrast(rgba, auto_contrast);
+    }
+    if let Some(value) = options.gamma {
+        rgba = gamma::gamma(rgba, value);
+    }
+    if let Some(unsharp) = &options.unsharp {
+        rgba = unsharp::unsharp(rgba, unsharp);
+    }
+    if let Some(binarize) = &options.binarize {
+        rgba = binarize::binarize(rgba, binarize);
+    }
+    if let Some(remove_background) = &options.remove_background {
+        rgba = background::remove_background(rgba, remove_background);
+    }
+    if let Some(quantize) = &options.quantize {
+        rgba = quantize::quantize(rgba, quantize);
+    }
+    Preprocessed {
+        image: DynamicImage::ImageRgba8(rgba),
+        geometry,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preprocess_pages() {
+        // 左右のページに黒い四角があり、中央に白い隙間がある見開き
+        let spread = RgbaImage::from_fn(400, 200, |x, y| {
+            let in_box = (40..80).contains(&x) || (300..340).contains(&x);
+            if (198..202).contains(&x) {
+                PAPER
+            } else if in_box && (50..100).contains(&y) {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([220, 220, 220, 255])
+            }
+        });
+        let options = PreprocessOptions {
+            split: Some(SplitOptions::default()),
+            auto_crop: Some(AutoCropOptions {
+                white_threshold: 200,
+                padding: 0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let pages = preprocess_pages(DynamicImage::ImageRgba8(spread.clone()), &options);
+        assert_eq!(pages.len(), 2);
+        let crops: Vec<_> = pages
+            .iter()
+            .map(|page| page.geometry.crop.unwrap())
+            .collect();
+        assert_eq!((crops[0].x, crops[0].y, crops[0].width), (40, 50, 40));
+        assert_eq!((crops[1].x, crops[1].y, crops[1].width), (300, 50, 40));
+
+        // 分割だけなら、それぞれのページの範囲
+        let options = PreprocessOptions {
+            split: Some(SplitOptions::default()),
+            ..Default::default()
+        };
+        let pages = preprocess_pages(DynamicImage::ImageRgba8(spread), &options);
+        let widths: Vec<_> = pages.iter().map(|page| page.image.width()).collect();
+        assert_eq!(widths.iter().sum::<u32>(), 400);
+        assert_eq!(pages[1].geometry.crop.unwrap().x, widths[0]);
+    }
+}
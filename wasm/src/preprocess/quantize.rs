@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::colors;
+
+/// パレットを作るのに使う画素の数の上限（大きなページは間引いて数える）
+const MAX_SAMPLES: usize = 1 << 18;
+
+/// 減色の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuantizeOptions {
+    /// パレットの色数（2-256）
+    pub colors: usize,
+    /// 誤差拡散（Floyd-Steinberg法）でグラデーションの段差を目立たなくする
+    pub dither: bool,
+}
+
+impl Default for QuantizeOptions {
+    fn default() -> Self {
+        Self {
+            colors: 256,
+            dither: false,
+        }
+    }
+}
+
+/// 画像をパレットの色だけに減色する（平坦な色のインフォグラフィック向け）
+///
+/// パレットは完全に透明でない画素からメディアンカットで作る。色の種類が少なく
+/// 可逆で圧縮しやすくなるため、ロスのあるエンコードより小さく、色も正確なタイルになる。
+/// 完全に透明な画素とアルファは変えない。
+pub fn quantize(mut image: RgbaImage, options: &QuantizeOptions) -> RgbaImage {
+    let palette = build_palette(&image, options.colors.clamp(2, 256));
+    if palette.is_empty() {
+        return image;
+    }
+
+    let mut nearest = NearestColor::new(palette);
+    if !options.dither {
+        for pixel in image.pixels_mut().filter(|pixel| pixel[3] > 0) {
+            let color = nearest.find([pixel[0], pixel[1], pixel[2]]);
+            pixel.0[..3].copy_from_slice(&color);
+        }
+        return image;
+    }
+
+    // 現在の行と次の行に拡散する誤差
+    let width = image.width() as usize;
+    let mut current = vec![[0f32; 3]; width + 2];
+    let mut next = vec![[0f32; 3]; width + 2];
+    for y in 0..image.height() {
+        for x in 0..width {
+            let pixel = image.get_pixel_mut(x as u32, y);
+            if pixel[3] == 0 {
+                continue;
+            }
+            let error = current[x + 1];
+            let wanted: [f32; 3] = std::array::from_fn(|c| pixel[c] as f32 + error[c]);
+            let color = nearest.find(wanted.map(|value| value.round().clamp(0.0, 255.0) as u8));
+            pixel.0[..3].copy_from_slice(&color);
+
+            for c in 0..3 {
+                let diff = wanted[c] - color[c] as f32;
+                current[x + 2][c] += diff * 7.0 / 16.0;
+                next[x][c] += diff * 3.0 / 16.0;
+                next[x + 1][c] += diff * 5.0 / 16.0;
+                next[x + 2][c] += diff / 16.0;
+            }
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.fill([0.0; 3]);
+    }
+    image
+}
+
+/// 完全に透明でない画素（大きな画像は間引いたもの）からパレットを作る
+fn build_palette(image: &RgbaImage, size: usize) -> Vec<[u8; 3]> {
+    let step = (image.pixels().len() / MAX_SAMPLES).max(1);
+    let pixels: Vec<[u8; 3]> = image
+        .pixels()
+        .step_by(step)
+        .filter(|pixel| pixel[3] > 0)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    colors::median_cut(pixels, size)
+        .into_iter()
+        .map(|(color, _)| color)
+        .collect()
+}
+
+/// パレットで最も近い色を探す（平坦な画像では同じ色が続くので結果を覚えておく）
+struct NearestColor {
+    palette: Vec<[u8; 3]>,
+    cache: HashMap<[u8; 3], [u8; 3]>,
+}
+
+impl NearestColor {
+    fn new(palette: Vec<[u8; 3]>) -> Self {
+        Self {
+            palette,
+            cache: HashMap::new(),
+        }
+    }
+
+    fn find(&mut self, color: [u8; 3]) -> [u8; 3] {
+        let palette = &self.palette;
+        *self.cache.entry(color).or_insert_with(|| {
+            let distance = |entry: &&[u8; 3]| -> u32 {
+                (0..3)
+                    .map(|c| (entry[c] as i32 - color[c] as i32).pow(2) as u32)
+                    .sum()
+            };
+            *palette.iter().min_by_key(distance).unwrap_or(&color)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 3色の図形に、アンチエイリアスの中間色が少し混ざったインフォグラフィック
+    fn infographic() -> RgbaImage {
+        RgbaImage::from_fn(60, 20, |x, _| match x {
+            0..=19 => Rgba([230, 60, 40, 255]),
+            20 => Rgba([130, 100, 90, 255]),
+            21..=39 => Rgba([40, 120, 200, 255]),
+            _ => Rgba([250, 250, 250, 255]),
+        })
+    }
+
+    #[test]
+    fn test_quantize() {
+        let options = QuantizeOptions {
+            colors: 3,
+            dither: false,
+        };
+        let quantized = quantize(infographic(), &options);
+
+        let mut distinct: Vec<[u8; 4]> = quantized.pixels().map(|pixel| pixel.0).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 3);
+
+        // 平坦な色はほぼそのまま残り、中間色はどれかにまとめられる
+        let close = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).all(|(a, b)| a.abs_diff(b) <= 10);
+        assert!(close(quantized.get_pixel(5, 5).0, [230, 60, 40, 255]));
+        assert!(close(quantized.get_pixel(30, 5).0, [40, 120, 200, 255]));
+        assert!(close(quantized.get_pixel(50, 5).0, [250, 250, 250, 255]));
+
+        // 色数が足りていれば何も変わらない
+        let quantized = quantize(infographic(), &QuantizeOptions::default());
+        assert_eq!(quantized, infographic());
+    }
+
+    #[test]
+    fn test_dither() {
+        // 黒から白へのグラデーションを2色にすると、誤差拡散で平均の明るさが保たれる
+        let gradient = RgbaImage::from_fn(64, 16, |x, _| {
+            let value = (x * 255 / 63) as u8;
+            Rgba([value, value, value, 255])
+        });
+        let options = QuantizeOptions {
+            colors: 2,
+            dither: true,
+        };
+        let dithered = quantize(gradient, &options);
+
+        // 2色（暗い灰色と明るい灰色）が混ざって中間の明るさを表す
+        let mean = dithered.pixels().map(|pixel| pixel[0] as f64).sum::<f64>() / (64.0 * 16.0);
+        assert!((mean - 127.5).abs() < 4.0, "{}", mean);
+        let mut middle: Vec<u8> = (0..16).map(|y| dithered.get_pixel(32, y)[0]).collect();
+        middle.sort();
+        middle.dedup();
+        assert_eq!(middle.len(), 2);
+    }
+
+    #[test]
+    fn test_transparent_pixels() {
+        let mut image = infographic();
+        image.put_pixel(0, 0, Rgba([1, 2, 3, 0]));
+        let quantized = quantize(image, &QuantizeOptions::default());
+        assert_eq!(quantized.get_pixel(0, 0).0, [1, 2, 3, 0]);
+
+        let transparent = RgbaImage::new(4, 4);
+        assert_eq!(
+            quantize(transparent.clone(), &QuantizeOptions::default()),
+            transparent
+        );
+    }
+}
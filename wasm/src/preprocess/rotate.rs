@@ -0,0 +1,186 @@
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 回転の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RotateOptions {
+    /// 回転する角度（度、時計回り）
+    pub degrees: f64,
+    /// 回転で空いた部分を埋める色（RGBA）
+    pub background: [u8; 4],
+}
+
+impl Default for RotateOptions {
+    fn default() -> Self {
+        Self {
+            degrees: 0.0,
+            background: [255, 255, 255, 255],
+        }
+    }
+}
+
+/// 画像を回転する
+///
+/// 90度単位の回転は画素をそのまま並べ替える（劣化しない）。それ以外の角度では、
+/// 回転した画像全体が収まるようにキャンバスを広げ、空いた部分を `background` で埋める。
+pub fn rotate(image: RgbaImage, options: &RotateOptions) -> RgbaImage {
+    if !options.degrees.is_finite() {
+        return image;
+    }
+    let degrees = options.degrees.rem_euclid(360.0);
+    let quarter = degrees / 90.0;
+    if (quarter - quarter.round()).abs() < 1e-9 {
+        return match quarter.round() as u32 % 4 {
+            1 => imageops::rotate90(&image),
+            2 => imageops::rotate180(&image),
+            3 => imageops::rotate270(&image),
+            _ => image,
+        };
+    }
+
+    let (width, height) = image.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let fit = |a: u32, b: u32| {
+        let size = a as f64 * cos.abs() + b as f64 * sin.abs();
+        // 浮動小数点の誤差で1ピクセル広がらないようにする
+        ((size - 1e-6).ceil() as u32).max(1)
+    };
+    rotate_into(
+        &image,
+        degrees,
+        Rgba(options.background),
+        fit(width, height),
+        fit(height, width),
+    )
+}
+
+/// 画像の中心を軸に回転する（キャンバスの大きさは変えない）
+///
+/// `degrees` は時計回り（画像の座標はy軸が下向き）。はみ出した部分は切り捨て、
+/// 空いた部分は `fill` で埋める。画素はバイリニア補間で求める。
+pub fn rotate_same_size(image: &RgbaImage, degrees: f64, fill: Rgba<u8>) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    rotate_into(image, degrees, fill, width, height)
+}
+
+/// 画像の中心を軸に回転し、中心をそろえて `width` x `height` のキャンバスに描く
+fn rotate_into(
+    image: &RgbaImage,
+    degrees: f64,
+    fill: Rgba<u8>,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (image.width() as f64 / 2.0, image.height() as f64 / 2.0);
+    let (ox, oy) = (width as f64 / 2.0, height as f64 / 2.0);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        // 出力の画素の中心を逆向きに回転して、元画像の位置を求める
+        let (dx, dy) = (x as f64 + 0.5 - ox, y as f64 + 0.5 - oy);
+        let sx = cx + dx * cos + dy * sin;
+        let sy = cy - dx * sin + dy * cos;
+        sample_bilinear(image, sx - 0.5, sy - 0.5, fill)
+    })
+}
+
+/// 画素の位置（整数が画素の中心）をバイリニア補間で読む（範囲外は `fill`）
+fn sample_bilinear(image: &RgbaImage, x: f64, y: f64, fill: Rgba<u8>) -> Rgba<u8> {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let pixel = |px: f64, py: f64| {
+        if px < 0.0 || py < 0.0 || px >= image.width() as f64 || py >= image.height() as f64 {
+            fill
+        } else {
+            *image.get_pixel(px as u32, py as u32)
+        }
+    };
+
+    let neighbours = [
+        (pixel(x0, y0), (1.0 - fx) * (1.0 - fy)),
+        (pixel(x0 + 1.0, y0), fx * (1.0 - fy)),
+        (pixel(x0, y0 + 1.0), (1.0 - fx) * fy),
+        (pixel(x0 + 1.0, y0 + 1.0), fx * fy),
+    ];
+    let mut out = [0u8; 4];
+    for (channel, value) in out.iter_mut().enumerate() {
+        let mixed: f64 = neighbours
+            .iter()
+            .map(|(pixel, weight)| pixel[channel] as f64 * weight)
+            .sum();
+        *value = mixed.round().clamp(0.0, 255.0) as u8;
+    }
+    Rgba(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_same_size() {
+        let mut image = RgbaImage::from_pixel(3, 3, Rgba([0, 0, 0, 255]));
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let white = Rgba([255, 255, 255, 255]);
+
+        assert_eq!(rotate_same_size(&image, 0.0, white), image);
+
+        // 時計回りに90度: 左上の画素が右上に移る
+        let rotated = rotate_same_size(&image, 90.0, white);
+        assert_eq!(rotated.dimensions(), (3, 3));
+        assert_eq!(rotated.get_pixel(2, 0).0, [255, 0, 0, 255]);
+        assert_eq!(rotated.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+        // 45度では角が空き、塗りつぶしの色になる
+        let rotated = rotate_same_size(&RgbaImage::new(20, 20), 45.0, white);
+        assert_eq!(rotated.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(rotated.get_pixel(10, 10).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut image = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 255]));
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+
+        // 90度単位はキャンバスごと回す
+        let rotated = rotate(
+            image.clone(),
+            &RotateOptions {
+                degrees: 90.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(rotated, imageops::rotate90(&image));
+        let rotated = rotate(
+            image.clone(),
+            &RotateOptions {
+                degrees: -90.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(rotated, imageops::rotate270(&image));
+        let rotated = rotate(
+            image.clone(),
+            &RotateOptions {
+                degrees: 540.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(rotated, imageops::rotate180(&image));
+        assert_eq!(rotate(image.clone(), &RotateOptions::default()), image);
+
+        // それ以外の角度はキャンバスを広げて背景色で埋める
+        let options = RotateOptions {
+            degrees: 45.0,
+            background: [0, 0, 255, 0],
+        };
+        let rotated = rotate(
+            RgbaImage::from_pixel(20, 10, Rgba([0, 0, 0, 255])),
+            &options,
+        );
+        assert_eq!(rotated.dimensions(), (22, 22));
+        assert_eq!(rotated.get_pixel(0, 0).0, [0, 0, 255, 0]);
+        assert_eq!(rotated.get_pixel(11, 11).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,268 @@
+use image::{imageops, GrayImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::CropRect;
+
+/// のどを探す列の明るさをならす幅（画像の幅に対する割合）
+const SMOOTHING_RATIO: f64 = 0.005;
+
+/// のどの列が周りよりこの値以上暗い（または明るい）場合だけ、そこで分割する
+/// （はっきりしなければ中央で分割する）
+const MIN_GUTTER_CONTRAST: f64 = 8.0;
+
+/// のどの影とみなす範囲（片ページの幅に対する割合）
+const SHADOW_RATIO: f64 = 0.15;
+
+/// のどの影を明るくする倍率の上限
+const MAX_SHADOW_GAIN: f64 = 3.0;
+
+/// 見開きの分割の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitOptions {
+    /// 見開きとみなす縦横比（幅 / 高さ）の下限。これより縦長の画像は分割しない
+    pub min_aspect: f64,
+    /// のどを探す範囲（中央から左右それぞれ、幅に対する割合）
+    pub search_range: f64,
+    /// のどの影を取り除く
+    pub remove_shadow: bool,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        Self {
+            min_aspect: 1.2,
+            search_range: 0.1,
+            remove_shadow: false,
+        }
+    }
+}
+
+/// 見開きのスキャンを左右のページに分割する
+///
+/// 見開きでない画像はそのまま1ページとして返す。
+///
+/// # Returns
+/// ページの画像と、分割前の画像での範囲（左 → 右の順）
+pub fn split_spread(image: RgbaImage, options: &SplitOptions) -> Vec<(RgbaImage, CropRect)> {
+    let (width, height) = image.dimensions();
+    let whole = CropRect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    let Some(gutter) = detect_gutter(&image, options) else {
+        return vec![(image, whole)];
+    };
+
+    let halves = [
+        CropRect {
+            x: 0,
+            y: 0,
+            width: gutter,
+            height,
+        },
+        CropRect {
+            x: gutter,
+            y: 0,
+            width: width - gutter,
+            height,
+        },
+    ];
+    halves
+        .into_iter()
+        .enumerate()
+        .map(|(index, rect)| {
+            let mut page =
+                imageops::crop_imm(&image, rect.x, rect.y, rect.width, rect.height).to_image();
+            if options.remove_shadow {
+                remove_gutter_shadow(&mut page, index == 0);
+            }
+            (page, rect)
+        })
+        .collect()
+}
+
+/// のど（分割する列）を検出する（見開きでなければ `None`）
+///
+/// 中央付近の列の平均の明るさを比べ、影で最も暗い列か、ページの間の隙間で最も明るい列を選ぶ。
+pub fn detect_gutter(image: &RgbaImage, options: &SplitOptions) -> Option<u32> {
+    let (width, height) = image.dimensions();
+    if width < 2 || height == 0 || (width as f64 / height as f64) < options.min_aspect {
+        return None;
+    }
+
+    let profile = smooth(&column_means(&imageops::grayscale(image)), width);
+    let center = width / 2;
+    let range = if options.search_range.is_finite() {
+        options.search_range.clamp(0.0, 0.4)
+    } else {
+        0.0
+    };
+    let reach = (width as f64 * range) as u32;
+    let (from, to) = (
+        center.saturating_sub(reach).max(1),
+        (center + reach).min(width - 1),
+    );
+
+    let columns = from..=to;
+    let typical = median(&profile[from as usize..=to as usize]);
+    let darkest = columns
+        .clone()
+        .min_by(|a, b| profile[*a as usize].total_cmp(&profile[*b as usize]))?;
+    let brightest = columns.max_by(|a, b| profile[*a as usize].total_cmp(&profile[*b as usize]))?;
+
+    let shadow = typical - profile[darkest as usize];
+    let gap = profile[brightest as usize] - typical;
+    let gutter = if shadow >= MIN_GUTTER_CONTRAST && shadow >= gap {
+        darkest
+    } else if gap >= MIN_GUTTER_CONTRAST {
+        brightest
+    } else {
+        center
+    };
+    Some(gutter)
+}
+
+/// のどの影を、ページの紙の明るさに合わせて明るくする
+///
+/// のど側の `SHADOW_RATIO` の範囲で、紙より暗い列に列ごとの倍率をかける
+fn remove_gutter_shadow(page: &mut RgbaImage, gutter_on_right: bool) {
+    let (width, height) = page.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let profile = column_means(&imageops::grayscale(page));
+    let paper = median(&profile);
+    let shadow_width = ((width as f64 * SHADOW_RATIO).ceil() as u32).min(width);
+
+    for offset in 0..shadow_width {
+        let x = if gutter_on_right {
+            width - 1 - offset
+        } else {
+            offset
+        };
+        let mean = profile[x as usize];
+        if mean >= paper || mean <= 0.0 {
+            continue;
+        }
+        let gain = (paper / mean).min(MAX_SHADOW_GAIN);
+        for y in 0..height {
+            let pixel = page.get_pixel_mut(x, y);
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as f64 * gain).round().min(255.0) as u8;
+            }
+        }
+    }
+}
+
+/// 列ごとの平均の明るさ
+fn column_means(gray: &GrayImage) -> Vec<f64> {
+    let (width, height) = gray.dimensions();
+    let mut sums = vec![0.0; width as usize];
+    for (x, _, pixel) in gray.enumerate_pixels() {
+        sums[x as usize] += pixel[0] as f64;
+    }
+    sums.into_iter().map(|sum| sum / height as f64).collect()
+}
+
+/// 移動平均でならす（文字の列で誤検出しないため）
+fn smooth(profile: &[f64], width: u32) -> Vec<f64> {
+    let half = (width as f64 * SMOOTHING_RATIO / 2.0).round() as usize;
+    (0..profile.len())
+        .map(|i| {
+            let window = &profile[i.saturating_sub(half)..(i + half + 1).min(profile.len())];
+            window.iter().sum::<f64>() / window.len() as f64
+        })
+        .collect()
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted.get(sorted.len() / 2).copied().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const PAPER: Rgba<u8> = Rgba([240, 240, 240, 255]);
+
+    /// 幅400・高さ200の見開きで、x = 210 を中心にのどの影がある
+    fn spread() -> RgbaImage {
+        RgbaImage::from_fn(400, 200, |x, _| {
+            let distance = (x as f64 - 210.0).abs();
+            if distance < 12.0 {
+                let value = (120.0 + distance * 10.0) as u8;
+                Rgba([value, value, value, 255])
+            } else {
+                PAPER
+            }
+        })
+    }
+
+    #[test]
+    fn test_detect_gutter() {
+        let options = SplitOptions::default();
+        assert_eq!(detect_gutter(&spread(), &options), Some(210));
+
+        // ページの間の白い隙間
+        let gap = RgbaImage::from_fn(400, 200, |x, _| {
+            if (190..194).contains(&x) {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([200, 200, 200, 255])
+            }
+        });
+        assert!(detect_gutter(&gap, &options).is_some_and(|x| (191..=192).contains(&x)));
+
+        // のどがはっきりしなければ中央
+        let plain = RgbaImage::from_pixel(400, 200, PAPER);
+        assert_eq!(detect_gutter(&plain, &options), Some(200));
+
+        // 縦長の画像は見開きではない
+        let single = RgbaImage::from_pixel(200, 280, PAPER);
+        assert_eq!(detect_gutter(&single, &options), None);
+    }
+
+    #[test]
+    fn test_split_spread() {
+        let pages = split_spread(spread(), &SplitOptions::default());
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].0.dimensions(), (210, 200));
+        assert_eq!(pages[1].0.dimensions(), (190, 200));
+        assert_eq!(
+            pages[1].1,
+            CropRect {
+                x: 210,
+                y: 0,
+                width: 190,
+                height: 200,
+            }
+        );
+        // 影は残る
+        assert!(pages[0].0.get_pixel(205, 100)[0] < 200);
+
+        let single = split_spread(
+            RgbaImage::from_pixel(200, 280, PAPER),
+            &SplitOptions::default(),
+        );
+        assert_eq!(single.len(), 1);
+        assert_eq!((single[0].1.width, single[0].1.height), (200, 280));
+    }
+
+    #[test]
+    fn test_remove_shadow() {
+        let options = SplitOptions {
+            remove_shadow: true,
+            ..Default::default()
+        };
+        let pages = split_spread(spread(), &options);
+        for (page, _) in &pages {
+            assert!(page.pixels().all(|pixel| pixel[0].abs_diff(240) <= 1));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// アンシャープマスクの設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnsharpOptions {
+    /// 強さ（ぼかした画像との差にかける倍率）
+    pub amount: f32,
+    /// ぼかしの半径（ガウスぼかしの標準偏差、ピクセル）
+    pub radius: f32,
+    /// 差がこの値（0-255）未満の画素はシャープにしない（紙のざらつきを強調しないため）
+    pub threshold: u8,
+}
+
+impl Default for UnsharpOptions {
+    fn default() -> Self {
+        Self {
+            amount: 1.0,
+            radius: 1.0,
+            threshold: 0,
+        }
+    }
+}
+
+/// 元の画像全体をアンシャープマスクでシャープにする
+///
+/// 元の画素にぼかした画像との差の `amount` 倍を足す。アルファは変えない。
+pub fn unsharp(mut image: RgbaImage, options: &UnsharpOptions) -> RgbaImage {
+    if !(options.amount.is_finite() && options.radius.is_finite())
+        || options.amount <= 0.0
+        || options.radius <= 0.0
+    {
+        return image;
+    }
+
+    let blurred = imageops::blur(&image, options.radius);
+    for (pixel, blurred) in image.pixels_mut().zip(blurred.pixels()) {
+        for channel in 0..3 {
+            let difference = pixel[channel] as f32 - blurred[channel] as f32;
+            if difference.abs() < options.threshold as f32 {
+                continue;
+            }
+            let value = pixel[channel] as f32 + difference * options.amount;
+            pixel[channel] = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 左が灰色、右が明るい灰色の画像
+    fn edge() -> RgbaImage {
+        RgbaImage::from_fn(20, 10, |x, _| {
+            if x < 10 {
+                Rgba([100, 100, 100, 255])
+            } else {
+                Rgba([160, 160, 160, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_unsharp() {
+        let sharpened = unsharp(edge(), &UnsharpOptions::default());
+        // 境界の両側のコントラストが強まる
+        assert!(sharpened.get_pixel(9, 5)[0] < 100);
+        assert!(sharpened.get_pixel(10, 5)[0] > 160);
+        // 平らな部分は変わらない
+        assert_eq!(sharpened.get_pixel(2, 5).0, [100, 100, 100, 255]);
+        assert_eq!(sharpened.get_pixel(17, 5).0, [160, 160, 160, 255]);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let options = UnsharpOptions {
+            threshold: 100,
+            ..Default::default()
+        };
+        assert_eq!(unsharp(edge(), &options), edge());
+
+        let options = UnsharpOptions {
+            amount: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(unsharp(edge(), &options), edge());
+    }
+}
@@ -0,0 +1,101 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// 解像度が分からない画像の解像度（古いWeb用の画像に多い72dpiとみなす）
+const DEFAULT_SOURCE_DPI: f64 = 72.0;
+
+/// この倍率未満の拡大はしない
+const MIN_SCALE: f64 = 1.01;
+
+/// 拡大の設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpscaleOptions {
+    /// 拡大後の解像度（dpi）
+    pub target_dpi: f64,
+    /// 元画像の解像度（省略時は画像に記録された値、記録が無ければ72dpi）
+    pub source_dpi: Option<f64>,
+    /// 拡大の倍率の上限
+    pub max_scale: f64,
+}
+
+impl Default for UpscaleOptions {
+    fn default() -> Self {
+        Self {
+            target_dpi: 150.0,
+            source_dpi: None,
+            max_scale: 4.0,
+        }
+    }
+}
+
+impl UpscaleOptions {
+    /// 元画像の解像度（指定が無い・不正な場合は72dpi）
+    pub fn effective_source_dpi(&self) -> f64 {
+        self.source_dpi
+            .filter(|dpi| dpi.is_finite() && *dpi > 0.0)
+            .unwrap_or(DEFAULT_SOURCE_DPI)
+    }
+}
+
+/// 解像度の低い画像を `target_dpi` までLanczos法で拡大する
+///
+/// # Returns
+/// 拡大した画像と倍率（拡大しなかった場合は `None`）
+pub fn upscale(image: RgbaImage, options: &UpscaleOptions) -> (RgbaImage, Option<f64>) {
+    let Some(scale) = upscale_factor(options) else {
+        return (image, None);
+    };
+
+    let (width, height) = image.dimensions();
+    let scaled = |size: u32| ((size as f64 * scale).round() as u32).max(1);
+    let upscaled = imageops::resize(&image, scaled(width), scaled(height), FilterType::Lanczos3);
+    (upscaled, Some(scale))
+}
+
+/// 拡大の倍率（拡大しない場合は `None`）
+fn upscale_factor(options: &UpscaleOptions) -> Option<f64> {
+    let source = options.effective_source_dpi();
+    let max_scale = if options.max_scale.is_finite() {
+        options.max_scale
+    } else {
+        1.0
+    };
+    let scale = (options.target_dpi / source).min(max_scale);
+    (scale.is_finite() && scale >= MIN_SCALE).then_some(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_upscale() {
+        let image = RgbaImage::from_pixel(72, 36, Rgba([10, 20, 30, 255]));
+
+        // 解像度が分からなければ72dpiとみなす
+        let (upscaled, scale) = upscale(image.clone(), &UpscaleOptions::default());
+        assert_eq!(upscaled.dimensions(), (150, 75));
+        assert!((scale.unwrap() - 150.0 / 72.0).abs() < 1e-9);
+        assert_eq!(upscaled.get_pixel(70, 30).0, [10, 20, 30, 255]);
+
+        // 倍率の上限
+        let options = UpscaleOptions {
+            target_dpi: 600.0,
+            max_scale: 2.0,
+            ..Default::default()
+        };
+        let (upscaled, scale) = upscale(image.clone(), &options);
+        assert_eq!((upscaled.dimensions(), scale), ((144, 72), Some(2.0)));
+
+        // 十分な解像度があれば拡大しない
+        let options = UpscaleOptions {
+            source_dpi: Some(300.0),
+            ..Default::default()
+        };
+        let (upscaled, scale) = upscale(image.clone(), &options);
+        assert_eq!((upscaled, scale), (image, None));
+    }
+}
@@ -0,0 +1,165 @@
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// 補正する倍率の上限（ほぼ単色の画像を極端に補正しないため）
+const MAX_GAIN: f64 = 2.0;
+
+/// ホワイトバランスの補正方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhiteBalanceMethod {
+    /// グレーワールド（画像全体の平均が無彩色になるようにする）
+    #[default]
+    GrayWorld,
+    /// ホワイトパッチ（最も明るい部分を白とみなす。余白の多いページ向け）
+    WhitePatch,
+}
+
+/// ホワイトバランスの設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhiteBalanceOptions {
+    pub method: WhiteBalanceMethod,
+    /// ホワイトパッチで白とみなす明るい画素の割合（%）
+    pub percentile: f64,
+}
+
+impl Default for WhiteBalanceOptions {
+    fn default() -> Self {
+        Self {
+            method: WhiteBalanceMethod::GrayWorld,
+            percentile: 1.0,
+        }
+    }
+}
+
+/// 色かぶり（フラットベッドスキャナーの黄ばみなど）を補正する
+///
+/// RGBの各チャンネルに倍率をかける。アルファは変えない。
+pub fn white_balance(mut image: RgbaImage, options: &WhiteBalanceOptions) -> RgbaImage {
+    let gains = match options.method {
+        WhiteBalanceMethod::GrayWorld => gray_world_gains(&image),
+        WhiteBalanceMethod::WhitePatch => white_patch_gains(&image, options.percentile),
+    };
+    let Some(gains) = gains else {
+        return image;
+    };
+
+    let tables: Vec<[u8; 256]> = gains
+        .iter()
+        .map(|gain| {
+            let mut table = [0u8; 256];
+            for (value, out) in table.iter_mut().enumerate() {
+                *out = (value as f64 * gain).round().clamp(0.0, 255.0) as u8;
+            }
+            table
+        })
+        .collect();
+    for pixel in image.pixels_mut() {
+        for (channel, table) in tables.iter().enumerate() {
+            pixel[channel] = table[pixel[channel] as usize];
+        }
+    }
+    image
+}
+
+/// 各チャンネルの平均をそろえる倍率
+fn gray_world_gains(image: &RgbaImage) -> Option<[f64; 3]> {
+    let mut sums = [0.0f64; 3];
+    for pixel in image.pixels() {
+        for (channel, sum) in sums.iter_mut().enumerate() {
+            *sum += pixel[channel] as f64;
+        }
+    }
+    if sums.iter().any(|sum| *sum <= 0.0) {
+        return None;
+    }
+
+    let gray = sums.iter().sum::<f64>() / 3.0;
+    Some(sums.map(|sum| (gray / sum).clamp(1.0 / MAX_GAIN, MAX_GAIN)))
+}
+
+/// 各チャンネルの明るい側 `percentile` %の値が255になる倍率
+fn white_patch_gains(image: &RgbaImage, percentile: f64) -> Option<[f64; 3]> {
+    let mut histograms = [[0u64; 256]; 3];
+    for pixel in image.pixels() {
+        for (channel, histogram) in histograms.iter_mut().enumerate() {
+            histogram[pixel[channel] as usize] += 1;
+        }
+    }
+
+    let total = image.width() as u64 * image.height() as u64;
+    let percentile = if percentile.is_finite() {
+        percentile.clamp(0.0, 50.0)
+    } else {
+        1.0
+    };
+    let bright = ((total as f64 * percentile / 100.0).ceil() as u64).max(1);
+
+    let mut gains = [1.0; 3];
+    for (gain, histogram) in gains.iter_mut().zip(&histograms) {
+        let mut count = 0;
+        let white = (0..256usize).rev().find(|&value| {
+            count += histogram[value];
+            count >= bright
+        })?;
+        if white == 0 {
+            return None;
+        }
+        *gain = (255.0 / white as f64).min(MAX_GAIN);
+    }
+    Some(gains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 黄ばんだ紙に文字のあるスキャン
+    fn yellowed() -> RgbaImage {
+        RgbaImage::from_fn(20, 20, |x, _| {
+            if x < 4 {
+                Rgba([40, 38, 20, 255])
+            } else {
+                Rgba([240, 230, 180, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_gray_world() {
+        let balanced = white_balance(yellowed(), &WhiteBalanceOptions::default());
+        let paper = balanced.get_pixel(10, 10);
+        assert!(paper[0].abs_diff(paper[2]) <= 8);
+        assert!(paper[1].abs_diff(paper[2]) <= 8);
+        assert_eq!(paper[3], 255);
+    }
+
+    #[test]
+    fn test_white_patch() {
+        let options = WhiteBalanceOptions {
+            method: WhiteBalanceMethod::WhitePatch,
+            ..Default::default()
+        };
+        let balanced = white_balance(yellowed(), &options);
+        assert_eq!(balanced.get_pixel(10, 10).0, [255, 255, 255, 255]);
+        // 暗い部分も同じ倍率で補正される
+        assert_eq!(balanced.get_pixel(0, 0).0, [43, 42, 28, 255]);
+    }
+
+    #[test]
+    fn test_black_image_is_unchanged() {
+        let black = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        for method in [
+            WhiteBalanceMethod::GrayWorld,
+            WhiteBalanceMethod::WhitePatch,
+        ] {
+            let options = WhiteBalanceOptions {
+                method,
+                ..Default::default()
+            };
+            assert_eq!(white_balance(black.clone(), &options), black);
+        }
+    }
+}
@@ -0,0 +1,835 @@
+use std::io::Cursor;
+
+use image::metadata::Orientation;
+use image::{ColorType, ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+
+use crate::decode::{self, InputFormat};
+use crate::error::PamphletError;
+use crate::measure;
+
+/// 入力画像の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Gif,
+    Heif,
+    Avif,
+    Jxl,
+    Jpeg2000,
+    Psd,
+    Raw,
+    #[cfg(feature = "pdf")]
+    Pdf,
+}
+
+/// 色空間（チャンネルの構成）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    Cmyk,
+    /// ヘッダーだけでは分からない
+    Unknown,
+}
+
+/// 入力画像のヘッダーから読み取った情報
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageProbe {
+    pub format: ProbeFormat,
+    /// 幅（ピクセル、EXIFの向きを適用する前）
+    pub width: u32,
+    /// 高さ（ピクセル、EXIFの向きを適用する前）
+    pub height: u32,
+    /// ページ数（アニメーションはフレーム数、PDFはページ数）
+    pub pages: u32,
+    pub color_space: ColorSpace,
+    /// 解像度（記録されていない場合は `None`）
+    pub dpi: Option<f64>,
+    /// EXIFの向き（1〜8、記録されていない場合は `None`）
+    pub exif_orientation: Option<u8>,
+}
+
+/// 入力画像のヘッダーを調べる
+///
+/// 画素データはデコードしないので、大きな画像でもすぐに返る。
+/// PDFは先頭ページを既定の解像度でラスタライズしたときのサイズを返す（`pdf` フィーチャーが必要）。
+///
+/// # Errors
+/// 形式を判別できない場合や、ヘッダーが壊れている場合
+pub fn probe_image(data: &[u8]) -> Result<ImageProbe, PamphletError> {
+    #[cfg(feature = "pdf")]
+    if data.starts_with(b"%PDF-") {
+        return probe_pdf(data);
+    }
+
+    let probe = match decode::detect_format(data) {
+        InputFormat::Heif => probe_heif(data, ProbeFormat::Heif),
+        InputFormat::Avif => probe_heif(data, ProbeFormat::Avif),
+        InputFormat::Jxl => probe_jxl(data),
+        InputFormat::Jpeg2000 => probe_jpeg2000(data),
+        InputFormat::Psd => probe_psd(data),
+        InputFormat::Raw => probe_raw(data),
+        InputFormat::Other => return probe_other(data),
+    };
+    probe.ok_or_else(|| {
+        PamphletError::DecodeFailed("Failed to read image header: invalid header".to_string())
+    })
+}
+
+/// `image` クレートが扱う形式（JPEG/PNG/WebP/GIF）
+fn probe_other(data: &[u8]) -> Result<ImageProbe, PamphletError> {
+    let error = |e: image::ImageError| {
+        PamphletError::DecodeFailed(format!("Failed to read image header: {}", e))
+    };
+
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to read image header: {}", e)))?;
+    let format = match reader.format() {
+        Some(ImageFormat::Jpeg) => ProbeFormat::Jpeg,
+        Some(ImageFormat::Png) => ProbeFormat::Png,
+        Some(ImageFormat::WebP) => ProbeFormat::Webp,
+        Some(ImageFormat::Gif) => ProbeFormat::Gif,
+        _ => {
+            return Err(PamphletError::UnsupportedFormat(
+                "Failed to read image header: unsupported format".to_string(),
+            ))
+        }
+    };
+
+    let mut decoder = reader.into_decoder().map_err(error)?;
+    let (width, height) = decoder.dimensions();
+    let color_space = match decoder.color_type() {
+        ColorType::L8 | ColorType::L16 => ColorSpace::Gray,
+        ColorType::La8 | ColorType::La16 => ColorSpace::GrayAlpha,
+        ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => ColorSpace::Rgb,
+        ColorType::Rgba8 | ColorType::Rgba16 | ColorType::Rgba32F => ColorSpace::Rgba,
+        _ => ColorSpace::Unknown,
+    };
+    let exif_orientation = decoder
+        .exif_metadata()
+        .ok()
+        .flatten()
+        .and_then(|exif| Orientation::from_exif_chunk(&exif))
+        .map(Orientation::to_exif);
+
+    let pages = match format {
+        ProbeFormat::Gif => gif_frame_count(data),
+        ProbeFormat::Png => apng_frame_count(data),
+        ProbeFormat::Webp => webp_frame_count(data),
+        _ => None,
+    };
+
+    Ok(ImageProbe {
+        format,
+        width,
+        height,
+        pages: pages.unwrap_or(1).max(1),
+        color_space,
+        dpi: measure::detect_dpi(data),
+        exif_orientation,
+    })
+}
+
+/// GIFのフレーム数（画像記述子の数）
+fn gif_frame_count(data: &[u8]) -> Option<u32> {
+    // ヘッダー（6バイト）と論理画面記述子（7バイト）
+    let flags = *data.get(10)?;
+    let mut pos = 13 + color_table_size(flags);
+    let mut count = 0;
+
+    // 途中で切れている場合は、最後まで読めたフレームまでを数える
+    loop {
+        match data.get(pos) {
+            // 拡張ブロック（導入子・ラベルの後にサブブロックが続く）
+            Some(0x21) => match skip_sub_blocks(data, pos + 2) {
+                Some(next) => pos = next,
+                None => break,
+            },
+            // 画像記述子（10バイト、局所カラーテーブル、LZWの最小符号長の後にサブブロックが続く）
+            Some(0x2C) => {
+                let next = data.get(pos + 9).and_then(|flags| {
+                    skip_sub_blocks(data, pos + 10 + color_table_size(*flags) + 1)
+                });
+                match next {
+                    Some(next) => pos = next,
+                    None => break,
+                }
+                count += 1;
+            }
+            // トレーラー
+            _ => break,
+        }
+    }
+    Some(count)
+}
+
+/// GIFのカラーテーブルのバイト数
+fn color_table_size(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        0
+    } else {
+        3 << ((flags & 0x07) + 1)
+    }
+}
+
+/// GIFのサブブロックの並び（長さ0のブロックで終わる）を読み飛ばす
+fn skip_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+/// APNGのフレーム数（`acTL` チャンク、アニメーションでない場合は `None`）
+fn apng_frame_count(data: &[u8]) -> Option<u32> {
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let len = be_u32(data, pos)? as usize;
+        match &data[pos + 4..pos + 8] {
+            b"acTL" => return be_u32(data, pos + 8),
+            // acTLはIDATより前に置かれる
+            b"IDAT" | b"IEND" => return None,
+            _ => {}
+        }
+        pos += 12 + len;
+    }
+    None
+}
+
+/// アニメーションWebPのフレーム数（`ANMF` チャンクの数、アニメーションでない場合は `None`）
+fn webp_frame_count(data: &[u8]) -> Option<u32> {
+    let mut pos = 12;
+    let mut count = 0;
+    while pos + 8 <= data.len() {
+        let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().ok()?) as usize;
+        if &data[pos..pos + 4] == b"ANMF" {
+            count += 1;
+        }
+        // チャンクは偶数バイトに揃えられる
+        pos += 8 + len + (len & 1);
+    }
+    (count > 0).then_some(count)
+}
+
+/// HEIF/AVIF（`meta` の `ispe`・`pixi`・`auxC` プロパティ）
+///
+/// グリッド画像やサムネイルを含む場合があるため、最も大きい `ispe` を画像のサイズとする
+fn probe_heif(data: &[u8], format: ProbeFormat) -> Option<ImageProbe> {
+    // `meta` はフルボックス（バージョンとフラグの4バイトが先頭にある）
+    let meta = find_box(data, b"meta")?.get(4..)?;
+    let ipco = find_box(find_box(meta, b"iprp")?, b"ipco")?;
+
+    let mut size: Option<(u32, u32)> = None;
+    let mut channels = 3;
+    let mut alpha = false;
+    for (kind, body) in boxes(ipco) {
+        match kind {
+            b"ispe" => {
+                let (width, height) = (be_u32(body, 4)?, be_u32(body, 8)?);
+                let area = |(w, h): (u32, u32)| w as u64 * h as u64;
+                if size.is_none_or(|size| area((width, height)) > area(size)) {
+                    size = Some((width, height));
+                }
+            }
+            b"pixi" => channels = *body.get(4)?,
+            // 補助画像の種類（アルファチャンネル）
+            b"auxC" => {
+                let aux_type = body.get(4..)?;
+                alpha |= aux_type.windows(5).any(|w| w == b"alpha")
+                    || aux_type.starts_with(b"urn:mpeg:hevc:2015:auxid:1\0");
+            }
+            _ => {}
+        }
+    }
+
+    let (width, height) = size?;
+    let color_space = match (channels, alpha) {
+        (1, false) => ColorSpace::Gray,
+        (1, true) => ColorSpace::GrayAlpha,
+        (_, false) => ColorSpace::Rgb,
+        (_, true) => ColorSpace::Rgba,
+    };
+    Some(ImageProbe {
+        format,
+        width,
+        height,
+        pages: 1,
+        color_space,
+        dpi: None,
+        exif_orientation: None,
+    })
+}
+
+/// JPEG XL（コードストリームの `SizeHeader`）
+fn probe_jxl(data: &[u8]) -> Option<ImageProbe> {
+    let codestream = if data.starts_with(&[0xFF, 0x0A]) {
+        data
+    } else {
+        // コンテナの場合は `jxlc`、または分割された最初の `jxlp`（先頭4バイトは通し番号）
+        boxes(data).find_map(|(kind, body)| match kind {
+            b"jxlc" => Some(body),
+            b"jxlp" => body.get(4..),
+            _ => None,
+        })?
+    };
+    if !codestream.starts_with(&[0xFF, 0x0A]) {
+        return None;
+    }
+
+    let mut bits = BitReader::new(&codestream[2..]);
+    let small = bits.read(1)? == 1;
+    let read_size = |bits: &mut BitReader<'_>| -> Option<u64> {
+        if small {
+            return Some((bits.read(5)? as u64 + 1) * 8);
+        }
+        let width = [9, 13, 18, 30][bits.read(2)? as usize];
+        Some(bits.read(width)? as u64 + 1)
+    };
+    let height = read_size(&mut bits)?;
+    let width = match bits.read(3)? {
+        0 => read_size(&mut bits)?,
+        ratio => {
+            let (num, den) =
+                [(1, 1), (12, 10), (4, 3), (3, 2), (16, 9), (5, 4), (2, 1)][ratio as usize - 1];
+            height * num / den
+        }
+    };
+
+    Some(ImageProbe {
+        format: ProbeFormat::Jxl,
+        width: u32::try_from(width).ok()?,
+        height: u32::try_from(height).ok()?,
+        pages: 1,
+        color_space: ColorSpace::Unknown,
+        dpi: None,
+        exif_orientation: None,
+    })
+}
+
+/// JPEG 2000（JP2の `ihdr` ボックス、またはコードストリームの `SIZ` マーカー）
+fn probe_jpeg2000(data: &[u8]) -> Option<ImageProbe> {
+    let jp2h = find_box(data, b"jp2h");
+    let (width, height, components, cmyk) = match jp2h.and_then(|jp2h| find_box(jp2h, b"ihdr")) {
+        Some(ihdr) => {
+            // 列挙型の色空間（12 = CMYK）
+            let cmyk = jp2h
+                .and_then(|jp2h| find_box(jp2h, b"colr"))
+                .is_some_and(|colr| colr.first() == Some(&1) && be_u32(colr, 3) == Some(12));
+            (be_u32(ihdr, 4)?, be_u32(ihdr, 0)?, be_u16(ihdr, 8)?, cmyk)
+        }
+        None => {
+            let codestream = if data.starts_with(&[0xFF, 0x4F]) {
+                data
+            } else {
+                find_box(data, b"jp2c")?
+            };
+            // SOC・SIZマーカーの後の画像サイズとオフセット、成分数
+            let (width, height) = (be_u32(codestream, 8)?, be_u32(codestream, 12)?);
+            let (x_offset, y_offset) = (be_u32(codestream, 16)?, be_u32(codestream, 20)?);
+            (
+                width.checked_sub(x_offset)?,
+                height.checked_sub(y_offset)?,
+                be_u16(codestream, 40)?,
+                false,
+            )
+        }
+    };
+
+    let color_space = match (components, cmyk) {
+        (_, true) => ColorSpace::Cmyk,
+        (1, _) => ColorSpace::Gray,
+        (2, _) => ColorSpace::GrayAlpha,
+        (3, _) => ColorSpace::Rgb,
+        (4, _) => ColorSpace::Rgba,
+        _ => ColorSpace::Unknown,
+    };
+    Some(ImageProbe {
+        format: ProbeFormat::Jpeg2000,
+        width,
+        height,
+        pages: 1,
+        color_space,
+        dpi: jp2h.and_then(jp2_dpi),
+        exif_orientation: None,
+    })
+}
+
+/// JP2の解像度（`res ` の中の取り込み時の解像度 `resc`、無ければ表示用の `resd`）
+fn jp2_dpi(jp2h: &[u8]) -> Option<f64> {
+    let res = find_box(jp2h, b"res ")?;
+    let body = find_box(res, b"resc").or_else(|| find_box(res, b"resd"))?;
+
+    // 水平方向の分子・分母と指数（メートルあたりの格子点数）
+    let (num, den) = (be_u16(body, 4)?, be_u16(body, 6)?);
+    let exponent = *body.get(9)? as i8;
+    let per_metre = num as f64 / den as f64 * 10f64.powi(exponent as i32);
+    (per_metre.is_finite() && per_metre > 0.0).then_some(per_metre * 0.0254)
+}
+
+/// Photoshop（ファイルヘッダーと画像リソースの `ResolutionInfo`）
+fn probe_psd(data: &[u8]) -> Option<ImageProbe> {
+    let channels = be_u16(data, 12)?;
+    let (height, width) = (be_u32(data, 14)?, be_u32(data, 18)?);
+    let color_space = match (be_u16(data, 24)?, channels) {
+        // モノクロ2階調・グレースケール・ダブルトーン
+        (0 | 1 | 8, 1) => ColorSpace::Gray,
+        (0 | 1 | 8, _) => ColorSpace::GrayAlpha,
+        // インデックスカラー・RGB
+        (2, _) | (3, 3) => ColorSpace::Rgb,
+        (3, _) => ColorSpace::Rgba,
+        (4, _) => ColorSpace::Cmyk,
+        _ => ColorSpace::Unknown,
+    };
+
+    Some(ImageProbe {
+        format: ProbeFormat::Psd,
+        width,
+        height,
+        pages: 1,
+        color_space,
+        dpi: psd_dpi(data),
+        exif_orientation: None,
+    })
+}
+
+/// PSDの画像リソース（ID 0x03ED）から水平方向の解像度を読む
+fn psd_dpi(data: &[u8]) -> Option<f64> {
+    // カラーモードデータの後に画像リソースのセクションが続く
+    let resources_at = 30 + be_u32(data, 26)? as usize;
+    let end = (resources_at + 4).checked_add(be_u32(data, resources_at)? as usize)?;
+    let mut pos = resources_at + 4;
+
+    while pos + 12 <= end {
+        if data.get(pos..pos + 4)? != b"8BIM" {
+            return None;
+        }
+        let id = be_u16(data, pos + 4)?;
+        // パスカル文字列の名前（長さのバイトを含めて偶数バイト）
+        let name_len = *data.get(pos + 6)? as usize;
+        let size_at = pos + 6 + ((name_len + 2) & !1);
+        let size = be_u32(data, size_at)? as usize;
+        let body = data.get(size_at + 4..size_at + 4 + size)?;
+
+        if id == 0x03ED {
+            // 16.16固定小数点の解像度と単位（1 = インチ、2 = センチメートル）
+            let resolution = be_u32(body, 0)? as f64 / 65536.0;
+            let dpi = match be_u16(body, 4)? {
+                2 => resolution * 2.54,
+                _ => resolution,
+            };
+            return (dpi > 0.0).then_some(dpi);
+        }
+        pos = size_at + 4 + size + (size & 1);
+    }
+    None
+}
+
+/// TIFFベースのカメラRAW（IFDの中で最も大きい画像のサイズと `Orientation` タグ）
+///
+/// RAWの画素データを指すIFDにサイズが無い形式では、埋め込まれたプレビューのサイズになる
+fn probe_raw(data: &[u8]) -> Option<ImageProbe> {
+    /// 読むIFDの数の上限（壊れたファイルの循環参照対策）
+    const MAX_IFDS: usize = 64;
+
+    let tiff = Tiff::new(data)?;
+    let mut queue = vec![tiff.u32(4)? as usize];
+    let mut visited = Vec::new();
+    let mut size = (0u32, 0u32);
+
+    while let Some(offset) = queue.pop() {
+        if offset == 0 || visited.contains(&offset) || visited.len() >= MAX_IFDS {
+            continue;
+        }
+        visited.push(offset);
+
+        let Some((entries, next)) = tiff.ifd(offset) else {
+            continue;
+        };
+        queue.push(next);
+
+        let value = |tag: u16| {
+            let entry = entries.iter().find(|entry| entry.tag == tag)?;
+            tiff.value(entry, 0)
+        };
+        if let (Some(width), Some(height)) = (value(256), value(257)) {
+            if width as u64 * height as u64 > size.0 as u64 * size.1 as u64 {
+                size = (width, height);
+            }
+        }
+        // SubIFDs
+        if let Some(entry) = entries.iter().find(|entry| entry.tag == 330) {
+            queue.extend(
+                (0..entry.count)
+                    .filter_map(|i| tiff.value(entry, i))
+                    .map(|v| v as usize),
+            );
+        }
+    }
+
+    if size.0 == 0 || size.1 == 0 {
+        return None;
+    }
+    Some(ImageProbe {
+        format: ProbeFormat::Raw,
+        width: size.0,
+        height: size.1,
+        pages: 1,
+        color_space: ColorSpace::Rgb,
+        dpi: None,
+        exif_orientation: Orientation::from_exif_chunk(data).map(Orientation::to_exif),
+    })
+}
+
+/// PDF（ページ数と、先頭ページを既定の解像度でラスタライズしたときのサイズ）
+#[cfg(feature = "pdf")]
+fn probe_pdf(data: &[u8]) -> Result<ImageProbe, PamphletError> {
+    use crate::pdf::{PdfDocument, DEFAULT_RASTER_DPI};
+
+    let document = PdfDocument::open(data.to_vec(), None)
+        .map_err(|e| PamphletError::DecodeFailed(e.to_string()))?;
+    let (width, height, dpi) = document.page_dimensions(0, DEFAULT_RASTER_DPI)?;
+    Ok(ImageProbe {
+        format: ProbeFormat::Pdf,
+        width,
+        height,
+        pages: document.page_count() as u32,
+        color_space: ColorSpace::Rgb,
+        dpi: Some(dpi as f64),
+        exif_orientation: None,
+    })
+}
+
+/// TIFFのIFDエントリ
+struct IfdEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// エントリの値フィールドの位置
+    at: usize,
+}
+
+/// TIFFの構造を読む
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(0..4)? {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16(&self, pos: usize) -> Option<u16> {
+        let bytes = self.data.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, pos: usize) -> Option<u32> {
+        let bytes = self.data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// IFDのエントリと、次のIFDの位置
+    fn ifd(&self, offset: usize) -> Option<(Vec<IfdEntry>, usize)> {
+        let count = self.u16(offset)? as usize;
+        let entries = (0..count)
+            .map(|i| {
+                let pos = offset + 2 + i * 12;
+                Some(IfdEntry {
+                    tag: self.u16(pos)?,
+                    kind: self.u16(pos + 2)?,
+                    count: self.u32(pos + 4)?,
+                    at: pos + 8,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let next = self.u32(offset + 2 + count * 12)? as usize;
+        Some((entries, next))
+    }
+
+    /// SHORT・LONG・IFD型のエントリの `index` 番目の値
+    fn value(&self, entry: &IfdEntry, index: u32) -> Option<u32> {
+        let size = match entry.kind {
+            3 => 2,
+            4 | 13 => 4,
+            _ => return None,
+        };
+        if index >= entry.count {
+            return None;
+        }
+        // 4バイトに収まらない値は別の位置に置かれる
+        let base = if entry.count as usize * size <= 4 {
+            entry.at
+        } else {
+            self.u32(entry.at)? as usize
+        };
+        let pos = base + index as usize * size;
+        match size {
+            2 => self.u16(pos).map(u32::from),
+            _ => self.u32(pos),
+        }
+    }
+}
+
+/// 下位ビットから読むビット列（JPEG XLのヘッダー用）
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..bits {
+            let byte = *self.data.get(self.pos / 8)?;
+            value |= (((byte >> (self.pos % 8)) & 1) as u32) << i;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+/// ISOBMFF（HEIF・JP2・JPEG XLのコンテナ）のボックスの種類と中身を順に返す
+fn boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        let (header, size) = match be_u32(rest, 0)? {
+            // 64bitのサイズ
+            1 => (16, usize::try_from(be_u64(rest, 8)?).ok()?),
+            // ファイルの終わりまで
+            0 => (8, rest.len()),
+            size => (8, size as usize),
+        };
+        let kind = rest.get(4..8)?;
+        let body = rest.get(header..size)?;
+        rest = &rest[size..];
+        Some((kind, body))
+    })
+}
+
+fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data).find_map(|(k, body)| (k == kind).then_some(body))
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbImage, RgbaImage};
+
+    fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    /// ISOBMFFのボックス
+    fn bmff_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn test_probe_raster_formats() {
+        let png = encode(
+            DynamicImage::ImageRgba8(RgbaImage::new(30, 20)),
+            ImageFormat::Png,
+        );
+        let probe = probe_image(&png).unwrap();
+        assert_eq!(
+            probe,
+            ImageProbe {
+                format: ProbeFormat::Png,
+                width: 30,
+                height: 20,
+                pages: 1,
+                color_space: ColorSpace::Rgba,
+                dpi: None,
+                exif_orientation: None,
+            }
+        );
+
+        // SOIの直後にEXIF（向き = 6、90度回転）のAPP1セグメントを入れる
+        let jpeg = encode(
+            DynamicImage::ImageRgb8(RgbImage::new(8, 4)),
+            ImageFormat::Jpeg,
+        );
+        let exif = b"Exif\0\0MM\0*\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0";
+        let mut with_exif = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        with_exif.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        with_exif.extend_from_slice(exif);
+        with_exif.extend_from_slice(&jpeg[2..]);
+        let probe = probe_image(&with_exif).unwrap();
+        assert_eq!(probe.format, ProbeFormat::Jpeg);
+        assert_eq!((probe.width, probe.height), (8, 4));
+        assert_eq!(probe.color_space, ColorSpace::Rgb);
+        assert_eq!(probe.exif_orientation, Some(6));
+
+        assert!(probe_image(b"not an image").is_err());
+        assert!(probe_image(&png[..20]).is_err());
+    }
+
+    #[test]
+    fn test_probe_animation_frames() {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            for _ in 0..3 {
+                let frame = image::Frame::new(RgbaImage::new(4, 2));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        let probe = probe_image(&gif).unwrap();
+        assert_eq!((probe.format, probe.pages), (ProbeFormat::Gif, 3));
+        assert_eq!((probe.width, probe.height), (4, 2));
+
+        // 途中で切れていても読めたフレームまで数える
+        assert_eq!(gif_frame_count(&gif[..gif.len() - 20]), Some(2));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        webp.extend_from_slice(&[10, 0, 0, 0, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        for _ in 0..2 {
+            webp.extend_from_slice(b"ANMF\x03\0\0\0abc\0");
+        }
+        assert_eq!(webp_frame_count(&webp), Some(2));
+        assert_eq!(webp_frame_count(&webp[..30]), None);
+    }
+
+    #[test]
+    fn test_probe_container_formats() {
+        // HEIF: サムネイル（小さいispe）とアルファの補助画像を含む
+        let ispe = |w: u32, h: u32| {
+            let mut body = vec![0; 4];
+            body.extend_from_slice(&w.to_be_bytes());
+            body.extend_from_slice(&h.to_be_bytes());
+            bmff_box(b"ispe", &body)
+        };
+        let mut ipco = ispe(160, 120);
+        ipco.extend(ispe(4032, 3024));
+        ipco.extend(bmff_box(b"auxC", b"\0\0\0\0urn:mpeg:hevc:2015:auxid:1\0"));
+        let mut meta = vec![0; 4];
+        meta.extend(bmff_box(b"iprp", &bmff_box(b"ipco", &ipco)));
+        let mut heif = bmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        heif.extend(bmff_box(b"meta", &meta));
+
+        let probe = probe_image(&heif).unwrap();
+        assert_eq!(probe.format, ProbeFormat::Heif);
+        assert_eq!((probe.width, probe.height), (4032, 3024));
+        assert_eq!(probe.color_space, ColorSpace::Rgba);
+
+        // JP2: ihdrと解像度（300dpi ≒ 11811格子点/m）
+        let mut jp2h = bmff_box(b"ihdr", &[0, 0, 0, 50, 0, 0, 0, 70, 0, 3, 7, 7, 0, 0]);
+        let resolution = 11811u16.to_be_bytes();
+        let resc = [&resolution[..], &[0, 1], &resolution, &[0, 1, 0, 0]].concat();
+        jp2h.extend(bmff_box(b"res ", &bmff_box(b"resc", &resc)));
+        let mut jp2 = bmff_box(b"jP  ", b"\r\n\x87\n");
+        jp2.extend(bmff_box(b"jp2h", &jp2h));
+
+        let probe = probe_image(&jp2).unwrap();
+        assert_eq!(probe.format, ProbeFormat::Jpeg2000);
+        assert_eq!((probe.width, probe.height), (70, 50));
+        assert_eq!(probe.color_space, ColorSpace::Rgb);
+        assert!((probe.dpi.unwrap() - 300.0).abs() < 0.01);
+
+        // JPEG XL: 1/8単位の高さ48と、幅の比率4:3
+        let probe = probe_image(&[0xFF, 0x0A, 0xCB, 0x00]).unwrap();
+        assert_eq!(probe.format, ProbeFormat::Jxl);
+        assert_eq!((probe.width, probe.height), (64, 48));
+    }
+
+    #[test]
+    fn test_probe_psd_and_raw() {
+        // 72dpiのResolutionInfoを持つCMYKのPSDのヘッダー
+        let mut psd = b"8BPS\0\x01".to_vec();
+        psd.extend_from_slice(&[0; 6]);
+        psd.extend_from_slice(&4u16.to_be_bytes());
+        psd.extend_from_slice(&200u32.to_be_bytes());
+        psd.extend_from_slice(&300u32.to_be_bytes());
+        psd.extend_from_slice(&8u16.to_be_bytes());
+        psd.extend_from_slice(&4u16.to_be_bytes());
+        psd.extend_from_slice(&0u32.to_be_bytes());
+        let mut resource = b"8BIM\x03\xED\0\0".to_vec();
+        resource.extend_from_slice(&16u32.to_be_bytes());
+        resource.extend_from_slice(&(72u32 << 16).to_be_bytes());
+        resource.extend_from_slice(&[0, 1, 0, 1]);
+        resource.extend_from_slice(&(72u32 << 16).to_be_bytes());
+        resource.extend_from_slice(&[0, 1, 0, 1]);
+        psd.extend_from_slice(&(resource.len() as u32).to_be_bytes());
+        psd.extend(resource);
+
+        let probe = probe_image(&psd).unwrap();
+        assert_eq!(probe.format, ProbeFormat::Psd);
+        assert_eq!((probe.width, probe.height), (300, 200));
+        assert_eq!(probe.color_space, ColorSpace::Cmyk);
+        assert_eq!(probe.dpi, Some(72.0));
+
+        // IFD0（プレビュー 160x120、向き = 8）と、SubIFDのRAW（6000x4000）
+        let entry = |tag: u16, kind: u16, value: u32| {
+            let mut entry = tag.to_le_bytes().to_vec();
+            entry.extend_from_slice(&kind.to_le_bytes());
+            entry.extend_from_slice(&1u32.to_le_bytes());
+            entry.extend_from_slice(&value.to_le_bytes());
+            entry
+        };
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend_from_slice(&4u16.to_le_bytes());
+        raw.extend(entry(256, 4, 160));
+        raw.extend(entry(257, 4, 120));
+        raw.extend(entry(274, 3, 8));
+        raw.extend(entry(330, 13, 62));
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        raw.extend_from_slice(&2u16.to_le_bytes());
+        raw.extend(entry(256, 3, 6000));
+        raw.extend(entry(257, 3, 4000));
+        raw.extend_from_slice(&0u32.to_le_bytes());
+
+        let probe = probe_image(&raw).unwrap();
+        assert_eq!(probe.format, ProbeFormat::Raw);
+        assert_eq!((probe.width, probe.height), (6000, 4000));
+        assert_eq!(probe.exif_orientation, Some(8));
+    }
+}
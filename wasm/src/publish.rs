@@ -0,0 +1,777 @@
+//! ページ画像・PDFのディレクトリをタイル化して出力する（ネイティブのみ）
+//!
+//! タイル化・ハッシュ・metadataの生成はブラウザと同じで、出力の配置は既定ではR2の `pamphlets/{id}/`
+//! と同じ（`tiles/{hash}.webp` と `metadata.json`）。`parallel` が有効ならファイルごとに並列にタイル化する
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{PageOverride, PamphletConfig};
+use crate::hasher::calculate_hash;
+use crate::layout::TileLayout;
+use crate::pages::{is_page_file, natural_cmp};
+use crate::pamphlet::{self, InputPages, PamphletAssembler};
+use crate::pdf::DEFAULT_RASTER_DPI;
+use crate::preprocess::PreprocessOptions;
+use crate::tiler::{pixel_hashes, TileOptions, TileWarning, TilerContext};
+
+/// タイルの画素のハッシュとタイルのハッシュの対応を書き出すファイル（`plan_files` が読む）
+pub(crate) const TILE_INDEX: &str = ".tile-index.json";
+
+/// ディレクトリのタイル化のオプション
+#[derive(Debug, Clone)]
+pub struct DirectoryOptions {
+    /// 出力先のディレクトリ（`tiles/` と `metadata.json` を作る）
+    pub output: PathBuf,
+    /// タイルサイズ・WebP品質など
+    pub tile: TileOptions,
+    /// PDFをラスタライズする解像度（DPI）
+    pub dpi: f32,
+    /// 並列にタイル化するスレッドの数（`None` はCPUの数）
+    pub jobs: Option<usize>,
+    /// タイルの出力の配置（`flat` 以外はmetadataの `tile_layout` に書く）
+    pub layout: TileLayout,
+    /// タイル化の前の前処理
+    pub preprocess: PreprocessOptions,
+    /// ファイルごとの設定（ファイル名が最初に合ったものを使う）
+    pub overrides: Vec<PageOverride>,
+}
+
+impl DirectoryOptions {
+    pub fn new(output: impl Into<PathBuf>) -> Self {
+        Self {
+            output: output.into(),
+            tile: TileOptions::default(),
+            dpi: DEFAULT_RASTER_DPI,
+            jobs: None,
+            layout: TileLayout::Flat,
+            preprocess: PreprocessOptions::default(),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// 設定ファイルに書かれた項目を反映する（出力先は変えない）
+    pub fn with_config(self, config: &PamphletConfig) -> Self {
+        Self {
+            tile: TileOptions {
+                tile_size: config.tile_size.unwrap_or(self.tile.tile_size),
+                quality: config.quality.or(self.tile.quality),
+                format: config.format.unwrap_or(self.tile.format),
+                padding: config.padding.unwrap_or(self.tile.padding),
+            },
+            dpi: config.dpi.unwrap_or(self.dpi),
+            jobs: config.jobs.or(self.jobs),
+            layout: config.layout.unwrap_or(self.layout),
+            preprocess: config.preprocess.clone(),
+            overrides: config.pages.clone(),
+            ..self
+        }
+    }
+
+    /// ファイルごとの設定を反映した、`file` のタイル化のオプション
+    pub fn for_file(&self, file: &Path) -> Self {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let Some(page) = self.overrides.iter().find(|page| page.matches(&name)) else {
+            return self.clone();
+        };
+        Self {
+            tile: TileOptions {
+                quality: page.quality.or(self.tile.quality),
+                ..self.tile.clone()
+            },
+            dpi: page.dpi.unwrap_or(self.dpi),
+            preprocess: page
+                .preprocess
+                .clone()
+                .unwrap_or_else(|| self.preprocess.clone()),
+            overrides: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// 出力したパンフレットの概要
+#[derive(Debug, Clone, PartialEq)]
+pub struct Publication {
+    /// 書き出したmetadata.jsonの内容
+    pub metadata: serde_json::Value,
+    pub pages: usize,
+    pub tiles: usize,
+    /// 新しく書き出したタイル（同じ内容のタイル・出力先に既にあるタイルは書き出さない）
+    pub written: usize,
+    /// タイル化は続けた問題（ファイル名付き）
+    pub warnings: Vec<(PathBuf, TileWarning)>,
+}
+
+/// 1ファイル分の結果（タイルのデータは書き出して捨てたもの。配置のパスにページ番号を含む
+/// 場合は、ページ番号が決まって `publish` で書き出すまで残す）
+struct FilePages {
+    input: InputPages,
+    written: usize,
+    /// タイルの画素のハッシュとタイルのハッシュ
+    index: Vec<(String, String)>,
+}
+
+/// ディレクトリのページ画像・PDFをタイル化し、タイルとmetadata.jsonを書き出す
+///
+/// ページは直下のファイル名の自然順（`2.jpg` が `10.jpg` より前）に並べる
+///
+/// # Errors
+/// ページが1つも無い場合、いずれかのファイルの読み込み・タイル化・書き出しに失敗した場合
+pub fn tile_directory(path: &Path, options: &DirectoryOptions) -> Result<Publication, String> {
+    tile_files(&page_files(path)?, options)
+}
+
+/// ディレクトリの直下のページ画像・PDFを、ファイル名の自然順に並べる
+///
+/// # Errors
+/// ディレクトリを読めない場合
+pub fn page_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let error = |e: std::io::Error| format!("Failed to read {}: {}", path.display(), e);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path).map_err(error)? {
+        let file = entry.map_err(error)?.path();
+        let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if file.is_file() && is_page_file(name) {
+            files.push(file);
+        }
+    }
+    files.sort_by(|a, b| {
+        let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+        natural_cmp(&a, &b).then_with(|| a.cmp(&b))
+    });
+    Ok(files)
+}
+
+/// ファイルを並べた順にページとしてタイル化し、タイルとmetadata.jsonを書き出す
+///
+/// ファイルとページのタイルは並列にタイル化し、タイルはできたものから書き出す
+/// （ページの順は `files` のまま）。スレッドの数は `options.jobs`。配置（`options.layout`）の
+/// パスにページ番号を含む場合は、すべてのファイルをタイル化してから書き出す
+///
+/// # Errors
+/// `files` が空の場合、いずれかのファイルの読み込み・タイル化・書き出しに失敗した場合
+pub fn tile_files(files: &[PathBuf], options: &DirectoryOptions) -> Result<Publication, String> {
+    if files.is_empty() {
+        return Err("No page images or PDFs found".to_string());
+    }
+    create_tiles_dir(&options.output)?;
+
+    let results: Vec<FilePages> =
+        map_jobs(options.jobs, files, TilerContext::new, |context, file| {
+            tile_file(file, context, options).map_err(|e| format!("{}: {}", file.display(), e))
+        })?
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+
+    let written = results.iter().map(|result| result.written).sum();
+    let index = results
+        .iter()
+        .flat_map(|result| result.index.iter().cloned())
+        .collect();
+    let inputs = files
+        .iter()
+        .cloned()
+        .zip(results.into_iter().map(|result| result.input));
+    publish(options, inputs, written, index)
+}
+
+/// 変更されたファイルだけをタイル化し直す（`pamphlet-tiler watch`）
+///
+/// ファイルの内容のハッシュとタイル化したページ（タイルのデータは除く）を覚えておき、
+/// 内容が変わったファイルだけをタイル化し直す。変わらなかったタイルは書き直さない
+/// （配置のパスにページ番号を含む場合は、ページがずれても書き出せるようにタイルのデータも覚えておく）
+#[derive(Debug)]
+pub struct IncrementalTiler {
+    options: DirectoryOptions,
+    sources: HashMap<PathBuf, Source>,
+    /// 前回書き出したmetadataのファイルの順
+    files: Vec<PathBuf>,
+}
+
+/// タイル化したファイル
+#[derive(Debug)]
+struct Source {
+    /// ファイルの大きさと更新日時（変わっていなければ内容を読まない）
+    stamp: (u64, Option<SystemTime>),
+    /// ファイルの内容のハッシュ
+    hash: String,
+    input: InputPages,
+    index: Vec<(String, String)>,
+}
+
+/// `IncrementalTiler::update` で書き出したパンフレット
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub publication: Publication,
+    /// タイル化し直したファイル（ファイルの並びが変わっただけの場合は空）
+    pub retiled: Vec<PathBuf>,
+}
+
+impl IncrementalTiler {
+    pub fn new(options: DirectoryOptions) -> Self {
+        Self {
+            options,
+            sources: HashMap::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// `files` を前回と比べ、内容が変わったファイルだけをタイル化し直してmetadata.jsonを書き直す
+    ///
+    /// 大きさか更新日時が変わったファイルだけを読み、内容のハッシュが変わっていれば並列に
+    /// タイル化し直す。ファイルの内容・並び・有無のいずれも変わっていなければ何も書き出さずに `None`
+    ///
+    /// # Errors
+    /// `files` が空の場合、いずれかのファイルの読み込み・タイル化・書き出しに失敗した場合
+    /// （失敗した場合は前回の状態のままで、次の呼び出しでもう一度確かめる）
+    pub fn update(&mut self, files: &[PathBuf]) -> Result<Option<Update>, String> {
+        if files.is_empty() {
+            return Err("No page images or PDFs found".to_string());
+        }
+        let mut candidates = Vec::new();
+        for file in files {
+            let stamp = file_stamp(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            if self
+                .sources
+                .get(file)
+                .is_none_or(|source| source.stamp != stamp)
+            {
+                candidates.push((file, stamp));
+            }
+        }
+        create_tiles_dir(&self.options.output)?;
+
+        // 変わった可能性のあるファイルが無ければスレッドを用意しない
+        let results: Vec<_> = if candidates.is_empty() {
+            Vec::new()
+        } else {
+            map_jobs(
+                self.options.jobs,
+                &candidates,
+                TilerContext::new,
+                |context, &(file, stamp)| {
+                    let (hash, pages) = self
+                        .retile(file, context)
+                        .map_err(|e| format!("{}: {}", file.display(), e))?;
+                    Ok((file, stamp, hash, pages))
+                },
+            )?
+            .into_iter()
+            .collect::<Result<_, String>>()?
+        };
+
+        let mut retiled = Vec::new();
+        let mut written = 0;
+        for (file, stamp, hash, pages) in results {
+            match pages {
+                Some(pages) => {
+                    written += pages.written;
+                    retiled.push(file.clone());
+                    let (input, index) = (pages.input, pages.index);
+                    self.sources.insert(
+                        file.clone(),
+                        Source {
+                            stamp,
+                            hash,
+                            input,
+                            index,
+                        },
+                    );
+                }
+                // 内容が同じ（更新日時だけが変わった）
+                None => {
+                    if let Some(source) = self.sources.get_mut(file) {
+                        source.stamp = stamp;
+                    }
+                }
+            }
+        }
+        self.sources.retain(|file, _| files.contains(file));
+        if retiled.is_empty() && self.files == files {
+            return Ok(None);
+        }
+
+        let inputs = files
+            .iter()
+            .map(|file| (file.clone(), self.sources[file].input.clone()));
+        let index = files
+            .iter()
+            .flat_map(|file| self.sources[file].index.iter().cloned())
+            .collect();
+        let publication = publish(&self.options, inputs, written, index)?;
+        self.files = files.to_vec();
+        Ok(Some(Update {
+            publication,
+            retiled,
+        }))
+    }
+
+    /// ファイルの内容のハッシュと、内容が前回と違えばタイル化したページ
+    fn retile(
+        &self,
+        file: &Path,
+        context: &mut TilerContext,
+    ) -> Result<(String, Option<FilePages>), String> {
+        let data = fs::read(file).map_err(|e| format!("Failed to read: {}", e))?;
+        let hash = calculate_hash(&data);
+        if self
+            .sources
+            .get(file)
+            .is_some_and(|source| source.hash == hash)
+        {
+            return Ok((hash, None));
+        }
+        let pages = tile_data(file, data, context, &self.options)?;
+        Ok((hash, Some(pages)))
+    }
+}
+
+/// `items` をそれぞれ `f` で処理する（結果は `items` の順）
+///
+/// `parallel` が有効なら `jobs` スレッド（`None` はrayonの既定のスレッド数）で並列に処理し、
+/// スレッドごとに `init` の状態を持つ。無効なら `jobs` によらず1つずつ処理する
+pub(crate) fn map_jobs<T: Sync, S, R: Send>(
+    jobs: Option<usize>,
+    items: &[T],
+    init: impl Fn() -> S + Send + Sync,
+    f: impl Fn(&mut S, &T) -> R + Send + Sync,
+) -> Result<Vec<R>, String> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        let map = || {
+            items
+                .par_iter()
+                .map_init(&init, |state, item| f(state, item))
+                .collect()
+        };
+        let Some(jobs) = jobs else {
+            return Ok(map());
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.max(1))
+            .build()
+            .map_err(|e| format!("Failed to start {} threads: {}", jobs, e))?;
+        Ok(pool.install(map))
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        let _ = jobs;
+        let mut state = init();
+        Ok(items.iter().map(|item| f(&mut state, item)).collect())
+    }
+}
+
+/// ファイルの大きさと更新日時
+fn file_stamp(file: &Path) -> Result<(u64, Option<SystemTime>), String> {
+    let metadata = fs::metadata(file).map_err(|e| format!("Failed to read: {}", e))?;
+    Ok((metadata.len(), metadata.modified().ok()))
+}
+
+/// 出力先に `tiles/` を作る
+fn create_tiles_dir(output: &Path) -> Result<(), String> {
+    let tiles_dir = output.join("tiles");
+    fs::create_dir_all(&tiles_dir)
+        .map_err(|e| format!("Failed to create {}: {}", tiles_dir.display(), e))
+}
+
+/// ファイルのページを読み順にmetadataにまとめ、metadata.jsonを書き出す
+///
+/// 配置のパスにページ番号を含む場合は、ここでタイルを書き出す（`written` に足す）。
+/// タイルの画素のハッシュとタイルのハッシュの対応（`index`）も書き出す
+fn publish(
+    options: &DirectoryOptions,
+    inputs: impl IntoIterator<Item = (PathBuf, InputPages)>,
+    mut written: usize,
+    index: BTreeMap<String, String>,
+) -> Result<Publication, String> {
+    let layout = options.layout;
+    let mut assembler = PamphletAssembler::new();
+    assembler.set_tile_layout(layout);
+    let mut warnings = Vec::new();
+    for (file, mut input) in inputs {
+        if layout.uses_page() {
+            let first_page = assembler.page_count() as u32;
+            for (page, (result, _)) in (first_page..).zip(&mut input.pages) {
+                for tile in &mut result.tiles {
+                    let path = layout.tile_path(page, tile.x, tile.y, &tile.hash);
+                    if write_tile(&options.output, &path, &tile.data, layout)? {
+                        written += 1;
+                    }
+                    tile.data = Vec::new();
+                }
+            }
+        }
+        for (page, _) in &input.pages {
+            warnings.extend(
+                page.warnings
+                    .iter()
+                    .map(|warning| (file.clone(), warning.clone())),
+            );
+        }
+        assembler.add(input)?;
+    }
+    let (pages, tiles) = (assembler.page_count(), assembler.tile_count());
+
+    let metadata = assembler.build()?;
+    let index = TileIndex {
+        options: options.tile.clone(),
+        tiles: index,
+    };
+    write_json(&options.output, TILE_INDEX, &index)?;
+    write_json(&options.output, "metadata.json", &metadata)?;
+    Ok(Publication {
+        metadata,
+        pages,
+        tiles,
+        written,
+        warnings,
+    })
+}
+
+/// タイルの画素のハッシュとタイルのハッシュの対応（`.tile-index.json`）
+///
+/// 画素と設定が同じタイルはエンコードしても同じデータになるため、`plan_files` はこの対応で
+/// エンコードせずにタイルのハッシュを求める
+#[derive(Debug, Serialize, Deserialize)]
+struct TileIndex {
+    /// タイル化の設定（設定が変わった場合は対応を使わない）
+    options: TileOptions,
+    tiles: BTreeMap<String, String>,
+}
+
+/// `.tile-index.json` のキー（ファイルごとの設定で品質が変わったタイルは、品質を付けて区別する）
+pub(crate) fn tile_index_key(pixel: &str, options: &TileOptions, file: &TileOptions) -> String {
+    match file.quality == options.quality {
+        true => pixel.to_string(),
+        false => format!("{}@{}", pixel, file.quality.unwrap_or_default()),
+    }
+}
+
+/// 出力先の `.tile-index.json` を読み、画素のハッシュとタイルのハッシュの対応を返す
+///
+/// ファイルが無い・読めない場合、タイル化の設定が `options` と異なる場合は空
+pub(crate) fn read_tile_index(output: &Path, options: &TileOptions) -> BTreeMap<String, String> {
+    fs::read(output.join(TILE_INDEX))
+        .ok()
+        .and_then(|json| serde_json::from_slice::<TileIndex>(&json).ok())
+        .filter(|index| index.options == *options)
+        .map(|index| index.tiles)
+        .unwrap_or_default()
+}
+
+/// JSONのファイル（metadata.jsonなど）を書き出す
+///
+/// 一時ファイルに書いてから置き換え、読み込み中のビューアに書きかけの内容を見せない
+fn write_json(output: &Path, name: &str, value: &impl Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    let path = output.join(name);
+    let temp = output.join(format!(".{}.tmp", name.trim_start_matches('.')));
+    let error = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    fs::write(&temp, json).map_err(error)?;
+    fs::rename(&temp, &path).map_err(error)
+}
+
+/// 1ファイルをタイル化し、タイルを書き出す（タイルのデータは書き出したら捨てる）
+fn tile_file(
+    file: &Path,
+    context: &mut TilerContext,
+    options: &DirectoryOptions,
+) -> Result<FilePages, String> {
+    let data = fs::read(file).map_err(|e| format!("Failed to read: {}", e))?;
+    tile_data(file, data, context, options)
+}
+
+/// 1ファイルの内容を前処理してタイル化し、タイルを書き出す（配置のパスにページ番号を含む
+/// 場合は書き出さない）。ファイルごとの設定は `file` のファイル名で選ぶ
+fn tile_data(
+    file: &Path,
+    data: Vec<u8>,
+    context: &mut TilerContext,
+    options: &DirectoryOptions,
+) -> Result<FilePages, String> {
+    let file_options = options.for_file(file);
+    let tile = &file_options.tile;
+    let settings = context.settings().resolve();
+    let (pages, toc) = pamphlet::map_preprocessed_pages(
+        data,
+        file_options.dpi,
+        &file_options.preprocess,
+        &settings,
+        |image, geometry| {
+            let result = context.tile_decoded_with(&image, tile)?;
+            let pixels = pixel_hashes(&image, tile.tile_size)?;
+            Ok(((result, geometry), pixels))
+        },
+    )?;
+    let index = pages
+        .iter()
+        .flat_map(|((result, _), pixels)| {
+            pixels
+                .iter()
+                .map(|pixel| tile_index_key(pixel, &options.tile, tile))
+                .zip(result.tiles.iter().map(|tile| tile.hash.clone()))
+        })
+        .collect();
+    let pages = pages.into_iter().map(|(page, _)| page).collect();
+    let mut input = InputPages { pages, toc };
+
+    let layout = options.layout;
+    let mut written = 0;
+    if !layout.uses_page() {
+        for (page, _) in &mut input.pages {
+            for tile in &mut page.tiles {
+                let path = layout.tile_path(0, tile.x, tile.y, &tile.hash);
+                if write_tile(&options.output, &path, &tile.data, layout)? {
+                    written += 1;
+                }
+                tile.data = Vec::new();
+            }
+        }
+    }
+    Ok(FilePages {
+        input,
+        written,
+        index,
+    })
+}
+
+/// タイルを出力先の `path` に書き出す（同じ内容のファイルが既にある場合は書き出さずに `false`）
+///
+/// パスにハッシュを含む配置では、並列に同じ内容のタイルを書き出そうとしても、ファイルを
+/// 作れた1つだけが書き出す。含まない配置（`zxy`）では、内容が変わった場合だけ書き直す
+fn write_tile(output: &Path, path: &str, data: &[u8], layout: TileLayout) -> Result<bool, String> {
+    let path = output.join(path);
+    let error = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(error)?;
+    }
+    if !layout.is_content_addressed() {
+        if fs::read(&path).is_ok_and(|existing| existing == data) {
+            return Ok(false);
+        }
+        fs::write(&path, data).map_err(error)?;
+        return Ok(true);
+    }
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(error(e)),
+    };
+    file.write_all(data).map_err(error)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::png;
+
+    /// テストごとの作業用ディレクトリ（前回の実行の残りは消す）
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tile-wasm-publish-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_page_files() {
+        let dir = temp_dir("page-files");
+        for name in ["10.png", "2.JPG", "1.pdf", "notes.txt", ".3.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        fs::create_dir(dir.join("4.png")).unwrap();
+        let files = page_files(&dir).unwrap();
+        assert_eq!(
+            files,
+            [dir.join("1.pdf"), dir.join("2.JPG"), dir.join("10.png")]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tile_directory() {
+        let dir = temp_dir("tile-directory");
+        let pages = dir.join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("p10.png"), png(20, 20, 9)).unwrap();
+        // 同じ画像のページはタイルを共有する
+        fs::write(pages.join("p1.png"), png(40, 20, 0)).unwrap();
+        fs::write(pages.join("p2.png"), png(40, 20, 0)).unwrap();
+
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, None),
+            ..DirectoryOptions::new(dir.join("out"))
+        };
+        let publication = tile_directory(&pages, &options).unwrap();
+        assert_eq!(
+            (publication.pages, publication.tiles, publication.written),
+            (3, 5, 3)
+        );
+
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("out/metadata.json")).unwrap()).unwrap();
+        assert_eq!(metadata, publication.metadata);
+        assert_eq!(metadata["tile_size"], 32);
+        // 自然順で `p10.png` は最後のページ
+        assert_eq!(metadata["pages"][2]["width"], 20);
+        let hash = metadata["pages"][2]["tiles"][0]["hash"].as_str().unwrap();
+        let tile = fs::read(dir.join("out/tiles").join(format!("{}.webp", hash))).unwrap();
+        assert_eq!(&tile[..4], b"RIFF");
+
+        // もう一度出力しても、既にあるタイルは書き出さない
+        let publication = tile_directory(&pages, &options).unwrap();
+        assert_eq!(publication.written, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tile_directory_layouts() {
+        let dir = temp_dir("tile-directory-layouts");
+        let pages = dir.join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("p1.png"), png(40, 20, 0)).unwrap();
+        fs::write(pages.join("p2.png"), png(40, 20, 0)).unwrap();
+
+        // 同じ内容のタイルは、パスにページ番号を含む配置ではページごとに書き出す
+        for (layout, written) in [
+            (TileLayout::Flat, 2),
+            (TileLayout::Sharded, 2),
+            (TileLayout::Page, 4),
+            (TileLayout::Zxy, 4),
+        ] {
+            let output = dir.join(layout.name());
+            let options = DirectoryOptions {
+                tile: TileOptions::new(32, None),
+                layout,
+                ..DirectoryOptions::new(&output)
+            };
+            let publication = tile_directory(&pages, &options).unwrap();
+            assert_eq!(publication.written, written, "{:?}", layout);
+            let metadata = &publication.metadata;
+            assert_eq!(TileLayout::from_metadata(metadata), Ok(layout));
+            for (page, info) in metadata["pages"].as_array().unwrap().iter().enumerate() {
+                for tile in info["tiles"].as_array().unwrap() {
+                    let path = layout.tile_path(
+                        page as u32,
+                        tile["x"].as_u64().unwrap() as u32,
+                        tile["y"].as_u64().unwrap() as u32,
+                        tile["hash"].as_str().unwrap(),
+                    );
+                    assert_eq!(&fs::read(output.join(&path)).unwrap()[..4], b"RIFF");
+                }
+            }
+
+            let publication = tile_directory(&pages, &options).unwrap();
+            assert_eq!(publication.written, 0, "{:?}", layout);
+        }
+
+        // `zxy` ではページの内容が変わったタイルを書き直す
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, None),
+            layout: TileLayout::Zxy,
+            ..DirectoryOptions::new(dir.join("zxy"))
+        };
+        fs::write(pages.join("p2.png"), png(40, 20, 9)).unwrap();
+        assert_eq!(tile_directory(&pages, &options).unwrap().written, 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tile_directory_config() {
+        let dir = temp_dir("tile-directory-config");
+        let pages = dir.join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("1.png"), png(40, 20, 0)).unwrap();
+        fs::write(pages.join("spread.png"), png(40, 20, 0)).unwrap();
+
+        // 見開きのファイルだけを分割し、ページの範囲をmetadataに書く
+        let config = PamphletConfig::from_json(
+            r#"{ "tile_size": 32, "pages": [{ "match": "spread.*", "quality": 50, "preprocess": { "split": {} } }] }"#,
+        )
+        .unwrap();
+        let options = DirectoryOptions::new(dir.join("out")).with_config(&config);
+        assert_eq!(options.tile, TileOptions::new(32, None));
+        let spread = options.for_file(&pages.join("spread.png"));
+        assert_eq!(spread.tile.quality, Some(50.0));
+        assert!(spread.preprocess.split.is_some());
+
+        let publication = tile_directory(&pages, &options).unwrap();
+        assert_eq!(publication.pages, 3);
+        let metadata = &publication.metadata;
+        assert!(metadata["pages"][0].get("crop").is_none());
+        assert_eq!(metadata["pages"][1]["crop"]["x"], 0);
+        assert!(metadata["pages"][2]["crop"]["x"].as_u64().unwrap() > 0);
+
+        // 品質を変えたファイルのタイルは、同じ画素でも別のタイルとして計画する
+        let index = read_tile_index(&options.output, &options.tile);
+        assert!(index.keys().any(|key| key.ends_with("@50")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_tiler() {
+        let dir = temp_dir("incremental");
+        let pages = [dir.join("p1.png"), dir.join("p2.png")];
+        fs::write(&pages[0], png(40, 20, 0)).unwrap();
+        fs::write(&pages[1], png(20, 20, 9)).unwrap();
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, None),
+            ..DirectoryOptions::new(dir.join("out"))
+        };
+        let mut tiler = IncrementalTiler::new(options);
+        let update = tiler.update(&pages).unwrap().unwrap();
+        assert_eq!(update.retiled, pages);
+        assert_eq!((update.publication.pages, update.publication.tiles), (2, 3));
+        assert_eq!(tiler.update(&pages).unwrap(), None);
+
+        // 内容が変わったファイルだけをタイル化し直す
+        fs::write(&pages[1], png(20, 20, 200)).unwrap();
+        let update = tiler.update(&pages).unwrap().unwrap();
+        assert_eq!(update.retiled, [pages[1].clone()]);
+        assert_eq!(update.publication.written, 1);
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("out/metadata.json")).unwrap()).unwrap();
+        assert_eq!(metadata, update.publication.metadata);
+        assert!(!dir.join("out/.metadata.json.tmp").exists());
+
+        // 並びだけが変わった場合はタイル化せずにmetadataを書き直す
+        let reversed = [pages[1].clone(), pages[0].clone()];
+        let update = tiler.update(&reversed).unwrap().unwrap();
+        assert!(update.retiled.is_empty());
+        assert_eq!(update.publication.metadata["pages"][0]["width"], 20);
+
+        fs::remove_file(&pages[0]).unwrap();
+        assert!(tiler.update(&reversed).is_err());
+        assert!(tiler.update(&reversed[..1]).unwrap().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tile_directory_errors() {
+        let dir = temp_dir("tile-directory-errors");
+        let options = DirectoryOptions::new(dir.join("out"));
+        assert!(tile_directory(&dir.join("missing"), &options).is_err());
+        assert_eq!(
+            tile_directory(&dir, &options).unwrap_err(),
+            "No page images or PDFs found"
+        );
+
+        fs::write(dir.join("1.png"), b"broken").unwrap();
+        let error = tile_directory(&dir, &options).unwrap_err();
+        assert!(error.contains("1.png: "), "{}", error);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+use std::io::Cursor;
+
+use image::{imageops, DynamicImage, RgbImage};
+use rawloader::{Orientation, RawImage, RawImageData};
+
+/// sRGB（D65）からXYZへの変換行列
+const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.412453, 0.357580, 0.180423],
+    [0.212671, 0.715160, 0.072169],
+    [0.019334, 0.119193, 0.950227],
+];
+
+/// カメラRAW（CR2/NEF/ARWなど）を現像する
+///
+/// 黒レベル・白レベルで正規化し、カメラが記録したホワイトバランスを掛けてから、
+/// バイリニア補間でデモザイクし、カメラの色空間からsRGBに変換する。
+/// 有効領域への切り抜きと、撮影時の向きの補正も行う。
+///
+/// # Errors
+/// RAWとして解釈できない場合や、対応していないカメラの場合
+pub fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+    let raw = rawloader::decode(&mut Cursor::new(data))
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    develop(&raw).map(DynamicImage::ImageRgb8)
+}
+
+/// RAWの画素データをsRGBの画像にする
+fn develop(raw: &RawImage) -> Result<RgbImage, String> {
+    let [top, right, bottom, left] = raw.crops;
+    let width = raw.width.saturating_sub(left + right);
+    let height = raw.height.saturating_sub(top + bottom);
+    if width == 0 || height == 0 || raw.cpp == 0 {
+        return Err("Failed to decode image: empty RAW image".to_string());
+    }
+    if raw.width * raw.height * raw.cpp > sample_count(&raw.data) {
+        return Err("Failed to decode image: truncated RAW data".to_string());
+    }
+
+    let wb = white_balance(raw);
+    let cam_to_rgb = cam_to_rgb(raw);
+    let mosaic = raw.cpp == 1 && raw.cfa.is_valid();
+
+    // 正規化した値（0.0〜1.0、ホワイトバランス適用済み）
+    let sample = |row: usize, col: usize, plane: usize| -> f32 {
+        let color = if mosaic {
+            raw.cfa.color_at(row, col)
+        } else {
+            plane
+        };
+        let index = (row * raw.width + col) * raw.cpp + plane;
+        let value = match &raw.data {
+            RawImageData::Integer(data) => data[index] as f32,
+            RawImageData::Float(data) => data[index],
+        };
+        let black = raw.blacklevels[color] as f32;
+        let white = raw.whitelevels[color] as f32;
+        let range = (white - black).max(1.0);
+        ((value - black) / range).max(0.0) * wb[color]
+    };
+
+    let mut image = RgbImage::new(width as u32, height as u32);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (row, col) = (y as usize + top, x as usize + left);
+
+        let cam = if mosaic {
+            demosaic(raw, row, col, top, left, width, height, &sample)
+        } else if raw.cpp >= 3 {
+            [
+                sample(row, col, 0),
+                sample(row, col, 1),
+                sample(row, col, 2),
+            ]
+        } else {
+            let value = sample(row, col, 0);
+            [value; 3]
+        };
+
+        let mut rgb = [0.0f32; 3];
+        for (out, matrix) in rgb.iter_mut().zip(&cam_to_rgb) {
+            *out = matrix.iter().zip(&cam).map(|(m, c)| m * c).sum();
+        }
+        pixel.0 = rgb.map(encode_srgb);
+    }
+
+    Ok(orient(image, raw.orientation))
+}
+
+/// バイリニア補間で画素の3色（カメラの色空間）を求める
+///
+/// 周囲3x3のうち同じ色のフィルターの画素を平均する（4色目は緑として扱う）
+#[allow(clippy::too_many_arguments)]
+fn demosaic(
+    raw: &RawImage,
+    row: usize,
+    col: usize,
+    top: usize,
+    left: usize,
+    width: usize,
+    height: usize,
+    sample: &impl Fn(usize, usize, usize) -> f32,
+) -> [f32; 3] {
+    let own = color_channel(raw, row, col);
+    let mut sums = [0.0f32; 3];
+    let mut counts = [0u32; 3];
+    for dy in -1i64..=1 {
+        for dx in -1i64..=1 {
+            let r = row as i64 + dy;
+            let c = col as i64 + dx;
+            if r < top as i64
+                || c < left as i64
+                || r >= (top + height) as i64
+                || c >= (left + width) as i64
+            {
+                continue;
+            }
+            let (r, c) = (r as usize, c as usize);
+            let channel = color_channel(raw, r, c);
+            // 自分と同じ色は自分の値だけを使う
+            if (dx != 0 || dy != 0) && channel == own {
+                continue;
+            }
+            sums[channel] += sample(r, c, 0);
+            counts[channel] += 1;
+        }
+    }
+
+    let mut cam = [0.0f32; 3];
+    for channel in 0..3 {
+        if counts[channel] > 0 {
+            cam[channel] = sums[channel] / counts[channel] as f32;
+        }
+    }
+    cam
+}
+
+/// フィルターの色（0 = 赤、1 = 緑、2 = 青）
+fn color_channel(raw: &RawImage, row: usize, col: usize) -> usize {
+    match raw.cfa.color_at(row, col) {
+        3 => 1,
+        color => color.min(2),
+    }
+}
+
+fn sample_count(data: &RawImageData) -> usize {
+    match data {
+        RawImageData::Integer(data) => data.len(),
+        RawImageData::Float(data) => data.len(),
+    }
+}
+
+/// ホワイトバランスの係数（緑を1とする）
+///
+/// ファイルに記録されていない場合はD65の中立な係数を使う
+fn white_balance(raw: &RawImage) -> [f32; 4] {
+    let coeffs = if raw.wb_coeffs[..3].iter().all(|c| c.is_finite() && *c > 0.0) {
+        raw.wb_coeffs
+    } else {
+        raw.neutralwb()
+    };
+    if !(coeffs[1].is_finite() && coeffs[1] > 0.0) {
+        return [1.0; 4];
+    }
+
+    let green = coeffs[1];
+    let mut wb = coeffs.map(|c| c / green);
+    if !(wb[3].is_finite() && wb[3] > 0.0) {
+        // 4色目（2つ目の緑）は緑と同じ
+        wb[3] = wb[1];
+    }
+    wb
+}
+
+/// カメラの色空間からsRGB（リニア）への変換行列
+///
+/// 白（ホワイトバランス後の (1, 1, 1)）が白に変換されるよう正規化する。
+/// 色変換行列が無い場合は変換しない。
+fn cam_to_rgb(raw: &RawImage) -> [[f32; 3]; 3] {
+    const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    if raw.xyz_to_cam[..3].iter().flatten().all(|v| *v == 0.0) {
+        return IDENTITY;
+    }
+
+    let mut rgb_to_cam = [[0.0f32; 3]; 4];
+    for (i, row) in rgb_to_cam.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3)
+                .map(|k| raw.xyz_to_cam[i][k] * SRGB_TO_XYZ[k][j])
+                .sum();
+        }
+        let sum: f32 = row.iter().sum();
+        if sum != 0.0 {
+            for value in row.iter_mut() {
+                *value /= sum;
+            }
+        }
+    }
+
+    let inverse = RawImage::pseudoinverse(rgb_to_cam);
+    // 4色目は緑として補間しているため、その列は緑にまとめる
+    let matrix = inverse.map(|row| [row[0], row[1] + row[3], row[2]]);
+    if matrix.iter().flatten().all(|v| v.is_finite()) {
+        matrix
+    } else {
+        IDENTITY
+    }
+}
+
+/// リニアの値をsRGBのガンマで8bitにする
+fn encode_srgb(linear: f32) -> u8 {
+    let linear = linear.clamp(0.0, 1.0);
+    let encoded = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+/// 撮影時の向きに合わせる（反転してから転置する）
+fn orient(image: RgbImage, orientation: Orientation) -> RgbImage {
+    let (transpose, flip_horizontal, flip_vertical) = orientation.to_flips();
+    let mut image = image;
+    if flip_horizontal {
+        imageops::flip_horizontal_in_place(&mut image);
+    }
+    if flip_vertical {
+        imageops::flip_vertical_in_place(&mut image);
+    }
+    if transpose {
+        image = imageops::flip_horizontal(&imageops::rotate90(&image));
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rawloader::CFA;
+
+    /// RGGBのベイヤー配列のRAW（黒レベル0、白レベル1000、ホワイトバランスは等倍）
+    fn bayer(width: usize, height: usize, values: [u16; 3]) -> RawImage {
+        let cfa = CFA::new("RGGB");
+        let data = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| match cfa.color_at(row, col) {
+                3 => values[1],
+                color => values[color],
+            })
+            .collect();
+
+        RawImage {
+            make: String::new(),
+            model: String::new(),
+            clean_make: String::new(),
+            clean_model: String::new(),
+            width,
+            height,
+            cpp: 1,
+            wb_coeffs: [1.0, 1.0, 1.0, f32::NAN],
+            whitelevels: [1000; 4],
+            blacklevels: [0; 4],
+            xyz_to_cam: [[0.0; 3]; 4],
+            cfa,
+            crops: [0; 4],
+            blackareas: Vec::new(),
+            orientation: Orientation::Normal,
+            data: RawImageData::Integer(data),
+        }
+    }
+
+    #[test]
+    fn test_develop_bayer() {
+        // 灰色は全ての画素で灰色になる
+        let image = develop(&bayer(6, 4, [500, 500, 500])).unwrap();
+        assert_eq!(image.dimensions(), (6, 4));
+        for pixel in image.pixels() {
+            assert_eq!(pixel.0, [encode_srgb(0.5); 3]);
+        }
+
+        // 赤だけの光は赤になる
+        let image = develop(&bayer(6, 4, [1000, 0, 0])).unwrap();
+        assert_eq!(image.get_pixel(2, 2).0, [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_develop_crop_and_orientation() {
+        let mut raw = bayer(8, 6, [1000, 1000, 1000]);
+        raw.crops = [2, 0, 0, 2];
+        raw.orientation = Orientation::Rotate90;
+
+        let image = develop(&raw).unwrap();
+        assert_eq!(image.dimensions(), (4, 6));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255]);
+
+        // 画素数が足りない
+        raw.data = RawImageData::Integer(vec![0; 10]);
+        assert!(develop(&raw).is_err());
+    }
+
+    #[test]
+    fn test_invalid_raw() {
+        assert!(decode(b"II*\0not a raw file").is_err());
+    }
+}
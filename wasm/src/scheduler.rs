@@ -0,0 +1,351 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Rect;
+use crate::TileMetadata;
+
+/// タイル取得の優先度（小さいほど先に取得する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchPriority {
+    /// viewport内のタイル
+    Visible,
+    /// viewportに隣接するタイル（1タイル分の外周）
+    Adjacent,
+    /// 先読み用のタイル
+    Prefetch,
+}
+
+/// 取得待ちのタイル
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchRequest {
+    pub x: u32,
+    pub y: u32,
+    pub hash: String,
+    pub priority: FetchPriority,
+}
+
+/// 取得待ちタイルの内部状態
+#[derive(Debug, Clone)]
+struct Pending {
+    request: FetchRequest,
+    /// 追加時に指定された優先度
+    requested: FetchPriority,
+    /// viewport中心からの距離（ページ座標）
+    distance: f64,
+    /// 追加順（同順位の場合のタイブレーク、ヒープ上の古いエントリの判定にも使う）
+    seq: u64,
+}
+
+/// ヒープに積むキー
+#[derive(Debug, Clone, PartialEq)]
+struct HeapKey {
+    priority: FetchPriority,
+    distance: f64,
+    seq: u64,
+    hash: String,
+}
+
+impl Eq for HeapKey {}
+
+impl Ord for HeapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeapは最大ヒープなので、優先すべきものが「大きい」よう逆順に比較
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| other.distance.total_cmp(&self.distance))
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for HeapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// タイル取得のスケジューラ
+///
+/// 優先度（visible > adjacent > prefetch）→ viewport中心からの距離 → 追加順で並べる。
+/// 同じハッシュのタイルは1回の取得で済むため、まとめて1件として扱う。
+#[derive(Debug)]
+pub struct FetchScheduler {
+    tile_size: u32,
+    /// 表示領域（ページ座標）
+    viewport: Option<Rect>,
+    pending: HashMap<String, Pending>,
+    heap: BinaryHeap<HeapKey>,
+    seq: u64,
+}
+
+impl FetchScheduler {
+    pub fn new(tile_size: u32) -> Self {
+        Self {
+            tile_size,
+            viewport: None,
+            pending: HashMap::new(),
+            heap: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    /// viewportを更新し、取得待ちのタイルを再優先度付けする
+    ///
+    /// # Arguments
+    /// * `viewport` - 表示領域（画面座標、ページ左上を原点とする）
+    /// * `zoom` - 表示倍率
+    pub fn set_viewport(&mut self, viewport: Rect, zoom: f64) {
+        if zoom <= 0.0 {
+            return;
+        }
+        self.viewport = Some(viewport.scale(1.0 / zoom));
+
+        for pending in self.pending.values_mut() {
+            let (priority, distance) = Self::classify(
+                self.viewport.as_ref(),
+                self.tile_size,
+                &pending.request,
+                pending.requested,
+            );
+            pending.request.priority = priority;
+            pending.distance = distance;
+        }
+        self.rebuild_heap();
+    }
+
+    /// タイルを取得待ちに追加する
+    ///
+    /// 同じハッシュが既にある場合は、高い方の優先度を採用する
+    pub fn push(&mut self, tile: &TileMetadata, priority: FetchPriority) {
+        let request = FetchRequest {
+            x: tile.x,
+            y: tile.y,
+            hash: tile.hash.clone(),
+            priority,
+        };
+
+        let requested = match self.pending.get(&tile.hash) {
+            Some(existing) => existing.requested.min(priority),
+            None => priority,
+        };
+        let (priority, distance) =
+            Self::classify(self.viewport.as_ref(), self.tile_size, &request, requested);
+
+        // 既存のエントリの方が優先される場合は、指定された優先度だけを更新する
+        if let Some(existing) = self.pending.get_mut(&tile.hash) {
+            let keep = existing
+                .request
+                .priority
+                .cmp(&priority)
+                .then_with(|| existing.distance.total_cmp(&distance))
+                .is_le();
+            if keep {
+                existing.requested = requested;
+                return;
+            }
+        }
+
+        self.seq += 1;
+        let pending = Pending {
+            request: FetchRequest {
+                priority,
+                ..request
+            },
+            requested,
+            distance,
+            seq: self.seq,
+        };
+        self.heap.push(Self::heap_key(&pending));
+        self.pending.insert(tile.hash.clone(), pending);
+    }
+
+    /// 次に取得すべきタイルを取り出す
+    pub fn pop_next(&mut self) -> Option<FetchRequest> {
+        while let Some(key) = self.heap.pop() {
+            // 再優先度付けや取り消しで古くなったエントリは読み飛ばす
+            let is_current = self
+                .pending
+                .get(&key.hash)
+                .is_some_and(|pending| pending.seq == key.seq);
+
+            if is_current {
+                return self.pending.remove(&key.hash).map(|p| p.request);
+            }
+        }
+        None
+    }
+
+    /// viewportにも隣接範囲にも入らなくなったタイルを取り消す
+    ///
+    /// 最初から `Prefetch` として追加されたタイルは取り消さない
+    ///
+    /// # Returns
+    /// 取り消したタイルのハッシュ
+    pub fn cancel_offscreen(&mut self) -> Vec<String> {
+        if self.viewport.is_none() {
+            return Vec::new();
+        }
+
+        let mut cancelled: Vec<(u64, String)> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| {
+                pending.request.priority == FetchPriority::Prefetch
+                    && pending.requested != FetchPriority::Prefetch
+            })
+            .map(|(hash, pending)| (pending.seq, hash.clone()))
+            .collect();
+        cancelled.sort();
+
+        for (_, hash) in &cancelled {
+            self.pending.remove(hash);
+        }
+        self.rebuild_heap();
+
+        cancelled.into_iter().map(|(_, hash)| hash).collect()
+    }
+
+    /// 指定したタイルを取り消す
+    pub fn cancel(&mut self, hash: &str) -> bool {
+        self.pending.remove(hash).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.heap.clear();
+    }
+
+    /// 取得待ちのタイル数
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// viewportとの位置関係から優先度と中心からの距離を求める
+    ///
+    /// viewport未設定の場合は指定された優先度をそのまま使う
+    fn classify(
+        viewport: Option<&Rect>,
+        tile_size: u32,
+        request: &FetchRequest,
+        requested: FetchPriority,
+    ) -> (FetchPriority, f64) {
+        let Some(viewport) = viewport else {
+            return (requested, 0.0);
+        };
+
+        let size = tile_size as f64;
+        let rect = Rect::new(request.x as f64 * size, request.y as f64 * size, size, size);
+        let distance = rect.center().distance(&viewport.center());
+
+        let adjacent = Rect::new(
+            viewport.x - size,
+            viewport.y - size,
+            viewport.width + size * 2.0,
+            viewport.height + size * 2.0,
+        );
+
+        let priority = if rect.intersects(viewport) {
+            FetchPriority::Visible
+        } else if rect.intersects(&adjacent) {
+            FetchPriority::Adjacent
+        } else {
+            FetchPriority::Prefetch
+        };
+
+        (priority, distance)
+    }
+
+    fn heap_key(pending: &Pending) -> HeapKey {
+        HeapKey {
+            priority: pending.request.priority,
+            distance: pending.distance,
+            seq: pending.seq,
+            hash: pending.request.hash.clone(),
+        }
+    }
+
+    fn rebuild_heap(&mut self) {
+        self.heap = self.pending.values().map(Self::heap_key).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(x: u32, y: u32) -> TileMetadata {
+        TileMetadata {
+            x,
+            y,
+            hash: format!("{}-{}", x, y),
+        }
+    }
+
+    #[test]
+    fn test_pop_order_by_priority_and_distance() {
+        let mut scheduler = FetchScheduler::new(100);
+        scheduler.set_viewport(Rect::new(0.0, 0.0, 300.0, 300.0), 1.0);
+
+        scheduler.push(&tile(9, 9), FetchPriority::Prefetch);
+        scheduler.push(&tile(3, 1), FetchPriority::Prefetch);
+        scheduler.push(&tile(0, 0), FetchPriority::Visible);
+        scheduler.push(&tile(1, 1), FetchPriority::Visible);
+
+        let order: Vec<(u32, u32, FetchPriority)> = std::iter::from_fn(|| scheduler.pop_next())
+            .map(|r| (r.x, r.y, r.priority))
+            .collect();
+
+        assert_eq!(
+            order,
+            vec![
+                (1, 1, FetchPriority::Visible),
+                (0, 0, FetchPriority::Visible),
+                (3, 1, FetchPriority::Adjacent),
+                (9, 9, FetchPriority::Prefetch),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reprioritize_and_cancel_offscreen() {
+        let mut scheduler = FetchScheduler::new(100);
+        scheduler.set_viewport(Rect::new(0.0, 0.0, 200.0, 200.0), 1.0);
+
+        scheduler.push(&tile(0, 0), FetchPriority::Visible);
+        scheduler.push(&tile(6, 0), FetchPriority::Prefetch);
+        scheduler.push(&tile(20, 0), FetchPriority::Prefetch);
+
+        // viewportが右へ移動すると優先度が入れ替わる
+        scheduler.set_viewport(Rect::new(500.0, 0.0, 200.0, 200.0), 1.0);
+        // 先読みとして追加したタイルは取り消されない
+        assert_eq!(scheduler.cancel_offscreen(), vec!["0-0".to_string()]);
+
+        let next = scheduler.pop_next().unwrap();
+        assert_eq!((next.x, next.priority), (6, FetchPriority::Visible));
+        let next = scheduler.pop_next().unwrap();
+        assert_eq!((next.x, next.priority), (20, FetchPriority::Prefetch));
+        assert!(scheduler.pop_next().is_none());
+        assert_eq!(scheduler.len(), 0);
+    }
+
+    #[test]
+    fn test_push_again_with_higher_priority() {
+        let mut scheduler = FetchScheduler::new(100);
+        scheduler.set_viewport(Rect::new(0.0, 0.0, 200.0, 200.0), 1.0);
+
+        // 先読みとして追加したタイルを、表示するタイルとして追加し直す
+        scheduler.push(&tile(20, 0), FetchPriority::Prefetch);
+        scheduler.push(&tile(20, 0), FetchPriority::Visible);
+        scheduler.push(&tile(30, 0), FetchPriority::Prefetch);
+        assert_eq!(scheduler.len(), 2);
+
+        // 表示するタイルとして追加し直したタイルは、viewportの外なら取り消される
+        assert_eq!(scheduler.cancel_offscreen(), vec!["20-0".to_string()]);
+        let next = scheduler.pop_next().unwrap();
+        assert_eq!((next.x, next.priority), (30, FetchPriority::Prefetch));
+        assert!(scheduler.pop_next().is_none());
+    }
+}
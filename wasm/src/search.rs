@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::binary::{self, Reader};
+use crate::geometry::Rect;
+
+/// シリアライズ形式の識別子
+const MAGIC: &[u8; 4] = b"PSIX";
+
+/// シリアライズ形式のバージョン
+const FORMAT_VERSION: u8 = 1;
+
+/// 単語とその位置（OCRやPDFから抽出したもの）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordBox {
+    pub text: String,
+    /// 位置（ページ座標、原寸ピクセル）
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// ページのテキスト
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageText {
+    pub page: u32,
+    pub words: Vec<WordBox>,
+}
+
+/// 検索結果（ページごと）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub page: u32,
+    /// 一致した単語の位置
+    pub rects: Vec<Rect>,
+}
+
+/// 索引に登録された単語
+#[derive(Debug, Clone, PartialEq)]
+struct IndexedWord {
+    page: u32,
+    /// 正規化済みのテキスト
+    text: String,
+    rect: Rect,
+}
+
+/// 全文検索用の転置索引
+///
+/// 単語を文字bigramで索引化するため、分かち書きされていない日本語でも部分一致で検索できる。
+/// バイト列には単語と位置だけを保存し、bigramの索引は読み込み時に再構築する。
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    words: Vec<IndexedWord>,
+    /// bigram（1文字の単語はその1文字）→ 単語のインデックス（昇順）
+    grams: HashMap<String, Vec<u32>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ページのテキストを索引に追加する
+    pub fn add_page(&mut self, page: &PageText) {
+        for word in &page.words {
+            let text = normalize(&word.text);
+            if text.is_empty() {
+                continue;
+            }
+
+            self.push_word(IndexedWord {
+                page: page.page,
+                text,
+                rect: Rect::new(word.x, word.y, word.width, word.height),
+            });
+        }
+    }
+
+    /// 語句を検索する
+    ///
+    /// 空白区切りの複数語は、全ての語を含むページだけを返す（AND検索）。
+    /// 結果はページ番号順で、一致した単語の位置を含む。
+    pub fn query(&self, query: &str) -> Vec<SearchHit> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(normalize)
+            .filter(|t| !t.is_empty())
+            .collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut pages: Option<BTreeMap<u32, Vec<Rect>>> = None;
+        for term in &terms {
+            let mut matched: BTreeMap<u32, Vec<Rect>> = BTreeMap::new();
+            for index in self.candidates(term) {
+                let word = &self.words[index as usize];
+                if word.text.contains(term.as_str()) {
+                    matched.entry(word.page).or_default().push(word.rect);
+                }
+            }
+
+            pages = Some(match pages {
+                None => matched,
+                Some(mut acc) => {
+                    acc.retain(|page, _| matched.contains_key(page));
+                    for (page, rects) in matched {
+                        if let Some(existing) = acc.get_mut(&page) {
+                            existing.extend(rects);
+                        }
+                    }
+                    acc
+                }
+            });
+        }
+
+        pages
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(page, rects)| SearchHit { page, rects })
+            .collect()
+    }
+
+    /// 登録されている単語数
+    pub fn word_count(&self) -> usize {
+        self.words.len()
+    }
+
+    /// CDN配信用のバイト列に変換する
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        binary::write_varint(&mut bytes, self.words.len() as u64);
+
+        let mut last_page = 0;
+        for word in &self.words {
+            // ページ番号は直前の単語との差分で保存（同じページが続くので小さくなる）
+            binary::write_varint(
+                &mut bytes,
+                binary::zigzag(word.page as i64 - last_page as i64),
+            );
+            last_page = word.page;
+
+            for value in [word.rect.x, word.rect.y, word.rect.width, word.rect.height] {
+                binary::write_f32(&mut bytes, value);
+            }
+
+            binary::write_str(&mut bytes, &word.text);
+        }
+
+        bytes
+    }
+
+    /// `to_bytes` で生成したバイト列から索引を復元する
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(bytes, "search index");
+        reader.header(MAGIC, FORMAT_VERSION)?;
+
+        let count = reader.varint()?;
+        let mut index = SearchIndex::new();
+        let mut page: i64 = 0;
+
+        for _ in 0..count {
+            page += binary::unzigzag(reader.varint()?);
+            let rect = Rect::new(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+            let text = reader.str()?.to_string();
+
+            index.push_word(IndexedWord {
+                page: u32::try_from(page).map_err(|_| reader.error("page out of range"))?,
+                text,
+                rect,
+            });
+        }
+
+        Ok(index)
+    }
+
+    fn push_word(&mut self, word: IndexedWord) {
+        let id = self.words.len() as u32;
+        for gram in grams(&word.text) {
+            let postings = self.grams.entry(gram).or_default();
+            if postings.last() != Some(&id) {
+                postings.push(id);
+            }
+        }
+        self.words.push(word);
+    }
+
+    /// 語を含みうる単語の候補（bigramの積集合）
+    fn candidates(&self, term: &str) -> Vec<u32> {
+        let chars: Vec<char> = term.chars().collect();
+
+        if chars.len() == 1 {
+            // 1文字の検索語は、その文字を含むbigramと1文字の単語を全て候補とする
+            let mut ids: Vec<u32> = self
+                .grams
+                .iter()
+                .filter(|(gram, _)| gram.contains(chars[0]))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            return ids;
+        }
+
+        let mut result: Option<Vec<u32>> = None;
+        for gram in grams(term) {
+            let Some(postings) = self.grams.get(&gram) else {
+                return Vec::new();
+            };
+            result = Some(match result {
+                None => postings.clone(),
+                Some(acc) => intersect(&acc, postings),
+            });
+        }
+        result.unwrap_or_default()
+    }
+}
+
+/// 検索用にテキストを正規化する（全角英数記号を半角に、英字を小文字に）
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 文字bigram（1文字の場合はその文字）
+fn grams(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() == 1 {
+        return vec![chars[0].to_string()];
+    }
+    chars.windows(2).map(|w| w.iter().collect()).collect()
+}
+
+/// 昇順に並んだ2つの配列の積集合
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x: f64) -> WordBox {
+        WordBox {
+            text: text.to_string(),
+            x,
+            y: 10.0,
+            width: 50.0,
+            height: 20.0,
+        }
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.add_page(&PageText {
+            page: 0,
+            words: vec![word("春の新商品", 0.0), word("Spring", 100.0)],
+        });
+        index.add_page(&PageText {
+            page: 3,
+            words: vec![word("新商品のご案内", 0.0), word("ＳＡＬＥ", 100.0)],
+        });
+        index
+    }
+
+    #[test]
+    fn test_query_japanese_and_normalized_text() {
+        let index = index();
+
+        let hits = index.query("新商品");
+        assert_eq!(hits.iter().map(|h| h.page).collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(hits[0].rects, vec![Rect::new(0.0, 10.0, 50.0, 20.0)]);
+
+        // 全角英字・大文字小文字を区別しない
+        assert_eq!(index.query("sale")[0].page, 3);
+        assert_eq!(index.query("SPRING")[0].page, 0);
+        // 1文字検索
+        assert_eq!(index.query("春").len(), 1);
+        // AND検索
+        let hits = index.query("新商品 sale");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].rects.len(), 2);
+
+        assert!(index.query("存在しない").is_empty());
+        assert!(index.query("  ").is_empty());
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let index = index();
+        let bytes = index.to_bytes();
+        let restored = SearchIndex::from_bytes(&bytes).unwrap();
+
+        assert_eq!(restored.word_count(), 4);
+        assert_eq!(restored.query("ご案内"), index.query("ご案内"));
+
+        assert!(SearchIndex::from_bytes(b"XXXX").is_err());
+        assert!(SearchIndex::from_bytes(&bytes[..bytes.len() - 3]).is_err());
+    }
+}
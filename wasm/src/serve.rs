@@ -0,0 +1,828 @@
+//! 公開前のプレビュー用のHTTPサーバー（`serve` フィーチャー、ネイティブのみ）
+//!
+//! ページ画像・PDFのヘッダーからタイルの配置だけを先に計算し、ページは最初にタイルを
+//! 要求されたときにデコード・ラスタライズして、タイルをエンコードして返す。metadataはWASMと
+//! 同じ形式で、Workersと同じパス（`/pamphlet/{id}/metadata`・`/pamphlet/{id}/tile/{hash}`）と
+//! R2と同じ配置（`/metadata.json`・`/tiles/{hash}.webp`）で返すため、ビューアをそのまま向けられる。
+//!
+//! タイルのハッシュはファイル・更新日時・ページ・位置・設定から計算するため、公開したタイルの
+//! ハッシュ（WebPのデータから計算）とは異なる。前処理を指定したファイルはページの大きさが
+//! 変わるため、開くときにデコードして `LazyTiles` と同じく画素からハッシュを計算する。
+//!
+//! 手元でのプレビュー用のため、同時の接続数・リクエストの大きさ・読み書きの時間を制限する
+//! 単純なサーバーとして標準ライブラリだけで実装する（GETとHEADのみで、keep-aliveは使わない）。
+//! hyperは非同期ランタイムが必要で、ビルドサーバー向けのCLIの依存が大きくなるため使わない
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use image::DynamicImage;
+
+use crate::decode::{self, AnimationMode};
+use crate::error::PamphletError;
+use crate::pamphlet::{self, InputPages, PamphletAssembler};
+use crate::preprocess::{PageGeometry, PreprocessOptions};
+use crate::probe::{self, ProbeFormat};
+use crate::publish::DirectoryOptions;
+use crate::settings::Settings;
+use crate::tiler::{self, LazyTiles, TileInfo, TileOptions, TileResult};
+use crate::{hasher, measure, TocEntry};
+
+/// 1つのリクエストの行・ヘッダーの合計の上限（バイト）
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// 同時に応答する接続の数の上限（超えた接続は空くまで受け付けを待つ）
+const MAX_CONNECTIONS: usize = 32;
+
+/// リクエストを読む時間の上限
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// レスポンスを書く時間の上限
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `pages` を指定しないmetadataの要求で返すページの範囲（Workersと同じ）
+const DEFAULT_PAGE_RANGE: (u32, u32) = (0, 5);
+
+/// 1回のmetadataの要求で返すページの数の上限（Workersと同じ）
+const MAX_PAGE_RANGE: u32 = 100;
+
+/// ページのタイルを要求されたときにエンコードして返すプレビュー
+#[derive(Debug)]
+pub struct PreviewServer {
+    metadata: serde_json::Value,
+    pages: Vec<PreviewPage>,
+    /// タイルのハッシュと、そのタイルのページ・ページの中のインデックス
+    tiles: HashMap<String, (usize, usize)>,
+    /// エンコードしたタイル（同じハッシュのタイルは1回だけエンコードする）
+    encoded: Mutex<HashMap<String, Vec<u8>>>,
+    /// ページをデコードし直すときの設定
+    settings: Settings,
+}
+
+/// プレビューの1ページ
+#[derive(Debug)]
+struct PreviewPage {
+    /// 読み込み元（開くときにデコードしたページは `None`）
+    source: Option<PageSource>,
+    /// デコードしたページ（最初にタイルを要求されるまでは `None`）
+    tiles: Mutex<Option<LazyTiles>>,
+}
+
+/// 最初にタイルを要求されたときにデコードするページ
+#[derive(Debug)]
+struct PageSource {
+    file: PathBuf,
+    /// PDFのページ番号（0始まり）と解像度（画像は `None`）
+    pdf_page: Option<(usize, f32)>,
+    tile: TileOptions,
+    /// 開いたときに調べたページの大きさ
+    width: u32,
+    height: u32,
+}
+
+/// HTTPのレスポンス
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub cache_control: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            cache_control: "no-cache",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+impl PreviewServer {
+    /// ファイルを並べた順にページとして読み込み、タイルの配置とハッシュを計算する
+    ///
+    /// 前処理のないファイルはヘッダーから大きさだけを調べ、画素はデコードしない。PDFは各ページを
+    /// `options.dpi` でラスタライズする（タイルサイズ・WebPの品質・前処理・ファイルごとの設定は
+    /// `tile_files` と同じ。出力先・配置は使わない）。画像のデコードは `settings`（設定していない
+    /// 項目はモジュール全体の設定）に従い、タイルを返すときにデコードし直す場合も同じ設定を使う
+    ///
+    /// # Errors
+    /// `files` が空の場合、いずれかのファイルの読み込みや、ヘッダー・前処理するページのデコードに
+    /// 失敗した場合
+    pub fn open(
+        files: &[PathBuf],
+        options: &DirectoryOptions,
+        settings: Settings,
+    ) -> Result<Self, String> {
+        if files.is_empty() {
+            return Err("No page images or PDFs found".to_string());
+        }
+        let mut assembler = PamphletAssembler::new();
+        let mut pages = Vec::new();
+        let mut tiles = HashMap::new();
+        for file in files {
+            let error = |e: String| format!("{}: {}", file.display(), e);
+            let data = fs::read(file).map_err(|e| error(format!("Failed to read: {}", e)))?;
+            let file_options = options.for_file(file);
+            let probed = match file_options.preprocess == PreprocessOptions::default() {
+                true => probe_pages(file, &data, &file_options, &settings),
+                false => Ok(None),
+            };
+            let (input, toc) = match probed {
+                Ok(Some(pages)) => Ok(pages),
+                Ok(None) => preprocess_pages(data, &file_options, &settings),
+                Err(e) => Err(e),
+            }
+            .map_err(|e| error(e.to_string()))?;
+
+            let mut results = Vec::new();
+            for (result, geometry, page) in input {
+                for (index, tile) in result.tiles.iter().enumerate() {
+                    tiles
+                        .entry(tile.hash.clone())
+                        .or_insert((pages.len(), index));
+                }
+                pages.push(page);
+                results.push((result, geometry));
+            }
+            assembler
+                .add(InputPages {
+                    pages: results,
+                    toc,
+                })
+                .map_err(error)?;
+        }
+
+        Ok(Self {
+            metadata: assembler.build()?,
+            pages,
+            tiles,
+            encoded: Mutex::new(HashMap::new()),
+            settings,
+        })
+    }
+
+    /// metadata.jsonの内容
+    pub fn metadata(&self) -> &serde_json::Value {
+        &self.metadata
+    }
+
+    /// タイルのWebPのデータ（はじめて要求されたタイルはエンコードする）
+    ///
+    /// # Errors
+    /// エンコードに失敗した場合
+    pub fn tile(&self, hash: &str) -> Result<Option<Vec<u8>>, String> {
+        let Some(&(page, index)) = self.tiles.get(hash) else {
+            return Ok(None);
+        };
+        // ページのデコードとタイルのエンコードは、ページごとに1つずつ行う
+        let page = &self.pages[page];
+        let mut lazy = page.tiles.lock().unwrap_or_else(PoisonError::into_inner);
+        let encoded = self.encoded.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(data) = encoded.get(hash) {
+            return Ok(Some(data.clone()));
+        }
+        drop(encoded);
+
+        let lazy = match &mut *lazy {
+            Some(lazy) => lazy,
+            None => {
+                let source = page
+                    .source
+                    .as_ref()
+                    .ok_or_else(|| format!("Tile {} has no page to decode", hash))?;
+                lazy.insert(source.decode(&self.settings)?)
+            }
+        };
+        let data = lazy
+            .encode(index)?
+            .ok_or_else(|| format!("Tile {} was already encoded", hash))?;
+        self.encoded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(hash.to_string(), data.clone());
+        Ok(Some(data))
+    }
+
+    /// リクエストに応じたレスポンス（`target` はクエリを含むパス）
+    pub fn respond(&self, method: &str, target: &str) -> Response {
+        if method != "GET" && method != "HEAD" {
+            return Response::error(405, "Method not allowed");
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["metadata.json"] => Response::json(200, &self.metadata),
+            ["pamphlet", _, "metadata"] => self.metadata_page(query_value(query, "pages")),
+            ["tiles", name] => match name.strip_suffix(".webp") {
+                Some(hash) => self.tile_response(hash),
+                None => Response::error(404, "Not found"),
+            },
+            ["pamphlet", _, "tile", hash] => self.tile_response(hash),
+            _ => Response::error(404, "Not found"),
+        }
+    }
+
+    /// Workersの `GET /pamphlet/{id}/metadata?pages=0-5` と同じ形のレスポンス
+    fn metadata_page(&self, pages: Option<&str>) -> Response {
+        let Some((start, end)) = parse_page_range(pages) else {
+            return Response::error(400, "Invalid page range format. Use: pages=0-5");
+        };
+        let all_pages = self.metadata["pages"]
+            .as_array()
+            .map_or(&[][..], Vec::as_slice);
+        let filtered: Vec<_> = all_pages
+            .iter()
+            .filter(|page| {
+                page["page"]
+                    .as_u64()
+                    .is_some_and(|page| (start as u64..=end as u64).contains(&page))
+            })
+            .collect();
+        Response::json(
+            200,
+            &serde_json::json!({
+                "version": self.metadata["version"],
+                "tile_size": self.metadata["tile_size"],
+                "pages": filtered,
+                "total_pages": all_pages.len(),
+                "has_more": (end as usize) + 1 < all_pages.len(),
+                "has_previous": start > 0,
+            }),
+        )
+    }
+
+    fn tile_response(&self, hash: &str) -> Response {
+        match self.tile(hash) {
+            Ok(Some(data)) => Response {
+                status: 200,
+                content_type: "image/webp",
+                // ハッシュが同じタイルは内容も同じ
+                cache_control: "public, max-age=31536000, immutable",
+                body: data,
+            },
+            Ok(None) => Response::error(404, "Tile not found"),
+            Err(message) => {
+                log::error!("Failed to encode tile {}: {}", hash, message);
+                Response::error(500, "Internal server error")
+            }
+        }
+    }
+
+    /// `listener` で受け付けた接続に、接続ごとのスレッドで応答し続ける
+    ///
+    /// 同時に応答する接続は `MAX_CONNECTIONS` まで（超えた接続は空くまで受け付けを待つ）
+    pub fn serve(&self, listener: TcpListener) {
+        let connections = Connections::default();
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let slot = connections.acquire();
+                        scope.spawn(move || {
+                            let _slot = slot;
+                            if let Err(e) = self.handle(stream) {
+                                log::warn!("Failed to respond: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("Failed to accept a connection: {}", e),
+                }
+            }
+        });
+    }
+
+    /// 1つの接続のリクエストを読み、応答して閉じる
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        // 行・ヘッダーの合計が上限を超えたら、改行がなくてもそこで読むのをやめる
+        let mut reader = BufReader::new((&stream).take(MAX_REQUEST_BYTES as u64));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut complete = request_line.ends_with('\n');
+        // ヘッダーは使わないが、空行まで読んでから応答する
+        while complete {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            if header == "\r\n" || header == "\n" {
+                break;
+            }
+            complete = header.ends_with('\n');
+        }
+        if !complete && reader.get_ref().limit() == 0 {
+            return write_response(&stream, &Response::error(431, "Request too large"), true);
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return write_response(&stream, &Response::error(400, "Bad request"), true);
+        };
+        let response = self.respond(method, target);
+        write_response(&stream, &response, method != "HEAD")
+    }
+}
+
+/// 同時に応答している接続の数
+#[derive(Debug, Default)]
+struct Connections {
+    count: Mutex<usize>,
+    released: Condvar,
+}
+
+/// 応答している接続（応答し終えてドロップすると数を減らす）
+struct ConnectionSlot<'a>(&'a Connections);
+
+impl Connections {
+    /// 接続の数を増やす（上限に達している場合は、どれかの応答が終わるまで待つ）
+    fn acquire(&self) -> ConnectionSlot<'_> {
+        let mut count = self.count.lock().unwrap_or_else(PoisonError::into_inner);
+        while *count >= MAX_CONNECTIONS {
+            count = self
+                .released
+                .wait(count)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *count += 1;
+        ConnectionSlot(self)
+    }
+}
+
+impl Drop for ConnectionSlot<'_> {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+impl PageSource {
+    /// ページをデコード・ラスタライズする（大きさが開いたときと異なる場合はエラー）
+    fn decode(&self, settings: &Settings) -> Result<LazyTiles, PamphletError> {
+        let data = fs::read(&self.file).map_err(|e| {
+            PamphletError::NotFound(format!("{}: Failed to read: {}", self.file.display(), e))
+        })?;
+        let image = match self.pdf_page {
+            Some((index, dpi)) => rasterize_pdf_page(data, index, dpi)?,
+            None => decode::decode_pages(&data, AnimationMode::FirstFrame, &settings.resolve())?
+                .swap_remove(0),
+        };
+        if (image.width(), image.height()) != (self.width, self.height) {
+            return Err(PamphletError::Internal(format!(
+                "{}: page size changed after the preview was opened",
+                self.file.display()
+            )));
+        }
+        let (_, lazy) = LazyTiles::new(image, self.tile.tile_size, self.tile.quality)?;
+        Ok(lazy)
+    }
+}
+
+/// 前処理のないファイルのページの大きさを、画素をデコードせずに調べる
+///
+/// タイルのハッシュはファイル・更新日時・ページ・位置・設定から計算する。ヘッダーの大きさが
+/// デコードした大きさと異なりうる形式（HEIF・RAWなど）は `None`（開くときにデコードする）
+fn probe_pages(
+    file: &Path,
+    data: &[u8],
+    options: &DirectoryOptions,
+    settings: &Settings,
+) -> Result<Option<PreviewPages>, PamphletError> {
+    let (sizes, toc) = match data.starts_with(b"%PDF") {
+        true => pdf_page_sizes(data, options.dpi)?,
+        false => {
+            let settings = settings.resolve();
+            decode::check_format(data, &settings)?;
+            let probe = probe::probe_image(data)?;
+            if !matches!(
+                probe.format,
+                ProbeFormat::Jpeg | ProbeFormat::Png | ProbeFormat::Webp | ProbeFormat::Gif
+            ) {
+                return Ok(None);
+            }
+            settings.limits().check(probe.width, probe.height)?;
+            let page = (probe.width, probe.height, None, measure::detect_dpi(data));
+            (vec![page], Vec::new())
+        }
+    };
+    let modified = fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let tile = &options.tile;
+    let pages = sizes
+        .into_iter()
+        .enumerate()
+        .map(|(index, (width, height, pdf_page, dpi))| {
+            let tiles_x = width.div_ceil(tile.tile_size);
+            let tiles_y = height.div_ceil(tile.tile_size);
+            let tiles = (0..tiles_y)
+                .flat_map(|y| (0..tiles_x).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let key = format!(
+                        "{}\0{:?}\0{}\0{}\0{}\0{}\0{:?}",
+                        file.display(),
+                        modified,
+                        index,
+                        x,
+                        y,
+                        tile.tile_size,
+                        tile.quality
+                    );
+                    TileInfo {
+                        x,
+                        y,
+                        hash: hasher::calculate_hash(key.as_bytes()),
+                        data: Vec::new(),
+                    }
+                })
+                .collect();
+            let result = TileResult {
+                width,
+                height,
+                tile_size: tile.tile_size,
+                tiles,
+                timings: None,
+                warnings: tiler::quality_warnings(tile.quality),
+            };
+            let geometry = PageGeometry {
+                dpi,
+                ..PageGeometry::default()
+            };
+            let page = PreviewPage {
+                source: Some(PageSource {
+                    file: file.to_path_buf(),
+                    pdf_page,
+                    tile: tile.clone(),
+                    width,
+                    height,
+                }),
+                tiles: Mutex::new(None),
+            };
+            (result, geometry, page)
+        })
+        .collect();
+    Ok(Some((pages, toc)))
+}
+
+/// 前処理するファイルのページをデコードして前処理し、画素からタイルのハッシュを計算する
+fn preprocess_pages(
+    data: Vec<u8>,
+    options: &DirectoryOptions,
+    settings: &Settings,
+) -> Result<PreviewPages, PamphletError> {
+    let tile = &options.tile;
+    let settings = settings.resolve();
+    pamphlet::map_preprocessed_pages(
+        data,
+        options.dpi,
+        &options.preprocess,
+        &settings,
+        |image, geometry| {
+            let (result, lazy) = LazyTiles::new(image, tile.tile_size, tile.quality)?;
+            let page = PreviewPage {
+                source: None,
+                tiles: Mutex::new(Some(lazy)),
+            };
+            Ok((result, geometry, page))
+        },
+    )
+}
+
+/// ファイルのページのタイルの配置・前処理の結果・プレビューのページと、しおり
+type PreviewPages = (Vec<(TileResult, PageGeometry, PreviewPage)>, Vec<TocEntry>);
+
+/// ページごとの大きさ・PDFのページ番号と解像度・metadataの解像度
+type PdfPageSizes = Vec<(u32, u32, Option<(usize, f32)>, Option<f64>)>;
+
+/// PDFの各ページを `dpi` でラスタライズしたときの大きさと、しおり
+#[cfg(feature = "pdf")]
+fn pdf_page_sizes(data: &[u8], dpi: f32) -> Result<(PdfPageSizes, Vec<TocEntry>), PamphletError> {
+    let document = crate::pdf::PdfDocument::open(data.to_vec(), None)
+        .map_err(|e| PamphletError::DecodeFailed(e.to_string()))?;
+    let sizes = (0..document.page_count())
+        .map(|index| {
+            let (width, height, dpi) = document.page_dimensions(index, dpi)?;
+            Ok((width, height, Some((index, dpi)), Some(dpi as f64)))
+        })
+        .collect::<Result<_, PamphletError>>()?;
+    Ok((sizes, document.outline()))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn pdf_page_sizes(_data: &[u8], _dpi: f32) -> Result<(PdfPageSizes, Vec<TocEntry>), PamphletError> {
+    Err(pdf_unsupported())
+}
+
+#[cfg(not(feature = "pdf"))]
+fn pdf_unsupported() -> PamphletError {
+    PamphletError::UnsupportedFormat(
+        "PDF input is not supported in this build (enable the `pdf` feature)".to_string(),
+    )
+}
+
+#[cfg(feature = "pdf")]
+fn rasterize_pdf_page(
+    data: Vec<u8>,
+    index: usize,
+    dpi: f32,
+) -> Result<DynamicImage, PamphletError> {
+    let document = crate::pdf::PdfDocument::open(data, None)
+        .map_err(|e| PamphletError::DecodeFailed(e.to_string()))?;
+    let (image, _) = document.render_page(index, dpi)?;
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn rasterize_pdf_page(
+    _data: Vec<u8>,
+    _index: usize,
+    _dpi: f32,
+) -> Result<DynamicImage, PamphletError> {
+    Err(pdf_unsupported())
+}
+
+/// レスポンスを書き出す（ビューアは別のオリジンから読み込むため、CORSを許可する）
+fn write_response(mut stream: &TcpStream, response: &Response, body: bool) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.cache_control,
+    );
+    stream.write_all(head.as_bytes())?;
+    if body {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// クエリの値（`a=1&b=2` の `b` は `2`）
+fn query_value<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+/// `pages` のページの範囲（`0-5`、省略時は `DEFAULT_PAGE_RANGE`）。不正な場合は `None`
+fn parse_page_range(pages: Option<&str>) -> Option<(u32, u32)> {
+    let Some(pages) = pages.filter(|pages| !pages.is_empty()) else {
+        return Some(DEFAULT_PAGE_RANGE);
+    };
+    let (start, end) = pages.split_once('-')?;
+    let digits = |value: &str| -> Option<u32> {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        value.parse().ok()
+    };
+    let (start, end) = (digits(start)?, digits(end)?);
+    (end >= start && end - start <= MAX_PAGE_RANGE).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{DecodeLimits, SourceFormat};
+    use crate::test_util::png;
+    use crate::tiler::TileOptions;
+    use std::io::Read;
+
+    /// テスト用のページ画像と、それを開いたプレビュー（ドロップするとページ画像を消す）
+    struct Preview {
+        server: PreviewServer,
+        dir: PathBuf,
+    }
+
+    impl std::ops::Deref for Preview {
+        type Target = PreviewServer;
+
+        fn deref(&self) -> &PreviewServer {
+            &self.server
+        }
+    }
+
+    impl Drop for Preview {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn preview(name: &str) -> Preview {
+        let dir =
+            std::env::temp_dir().join(format!("tile-wasm-serve-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let files = [dir.join("1.png"), dir.join("2.png")];
+        fs::write(&files[0], png(40, 20, 0)).unwrap();
+        fs::write(&files[1], png(20, 20, 9)).unwrap();
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, None),
+            ..DirectoryOptions::new(dir.join("out"))
+        };
+        let server = PreviewServer::open(&files, &options, Settings::default()).unwrap();
+        Preview { server, dir }
+    }
+
+    fn body(response: &Response) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_preview_tiles() {
+        let server = preview("tiles");
+        let metadata = server.metadata();
+        assert_eq!(metadata["tile_size"], 32);
+        assert_eq!(metadata["pages"].as_array().unwrap().len(), 2);
+        assert_eq!(metadata["pages"][0]["width"], 40);
+
+        let hash = metadata["pages"][0]["tiles"][1]["hash"].as_str().unwrap();
+        let response = server.respond("GET", &format!("/tiles/{}.webp", hash));
+        assert_eq!(
+            (response.status, response.content_type),
+            (200, "image/webp")
+        );
+        assert_eq!(&response.body[..4], b"RIFF");
+        // 2回目はエンコードしたタイルを返す
+        let again = server.respond("GET", &format!("/pamphlet/preview/tile/{}", hash));
+        assert_eq!(again.body, response.body);
+
+        assert_eq!(server.respond("GET", "/tiles/missing.webp").status, 404);
+        assert_eq!(server.respond("POST", "/metadata.json").status, 405);
+        assert_eq!(body(&server.respond("GET", "/metadata.json")), *metadata);
+    }
+
+    #[test]
+    fn test_preview_settings() {
+        let preview = preview("settings");
+        let files = [preview.dir.join("1.png")];
+        let options = DirectoryOptions::new(preview.dir.join("out"));
+        // 開くときのデコードは渡した設定に従う
+        let jpeg_only = Settings {
+            allowed_formats: Some(vec![SourceFormat::Jpeg]),
+            ..Settings::default()
+        };
+        assert!(PreviewServer::open(&files, &options, jpeg_only).is_err());
+        let small = Settings {
+            decode_limits: Some(DecodeLimits {
+                max_dimension: 32,
+                max_pixels: u64::MAX,
+            }),
+            ..Settings::default()
+        };
+        assert!(PreviewServer::open(&files, &options, small).is_err());
+        assert!(PreviewServer::open(&files, &options, Settings::default()).is_ok());
+    }
+
+    #[test]
+    fn test_preview_decodes_pages_lazily() {
+        let server = preview("lazy");
+        let decoded = |server: &PreviewServer| -> Vec<bool> {
+            server
+                .pages
+                .iter()
+                .map(|page| page.tiles.lock().unwrap().is_some())
+                .collect()
+        };
+        // 開いただけではページをデコードしない
+        assert_eq!(decoded(&server), [false, false]);
+
+        let hash = server.metadata()["pages"][1]["tiles"][0]["hash"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            server
+                .respond("GET", &format!("/tiles/{}.webp", hash))
+                .status,
+            200
+        );
+        assert_eq!(decoded(&server), [false, true]);
+    }
+
+    #[test]
+    fn test_preview_preprocessed_pages() {
+        let dir = std::env::temp_dir().join(format!("tile-wasm-serve-pre-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("1.png");
+        fs::write(&file, png(40, 20, 0)).unwrap();
+        let options = DirectoryOptions {
+            tile: TileOptions::new(32, None),
+            preprocess: PreprocessOptions {
+                gamma: Some(1.2),
+                ..PreprocessOptions::default()
+            },
+            ..DirectoryOptions::new(dir.join("out"))
+        };
+        // 前処理するページは開くときにデコードし、ファイルを消しても返せる
+        let server = PreviewServer::open(&[file], &options, Settings::default()).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        let hash = server.metadata()["pages"][0]["tiles"][0]["hash"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            server
+                .respond("GET", &format!("/tiles/{}.webp", hash))
+                .status,
+            200
+        );
+    }
+
+    #[test]
+    fn test_preview_metadata_page() {
+        let server = preview("metadata-page");
+        let page = body(&server.respond("GET", "/pamphlet/preview/metadata?pages=1-1"));
+        assert_eq!(page["pages"].as_array().unwrap().len(), 1);
+        assert_eq!(page["pages"][0]["page"], 1);
+        assert_eq!(page["total_pages"], 2);
+        assert_eq!(page["has_more"], false);
+        assert_eq!(page["has_previous"], true);
+
+        let page = body(&server.respond("GET", "/pamphlet/preview/metadata"));
+        assert_eq!(page["pages"].as_array().unwrap().len(), 2);
+        let response = server.respond("GET", "/pamphlet/preview/metadata?pages=3-1");
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(parse_page_range(None), Some((0, 5)));
+        assert_eq!(parse_page_range(Some("10-19")), Some((10, 19)));
+        assert_eq!(parse_page_range(Some("0-101")), None);
+        assert_eq!(parse_page_range(Some("-1-2")), None);
+        assert_eq!(parse_page_range(Some("1")), None);
+    }
+
+    #[test]
+    fn test_serve() {
+        let server = preview("serve");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /metadata.json HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Access-Control-Allow-Origin: *\r\n"));
+        let (_, json) = response.split_once("\r\n\r\n").unwrap();
+        let metadata: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(metadata["tile_size"], 32);
+
+        // 改行のない長すぎるリクエストは、上限まで読んで断る
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(&[b'a'; MAX_REQUEST_BYTES]).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+    }
+
+    #[test]
+    fn test_connections() {
+        let connections = Connections::default();
+        let slots: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| connections.acquire())
+            .collect();
+        assert_eq!(*connections.count.lock().unwrap(), MAX_CONNECTIONS);
+
+        // 上限に達している間は、応答が終わるまで待つ
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| drop(connections.acquire()));
+            thread::sleep(Duration::from_millis(50));
+            assert!(!waiting.is_finished());
+            drop(slots);
+            waiting.join().unwrap();
+        });
+        assert_eq!(*connections.count.lock().unwrap(), 0);
+    }
+}
@@ -0,0 +1,209 @@
+//! タイル化の設定
+//!
+//! デコードの上限などの設定は、モジュール全体の値（`set_decode_limits` など）を既定とし、
+//! `Tiler` のインスタンスの設定で上書きする。処理の入口で `resolve` した設定を
+//! `TilerContext`・デコーダーなどに引数で渡すため、1つのWASMのインスタンスで複数の
+//! タイル化を並行しても、rayonのワーカーで処理しても、呼び出し元の設定を使う
+
+use std::sync::{PoisonError, RwLock};
+
+use log::LevelFilter;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::decode::{DecodeLimits, SourceFormat};
+use crate::{logging, metadata};
+
+/// インスタンスの設定（`None` の項目はモジュール全体の設定に従う）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// デコードする画像の大きさの上限（`set_decode_limits`）
+    pub decode_limits: Option<DecodeLimits>,
+    /// デコードを許可する入力の形式（`set_allowed_formats`）
+    pub allowed_formats: Option<Vec<SourceFormat>>,
+    /// 処理時間を記録するか（`set_timing_enabled`）
+    pub timing: Option<bool>,
+    /// metadataのバージョン（`set_metadata_version`）
+    pub metadata_version: Option<u64>,
+    /// ページ情報・目次を厳密に読み込むか（`set_metadata_strict`）
+    pub strict_metadata: Option<bool>,
+    /// ログのレベル（`set_log_level`）
+    #[serde(
+        serialize_with = "serialize_level",
+        deserialize_with = "deserialize_level"
+    )]
+    pub log_level: Option<LevelFilter>,
+}
+
+/// モジュール全体の設定（`set_decode_limits` などで変える。ログのレベルは `log` の設定に従う）
+static GLOBAL: RwLock<Settings> = RwLock::new(Settings {
+    decode_limits: None,
+    allowed_formats: None,
+    timing: None,
+    metadata_version: None,
+    strict_metadata: None,
+    log_level: None,
+});
+
+impl Settings {
+    /// モジュール全体の設定
+    pub fn global() -> Settings {
+        GLOBAL
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// モジュール全体の設定を変える
+    pub fn update_global(update: impl FnOnce(&mut Settings)) {
+        update(&mut GLOBAL.write().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    /// 設定していない項目をモジュール全体の設定で補う
+    pub fn resolve(&self) -> Settings {
+        let global = Self::global();
+        Settings {
+            decode_limits: self.decode_limits.or(global.decode_limits),
+            allowed_formats: self.allowed_formats.clone().or(global.allowed_formats),
+            timing: self.timing.or(global.timing),
+            metadata_version: self.metadata_version.or(global.metadata_version),
+            strict_metadata: self.strict_metadata.or(global.strict_metadata),
+            log_level: self.log_level,
+        }
+    }
+
+    /// デコードする画像の大きさの上限
+    pub fn limits(&self) -> DecodeLimits {
+        self.decode_limits.unwrap_or_default()
+    }
+
+    /// デコードを許可する入力の形式
+    pub fn formats(&self) -> &[SourceFormat] {
+        self.allowed_formats
+            .as_deref()
+            .unwrap_or(&SourceFormat::ALL)
+    }
+
+    /// 処理時間を記録するか
+    pub fn timing_enabled(&self) -> bool {
+        self.timing.unwrap_or(false)
+    }
+
+    /// metadataのバージョン（固定していなければ現在時刻）
+    pub fn version(&self) -> u64 {
+        self.metadata_version
+            .unwrap_or_else(metadata::current_timestamp)
+    }
+
+    /// ページ情報・目次を厳密に読み込むか
+    pub fn is_strict(&self) -> bool {
+        self.strict_metadata.unwrap_or(false)
+    }
+}
+
+fn serialize_level<S: Serializer>(
+    level: &Option<LevelFilter>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    level
+        .map(|level| level.as_str().to_ascii_lowercase())
+        .serialize(serializer)
+}
+
+fn deserialize_level<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<LevelFilter>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|level| logging::parse_level(&level).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::png;
+
+    use crate::decode;
+
+    #[test]
+    fn test_settings() {
+        let small = Settings {
+            decode_limits: Some(DecodeLimits {
+                max_dimension: 50,
+                max_pixels: 2500,
+            }),
+            metadata_version: Some(42),
+            ..Settings::default()
+        };
+        let jpeg_only = Settings {
+            allowed_formats: Some(vec![SourceFormat::Jpeg]),
+            timing: Some(true),
+            ..Settings::default()
+        };
+        let image = png(100, 100, 0);
+
+        let error = decode::decode_image(&image, &small).unwrap_err();
+        assert_eq!(error.code(), "image_too_large", "{}", error);
+        assert_eq!(small.version(), 42);
+        let error = decode::decode_image(&image, &jpeg_only).unwrap_err();
+        assert_eq!(error.code(), "unsupported_format", "{}", error);
+        assert!(jpeg_only.timing_enabled());
+        assert_eq!(jpeg_only.limits(), DecodeLimits::default());
+        assert!(decode::decode_image(&image, &Settings::default()).is_ok());
+    }
+
+    #[test]
+    fn test_settings_across_threads() {
+        // rayonのワーカーなど、別のスレッドでも渡した設定で処理する
+        let settings = Settings {
+            decode_limits: Some(DecodeLimits {
+                max_dimension: 50,
+                max_pixels: 2500,
+            }),
+            ..Settings::default()
+        };
+        let image = png(100, 100, 0);
+        let error = std::thread::scope(|scope| {
+            scope
+                .spawn(|| decode::decode_image(&image, &settings))
+                .join()
+                .unwrap()
+                .unwrap_err()
+        });
+        assert_eq!(error.code(), "image_too_large");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let settings = Settings {
+            metadata_version: Some(7),
+            log_level: Some(LevelFilter::Debug),
+            ..Settings::default()
+        };
+        let resolved = settings.resolve();
+        assert_eq!(resolved.version(), 7);
+        assert_eq!(resolved.log_level, Some(LevelFilter::Debug));
+        assert_eq!(resolved.formats(), Settings::global().formats());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let settings: Settings = serde_json::from_value(serde_json::json!({
+            "allowed_formats": ["jpeg", "png"],
+            "log_level": "debug",
+            "decode_limits": { "max_dimension": 1000 },
+        }))
+        .unwrap();
+        assert_eq!(settings.log_level, Some(LevelFilter::Debug));
+        assert_eq!(settings.decode_limits.unwrap().max_pixels, 1 << 28);
+        assert_eq!(
+            serde_json::to_value(&settings).unwrap()["log_level"],
+            "debug"
+        );
+
+        let error = serde_json::from_value::<Settings>(serde_json::json!({ "log_level": "loud" }));
+        assert!(error.unwrap_err().to_string().contains("Invalid log level"));
+        let error = serde_json::from_value::<Settings>(serde_json::json!({ "timeing": true }));
+        assert!(error.is_err());
+    }
+}
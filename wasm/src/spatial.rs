@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{Point, Rect};
+
+/// 1ノードに保持するアイテム数の目安（超えたら分割する）
+const MAX_ITEMS_PER_NODE: usize = 8;
+
+/// 分割の最大深さ
+const MAX_DEPTH: u32 = 8;
+
+/// 空間インデックスに登録するアイテム（ホットスポット・注釈など）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpatialItem {
+    /// アイテムのID（ホットスポットID、注釈IDなど）
+    pub id: String,
+    /// ページ番号
+    pub page: u32,
+    /// 領域（ページ座標、原寸ピクセル）
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl SpatialItem {
+    fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+/// 四分木のノード
+#[derive(Debug)]
+struct QuadNode {
+    bounds: Rect,
+    /// このノードに属するアイテムのインデックス（子に収まらないものを含む）
+    items: Vec<usize>,
+    children: Option<Box<[QuadNode; 4]>>,
+    depth: u32,
+}
+
+impl QuadNode {
+    fn new(bounds: Rect, depth: u32) -> Self {
+        Self {
+            bounds,
+            items: Vec::new(),
+            children: None,
+            depth,
+        }
+    }
+
+    fn insert(&mut self, index: usize, rects: &[Rect]) {
+        if let Some(children) = self.children.as_mut() {
+            if let Some(child) = children
+                .iter_mut()
+                .find(|c| c.bounds.contains_rect(&rects[index]))
+            {
+                child.insert(index, rects);
+                return;
+            }
+        }
+
+        self.items.push(index);
+
+        if self.children.is_none()
+            && self.items.len() > MAX_ITEMS_PER_NODE
+            && self.depth < MAX_DEPTH
+        {
+            self.split(rects);
+        }
+    }
+
+    fn split(&mut self, rects: &[Rect]) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.bounds;
+        let (hw, hh) = (width / 2.0, height / 2.0);
+        let depth = self.depth + 1;
+
+        self.children = Some(Box::new([
+            QuadNode::new(Rect::new(x, y, hw, hh), depth),
+            QuadNode::new(Rect::new(x + hw, y, hw, hh), depth),
+            QuadNode::new(Rect::new(x, y + hh, hw, hh), depth),
+            QuadNode::new(Rect::new(x + hw, y + hh, hw, hh), depth),
+        ]));
+
+        for index in std::mem::take(&mut self.items) {
+            self.insert(index, rects);
+        }
+    }
+
+    fn query_point(&self, point: &Point, rects: &[Rect], out: &mut Vec<usize>) {
+        if !self.bounds.contains(point) {
+            return;
+        }
+
+        out.extend(self.items.iter().filter(|&&i| rects[i].contains(point)));
+
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.query_point(point, rects, out);
+            }
+        }
+    }
+
+    fn query_rect(&self, rect: &Rect, rects: &[Rect], out: &mut Vec<usize>) {
+        if !self.bounds.intersects(rect) {
+            return;
+        }
+
+        out.extend(self.items.iter().filter(|&&i| rects[i].intersects(rect)));
+
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.query_rect(rect, rects, out);
+            }
+        }
+    }
+}
+
+/// ページごとの四分木
+#[derive(Debug)]
+struct PageIndex {
+    items: Vec<SpatialItem>,
+    rects: Vec<Rect>,
+    root: QuadNode,
+}
+
+impl PageIndex {
+    fn build(items: Vec<SpatialItem>) -> Self {
+        let rects: Vec<Rect> = items.iter().map(SpatialItem::rect).collect();
+        let bounds = rects
+            .iter()
+            .skip(1)
+            .fold(rects.first().copied().unwrap_or_default(), |acc, r| {
+                acc.union(r)
+            });
+
+        let mut root = QuadNode::new(bounds, 0);
+        for index in 0..rects.len() {
+            root.insert(index, &rects);
+        }
+
+        Self { items, rects, root }
+    }
+}
+
+/// ホットスポットや注釈のヒットテスト用空間インデックス
+///
+/// ページごとに四分木を構築し、タップ判定を O(log n) で行う。
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+    pages: BTreeMap<u32, PageIndex>,
+}
+
+impl SpatialIndex {
+    pub fn new(items: Vec<SpatialItem>) -> Self {
+        let mut by_page: BTreeMap<u32, Vec<SpatialItem>> = BTreeMap::new();
+        for item in items {
+            by_page.entry(item.page).or_default().push(item);
+        }
+
+        Self {
+            pages: by_page
+                .into_iter()
+                .map(|(page, items)| (page, PageIndex::build(items)))
+                .collect(),
+        }
+    }
+
+    /// 指定した点を含むアイテムを返す
+    ///
+    /// 後から登録したもの（上に重なっているもの）が先頭
+    pub fn hit_test(&self, page: u32, point: Point) -> Vec<&SpatialItem> {
+        let Some(index) = self.pages.get(&page) else {
+            return Vec::new();
+        };
+
+        let mut hits = Vec::new();
+        index.root.query_point(&point, &index.rects, &mut hits);
+        hits.sort_unstable_by(|a, b| b.cmp(a));
+
+        hits.into_iter().map(|i| &index.items[i]).collect()
+    }
+
+    /// 指定した矩形と交差するアイテムを登録順に返す
+    pub fn query_rect(&self, page: u32, rect: &Rect) -> Vec<&SpatialItem> {
+        let Some(index) = self.pages.get(&page) else {
+            return Vec::new();
+        };
+
+        let mut hits = Vec::new();
+        index.root.query_rect(rect, &index.rects, &mut hits);
+        hits.sort_unstable();
+
+        hits.into_iter().map(|i| &index.items[i]).collect()
+    }
+
+    /// 登録されているアイテム数
+    pub fn len(&self) -> usize {
+        self.pages.values().map(|p| p.items.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, page: u32, x: f64, y: f64, size: f64) -> SpatialItem {
+        SpatialItem {
+            id: id.to_string(),
+            page,
+            x,
+            y,
+            width: size,
+            height: size,
+        }
+    }
+
+    fn ids(items: Vec<&SpatialItem>) -> Vec<&str> {
+        items.into_iter().map(|i| i.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_hit_test_returns_topmost_first() {
+        let index = SpatialIndex::new(vec![
+            item("background", 0, 0.0, 0.0, 1000.0),
+            item("product", 0, 100.0, 100.0, 50.0),
+            item("other-page", 1, 100.0, 100.0, 50.0),
+        ]);
+
+        assert_eq!(
+            ids(index.hit_test(0, Point::new(120.0, 120.0))),
+            vec!["product", "background"]
+        );
+        assert_eq!(
+            ids(index.hit_test(0, Point::new(500.0, 500.0))),
+            vec!["background"]
+        );
+        assert!(index.hit_test(2, Point::new(120.0, 120.0)).is_empty());
+    }
+
+    #[test]
+    fn test_query_with_many_items() {
+        // 20x20のグリッド状に400個のホットスポットを配置（四分木が分割される）
+        let items: Vec<SpatialItem> = (0..400)
+            .map(|i| {
+                item(
+                    &i.to_string(),
+                    0,
+                    (i % 20) as f64 * 50.0,
+                    (i / 20) as f64 * 50.0,
+                    40.0,
+                )
+            })
+            .collect();
+        let index = SpatialIndex::new(items);
+
+        assert_eq!(index.len(), 400);
+        assert_eq!(ids(index.hit_test(0, Point::new(60.0, 10.0))), vec!["1"]);
+        // 隙間（40〜50px）はヒットしない
+        assert!(index.hit_test(0, Point::new(45.0, 10.0)).is_empty());
+
+        let hits = index.query_rect(0, &Rect::new(0.0, 0.0, 90.0, 90.0));
+        assert_eq!(ids(hits), vec!["0", "1", "20", "21"]);
+    }
+}
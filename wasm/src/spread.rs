@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+/// 読み方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingDirection {
+    /// 左綴じ（左→右）
+    #[default]
+    Ltr,
+    /// 右綴じ（右→左、縦書きの冊子など）
+    Rtl,
+}
+
+/// 見開きレイアウトに必要なページ情報
+///
+/// metadataのページ情報（`tiles` などの他のフィールドは無視）をそのまま渡せる
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpreadPage {
+    pub page: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 見開き内でのページの配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PagePlacement {
+    pub page: u32,
+    /// 見開き座標系での配置（左上とサイズ）
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// ページの原寸ピクセル → 見開き座標系の倍率
+    pub scale: f64,
+}
+
+/// 見開きのレイアウト
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpreadLayout {
+    /// 見開き座標系の幅
+    pub width: f64,
+    /// 見開き座標系の高さ
+    pub height: f64,
+    /// 画面上の左から順に並んだ配置
+    pub placements: Vec<PagePlacement>,
+}
+
+/// 見開きのレイアウトを計算する
+///
+/// 高さの異なるページは高い方に揃えて拡大する。`page_b` がない場合（表紙・裏表紙）は
+/// 見開きと同じ座標系のまま片側にだけ配置し、ページ送りで表示位置がずれないようにする。
+/// 1ページ目（表紙）は左綴じなら右側・右綴じなら左側に、それ以外の単独ページ（裏表紙など）は反対側に置く。
+///
+/// # Arguments
+/// * `page_a` - 読み順で先のページ
+/// * `page_b` - 読み順で後のページ
+/// * `direction` - 読み方向
+/// * `gutter` - ページ間の余白（見開き座標系）
+pub fn layout_spread(
+    page_a: &SpreadPage,
+    page_b: Option<&SpreadPage>,
+    direction: ReadingDirection,
+    gutter: f64,
+) -> SpreadLayout {
+    let gutter = gutter.max(0.0);
+    let height = page_b.map_or(page_a.height, |b| page_a.height.max(b.height)) as f64;
+
+    let place = |page: &SpreadPage, x: f64| {
+        let scale = height / page.height.max(1) as f64;
+        PagePlacement {
+            page: page.page,
+            x,
+            y: 0.0,
+            width: page.width as f64 * scale,
+            height,
+            scale,
+        }
+    };
+
+    match page_b {
+        Some(page_b) => {
+            let (left, right) = match direction {
+                ReadingDirection::Ltr => (page_a, page_b),
+                ReadingDirection::Rtl => (page_b, page_a),
+            };
+            let left = place(left, 0.0);
+            let right = place(right, left.width + gutter);
+
+            SpreadLayout {
+                width: right.x + right.width,
+                height,
+                placements: vec![left, right],
+            }
+        }
+        None => {
+            let single = place(page_a, 0.0);
+            // 表紙は左綴じなら右側、右綴じなら左側に置く
+            let on_right = (page_a.page == 0) == (direction == ReadingDirection::Ltr);
+            let x = if on_right { single.width + gutter } else { 0.0 };
+
+            SpreadLayout {
+                width: single.width * 2.0 + gutter,
+                height,
+                placements: vec![PagePlacement { x, ..single }],
+            }
+        }
+    }
+}
+
+/// 見開きの組み合わせ（読み順）を求める
+///
+/// # Arguments
+/// * `page_count` - 総ページ数
+/// * `cover_alone` - 表紙（1ページ目）を単独で表示するか
+///
+/// # Returns
+/// `(先のページ, 後のページ)` の配列（単独ページは後のページが `None`）
+pub fn spread_pairs(page_count: u32, cover_alone: bool) -> Vec<(u32, Option<u32>)> {
+    let mut pairs = Vec::new();
+    let mut page = 0;
+
+    if cover_alone && page_count > 0 {
+        pairs.push((0, None));
+        page = 1;
+    }
+
+    while page < page_count {
+        let next = (page + 1 < page_count).then_some(page + 1);
+        pairs.push((page, next));
+        page += 2;
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(page: u32, width: u32, height: u32) -> SpreadPage {
+        SpreadPage {
+            page,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_layout_spread_orders_by_direction() {
+        let a = page(1, 1000, 1400);
+        let b = page(2, 500, 700);
+
+        let ltr = layout_spread(&a, Some(&b), ReadingDirection::Ltr, 20.0);
+        assert_eq!(ltr.placements[0].page, 1);
+        assert_eq!(ltr.placements[1].page, 2);
+        // 低いページは高い方に揃えて2倍に拡大される
+        assert_eq!(ltr.placements[1].scale, 2.0);
+        assert_eq!(ltr.placements[1].x, 1020.0);
+        assert_eq!((ltr.width, ltr.height), (2020.0, 1400.0));
+
+        let rtl = layout_spread(&a, Some(&b), ReadingDirection::Rtl, 20.0);
+        assert_eq!(rtl.placements[0].page, 2);
+        assert_eq!(rtl.placements[1].page, 1);
+    }
+
+    #[test]
+    fn test_layout_lone_pages() {
+        let cover = layout_spread(&page(0, 1000, 1400), None, ReadingDirection::Ltr, 0.0);
+        assert_eq!(cover.width, 2000.0);
+        assert_eq!(cover.placements[0].x, 1000.0);
+
+        let cover = layout_spread(&page(0, 1000, 1400), None, ReadingDirection::Rtl, 0.0);
+        assert_eq!(cover.placements[0].x, 0.0);
+
+        let back = layout_spread(&page(9, 1000, 1400), None, ReadingDirection::Ltr, 0.0);
+        assert_eq!(back.placements[0].x, 0.0);
+    }
+
+    #[test]
+    fn test_spread_pairs() {
+        assert_eq!(
+            spread_pairs(6, true),
+            vec![(0, None), (1, Some(2)), (3, Some(4)), (5, None)]
+        );
+        assert_eq!(spread_pairs(3, false), vec![(0, Some(1)), (2, None)]);
+        assert!(spread_pairs(0, true).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use image::{DynamicImage, RgbaImage};
+use serde::Serialize;
+
+use crate::decode;
+use crate::error::PamphletError;
+use crate::settings::Settings;
+
+/// 真っ黒・真っ白とみなす輝度（この値以下・以上）
+const SHADOW_CLIP: u8 = 5;
+const HIGHLIGHT_CLIP: u8 = 250;
+
+/// 露出の判定に使う、真っ黒・真っ白な画素の割合の上限
+const MAX_CLIPPED_RATIO: f64 = 0.2;
+
+/// 露出の判定に使う平均輝度の範囲
+const DARK_MEAN: f64 = 70.0;
+const BRIGHT_MEAN: f64 = 235.0;
+
+/// 中間の輝度
+const MID_GRAY: u8 = 128;
+
+/// 露出の判定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exposure {
+    /// 暗すぎる（露出不足）
+    Under,
+    Normal,
+    /// 明るすぎる（露出過多）
+    Over,
+}
+
+/// ページの統計
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageStats {
+    pub width: u32,
+    pub height: u32,
+    /// 輝度（ITU-R BT.601）のヒストグラム（256段階）
+    pub luminance_histogram: Vec<u32>,
+    /// RGBの各チャンネルのヒストグラム（256段階）
+    pub red_histogram: Vec<u32>,
+    pub green_histogram: Vec<u32>,
+    pub blue_histogram: Vec<u32>,
+    /// 平均輝度（0-255）
+    pub mean_brightness: f64,
+    /// 輝度の標準偏差（RMSコントラスト）
+    pub contrast: f64,
+    /// 輝度の1パーセンタイルと99パーセンタイル（実質的な明るさの範囲）
+    pub low_percentile: u8,
+    pub high_percentile: u8,
+    /// 真っ黒（輝度5以下）な画素の割合
+    pub clipped_shadows: f64,
+    /// 真っ白（輝度250以上）な画素の割合
+    pub clipped_highlights: f64,
+    /// 露出の判定
+    pub exposure: Exposure,
+}
+
+/// 画像をデコードしてページの統計を求める
+///
+/// # Errors
+/// 画像のデコードに失敗した場合
+pub fn page_stats(image_data: &[u8]) -> Result<PageStats, PamphletError> {
+    let image = decode::decode_image(image_data, &Settings::global())?;
+    Ok(image_stats(&image))
+}
+
+/// デコード済みの画像の統計を求める（完全に透明な画素は数えない）
+pub fn image_stats(image: &DynamicImage) -> PageStats {
+    let rgba: RgbaImage = image.to_rgba8();
+    let mut luminance = vec![0u32; 256];
+    let mut channels = [vec![0u32; 256], vec![0u32; 256], vec![0u32; 256]];
+
+    for pixel in rgba.pixels().filter(|pixel| pixel[3] > 0) {
+        for (channel, histogram) in channels.iter_mut().enumerate() {
+            histogram[pixel[channel] as usize] += 1;
+        }
+        luminance[luma(pixel.0) as usize] += 1;
+    }
+
+    let total: u64 = luminance.iter().map(|count| *count as u64).sum();
+    let count = total.max(1) as f64;
+    let mean = weighted_sum(&luminance, |value| value) / count;
+    let variance = weighted_sum(&luminance, |value| (value - mean).powi(2)) / count;
+    let clipped_shadows = luminance[..=SHADOW_CLIP as usize]
+        .iter()
+        .map(|count| *count as f64)
+        .sum::<f64>()
+        / count;
+    let clipped_highlights = luminance[HIGHLIGHT_CLIP as usize..]
+        .iter()
+        .map(|count| *count as f64)
+        .sum::<f64>()
+        / count;
+
+    let (low_percentile, high_percentile) = (
+        percentile(&luminance, total, 0.01),
+        percentile(&luminance, total, 0.99),
+    );
+    // 暗い側・明るい側にかたより、反対側の明るさがほとんど無いものを露出の失敗とする
+    // （白い紙の余白が多いだけのページは露出過多としない）
+    let exposure = if total == 0 {
+        Exposure::Normal
+    } else if mean < DARK_MEAN || clipped_shadows > MAX_CLIPPED_RATIO && high_percentile < MID_GRAY
+    {
+        Exposure::Under
+    } else if mean > BRIGHT_MEAN && low_percentile > MID_GRAY {
+        Exposure::Over
+    } else {
+        Exposure::Normal
+    };
+
+    let [red_histogram, green_histogram, blue_histogram] = channels;
+    PageStats {
+        width: rgba.width(),
+        height: rgba.height(),
+        low_percentile,
+        high_percentile,
+        luminance_histogram: luminance,
+        red_histogram,
+        green_histogram,
+        blue_histogram,
+        mean_brightness: mean,
+        contrast: variance.sqrt(),
+        clipped_shadows,
+        clipped_highlights,
+        exposure,
+    }
+}
+
+/// ITU-R BT.601の輝度
+fn luma([r, g, b, _]: [u8; 4]) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114 + 500) / 1000) as u8
+}
+
+/// ヒストグラムの値ごとに `f` をかけて足し合わせる
+fn weighted_sum(histogram: &[u32], f: impl Fn(f64) -> f64) -> f64 {
+    histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| f(value as f64) * *count as f64)
+        .sum()
+}
+
+/// ヒストグラムの `ratio`（0-1）の位置の値
+fn percentile(histogram: &[u32], total: u64, ratio: f64) -> u8 {
+    let target = ((total as f64 * ratio).ceil() as u64).max(1);
+    let mut count = 0u64;
+    for (value, bucket) in histogram.iter().enumerate() {
+        count += *bucket as u64;
+        if count >= target {
+            return value as u8;
+        }
+    }
+    255
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_image_stats() {
+        // 半分が黒、半分が白
+        let image = RgbaImage::from_fn(10, 10, |x, _| {
+            if x < 5 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let stats = image_stats(&DynamicImage::ImageRgba8(image));
+
+        assert_eq!((stats.width, stats.height), (10, 10));
+        assert_eq!(stats.luminance_histogram[0], 50);
+        assert_eq!(stats.luminance_histogram[255], 50);
+        assert_eq!(stats.red_histogram.iter().sum::<u32>(), 100);
+        assert!((stats.mean_brightness - 127.5).abs() < 1e-9);
+        assert!((stats.contrast - 127.5).abs() < 1e-9);
+        assert_eq!((stats.low_percentile, stats.high_percentile), (0, 255));
+        assert!((stats.clipped_shadows - 0.5).abs() < 1e-9);
+        assert!((stats.clipped_highlights - 0.5).abs() < 1e-9);
+        assert_eq!(stats.exposure, Exposure::Normal);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let exposure = |image: RgbaImage| image_stats(&DynamicImage::ImageRgba8(image)).exposure;
+
+        let dark = RgbaImage::from_fn(10, 10, |x, _| Rgba([x as u8 * 5, 0, 0, 255]));
+        assert_eq!(exposure(dark), Exposure::Under);
+
+        let washed_out = RgbaImage::from_fn(10, 10, |x, _| {
+            let value = if x == 0 { 200 } else { 255 };
+            Rgba([value, value, value, 255])
+        });
+        assert_eq!(exposure(washed_out), Exposure::Over);
+
+        let page = RgbaImage::from_fn(10, 10, |x, y| {
+            let value = if (x + y) % 5 == 0 { 30 } else { 235 };
+            Rgba([value, value, value, 255])
+        });
+        assert_eq!(exposure(page), Exposure::Normal);
+
+        // 透明な画素だけの画像
+        let transparent = RgbaImage::new(4, 4);
+        let stats = image_stats(&DynamicImage::ImageRgba8(transparent));
+        assert_eq!(stats.luminance_histogram.iter().sum::<u32>(), 0);
+        assert_eq!(stats.exposure, Exposure::Normal);
+    }
+}
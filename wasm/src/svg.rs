@@ -0,0 +1,101 @@
+use image::{DynamicImage, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{Options, Tree};
+
+use crate::error::PamphletError;
+use crate::tiler::{TileResult, TilerContext};
+
+/// ラスタライズの既定の解像度（DPI）
+pub const DEFAULT_SVG_DPI: f32 = 96.0;
+
+/// SVGの `px` 単位の解像度（CSSの定義で1インチ = 96px）
+const CSS_PIXELS_PER_INCH: f32 = 96.0;
+
+/// SVGをラスタライズする
+///
+/// 透明な部分は透明のまま残す。テキストは描画されないため、文字はアウトライン化して書き出すこと。
+///
+/// # Arguments
+/// * `data` - SVGのバイトデータ
+/// * `dpi` - 解像度（DPI、96でSVGの `px` と等倍）
+///
+/// # Errors
+/// SVGとして解釈できない場合や、画像が大きすぎて確保できない場合
+pub fn rasterize_svg(data: &[u8], dpi: f32) -> Result<RgbaImage, PamphletError> {
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err(PamphletError::InvalidArgument(format!(
+            "Invalid DPI: {}",
+            dpi
+        )));
+    }
+
+    let tree = Tree::from_data(data, &Options::default())
+        .map_err(|e| PamphletError::DecodeFailed(format!("Failed to parse SVG: {}", e)))?;
+
+    let scale = dpi / CSS_PIXELS_PER_INCH;
+    let size = tree.size();
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+
+    let mut pixmap = Pixmap::new(width, height).ok_or_else(|| {
+        PamphletError::DecodeFailed(format!("SVG is too large to rasterize at {} DPI", dpi))
+    })?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    RgbaImage::from_raw(width, height, pixmap.take_demultiplied())
+        .ok_or_else(|| PamphletError::Internal("Failed to read rendered SVG".to_string()))
+}
+
+/// SVGをラスタライズしてタイル化する
+///
+/// # Arguments
+/// * `data` - SVGのバイトデータ
+/// * `dpi` - 解像度（DPI）
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `quality` - WebP品質（1-100）
+/// * `context` - タイル化の設定と作業用バッファ
+pub fn tile_svg(
+    data: &[u8],
+    dpi: f32,
+    tile_size: u32,
+    quality: Option<f32>,
+    context: &mut TilerContext,
+) -> Result<TileResult, PamphletError> {
+    let image = rasterize_svg(data, dpi)?;
+    context.tile_decoded(&DynamicImage::ImageRgba8(image), tile_size, quality)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50">
+        <rect x="0" y="0" width="50" height="50" fill="#ff0000"/>
+    </svg>"##;
+
+    #[test]
+    fn test_rasterize_at_dpi() {
+        let image = rasterize_svg(SVG, 96.0).unwrap();
+        assert_eq!(image.dimensions(), (100, 50));
+        assert_eq!(image.get_pixel(10, 10).0, [255, 0, 0, 255]);
+        // 塗られていない部分は透明
+        assert_eq!(image.get_pixel(90, 10)[3], 0);
+
+        let image = rasterize_svg(SVG, 300.0).unwrap();
+        assert_eq!(image.dimensions(), (313, 157));
+
+        assert!(rasterize_svg(SVG, 0.0).is_err());
+        assert!(rasterize_svg(b"<html></html>", 96.0).is_err());
+    }
+
+    #[test]
+    fn test_tile_svg() {
+        let result = tile_svg(SVG, 192.0, 128, None, &mut TilerContext::new()).unwrap();
+        assert_eq!((result.width, result.height), (200, 100));
+        assert_eq!(result.tiles.len(), 2);
+    }
+}
@@ -0,0 +1,12 @@
+//! テスト用の共通のフィクスチャ（CLIのテストには `test-util` フィーチャーで公開する）
+
+use std::io::Cursor;
+
+/// 横方向のグラデーションのページ画像（PNG）。`shade` を変えると別の画像になる
+pub fn png(width: u32, height: u32, shade: u8) -> Vec<u8> {
+    let img = image::RgbImage::from_fn(width, height, |x, _| image::Rgb([x as u8, shade, 0]));
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    png
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// キャッシュエントリ
+#[derive(Debug)]
+struct CacheEntry {
+    data: Vec<u8>,
+    /// 最終アクセス時のティック（LRU順序のキー）
+    tick: u64,
+}
+
+/// バイト数上限付きのLRUタイルキャッシュ
+///
+/// 上限を超えると最も長く使われていないエントリから破棄する。
+/// ピン留めされたエントリ（現在のviewportのタイル等）は破棄されないため、
+/// ピン留め分だけで上限を超えることはありうる。
+#[derive(Debug)]
+pub struct TileCache {
+    entries: HashMap<String, CacheEntry>,
+    /// ティック → キー（古い順）
+    lru: BTreeMap<u64, String>,
+    pinned: HashSet<String>,
+    budget: usize,
+    used: usize,
+    tick: u64,
+}
+
+impl TileCache {
+    /// キャッシュを作成する
+    ///
+    /// # Arguments
+    /// * `budget` - 保持するデータの合計バイト数の上限
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            pinned: HashSet::new(),
+            budget,
+            used: 0,
+            tick: 0,
+        }
+    }
+
+    /// エントリを追加する（同じキーが存在する場合は置き換える）
+    ///
+    /// 上限を単独で超えるデータはキャッシュしない。
+    ///
+    /// # Returns
+    /// 上限を守るために破棄されたキー
+    pub fn insert(&mut self, key: String, data: Vec<u8>) -> Vec<String> {
+        self.remove(&key);
+
+        if data.len() > self.budget {
+            return Vec::new();
+        }
+
+        let tick = self.next_tick();
+        self.used += data.len();
+        self.lru.insert(tick, key.clone());
+        self.entries.insert(key, CacheEntry { data, tick });
+
+        self.evict_to_budget()
+    }
+
+    /// エントリを取得し、最近使用したものとして記録する
+    pub fn get(&mut self, key: &str) -> Option<&[u8]> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+
+        self.lru.remove(&entry.tick);
+        self.lru.insert(tick, key.to_string());
+        entry.tick = tick;
+
+        Some(&entry.data)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// エントリを削除する（ピン留めに関わらず削除）
+    ///
+    /// # Returns
+    /// 削除したかどうか
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.lru.remove(&entry.tick);
+                self.used -= entry.data.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ピン留めするキーを置き換える
+    ///
+    /// viewportが変わるたびに、表示中のタイルのキーを渡す想定。
+    /// ピン留めが外れたことで上限を超えている場合はその場で破棄する。
+    ///
+    /// # Returns
+    /// 破棄されたキー
+    pub fn set_pinned(&mut self, keys: impl IntoIterator<Item = String>) -> Vec<String> {
+        self.pinned = keys.into_iter().collect();
+        self.evict_to_budget()
+    }
+
+    /// 上限を変更する
+    ///
+    /// # Returns
+    /// 破棄されたキー
+    pub fn set_budget(&mut self, budget: usize) -> Vec<String> {
+        self.budget = budget;
+        self.evict_to_budget()
+    }
+
+    /// 全エントリを削除する（ピン留めも解除）
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.pinned.clear();
+        self.used = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 保持しているデータの合計バイト数
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// 上限以下になるまで、ピン留めされていないエントリを古い順に破棄する
+    fn evict_to_budget(&mut self) -> Vec<String> {
+        let mut evicted = Vec::new();
+        if self.used <= self.budget {
+            return evicted;
+        }
+
+        let candidates: Vec<String> = self
+            .lru
+            .values()
+            .filter(|key| !self.pinned.contains(*key))
+            .cloned()
+            .collect();
+
+        for key in candidates {
+            if self.used <= self.budget {
+                break;
+            }
+            self.remove(&key);
+            evicted.push(key);
+        }
+
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = TileCache::new(300);
+
+        cache.insert("a".to_string(), vec![0; 100]);
+        cache.insert("b".to_string(), vec![0; 100]);
+        cache.insert("c".to_string(), vec![0; 100]);
+
+        // aにアクセスしたので、次に古いのはb
+        assert!(cache.get("a").is_some());
+        let evicted = cache.insert("d".to_string(), vec![0; 100]);
+
+        assert_eq!(evicted, vec!["b".to_string()]);
+        assert_eq!(cache.used_bytes(), 300);
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+
+        // 上限を単独で超えるデータはキャッシュされない
+        assert!(cache.insert("huge".to_string(), vec![0; 301]).is_empty());
+        assert!(!cache.contains("huge"));
+    }
+
+    #[test]
+    fn test_pinned_entries_are_not_evicted() {
+        let mut cache = TileCache::new(200);
+
+        cache.insert("a".to_string(), vec![0; 100]);
+        cache.insert("b".to_string(), vec![0; 100]);
+        cache.set_pinned(vec!["a".to_string()]);
+
+        // 最も古いaはピン留めされているため、bが破棄される
+        let evicted = cache.insert("c".to_string(), vec![0; 100]);
+        assert_eq!(evicted, vec!["b".to_string()]);
+        assert!(cache.contains("a"));
+
+        // ピン留め分だけで上限を超える場合は一時的に超過する
+        cache.set_pinned(vec!["a".to_string(), "c".to_string()]);
+        assert!(cache.set_budget(100).is_empty());
+        assert_eq!(cache.used_bytes(), 200);
+
+        // ピン留めを外すと古い順に破棄される
+        assert_eq!(cache.set_pinned(Vec::new()), vec!["a".to_string()]);
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -1,8 +1,22 @@
-use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba};
+use std::collections::HashMap;
+use std::future::Future;
+
+use image::{DynamicImage, ExtendedColorType, GenericImageView, ImageBuffer, Rgba};
 use serde::{Deserialize, Serialize};
-use std::io::Cursor;
 
-use crate::hasher;
+use crate::decode::{self, AnimationMode};
+use crate::error::PamphletError;
+use crate::logging::log_with;
+use crate::preprocess::{self, PageGeometry, PreprocessOptions};
+use crate::settings::Settings;
+use crate::timing::{Stopwatch, TileTiming, Timings};
+use crate::{hasher, jpeg, measure, probe, PageInfo, TileMetadata};
+
+/// タイルサイズの最小値（ピクセル）
+pub const MIN_TILE_SIZE: u32 = 16;
+
+/// タイルサイズの最大値（ピクセル）
+pub const MAX_TILE_SIZE: u32 = 4096;
 
 /// タイル情報
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,8 +32,71 @@ pub struct TileInfo {
     pub data: Vec<u8>,
 }
 
+/// タイル化の設定（省略した項目は既定値）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileOptions {
+    /// タイルサイズ（ピクセル）
+    pub tile_size: u32,
+    /// WebP品質（1-100、省略時80）
+    pub quality: Option<f32>,
+    /// タイルの画像形式
+    pub format: TileFormat,
+    /// 端のタイルの埋め方
+    pub padding: EdgePadding,
+}
+
+impl TileOptions {
+    /// タイルサイズと品質を指定し、それ以外は既定値にする
+    pub fn new(tile_size: u32, quality: Option<f32>) -> Self {
+        Self {
+            tile_size,
+            quality,
+            ..Self::default()
+        }
+    }
+
+    /// 設定ファイル（`pamphlet.toml`、またはJSON）のタイル化の設定
+    ///
+    /// # Errors
+    /// 設定の誤り（`PamphletConfig::parse`）
+    pub fn from_config(text: &str) -> Result<Self, String> {
+        Ok(crate::config::PamphletConfig::parse(text)?.tile_options())
+    }
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 512,
+            quality: None,
+            format: TileFormat::default(),
+            padding: EdgePadding::default(),
+        }
+    }
+}
+
+/// タイルの画像形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TileFormat {
+    #[default]
+    Webp,
+}
+
+/// 端のタイル（画像の右端・下端でタイルサイズに満たないタイル）の埋め方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgePadding {
+    /// タイルサイズまで透明な画素で埋める
+    #[default]
+    Transparent,
+    /// 埋めずに画像の端までの大きさにする
+    None,
+}
+
 /// タイル化結果
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TileResult {
     /// 元画像の幅（ピクセル）
     pub width: u32,
@@ -29,119 +106,1052 @@ pub struct TileResult {
     pub tile_size: u32,
     /// タイル配列
     pub tiles: Vec<TileInfo>,
+    /// 処理時間の内訳（設定の `timing` で記録を有効にした場合）
+    #[serde(skip)]
+    pub timings: Option<Timings>,
+    /// タイル化は続けたが、呼び出し側に知らせる問題
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<TileWarning>,
 }
 
-/// 画像をタイル化する
-///
-/// # Arguments
-/// * `image_data` - 元画像のバイトデータ（JPEG/PNG等）
-/// * `tile_size` - タイルサイズ（ピクセル、例: 512）
-/// * `quality` - WebP品質（1-100、デフォルト: 80）
+/// タイル化を止めない問題（品質の指定を無視した、EXIFの向きを適用していないなど）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileWarning {
+    /// 機械で判別するためのコード（`"quality_ignored"` など）
+    pub code: String,
+    pub message: String,
+}
+
+impl TileResult {
+    /// metadataのページ情報（ページ番号は `page`）
+    pub fn page_info(&self, page: u32) -> PageInfo {
+        PageInfo {
+            page,
+            width: self.width,
+            height: self.height,
+            tiles: self
+                .tiles
+                .iter()
+                .map(|tile| TileMetadata {
+                    x: tile.x,
+                    y: tile.y,
+                    hash: tile.hash.clone(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl TileWarning {
+    fn new(code: &str, message: String) -> Self {
+        Self {
+            code: code.to_string(),
+            message,
+        }
+    }
+}
+
+/// エンコーダーが品質を使う（非可逆でエンコードできる）か
+pub const LOSSY_ENCODER: bool = cfg!(all(feature = "libwebp", not(target_arch = "wasm32")));
+
+/// 指定した品質をエンコーダーが使わない場合の警告
+pub(crate) fn quality_warnings(quality: Option<f32>) -> Vec<TileWarning> {
+    match quality {
+        Some(quality) if !LOSSY_ENCODER && quality < 100.0 => vec![TileWarning::new(
+            "quality_ignored",
+            format!(
+                "quality {} is ignored: this build encodes tiles losslessly",
+                quality
+            ),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// EXIFの向き（回転・反転）を適用せずにタイル化した場合、警告を加える
+fn with_orientation_warning(mut result: TileResult, image_data: &[u8]) -> TileResult {
+    let orientation = probe::probe_image(image_data)
+        .ok()
+        .and_then(|probe| probe.exif_orientation)
+        .filter(|&orientation| orientation > 1);
+    if let Some(orientation) = orientation {
+        log::info!("EXIF orientation {} is not applied", orientation);
+        result.warnings.push(TileWarning::new(
+            "exif_orientation_ignored",
+            format!(
+                "EXIF orientation {} is not applied: tiles keep the stored pixel layout",
+                orientation
+            ),
+        ));
+    }
+    result
+}
+
+/// 処理時間を記録している場合、デコードの時間を設定する
+fn with_decode_time(mut result: TileResult, decode_ms: f64) -> TileResult {
+    if let Some(timings) = &mut result.timings {
+        timings.decode_ms = decode_ms;
+    }
+    result
+}
+
+/// 非同期のタイル化でイベントループに戻る間隔（ミリ秒、60fpsの1フレームの半分）
+const YIELD_INTERVAL_MS: f64 = 8.0;
+
+/// タイルの合間に `yield_now` を待ちながら、設定に従って画像をタイル化する
 ///
-/// # Returns
-/// タイル化結果
+/// デコードの後と、前回戻ってから `YIELD_INTERVAL_MS` を超えて処理したタイルの後に
+/// `yield_now(処理したタイル数, 全タイル数)` を待つ。メインスレッドから呼んでも描画や
+/// 入力の処理が止まり続けないようにし、進捗の通知にも使う。結果は `tile_image_with` と同じ。
+/// `settings` で設定していない項目は、呼んだときのモジュール全体の設定に従う
 ///
 /// # Errors
 /// 画像のデコードやエンコードに失敗した場合
-pub fn tile_image(
+pub async fn tile_image_yielding<F, Fut>(
     image_data: &[u8],
+    options: &TileOptions,
+    settings: &Settings,
+    mut yield_now: F,
+) -> Result<TileResult, PamphletError>
+where
+    F: FnMut(u32, u32) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    validate_tile_size(options.tile_size)?;
+    let settings = settings.resolve();
+    let mut stopwatch = Stopwatch::new(settings.timing_enabled());
+    let img = decode::decode_image(image_data, &settings)?;
+    let decode_ms = stopwatch.lap();
+    let (tiles_x, tiles_y) = tile_counts(&img, options.tile_size);
+    let total = tiles_x * tiles_y;
+    yield_now(0, total).await;
+
+    let mut timings = settings.timing_enabled().then(Timings::default);
+    let mut context = TilerContext::with_settings(settings);
+    let mut tiles = Vec::new();
+    let mut slice = Stopwatch::new(true);
+    let mut elapsed = 0.0;
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            tiles.push(context.tile_at(&img, tx, ty, options, &mut timings)?);
+            elapsed += slice.lap();
+            if elapsed >= YIELD_INTERVAL_MS {
+                yield_now(tiles.len() as u32, total).await;
+                // 戻っていた間の時間は数えない
+                slice.lap();
+                elapsed = 0.0;
+            }
+        }
+    }
+
+    let result = tiled_result(&img, options, tiles, timings, &context.settings);
+    Ok(with_orientation_warning(
+        with_decode_time(result, decode_ms),
+        image_data,
+    ))
+}
+
+/// 横・縦のタイル数
+fn tile_counts(img: &DynamicImage, tile_size: u32) -> (u32, u32) {
+    (
+        img.width().div_ceil(tile_size),
+        img.height().div_ceil(tile_size),
+    )
+}
+
+/// 生成したタイルからタイル化の結果を作る
+fn tiled_result(
+    img: &DynamicImage,
+    options: &TileOptions,
+    tiles: Vec<TileInfo>,
+    timings: Option<Timings>,
+    settings: &Settings,
+) -> TileResult {
+    let (tiles_x, tiles_y) = tile_counts(img, options.tile_size);
+    log_with!(
+        settings,
+        log::Level::Debug,
+        "tiled {}x{} image into {}x{} tiles of {} pixels ({} bytes)",
+        img.width(),
+        img.height(),
+        tiles_x,
+        tiles_y,
+        options.tile_size,
+        tiles.iter().map(|tile| tile.data.len()).sum::<usize>()
+    );
+    TileResult {
+        width: img.width(),
+        height: img.height(),
+        tile_size: options.tile_size,
+        tiles,
+        timings,
+        warnings: quality_warnings(options.quality),
+    }
+}
+
+/// 拡大の元画像の解像度が指定されていなければ、画像に記録された解像度を使う
+fn with_source_dpi(image_data: &[u8], options: &PreprocessOptions) -> PreprocessOptions {
+    let mut options = options.clone();
+    if let Some(upscale) = &mut options.upscale {
+        if upscale.source_dpi.is_none() {
+            upscale.source_dpi = measure::detect_dpi(image_data);
+        }
+    }
+    options
+}
+
+/// タイルサイズが `MIN_TILE_SIZE` から `MAX_TILE_SIZE` の範囲か確かめる
+///
+/// # Errors
+/// 範囲外の場合（0ではタイルの数の計算が0除算になる）
+pub fn validate_tile_size(tile_size: u32) -> Result<(), PamphletError> {
+    if (MIN_TILE_SIZE..=MAX_TILE_SIZE).contains(&tile_size) {
+        Ok(())
+    } else {
+        Err(PamphletError::InvalidTileSize {
+            tile_size,
+            min: MIN_TILE_SIZE,
+            max: MAX_TILE_SIZE,
+        })
+    }
+}
+
+/// WebP品質を1-100に収める（NaNは省略と同じく既定の品質にする）
+pub fn clamp_quality(quality: Option<f32>) -> Option<f32> {
+    quality
+        .filter(|quality| !quality.is_nan())
+        .map(|quality| quality.clamp(1.0, 100.0))
+}
+
+/// エンコードを後回しにしたタイルの元の画像
+///
+/// タイルのハッシュは切り出した（端はパディングした）タイルのRGBAの画素から計算する。
+/// `tile_image` のハッシュ（WebPのデータから計算）とは異なるが、画素が同じタイルは
+/// 同じハッシュになる。`encode` で作るデータは `tile_image` のタイルと同じため、公開する
+/// 場合はエンコードしたデータからハッシュを計算し直す。
+#[derive(Debug)]
+pub struct LazyTiles {
+    image: DynamicImage,
     tile_size: u32,
     quality: Option<f32>,
-) -> Result<TileResult, String> {
-    // 画像をデコード
-    let img = image::load_from_memory(image_data)
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    /// タイルごとのエンコード済みか
+    encoded: Vec<bool>,
+    context: TilerContext,
+}
 
-    let width = img.width();
-    let height = img.height();
+impl LazyTiles {
+    /// タイルの配置と画素のハッシュを計算する（タイルのデータは空）
+    ///
+    /// # Errors
+    /// タイルの切り出しに失敗した場合
+    pub fn new(
+        image: DynamicImage,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<(TileResult, Self), PamphletError> {
+        let width = image.width();
+        let height = image.height();
+        let tiles_x = width.div_ceil(tile_size.max(1));
 
-    // タイル数を計算
-    let tiles_x = (width + tile_size - 1) / tile_size;
-    let tiles_y = (height + tile_size - 1) / tile_size;
+        let tiles: Vec<_> = (0u32..)
+            .zip(pixel_hashes(&image, tile_size)?)
+            .map(|(index, hash)| TileInfo {
+                x: index % tiles_x,
+                y: index / tiles_x,
+                hash,
+                data: Vec::new(),
+            })
+            .collect();
 
-    let mut tiles = Vec::new();
+        let lazy = Self {
+            image,
+            tile_size,
+            quality,
+            encoded: vec![false; tiles.len()],
+            context: TilerContext::new(),
+        };
+        let result = TileResult {
+            width,
+            height,
+            tile_size,
+            tiles,
+            timings: None,
+            warnings: quality_warnings(quality),
+        };
+        Ok((result, lazy))
+    }
+
+    /// 指定したインデックスのタイルをWebP形式にエンコードする
+    ///
+    /// エンコード済み（一度データを返した）のタイルや範囲外のインデックスは `None` を返す
+    ///
+    /// # Errors
+    /// エンコードに失敗した場合
+    pub fn encode(&mut self, index: usize) -> Result<Option<Vec<u8>>, PamphletError> {
+        match self.encoded.get(index) {
+            Some(false) => {}
+            _ => return Ok(None),
+        }
 
-    // 各タイルを生成
-    // 注: 全てのタイルを保持します（重複排除なし）
-    // これにより、フロントエンドで座標→ハッシュのマッピングが容易になります
+        let tiles_x = self.image.width().div_ceil(self.tile_size) as usize;
+        let x = (index % tiles_x) as u32 * self.tile_size;
+        let y = (index / tiles_x) as u32 * self.tile_size;
+        let w = self.tile_size.min(self.image.width() - x);
+        let h = self.tile_size.min(self.image.height() - y);
+        let data =
+            self.context
+                .encode_tile(&self.image, x, y, w, h, self.tile_size, self.quality)?;
+        self.encoded[index] = true;
+        Ok(Some(data))
+    }
+
+    /// すべてのタイルをエンコード済みか（元の画像は不要になる）
+    pub fn is_complete(&self) -> bool {
+        self.encoded.iter().all(|&encoded| encoded)
+    }
+
+    /// 保持している元の画像と作業用バッファのバイト数
+    pub fn retained_bytes(&self) -> usize {
+        self.image.as_bytes().len() + self.context.retained_bytes()
+    }
+}
+
+/// 画像のタイルの画素のハッシュ（`LazyTiles` のハッシュ、タイルの順）
+///
+/// 切り出した（端はパディングした）タイルのRGBAの画素から計算する。画素と設定が同じタイルは
+/// エンコードしても同じデータになるため、エンコードせずに変わったタイルを見つけられる
+///
+/// # Errors
+/// タイルサイズが範囲外の場合、タイルの切り出しに失敗した場合
+pub fn pixel_hashes(image: &DynamicImage, tile_size: u32) -> Result<Vec<String>, PamphletError> {
+    validate_tile_size(tile_size)?;
+    let (width, height) = (image.width(), image.height());
+    let (tiles_x, tiles_y) = tile_counts(image, tile_size);
+    let mut hashes = Vec::with_capacity((tiles_x * tiles_y) as usize);
     for ty in 0..tiles_y {
         for tx in 0..tiles_x {
-            // タイルの座標とサイズを計算
-            let x = tx * tile_size;
-            let y = ty * tile_size;
+            let (x, y) = (tx * tile_size, ty * tile_size);
             let w = tile_size.min(width - x);
             let h = tile_size.min(height - y);
+            let tile_img = crop_and_pad(image, x, y, w, h, tile_size)?;
+            hashes.push(hasher::calculate_hash(tile_img.to_rgba8().as_raw()));
+        }
+    }
+    Ok(hashes)
+}
+
+/// 一色のタイルのエンコード結果を覚えておく色の数の上限
+const MAX_UNIFORM_TILES: usize = 64;
+
+/// 一色かを全画素で確かめる前に、先に調べる画素の間隔
+const UNIFORM_SAMPLE_STEP: usize = 61;
+
+/// タイル化の作業用バッファ
+///
+/// 連続して多数のページをタイル化するとき、デコードした画素・切り出したタイル・
+/// エンコード中のWebPの領域をページをまたいで使い回し、ページごとの数MBの確保
+/// （とWASMのメモリの拡張）を繰り返さないようにする。結果は `tile_image` と同じ。
+#[derive(Debug, Default)]
+pub struct TilerContext {
+    /// デコードした画素
+    pixels: Vec<u8>,
+    /// 切り出したタイルの画素
+    tile: Vec<u8>,
+    /// エンコード中のWebP
+    encoded: Vec<u8>,
+    /// 一色のタイルのエンコード結果（余白などの同じタイルはエンコーダーを通さない）
+    uniform: HashMap<UniformTile, Vec<u8>>,
+    /// 設定（設定していない項目は処理のたびにモジュール全体の設定に従う）
+    settings: Settings,
+}
+
+/// 一色のタイルの形式と色（と品質）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct UniformTile {
+    width: u32,
+    height: u32,
+    color: ExtendedColorType,
+    /// 画素の値（`color` の1画素分のバイト、残りは0）
+    pixel: [u8; 4],
+    /// 品質（`f32` のビット列）
+    quality: u32,
+}
+
+impl TilerContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 設定（デコードの上限・処理時間の記録など）を持つ作業用バッファ
+    pub fn with_settings(settings: Settings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// 設定を置き換える（作業用バッファはそのまま使う）
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+    }
+
+    /// 設定に従って画像をデコードしてタイル化する
+    ///
+    /// # Errors
+    /// 画像のデコードやエンコードに失敗した場合
+    pub fn tile_image_with(
+        &mut self,
+        image_data: &[u8],
+        options: &TileOptions,
+    ) -> Result<TileResult, PamphletError> {
+        let settings = self.settings.resolve();
+        let mut stopwatch = Stopwatch::new(settings.timing_enabled());
+        let img =
+            decode::decode_image_into(image_data, std::mem::take(&mut self.pixels), &settings)?;
+        let decode_ms = stopwatch.lap();
+        let result = self.tile_resolved(&img, options, &settings);
+        self.pixels = decode::into_buffer(img);
+        Ok(with_orientation_warning(
+            with_decode_time(result?, decode_ms),
+            image_data,
+        ))
+    }
+
+    /// 画像に前処理（傾き補正など）を適用してからタイル化する
+    ///
+    /// # Arguments
+    /// * `image_data` - 元画像のバイトデータ
+    /// * `tile_size` - タイルサイズ（ピクセル）
+    /// * `quality` - WebP品質（1-100、デフォルト: 80）
+    /// * `options` - 前処理の設定
+    ///
+    /// # Returns
+    /// タイル化結果と、自動切り抜きの範囲・拡大の倍率
+    pub fn tile_image_preprocessed(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        options: &PreprocessOptions,
+    ) -> Result<(TileResult, PageGeometry), PamphletError> {
+        let settings = self.settings.resolve();
+        let img = decode::decode_image(image_data, &settings)?;
+        let preprocessed = preprocess::preprocess(img, &with_source_dpi(image_data, options));
+        let options = TileOptions::new(tile_size, quality);
+        let result = self.tile_resolved(&preprocessed.image, &options, &settings)?;
+        Ok((result, preprocessed.geometry))
+    }
+
+    /// 見開きのスキャンを左右のページに分割し、前処理を適用してからタイル化する
+    ///
+    /// 見開きでない画像は1ページとしてタイル化する
+    ///
+    /// # Returns
+    /// ページ（左 → 右）ごとのタイル化結果と、元の画像（傾き補正の後）での範囲・拡大の倍率
+    pub fn tile_image_split(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        options: &PreprocessOptions,
+    ) -> Result<Vec<(TileResult, PageGeometry)>, PamphletError> {
+        let settings = self.settings.resolve();
+        let img = decode::decode_image(image_data, &settings)?;
+        let tile_options = TileOptions::new(tile_size, quality);
+        preprocess::preprocess_pages(img, &with_source_dpi(image_data, options))
+            .into_iter()
+            .map(|page| {
+                let result = self.tile_resolved(&page.image, &tile_options, &settings)?;
+                Ok((result, page.geometry))
+            })
+            .collect()
+    }
 
-            // タイルを切り出し
-            let tile_img = crop_and_pad(&img, x, y, w, h, tile_size)?;
+    /// 画像を縮小してタイル化する（ピラミッドの下位レベル用）
+    ///
+    /// JPEG 2000の入力は解像度レベルの構造を使い、原寸を復号せずに縮小版を得る
+    ///
+    /// # Arguments
+    /// * `image_data` - 元画像のバイトデータ
+    /// * `level` - 縮小レベル（0 = 原寸、1 = 1/2、2 = 1/4 ...）
+    /// * `tile_size` - タイルサイズ（ピクセル）
+    /// * `quality` - WebP品質（1-100、デフォルト: 80）
+    pub fn tile_image_level(
+        &mut self,
+        image_data: &[u8],
+        level: u32,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<TileResult, PamphletError> {
+        let settings = self.settings.resolve();
+        let img = decode::decode_image_reduced(image_data, level, &settings)?;
+        self.tile_resolved(&img, &TileOptions::new(tile_size, quality), &settings)
+    }
 
-            // WebP形式にエンコード
-            let webp_data = encode_webp(&tile_img, quality.unwrap_or(80.0))?;
+    /// 画像をページ単位でタイル化する
+    ///
+    /// アニメーション（GIF・APNG・アニメーションWebP）は `animation` に従って
+    /// 最初のフレームだけ、または各フレームを別のページとしてタイル化する
+    pub fn tile_image_pages(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+        animation: AnimationMode,
+    ) -> Result<Vec<TileResult>, PamphletError> {
+        let settings = self.settings.resolve();
+        let options = TileOptions::new(tile_size, quality);
+        decode::decode_pages(image_data, animation, &settings)?
+            .iter()
+            .map(|img| self.tile_resolved(img, &options, &settings))
+            .collect()
+    }
 
-            // ハッシュを計算（タイル識別用）
-            let hash = hasher::calculate_hash(&webp_data);
+    /// JPEGをタイルの行ごとの帯に分けてデコードしながらタイル化する
+    ///
+    /// ページ全体をデコードせず、タイル1行分の高さの帯だけを持つため、縦長のページでは
+    /// メモリの使用量の最大値が大きく下がる。画素（とタイルのハッシュ）は
+    /// `tile_image_with` の結果と同じ。帯に分けてデコードできないJPEG（プログレッシブ・
+    /// 壊れたデータなど）やJPEG以外の画像は `tile_image_with` と同じくページ全体を
+    /// デコードする。
+    ///
+    /// # Errors
+    /// 画像のデコードやエンコードに失敗した場合
+    pub fn tile_image_banded(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<TileResult, PamphletError> {
+        let full = |context: &mut Self| {
+            context.tile_image_with(image_data, &TileOptions::new(tile_size, quality))
+        };
+        let decoder = match image::guess_format(image_data) {
+            Ok(image::ImageFormat::Jpeg) => jpeg::BandDecoder::new(image_data).ok().flatten(),
+            _ => None,
+        };
+        let Some(mut decoder) = decoder else {
+            return full(self);
+        };
+        validate_tile_size(tile_size)?;
+        let settings = self.settings.resolve();
+        decode::check_format(image_data, &settings)?;
 
-            tiles.push(TileInfo {
+        let (width, height) = decoder.dimensions();
+        settings.limits().check(width, height)?;
+        let options = TileOptions::new(tile_size, quality);
+        let mut timings = settings.timing_enabled().then(Timings::default);
+        let mut tiles = Vec::new();
+        let mut buffer = Vec::new();
+        for ty in 0..height.div_ceil(tile_size) {
+            let mut stopwatch = Stopwatch::new(timings.is_some());
+            // 途中で壊れたデータがあれば、通常のデコード（壊れた所までの画像）に切り替える
+            let Ok(band) = decoder.next_band(tile_size, buffer) else {
+                return full(self);
+            };
+            let decode_ms = stopwatch.lap();
+            let result = self.tile_resolved(&band, &options, &settings)?;
+            tiles.extend(
+                result
+                    .tiles
+                    .into_iter()
+                    .map(|tile| TileInfo { y: ty, ..tile }),
+            );
+            if let (Some(timings), Some(band_timings)) = (&mut timings, result.timings) {
+                timings.decode_ms += decode_ms;
+                for tile in band_timings.tiles {
+                    timings.push(TileTiming { y: ty, ..tile });
+                }
+            }
+            buffer = decode::into_buffer(band);
+        }
+
+        let result = TileResult {
+            width,
+            height,
+            tile_size,
+            tiles,
+            timings,
+            warnings: quality_warnings(quality),
+        };
+        Ok(with_orientation_warning(result, image_data))
+    }
+
+    /// WebPのエンコードを後回しにして画像をタイル化する
+    ///
+    /// 配置と画素のハッシュだけを先に計算し、タイルのデータは空のまま返す。データは
+    /// 返した `LazyTiles` の `encode` で必要になったときに作る。変更の検出などで
+    /// ハッシュだけが必要な場合に、すべてのタイルをエンコードする時間を省ける。
+    /// 返した `LazyTiles` もこのコンテキストの設定でエンコードする。
+    ///
+    /// # Errors
+    /// 画像のデコードに失敗した場合
+    pub fn tile_image_lazy(
+        &mut self,
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<(TileResult, LazyTiles), PamphletError> {
+        let img = decode::decode_image(image_data, &self.settings.resolve())?;
+        let (result, mut lazy) = LazyTiles::new(img, tile_size, quality)?;
+        lazy.context.set_settings(self.settings.clone());
+        Ok((with_orientation_warning(result, image_data), lazy))
+    }
+
+    /// デコード済みの画像をタイル化する
+    ///
+    /// PDFのラスタライズ結果など、エンコードされたバイト列を経由しない入力に使う
+    ///
+    /// # Errors
+    /// タイルのエンコードに失敗した場合
+    pub fn tile_decoded(
+        &mut self,
+        img: &DynamicImage,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<TileResult, PamphletError> {
+        self.tile_decoded_with(img, &TileOptions::new(tile_size, quality))
+    }
+
+    /// 設定に従ってデコード済みの画像をタイル化する
+    pub fn tile_decoded_with(
+        &mut self,
+        img: &DynamicImage,
+        options: &TileOptions,
+    ) -> Result<TileResult, PamphletError> {
+        let settings = self.settings.resolve();
+        self.tile_resolved(img, options, &settings)
+    }
+
+    /// モジュール全体の設定で補った `settings` に従ってデコード済みの画像をタイル化する
+    fn tile_resolved(
+        &mut self,
+        img: &DynamicImage,
+        options: &TileOptions,
+        settings: &Settings,
+    ) -> Result<TileResult, PamphletError> {
+        validate_tile_size(options.tile_size)?;
+        let (tiles_x, tiles_y) = tile_counts(img, options.tile_size);
+
+        let mut tiles = Vec::new();
+        let mut timings = settings.timing_enabled().then(Timings::default);
+
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        if tiles_x * tiles_y > 1 && rayon::current_num_threads() > 1 {
+            for (tile, tile_timings) in self.tile_parallel(img, options, settings)? {
+                if let (Some(timings), Some(tile_timings)) = (&mut timings, tile_timings) {
+                    tile_timings
+                        .tiles
+                        .into_iter()
+                        .for_each(|tile| timings.push(tile));
+                }
+                tiles.push(tile);
+            }
+            return Ok(tiled_result(img, options, tiles, timings, settings));
+        }
+
+        // 各タイルを生成
+        // 注: 全てのタイルを保持します（重複排除なし）
+        // これにより、フロントエンドで座標→ハッシュのマッピングが容易になります
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                tiles.push(self.tile_at(img, tx, ty, options, &mut timings)?);
+            }
+        }
+
+        Ok(tiled_result(img, options, tiles, timings, settings))
+    }
+
+    /// 1つのタイルを切り出してWebP形式にエンコードし、処理時間を記録する
+    fn tile_at(
+        &mut self,
+        img: &DynamicImage,
+        tx: u32,
+        ty: u32,
+        options: &TileOptions,
+        timings: &mut Option<Timings>,
+    ) -> Result<TileInfo, PamphletError> {
+        let tile_size = options.tile_size;
+        let pad = options.padding == EdgePadding::Transparent;
+
+        // タイルの座標とサイズを計算
+        let x = tx * tile_size;
+        let y = ty * tile_size;
+        let w = tile_size.min(img.width() - x);
+        let h = tile_size.min(img.height() - y);
+
+        let mut stopwatch = Stopwatch::new(timings.is_some());
+        let cropped = self.crop_tile(img, x, y, w, h, tile_size, pad)?;
+        let crop_ms = stopwatch.lap();
+        let webp_data = self.encode_cropped(cropped, options.quality)?;
+        let encode_ms = stopwatch.lap();
+
+        // ハッシュを計算（タイル識別用）
+        let hash = hasher::calculate_hash(&webp_data);
+
+        if let Some(timings) = timings {
+            timings.push(TileTiming {
                 x: tx,
                 y: ty,
-                hash,
-                data: webp_data,
+                crop_ms,
+                encode_ms,
+                hash_ms: stopwatch.lap(),
+                bytes: webp_data.len(),
             });
         }
+        Ok(TileInfo {
+            x: tx,
+            y: ty,
+            hash,
+            data: webp_data,
+        })
     }
 
-    Ok(TileResult {
-        width,
-        height,
-        tile_size,
-        tiles,
-    })
+    /// タイルを並列にエンコードする（ネイティブのみ、`parallel`）
+    ///
+    /// rayonのスレッドごとに、このコンテキストの設定と一色のタイルのエンコード結果を引き継いだ
+    /// 作業用バッファを持ち、終わったらスレッドで増えたエンコード結果をこのコンテキストに戻す。
+    /// タイルの順と内容は1つずつエンコードした場合と同じ。処理時間はタイルごとに返す
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    fn tile_parallel(
+        &mut self,
+        img: &DynamicImage,
+        options: &TileOptions,
+        settings: &Settings,
+    ) -> Result<Vec<(TileInfo, Option<Timings>)>, PamphletError> {
+        use rayon::prelude::*;
+
+        let (tiles_x, tiles_y) = tile_counts(img, options.tile_size);
+        let this = &*self;
+        let workers: Vec<(TilerContext, Vec<_>)> = (0..tiles_x * tiles_y)
+            .into_par_iter()
+            .fold(
+                || (this.worker(), Vec::new()),
+                |(mut context, mut tiles), index| {
+                    let mut timings = settings.timing_enabled().then(Timings::default);
+                    let (tx, ty) = (index % tiles_x, index / tiles_x);
+                    let tile = context.tile_at(img, tx, ty, options, &mut timings);
+                    tiles.push((index, tile.map(|tile| (tile, timings))));
+                    (context, tiles)
+                },
+            )
+            .collect();
+
+        let mut tiles = Vec::with_capacity((tiles_x * tiles_y) as usize);
+        for (context, worker_tiles) in workers {
+            for (key, data) in context.uniform {
+                if self.uniform.len() >= MAX_UNIFORM_TILES {
+                    break;
+                }
+                self.uniform.entry(key).or_insert(data);
+            }
+            tiles.extend(worker_tiles);
+        }
+        tiles.sort_unstable_by_key(|(index, _)| *index);
+        tiles.into_iter().map(|(_, tile)| tile).collect()
+    }
+
+    /// 並列のエンコードのスレッドの作業用バッファ（設定と一色のタイルのエンコード結果を引き継ぐ）
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    fn worker(&self) -> TilerContext {
+        TilerContext {
+            uniform: self.uniform.clone(),
+            ..TilerContext::with_settings(self.settings.clone())
+        }
+    }
+
+    /// 保持している作業用バッファのバイト数
+    pub fn retained_bytes(&self) -> usize {
+        let uniform: usize = self.uniform.values().map(Vec::capacity).sum();
+        self.pixels.capacity() + self.tile.capacity() + self.encoded.capacity() + uniform
+    }
+
+    /// 作業用バッファを解放する（設定はそのまま）
+    pub fn release(&mut self) {
+        *self = Self::with_settings(std::mem::take(&mut self.settings));
+    }
+
+    /// タイルを切り出してエンコードする
+    #[allow(clippy::too_many_arguments)]
+    fn encode_tile(
+        &mut self,
+        img: &DynamicImage,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<Vec<u8>, PamphletError> {
+        let cropped = self.crop_tile(img, x, y, w, h, tile_size, true)?;
+        self.encode_cropped(cropped, quality)
+    }
+
+    /// タイルを切り出す
+    ///
+    /// 8bitの画素形式で端でない（または `pad` しない）タイルは作業用バッファに書き写し、
+    /// それ以外は `crop_and_pad` でパディングする（どちらもエンコードした結果は同じ）
+    #[allow(clippy::too_many_arguments)]
+    fn crop_tile(
+        &mut self,
+        img: &DynamicImage,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        tile_size: u32,
+        pad: bool,
+    ) -> Result<CroppedTile, PamphletError> {
+        let full = w == tile_size && h == tile_size;
+        let raw = raw_pixels(img).filter(|_| full || !pad);
+        let Some((pixels, color)) = raw else {
+            if !pad {
+                return Ok(CroppedTile::Image(img.crop_imm(x, y, w, h)));
+            }
+            return crop_and_pad(img, x, y, w, h, tile_size).map(CroppedTile::Image);
+        };
+
+        let bytes_per_pixel = color.bits_per_pixel() as usize / 8;
+        let row_bytes = w as usize * bytes_per_pixel;
+        self.tile.clear();
+        for row in y..y + h {
+            let start = (row as usize * img.width() as usize + x as usize) * bytes_per_pixel;
+            let end = start + row_bytes;
+            self.tile.extend_from_slice(&pixels[start..end]);
+        }
+        Ok(CroppedTile::Buffer {
+            width: w,
+            height: h,
+            color,
+        })
+    }
+
+    /// 切り出したタイルをWebP形式にエンコードする
+    ///
+    /// 一色のタイルは、同じ色のタイルを一度エンコードした結果を使い回す
+    fn encode_cropped(
+        &mut self,
+        cropped: CroppedTile,
+        quality: Option<f32>,
+    ) -> Result<Vec<u8>, PamphletError> {
+        let quality = quality.unwrap_or(80.0);
+        let (width, height, color) = match cropped {
+            CroppedTile::Image(tile_img) => return encode_webp(&tile_img, quality),
+            CroppedTile::Buffer {
+                width,
+                height,
+                color,
+            } => (width, height, color),
+        };
+
+        let bytes_per_pixel = color.bits_per_pixel() as usize / 8;
+        let key = uniform_pixel(&self.tile, bytes_per_pixel).map(|value| {
+            let mut pixel = [0; 4];
+            pixel[..bytes_per_pixel].copy_from_slice(value);
+            UniformTile {
+                width,
+                height,
+                color,
+                pixel,
+                quality: quality.to_bits(),
+            }
+        });
+        if let Some(data) = key.and_then(|key| self.uniform.get(&key)) {
+            return Ok(data.clone());
+        }
+
+        self.encoded.clear();
+        encode_pixels(&self.tile, width, height, color, quality, &mut self.encoded)?;
+        if let Some(key) = key.filter(|_| self.uniform.len() < MAX_UNIFORM_TILES) {
+            self.uniform.insert(key, self.encoded.clone());
+        }
+        Ok(self.encoded.clone())
+    }
+}
+
+/// すべての画素が同じ値なら、その1画素分のバイト
+///
+/// 間隔を空けた画素を先に調べ、写真などのタイルは全画素を見ずに早く打ち切る
+fn uniform_pixel(pixels: &[u8], bytes_per_pixel: usize) -> Option<&[u8]> {
+    if !(1..=4).contains(&bytes_per_pixel) {
+        return None;
+    }
+    let first = pixels.get(..bytes_per_pixel)?;
+    let mut chunks = pixels.chunks_exact(bytes_per_pixel);
+    let mut samples = chunks.clone().step_by(UNIFORM_SAMPLE_STEP);
+    (samples.all(|p| p == first) && chunks.all(|p| p == first)).then_some(first)
 }
 
+/// 切り出したタイル
+enum CroppedTile {
+    /// パディングした（または8bitでない）画像
+    Image(DynamicImage),
+    /// 作業用バッファに書き写した画素
+    Buffer {
+        width: u32,
+        height: u32,
+        color: ExtendedColorType,
+    },
+}
+
+/// パディングの画素（透明な白）
+const PADDING: Rgba<u8> = Rgba([255, 255, 255, 0]);
+
 /// 画像を切り出し、必要に応じてパディングする
 ///
-/// タイルサイズに満たない場合は、透明ピクセルでパディング
-fn crop_and_pad(
+/// タイルサイズに満たない場合は、透明ピクセルでパディングしたRGBAの画像に
+/// 切り出す範囲の画素を1回だけ書き写す（完全に透明な画素はパディングと同じ画素にする）
+///
+/// 半透明な画素は元の値のまま書き写す（`imageops::overlay` で重ねた場合の合成の丸めはない）
+pub fn crop_and_pad(
     img: &DynamicImage,
     x: u32,
     y: u32,
     w: u32,
     h: u32,
     tile_size: u32,
-) -> Result<DynamicImage, String> {
-    // 切り出し
-    let mut cropped = img.crop_imm(x, y, w, h);
+) -> Result<DynamicImage, PamphletError> {
+    // パディングが不要な場合は切り出すだけ
+    if w >= tile_size && h >= tile_size {
+        return Ok(img.crop_imm(x, y, w, h));
+    }
 
-    // パディングが必要な場合
-    if w < tile_size || h < tile_size {
-        let mut padded: ImageBuffer<Rgba<u8>, Vec<u8>> =
-            ImageBuffer::from_pixel(tile_size, tile_size, Rgba([255, 255, 255, 0]));
+    let mut padded: ImageBuffer<Rgba<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(tile_size, tile_size, PADDING);
+    let (w, h) = (w.min(tile_size), h.min(tile_size));
+    let row_bytes = tile_size as usize * 4;
+    let rows = padded.chunks_exact_mut(row_bytes);
 
-        // 切り出した画像を左上に配置
-        image::imageops::overlay(&mut padded, &cropped.to_rgba8(), 0, 0);
-        cropped = DynamicImage::ImageRgba8(padded);
+    match raw_pixels(img) {
+        Some((pixels, color)) => {
+            let bytes_per_pixel = color.bits_per_pixel() as usize / 8;
+            for (dy, row) in rows.take(h as usize).enumerate() {
+                let start =
+                    ((y as usize + dy) * img.width() as usize + x as usize) * bytes_per_pixel;
+                let source = &pixels[start..start + w as usize * bytes_per_pixel];
+                for (target, pixel) in row
+                    .chunks_exact_mut(4)
+                    .zip(source.chunks_exact(bytes_per_pixel))
+                {
+                    let rgba = match *pixel {
+                        [l] => [l, l, l, 255],
+                        [l, a] => [l, l, l, a],
+                        [r, g, b] => [r, g, b, 255],
+                        [r, g, b, a] => [r, g, b, a],
+                        _ => unreachable!("8bitの画素は1-4バイト"),
+                    };
+                    if rgba[3] != 0 {
+                        target.copy_from_slice(&rgba);
+                    }
+                }
+            }
+        }
+        None => {
+            // 16bit・浮動小数点の画素は1画素ずつ8bitのRGBAに変換する
+            for (dy, row) in rows.take(h as usize).enumerate() {
+                for (dx, target) in row.chunks_exact_mut(4).take(w as usize).enumerate() {
+                    let pixel = img.get_pixel(x + dx as u32, y + dy as u32);
+                    if pixel[3] != 0 {
+                        target.copy_from_slice(&pixel.0);
+                    }
+                }
+            }
+        }
     }
 
-    Ok(cropped)
+    Ok(DynamicImage::ImageRgba8(padded))
 }
 
 /// 画像をWebP形式にエンコード
 ///
-/// 注: 現在、品質パラメータは未サポートです。
-/// image crateのWebPエンコーダーはデフォルト品質を使用します。
-fn encode_webp(img: &DynamicImage, _quality: f32) -> Result<Vec<u8>, String> {
-    let mut buffer = Cursor::new(Vec::new());
+/// 注: `libwebp` フィーチャーが無効な場合、品質パラメータは未サポートです。
+/// image crateのWebPエンコーダーは可逆でエンコードします。
+#[cfg(not(all(feature = "libwebp", not(target_arch = "wasm32"))))]
+pub fn encode_webp(img: &DynamicImage, _quality: f32) -> Result<Vec<u8>, PamphletError> {
+    let mut buffer = std::io::Cursor::new(Vec::new());
 
     // WebPエンコーダを使用（品質パラメータは現在未サポート）
-    img.write_to(&mut buffer, ImageFormat::WebP)
-        .map_err(|e| format!("Failed to encode WebP: {}", e))?;
+    img.write_to(&mut buffer, image::ImageFormat::WebP)
+        .map_err(|e| PamphletError::EncodeFailed(format!("Failed to encode WebP: {}", e)))?;
 
     Ok(buffer.into_inner())
 }
 
+/// 画像をWebP形式にエンコード（libwebp、品質100以上は可逆）
+#[cfg(all(feature = "libwebp", not(target_arch = "wasm32")))]
+pub fn encode_webp(img: &DynamicImage, quality: f32) -> Result<Vec<u8>, PamphletError> {
+    let rgba;
+    let (pixels, color) = match raw_pixels(img) {
+        Some(raw) => raw,
+        None => {
+            rgba = img.to_rgba8();
+            (rgba.as_raw().as_slice(), ExtendedColorType::Rgba8)
+        }
+    };
+    let (width, height) = (img.width(), img.height());
+    let mut buffer = Vec::new();
+    encode_pixels(pixels, width, height, color, quality, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// 8bitの画素形式の画像の画素と形式（それ以外は `None`）
+fn raw_pixels(img: &DynamicImage) -> Option<(&[u8], ExtendedColorType)> {
+    match img {
+        DynamicImage::ImageLuma8(buffer) => Some((buffer.as_raw(), ExtendedColorType::L8)),
+        DynamicImage::ImageLumaA8(buffer) => Some((buffer.as_raw(), ExtendedColorType::La8)),
+        DynamicImage::ImageRgb8(buffer) => Some((buffer.as_raw(), ExtendedColorType::Rgb8)),
+        DynamicImage::ImageRgba8(buffer) => Some((buffer.as_raw(), ExtendedColorType::Rgba8)),
+        _ => None,
+    }
+}
+
+/// 8bitの画素をWebP形式にエンコードし、`out` に書き込む
+///
+/// `libwebp` フィーチャーが無効な場合は `encode_webp` と同じく可逆でエンコードする
+#[cfg(not(all(feature = "libwebp", not(target_arch = "wasm32"))))]
+fn encode_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    color: ExtendedColorType,
+    _quality: f32,
+    out: &mut Vec<u8>,
+) -> Result<(), PamphletError> {
+    image::codecs::webp::WebPEncoder::new_lossless(out)
+        .encode(pixels, width, height, color)
+        .map_err(|e| PamphletError::EncodeFailed(format!("Failed to encode WebP: {}", e)))
+}
+
+#[cfg(all(feature = "libwebp", not(target_arch = "wasm32")))]
+fn encode_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    color: ExtendedColorType,
+    quality: f32,
+    out: &mut Vec<u8>,
+) -> Result<(), PamphletError> {
+    crate::libwebp::encode(pixels, width, height, color, quality, out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use image::ImageFormat;
+    use std::io::Cursor;
+
+    fn tile_image(
+        image_data: &[u8],
+        tile_size: u32,
+        quality: Option<f32>,
+    ) -> Result<TileResult, PamphletError> {
+        TilerContext::new().tile_image_with(image_data, &TileOptions::new(tile_size, quality))
+    }
 
     #[test]
     fn test_tile_image() {
@@ -165,6 +1175,30 @@ mod tests {
         assert_eq!(result.tiles.len(), 4); // 2x2タイル
     }
 
+    #[test]
+    fn test_validate_tile_size() {
+        assert!(validate_tile_size(16).is_ok());
+        assert!(validate_tile_size(4096).is_ok());
+        assert_eq!(
+            validate_tile_size(0),
+            Err(PamphletError::InvalidTileSize {
+                tile_size: 0,
+                min: 16,
+                max: 4096
+            })
+        );
+        assert!(validate_tile_size(8192).is_err());
+
+        let img = DynamicImage::new_rgb8(10, 10);
+        assert!(TilerContext::new().tile_decoded(&img, 0, None).is_err());
+        assert!(LazyTiles::new(img, 0, None).is_err());
+
+        assert_eq!(clamp_quality(Some(0.0)), Some(1.0));
+        assert_eq!(clamp_quality(Some(250.0)), Some(100.0));
+        assert_eq!(clamp_quality(Some(f32::NAN)), None);
+        assert_eq!(clamp_quality(None), None);
+    }
+
     #[test]
     fn test_crop_and_pad() {
         let img: ImageBuffer<Rgba<u8>, Vec<u8>> =
@@ -176,5 +1210,553 @@ mod tests {
 
         assert_eq!(result.width(), 64);
         assert_eq!(result.height(), 64);
+
+        // 切り出してから重ねる方法と同じ画素になる
+        let images = [
+            DynamicImage::ImageRgb8(image::RgbImage::from_fn(50, 40, |x, y| {
+                image::Rgb([x as u8, y as u8, 7])
+            })),
+            DynamicImage::ImageLumaA8(image::GrayAlphaImage::from_fn(50, 40, |x, y| {
+                image::LumaA([(x * y) as u8, if x % 3 == 0 { 0 } else { 255 }])
+            })),
+            DynamicImage::ImageRgba8(ImageBuffer::from_fn(50, 40, |x, y| {
+                Rgba([x as u8, 9, y as u8, if y % 2 == 0 { 0 } else { 255 }])
+            })),
+            DynamicImage::ImageRgb16(ImageBuffer::from_fn(50, 40, |x, y| {
+                image::Rgb([x as u16 * 1000, y as u16 * 1500, 40000])
+            })),
+        ];
+        for img in &images {
+            let padded = crop_and_pad(img, 32, 16, 18, 24, 32).unwrap();
+            let mut expected = ImageBuffer::from_pixel(32, 32, PADDING);
+            let cropped = img.crop_imm(32, 16, 18, 24).to_rgba8();
+            image::imageops::overlay(&mut expected, &cropped, 0, 0);
+            assert_eq!(padded.to_rgba8(), expected);
+        }
+    }
+
+    /// 以前の実装（切り出してRGBAにし、パディングが必要なら透明な画像に `imageops::overlay` で重ねる）
+    fn overlay_pad(
+        img: &DynamicImage,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        tile_size: u32,
+    ) -> image::RgbaImage {
+        let cropped = img.crop_imm(x, y, w, h).to_rgba8();
+        if w >= tile_size && h >= tile_size {
+            return cropped;
+        }
+        let mut padded = ImageBuffer::from_pixel(tile_size, tile_size, PADDING);
+        image::imageops::overlay(&mut padded, &cropped, 0, 0);
+        padded
+    }
+
+    #[test]
+    fn test_crop_and_pad_matches_overlay() {
+        // タイルサイズで割り切れない大きさの、半透明の画素を含む画像
+        let alpha = |x: u32, y: u32| ((x * 37 + y * 11) % 256) as u8;
+        let images = [
+            DynamicImage::ImageRgba8(ImageBuffer::from_fn(75, 53, |x, y| {
+                Rgba([x as u8 * 3, y as u8 * 4, 200, alpha(x, y)])
+            })),
+            DynamicImage::ImageLumaA8(image::GrayAlphaImage::from_fn(75, 53, |x, y| {
+                image::LumaA([(x + y) as u8, alpha(x, y)])
+            })),
+            DynamicImage::ImageLuma8(image::GrayImage::from_fn(75, 53, |x, y| {
+                image::Luma([(x * y) as u8])
+            })),
+            DynamicImage::ImageRgba16(ImageBuffer::from_fn(75, 53, |x, y| {
+                Rgba([
+                    x as u16 * 800,
+                    y as u16 * 1200,
+                    30000,
+                    alpha(x, y) as u16 * 257,
+                ])
+            })),
+        ];
+        for img in &images {
+            for tile_size in [16, 32, 50] {
+                // 右端・下端・右下の端のタイルを含む全てのタイル
+                for y in (0..img.height()).step_by(tile_size as usize) {
+                    for x in (0..img.width()).step_by(tile_size as usize) {
+                        let w = tile_size.min(img.width() - x);
+                        let h = tile_size.min(img.height() - y);
+                        let padded = crop_and_pad(img, x, y, w, h, tile_size).unwrap().to_rgba8();
+                        let expected = overlay_pad(img, x, y, w, h, tile_size);
+                        assert_eq!(padded.dimensions(), (tile_size, tile_size));
+                        for (a, b) in padded.pixels().zip(expected.pixels()) {
+                            // 不透明・透明な画素とパディングは同じ。半透明な画素は `overlay` の
+                            // 合成の丸めで1だけ小さくなることがあり、元の値のままの方を許す
+                            assert_eq!(
+                                a[3],
+                                b[3],
+                                "{:?} ({}, {}) {}",
+                                img.color(),
+                                x,
+                                y,
+                                tile_size
+                            );
+                            if a[3] == 0 || a[3] == 255 {
+                                assert_eq!(a, b);
+                            } else {
+                                assert!(
+                                    a.0.iter().zip(b.0).all(|(a, b)| a - b <= 1),
+                                    "{:?} {:?}",
+                                    a,
+                                    b
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiler_context() {
+        // 端のタイルを含むよう、タイルサイズで割り切れない画像
+        let images = [
+            DynamicImage::ImageRgb8(image::RgbImage::from_fn(90, 70, |x, y| {
+                image::Rgb([x as u8, y as u8, 128])
+            })),
+            DynamicImage::ImageLuma8(image::GrayImage::from_fn(90, 70, |x, y| {
+                image::Luma([(x + y) as u8])
+            })),
+            DynamicImage::ImageRgba8(ImageBuffer::from_fn(90, 70, |x, y| {
+                Rgba([x as u8, 0, y as u8, 200])
+            })),
+        ];
+
+        let mut context = TilerContext::new();
+        for img in &images {
+            let mut buffer = Cursor::new(Vec::new());
+            img.write_to(&mut buffer, ImageFormat::Png).unwrap();
+            let result = context
+                .tile_image_with(buffer.get_ref(), &TileOptions::new(32, None))
+                .unwrap();
+            assert_eq!(result.tiles.len(), 9);
+
+            // タイルごとに切り出してエンコードした場合と同じ結果になる
+            for tile in &result.tiles {
+                let (x, y) = (tile.x * 32, tile.y * 32);
+                let (w, h) = (32.min(90 - x), 32.min(70 - y));
+                let expected = encode_webp(&crop_and_pad(img, x, y, w, h, 32).unwrap(), 80.0);
+                assert_eq!(tile.data, expected.unwrap());
+            }
+        }
+
+        // バッファはページをまたいで残り、解放できる
+        assert!(context.retained_bytes() >= 90 * 70 * 3);
+        context.release();
+        assert_eq!(context.retained_bytes(), 0);
+    }
+
+    #[test]
+    fn test_tile_options() {
+        let options: TileOptions = serde_json::from_str(r#"{ "tile_size": 256 }"#).unwrap();
+        assert_eq!(options, TileOptions::new(256, None));
+        let options: TileOptions =
+            serde_json::from_str(r#"{ "quality": 90, "padding": "none" }"#).unwrap();
+        assert_eq!(
+            (options.tile_size, options.padding),
+            (512, EdgePadding::None)
+        );
+        assert!(serde_json::from_str::<TileOptions>(r#"{ "format": "png" }"#).is_err());
+
+        // 埋めない場合、端のタイルは画像の端までの大きさになる
+        let images = [
+            DynamicImage::ImageRgb8(image::RgbImage::from_fn(90, 70, |x, y| {
+                image::Rgb([x as u8, y as u8, 128])
+            })),
+            DynamicImage::ImageRgb16(ImageBuffer::from_fn(90, 70, |x, y| {
+                image::Rgb([x as u16 * 500, y as u16 * 700, 9000])
+            })),
+        ];
+        let options = TileOptions {
+            padding: EdgePadding::None,
+            ..TileOptions::new(32, None)
+        };
+        for img in &images {
+            let padded = TilerContext::new().tile_decoded(img, 32, None).unwrap();
+            let result = TilerContext::new()
+                .tile_decoded_with(img, &options)
+                .unwrap();
+            for (tile, padded) in result.tiles.iter().zip(&padded.tiles) {
+                let decoded = image::load_from_memory(&tile.data).unwrap();
+                let expected = (32.min(90 - tile.x * 32), 32.min(70 - tile.y * 32));
+                assert_eq!((decoded.width(), decoded.height()), expected);
+                if expected == (32, 32) {
+                    assert_eq!(tile.data, padded.data);
+                }
+            }
+        }
+    }
+
+    #[cfg(all(feature = "libwebp", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_tile_lossy_quality() {
+        // 可逆では圧縮しにくい細かい模様
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([
+                ((x * 37) ^ (y * 11)) as u8,
+                (x * y) as u8,
+                (x + y * 7) as u8,
+            ])
+        }));
+        let mut context = TilerContext::new();
+        let mut tile = |quality| {
+            let options = TileOptions::new(64, Some(quality));
+            let result = context.tile_decoded_with(&img, &options).unwrap();
+            result.tiles[0].data.clone()
+        };
+        let (low, high, lossless) = (tile(30.0), tile(90.0), tile(100.0));
+
+        // 100未満は非可逆（VP8）で、品質が低いほど小さい。100以上は可逆（VP8L）
+        assert_eq!(&low[12..16], b"VP8 ");
+        assert_eq!(&high[12..16], b"VP8 ");
+        assert_eq!(&lossless[12..16], b"VP8L");
+        assert!(low.len() < high.len());
+        let decode = |data: &[u8]| {
+            image::load_from_memory_with_format(data, ImageFormat::WebP)
+                .unwrap()
+                .to_rgb8()
+        };
+        assert_ne!(decode(&low), *img.as_rgb8().unwrap());
+        assert_eq!(decode(&lossless), *img.as_rgb8().unwrap());
+    }
+
+    #[test]
+    fn test_warnings() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(40, 24, |x, y| {
+            image::Rgb([x as u8 * 6, y as u8 * 10, 128])
+        }));
+        let mut jpeg = Vec::new();
+        img.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+
+        let result = tile_image(&jpeg, 32, None).unwrap();
+        assert!(result.warnings.is_empty());
+        let result = tile_image(&jpeg, 32, Some(60.0)).unwrap();
+        let codes: Vec<&str> = result.warnings.iter().map(|w| w.code.as_str()).collect();
+        if LOSSY_ENCODER {
+            assert!(codes.is_empty());
+        } else {
+            assert_eq!(codes, ["quality_ignored"]);
+        }
+
+        // SOIの直後にEXIF（向き = 6、90度回転）のAPP1セグメントを入れる
+        let exif = b"Exif\0\0MM\0*\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0";
+        let mut with_exif = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        with_exif.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        with_exif.extend_from_slice(exif);
+        with_exif.extend_from_slice(&jpeg[2..]);
+        for result in [
+            tile_image(&with_exif, 32, None).unwrap(),
+            TilerContext::new()
+                .tile_image_banded(&with_exif, 32, None)
+                .unwrap(),
+            TilerContext::new()
+                .tile_image_lazy(&with_exif, 32, None)
+                .unwrap()
+                .0,
+        ] {
+            assert_eq!(result.warnings.len(), 1);
+            assert_eq!(result.warnings[0].code, "exif_orientation_ignored");
+        }
+    }
+
+    #[test]
+    fn test_tile_image_yielding() {
+        use std::task::{Context, Poll, Waker};
+
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(100, 70, |x, y| {
+            image::Rgb([x as u8 * 2, y as u8 * 3, 64])
+        }));
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let options = TileOptions::new(32, None);
+
+        // すぐに再開する `yield_now` なら1回のpollで終わる
+        let mut yields = 0;
+        let settings = Settings::default();
+        let future = tile_image_yielding(&png, &options, &settings, |done, total| {
+            assert!(done <= total && total == 12);
+            yields += 1;
+            std::future::ready(())
+        });
+        let mut context = Context::from_waker(Waker::noop());
+        let Poll::Ready(result) = std::pin::pin!(future).poll(&mut context) else {
+            panic!("tiling did not finish");
+        };
+        let result = result.unwrap();
+        // デコードの後は必ず戻る
+        assert!(yields >= 1);
+
+        let expected = TilerContext::new().tile_image_with(&png, &options).unwrap();
+        assert_eq!((result.width, result.height), (100, 70));
+        assert_eq!(result.tiles.len(), 12);
+        for (tile, expected) in result.tiles.iter().zip(&expected.tiles) {
+            assert_eq!(
+                (tile.x, tile.y, &tile.hash),
+                (expected.x, expected.y, &expected.hash)
+            );
+        }
+    }
+
+    #[test]
+    fn test_uniform_tiles() {
+        assert_eq!(uniform_pixel(&[7, 8, 7, 8, 7, 8], 2), Some(&[7, 8][..]));
+        assert_eq!(uniform_pixel(&[7, 8, 7, 8, 7, 9], 2), None);
+        assert_eq!(uniform_pixel(&[], 3), None);
+
+        // 白い余白の中に1点だけ模様のあるページ
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(96, 64, |x, y| {
+            if (x, y) == (40, 10) {
+                image::Rgb([0, 0, 0])
+            } else {
+                image::Rgb([255, 255, 255])
+            }
+        }));
+        let mut context = TilerContext::new();
+        let result = context.tile_decoded(&img, 32, None).unwrap();
+
+        // エンコードを省いても、タイルごとにエンコードした場合と同じ結果になる
+        for tile in &result.tiles {
+            let (x, y) = (tile.x * 32, tile.y * 32);
+            let expected = encode_webp(&img.crop_imm(x, y, 32, 32), 80.0).unwrap();
+            assert_eq!(tile.data, expected);
+        }
+        assert_eq!(result.tiles[0].hash, result.tiles[5].hash);
+        assert_ne!(result.tiles[0].hash, result.tiles[1].hash);
+        assert_eq!(context.uniform.len(), 1);
+    }
+
+    #[test]
+    fn test_timings() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(90, 70, |x, y| {
+            image::Rgb([x as u8, y as u8, 128])
+        }));
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png).unwrap();
+        let mut jpeg = Cursor::new(Vec::new());
+        img.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+
+        let timed = Settings {
+            timing: Some(true),
+            ..Settings::default()
+        };
+        let result = TilerContext::with_settings(timed)
+            .tile_image_with(png.get_ref(), &TileOptions::new(32, None))
+            .unwrap();
+        // 他のテストが並行して記録を有効にしても結果は変わらないので、無効の場合は確かめない
+        Settings::update_global(|settings| settings.timing = Some(true));
+        let banded = TilerContext::new()
+            .tile_image_banded(jpeg.get_ref(), 32, None)
+            .unwrap();
+        Settings::update_global(|settings| settings.timing = None);
+
+        for result in [result, banded] {
+            let timings = result.timings.unwrap();
+            assert_eq!(timings.tiles.len(), 9);
+            let total: usize = result.tiles.iter().map(|tile| tile.data.len()).sum();
+            assert_eq!(timings.total_bytes, total);
+            assert!(timings.decode_ms > 0.0 && timings.encode_ms > 0.0);
+            let (tile, last) = (&timings.tiles[8], &result.tiles[8]);
+            assert_eq!((tile.x, tile.y, tile.bytes), (2, 2, last.data.len()));
+        }
+    }
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_tile_parallel() {
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(100, 70, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }));
+        let options = TileOptions::new(32, None);
+        let tile = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| TilerContext::new().tile_decoded_with(&img, &options))
+                .unwrap()
+        };
+        // 並列にエンコードしても、タイルの順と内容は1つずつの場合と同じ
+        let (sequential, parallel) = (tile(1), tile(4));
+        assert_eq!(parallel.tiles.len(), 12);
+        for (a, b) in sequential.tiles.iter().zip(&parallel.tiles) {
+            assert_eq!((a.x, a.y, &a.hash, &a.data), (b.x, b.y, &b.hash, &b.data));
+        }
+
+        // ワーカーも呼び出し元の設定（処理時間の記録）に従う
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let timed = Settings {
+            timing: Some(true),
+            ..Settings::default()
+        };
+        let result = pool
+            .install(|| TilerContext::with_settings(timed).tile_decoded_with(&img, &options))
+            .unwrap();
+        assert_eq!(result.timings.unwrap().tiles.len(), 12);
+
+        // 一色のタイルのエンコード結果はスレッドから呼び出し元のコンテキストに戻る
+        let blank =
+            DynamicImage::ImageRgba8(ImageBuffer::from_pixel(100, 70, Rgba([9, 9, 9, 255])));
+        let mut context = TilerContext::new();
+        let first = pool
+            .install(|| context.tile_decoded_with(&blank, &options))
+            .unwrap();
+        assert!(!context.uniform.is_empty());
+        let second = pool
+            .install(|| context.tile_decoded_with(&blank, &options))
+            .unwrap();
+        for (a, b) in first.tiles.iter().zip(&second.tiles) {
+            assert_eq!((&a.hash, &a.data), (&b.hash, &b.data));
+        }
+    }
+
+    #[test]
+    fn test_context_settings() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(90, 70, |x, y| {
+            image::Rgb([x as u8, y as u8, 128])
+        }));
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png).unwrap();
+        let png = png.into_inner();
+        let mut jpeg = Cursor::new(Vec::new());
+        img.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+        let jpeg = jpeg.into_inner();
+
+        // 上限を設定したコンテキストだけが、どの方法のタイル化でも上限を超える画像を拒む
+        let mut limited = TilerContext::with_settings(Settings {
+            decode_limits: Some(decode::DecodeLimits {
+                max_dimension: 64,
+                max_pixels: u64::MAX,
+            }),
+            ..Settings::default()
+        });
+        let mut context = TilerContext::new();
+        let preprocess = PreprocessOptions::default();
+        assert!(limited.tile_image_level(&png, 0, 32, None).is_err());
+        assert!(limited
+            .tile_image_pages(&png, 32, None, AnimationMode::default())
+            .is_err());
+        assert!(limited
+            .tile_image_preprocessed(&png, 32, None, &preprocess)
+            .is_err());
+        assert!(limited
+            .tile_image_split(&png, 32, None, &preprocess)
+            .is_err());
+        assert!(limited.tile_image_banded(&jpeg, 32, None).is_err());
+        assert!(limited.tile_image_lazy(&png, 32, None).is_err());
+        assert_eq!(
+            context
+                .tile_image_level(&png, 0, 32, None)
+                .unwrap()
+                .tiles
+                .len(),
+            9
+        );
+        assert_eq!(
+            context
+                .tile_image_banded(&jpeg, 32, None)
+                .unwrap()
+                .tiles
+                .len(),
+            9
+        );
+        // 縮小後の大きさが上限に収まっても、元の大きさで拒む
+        assert!(limited.tile_image_level(&png, 1, 32, None).is_err());
+
+        // 遅延したエンコードもコンテキストの設定に従う
+        let timed = Settings {
+            timing: Some(true),
+            ..Settings::default()
+        };
+        let (_, lazy) = TilerContext::with_settings(timed.clone())
+            .tile_image_lazy(&png, 32, None)
+            .unwrap();
+        assert_eq!(lazy.context.settings(), &timed);
+    }
+
+    #[test]
+    fn test_tile_image_lazy() {
+        // 上半分が白、下半分が模様の画像（白いタイルは同じハッシュになる）
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(90, 64, |x, y| {
+            if y < 32 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([x as u8, y as u8, 128])
+            }
+        }));
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png).unwrap();
+
+        let (result, mut lazy) = TilerContext::new()
+            .tile_image_lazy(png.get_ref(), 32, None)
+            .unwrap();
+        assert_eq!(result.tiles.len(), 6);
+        assert!(result.tiles.iter().all(|tile| tile.data.is_empty()));
+        assert_eq!(result.tiles[0].hash, result.tiles[1].hash);
+        assert_ne!(result.tiles[0].hash, result.tiles[3].hash);
+        // 端のタイルはパディングを含むので、白い部分が同じでもハッシュが異なる
+        assert_ne!(result.tiles[0].hash, result.tiles[2].hash);
+
+        // エンコードしたデータは通常のタイル化と同じ
+        let expected = tile_image(png.get_ref(), 32, None).unwrap();
+        for (index, tile) in expected.tiles.iter().enumerate() {
+            assert!(!lazy.is_complete());
+            assert_eq!(lazy.encode(index).unwrap().unwrap(), tile.data);
+        }
+        assert!(lazy.is_complete());
+        assert_eq!(lazy.encode(0).unwrap(), None);
+        assert_eq!(lazy.encode(6).unwrap(), None);
+    }
+
+    #[test]
+    fn test_tile_image_banded() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(90, 140, |x, y| {
+            image::Rgb([x as u8 * 2, y as u8, 128])
+        }));
+        let mut jpeg = Cursor::new(Vec::new());
+        img.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+
+        // 座標もタイルのハッシュも通常のタイル化と同じ
+        let hashes = |result: &TileResult| -> Vec<(u32, u32, String)> {
+            let tiles = result.tiles.iter();
+            tiles
+                .map(|tile| (tile.x, tile.y, tile.hash.clone()))
+                .collect()
+        };
+        let banded = TilerContext::new()
+            .tile_image_banded(jpeg.get_ref(), 32, None)
+            .unwrap();
+        let expected = tile_image(jpeg.get_ref(), 32, None).unwrap();
+        assert_eq!((banded.width, banded.height), (90, 140));
+        assert_eq!(hashes(&banded), hashes(&expected));
+
+        // 途中で切れたJPEGは通常のタイル化に切り替え、同じ結果になる
+        let truncated = &jpeg.get_ref()[..jpeg.get_ref().len() / 2];
+        let banded = TilerContext::new()
+            .tile_image_banded(truncated, 32, None)
+            .unwrap();
+        let expected = tile_image(truncated, 32, None).unwrap();
+        assert_eq!(hashes(&banded), hashes(&expected));
+
+        // JPEG以外は通常のタイル化と同じ
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png).unwrap();
+        let banded = TilerContext::new()
+            .tile_image_banded(png.get_ref(), 32, None)
+            .unwrap();
+        let expected = tile_image(png.get_ref(), 32, None).unwrap();
+        assert_eq!(hashes(&banded), hashes(&expected));
     }
 }
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+/// タイル化の処理時間の内訳（ミリ秒）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// 画像のデコード
+    pub decode_ms: f64,
+    /// タイルの切り出し（全タイルの合計）
+    pub crop_ms: f64,
+    /// WebPのエンコード（全タイルの合計）
+    pub encode_ms: f64,
+    /// ハッシュの計算（全タイルの合計）
+    pub hash_ms: f64,
+    /// タイルのデータの合計バイト数
+    pub total_bytes: usize,
+    /// タイルごとの内訳
+    pub tiles: Vec<TileTiming>,
+}
+
+/// タイルごとの処理時間（ミリ秒）とデータの大きさ
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TileTiming {
+    /// タイルのX座標（タイル単位）
+    pub x: u32,
+    /// タイルのY座標（タイル単位）
+    pub y: u32,
+    pub crop_ms: f64,
+    pub encode_ms: f64,
+    pub hash_ms: f64,
+    /// タイルのデータのバイト数
+    pub bytes: usize,
+}
+
+impl Timings {
+    /// タイルの内訳を追加し、合計に加える
+    pub fn push(&mut self, tile: TileTiming) {
+        self.crop_ms += tile.crop_ms;
+        self.encode_ms += tile.encode_ms;
+        self.hash_ms += tile.hash_ms;
+        self.total_bytes += tile.bytes;
+        self.tiles.push(tile);
+    }
+}
+
+/// 区間ごとの経過時間を測る（無効な場合は時刻を読まず、常に0を返す）
+pub struct Stopwatch {
+    last: Option<f64>,
+}
+
+impl Stopwatch {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            last: enabled.then(now),
+        }
+    }
+
+    /// 前回（または開始）からの経過時間（ミリ秒）
+    pub fn lap(&mut self) -> f64 {
+        let Some(last) = &mut self.last else {
+            return 0.0;
+        };
+        let now = now();
+        let elapsed = now - *last;
+        *last = now;
+        elapsed
+    }
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// 現在の時刻（ミリ秒、ブラウザ・ワーカーでは `performance.now()`）
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn now() -> f64 {
+    performance_now()
+}
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+fn now() -> f64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START
+        .get_or_init(std::time::Instant::now)
+        .elapsed()
+        .as_secs_f64()
+        * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopwatch() {
+        let mut disabled = Stopwatch::new(false);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(disabled.lap(), 0.0);
+
+        let mut stopwatch = Stopwatch::new(true);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(stopwatch.lap() >= 2.0);
+        assert!(stopwatch.lap() < 2.0);
+    }
+
+    #[test]
+    fn test_timings_push() {
+        let mut timings = Timings::default();
+        for bytes in [100, 50] {
+            timings.push(TileTiming {
+                crop_ms: 1.0,
+                encode_ms: 2.0,
+                hash_ms: 0.5,
+                bytes,
+                ..TileTiming::default()
+            });
+        }
+        assert_eq!(timings.encode_ms, 4.0);
+        assert_eq!(timings.hash_ms, 1.0);
+        assert_eq!(timings.total_bytes, 150);
+        assert_eq!(timings.tiles.len(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::camera::CameraState;
+use crate::geometry::Point;
+
+/// ページ座標と画面座標の変換
+///
+/// `CameraState` と同じく、画面座標 `s` とページ座標 `p` は `p = (s + (x, y)) / zoom` の関係にある
+#[derive(Debug, Clone)]
+pub struct ViewTransform {
+    state: CameraState,
+    min_zoom: f64,
+    max_zoom: f64,
+}
+
+impl ViewTransform {
+    pub fn new(state: CameraState, min_zoom: f64, max_zoom: f64) -> Self {
+        let min_zoom = min_zoom.max(f64::EPSILON);
+        let mut transform = Self {
+            state,
+            min_zoom,
+            max_zoom: max_zoom.max(min_zoom),
+        };
+        transform.state.zoom = transform.clamp_zoom(state.zoom);
+        transform
+    }
+
+    pub fn state(&self) -> CameraState {
+        self.state
+    }
+
+    /// 状態を置き換える（倍率は範囲内に収める）
+    pub fn set_state(&mut self, state: CameraState) {
+        self.state = CameraState {
+            zoom: self.clamp_zoom(state.zoom),
+            ..state
+        };
+    }
+
+    pub fn screen_to_page(&self, screen: Point) -> Point {
+        Point::new(
+            (screen.x + self.state.x) / self.state.zoom,
+            (screen.y + self.state.y) / self.state.zoom,
+        )
+    }
+
+    pub fn page_to_screen(&self, page: Point) -> Point {
+        Point::new(
+            page.x * self.state.zoom - self.state.x,
+            page.y * self.state.zoom - self.state.y,
+        )
+    }
+
+    /// ピンチ操作を適用する
+    ///
+    /// `center`（画面座標）の下にあるページ上の点が動かないように倍率を `scale` 倍する
+    pub fn apply_pinch(&mut self, center: Point, scale: f64) {
+        let anchor = self.screen_to_page(center);
+        let zoom = self.clamp_zoom(self.state.zoom * scale);
+
+        self.state = CameraState {
+            x: anchor.x * zoom - center.x,
+            y: anchor.y * zoom - center.y,
+            zoom,
+        };
+    }
+
+    /// パン操作を適用する（指の移動量、画面ピクセル）
+    pub fn apply_pan(&mut self, dx: f64, dy: f64) {
+        self.state.x -= dx;
+        self.state.y -= dy;
+    }
+
+    /// ページ座標 → 画面座標の変換行列（`[a, b, c, d, e, f]`、Canvasの `setTransform` の引数順）
+    pub fn matrix(&self) -> [f64; 6] {
+        let CameraState { x, y, zoom } = self.state;
+        [zoom, 0.0, 0.0, zoom, -x, -y]
+    }
+
+    fn clamp_zoom(&self, zoom: f64) -> f64 {
+        if zoom.is_finite() {
+            zoom.clamp(self.min_zoom, self.max_zoom)
+        } else {
+            self.state.zoom
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform() -> ViewTransform {
+        ViewTransform::new(
+            CameraState {
+                x: 100.0,
+                y: 50.0,
+                zoom: 2.0,
+            },
+            0.5,
+            8.0,
+        )
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let transform = transform();
+        let page = transform.screen_to_page(Point::new(300.0, 150.0));
+        assert_eq!(page, Point::new(200.0, 100.0));
+        assert_eq!(transform.page_to_screen(page), Point::new(300.0, 150.0));
+        assert_eq!(transform.matrix(), [2.0, 0.0, 0.0, 2.0, -100.0, -50.0]);
+    }
+
+    #[test]
+    fn test_pinch_keeps_center_fixed_and_clamps() {
+        let mut transform = transform();
+        let center = Point::new(300.0, 150.0);
+        let before = transform.screen_to_page(center);
+
+        transform.apply_pinch(center, 1.5);
+        assert_eq!(transform.state().zoom, 3.0);
+        assert_eq!(transform.screen_to_page(center), before);
+
+        transform.apply_pinch(center, 100.0);
+        assert_eq!(transform.state().zoom, 8.0);
+        assert_eq!(transform.screen_to_page(center), before);
+    }
+
+    #[test]
+    fn test_pan_moves_content_with_finger() {
+        let mut transform = transform();
+        let page = Point::new(200.0, 100.0);
+        let before = transform.page_to_screen(page);
+
+        transform.apply_pan(30.0, -10.0);
+        let after = transform.page_to_screen(page);
+        assert_eq!((after.x - before.x, after.y - before.y), (30.0, -10.0));
+    }
+}
@@ -0,0 +1,162 @@
+//! 出力したパンフレットをS3互換のストレージ（R2など）にアップロードする計画
+//!
+//! キーはWorkersが読む配置（`pamphlets/{id}/tiles/{hash}.webp` と `pamphlets/{id}/metadata.json`）。
+//! metadataに `tile_layout` があればタイルのキーはその配置に従う。
+//! タイルはハッシュが同じなら内容も同じため、既にあれば送らずに長くキャッシュさせる。
+//! metadata.jsonはすべてのタイルの後に送り、ビューアがまだ無いタイルを読まないようにする
+
+use serde::Serialize;
+
+use crate::layout::TileLayout;
+
+/// タイルのCache-Control（ハッシュが同じタイルは内容も同じ）
+pub const TILE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// metadata.jsonのCache-Control（パンフレットを更新したらすぐに読み直させる）
+pub const METADATA_CACHE_CONTROL: &str = "no-cache";
+
+/// パスにハッシュを含まない配置（`zxy`）のタイルのCache-Control（同じキーの内容が変わりうる）
+pub const POSITIONAL_TILE_CACHE_CONTROL: &str = "no-cache";
+
+/// アップロードする1つのオブジェクト
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UploadObject {
+    /// ストレージのキー
+    pub key: String,
+    /// 出力先のディレクトリの中のパス（`tiles/{hash}.webp` など）
+    pub path: String,
+    pub content_type: &'static str,
+    pub cache_control: &'static str,
+    /// 同じキーのオブジェクトが既にあれば送らない
+    pub skip_if_exists: bool,
+}
+
+/// パンフレットのキーの接頭辞（Workersの `pamphlets/{id}`）
+pub fn pamphlet_prefix(id: &str) -> String {
+    format!("pamphlets/{}", id)
+}
+
+/// metadataのタイルとmetadata.jsonをアップロードする順に並べる
+///
+/// タイルはmetadataに出てくる順で、同じパスのタイルは1つにまとめる。metadata.jsonは最後。
+/// パスにハッシュを含まない配置（`zxy`）のタイルは、既にあっても送り直す
+///
+/// # Errors
+/// metadataにページ・タイルのハッシュが無い場合、`tile_layout` が知らない配置の場合
+pub fn upload_plan(
+    prefix: &str,
+    metadata: &serde_json::Value,
+) -> Result<Vec<UploadObject>, String> {
+    let prefix = prefix.trim_end_matches('/');
+    let key = |path: &str| match prefix {
+        "" => path.to_string(),
+        prefix => format!("{}/{}", prefix, path),
+    };
+    let pages = metadata["pages"]
+        .as_array()
+        .ok_or("Metadata has no pages")?;
+    let layout = TileLayout::from_metadata(metadata)?;
+    let (cache_control, skip_if_exists) = match layout.is_content_addressed() {
+        true => (TILE_CACHE_CONTROL, true),
+        false => (POSITIONAL_TILE_CACHE_CONTROL, false),
+    };
+    let index = |value: &serde_json::Value| value.as_u64().unwrap_or(0) as u32;
+
+    let mut seen = std::collections::HashSet::new();
+    let mut objects = Vec::new();
+    for (number, page) in pages.iter().enumerate() {
+        for tile in page["tiles"].as_array().into_iter().flatten() {
+            let hash = tile["hash"]
+                .as_str()
+                .ok_or("Metadata has a tile without hash")?;
+            let path = layout.tile_path(number as u32, index(&tile["x"]), index(&tile["y"]), hash);
+            if seen.insert(path.clone()) {
+                objects.push(UploadObject {
+                    key: key(&path),
+                    path,
+                    content_type: "image/webp",
+                    cache_control,
+                    skip_if_exists,
+                });
+            }
+        }
+    }
+    objects.push(UploadObject {
+        key: key("metadata.json"),
+        path: "metadata.json".to_string(),
+        content_type: "application/json",
+        cache_control: METADATA_CACHE_CONTROL,
+        skip_if_exists: false,
+    });
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upload_plan() {
+        let metadata = serde_json::json!({
+            "version": 1,
+            "tile_size": 512,
+            "pages": [
+                { "page": 0, "width": 10, "height": 10, "tiles": [
+                    { "x": 0, "y": 0, "hash": "aa" },
+                    { "x": 1, "y": 0, "hash": "bb" },
+                ] },
+                { "page": 1, "width": 10, "height": 10, "tiles": [
+                    { "x": 0, "y": 0, "hash": "aa" },
+                ] },
+            ],
+        });
+        let plan = upload_plan(&pamphlet_prefix("demo"), &metadata).unwrap();
+        let keys: Vec<_> = plan.iter().map(|object| object.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "pamphlets/demo/tiles/aa.webp",
+                "pamphlets/demo/tiles/bb.webp",
+                "pamphlets/demo/metadata.json",
+            ]
+        );
+        assert_eq!(plan[0].path, "tiles/aa.webp");
+        assert_eq!(
+            (
+                plan[0].content_type,
+                plan[0].cache_control,
+                plan[0].skip_if_exists
+            ),
+            ("image/webp", TILE_CACHE_CONTROL, true)
+        );
+        assert_eq!(
+            (plan[2].content_type, plan[2].skip_if_exists),
+            ("application/json", false)
+        );
+
+        let plan = upload_plan("", &metadata).unwrap();
+        assert_eq!(plan[2].key, "metadata.json");
+
+        // 配置がmetadataにあればタイルのキーはそれに従う
+        let mut metadata = metadata;
+        metadata["tile_layout"] = serde_json::json!("zxy");
+        let plan = upload_plan("demo", &metadata).unwrap();
+        let keys: Vec<_> = plan.iter().map(|object| object.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "demo/tiles/0/0/0.webp",
+                "demo/tiles/0/1/0.webp",
+                "demo/tiles/1/0/0.webp",
+                "demo/metadata.json",
+            ]
+        );
+        assert_eq!(
+            (plan[0].cache_control, plan[0].skip_if_exists),
+            (POSITIONAL_TILE_CACHE_CONTROL, false)
+        );
+        metadata["tile_layout"] = serde_json::json!("nested");
+        assert!(upload_plan("demo", &metadata).is_err());
+        assert!(upload_plan("x", &serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+use crate::binary::{self, Reader};
+
+/// 現在のエンコード形式のバージョン
+///
+/// 形式を変更する場合はバージョンを上げ、古いバージョンの復元処理は残す（共有済みのリンクを壊さないため）
+const CURRENT_VERSION: u8 = 1;
+
+/// 表示倍率の量子化単位（1倍あたりの分割数）
+const ZOOM_PRECISION: f64 = 1000.0;
+
+/// 共有リンクで再現する表示状態
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ViewState {
+    pub page: u32,
+    /// 表示中心（ページの原寸ピクセル、1ピクセル単位で保存）
+    pub x: f64,
+    pub y: f64,
+    /// 表示倍率（0.001単位で保存）
+    pub zoom: f64,
+}
+
+/// 表示状態をURLのフラグメントやクエリに使える短い文字列に変換する
+///
+/// 先頭バイトがバージョンで、以降は可変長整数を並べてURLセーフなBase64にする
+pub fn encode(state: &ViewState) -> String {
+    let mut bytes = vec![CURRENT_VERSION];
+    binary::write_varint(&mut bytes, state.page as u64);
+    binary::write_varint(&mut bytes, binary::zigzag(state.x.round() as i64));
+    binary::write_varint(&mut bytes, binary::zigzag(state.y.round() as i64));
+    let zoom = (state.zoom * ZOOM_PRECISION).round().max(1.0);
+    binary::write_varint(&mut bytes, zoom as u64);
+
+    binary::base64url_encode(&bytes)
+}
+
+/// `encode` で生成した文字列から表示状態を復元する
+pub fn decode(text: &str) -> Result<ViewState, String> {
+    let bytes = binary::base64url_decode(text.trim())?;
+    let mut reader = Reader::new(&bytes, "view state");
+
+    match reader.u8()? {
+        1 => {
+            let page = reader.varint()?;
+            let x = binary::unzigzag(reader.varint()?);
+            let y = binary::unzigzag(reader.varint()?);
+            let zoom = reader.varint()?;
+
+            Ok(ViewState {
+                page: u32::try_from(page).map_err(|_| reader.error("page out of range"))?,
+                x: x as f64,
+                y: y as f64,
+                zoom: zoom as f64 / ZOOM_PRECISION,
+            })
+        }
+        version => Err(format!("Unsupported view state version: {}", version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_is_short_and_url_safe() {
+        let state = ViewState {
+            page: 12,
+            x: 1523.4,
+            y: -20.0,
+            zoom: 2.5,
+        };
+        let encoded = encode(&state);
+
+        assert!(encoded.len() <= 12, "{}", encoded);
+        assert!(encoded
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+        assert_eq!(decode(&encoded).unwrap(), ViewState { x: 1523.0, ..state });
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_version() {
+        assert_eq!(
+            decode(&binary::base64url_encode(&[9, 0])).unwrap_err(),
+            "Unsupported view state version: 9"
+        );
+        assert!(decode("").is_err());
+        assert!(decode("AQ").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::geometry::Rect;
+use crate::{PageInfo, TileMetadata};
+
+/// viewportと交差するタイルを、viewport中心に近い順に返す
+///
+/// # Arguments
+/// * `page` - ページのメタデータ
+/// * `tile_size` - タイルサイズ（ピクセル）
+/// * `viewport` - 表示領域（画面座標、ページ左上を原点とする）
+/// * `zoom` - 表示倍率（1.0 = 原寸）
+///
+/// # Returns
+/// 交差するタイル（中心から外側へ向かう順）
+pub fn tiles_for_viewport<'a>(
+    page: &'a PageInfo,
+    tile_size: u32,
+    viewport: &Rect,
+    zoom: f64,
+) -> Vec<&'a TileMetadata> {
+    if tile_size == 0 || zoom <= 0.0 {
+        return Vec::new();
+    }
+
+    // 画面座標 → ページ座標（原寸ピクセル）
+    let view = viewport.scale(1.0 / zoom);
+    let center = view.center();
+    let size = tile_size as f64;
+
+    let mut visible: Vec<(&TileMetadata, f64)> = page
+        .tiles
+        .iter()
+        .filter_map(|tile| {
+            let rect = tile_rect(page, tile, size);
+            rect.intersects(&view)
+                .then(|| (tile, rect.center().distance(&center)))
+        })
+        .collect();
+
+    visible.sort_by(|(a, da), (b, db)| {
+        da.total_cmp(db)
+            .then_with(|| a.y.cmp(&b.y))
+            .then_with(|| a.x.cmp(&b.x))
+    });
+
+    visible.into_iter().map(|(tile, _)| tile).collect()
+}
+
+/// タイルがページ上で占める矩形（ページ外のパディング部分は除く）
+fn tile_rect(page: &PageInfo, tile: &TileMetadata, size: f64) -> Rect {
+    let x = tile.x as f64 * size;
+    let y = tile.y as f64 * size;
+    let width = size.min(page.width as f64 - x).max(0.0);
+    let height = size.min(page.height as f64 - y).max(0.0);
+
+    Rect::new(x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(tiles_x: u32, tiles_y: u32, tile_size: u32) -> PageInfo {
+        let tiles = (0..tiles_y)
+            .flat_map(|y| {
+                (0..tiles_x).map(move |x| TileMetadata {
+                    x,
+                    y,
+                    hash: format!("{}-{}", x, y),
+                })
+            })
+            .collect();
+
+        PageInfo {
+            page: 0,
+            width: tiles_x * tile_size,
+            height: tiles_y * tile_size,
+            tiles,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tiles_for_viewport_culls_and_sorts() {
+        let page = page(4, 4, 100);
+
+        // ページ座標 (150,150)-(250,250) を表示
+        let tiles = tiles_for_viewport(&page, 100, &Rect::new(150.0, 150.0, 100.0, 100.0), 1.0);
+        let coords: Vec<(u32, u32)> = tiles.iter().map(|t| (t.x, t.y)).collect();
+
+        assert_eq!(coords, vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_tiles_for_viewport_applies_zoom() {
+        let page = page(4, 4, 100);
+
+        // 2倍表示で画面200x200 → ページ上100x100
+        let tiles = tiles_for_viewport(&page, 100, &Rect::new(0.0, 0.0, 200.0, 200.0), 2.0);
+        assert_eq!(tiles.len(), 1);
+        assert_eq!((tiles[0].x, tiles[0].y), (0, 0));
+
+        // 中心のタイルが先頭に来る
+        let tiles = tiles_for_viewport(&page, 100, &Rect::new(0.0, 0.0, 300.0, 300.0), 1.0);
+        assert_eq!(tiles.len(), 9);
+        assert_eq!((tiles[0].x, tiles[0].y), (1, 1));
+    }
+}
@@ -0,0 +1,72 @@
+/** タイル化の設定（省略した項目は既定値） */
+export interface TileOptions {
+  /** タイルサイズ（ピクセル、16-4096、既定512） */
+  tile_size?: number;
+  /** WebP品質（1-100、既定80） */
+  quality?: number;
+  format?: 'webp';
+  padding?: 'transparent' | 'none';
+}
+
+/** メインスレッドからワーカーへ: 画像をタイル化する */
+export interface TileRequest {
+  type: 'tile_request';
+  /** 応答と対応付けるための番号 */
+  id: number;
+  /** 元画像のバイトデータ */
+  data: Uint8Array | ArrayBuffer;
+  options?: TileOptions;
+  /** ページ情報に入れるページ番号（既定0） */
+  page?: number;
+}
+
+export type WorkerCommand = TileRequest;
+
+/** ワーカーからメインスレッドへ: タイル化の進捗 */
+export interface Progress {
+  type: 'progress';
+  id: number;
+  /** 処理したタイル数 */
+  done: number;
+  total: number;
+}
+
+/** タイル化したタイル */
+export interface ReadyTile {
+  x: number;
+  y: number;
+  hash: string;
+  /** WebP形式のデータ（ワーカーから移したもの） */
+  data: Uint8Array;
+}
+
+/** ワーカーからメインスレッドへ: タイル化が終わった */
+export interface TileReady {
+  type: 'tile_ready';
+  id: number;
+  tile_size: number;
+  page: PageInfo;
+  tiles: ReadyTile[];
+  warnings: TileWarning[];
+}
+
+/** タイル化のエラー（`code` で判別する） */
+export interface PamphletError {
+  code: string;
+  message: string;
+  details: Record<string, unknown> | null;
+  /** 翻訳のキー（`errors.<code>`） */
+  key: string;
+  /** 翻訳したメッセージに埋め込む値 */
+  params: Record<string, unknown>;
+}
+
+/** ワーカーからメインスレッドへ: タイル化に失敗した（Rustの `WorkerEvent::Error`） */
+export interface TileError {
+  type: 'error';
+  /** メッセージを読めなかった場合は `null` */
+  id: number | null;
+  error: PamphletError;
+}
+
+export type WorkerEvent = Progress | TileReady | TileError;
@@ -0,0 +1,253 @@
+//! 専用のWeb Workerでタイル化するときの、メインスレッドとワーカーの間のメッセージ
+//!
+//! ワーカーは受け取ったメッセージを `handle_worker_message` に渡し、返すメッセージを
+//! `postMessage` するだけでよい。TypeScriptの型は `worker.d.ts`
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::error::PamphletError;
+use crate::settings::Settings;
+use crate::tiler::{self, TileOptions, TileWarning};
+use crate::{PageInfo, TileMetadata};
+
+/// メインスレッドからワーカーへのメッセージ
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerCommand {
+    /// 画像をタイル化する
+    TileRequest {
+        /// 応答と対応付けるための番号（呼び出し側が振る）
+        id: u32,
+        /// 元画像のバイトデータ（`Uint8Array` または `ArrayBuffer`）
+        #[serde(deserialize_with = "deserialize_bytes")]
+        data: Vec<u8>,
+        #[serde(default)]
+        options: TileOptions,
+        /// ページ情報に入れるページ番号（省略時0）
+        #[serde(default)]
+        page: u32,
+    },
+}
+
+/// ワーカーからメインスレッドへのメッセージ
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerEvent {
+    /// タイル化の進捗（デコードの後と、タイルを処理する合間に送る）
+    Progress { id: u32, done: u32, total: u32 },
+    /// タイル化が終わった
+    TileReady {
+        id: u32,
+        tile_size: u32,
+        /// metadataのページ情報
+        page: PageInfo,
+        tiles: Vec<ReadyTile>,
+        warnings: Vec<TileWarning>,
+    },
+    /// タイル化に失敗した（`id` はメッセージを読めなかった場合 `null`）
+    Error {
+        id: Option<u32>,
+        error: PamphletError,
+    },
+}
+
+/// タイル化したタイル（`data` はJavaScriptでは `Uint8Array`）
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadyTile {
+    pub x: u32,
+    pub y: u32,
+    pub hash: String,
+    #[serde(serialize_with = "serialize_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// コマンドを処理し、最後に送るメッセージ（`TileReady` か `Error`）を返す
+///
+/// 途中の `Progress` は `progress` に渡す
+pub async fn respond(command: WorkerCommand, mut progress: impl FnMut(WorkerEvent)) -> WorkerEvent {
+    match command {
+        WorkerCommand::TileRequest {
+            id,
+            data,
+            options,
+            page,
+        } => {
+            let result = tile_request(&data, options, page, |done, total| {
+                progress(WorkerEvent::Progress { id, done, total });
+            })
+            .await;
+            match result {
+                Ok((tile_size, page, tiles, warnings)) => WorkerEvent::TileReady {
+                    id,
+                    tile_size,
+                    page,
+                    tiles,
+                    warnings,
+                },
+                Err(error) => WorkerEvent::Error {
+                    id: Some(id),
+                    error,
+                },
+            }
+        }
+    }
+}
+
+/// `TileReady` の中身（タイルサイズ・ページ情報・タイル・警告）
+type TileReadyParts = (u32, PageInfo, Vec<ReadyTile>, Vec<TileWarning>);
+
+/// 画像をタイル化し、ページ情報とタイルに分ける
+async fn tile_request(
+    data: &[u8],
+    options: TileOptions,
+    page: u32,
+    mut progress: impl FnMut(u32, u32),
+) -> Result<TileReadyParts, PamphletError> {
+    tiler::validate_tile_size(options.tile_size)?;
+    let options = TileOptions {
+        quality: tiler::clamp_quality(options.quality),
+        ..options
+    };
+    // ワーカーではイベントループに戻る必要がないため、進捗を送ってすぐに続ける
+    let result = tiler::tile_image_yielding(data, &options, &Settings::default(), |done, total| {
+        progress(done, total);
+        std::future::ready(())
+    })
+    .await?;
+
+    let page = PageInfo {
+        page,
+        width: result.width,
+        height: result.height,
+        tiles: result
+            .tiles
+            .iter()
+            .map(|tile| TileMetadata {
+                x: tile.x,
+                y: tile.y,
+                hash: tile.hash.clone(),
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let tiles = result
+        .tiles
+        .into_iter()
+        .map(|tile| ReadyTile {
+            x: tile.x,
+            y: tile.y,
+            hash: tile.hash,
+            data: tile.data,
+        })
+        .collect();
+    Ok((result.tile_size, page, tiles, result.warnings))
+}
+
+/// バイト列として書き出す（JavaScriptでは `Uint8Array`、JSONでは数値の配列）
+fn serialize_bytes<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(data)
+}
+
+/// バイト列（`Uint8Array`・`ArrayBuffer`）か数値の配列を読み込む
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a Uint8Array, an ArrayBuffer or an array of bytes")
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(bytes.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(bytes)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    deserializer.deserialize_byte_buf(BytesVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::png;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    /// すぐに終わるFutureを1回のpollで完了させる
+    fn complete<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match std::pin::pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future did not complete"),
+        }
+    }
+
+    #[test]
+    fn test_respond() {
+        let message = serde_json::json!({
+            "type": "tile_request",
+            "id": 7,
+            "data": png(40, 20, 0),
+            "options": { "tile_size": 32 },
+            "page": 3,
+        });
+        let command: WorkerCommand = serde_json::from_value(message).unwrap();
+
+        let mut events = Vec::new();
+        let ready = complete(respond(command, |event| events.push(event)));
+        let progress = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(
+            progress,
+            serde_json::json!({ "type": "progress", "id": 7, "done": 0, "total": 2 })
+        );
+
+        let ready = serde_json::to_value(&ready).unwrap();
+        assert_eq!(ready["type"], "tile_ready");
+        assert_eq!(ready["id"], 7);
+        assert_eq!(ready["tile_size"], 32);
+        assert_eq!(ready["page"]["page"], 3);
+        assert_eq!(ready["page"]["width"], 40);
+        assert_eq!(ready["tiles"].as_array().unwrap().len(), 2);
+        assert_eq!(ready["tiles"][1]["hash"], ready["page"]["tiles"][1]["hash"]);
+        assert!(!ready["tiles"][0]["data"].as_array().unwrap().is_empty());
+        assert_eq!(ready["warnings"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_respond_error() {
+        let command = WorkerCommand::TileRequest {
+            id: 1,
+            data: png(8, 8, 0),
+            options: TileOptions::new(4, None),
+            page: 0,
+        };
+        let error = complete(respond(command, |_| panic!("unexpected progress")));
+        let error = serde_json::to_value(&error).unwrap();
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["id"], 1);
+        assert_eq!(error["error"]["code"], "invalid_tile_size");
+
+        let command = WorkerCommand::TileRequest {
+            id: 2,
+            data: vec![1, 2, 3],
+            options: TileOptions::default(),
+            page: 0,
+        };
+        let error = serde_json::to_value(complete(respond(command, |_| {}))).unwrap();
+        assert_eq!(error["error"]["code"], "decode_failed");
+    }
+}
@@ -0,0 +1,110 @@
+/// レベル切り替えのデフォルトのヒステリシス幅（必要解像度に対する割合）
+pub const DEFAULT_HYSTERESIS: f32 = 0.1;
+
+/// 表示に必要な解像度を満たすピラミッドレベルを選択する
+///
+/// 「必要解像度以上のレベルのうち最も小さいもの」を選ぶ。
+/// どのレベルも必要解像度に満たない場合は最も高解像度のレベルを返す。
+///
+/// # Arguments
+/// * `viewport_scale` - 表示倍率（原寸に対する画面上の縮尺、1.0 = 原寸）
+/// * `device_pixel_ratio` - デバイスピクセル比
+/// * `levels` - 各レベルの縮尺（原寸に対する割合、例: [1.0, 0.5, 0.25]）
+/// * `current` - 現在表示中のレベル（ヒステリシス判定用）
+/// * `hysteresis` - 切り替えを抑制する幅（0.1 = ±10%）
+///
+/// # Returns
+/// 選択されたレベルのインデックス（`levels` が空の場合は `None`）
+pub fn select_level(
+    viewport_scale: f32,
+    device_pixel_ratio: f32,
+    levels: &[f32],
+    current: Option<usize>,
+    hysteresis: f32,
+) -> Option<usize> {
+    let required = viewport_scale * device_pixel_ratio;
+    let ideal = ideal_level(required, levels)?;
+
+    let current = match current {
+        Some(current) if current < levels.len() && current != ideal => current,
+        _ => return Some(ideal),
+    };
+
+    let current_scale = levels[current];
+
+    let keep = if current_scale < required {
+        // 現在のレベルは解像度不足: 許容幅を超えるまでは据え置く
+        current_scale * (1.0 + hysteresis) >= required
+    } else {
+        // 現在のレベルは必要以上に高解像度: 1つ下のレベルとの境界を明確に下回るまでは据え置く
+        // （大きく縮小した場合は境界を下回るため、途中のレベルを飛ばして最適なレベルにする）
+        let lower = levels
+            .iter()
+            .filter(|&&scale| scale < current_scale)
+            .max_by(|a, b| a.total_cmp(b));
+        lower.is_some_and(|&lower| required > lower * (1.0 - hysteresis))
+    };
+
+    Some(if keep { current } else { ideal })
+}
+
+/// ヒステリシスなしで最適なレベルを求める
+fn ideal_level(required: f32, levels: &[f32]) -> Option<usize> {
+    let sufficient = levels
+        .iter()
+        .enumerate()
+        .filter(|(_, &scale)| scale >= required)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index);
+
+    sufficient.or_else(|| {
+        levels
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVELS: [f32; 4] = [1.0, 0.5, 0.25, 0.125];
+
+    #[test]
+    fn test_select_smallest_sufficient_level() {
+        assert_eq!(select_level(0.2, 1.0, &LEVELS, None, 0.0), Some(2));
+        assert_eq!(select_level(0.2, 2.0, &LEVELS, None, 0.0), Some(1));
+        assert_eq!(select_level(0.5, 1.0, &LEVELS, None, 0.0), Some(1));
+
+        // 原寸を超える拡大時は最高解像度
+        assert_eq!(select_level(3.0, 2.0, &LEVELS, None, 0.0), Some(0));
+
+        assert_eq!(select_level(1.0, 1.0, &[], None, 0.0), None);
+    }
+
+    #[test]
+    fn test_hysteresis_keeps_current_level() {
+        // 0.26 は本来レベル1だが、レベル2(0.25)の+10%以内なので据え置き
+        assert_eq!(select_level(0.26, 1.0, &LEVELS, Some(2), 0.1), Some(2));
+        assert_eq!(select_level(0.3, 1.0, &LEVELS, Some(2), 0.1), Some(1));
+
+        // 0.24 は本来レベル2だが、レベル2(0.25)の-10%以内なのでレベル1を維持
+        assert_eq!(select_level(0.24, 1.0, &LEVELS, Some(1), 0.1), Some(1));
+        assert_eq!(select_level(0.2, 1.0, &LEVELS, Some(1), 0.1), Some(2));
+    }
+
+    #[test]
+    fn test_large_zoom_jumps_levels() {
+        // 大きく縮小した場合は、途中のレベルを飛ばして最適なレベルにする
+        assert_eq!(select_level(0.24, 1.0, &LEVELS, Some(0), 0.1), Some(2));
+        assert_eq!(select_level(0.1, 1.0, &LEVELS, Some(0), 0.1), Some(3));
+        // 1つ下のレベルとの境界付近では据え置く
+        assert_eq!(select_level(0.48, 1.0, &LEVELS, Some(0), 0.1), Some(0));
+
+        // 大きく拡大した場合も同じ
+        assert_eq!(select_level(0.9, 1.0, &LEVELS, Some(3), 0.1), Some(0));
+        assert_eq!(select_level(0.13, 1.0, &LEVELS, Some(3), 0.1), Some(3));
+    }
+}